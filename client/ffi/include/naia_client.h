@@ -0,0 +1,119 @@
+/*
+ * C bindings for naia-client. See client/ffi/src/lib.rs for details.
+ *
+ * All messages are exchanged as opaque byte blobs on up to 8 channels, identified
+ * by index. The server must register matching channels and the Blob message.
+ */
+
+#ifndef NAIA_CLIENT_H
+#define NAIA_CLIENT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define NAIA_OK 0
+#define NAIA_ERR_INVALID_ARGUMENT -1
+#define NAIA_ERR_INVALID_STATE -2
+#define NAIA_ERR_IO -3
+
+#define NAIA_MAX_CHANNELS 8
+
+enum {
+	NAIA_MODE_UNORDERED_UNRELIABLE = 0,
+	NAIA_MODE_SEQUENCED_UNRELIABLE = 1,
+	NAIA_MODE_UNORDERED_RELIABLE = 2,
+	NAIA_MODE_SEQUENCED_RELIABLE = 3,
+	NAIA_MODE_ORDERED_RELIABLE = 4,
+};
+
+enum {
+	NAIA_DIRECTION_CLIENT_TO_SERVER = 0,
+	NAIA_DIRECTION_SERVER_TO_CLIENT = 1,
+	NAIA_DIRECTION_BIDIRECTIONAL = 2,
+};
+
+enum {
+	NAIA_REJECT_AUTH_FAILED = 0,
+	NAIA_REJECT_DISCONNECT = 1,
+	NAIA_REJECT_SERVER_FULL = 2,
+	NAIA_REJECT_VERSION = 3,
+	NAIA_REJECT_DUPLICATE = 4,
+	NAIA_REJECT_CONNECT_TOO_LARGE = 5,
+};
+
+enum {
+	NAIA_HANDSHAKE_ENCRYPT_SENT = 0,
+	NAIA_HANDSHAKE_KEYS_ESTABLISHED = 1,
+	NAIA_HANDSHAKE_CONNECT_SENT = 2,
+};
+
+typedef enum NaiaEventKind {
+	NAIA_EVENT_CONNECT = 0,
+	NAIA_EVENT_DISCONNECT = 1,
+	/* data holds a UTF-8 description of the error (not NUL-terminated) */
+	NAIA_EVENT_ERROR = 2,
+	/* data holds the message bytes */
+	NAIA_EVENT_MESSAGE = 3,
+	/* reject_reason holds one of NAIA_REJECT_* */
+	NAIA_EVENT_REJECT = 4,
+	/* data holds the UTF-8 address of the new server (not NUL-terminated) */
+	NAIA_EVENT_REDIRECT = 5,
+	/* data holds the UTF-8 address of the new server (not NUL-terminated) */
+	NAIA_EVENT_REDIRECTED = 6,
+	NAIA_EVENT_LINK_DEGRADED = 7,
+	NAIA_EVENT_LINK_RECOVERED = 8,
+	NAIA_EVENT_DECRYPT_FAILURES = 9,
+	/* data holds the fraction of the snapshot received, as a little-endian float */
+	NAIA_EVENT_SYNC_PROGRESS = 10,
+	/* data holds the snapshot */
+	NAIA_EVENT_SYNCED = 11,
+	/* data holds one of NAIA_HANDSHAKE_* */
+	NAIA_EVENT_HANDSHAKE_PROGRESS = 12,
+} NaiaEventKind;
+
+typedef struct NaiaChannelConfig {
+	uint8_t mode;      /* NAIA_MODE_* */
+	uint8_t direction; /* NAIA_DIRECTION_* */
+} NaiaChannelConfig;
+
+typedef struct NaiaEvent {
+	NaiaEventKind kind;
+	uint8_t reject_reason;
+	/* valid until the next naia_client_poll() or naia_client_free() */
+	const uint8_t *data;
+	size_t len;
+} NaiaEvent;
+
+typedef struct NaiaClient NaiaClient;
+
+/* Returns NULL if the channel settings are invalid */
+NaiaClient *naia_client_new(const NaiaChannelConfig *channels, size_t channel_count);
+void naia_client_free(NaiaClient *client);
+
+/* addr is e.g. "127.0.0.1:4000"; auth is sent as the connect message */
+int32_t naia_client_connect(
+	NaiaClient *client, const char *addr, const uint8_t *auth, size_t auth_len);
+int32_t naia_client_disconnect(NaiaClient *client);
+bool naia_client_is_connected(const NaiaClient *client);
+
+/* Queue a message to be sent during the next naia_client_send() */
+int32_t naia_client_send_message(
+	NaiaClient *client, uint8_t channel, const uint8_t *data, size_t len);
+
+/* Call regularly: receive() at the start of a frame, send() at the end */
+void naia_client_send(NaiaClient *client);
+size_t naia_client_receive(NaiaClient *client);
+
+/* Pop the next event received by naia_client_receive(); false if none remain */
+bool naia_client_poll(NaiaClient *client, NaiaEvent *event);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,374 @@
+//! # Naia Client FFI
+//! C bindings for naia-client, so engines and tools that cannot link Rust crates
+//! directly can talk to a naia Server. See `include/naia_client.h` for the C API.
+//!
+//! Foreign clients cannot share Rust Message or Channel types, so all messages are
+//! exchanged as `Blob`s on `BlobChannel`s. A Server accepting these clients must
+//! register `BlobChannel::<0>` through `BlobChannel::<N - 1>` as its first channels,
+//! with the same settings passed to `naia_client_new()`, and `Blob` as its first
+//! message. Only `Blob`s may be sent to these clients.
+
+#![deny(
+    trivial_casts,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_import_braces
+)]
+
+use naia_client::{Client, ClientConfig, ClientEvent, HandshakeStep};
+use naia_shared::{
+	Blob, BlobChannel, ChannelDirection, ChannelMode, MAX_BLOB_CHANNELS, RejectReason,
+	Schema,
+};
+use std::{collections::VecDeque, ffi::{c_char, CStr}, net::SocketAddr, slice};
+
+// Dispatch a runtime channel id to the matching BlobChannel type
+macro_rules! with_blob_channel {
+	($id:expr, $channel:ident => $body:expr) => {
+		match $id {
+			0 => { type $channel = BlobChannel<0>; $body }
+			1 => { type $channel = BlobChannel<1>; $body }
+			2 => { type $channel = BlobChannel<2>; $body }
+			3 => { type $channel = BlobChannel<3>; $body }
+			4 => { type $channel = BlobChannel<4>; $body }
+			5 => { type $channel = BlobChannel<5>; $body }
+			6 => { type $channel = BlobChannel<6>; $body }
+			7 => { type $channel = BlobChannel<7>; $body }
+			_ => unreachable!(),
+		}
+	};
+}
+
+const _: () = assert!(MAX_BLOB_CHANNELS == 8);
+
+pub const NAIA_OK: i32 = 0;
+pub const NAIA_ERR_INVALID_ARGUMENT: i32 = -1;
+pub const NAIA_ERR_INVALID_STATE: i32 = -2;
+pub const NAIA_ERR_IO: i32 = -3;
+
+/// Settings for one BlobChannel; see `ChannelMode` and `ChannelDirection`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct NaiaChannelConfig {
+	/// 0: UnorderedUnreliable, 1: SequencedUnreliable, 2: UnorderedReliable,
+	/// 3: SequencedReliable, 4: OrderedReliable
+	pub mode: u8,
+	/// 0: ClientToServer, 1: ServerToClient, 2: Bidirectional
+	pub direction: u8,
+}
+
+impl NaiaChannelConfig {
+	fn mode(&self) -> Option<ChannelMode> {
+		Some(match self.mode {
+			0 => ChannelMode::UnorderedUnreliable,
+			1 => ChannelMode::SequencedUnreliable,
+			2 => ChannelMode::UnorderedReliable,
+			3 => ChannelMode::SequencedReliable,
+			4 => ChannelMode::OrderedReliable,
+			_ => return None,
+		})
+	}
+
+	fn direction(&self) -> Option<ChannelDirection> {
+		Some(match self.direction {
+			0 => ChannelDirection::ClientToServer,
+			1 => ChannelDirection::ServerToClient,
+			2 => ChannelDirection::Bidirectional,
+			_ => return None,
+		})
+	}
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NaiaEventKind {
+	Connect = 0,
+	Disconnect = 1,
+	/// `data` holds a UTF-8 description of the error
+	Error = 2,
+	/// `data` holds the Blob bytes
+	Message = 3,
+	/// `reject_reason` holds the reason
+	Reject = 4,
+	/// `data` holds the UTF-8 address of the new Server
+	Redirect = 5,
+	/// `data` holds the UTF-8 address of the new Server
+	Redirected = 6,
+	LinkDegraded = 7,
+	LinkRecovered = 8,
+	DecryptFailures = 9,
+	/// `data` holds the fraction of the snapshot received, as a little-endian f32
+	SyncProgress = 10,
+	/// `data` holds the snapshot
+	Synced = 11,
+	/// `data` holds the step as a byte: 0: EncryptSent, 1: KeysEstablished, 2: ConnectSent
+	HandshakeProgress = 12,
+}
+
+#[repr(C)]
+pub struct NaiaEvent {
+	pub kind: NaiaEventKind,
+	/// 0: AuthFailed, 1: Disconnect, 2: ServerFull, 3: Version, 4: Duplicate,
+	/// 5: ConnectTooLarge
+	pub reject_reason: u8,
+	/// Valid until the next call to `naia_client_poll()` or `naia_client_free()`
+	pub data: *const u8,
+	pub len: usize,
+}
+
+pub struct NaiaClient {
+	client: Client,
+	channels: Vec<NaiaChannelConfig>,
+	events: VecDeque<ClientEvent>,
+	/// Backing storage for the data of the most recently polled event
+	event_data: Box<[u8]>,
+}
+
+/// Create a new client with the given BlobChannels. Returns null if the channel
+/// settings are invalid.
+///
+/// # Safety
+/// `channels` must point to `channel_count` valid `NaiaChannelConfig`s.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn naia_client_new(
+	channels: *const NaiaChannelConfig, channel_count: usize,
+) -> *mut NaiaClient {
+	if channel_count > MAX_BLOB_CHANNELS as usize || (channels.is_null() && channel_count > 0) {
+		return std::ptr::null_mut();
+	}
+	let channels: &[NaiaChannelConfig] = if channel_count == 0 {
+		&[]
+	} else {
+		unsafe { slice::from_raw_parts(channels, channel_count) }
+	};
+
+	let mut builder = Schema::builder();
+	for (id, config) in channels.iter().enumerate() {
+		let (Some(mode), Some(direction)) = (config.mode(), config.direction()) else {
+			return std::ptr::null_mut();
+		};
+		builder = with_blob_channel!(id, C => builder.add_channel::<C>(direction, mode));
+	}
+	let schema = builder.add_message::<Blob>().build();
+
+	let client = NaiaClient {
+		client: Client::new(ClientConfig::default(), schema),
+		channels: channels.to_vec(),
+		events: VecDeque::new(),
+		event_data: Box::new([]),
+	};
+	Box::into_raw(Box::new(client))
+}
+
+/// Free a client created with `naia_client_new()`
+///
+/// # Safety
+/// `client` must be null or a pointer returned by `naia_client_new()` that has not
+/// already been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn naia_client_free(client: *mut NaiaClient) {
+	if !client.is_null() {
+		drop(unsafe { Box::from_raw(client) });
+	}
+}
+
+/// Start connecting to the server at `addr` (e.g. "127.0.0.1:4000"), sending `auth`
+/// as the connect message
+///
+/// # Safety
+/// `client` must be a valid client, `addr` a NUL-terminated string, and `auth` must
+/// point to `auth_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn naia_client_connect(
+	client: *mut NaiaClient, addr: *const c_char, auth: *const u8, auth_len: usize,
+) -> i32 {
+	let Some(client) = (unsafe { client.as_mut() }) else {
+		return NAIA_ERR_INVALID_ARGUMENT;
+	};
+	if addr.is_null() {
+		return NAIA_ERR_INVALID_ARGUMENT;
+	}
+	let Some(addr) = unsafe { CStr::from_ptr(addr) }.to_str().ok()
+		.and_then(|addr| addr.parse::<SocketAddr>().ok())
+	else {
+		return NAIA_ERR_INVALID_ARGUMENT;
+	};
+	let Some(auth) = (unsafe { bytes(auth, auth_len) }) else {
+		return NAIA_ERR_INVALID_ARGUMENT;
+	};
+	if !client.client.is_disconnected() {
+		return NAIA_ERR_INVALID_STATE;
+	}
+
+	match client.client.connect(addr, Blob::new(auth)) {
+		Ok(()) => NAIA_OK,
+		Err(_) => NAIA_ERR_IO,
+	}
+}
+
+/// Disconnect from the server
+///
+/// # Safety
+/// `client` must be a valid client.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn naia_client_disconnect(client: *mut NaiaClient) -> i32 {
+	let Some(client) = (unsafe { client.as_mut() }) else {
+		return NAIA_ERR_INVALID_ARGUMENT;
+	};
+	if client.client.is_disconnected() {
+		return NAIA_ERR_INVALID_STATE;
+	}
+
+	match client.client.disconnect() {
+		Ok(()) => NAIA_OK,
+		Err(_) => NAIA_ERR_IO,
+	}
+}
+
+/// Returns whether a connection to the server has been established
+///
+/// # Safety
+/// `client` must be a valid client.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn naia_client_is_connected(client: *const NaiaClient) -> bool {
+	unsafe { client.as_ref() }.is_some_and(|client| client.client.is_connected())
+}
+
+/// Queue a Blob to be sent on the given channel during the next `naia_client_send()`
+///
+/// # Safety
+/// `client` must be a valid client, and `data` must point to `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn naia_client_send_message(
+	client: *mut NaiaClient, channel: u8, data: *const u8, len: usize,
+) -> i32 {
+	let Some(client) = (unsafe { client.as_mut() }) else {
+		return NAIA_ERR_INVALID_ARGUMENT;
+	};
+	let Some(data) = (unsafe { bytes(data, len) }) else {
+		return NAIA_ERR_INVALID_ARGUMENT;
+	};
+	let Some(config) = client.channels.get(channel as usize) else {
+		return NAIA_ERR_INVALID_ARGUMENT;
+	};
+	if config.direction() == Some(ChannelDirection::ServerToClient) {
+		return NAIA_ERR_INVALID_ARGUMENT;
+	}
+	if client.client.is_disconnected() {
+		return NAIA_ERR_INVALID_STATE;
+	}
+
+	let blob = Blob::new(data);
+	with_blob_channel!(channel, C => client.client.send_message::<C, Blob>(&blob));
+	NAIA_OK
+}
+
+/// Send all queued messages and maintain the connection. Call this regularly, e.g.
+/// at the end of every frame.
+///
+/// # Safety
+/// `client` must be a valid client.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn naia_client_send(client: *mut NaiaClient) {
+	let Some(client) = (unsafe { client.as_mut() }) else {
+		return;
+	};
+	if !client.client.is_disconnected() {
+		client.client.send();
+	}
+}
+
+/// Receive incoming packets and queue the resulting events for `naia_client_poll()`.
+/// Call this regularly, e.g. at the start of every frame. Returns the number of
+/// queued events.
+///
+/// # Safety
+/// `client` must be a valid client.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn naia_client_receive(client: *mut NaiaClient) -> usize {
+	let Some(client) = (unsafe { client.as_mut() }) else {
+		return 0;
+	};
+	if !client.client.is_disconnected() {
+		client.events.extend(client.client.receive());
+	}
+	client.events.len()
+}
+
+/// Pop the next queued event into `event`. Returns false if there are no more events.
+///
+/// # Safety
+/// `client` must be a valid client, and `event` must point to writable memory for a
+/// `NaiaEvent`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn naia_client_poll(client: *mut NaiaClient, event: *mut NaiaEvent) -> bool {
+	let (Some(client), Some(event)) = (unsafe { client.as_mut() }, unsafe { event.as_mut() }) else {
+		return false;
+	};
+	let Some(next) = client.events.pop_front() else {
+		return false;
+	};
+
+	let (kind, reject_reason, data) = match next {
+		ClientEvent::Connect(_) => (NaiaEventKind::Connect, 0, Box::default()),
+		ClientEvent::DecryptFailures => (NaiaEventKind::DecryptFailures, 0, Box::default()),
+		ClientEvent::Disconnect(_) => (NaiaEventKind::Disconnect, 0, Box::default()),
+		ClientEvent::Error(e) => (NaiaEventKind::Error, 0, e.to_string().into_bytes().into()),
+		ClientEvent::LinkDegraded { .. } => (NaiaEventKind::LinkDegraded, 0, Box::default()),
+		ClientEvent::LinkRecovered => (NaiaEventKind::LinkRecovered, 0, Box::default()),
+		ClientEvent::Message(msg) => match msg.downcast::<Blob>() {
+			Ok(blob) => (NaiaEventKind::Message, 0, blob.bytes),
+			// unwinding must not cross the FFI boundary
+			Err(msg) => {
+				let error = format!("Received {}, but only Blob messages are supported", msg.name());
+				(NaiaEventKind::Error, 0, error.into_bytes().into())
+			}
+		},
+		ClientEvent::Redirect { addr, .. } =>
+			(NaiaEventKind::Redirect, 0, addr.to_string().into_bytes().into()),
+		ClientEvent::Redirected(addr) =>
+			(NaiaEventKind::Redirected, 0, addr.to_string().into_bytes().into()),
+		ClientEvent::SyncProgress(progress) =>
+			(NaiaEventKind::SyncProgress, 0, progress.to_le_bytes().into()),
+		ClientEvent::Synced(snapshot) => (NaiaEventKind::Synced, 0, snapshot),
+		ClientEvent::HandshakeProgress(step) => {
+			let step: u8 = match step {
+				HandshakeStep::EncryptSent => 0,
+				HandshakeStep::KeysEstablished => 1,
+				HandshakeStep::ConnectSent => 2,
+			};
+			(NaiaEventKind::HandshakeProgress, 0, [step].into())
+		}
+		ClientEvent::Reject(_, reason) => {
+			let reason = match reason {
+				RejectReason::AuthFailed => 0,
+				RejectReason::Disconnect => 1,
+				RejectReason::ServerFull => 2,
+				RejectReason::Version => 3,
+				RejectReason::Duplicate => 4,
+				RejectReason::ConnectTooLarge => 5,
+			};
+			(NaiaEventKind::Reject, reason, Box::default())
+		}
+	};
+
+	client.event_data = data;
+	*event = NaiaEvent {
+		kind,
+		reject_reason,
+		data: client.event_data.as_ptr(),
+		len: client.event_data.len(),
+	};
+	true
+}
+
+/// Borrow `len` bytes at `data`, which may be null only if `len` is 0
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+	if len == 0 {
+		return Some(&[]);
+	}
+	if data.is_null() {
+		return None;
+	}
+	Some(unsafe { slice::from_raw_parts(data, len) })
+}
@@ -1,13 +1,16 @@
 use log::warn;
 use naia_shared::{
-	Channel, ChannelKind, error::*, Io, ConditionerConfig, Message,
-	MessageContainer, Schema,
+	BitReader, BitVecWriter, Channel, ChannelKind, CipherKind, ConnectionMiddleware, error::*, Instant, Io,
+	ConditionerConfig, ConnectionStats, HandshakeTiming, LinkQuality, Message, MessageContainer, QueueDepth, Schema,
+	ChecksumChannel, SnapshotReceiver, Stamped, StateChecksum, SyncChannel, SyncChunk, SyncDone,
 };
-use std::{collections::VecDeque, io, net::SocketAddr, time::Instant};
+use naia_shared::metrics::{MetricsReporter, MetricsSink, MetricsSnapshot};
+use std::{collections::VecDeque, io, net::SocketAddr, time::Duration};
 use super::{
 	client_config::ClientConfig,
 	ClientEvent,
 	connection::*,
+	quality::Quality,
 };
 
 /// Client can send/receive messages to/from a server, and has a pool of
@@ -18,9 +21,25 @@ pub struct Client {
     schema: Schema,
     // Connection
 	io_conn: Option<(Io, Connection)>,
+	suspended: bool,
+	/// Whether the socket reported the Server's port closed since connecting
+	refused: bool,
     waitlist_messages: VecDeque<(ChannelKind, Box<dyn Message>)>,
+	/// Captured when the last established connection timed out or was dropped by the
+	/// Server
+	resume_token: Option<Box<[u8]>>,
+	/// The snapshot being received, see `ClientEvent::SyncProgress`
+	sync: SnapshotReceiver,
     // Events
     incoming_events: Vec::<ClientEvent>,
+	/// Events held back by `set_simulated_latency()`, with when they are due
+	delayed_events: VecDeque<(Instant, ClientEvent)>,
+	simulated_latency: Duration,
+	/// The number of events returned by `receive()` so far
+	event_seq: u64,
+	/// Counters at the last `reset_metrics()`, until the connection is reset
+	metrics_baseline: Option<MetricsSnapshot>,
+	metrics_reporter: Option<MetricsReporter>,
 }
 
 impl Client {
@@ -32,9 +51,18 @@ impl Client {
             schema,
             // Connection
 			io_conn: None,
+			suspended: false,
+			refused: false,
             waitlist_messages: VecDeque::new(),
+			resume_token: None,
+			sync: SnapshotReceiver::default(),
             // Events
             incoming_events: Vec::new(),
+			delayed_events: VecDeque::new(),
+			simulated_latency: Duration::ZERO,
+			event_seq: 0,
+			metrics_baseline: None,
+			metrics_reporter: None,
         }
     }
 
@@ -43,29 +71,129 @@ impl Client {
 
     /// Connect to the given server address
     pub fn connect<M: Message>(&mut self, addr: SocketAddr, msg: M) -> NaiaResult {
+		self.connect_inner(addr, None, msg)
+    }
+
+    /// Like `connect()`, claiming to be the given account, e.g. so the Server can
+    /// detect duplicate sessions; see `ServerConfig::duplicate_identity`. The claim
+    /// is not verified by naia, so the Server should check it along with `msg`.
+    pub fn connect_as<M: Message>(&mut self, addr: SocketAddr, identity: &str, msg: M) -> NaiaResult {
+		self.connect_inner(addr, Some(identity.to_string()), msg)
+    }
+
+    fn connect_inner<M: Message>(&mut self, addr: SocketAddr, identity: Option<String>, msg: M) -> NaiaResult {
 		debug_assert!(self.is_disconnected());
         if !self.is_disconnected() {
             warn!("Client is already connected");
 			return Err(io::ErrorKind::AlreadyExists.into());
         }
 
-		let io = Io::connect(addr, self.conditioner_config())?;
+		let io = Io::connect(addr, self.conditioner_config(), &self.config.socket)?;
 		let mut conn = Connection::new(
 			&addr,
 			&self.config.connection,
 			self.config.handshake_resend_interval,
+			self.config.observer,
 			self.schema.channel_kinds(),
 		);
 		conn.set_connect_message(Box::new(msg));
+		if let Some(identity) = identity {
+			conn.set_identity(identity);
+		}
 
 		self.io_conn = Some((io, conn));
+		self.resume_token = None;
 
 		Ok(())
     }
 
+    /// Returns the token needed to resume the last connection with
+    /// `resume_session()`, if it timed out or was dropped by the Server. The token
+    /// contains the connection's encryption key, so it must be stored securely.
+	pub fn resume_token(&self) -> Option<&[u8]> { self.resume_token.as_deref() }
+
+    /// Resume a connection from its `resume_token()`, keeping its UserKey and
+    /// skipping the handshake, if the Server still holds the session; see
+    /// `ServerConfig::resume_window`. A Connect event follows if the Server accepts
+    /// it, or a Disconnect event once the connection times out otherwise.
+	pub fn resume_session(&mut self, token: &[u8]) -> NaiaResult {
+		debug_assert!(self.is_disconnected());
+        if !self.is_disconnected() {
+            warn!("Client is already connected");
+			return Err(io::ErrorKind::AlreadyExists.into());
+        }
+
+		let mut reader = BitReader::from_slice(token);
+		let conn = Connection::import(
+			&self.config.connection,
+			self.config.handshake_resend_interval,
+			&self.schema,
+			&mut reader,
+		)?;
+		let io = Io::connect(*conn.address(), self.conditioner_config(), &self.config.socket)?;
+
+		self.io_conn = Some((io, conn));
+		self.resume_token = None;
+
+		Ok(())
+	}
+
+    /// Register middleware to observe (or modify) the packets exchanged with the
+    /// Server. Must be called after `connect()`; middleware is dropped along with the
+    /// connection.
+	pub fn add_middleware(&mut self, middleware: Box<dyn ConnectionMiddleware>) {
+		debug_assert!(!self.is_disconnected());
+		if let Some((_, conn)) = &mut self.io_conn {
+			conn.add_middleware(middleware);
+		}
+	}
+
+    /// Buffer messages received on channel `C` instead of emitting them, e.g. until a
+    /// scene is loaded. Must be called after `connect()`; holds are dropped along with
+    /// the connection.
+	pub fn hold_channel<C: Channel>(&mut self) {
+		debug_assert!(!self.is_disconnected());
+		if let Some((_, conn)) = &mut self.io_conn {
+			conn.hold_channel(&ChannelKind::of::<C>());
+		}
+	}
+
+    /// Stop holding channel `C`. The held messages are emitted by the next
+    /// `receive()`, in order and before any received later.
+	pub fn release_channel<C: Channel>(&mut self) {
+		if let Some((_, conn)) = &mut self.io_conn {
+			conn.release_channel(&ChannelKind::of::<C>());
+		}
+	}
+
+	pub fn is_channel_held<C: Channel>(&self) -> bool {
+		self.conn().is_some_and(|conn| conn.is_channel_held(&ChannelKind::of::<C>()))
+	}
+
+    /// Like `hold_channel()`, for all channels of `group`; see
+    /// `SchemaBuilder::channel_group()`
+	pub fn hold_group(&mut self, group: &str) {
+		debug_assert!(!self.is_disconnected());
+		let channel_kinds = self.schema.channel_group(group);
+		if let Some((_, conn)) = &mut self.io_conn {
+			for channel_kind in &channel_kinds {
+				conn.hold_channel(channel_kind);
+			}
+		}
+	}
+
+	pub fn release_group(&mut self, group: &str) {
+		let channel_kinds = self.schema.channel_group(group);
+		if let Some((_, conn)) = &mut self.io_conn {
+			for channel_kind in &channel_kinds {
+				conn.release_channel(channel_kind);
+			}
+		}
+	}
+
     /// Returns whether or not the client is disconnected
     pub fn is_disconnected(&self) -> bool {
-		self.conn().map(Connection::is_connected) == None
+		self.conn().map(Connection::is_connected).is_none()
 	}
 
     /// Returns whether or not a connection is being established with the Server
@@ -78,6 +206,13 @@ impl Client {
 		self.conn().map(Connection::is_connected) == Some(true)
 	}
 
+    /// Returns whether or not the Server accepted this Client as a receive-only
+    /// observer. Messages sent by observers are dropped by the Server, except on
+    /// channels registered with `SchemaBuilder::add_observer_channel()`.
+    pub fn is_observer(&self) -> bool {
+		self.conn().is_some_and(Connection::is_observer)
+	}
+
     /// Disconnect from Server
 	pub fn disconnect(&mut self) -> NaiaResult {
 		debug_assert!(!self.is_disconnected());
@@ -94,6 +229,44 @@ impl Client {
 		Ok(())
 	}
 
+    /// Suspend the connection, e.g. when a mobile app is moved to the background.
+    /// While suspended, `receive()` and `send()` do nothing and the connection can't
+    /// time out. The Server still times out Clients which stay suspended for longer
+    /// than its timeout.
+	pub fn suspend(&mut self) {
+		debug_assert!(!self.is_disconnected());
+		self.suspended = true;
+	}
+
+    /// Resume a suspended connection. The socket is rebound, since the OS may have
+    /// closed it or the NAT binding may have changed in the meantime, and an
+    /// established connection is migrated to the new address instead of reconnecting.
+	pub fn resume(&mut self) -> NaiaResult {
+		debug_assert!(self.is_suspended());
+		self.suspended = false;
+		let Some((io, conn)) = &mut self.io_conn else {
+			return Err(io::ErrorKind::NotConnected.into());
+		};
+
+		io.reconnect(*conn.address())?;
+		conn.resume(&Instant::now());
+
+		Ok(())
+	}
+
+    /// Returns whether or not the connection is suspended
+	pub fn is_suspended(&self) -> bool { self.suspended }
+
+    /// Delay all events returned by `receive()` by `latency`, e.g. to try out high
+    /// ping from an in-game slider in a local build. Unlike the conditioner, it can
+    /// be changed at any time, and packets are still handled as they arrive. Events
+    /// still held back when the connection is dropped are discarded.
+	pub fn set_simulated_latency(&mut self, latency: Duration) {
+		self.simulated_latency = latency;
+	}
+
+	pub fn simulated_latency(&self) -> Duration { self.simulated_latency }
+
     /// Returns conditioner config
 	pub fn conditioner_config(&self) -> &Option<ConditionerConfig> {
 		&self.config.connection.conditioner
@@ -106,16 +279,30 @@ impl Client {
     /// Retrieves incoming update data from the server, and maintains the connection.
     pub fn receive(&mut self) -> Vec<ClientEvent> {
 		debug_assert!(!self.is_disconnected());
-		if self.io_conn.is_none() {
+		let events = self.receive_events();
+		self.event_seq += events.len() as u64;
+		events
+    }
+
+    /// Like `receive()`, but with each event's sequence number and receive time; see
+    /// `Stamped`
+    pub fn receive_stamped(&mut self) -> Vec<Stamped<ClientEvent>> {
+		let (first_seq, received) = (self.event_seq, Instant::now());
+		Stamped::stamp_all(self.receive(), first_seq, received)
+    }
+
+    fn receive_events(&mut self) -> Vec<ClientEvent> {
+		if self.io_conn.is_none() || self.suspended {
 			return Vec::new();
 		};
 
 		// receive from socket
+		let now = Instant::now();
 		loop {
 			let (io, conn) = self.io_conn.as_mut().unwrap();
 			match io.recv_reader() {
 				Ok(Some((_, mut reader))) => {
-					match conn.receive_packet(&mut reader, io, &self.schema) {
+					match conn.receive_packet(&now, &mut reader, io, &self.schema) {
 						Ok(ReceiveEvent::Connected) => {
 							let addr = *conn.address();
 							self.on_connect();
@@ -123,6 +310,8 @@ impl Client {
 							break;
 						}
 						Ok(ReceiveEvent::Disconnect) => {
+							self.capture_resume_token();
+							let (_, conn) = self.io_conn.as_ref().unwrap();
 							let event = ClientEvent::Disconnect(*conn.address());
 							return self.disconnect_with_events(event);
 						}
@@ -130,12 +319,53 @@ impl Client {
 							let event = ClientEvent::Reject(*conn.address(), reason);
 							return self.disconnect_with_events(event);
 						}
-						Ok(ReceiveEvent::None) => (),
-						Err(e) => self.incoming_events.push(ClientEvent::Error(e)),
+						Ok(ReceiveEvent::Redirect { addr, token }) =>
+							self.incoming_events.push(ClientEvent::Redirect { addr, token }),
+						Ok(ReceiveEvent::Redirected) =>
+							self.incoming_events.push(ClientEvent::Redirected(*conn.address())),
+						Ok(ReceiveEvent::None) => {
+							if let Some(step) = conn.take_handshake_step() {
+								self.incoming_events.push(ClientEvent::HandshakeProgress(step));
+							}
+						}
+						Err(e @ NaiaError::Loopback(_)) => {
+							// fail fast instead of handshaking with ourselves until the timeout
+							let event = ClientEvent::Disconnect(*conn.address());
+							let mut events = self.disconnect_with_events(event);
+							events.insert(0, ClientEvent::Error(e));
+							return events;
+						}
+						Err(e) if matches!(e, NaiaError::MessageTooLarge(_) | NaiaError::ReassemblyFailed)
+							|| matches!(e, NaiaError::ChannelMisuse(_)) && self.config.connection.strict_channels => {
+							self.incoming_events.push(ClientEvent::Error(e));
+							// best effort
+							if let Err(e) = conn.disconnect(io) {
+								warn!("Failed to disconnect from Server: {e:?}");
+							}
+							let event = ClientEvent::Disconnect(*conn.address());
+							return self.disconnect_with_events(event);
+						}
+						Err(e) => {
+							self.incoming_events.push(ClientEvent::Error(e));
+							if !conn.take_decrypt_alarm() {
+								continue;
+							}
+
+							self.incoming_events.push(ClientEvent::DecryptFailures);
+							if self.config.connection.decrypt_failures.as_ref().is_some_and(|config| config.disconnect) {
+								// best effort
+								if let Err(e) = conn.disconnect(io) {
+									warn!("Failed to disconnect from Server: {e:?}");
+								}
+								let event = ClientEvent::Disconnect(*conn.address());
+								return self.disconnect_with_events(event);
+							}
+						}
 					}
 				}
 				Ok(None) => break,
 				Err(e) => {
+					self.refused |= conn.is_connected() && e.is_refused();
 					self.incoming_events.push(ClientEvent::Error(e));
 					break;
 				}
@@ -144,52 +374,166 @@ impl Client {
 
         // all other operations
 		let (_, conn) = self.io_conn.as_mut().unwrap();
-		if conn.timed_out() {
+		if conn.timed_out(&now) || self.refused && self.config.disconnect_on_refused {
 			let event = ClientEvent::Disconnect(*conn.address());
+			self.capture_resume_token();
 			return self.disconnect_with_events(event);
 		}
 
+		let mut synced = false;
 		for msg in conn.receive_messages() {
-			self.incoming_events.push(ClientEvent::Message(msg));
+			let chunk = match msg.downcast::<SyncChunk>() {
+				Ok(chunk) => chunk,
+				Err(msg) => {
+					self.incoming_events.push(ClientEvent::Message(msg));
+					continue;
+				}
+			};
+
+			let snapshot = self.sync.receive(chunk);
+			let progress = if snapshot.is_some() { 1.0 } else { self.sync.progress() };
+			self.incoming_events.push(ClientEvent::SyncProgress(progress));
+			if let Some(snapshot) = snapshot {
+				self.incoming_events.push(ClientEvent::Synced(snapshot));
+				synced = true;
+			}
+		}
+		if synced {
+			self.send_message_inner(&ChannelKind::of::<SyncChannel>(), Box::new(SyncDone));
 		}
 
-        std::mem::take(&mut self.incoming_events)
+		let events = std::mem::take(&mut self.incoming_events);
+		self.delay_events(events)
     }
 
+	/// Hold back `events` for the simulated latency, returning those now due
+	fn delay_events(&mut self, events: Vec<ClientEvent>) -> Vec<ClientEvent> {
+		if self.simulated_latency.is_zero() && self.delayed_events.is_empty() {
+			return events;
+		}
+
+		let now = Instant::now();
+		let due = now + self.simulated_latency;
+		self.delayed_events.extend(events.into_iter().map(|event| (due, event)));
+		// in order, even if the latency was lowered meanwhile
+		let count = self.delayed_events.iter().take_while(|(due, _)| *due <= now).count();
+		self.delayed_events.drain(..count).map(|(_, event)| event).collect()
+	}
+
+	/// Send the protocol maintenance packets which are due, i.e. handshake and Migrate
+	/// resends, pings and heartbeats, but no messages, and return when the next ones
+	/// are due. Use it to keep the connection alive while `send()` can't be called
+	/// often enough, e.g. during a long level load, or from a timer while the frame
+	/// rate is low. Returns `None` without a connection.
+	pub fn duty_cycle(&mut self) -> Option<Instant> {
+		let (io, conn) = self.io_conn.as_mut()?;
+		if self.suspended {
+			return None;
+		}
+
+		if let Err(e) = conn.send_maintenance(&Instant::now(), &self.schema, io) {
+			self.refused |= conn.is_connected() && e.is_refused();
+			self.incoming_events.push(ClientEvent::Error(e));
+		}
+		conn.next_maintenance()
+	}
+
 	pub fn send(&mut self) {
 		debug_assert!(!self.is_disconnected());
 		let Some((io, conn)) = &mut self.io_conn else {
 			return;
 		};
+		if self.suspended {
+			return;
+		}
 
 		if let Err(e) = conn.send(&Instant::now(), &self.schema, io) {
+			self.refused |= conn.is_connected() && e.is_refused();
 			self.incoming_events.push(ClientEvent::Error(e));
 		}
+		if let Some(step) = conn.take_handshake_step() {
+			self.incoming_events.push(ClientEvent::HandshakeProgress(step));
+		}
+
+		match conn.take_link_change() {
+			Some(LinkQuality::Bad) => self.incoming_events.push(ClientEvent::LinkDegraded {
+				loss: conn.packet_loss(), rtt_ms: conn.rtt_ms(),
+			}),
+			Some(LinkQuality::Good) => self.incoming_events.push(ClientEvent::LinkRecovered),
+			None => (),
+		}
+
+		self.report_metrics();
+	}
+
+	/// Report the performance counters, rates, and link statistics to the given sink
+	/// every `interval`, from within `send()`. Replaces any previous sink. Counters are
+	/// reported as returned by their getters, i.e. unaffected by `reset_metrics()`.
+	pub fn set_metrics_sink(&mut self, sink: Box<dyn MetricsSink>, interval: Duration) {
+		self.metrics_reporter = Some(MetricsReporter::new(sink, interval));
+	}
+
+	/// Stop reporting metrics, returning the current sink
+	pub fn remove_metrics_sink(&mut self) -> Option<Box<dyn MetricsSink>> {
+		self.metrics_reporter.take().map(MetricsReporter::into_sink)
+	}
+
+	fn report_metrics(&mut self) {
+		if !self.metrics_reporter.as_mut().is_some_and(MetricsReporter::due) {
+			return;
+		}
+
+		let snapshot = self.counters_snapshot();
+		let gauges: [(&str, &[(&str, &str)], f64); 7] = [
+			("bytes_rx_per_sec", &[], self.bytes_rx_per_sec() as f64),
+			("bytes_tx_per_sec", &[], self.bytes_tx_per_sec() as f64),
+			("pkt_rx_per_sec", &[], self.pkt_rx_per_sec() as f64),
+			("pkt_tx_per_sec", &[], self.pkt_tx_per_sec() as f64),
+			("rtt_ms", &[], self.rtt_ms() as f64),
+			("jitter_ms", &[], self.jitter_ms() as f64),
+			("packet_loss", &[], self.packet_loss() as f64),
+		];
+		self.metrics_reporter.as_mut().unwrap().report(&snapshot, &gauges);
 	}
 
     // Messages
 
-    /// Queues up an Message to be sent to the Server
+    /// Queues up an Message to be sent to the Server. Channels which don't allow
+    /// client-to-server messages emit a `NaiaError::ChannelMisuse` error event
+    /// instead, and messages exceeding the Schema's maximum message size a
+    /// `NaiaError::MessageTooLarge` error event.
     pub fn send_message<C: Channel, M: Message>(&mut self, message: &M) {
 		debug_assert!(!self.is_disconnected());
         let cloned_message = M::clone_box(message);
         self.send_message_inner(&ChannelKind::of::<C>(), cloned_message);
     }
 
+    /// Report a hash of the simulation state after `tick`, for the Server to compare
+    /// with its own; see `Server::set_checksum()`. Requires
+    /// `SchemaBuilder::enable_checksums()`.
+    pub fn send_checksum(&mut self, tick: u32, hash: u64) {
+		debug_assert!(self.schema.has_checksums(), "the Schema doesn't enable checksums");
+		self.send_message_inner(&ChannelKind::of::<ChecksumChannel>(), Box::new(StateChecksum { tick, hash }));
+    }
+
     fn send_message_inner(&mut self, channel_kind: &ChannelKind, message_box: Box<dyn Message>) {
 		debug_assert!(!self.is_disconnected());
 
         let channel_settings = self.schema.channel_kinds().channel(channel_kind);
         if !channel_settings.can_send_to_server() {
-            panic!("Cannot send message to Server on this Channel");
+			let name = self.schema.channel_kinds().name(channel_kind).to_string();
+			self.incoming_events.push(ClientEvent::Error(NaiaError::ChannelMisuse(name)));
+			return;
         }
 
         if let Some((_, conn)) = &mut self.io_conn {
             let msg = MessageContainer::from_write(message_box);
-            conn.queue_message(&self.schema, channel_kind, msg);
+            if let Err(e) = conn.queue_message(&self.schema, channel_kind, msg) {
+				self.incoming_events.push(ClientEvent::Error(e));
+			}
         } else {
             self.waitlist_messages
-                .push_back((channel_kind.clone(), message_box));
+                .push_back((*channel_kind, message_box));
         }
     }
 
@@ -220,8 +564,62 @@ impl Client {
 		self.conn().map(Connection::jitter_ms).unwrap_or(0.0)
     }
 
+    /// Gets the fraction of packets recently lost in transit to the Server
+    pub fn packet_loss(&self) -> f32 {
+		debug_assert!(!self.is_disconnected());
+		self.conn().map(Connection::packet_loss).unwrap_or(0.0)
+    }
+
+    /// Gets the round trip time, jitter and packet loss to the Server, rated for
+    /// display, e.g. as connection bars
+    pub fn connection_quality(&self) -> Quality {
+		debug_assert!(!self.is_disconnected());
+		Quality::new(self.rtt_ms(), self.jitter_ms(), 100.0 * self.packet_loss())
+    }
+
+    /// Gets the link quality to the Server, as determined by congestion control.
+    /// Applications may want to reduce update fidelity when this is `Bad`.
+    pub fn link_quality(&self) -> LinkQuality {
+		debug_assert!(!self.is_disconnected());
+		self.conn().map(Connection::link_quality).unwrap_or_default()
+    }
+
+    /// Gets the backlog of messages queued for the Server, or awaiting its
+    /// acknowledgement
+    pub fn outgoing_queue_depth(&self) -> QueueDepth {
+		debug_assert!(!self.is_disconnected());
+		self.conn().map(Connection::queue_depth).unwrap_or_default()
+    }
+
+    /// Gets the counts of packets received from the Server, by type
+    pub fn connection_stats(&self) -> ConnectionStats {
+		self.conn().map(Connection::stats).unwrap_or_default()
+    }
+
+    /// Gets how long each step of the handshake with the Server took
+    pub fn handshake_timing(&self) -> HandshakeTiming {
+		self.conn().map(Connection::handshake_timing).unwrap_or_default()
+    }
+
+    /// Gets the cipher negotiated with the Server, once keys were exchanged
+    pub fn cipher(&self) -> Option<CipherKind> {
+		self.conn().and_then(Connection::cipher)
+    }
+
     // Private methods
 
+	fn capture_resume_token(&mut self) {
+		let Some(conn) = self.conn() else {
+			return;
+		};
+
+		let mut writer = BitVecWriter::new();
+		self.resume_token = match conn.export(&self.schema, &mut writer) {
+			Ok(()) => Some(writer.into_bytes()),
+			Err(_) => None,
+		};
+	}
+
 	fn disconnect_with_events(&mut self, event: ClientEvent) -> Vec<ClientEvent> {
 		self.reset_connection();
 		self.incoming_events.push(event);
@@ -230,19 +628,81 @@ impl Client {
 
 	fn reset_connection(&mut self) {
 		self.io_conn = None;
+		self.suspended = false;
+		self.refused = false;
 		self.incoming_events.clear();
+		self.delayed_events.clear();
 		self.waitlist_messages.clear();
+		self.sync = SnapshotReceiver::default();
+		self.metrics_baseline = None;
 	}
 
 	// performance counters
 
 	pub fn bytes_rx(&self) -> u64 { self.io().map(Io::bytes_rx).unwrap_or(0) }
 	pub fn bytes_tx(&self) -> u64 { self.io().map(Io::bytes_tx).unwrap_or(0) }
+	pub fn frag_rx_expired_count(&self) -> u64 { self.conn().map(Connection::frag_rx_expired_count).unwrap_or(0) }
+	pub fn frag_rx_refused_count(&self) -> u64 { self.conn().map(Connection::frag_rx_refused_count).unwrap_or(0) }
 	pub fn msg_rx_count(&self) -> u64 { self.conn().map(Connection::msg_rx_count).unwrap_or(0) }
 	pub fn msg_rx_drop_count(&self) -> u64 { self.conn().map(Connection::msg_rx_drop_count).unwrap_or(0) }
 	pub fn msg_rx_miss_count(&self) -> u64 { self.conn().map(Connection::msg_rx_miss_count).unwrap_or(0) }
 	pub fn msg_tx_count(&self) -> u64 { self.conn().map(Connection::msg_tx_count).unwrap_or(0) }
 	pub fn msg_tx_queue_count(&self) -> u64 { self.conn().map(Connection::msg_tx_queue_count).unwrap_or(0) }
 	pub fn pkt_rx_count(&self) -> u64 { self.io().map(Io::pkt_rx_count).unwrap_or(0) }
+	pub fn pkt_rx_decrypt_fail_count(&self) -> u64 { self.conn().map(Connection::pkt_rx_decrypt_fail_count).unwrap_or(0) }
 	pub fn pkt_tx_count(&self) -> u64 { self.io().map(Io::pkt_tx_count).unwrap_or(0) }
+
+	/// The performance counters accumulated since the last `reset_metrics()`, e.g. to
+	/// `diff()` with an older snapshot
+	pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+		let snapshot = self.counters_snapshot();
+		match &self.metrics_baseline {
+			Some(baseline) => snapshot.since(baseline),
+			None => snapshot,
+		}
+	}
+
+	/// Restart the counters of `metrics_snapshot()` from 0. The individual counter
+	/// getters are unaffected.
+	pub fn reset_metrics(&mut self) {
+		self.metrics_baseline = Some(self.counters_snapshot());
+	}
+
+	fn counters_snapshot(&self) -> MetricsSnapshot {
+		MetricsSnapshot {
+			taken: Instant::now(),
+			bytes_rx: self.bytes_rx(),
+			bytes_tx: self.bytes_tx(),
+			frag_rx_expired_count: self.frag_rx_expired_count(),
+			frag_rx_refused_count: self.frag_rx_refused_count(),
+			msg_rx_count: self.msg_rx_count(),
+			msg_rx_drop_count: self.msg_rx_drop_count(),
+			msg_rx_miss_count: self.msg_rx_miss_count(),
+			msg_rx_observer_drop_count: 0,
+			msg_rx_reject_count: 0,
+			msg_rx_throttle_count: 0,
+			msg_tx_count: self.msg_tx_count(),
+			msg_tx_queue_count: self.msg_tx_queue_count(),
+			pkt_rx_count: self.pkt_rx_count(),
+			pkt_rx_decrypt_fail_count: self.pkt_rx_decrypt_fail_count(),
+			pkt_tx_count: self.pkt_tx_count(),
+		}
+	}
+
+	// rates over the last second
+
+	pub fn bytes_rx_per_sec(&self) -> f32 { self.io().map(Io::bytes_rx_per_sec).unwrap_or(0.0) }
+	pub fn bytes_tx_per_sec(&self) -> f32 { self.io().map(Io::bytes_tx_per_sec).unwrap_or(0.0) }
+	pub fn pkt_rx_per_sec(&self) -> f32 { self.io().map(Io::pkt_rx_per_sec).unwrap_or(0.0) }
+	pub fn pkt_tx_per_sec(&self) -> f32 { self.io().map(Io::pkt_tx_per_sec).unwrap_or(0.0) }
+
+	/// The rate of messages received on channel `C`
+	pub fn msg_rx_per_sec<C: Channel>(&self) -> f32 {
+		self.conn().map(|conn| conn.msg_rx_per_sec(&ChannelKind::of::<C>())).unwrap_or(0.0)
+	}
+
+	/// The rate of messages sent (including resends) on channel `C`
+	pub fn msg_tx_per_sec<C: Channel>(&self) -> f32 {
+		self.conn().map(|conn| conn.msg_tx_per_sec(&ChannelKind::of::<C>())).unwrap_or(0.0)
+	}
 }
@@ -1,4 +1,4 @@
-use naia_shared::ConnectionConfig;
+use naia_shared::{ConnectionConfig, SocketConfig};
 use std::{default::Default, time::Duration};
 
 /// Contains Config properties which will be used by a Client
@@ -6,8 +6,19 @@ use std::{default::Default, time::Duration};
 pub struct ClientConfig {
     /// Used to configure the connection with the Server
     pub connection: ConnectionConfig,
-    /// The duration between the resend of certain connection handshake messages
+    /// The duration between the resend of certain connection handshake messages, and
+    /// of Migrate probes after `Client::resume()`
     pub handshake_resend_interval: Duration,
+    /// Request to connect as a receive-only observer, e.g. a spectator. The Server
+    /// decides; see `Client::is_observer()`.
+    pub observer: bool,
+    /// Options of the socket, applied on each connect
+    pub socket: SocketConfig,
+    /// Disconnect as soon as the socket reports the Server's port closed, e.g. after
+    /// the Server process exited, rather than after the connection timeout. Only
+    /// applies once connected, on platforms reporting ICMP port unreachable to
+    /// connected sockets (e.g. Linux and Windows).
+    pub disconnect_on_refused: bool,
 }
 
 impl Default for ClientConfig {
@@ -15,6 +26,9 @@ impl Default for ClientConfig {
         Self {
             connection: ConnectionConfig::default(),
             handshake_resend_interval: Duration::from_millis(250),
+            observer: false,
+            socket: SocketConfig::default(),
+            disconnect_on_refused: true,
         }
     }
 }
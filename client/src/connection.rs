@@ -1,23 +1,27 @@
-use log::trace;
+use crate::HandshakeStep;
 use naia_shared::{
-	BaseConnection, BitReader, ChannelKind, ChannelKinds, ConnectionConfig, error::*,
-	HostType, Io, Message, MessageContainer, packet::*, Schema, Serde, Timer,
+	BaseConnection, BitReader, BitWrite, ChannelKind, ChannelKinds, CipherKind, EphemeralKey,
+	ConnectionConfig, ConnectionMiddleware, ConnectionStats, error::*, HandshakePadding, HandshakeTiming, HostType, Instant, Io, LinkQuality,
+	Message, MessageContainer, packet::*, QueueDepth, Salt, Schema, Serde, Timer,
 };
 use std::mem;
 use std::net::SocketAddr;
-use std::time::{Duration, Instant};
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use std::time::Duration;
 
 pub enum ReceiveEvent {
 	Connected,
 	Disconnect,
 	None,
+	Redirect { addr: SocketAddr, token: SessionId },
+	Redirected,
 	Rejected(RejectReason),
 }
 
 pub enum ConnectionState {
-	AwaitingEncryptResponse{ priv_key: EphemeralSecret, pub_key: PublicKey },
+	AwaitingEncryptResponse{ priv_key: EphemeralKey, pub_key: [u8; packet::DH_KEY_SIZE] },
 	AwaitingConnectResponse{ server_timestamp_ns: TimestampNs },
+	/// Restored from a resume token, awaiting the Server's answer to Migrate probes
+	Resuming,
 	Connected,
 	Disconnected,
 }
@@ -27,6 +31,27 @@ pub struct Connection {
 	state: ConnectionState,
 	handshake_timer: Timer,
 	connect_message: Option<Box<dyn Message>>,
+	/// The identity claimed in the connect request
+	identity: Option<String>,
+	cipher: CipherKind,
+	session_id: SessionId,
+	/// Whether Migrate probes are being sent until the Server responds
+	migrating: bool,
+	/// Salt of the key switched to by the current migration
+	migrate_salt: Salt,
+	/// Whether the Migrate probes were started by a Redirect
+	redirecting: bool,
+	/// Requested observer mode until connected, then the mode granted by the Server
+	observer: bool,
+	/// Whether to propose compact headers to the Server
+	compact_headers: bool,
+	handshake_padding: HandshakePadding,
+	/// The cookie of the Server's HandshakeRetry, echoed in further encrypt requests
+	cookie: Option<packet::HandshakeCookie>,
+	/// The furthest handshake step reached
+	handshake_step: Option<HandshakeStep>,
+	/// Whether `handshake_step` wasn't reported yet
+	handshake_step_new: bool,
 }
 
 impl Connection {
@@ -34,16 +59,29 @@ impl Connection {
 		address: &SocketAddr,
 		config: &ConnectionConfig,
 		handshake_resend_interval: Duration,
+		observer: bool,
 		channel_kinds: &ChannelKinds,
     ) -> Self {
-		let priv_key = EphemeralSecret::random();
-		let pub_key = PublicKey::from(&priv_key);
+		let priv_key = EphemeralKey::random();
+		let pub_key = priv_key.public_key();
 
         Self {
             base: BaseConnection::new(address, HostType::Client, config, channel_kinds),
 			state: ConnectionState::AwaitingEncryptResponse{ priv_key, pub_key },
 			handshake_timer: Timer::new_ringing(handshake_resend_interval),
 			connect_message: None,
+			identity: None,
+			cipher: config.cipher.unwrap_or_else(CipherKind::preferred),
+			session_id: 0,
+			migrating: false,
+			migrate_salt: Salt::default(),
+			redirecting: false,
+			observer,
+			compact_headers: config.compact_headers,
+			handshake_padding: config.handshake_padding,
+			cookie: None,
+			handshake_step: None,
+			handshake_step_new: false,
         }
     }
 
@@ -56,14 +94,39 @@ impl Connection {
 		mem::replace(&mut self.state, state)
 	}
 
+	fn reach_step(&mut self, step: HandshakeStep) {
+		if self.handshake_step < Some(step) {
+			self.handshake_step = Some(step);
+			self.handshake_step_new = true;
+		}
+	}
+
+	/// Returns the handshake step reached since the last call, if any
+	pub fn take_handshake_step(&mut self) -> Option<HandshakeStep> {
+		if !mem::take(&mut self.handshake_step_new) {
+			return None;
+		}
+		self.handshake_step
+	}
+
+	pub fn add_middleware(&mut self, middleware: Box<dyn ConnectionMiddleware>) {
+		self.base.add_middleware(middleware);
+	}
+
 	pub fn set_connect_message(&mut self, msg: Box<dyn Message>) {
 		self.connect_message = Some(msg);
 	}
 
+	pub fn set_identity(&mut self, identity: String) {
+		self.identity = Some(identity);
+	}
+
 	pub fn is_connected(&self) -> bool {
 		matches!(self.state, ConnectionState::Connected)
 	}
 
+	pub fn is_observer(&self) -> bool { self.is_connected() && self.observer }
+
 	fn send_handshake(&mut self, schema: &Schema, io: &mut Io) -> NaiaResult {
 		debug_assert!(!matches!(self.state, ConnectionState::Connected));
 
@@ -73,13 +136,14 @@ impl Connection {
 
 		match &mut self.state {
 			ConnectionState::AwaitingEncryptResponse{ pub_key , .. } => {
-				let pub_key = pub_key.to_bytes();
+				let pub_key = *pub_key;
 				self.send_encrypt_request(pub_key, io)?;
 			}
 			ConnectionState::AwaitingConnectResponse{ server_timestamp_ns } => {
 				let server_timestamp_ns = *server_timestamp_ns;
 				self.send_connect_request(schema, server_timestamp_ns, io)?;
 			}
+			ConnectionState::Resuming => unreachable!(),
 			ConnectionState::Connected => unreachable!(),
 			ConnectionState::Disconnected => unreachable!(),
 		}
@@ -91,20 +155,25 @@ impl Connection {
 		&mut self, reader: &mut BitReader
 	) -> NaiaResult<ReceiveEvent> {
 		let header = self.base.maybe_decrypt(reader)?;
-		match header.packet_type {
+		let result = match header.packet_type {
 			PacketType::EncryptResponse => self.recv_encrypt_response(reader),
+			PacketType::HandshakeRetry => self.recv_handshake_retry(reader),
 			PacketType::ConnectResponse => self.recv_connect_response(reader),
 			PacketType::HandshakeReject => self.recv_reject_response(reader),
-			_ => Ok(ReceiveEvent::None),
-		}
+			// only Clients send these, so the handshake is talking to itself
+			PacketType::EncryptRequest | PacketType::ConnectRequest =>
+				Err(NaiaError::Loopback(*self.address())),
+			_ => return Ok(ReceiveEvent::None),
+		};
+		self.base.inspect_rx(header.packet_type, &result, reader);
+		result
 	}
 
 	fn recv_reject_response(
 		&mut self, reader: &mut BitReader
 	) -> NaiaResult<ReceiveEvent> {
-		let Ok(resp) = packet::HandshakeReject::de(reader) else {
-			return Err(NaiaError::malformed::<packet::HandshakeReject>());
-		};
+		let resp = packet::HandshakeReject::de(reader)
+			.map_err(NaiaError::malformed_by::<packet::HandshakeReject>)?;
 		Ok(ReceiveEvent::Rejected(resp.reason))
 	}
 
@@ -118,10 +187,32 @@ impl Connection {
 		packet::EncryptRequest {
 			client_public_key: pub_key,
 			client_timestamp_ns: self.base.timestamp_ns(),
-			padding: [0; 256],
+			cipher: self.cipher,
+			cookie: self.cookie,
 		}.ser(&mut writer);
+		packet::EncryptRequest::write_padding(&mut writer, self.handshake_padding.bytes());
 
-		self.base.send(io, writer)
+		self.base.send(io, writer)?;
+		self.reach_step(HandshakeStep::EncryptSent);
+		Ok(())
+	}
+
+	// Step 1b of Handshake
+	fn recv_handshake_retry(
+		&mut self, reader: &mut BitReader,
+	) -> NaiaResult<ReceiveEvent> {
+		if !matches!(self.state, ConnectionState::AwaitingEncryptResponse{..}) {
+			return Ok(ReceiveEvent::None);
+		}
+
+		let retry = packet::HandshakeRetry::de(reader)
+			.map_err(NaiaError::malformed_by::<packet::HandshakeRetry>)?;
+
+		// echo the cookie right away
+		self.cookie = Some(retry.cookie);
+		self.handshake_timer.ring_manual();
+
+		Ok(ReceiveEvent::None)
 	}
 
 	// Step 2 of Handshake
@@ -132,9 +223,8 @@ impl Connection {
 			return Ok(ReceiveEvent::None);
 		}
 
-		let Ok(resp) = packet::EncryptResponse::de(reader) else {
-			return Err(NaiaError::malformed::<packet::EncryptResponse>());
-		};
+		let resp = packet::EncryptResponse::de(reader)
+			.map_err(NaiaError::malformed_by::<packet::EncryptResponse>)?;
 
 		self.base.sample_rtt(resp.client_timestamp_ns);
 
@@ -145,7 +235,9 @@ impl Connection {
 			unreachable!();
 		};
 
-		self.base.set_shared_key(priv_key, resp.server_public_key.into());
+		self.base.set_shared_key(priv_key, &resp.server_public_key, resp.cipher);
+		self.base.mark_encrypted();
+		self.reach_step(HandshakeStep::KeysEstablished);
 
 		Ok(ReceiveEvent::None)
 	}
@@ -160,6 +252,10 @@ impl Connection {
 		packet::ConnectRequest {
 			client_timestamp_ns: self.base.timestamp_ns(),
 			server_timestamp_ns,
+			observer: self.observer,
+			compact_headers: self.compact_headers,
+			schema_fingerprint: schema.fingerprint(),
+			identity: self.identity.clone(),
 		}.ser(&mut writer);
 
 		if let Some(connect_message) = &self.connect_message {
@@ -171,7 +267,9 @@ impl Connection {
 			false.ser(&mut writer);
 		}
 
-		self.base.send(io, writer)
+		self.base.send(io, writer)?;
+		self.reach_step(HandshakeStep::ConnectSent);
+		Ok(())
 	}
 
 	// Step 4 of Handshake
@@ -182,13 +280,18 @@ impl Connection {
 			return Ok(ReceiveEvent::None);
 		};
 
-		let Ok(resp) = packet::ConnectResponse::de(reader) else {
-			return Err(NaiaError::malformed::<packet::ConnectResponse>());
-		};
+		let resp = packet::ConnectResponse::de(reader)
+			.map_err(NaiaError::malformed_by::<packet::ConnectResponse>)?;
 
 		self.base.sample_rtt(resp.client_timestamp_ns);
+		self.session_id = resp.session_id;
+		self.observer = resp.observer;
+		self.base.set_compact_headers(resp.compact_headers);
+		// no longer needed for resends, and may hold credentials
+		self.connect_message = None;
 
 		self.set_state(ConnectionState::Connected);
+		self.base.mark_connected();
 		Ok(ReceiveEvent::Connected)
 	}
 
@@ -209,12 +312,119 @@ impl Connection {
 		Ok(())
 	}
 
+	// Migration
+
+	/// Resume after the socket was replaced, without counting the time spent
+	/// suspended towards the timeout. Established connections are moved to the new
+	/// address with Migrate probes; handshakes simply continue from the new address.
+	pub fn resume(&mut self, now: &Instant) {
+		self.base.set_now(now);
+		self.restart_from_new_address();
+	}
+
+	fn restart_from_new_address(&mut self) {
+		self.base.mark_heard();
+		self.handshake_timer.ring_manual();
+		if self.is_established() && !self.migrating {
+			// the Server may resume this connection from a snapshot, taken while the
+			// shared key was still in use
+			self.migrate_salt = fastrand::u128(..).to_le_bytes();
+			self.base.rekey(&self.migrate_salt);
+			self.migrating = true;
+		}
+	}
+
+	/// Whether the handshake is complete, even if the Server has yet to confirm a
+	/// resumed session
+	fn is_established(&self) -> bool {
+		matches!(self.state, ConnectionState::Connected | ConnectionState::Resuming)
+	}
+
+	fn send_migrate(&mut self, io: &mut Io) -> NaiaResult {
+		debug_assert!(self.migrating);
+
+		if !self.handshake_timer.try_reset() {
+			return Ok(());
+		}
+
+		let mut writer: _ = self.base.packet_writer(PacketType::Migrate);
+		let session_id = self.session_id;
+		let tag = self.base.sign(&writer, &session_id.to_le_bytes())?;
+		packet::Migrate { session_id, salt: self.migrate_salt, tag }.ser(&mut writer);
+		self.base.send(io, writer)
+	}
+
+	/// Move to the Server named by a Redirect packet, presenting the token with
+	/// Migrate probes until the new Server responds
+	fn recv_redirect(
+		&mut self, reader: &mut BitReader, io: &mut Io,
+	) -> NaiaResult<ReceiveEvent> {
+		let req = packet::Redirect::de(reader)
+			.map_err(NaiaError::malformed_by::<packet::Redirect>)?;
+
+		io.reconnect(req.address)?;
+		self.base.set_address(&req.address);
+		self.session_id = req.token;
+		self.restart_from_new_address();
+		self.redirecting = true;
+
+		Ok(ReceiveEvent::Redirect { addr: req.address, token: req.token })
+	}
+
+	// Resumption
+
+	/// Write this connection's state for `import()`, e.g. after it timed out
+	pub fn export(&self, schema: &Schema, writer: &mut dyn BitWrite) -> NaiaResult {
+		if !self.is_established() {
+			return Err(NaiaError::from("cannot export a connection which is not established"));
+		}
+
+		self.session_id.ser(writer);
+		self.observer.ser(writer);
+		self.base.export(schema, writer)
+	}
+
+	/// Restore a connection written by `export()`, which resumes with Migrate probes
+	/// from the current socket
+	pub fn import(
+		config: &ConnectionConfig,
+		handshake_resend_interval: Duration,
+		schema: &Schema,
+		reader: &mut BitReader,
+	) -> NaiaResult<Self> {
+		let session_id = SessionId::de(reader)?;
+		let observer = bool::de(reader)?;
+		let base = BaseConnection::import(HostType::Client, config, schema, reader)?;
+
+		let mut conn = Self {
+			cipher: base.cipher().unwrap_or_else(CipherKind::preferred),
+			base,
+			state: ConnectionState::Resuming,
+			handshake_timer: Timer::new_ringing(handshake_resend_interval),
+			connect_message: None,
+			identity: None,
+			session_id,
+			migrating: false,
+			migrate_salt: Salt::default(),
+			redirecting: false,
+			observer,
+			compact_headers: config.compact_headers,
+			handshake_padding: config.handshake_padding,
+			cookie: None,
+			handshake_step: None,
+			handshake_step_new: false,
+		};
+		conn.resume(&Instant::now());
+		Ok(conn)
+	}
+
     // Incoming data
 
 	pub fn receive_packet(
-		&mut self, reader: &mut BitReader, io: &mut Io, schema: &Schema,
+		&mut self, now: &Instant, reader: &mut BitReader, io: &mut Io, schema: &Schema,
 	) -> NaiaResult<ReceiveEvent> {
-		if self.is_connected() {
+		self.base.set_now(now);
+		if self.is_established() {
 			self.receive_packet_connected(reader, io, schema)
 		} else {
 			self.receive_packet_handshake(reader)
@@ -224,38 +434,59 @@ impl Connection {
 	fn receive_packet_connected(
 		&mut self, reader: &mut BitReader, io: &mut Io, schema: &Schema,
 	) -> NaiaResult<ReceiveEvent> {
-		self.base.mark_heard();
-
 		let header = self.base.maybe_decrypt(reader)?;
-		match header.packet_type {
-			PacketType::Data => self.base.read_data_packet(schema, header.packet_seq, reader)?,
-			PacketType::Disconnect => return Ok(ReceiveEvent::Disconnect),
-			PacketType::Heartbeat => (),
-			PacketType::Ping => self.base.ping_pong(reader, io)?,
-			PacketType::Pong => self.base.read_pong(reader)?,
-			t => trace!("Dropping spurious {t:?} packet"),
+		// only authentic packets keep the connection alive
+		self.base.mark_heard();
+		let mut event = ReceiveEvent::None;
+		if self.migrating && header.packet_type.is_encrypted() {
+			// the Server has heard from the new address
+			self.migrating = false;
+			if matches!(self.state, ConnectionState::Resuming) {
+				self.set_state(ConnectionState::Connected);
+				event = ReceiveEvent::Connected;
+			} else if mem::take(&mut self.redirecting) {
+				event = ReceiveEvent::Redirected;
+			}
 		}
 
-		Ok(ReceiveEvent::None)
+		let result = match header.packet_type {
+			PacketType::Data => self.base.read_data_packet(schema, header.packet_seq, reader).map(|_| event),
+			PacketType::Disconnect => Ok(ReceiveEvent::Disconnect),
+			PacketType::Redirect => self.recv_redirect(reader, io),
+			PacketType::Heartbeat => self.base.read_heartbeat(header.packet_seq, reader).map(|_| event),
+			PacketType::Ping => self.base.ping_pong(reader, io).map(|_| event),
+			PacketType::Pong => self.base.read_pong(reader).map(|_| event),
+			t => {
+				self.base.drop_spurious(t);
+				return Ok(event);
+			}
+		};
+		self.base.inspect_rx(header.packet_type, &result, reader);
+		result
 	}
 
 	pub fn receive_messages(&mut self) -> impl Iterator<Item = MessageContainer> + '_ {
 		self.base.receive_messages()
 	}
 
+	pub fn hold_channel(&mut self, channel: &ChannelKind) { self.base.hold_channel(channel) }
+	pub fn release_channel(&mut self, channel: &ChannelKind) { self.base.release_channel(channel) }
+	pub fn is_channel_held(&self, channel: &ChannelKind) -> bool { self.base.is_channel_held(channel) }
+
     // Outgoing data
 
 	pub fn queue_message(
 		&mut self, schema: &Schema, channel: &ChannelKind, msg: MessageContainer,
-	) {
-		self.base.queue_message(schema.message_kinds(), channel, msg);
+	) -> NaiaResult {
+		self.base.queue_message(schema.message_kinds(), channel, msg)
 	}
 
 	pub fn send(
 		&mut self, now: &Instant, schema: &Schema, io: &mut Io
 	) -> NaiaResult {
+		self.base.set_now(now);
 		match self.state {
-			ConnectionState::Connected => self.send_connected(now, schema, io),
+			ConnectionState::Connected | ConnectionState::Resuming => self.send_connected(now, schema, io),
 			ConnectionState::Disconnected => Ok(()),
 			_ => self.send_handshake(schema, io),
 		}
@@ -264,22 +495,65 @@ impl Connection {
 	fn send_connected(
 		&mut self, now: &Instant, schema: &Schema, io: &mut Io
 	) -> NaiaResult {
-		debug_assert!(matches!(self.state, ConnectionState::Connected));
+		debug_assert!(self.is_established());
+		if self.migrating {
+			// hold other packets until the Server knows the new address
+			return self.send_migrate(io);
+		}
+
 		self.base.send_data_packets(schema, now, io)?;
 		self.base.try_send_ping(io)?;
 		self.base.try_send_heartbeat(io)
 	}
 
-	pub fn timed_out(&self) -> bool { self.base.timed_out() }
+	/// Send the handshake, Migrate, ping and heartbeat packets which are due, but no
+	/// messages
+	pub fn send_maintenance(&mut self, now: &Instant, schema: &Schema, io: &mut Io) -> NaiaResult {
+		self.base.set_now(now);
+		match self.state {
+			ConnectionState::Connected | ConnectionState::Resuming if self.migrating => self.send_migrate(io),
+			ConnectionState::Connected | ConnectionState::Resuming => {
+				self.base.try_send_ping(io)?;
+				self.base.try_send_heartbeat(io)
+			}
+			ConnectionState::Disconnected => Ok(()),
+			_ => self.send_handshake(schema, io),
+		}
+	}
+
+	/// When the next packet sent by `send_maintenance()` is due
+	pub fn next_maintenance(&self) -> Option<Instant> {
+		match self.state {
+			ConnectionState::Connected | ConnectionState::Resuming if !self.migrating =>
+				Some(self.base.next_maintenance()),
+			ConnectionState::Disconnected => None,
+			_ => Some(self.handshake_timer.deadline()),
+		}
+	}
+
+	pub fn timed_out(&self, now: &Instant) -> bool { self.base.timed_out(now) }
 
 	pub fn rtt_ms(&self) -> f32 { self.base.rtt_ms() }
 	pub fn jitter_ms(&self) -> f32 { self.base.jitter_ms() }
+	pub fn packet_loss(&self) -> f32 { self.base.packet_loss() }
+	pub fn link_quality(&self) -> LinkQuality { self.base.link_quality() }
+	pub fn queue_depth(&self) -> QueueDepth { self.base.queue_depth() }
+	pub fn stats(&self) -> ConnectionStats { self.base.stats() }
+	pub fn handshake_timing(&self) -> HandshakeTiming { self.base.handshake_timing() }
+	pub fn cipher(&self) -> Option<CipherKind> { self.base.cipher() }
+	pub fn take_link_change(&mut self) -> Option<LinkQuality> { self.base.take_link_change() }
+	pub fn take_decrypt_alarm(&mut self) -> bool { self.base.take_decrypt_alarm() }
 
 	// performance counters
 
 	pub fn msg_rx_count(&self) -> u64 { self.base.msg_rx_count() }
 	pub fn msg_rx_drop_count(&self) -> u64 { self.base.msg_rx_drop_count() }
 	pub fn msg_rx_miss_count(&self) -> u64 { self.base.msg_rx_miss_count() }
+	pub fn frag_rx_expired_count(&self) -> u64 { self.base.frag_rx_expired_count() }
+	pub fn frag_rx_refused_count(&self) -> u64 { self.base.frag_rx_refused_count() }
+	pub fn pkt_rx_decrypt_fail_count(&self) -> u64 { self.base.pkt_rx_decrypt_fail_count() }
 	pub fn msg_tx_count(&self) -> u64 { self.base.msg_tx_count() }
 	pub fn msg_tx_queue_count(&self) -> u64 { self.base.msg_tx_queue_count() }
+	pub fn msg_rx_per_sec(&self, channel_kind: &ChannelKind) -> f32 { self.base.msg_rx_per_sec(channel_kind) }
+	pub fn msg_tx_per_sec(&self, channel_kind: &ChannelKind) -> f32 { self.base.msg_tx_per_sec(channel_kind) }
 }
@@ -1,10 +1,45 @@
-use naia_shared::{MessageContainer, NaiaError, RejectReason};
+use naia_shared::{MessageContainer, NaiaError, RejectReason, SessionId};
 use std::net::SocketAddr;
 
+/// The steps of connecting to a Server, see `ClientEvent::HandshakeProgress`
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum HandshakeStep {
+	/// The first handshake packet was sent; no answer yet
+	EncryptSent,
+	/// The Server answered, and encryption keys were agreed on
+	KeysEstablished,
+	/// The connect request, with the connect message, was sent; `Connect` follows once
+	/// the Server accepts it
+	ConnectSent,
+}
+
 pub enum ClientEvent {
 	Connect(SocketAddr),
+	/// Packets from the Server failed decryption more often than allowed by
+	/// `ConnectionConfig::decrypt_failures`. A `Disconnect` follows if configured.
+	DecryptFailures,
 	Disconnect(SocketAddr),
 	Error(NaiaError),
+	/// The handshake reached the given step, e.g. to show progress while connecting.
+	/// Each step is reported once per connection attempt, in order.
+	HandshakeProgress(HandshakeStep),
+	/// The link to the Server crossed the thresholds of
+	/// `ConnectionConfig::congestion`. `loss` is the fraction of packets recently lost.
+	LinkDegraded { loss: f32, rtt_ms: f32 },
+	/// The link to the Server stayed within thresholds for the congestion controller's
+	/// recovery time, after `LinkDegraded`
+	LinkRecovered,
 	Message(MessageContainer),
+	/// The Server handed the connection off to the Server at `addr`. The Client
+	/// reconnects automatically, presenting `token`; `Redirected` follows once the new
+	/// Server responds, or `Disconnect` if it never does.
+	Redirect { addr: SocketAddr, token: SessionId },
+	/// The Server at the given address took over the connection after a `Redirect`
+	Redirected(SocketAddr),
 	Reject(SocketAddr, RejectReason),
+	/// Part of the Server's snapshot was received, see `Server::set_snapshot_provider()`.
+	/// Holds the fraction received so far, between 0 and 1.
+	SyncProgress(f32),
+	/// The whole snapshot was received, and the Server was notified
+	Synced(Box<[u8]>),
 }
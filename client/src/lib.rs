@@ -12,8 +12,10 @@ mod client;
 mod client_config;
 mod connection;
 mod events;
+mod quality;
 
 pub use client::Client;
 pub use client_config::ClientConfig;
 pub use events::*;
-pub use naia_shared::RejectReason;
+pub use quality::{Quality, QualityRating};
+pub use naia_shared::{ConnectionMiddleware, LinkQuality, QueueDepth, RejectReason, Stamped};
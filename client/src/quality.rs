@@ -0,0 +1,44 @@
+/// A snapshot of the connection to the Server, for display, e.g. as connection bars;
+/// see `Client::connection_quality()`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quality {
+	pub rtt_ms: f32,
+	pub jitter_ms: f32,
+	/// Packets recently lost in transit, in percent
+	pub loss_pct: f32,
+	pub rating: QualityRating,
+}
+
+/// A coarse rating of a connection, from the worst of its round trip time, jitter
+/// and packet loss
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum QualityRating {
+	Poor,
+	Fair,
+	Good,
+	Excellent,
+}
+
+/// The maximum (rtt_ms, jitter_ms, loss_pct) of each rating, best first
+const THRESHOLDS: [(QualityRating, f32, f32, f32); 3] = [
+	(QualityRating::Excellent, 50.0, 10.0, 1.0),
+	(QualityRating::Good, 100.0, 25.0, 3.0),
+	(QualityRating::Fair, 200.0, 50.0, 8.0),
+];
+
+impl QualityRating {
+	pub fn rate(rtt_ms: f32, jitter_ms: f32, loss_pct: f32) -> Self {
+		THRESHOLDS.iter()
+			.find(|(_, rtt, jitter, loss)| rtt_ms <= *rtt && jitter_ms <= *jitter && loss_pct <= *loss)
+			.map_or(Self::Poor, |(rating, ..)| *rating)
+	}
+
+	/// The number of connection bars to show, from 1 to 4
+	pub fn bars(&self) -> u8 { *self as u8 + 1 }
+}
+
+impl Quality {
+	pub fn new(rtt_ms: f32, jitter_ms: f32, loss_pct: f32) -> Self {
+		Self { rtt_ms, jitter_ms, loss_pct, rating: QualityRating::rate(rtt_ms, jitter_ms, loss_pct) }
+	}
+}
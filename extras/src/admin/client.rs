@@ -0,0 +1,42 @@
+use naia_client::{Client, ClientEvent};
+use super::*;
+
+/// Sends admin commands, and collects their responses
+pub struct AdminClient {
+	next_id: AdminRequestId,
+	responses: Vec<AdminResponse>,
+}
+
+impl AdminClient {
+	pub fn new() -> Self {
+		Self { next_id: 0, responses: Vec::new() }
+	}
+
+	/// Run `command` with `args` on the Server. Returns the id of the matching
+	/// `AdminResponse`.
+	pub fn run(&mut self, client: &mut Client, command: &str, args: &[&str]) -> AdminRequestId {
+		let id = self.next_id;
+		self.next_id = self.next_id.wrapping_add(1);
+
+		let args = args.iter().map(|arg| arg.to_string()).collect();
+		client.send_message::<AdminChannel, _>(&AdminRequest { id, command: command.to_string(), args });
+		id
+	}
+
+	/// Collect the admin responses among `events`; see `take_responses()`. Returns all
+	/// other events.
+	pub fn process(&mut self, events: Vec<ClientEvent>) -> Vec<ClientEvent> {
+		let mut remaining = Vec::with_capacity(events.len());
+		for event in events {
+			match event {
+				ClientEvent::Message(msg) if msg.is::<AdminResponse>() =>
+					self.responses.push(msg.downcast::<AdminResponse>().unwrap()),
+				event => remaining.push(event),
+			}
+		}
+		remaining
+	}
+
+	/// Take the responses collected by `process()` since the last call
+	pub fn take_responses(&mut self) -> Vec<AdminResponse> { std::mem::take(&mut self.responses) }
+}
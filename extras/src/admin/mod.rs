@@ -0,0 +1,58 @@
+//! Remote administration of headless Servers. Privileged Clients (including
+//! observers) send commands with `AdminClient`; `AdminServer` runs the commands each
+//! Client's roles allow, e.g. the built-in `kick`, `ban`, `unban`, and `metrics`. Add
+//! `AdminPlugin` to the Schema.
+//!
+//! Roles are granted by the Server application, e.g. after checking the connect
+//! message; the connection itself is already authenticated and encrypted.
+
+use naia_shared::{
+	Channel, ChannelDirection, ChannelMode, Message, ProtocolPlugin, SchemaBuilder,
+};
+
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "server")]
+mod server;
+
+#[cfg(feature = "client")]
+pub use client::AdminClient;
+#[cfg(feature = "server")]
+pub use server::{ADMIN_ROLE, AdminCommand, AdminServer};
+
+/// Correlates an `AdminResponse` with its `AdminRequest`
+pub type AdminRequestId = u32;
+
+#[derive(Channel)]
+pub struct AdminChannel;
+
+/// Run `command` with `args` on the Server
+#[derive(Message)]
+pub struct AdminRequest {
+	pub id: AdminRequestId,
+	pub command: String,
+	pub args: Vec<String>,
+}
+
+/// The result of the `AdminRequest` with the same `id`. On failure (including
+/// unknown or forbidden commands), `output` describes the error.
+#[derive(Message)]
+pub struct AdminResponse {
+	pub id: AdminRequestId,
+	pub ok: bool,
+	pub output: String,
+}
+
+/// Registers `AdminChannel` and the admin messages
+pub struct AdminPlugin;
+
+impl ProtocolPlugin for AdminPlugin {
+	fn build(&self, builder: SchemaBuilder) -> SchemaBuilder {
+		builder
+			.add_observer_channel::<AdminChannel>(
+				ChannelDirection::Bidirectional, ChannelMode::OrderedReliable,
+			)
+			.add_message::<AdminRequest>()
+			.add_message::<AdminResponse>()
+	}
+}
@@ -0,0 +1,241 @@
+use naia_server::{RejectReason, Server, ServerEvent, UserKey};
+use naia_shared::MessageContainer;
+use std::{collections::{HashMap, HashSet}, fmt::Write, net::IpAddr};
+use super::*;
+
+/// The role allowed to run the built-in commands, unless changed with
+/// `AdminServer::set_acl()`
+pub const ADMIN_ROLE: &str = "admin";
+
+/// A command run on behalf of a privileged Client
+pub trait AdminCommand: Send + Sync {
+	/// Returns the output to send back to `user_key`, or a description of the error
+	fn run(&mut self, server: &mut Server, user_key: &UserKey, args: &[String]) -> Result<String, String>;
+}
+
+impl<F> AdminCommand for F
+where
+	F: FnMut(&mut Server, &UserKey, &[String]) -> Result<String, String> + Send + Sync,
+{
+	fn run(&mut self, server: &mut Server, user_key: &UserKey, args: &[String]) -> Result<String, String> {
+		self(server, user_key, args)
+	}
+}
+
+struct Command {
+	/// Roles allowed to run the command
+	roles: HashSet<String>,
+	/// `None` for built-in commands
+	handler: Option<Box<dyn AdminCommand>>,
+}
+
+/// Runs admin commands for Clients whose roles allow them. Built-in commands:
+///  * `kick <user id>`: disconnect a user
+///  * `ban <user id | ip>`: disconnect all users at an IP address, and reject their
+///    future connections
+///  * `unban <ip>`
+///  * `metrics`: dump the Server's performance counters
+///  * `help`: list the commands the caller may run
+pub struct AdminServer {
+	commands: HashMap<String, Command>,
+	roles: HashMap<UserKey, HashSet<String>>,
+	bans: HashSet<IpAddr>,
+	denied_count: u64,
+}
+
+impl AdminServer {
+	pub fn new() -> Self {
+		let mut admin = Self {
+			commands: HashMap::new(),
+			roles: HashMap::new(),
+			bans: HashSet::new(),
+			denied_count: 0,
+		};
+		for name in ["kick", "ban", "unban", "metrics", "help"] {
+			admin.commands.insert(name.to_string(), Command { roles: HashSet::new(), handler: None });
+			admin.set_acl(name, &[ADMIN_ROLE]);
+		}
+		admin
+	}
+
+	/// Add (or replace) a command, which Clients with any of `roles` may run
+	pub fn add_command(&mut self, name: &str, roles: &[&str], handler: Box<dyn AdminCommand>) {
+		let roles = roles.iter().map(|role| role.to_string()).collect();
+		self.commands.insert(name.to_string(), Command { roles, handler: Some(handler) });
+	}
+
+	/// Set the roles allowed to run `name`, including built-in commands. Unknown
+	/// commands are ignored.
+	pub fn set_acl(&mut self, name: &str, roles: &[&str]) {
+		if let Some(command) = self.commands.get_mut(name) {
+			command.roles = roles.iter().map(|role| role.to_string()).collect();
+		}
+	}
+
+	/// Grant `role` to `user_key` until it disconnects
+	pub fn grant(&mut self, user_key: &UserKey, role: &str) {
+		self.roles.entry(*user_key).or_default().insert(role.to_string());
+	}
+
+	pub fn revoke(&mut self, user_key: &UserKey, role: &str) {
+		if let Some(roles) = self.roles.get_mut(user_key) {
+			roles.remove(role);
+		}
+	}
+
+	/// Returns whether `user_key` may run `name`
+	pub fn is_allowed(&self, user_key: &UserKey, name: &str) -> bool {
+		let (Some(roles), Some(command)) = (self.roles.get(user_key), self.commands.get(name)) else {
+			return false;
+		};
+		!roles.is_disjoint(&command.roles)
+	}
+
+	/// Disconnect all users at `ip`, and reject their future connections
+	pub fn ban(&mut self, server: &mut Server, ip: IpAddr) -> usize {
+		self.bans.insert(ip);
+
+		let banned: Vec<_> = server.users()
+			.filter(|user| user.address().ip() == ip)
+			.map(|user| user.key())
+			.collect();
+		for user_key in &banned {
+			server.user_disconnect(user_key);
+		}
+		banned.len()
+	}
+
+	/// Returns whether `ip` was banned
+	pub fn unban(&mut self, ip: &IpAddr) -> bool { self.bans.remove(ip) }
+
+	pub fn is_banned(&self, ip: &IpAddr) -> bool { self.bans.contains(ip) }
+
+	/// Run the admin requests among `events`, reject connections (and resumed
+	/// sessions) from banned addresses, and revoke the roles of disconnected users.
+	/// Returns all other events, including disconnects.
+	pub fn process(&mut self, server: &mut Server, events: Vec<ServerEvent>) -> Vec<ServerEvent> {
+		let mut remaining = Vec::with_capacity(events.len());
+		for event in events {
+			match event {
+				ServerEvent::Connect { user_key, addr, .. } if self.is_banned(&addr.ip()) => {
+					// can only fail if the app already handled the event
+					let _ = server.reject_connection(&user_key, RejectReason::AuthFailed);
+				}
+				ServerEvent::Resume { user_key, addr } if self.is_banned(&addr.ip()) =>
+					server.user_disconnect(&user_key),
+				ServerEvent::Message { user_key, msg } if msg.is::<AdminRequest>() =>
+					self.receive(server, &user_key, msg),
+				ServerEvent::Disconnect { user_key, addr } => {
+					self.roles.remove(&user_key);
+					remaining.push(ServerEvent::Disconnect { user_key, addr });
+				}
+				event => remaining.push(event),
+			}
+		}
+		remaining
+	}
+
+	fn receive(&mut self, server: &mut Server, user_key: &UserKey, msg: MessageContainer) {
+		let AdminRequest { id, command, args } = msg.downcast::<AdminRequest>().unwrap();
+		let result = self.run(server, user_key, &command, &args);
+
+		let (ok, output) = match result {
+			Ok(output) => (true, output),
+			Err(output) => (false, output),
+		};
+		// dropped if the command disconnected the caller
+		server.send_message::<AdminChannel, _>(user_key, &AdminResponse { id, ok, output });
+	}
+
+	fn run(
+		&mut self, server: &mut Server, user_key: &UserKey, name: &str, args: &[String],
+	) -> Result<String, String> {
+		if !self.commands.contains_key(name) {
+			return Err(format!("unknown command '{name}'"));
+		}
+		if !self.is_allowed(user_key, name) {
+			self.denied_count = self.denied_count.wrapping_add(1);
+			return Err(format!("permission denied for '{name}'"));
+		}
+
+		if let Some(handler) = &mut self.commands.get_mut(name).unwrap().handler {
+			return handler.run(server, user_key, args);
+		}
+
+		match name {
+			"kick" => {
+				let target = user_arg(args)?;
+				if !server.user_exists(&target) {
+					return Err(format!("unknown user {}", target));
+				}
+				server.user_disconnect(&target);
+				Ok(format!("kicked user {}", target))
+			}
+			"ban" => {
+				let ip = match args.first().and_then(|arg| arg.parse::<IpAddr>().ok()) {
+					Some(ip) => ip,
+					None => {
+						let target = user_arg(args)?;
+						let Some(addr) = server.user_address(&target) else {
+							return Err(format!("unknown user {}", target));
+						};
+						addr.ip()
+					}
+				};
+				let count = self.ban(server, ip);
+				Ok(format!("banned {ip}, disconnecting {count} users"))
+			}
+			"unban" => {
+				let Some(ip) = args.first().and_then(|arg| arg.parse::<IpAddr>().ok()) else {
+					return Err("usage: unban <ip>".to_string());
+				};
+				match self.unban(&ip) {
+					true => Ok(format!("unbanned {ip}")),
+					false => Err(format!("{ip} is not banned")),
+				}
+			}
+			"metrics" => Ok(metrics(server)),
+			"help" => {
+				let mut names: Vec<_> = self.commands.keys()
+					.filter(|name| self.is_allowed(user_key, name))
+					.map(String::as_str)
+					.collect();
+				names.sort_unstable();
+				Ok(names.join("\n"))
+			}
+			_ => unreachable!(),
+		}
+	}
+
+	// performance counters
+
+	/// The number of commands refused because the caller lacked the required role
+	pub fn denied_count(&self) -> u64 { self.denied_count }
+}
+
+/// Parse the first argument as a user id, in the `index.generation` form of `UserKey`
+fn user_arg(args: &[String]) -> Result<UserKey, String> {
+	args.first()
+		.and_then(|arg| arg.parse::<UserKey>().ok())
+		.ok_or_else(|| "expected a user id".to_string())
+}
+
+fn metrics(server: &Server) -> String {
+	let mut counters = vec![("users", server.users_count() as u64)];
+	counters.extend(server.metrics_snapshot().counters());
+	let rates = [
+		("bytes_rx_per_sec", server.bytes_rx_per_sec()),
+		("bytes_tx_per_sec", server.bytes_tx_per_sec()),
+		("pkt_rx_per_sec", server.pkt_rx_per_sec()),
+		("pkt_tx_per_sec", server.pkt_tx_per_sec()),
+	];
+
+	let mut output = String::new();
+	for (name, value) in counters {
+		let _ = writeln!(output, "{name}: {value}");
+	}
+	for (name, value) in rates {
+		let _ = writeln!(output, "{name}: {value:.1}");
+	}
+	output
+}
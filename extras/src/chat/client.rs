@@ -0,0 +1,89 @@
+use naia_client::{Client, ClientEvent};
+use naia_shared::MessageContainer;
+use std::collections::HashMap;
+use super::*;
+
+pub enum ChatEvent {
+	/// A member joined the chat, or changed name. Includes this Client after it joins.
+	Join { user_id: ChatUserId, name: String },
+	Leave { user_id: ChatUserId, name: String },
+	Text { user_id: ChatUserId, text: String },
+	Whisper { user_id: ChatUserId, text: String },
+}
+
+/// Sends chat messages, and tracks the members of the chat
+pub struct ChatClient {
+	members: HashMap<ChatUserId, String>,
+	events: Vec<ChatEvent>,
+}
+
+impl ChatClient {
+	pub fn new() -> Self {
+		Self { members: HashMap::new(), events: Vec::new() }
+	}
+
+	/// Join the chat with the given display name, or change name if already joined
+	pub fn join(&mut self, client: &mut Client, name: &str) {
+		client.send_message::<ChatChannel, _>(&ChatJoin { user_id: 0, name: name.to_string() });
+	}
+
+	/// Leave the chat
+	pub fn leave(&mut self, client: &mut Client) {
+		client.send_message::<ChatChannel, _>(&ChatLeave { user_id: 0 });
+		self.members.clear();
+	}
+
+	/// Say `text` to all members
+	pub fn say(&mut self, client: &mut Client, text: &str) {
+		client.send_message::<ChatChannel, _>(&ChatText { user_id: 0, text: text.to_string() });
+	}
+
+	/// Say `text` to the given member only
+	pub fn whisper(&mut self, client: &mut Client, user_id: ChatUserId, text: &str) {
+		client.send_message::<ChatChannel, _>(&ChatWhisper { user_id, text: text.to_string() });
+	}
+
+	/// Display names of all members, by user id
+	pub fn members(&self) -> &HashMap<ChatUserId, String> { &self.members }
+
+	/// Handle the chat messages among `events`; see `take_events()`. Returns all other
+	/// events.
+	pub fn process(&mut self, events: Vec<ClientEvent>) -> Vec<ClientEvent> {
+		let mut remaining = Vec::with_capacity(events.len());
+		for event in events {
+			match event {
+				ClientEvent::Message(msg) if is_chat(&msg) => self.receive(msg),
+				ClientEvent::Disconnect(addr) => {
+					self.members.clear();
+					remaining.push(ClientEvent::Disconnect(addr));
+				}
+				event => remaining.push(event),
+			}
+		}
+		remaining
+	}
+
+	/// Take the chat events handled by `process()` since the last call
+	pub fn take_events(&mut self) -> Vec<ChatEvent> { std::mem::take(&mut self.events) }
+
+	fn receive(&mut self, msg: MessageContainer) {
+		let event = if msg.is::<ChatJoin>() {
+			let ChatJoin { user_id, name } = msg.downcast::<ChatJoin>().unwrap();
+			self.members.insert(user_id, name.clone());
+			ChatEvent::Join { user_id, name }
+		} else if msg.is::<ChatLeave>() {
+			let ChatLeave { user_id } = msg.downcast::<ChatLeave>().unwrap();
+			let Some(name) = self.members.remove(&user_id) else {
+				return;
+			};
+			ChatEvent::Leave { user_id, name }
+		} else if msg.is::<ChatText>() {
+			let ChatText { user_id, text } = msg.downcast::<ChatText>().unwrap();
+			ChatEvent::Text { user_id, text }
+		} else {
+			let ChatWhisper { user_id, text } = msg.downcast::<ChatWhisper>().unwrap();
+			ChatEvent::Whisper { user_id, text }
+		};
+		self.events.push(event);
+	}
+}
@@ -0,0 +1,75 @@
+//! Chat between Clients, with display names and whispers. Add `ChatPlugin` to the
+//! Schema, then use `ChatServer` and `ChatClient` to handle chat messages. Observers
+//! may chat too.
+
+use naia_shared::{
+	Channel, ChannelDirection, ChannelMode, Message, MessageContainer, ProtocolPlugin,
+	SchemaBuilder,
+};
+
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "server")]
+mod server;
+
+#[cfg(feature = "client")]
+pub use client::{ChatClient, ChatEvent};
+#[cfg(feature = "server")]
+pub use server::{ChatConfig, ChatFilter, ChatServer};
+
+/// Identifies chat members to each other; the `UserKey::to_bits()` of the member
+pub type ChatUserId = u32;
+
+#[derive(Channel)]
+pub struct ChatChannel;
+
+/// To the Server: join the chat (or change name) with the given display name; `user_id`
+/// is ignored. To Clients: `user_id` joined the chat or changed name.
+#[derive(Message)]
+pub struct ChatJoin {
+	pub user_id: ChatUserId,
+	pub name: String,
+}
+
+/// To the Server: leave the chat; `user_id` is ignored. To Clients: `user_id` left.
+#[derive(Message)]
+pub struct ChatLeave {
+	pub user_id: ChatUserId,
+}
+
+/// To the Server: say `text` to all members; `user_id` is ignored. To Clients:
+/// `user_id` said `text`.
+#[derive(Message)]
+pub struct ChatText {
+	pub user_id: ChatUserId,
+	pub text: String,
+}
+
+/// To the Server: say `text` to member `user_id` only. To Clients: `user_id` whispered
+/// `text`.
+#[derive(Message)]
+pub struct ChatWhisper {
+	pub user_id: ChatUserId,
+	pub text: String,
+}
+
+/// Registers `ChatChannel` and the chat messages
+pub struct ChatPlugin;
+
+impl ProtocolPlugin for ChatPlugin {
+	fn build(&self, builder: SchemaBuilder) -> SchemaBuilder {
+		builder
+			.add_observer_channel::<ChatChannel>(
+				ChannelDirection::Bidirectional, ChannelMode::OrderedReliable,
+			)
+			.add_message::<ChatJoin>()
+			.add_message::<ChatLeave>()
+			.add_message::<ChatText>()
+			.add_message::<ChatWhisper>()
+	}
+}
+
+#[cfg_attr(not(any(feature = "client", feature = "server")), allow(dead_code))]
+fn is_chat(msg: &MessageContainer) -> bool {
+	msg.is::<ChatJoin>() || msg.is::<ChatLeave>() || msg.is::<ChatText>() || msg.is::<ChatWhisper>()
+}
@@ -0,0 +1,203 @@
+use naia_server::{Server, ServerEvent, UserKey};
+use naia_shared::{Instant, MessageContainer};
+use std::collections::HashMap;
+use super::*;
+
+#[derive(Clone, Debug)]
+pub struct ChatConfig {
+	/// Sustained number of chat messages (joins, renames and leaves included) each
+	/// user may send per second
+	pub rate: f32,
+	/// Number of chat messages each user may send in a burst
+	pub burst: u32,
+	/// Maximum display name length in bytes; longer names are rejected
+	pub max_name_len: usize,
+	/// Maximum text length in bytes; longer texts are dropped
+	pub max_text_len: usize,
+}
+
+impl Default for ChatConfig {
+	fn default() -> Self {
+		Self {
+			rate: 1.0,
+			burst: 5,
+			max_name_len: 32,
+			max_text_len: 256,
+		}
+	}
+}
+
+/// Inspects (or rewrites) texts and whispers before they are delivered, e.g. to
+/// filter profanity
+pub trait ChatFilter: Send + Sync {
+	/// Returns whether to deliver `text`, which may be modified in place
+	fn filter(&mut self, user_key: &UserKey, text: &mut String) -> bool;
+}
+
+/// Rate limiting token bucket, kept from a user's first chat message until they
+/// disconnect, so leaving and joining again doesn't refill it
+struct Bucket {
+	tokens: f32,
+	last_refill: Instant,
+}
+
+impl Bucket {
+	fn new(config: &ChatConfig, now: Instant) -> Self {
+		Self { tokens: config.burst as f32, last_refill: now }
+	}
+
+	fn try_spend(&mut self, config: &ChatConfig, now: Instant) -> bool {
+		let elapsed = now.duration_since(self.last_refill).as_secs_f32();
+		self.tokens = f32::min(self.tokens + elapsed * config.rate, config.burst as f32);
+		self.last_refill = now;
+
+		if self.tokens < 1.0 {
+			return false;
+		}
+		self.tokens -= 1.0;
+		true
+	}
+}
+
+/// Relays chat messages between members, enforcing rate and length limits
+pub struct ChatServer {
+	config: ChatConfig,
+	/// Display names of members
+	members: HashMap<UserKey, String>,
+	buckets: HashMap<UserKey, Bucket>,
+	filter: Option<Box<dyn ChatFilter>>,
+	drop_count: u64,
+}
+
+impl ChatServer {
+	pub fn new(config: ChatConfig) -> Self {
+		Self {
+			config,
+			members: HashMap::new(),
+			buckets: HashMap::new(),
+			filter: None,
+			drop_count: 0,
+		}
+	}
+
+	/// Set the filter applied to all texts and whispers
+	pub fn set_filter(&mut self, filter: Box<dyn ChatFilter>) { self.filter = Some(filter) }
+
+	/// Iterate over all members and their display names
+	pub fn members(&self) -> impl Iterator<Item = (&UserKey, &str)> {
+		self.members.iter().map(|(user_key, name)| (user_key, name.as_str()))
+	}
+
+	/// Handle the chat messages among `events`, and remove disconnected users from the
+	/// chat. Returns all other events, including disconnects.
+	pub fn process(&mut self, server: &mut Server, events: Vec<ServerEvent>) -> Vec<ServerEvent> {
+		let mut remaining = Vec::with_capacity(events.len());
+		for event in events {
+			match event {
+				ServerEvent::Message { user_key, msg } if is_chat(&msg) =>
+					self.receive(server, &user_key, msg),
+				ServerEvent::Disconnect { user_key, addr } => {
+					self.leave(server, &user_key);
+					self.buckets.remove(&user_key);
+					remaining.push(ServerEvent::Disconnect { user_key, addr });
+				}
+				event => remaining.push(event),
+			}
+		}
+		remaining
+	}
+
+	fn receive(&mut self, server: &mut Server, user_key: &UserKey, msg: MessageContainer) {
+		// every chat message may be relayed to all members, so all of them are charged
+		let now = Instant::now();
+		let bucket = self.buckets.entry(*user_key).or_insert_with(|| Bucket::new(&self.config, now));
+		if !bucket.try_spend(&self.config, now) {
+			self.drop_count = self.drop_count.wrapping_add(1);
+			return;
+		}
+
+		if msg.is::<ChatJoin>() {
+			self.join(server, user_key, msg.downcast::<ChatJoin>().unwrap().name);
+		} else if msg.is::<ChatLeave>() {
+			self.leave(server, user_key);
+		} else if msg.is::<ChatText>() {
+			let ChatText { text, .. } = msg.downcast::<ChatText>().unwrap();
+			let Some(text) = self.check_text(user_key, text) else {
+				return;
+			};
+			let msg = ChatText { user_id: user_key.to_bits(), text };
+			for member in self.members.keys() {
+				server.send_message::<ChatChannel, _>(member, &msg);
+			}
+		} else if msg.is::<ChatWhisper>() {
+			let ChatWhisper { user_id, text } = msg.downcast::<ChatWhisper>().unwrap();
+			let recipient = UserKey::from_bits(user_id);
+			if !self.members.contains_key(&recipient) {
+				self.drop_count = self.drop_count.wrapping_add(1);
+				return;
+			}
+			let Some(text) = self.check_text(user_key, text) else {
+				return;
+			};
+			server.send_message::<ChatChannel, _>(&recipient, &ChatWhisper { user_id: user_key.to_bits(), text });
+		}
+	}
+
+	fn join(&mut self, server: &mut Server, user_key: &UserKey, name: String) {
+		if name.is_empty() || name.len() > self.config.max_name_len {
+			self.drop_count = self.drop_count.wrapping_add(1);
+			return;
+		}
+
+		match self.members.get_mut(user_key) {
+			// rename
+			Some(member) => member.clone_from(&name),
+			None => {
+				// introduce the existing members to the new one
+				for (member_key, member) in &self.members {
+					let msg = ChatJoin { user_id: member_key.to_bits(), name: member.clone() };
+					server.send_message::<ChatChannel, _>(user_key, &msg);
+				}
+				self.members.insert(*user_key, name.clone());
+			}
+		}
+
+		let msg = ChatJoin { user_id: user_key.to_bits(), name };
+		for member in self.members.keys() {
+			server.send_message::<ChatChannel, _>(member, &msg);
+		}
+	}
+
+	fn leave(&mut self, server: &mut Server, user_key: &UserKey) {
+		if !self.members.contains_key(user_key) {
+			return;
+		}
+
+		// messages to disconnected users are dropped
+		let msg = ChatLeave { user_id: user_key.to_bits() };
+		for member in self.members.keys() {
+			server.send_message::<ChatChannel, _>(member, &msg);
+		}
+		self.members.remove(user_key);
+	}
+
+	/// Apply membership, length, and filter checks to a text from `user_key`. Returns
+	/// the text to deliver, if any.
+	fn check_text(&mut self, user_key: &UserKey, mut text: String) -> Option<String> {
+		let allowed = self.members.contains_key(user_key)
+			&& text.len() <= self.config.max_text_len
+			&& self.filter.as_mut().is_none_or(|filter| filter.filter(user_key, &mut text));
+
+		if !allowed {
+			self.drop_count = self.drop_count.wrapping_add(1);
+			return None;
+		}
+		Some(text)
+	}
+
+	// performance counters
+
+	/// The number of chat messages dropped for exceeding limits, failing the filter,
+	/// or coming from (or addressed to) non-members
+	pub fn drop_count(&self) -> u64 { self.drop_count }
+}
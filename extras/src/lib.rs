@@ -0,0 +1,22 @@
+//! # Naia Extras
+//! Optional, batteries-included protocol plugins. Each module is gated behind a
+//! feature of the same name, and provides a `ProtocolPlugin` to add to the Schema
+//! along with Client and Server APIs (behind the `client` and `server` features).
+
+#![deny(
+    trivial_casts,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_import_braces
+)]
+
+#[cfg(feature = "admin")]
+pub mod admin;
+#[cfg(feature = "chat")]
+pub mod chat;
+#[cfg(feature = "lockstep")]
+pub mod lockstep;
+#[cfg(feature = "matchmaking")]
+pub mod matchmaking;
+#[cfg(feature = "voice")]
+pub mod voice;
@@ -0,0 +1,69 @@
+use naia_client::{Client, ClientEvent};
+use std::collections::VecDeque;
+use super::*;
+
+/// Sends inputs, and paces the simulation of received frames
+pub struct LockstepClient {
+	config: LockstepConfig,
+	frames: VecDeque<LockstepFrame>,
+	/// The tick of the next frame to simulate, once the first frame arrived
+	next_tick: Option<u32>,
+}
+
+impl LockstepClient {
+	pub fn new(config: LockstepConfig) -> Self {
+		Self { config, frames: VecDeque::new(), next_tick: None }
+	}
+
+	/// Send this player's input for `tick`, typically a few ticks after `next_tick()`
+	/// to hide the round trip to the Server
+	pub fn send_input(&mut self, client: &mut Client, tick: u32, input: &[u8]) {
+		client.send_message::<LockstepChannel, _>(&LockstepInput { tick, input: input.into() });
+	}
+
+	/// The tick of the next frame `take_frames()` returns, once known
+	pub fn next_tick(&self) -> Option<u32> { self.next_tick }
+
+	/// The number of received frames not taken yet
+	pub fn pending_frames(&self) -> usize { self.frames.len() }
+
+	/// Collect the frames among `events`. Returns all other events.
+	pub fn process(&mut self, events: Vec<ClientEvent>) -> Vec<ClientEvent> {
+		let mut remaining = Vec::with_capacity(events.len());
+		for event in events {
+			match event {
+				ClientEvent::Message(msg) if is_lockstep(&msg) => {
+					if let Ok(frame) = msg.downcast::<LockstepFrame>() {
+						self.next_tick.get_or_insert(frame.tick);
+						self.frames.push_back(frame);
+					}
+				}
+				ClientEvent::Disconnect(addr) => {
+					self.frames.clear();
+					self.next_tick = None;
+					remaining.push(ClientEvent::Disconnect(addr));
+				}
+				event => remaining.push(event),
+			}
+		}
+		remaining
+	}
+
+	/// The frames to simulate now, in order; call once per local tick. Returns none
+	/// while the Server hasn't released the next tick, i.e. the simulation must stall,
+	/// one normally, and up to `max_catch_up` while more than `buffer_frames` are
+	/// pending.
+	pub fn take_frames(&mut self) -> Vec<LockstepFrame> {
+		let pending = self.frames.len();
+		let count = match pending {
+			0 => 0,
+			_ => pending.saturating_sub(self.config.buffer_frames).clamp(1, self.config.max_catch_up.max(1)),
+		};
+
+		let frames: Vec<_> = self.frames.drain(..count).collect();
+		if let Some(frame) = frames.last() {
+			self.next_tick = Some(frame.tick.wrapping_add(1));
+		}
+		frames
+	}
+}
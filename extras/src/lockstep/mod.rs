@@ -0,0 +1,102 @@
+//! Deterministic lockstep. Clients send their input for each tick with
+//! `LockstepClient`, `LockstepServer` waits until it has the inputs of all players for
+//! a tick (or gives up on late ones, per `DropPolicy`) and broadcasts them as one
+//! `LockstepFrame`, and Clients simulate the frames in order, catching up when they
+//! fall behind. Add `LockstepPlugin` to the Schema.
+
+use naia_shared::{
+	Channel, ChannelDirection, ChannelMode, Message, MessageContainer, ProtocolPlugin,
+	SchemaBuilder,
+};
+use std::time::Duration;
+
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "server")]
+mod server;
+
+#[cfg(feature = "client")]
+pub use client::LockstepClient;
+#[cfg(feature = "server")]
+pub use server::LockstepServer;
+
+/// Identifies players in frames; the `UserKey::to_bits()` of the player
+pub type LockstepUserId = u32;
+
+/// What the Server does with players whose input for a tick didn't arrive in time.
+/// Until they have input for a tick again, later ticks don't wait for them, and they
+/// are dropped from those ticks the same way.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DropPolicy {
+	/// Release the frame without their input
+	Skip,
+	/// Release the frame with their previous input
+	RepeatLast,
+	/// Disconnect them, then release the frame without their input
+	Disconnect,
+}
+
+#[derive(Clone, Debug)]
+pub struct LockstepConfig {
+	/// How long the Server waits for the inputs of a tick before applying `drop_policy`
+	pub input_timeout: Duration,
+	pub drop_policy: DropPolicy,
+	/// Inputs for ticks more than this far ahead of the Server's next tick are dropped
+	pub max_input_ahead: u32,
+	/// Maximum input size in bytes; larger inputs are dropped
+	pub max_input_bytes: usize,
+	/// Frames Clients keep pending to absorb jitter; beyond that, they simulate more
+	/// than one frame per tick to catch up
+	pub buffer_frames: usize,
+	/// The maximum number of frames Clients simulate per tick while catching up
+	pub max_catch_up: usize,
+}
+
+impl Default for LockstepConfig {
+	fn default() -> Self {
+		Self {
+			input_timeout: Duration::from_millis(250),
+			drop_policy: DropPolicy::RepeatLast,
+			max_input_ahead: 64,
+			max_input_bytes: 256,
+			buffer_frames: 2,
+			max_catch_up: 4,
+		}
+	}
+}
+
+#[derive(Channel)]
+pub struct LockstepChannel;
+
+/// A player's input for `tick`, sent to the Server
+#[derive(Message)]
+pub struct LockstepInput {
+	pub tick: u32,
+	pub input: Box<[u8]>,
+}
+
+/// The inputs of all players for `tick`, sent to Clients, ordered by player
+#[derive(Message)]
+pub struct LockstepFrame {
+	pub tick: u32,
+	pub inputs: Vec<(LockstepUserId, Box<[u8]>)>,
+}
+
+/// Registers `LockstepChannel` and the lockstep messages
+pub struct LockstepPlugin;
+
+impl ProtocolPlugin for LockstepPlugin {
+	fn build(&self, builder: SchemaBuilder) -> SchemaBuilder {
+		builder
+			.add_channel::<LockstepChannel>(
+				ChannelDirection::Bidirectional, ChannelMode::OrderedReliable,
+			)
+			.add_message::<LockstepInput>()
+			.add_message::<LockstepFrame>()
+	}
+}
+
+#[cfg_attr(not(any(feature = "client", feature = "server")), allow(dead_code))]
+fn is_lockstep(msg: &MessageContainer) -> bool {
+	msg.is::<LockstepInput>() || msg.is::<LockstepFrame>()
+}
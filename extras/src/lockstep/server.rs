@@ -0,0 +1,174 @@
+use naia_server::{Server, ServerEvent, UserKey};
+use naia_shared::{Instant, MessageContainer};
+use std::collections::{BTreeMap, HashMap};
+use super::*;
+
+struct Player {
+	/// Whether the player sent any input yet; ticks only wait for active players
+	active: bool,
+	/// Whether the player missed a tick and didn't have input for one since; ticks
+	/// don't wait for lagging players, so going silent costs one `input_timeout` only
+	lagging: bool,
+	last_input: Option<Box<[u8]>>,
+}
+
+/// Collects the inputs of all players for each tick, and releases them as frames
+pub struct LockstepServer {
+	config: LockstepConfig,
+	players: HashMap<UserKey, Player>,
+	next_tick: u32,
+	/// Inputs received for `next_tick` and later
+	inputs: BTreeMap<u32, HashMap<UserKey, Box<[u8]>>>,
+	/// When the Server started waiting for the inputs of `next_tick`
+	waiting_since: Option<Instant>,
+	drop_count: u64,
+	late_count: u64,
+}
+
+impl LockstepServer {
+	pub fn new(config: LockstepConfig) -> Self {
+		Self {
+			config,
+			players: HashMap::new(),
+			next_tick: 0,
+			inputs: BTreeMap::new(),
+			waiting_since: None,
+			drop_count: 0,
+			late_count: 0,
+		}
+	}
+
+	/// Add `user_key` to the game; inputs from non-players are dropped. Frames are
+	/// sent to players right away, but ticks only wait for their input once they sent
+	/// any, so joining players can start from the tick of the first frame they get.
+	pub fn join(&mut self, user_key: &UserKey) {
+		self.players.entry(*user_key).or_insert(Player { active: false, lagging: false, last_input: None });
+	}
+
+	pub fn leave(&mut self, user_key: &UserKey) {
+		self.players.remove(user_key);
+		for inputs in self.inputs.values_mut() {
+			inputs.remove(user_key);
+		}
+	}
+
+	pub fn players(&self) -> impl Iterator<Item = &UserKey> { self.players.keys() }
+
+	/// The tick of the next frame to be released
+	pub fn next_tick(&self) -> u32 { self.next_tick }
+
+	/// Collect the inputs among `events`, and remove disconnected users from the game.
+	/// Returns all other events, including disconnects.
+	pub fn process(&mut self, events: Vec<ServerEvent>) -> Vec<ServerEvent> {
+		let mut remaining = Vec::with_capacity(events.len());
+		for event in events {
+			match event {
+				ServerEvent::Message { user_key, msg } if is_lockstep(&msg) => self.receive(&user_key, msg),
+				ServerEvent::Disconnect { user_key, addr } => {
+					self.leave(&user_key);
+					remaining.push(ServerEvent::Disconnect { user_key, addr });
+				}
+				event => remaining.push(event),
+			}
+		}
+		remaining
+	}
+
+	fn receive(&mut self, user_key: &UserKey, msg: MessageContainer) {
+		let Ok(LockstepInput { tick, input }) = msg.downcast::<LockstepInput>() else {
+			self.drop_count = self.drop_count.wrapping_add(1);
+			return;
+		};
+		let Some(player) = self.players.get_mut(user_key) else {
+			self.drop_count = self.drop_count.wrapping_add(1);
+			return;
+		};
+		let in_window = tick >= self.next_tick
+			&& tick - self.next_tick <= self.config.max_input_ahead;
+		if !in_window || input.len() > self.config.max_input_bytes {
+			self.drop_count = self.drop_count.wrapping_add(1);
+			return;
+		}
+
+		player.active = true;
+		self.inputs.entry(tick).or_default().insert(*user_key, input);
+	}
+
+	/// Release the frames whose inputs are complete, or which timed out, sending them
+	/// to all players. Returns the released frames, e.g. for the Server to simulate
+	/// them as well.
+	pub fn update(&mut self, server: &mut Server) -> Vec<LockstepFrame> {
+		let now = Instant::now();
+		let mut frames = Vec::new();
+		loop {
+			let inputs = self.inputs.get(&self.next_tick);
+			let has_input = |user_key: &UserKey| inputs.is_some_and(|inputs| inputs.contains_key(user_key));
+			if !self.players.iter().any(|(user_key, player)| player.active && (!player.lagging || has_input(user_key))) {
+				break;
+			}
+			let missing: Vec<UserKey> = self.players.iter()
+				.filter(|(user_key, player)| player.active && !player.lagging && !has_input(user_key))
+				.map(|(user_key, _)| *user_key)
+				.collect();
+
+			if !missing.is_empty() {
+				let waiting_since = *self.waiting_since.get_or_insert(now);
+				if now.duration_since(waiting_since) < self.config.input_timeout {
+					break;
+				}
+				self.late_count = self.late_count.wrapping_add(missing.len() as u64);
+				for user_key in &missing {
+					if self.config.drop_policy == DropPolicy::Disconnect {
+						self.leave(user_key);
+						server.user_disconnect(user_key);
+					} else if let Some(player) = self.players.get_mut(user_key) {
+						player.lagging = true;
+					}
+				}
+			}
+
+			frames.push(self.release(server));
+		}
+		frames
+	}
+
+	fn release(&mut self, server: &mut Server) -> LockstepFrame {
+		let mut inputs = self.inputs.remove(&self.next_tick).unwrap_or_default();
+		for (user_key, player) in &mut self.players {
+			match inputs.get(user_key) {
+				Some(input) => {
+					player.last_input = Some(input.clone());
+					player.lagging = false;
+				}
+				None if player.lagging && self.config.drop_policy == DropPolicy::RepeatLast => {
+					if let Some(input) = &player.last_input {
+						inputs.insert(*user_key, input.clone());
+					}
+				}
+				None => (),
+			}
+		}
+
+		let mut inputs: Vec<_> = inputs.into_iter()
+			.map(|(user_key, input)| (user_key.to_bits(), input))
+			.collect();
+		inputs.sort_unstable_by_key(|(user_id, _)| *user_id);
+		let frame = LockstepFrame { tick: self.next_tick, inputs };
+		for player in self.players.keys() {
+			server.send_message::<LockstepChannel, _>(player, &frame);
+		}
+
+		self.next_tick = self.next_tick.wrapping_add(1);
+		self.waiting_since = None;
+		frame
+	}
+
+	// performance counters
+
+	/// The number of inputs dropped for being late, too far ahead, oversized, or
+	/// coming from non-players
+	pub fn drop_count(&self) -> u64 { self.drop_count }
+
+	/// The number of inputs the Server gave up waiting for, per `DropPolicy`
+	pub fn late_count(&self) -> u64 { self.late_count }
+}
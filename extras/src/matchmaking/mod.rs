@@ -0,0 +1,175 @@
+//! Matchmaker-issued tickets. The matchmaker signs `TicketClaims` with a key shared
+//! with the fleet of dedicated Servers, and hands the resulting `MatchTicket` to the
+//! player, whose Client presents it as its connect message. `MatchmakingServer` checks
+//! the ticket of each connecting Client, rejecting forged, expired and misdirected
+//! ones, and exposes the claims of admitted users. Add `MatchmakingPlugin` to the
+//! Schema.
+
+use chacha20poly1305::{aead::{AeadInPlace, KeyInit}, ChaCha20Poly1305, Nonce, Tag};
+use naia_shared::{Message, ProtocolPlugin, SchemaBuilder};
+use std::{error, fmt, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+#[cfg(feature = "server")]
+mod server;
+
+#[cfg(feature = "server")]
+pub use server::MatchmakingServer;
+
+/// The size of the key shared by the matchmaker and the Servers
+pub const TICKET_KEY_SIZE: usize = 32;
+
+/// What the matchmaker vouches for
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TicketClaims {
+	pub match_id: u64,
+	/// Identifies the player across connections, e.g. their account
+	pub player_id: u64,
+	pub team: u8,
+	/// Tickets are refused from this time on (with a resolution of seconds)
+	pub expires: SystemTime,
+}
+
+
+/// The connect message of matchmade Clients
+#[derive(Message)]
+pub struct MatchTicket {
+	pub match_id: u64,
+	pub player_id: u64,
+	pub team: u8,
+	/// Unix time in seconds
+	pub expires: u64,
+	pub nonce: [u8; 12],
+	/// Authenticates all other fields
+	pub tag: [u8; 16],
+}
+
+impl MatchTicket {
+	/// The bytes authenticated by the ticket's tag
+	fn signed_bytes(&self) -> [u8; 36] {
+		let mut bytes = [0; 36];
+		bytes[..3].copy_from_slice(b"mt1");
+		bytes[3..11].copy_from_slice(&self.match_id.to_le_bytes());
+		bytes[11..19].copy_from_slice(&self.player_id.to_le_bytes());
+		bytes[19] = self.team;
+		bytes[20..28].copy_from_slice(&self.expires.to_le_bytes());
+		bytes
+	}
+
+	/// Sign `claims` with the shared `key`; done by the matchmaker
+	pub fn issue(key: &[u8; TICKET_KEY_SIZE], claims: &TicketClaims) -> Self {
+		let mut ticket = Self {
+			match_id: claims.match_id,
+			player_id: claims.player_id,
+			team: claims.team,
+			expires: claims.expires.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+			nonce: rand::random(),
+			tag: [0; 16],
+		};
+		ticket.tag = ChaCha20Poly1305::new(key.into())
+			.encrypt_in_place_detached(Nonce::from_slice(&ticket.nonce), &ticket.signed_bytes(), &mut [])
+			.expect("an empty payload always fits")
+			.into();
+		ticket
+	}
+
+	/// The claims of this ticket, if it was issued with `key` and didn't expire yet
+	pub fn verify(&self, key: &[u8; TICKET_KEY_SIZE], now: SystemTime) -> Result<TicketClaims, TicketError> {
+		ChaCha20Poly1305::new(key.into())
+			.decrypt_in_place_detached(
+				Nonce::from_slice(&self.nonce), &self.signed_bytes(), &mut [], Tag::from_slice(&self.tag),
+			)
+			.map_err(|_| TicketError::Forged)?;
+
+		// Only the matchmaker's own bugs can sign expiry times out of range
+		let expires = UNIX_EPOCH.checked_add(Duration::from_secs(self.expires)).ok_or(TicketError::Forged)?;
+		let claims = TicketClaims {
+			match_id: self.match_id,
+			player_id: self.player_id,
+			team: self.team,
+			expires,
+		};
+
+		if now >= claims.expires {
+			return Err(TicketError::Expired);
+		}
+		Ok(claims)
+	}
+}
+
+/// Why a Client's ticket was refused
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TicketError {
+	/// The connect message isn't a `MatchTicket`
+	Missing,
+	/// The ticket wasn't issued with the shared key, or was tampered with
+	Forged,
+	Expired,
+	/// The ticket is for another match than the one this Server is allocated to
+	WrongMatch,
+	/// Another connected user presented a ticket for the same player
+	DuplicatePlayer,
+}
+
+impl fmt::Display for TicketError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			TicketError::Missing => write!(f, "no match ticket"),
+			TicketError::Forged => write!(f, "forged match ticket"),
+			TicketError::Expired => write!(f, "expired match ticket"),
+			TicketError::WrongMatch => write!(f, "match ticket for another match"),
+			TicketError::DuplicatePlayer => write!(f, "match ticket of a connected player"),
+		}
+	}
+}
+
+impl error::Error for TicketError {}
+
+/// Registers `MatchTicket`
+pub struct MatchmakingPlugin;
+
+impl ProtocolPlugin for MatchmakingPlugin {
+	fn build(&self, builder: SchemaBuilder) -> SchemaBuilder {
+		builder.add_message::<MatchTicket>()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn verify() {
+		let key = [7; TICKET_KEY_SIZE];
+		let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+		let claims = TicketClaims {
+			match_id: 42, player_id: 9, team: 1, expires: now + Duration::from_secs(60),
+		};
+
+		let ticket = MatchTicket::issue(&key, &claims);
+		assert_eq!(ticket.verify(&key, now), Ok(claims.clone()));
+		assert_eq!(ticket.verify(&key, claims.expires), Err(TicketError::Expired));
+		assert_eq!(ticket.verify(&[8; TICKET_KEY_SIZE], now), Err(TicketError::Forged));
+
+		let tampered = MatchTicket { team: 2, ..MatchTicket::issue(&key, &claims) };
+		assert_eq!(tampered.verify(&key, now), Err(TicketError::Forged));
+	}
+
+	#[test]
+	fn verify_unrepresentable_expiry() {
+		let key = [7; TICKET_KEY_SIZE];
+		let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+		let claims = TicketClaims {
+			match_id: 42, player_id: 9, team: 1, expires: now + Duration::from_secs(60),
+		};
+
+		let forged = MatchTicket { expires: u64::MAX, ..MatchTicket::issue(&key, &claims) };
+		assert_eq!(forged.verify(&key, now), Err(TicketError::Forged));
+
+		let mut signed = MatchTicket { expires: u64::MAX, ..MatchTicket::issue(&key, &claims) };
+		signed.tag = ChaCha20Poly1305::new((&key).into())
+			.encrypt_in_place_detached(Nonce::from_slice(&signed.nonce), &signed.signed_bytes(), &mut [])
+			.unwrap()
+			.into();
+		assert_eq!(signed.verify(&key, now), Err(TicketError::Forged));
+	}
+}
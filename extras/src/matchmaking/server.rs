@@ -0,0 +1,80 @@
+use naia_server::{RejectReason, Server, ServerEvent, UserKey};
+use naia_shared::MessageContainer;
+use std::collections::HashMap;
+use super::*;
+
+/// Admits Clients presenting a valid `MatchTicket`, and keeps their claims
+pub struct MatchmakingServer {
+	key: [u8; TICKET_KEY_SIZE],
+	/// The match this Server was allocated to, if any
+	match_id: Option<u64>,
+	claims: HashMap<UserKey, TicketClaims>,
+	reject_count: u64,
+}
+
+impl MatchmakingServer {
+	pub fn new(key: [u8; TICKET_KEY_SIZE]) -> Self {
+		Self { key, match_id: None, claims: HashMap::new(), reject_count: 0 }
+	}
+
+	/// Only admit tickets for `match_id`, once the fleet allocated this Server to it.
+	/// `None` admits tickets for any match.
+	pub fn set_match(&mut self, match_id: Option<u64>) { self.match_id = match_id }
+
+	pub fn match_id(&self) -> Option<u64> { self.match_id }
+
+	/// The claims of the ticket `user_key` connected with, available from its Connect
+	/// event on
+	pub fn claims(&self, user_key: &UserKey) -> Option<&TicketClaims> { self.claims.get(user_key) }
+
+	/// Check the tickets of the Connect events among `events`, rejecting Clients with
+	/// invalid ones, and forget the claims of disconnected users. Returns all other
+	/// events, including the Connect events of admitted Clients, which still need to
+	/// be accepted.
+	pub fn process(&mut self, server: &mut Server, events: Vec<ServerEvent>) -> Vec<ServerEvent> {
+		// e.g. rejected by the app, which emits no Disconnect event
+		self.claims.retain(|user_key, _| server.user(user_key).is_some());
+
+		let mut remaining = Vec::with_capacity(events.len());
+		for event in events {
+			match event {
+				ServerEvent::Connect { user_key, addr, msg, ctx } => match self.check(msg) {
+					Ok((claims, msg)) => {
+						self.claims.insert(user_key, claims);
+						remaining.push(ServerEvent::Connect { user_key, addr, msg: Some(msg), ctx });
+					}
+					Err(_) => {
+						self.reject_count = self.reject_count.wrapping_add(1);
+						// can only fail if the app already handled the event
+						let _ = server.reject_connection(&user_key, RejectReason::AuthFailed);
+					}
+				},
+				ServerEvent::Disconnect { user_key, addr } => {
+					self.claims.remove(&user_key);
+					remaining.push(ServerEvent::Disconnect { user_key, addr });
+				}
+				event => remaining.push(event),
+			}
+		}
+		remaining
+	}
+
+	fn check(&self, msg: Option<MessageContainer>) -> Result<(TicketClaims, MessageContainer), TicketError> {
+		let ticket = msg
+			.and_then(|msg| msg.downcast::<MatchTicket>().ok())
+			.ok_or(TicketError::Missing)?;
+		let claims = ticket.verify(&self.key, SystemTime::now())?;
+		if self.match_id.is_some_and(|match_id| match_id != claims.match_id) {
+			return Err(TicketError::WrongMatch);
+		}
+		if self.claims.values().any(|other| other.player_id == claims.player_id) {
+			return Err(TicketError::DuplicatePlayer);
+		}
+		Ok((claims, MessageContainer::from_read(Box::new(ticket))))
+	}
+
+	// performance counters
+
+	/// The number of Clients rejected for their ticket
+	pub fn reject_count(&self) -> u64 { self.reject_count }
+}
@@ -0,0 +1,141 @@
+use naia_client::{Client, ClientEvent};
+use naia_shared::{Instant, MessageContainer};
+use std::collections::{HashMap, HashSet, VecDeque};
+use super::*;
+
+/// Queues encoded frames, and sends them no faster than one per frame duration so
+/// bursts from the encoder do not flood the connection
+pub struct VoiceSender {
+	config: VoiceConfig,
+	seq: u16,
+	queue: VecDeque<Box<[u8]>>,
+	next_send: Option<Instant>,
+	drop_count: u64,
+}
+
+impl VoiceSender {
+	pub fn new(config: VoiceConfig) -> Self {
+		Self {
+			config,
+			seq: 0,
+			queue: VecDeque::new(),
+			next_send: None,
+			drop_count: 0,
+		}
+	}
+
+	/// Queue an encoded frame. Oversized frames, and the oldest frames once the queue
+	/// is full, are dropped.
+	pub fn push(&mut self, frame: Box<[u8]>) {
+		if frame.len() > self.config.max_frame_bytes {
+			self.drop_count = self.drop_count.wrapping_add(1);
+			return;
+		}
+
+		self.queue.push_back(frame);
+		while self.queue.len() > self.config.max_send_queue {
+			self.queue.pop_front();
+			self.drop_count = self.drop_count.wrapping_add(1);
+		}
+	}
+
+	/// Send the frames which are due, e.g. before every `Client::send()`
+	pub fn send(&mut self, client: &mut Client, now: Instant) {
+		let frame_duration = self.config.frame_duration;
+
+		// more than a frame behind means the sender was idle; restart pacing
+		let mut next_send = self.next_send
+			.filter(|next_send| *next_send + frame_duration > now)
+			.unwrap_or(now);
+
+		while next_send <= now {
+			let Some(data) = self.queue.pop_front() else {
+				break;
+			};
+			let msg = VoiceFrame { user_id: 0, seq: self.seq, data };
+			client.send_message::<VoiceChannel, _>(&msg);
+			self.seq = self.seq.wrapping_add(1);
+			next_send += frame_duration;
+		}
+		self.next_send = Some(next_send);
+	}
+
+	/// Drop all queued frames, e.g. when push-to-talk is released
+	pub fn clear(&mut self) { self.queue.clear() }
+
+	// performance counters
+
+	/// The number of frames dropped for being oversized, or overflowing the queue
+	pub fn drop_count(&self) -> u64 { self.drop_count }
+}
+
+/// Buffers the frames received from each speaker for playout
+pub struct VoiceReceiver {
+	config: VoiceConfig,
+	speakers: HashMap<VoiceUserId, JitterBuffer>,
+	muted: HashSet<VoiceUserId>,
+}
+
+impl VoiceReceiver {
+	pub fn new(config: VoiceConfig) -> Self {
+		Self { config, speakers: HashMap::new(), muted: HashSet::new() }
+	}
+
+	/// Buffer the voice frames among `events`. Returns all other events.
+	pub fn process(&mut self, events: Vec<ClientEvent>) -> Vec<ClientEvent> {
+		let mut remaining = Vec::with_capacity(events.len());
+		for event in events {
+			match event {
+				ClientEvent::Message(msg) if is_voice(&msg) => self.receive(msg),
+				ClientEvent::Disconnect(addr) => {
+					self.speakers.clear();
+					remaining.push(ClientEvent::Disconnect(addr));
+				}
+				event => remaining.push(event),
+			}
+		}
+		remaining
+	}
+
+	/// Returns the next frame to play for `user_id`, or `None` while buffering or when
+	/// the speaker is silent. Call once per frame duration for each speaker.
+	pub fn pull(&mut self, user_id: VoiceUserId) -> Option<VoicePlayout> {
+		self.speakers.get_mut(&user_id)?.pull()
+	}
+
+	/// Iterate over the user ids of all speakers heard since they were last removed
+	pub fn speakers(&self) -> impl Iterator<Item = VoiceUserId> + '_ {
+		self.speakers.keys().copied()
+	}
+
+	/// The jitter buffer of `user_id`, e.g. to read its counters
+	pub fn jitter_buffer(&self, user_id: VoiceUserId) -> Option<&JitterBuffer> {
+		self.speakers.get(&user_id)
+	}
+
+	/// Forget `user_id`, e.g. when the application learns the user left
+	pub fn remove(&mut self, user_id: VoiceUserId) { self.speakers.remove(&user_id); }
+
+	/// Stop buffering frames from `user_id`, locally only; see `VoiceServer::set_muted()`
+	/// to mute a speaker for everyone
+	pub fn mute(&mut self, user_id: VoiceUserId) {
+		self.muted.insert(user_id);
+		self.speakers.remove(&user_id);
+	}
+
+	pub fn unmute(&mut self, user_id: VoiceUserId) { self.muted.remove(&user_id); }
+
+	pub fn is_muted(&self, user_id: VoiceUserId) -> bool { self.muted.contains(&user_id) }
+
+	fn receive(&mut self, msg: MessageContainer) {
+		let VoiceFrame { user_id, seq, data } = msg.downcast::<VoiceFrame>().unwrap();
+		if self.muted.contains(&user_id) || data.len() > self.config.max_frame_bytes {
+			return;
+		}
+
+		let depth = self.config.jitter_depth;
+		self.speakers.entry(user_id)
+			.or_insert_with(|| JitterBuffer::new(depth))
+			.push(seq, data);
+	}
+}
@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+pub enum VoicePlayout {
+	/// The next frame to decode and play
+	Frame(Box<[u8]>),
+	/// The next frame was lost or late; synthesize it with the codec's packet loss
+	/// concealment (e.g. by decoding an empty Opus packet)
+	Lost,
+}
+
+/// Reorders a speaker's frames and delays playout by a few frames to absorb network
+/// jitter. Call `pull()` once per frame duration, e.g. from the audio callback.
+pub struct JitterBuffer {
+	/// Frames buffered before playout starts
+	depth: usize,
+	/// Sequence number of the frame at the front of `slots`
+	next_seq: u16,
+	slots: VecDeque<Option<Box<[u8]>>>,
+	playing: bool,
+	/// Consecutive frames lost during playout
+	lost_run: usize,
+	late_count: u64,
+	lost_count: u64,
+}
+
+impl JitterBuffer {
+	pub fn new(depth: usize) -> Self {
+		Self {
+			depth: depth.max(1),
+			next_seq: 0,
+			slots: VecDeque::new(),
+			playing: false,
+			lost_run: 0,
+			late_count: 0,
+			lost_count: 0,
+		}
+	}
+
+	/// Frames further ahead than this restart the buffer
+	fn capacity(&self) -> usize { 4 * self.depth }
+
+	fn is_idle(&self) -> bool { !self.playing && self.slots.is_empty() }
+
+	/// Buffer a received frame
+	pub fn push(&mut self, seq: u16, data: Box<[u8]>) {
+		if self.is_idle() {
+			self.next_seq = seq;
+		}
+
+		let mut offset = seq.wrapping_sub(self.next_seq) as i16;
+		let behind = offset.unsigned_abs() as usize;
+		if offset < 0 && !self.playing && self.slots.len() + behind < self.capacity() {
+			// arrived out of order before playout started
+			for _ in 0..behind {
+				self.slots.push_front(None);
+			}
+			self.next_seq = seq;
+			offset = 0;
+		}
+		if offset < 0 {
+			// already played (or concealed)
+			self.late_count = self.late_count.wrapping_add(1);
+			return;
+		}
+
+		let mut offset = offset as usize;
+		if offset >= self.capacity() {
+			// too far ahead; the speaker's stream was interrupted, so start over
+			self.reset();
+			self.next_seq = seq;
+			offset = 0;
+		}
+
+		if self.slots.len() <= offset {
+			self.slots.resize(offset + 1, None);
+		}
+		self.slots[offset] = Some(data);
+	}
+
+	/// Returns the next frame to play, or `None` while buffering or when the speaker is
+	/// silent
+	pub fn pull(&mut self) -> Option<VoicePlayout> {
+		if !self.playing {
+			if self.slots.len() < self.depth {
+				return None;
+			}
+			self.playing = true;
+		}
+
+		self.next_seq = self.next_seq.wrapping_add(1);
+		if let Some(Some(data)) = self.slots.pop_front() {
+			self.lost_run = 0;
+			return Some(VoicePlayout::Frame(data));
+		}
+
+		self.lost_run += 1;
+		if self.slots.is_empty() && self.lost_run > self.depth {
+			// the speaker stopped talking
+			self.reset();
+			return None;
+		}
+
+		self.lost_count = self.lost_count.wrapping_add(1);
+		Some(VoicePlayout::Lost)
+	}
+
+	/// Drop all buffered frames, and wait for the buffer to fill up again
+	pub fn reset(&mut self) {
+		self.slots.clear();
+		self.playing = false;
+		self.lost_run = 0;
+	}
+
+	// performance counters
+
+	/// Number of frames which arrived too late to be played
+	pub fn late_count(&self) -> u64 { self.late_count }
+	/// Number of frames concealed during playout
+	pub fn lost_count(&self) -> u64 { self.lost_count }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn frame(byte: u8) -> Box<[u8]> { Box::new([byte]) }
+
+	fn pull(buffer: &mut JitterBuffer) -> Option<Option<u8>> {
+		buffer.pull().map(|playout| match playout {
+			VoicePlayout::Frame(data) => Some(data[0]),
+			VoicePlayout::Lost => None,
+		})
+	}
+
+	#[test]
+	fn reorder() {
+		let mut buffer = JitterBuffer::new(3);
+		buffer.push(11, frame(1));
+		assert_eq!(pull(&mut buffer), None);
+
+		buffer.push(10, frame(0));
+		buffer.push(12, frame(2));
+		assert_eq!(pull(&mut buffer), Some(Some(0)));
+		assert_eq!(pull(&mut buffer), Some(Some(1)));
+		assert_eq!(pull(&mut buffer), Some(Some(2)));
+	}
+
+	#[test]
+	fn conceal_loss() {
+		let mut buffer = JitterBuffer::new(2);
+		buffer.push(u16::MAX, frame(0));
+		buffer.push(1, frame(2));
+		assert_eq!(pull(&mut buffer), Some(Some(0)));
+		assert_eq!(pull(&mut buffer), Some(None));
+		assert_eq!(pull(&mut buffer), Some(Some(2)));
+		assert_eq!(buffer.lost_count(), 1);
+
+		// too late to play
+		buffer.push(0, frame(1));
+		assert_eq!(buffer.late_count(), 1);
+	}
+
+	#[test]
+	fn silence() {
+		let mut buffer = JitterBuffer::new(1);
+		buffer.push(0, frame(0));
+		assert_eq!(pull(&mut buffer), Some(Some(0)));
+		assert_eq!(pull(&mut buffer), Some(None));
+		assert_eq!(pull(&mut buffer), None);
+
+		// the next talk spurt starts from scratch
+		buffer.push(100, frame(1));
+		assert_eq!(pull(&mut buffer), Some(Some(1)));
+	}
+}
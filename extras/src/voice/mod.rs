@@ -0,0 +1,78 @@
+//! Voice chat transport. Frames encoded by the application (e.g. with Opus) are
+//! paced out by `VoiceSender`, relayed by `VoiceServer` to the other participants, and
+//! smoothed by a `JitterBuffer` per speaker in `VoiceReceiver`. Add `VoicePlugin` to
+//! the Schema.
+
+use naia_shared::{
+	Channel, ChannelDirection, ChannelMode, Message, MessageContainer, ProtocolPlugin,
+	SchemaBuilder,
+};
+use std::time::Duration;
+
+#[cfg(feature = "client")]
+mod client;
+mod jitter_buffer;
+#[cfg(feature = "server")]
+mod server;
+
+#[cfg(feature = "client")]
+pub use client::{VoiceReceiver, VoiceSender};
+pub use jitter_buffer::{JitterBuffer, VoicePlayout};
+#[cfg(feature = "server")]
+pub use server::VoiceServer;
+
+/// Identifies speakers; the `UserKey::to_bits()` of the speaker
+pub type VoiceUserId = u32;
+
+#[derive(Clone, Debug)]
+pub struct VoiceConfig {
+	/// Duration of audio in each frame; all frames must cover the same duration
+	pub frame_duration: Duration,
+	/// Maximum encoded frame size in bytes; larger frames are dropped
+	pub max_frame_bytes: usize,
+	/// Frames buffered by `VoiceSender` before the oldest are dropped
+	pub max_send_queue: usize,
+	/// Frames buffered by each `JitterBuffer` before playout starts. Higher values
+	/// absorb more jitter at the cost of latency.
+	pub jitter_depth: usize,
+}
+
+impl Default for VoiceConfig {
+	fn default() -> Self {
+		Self {
+			frame_duration: Duration::from_millis(20),
+			max_frame_bytes: 1275,
+			max_send_queue: 5,
+			jitter_depth: 3,
+		}
+	}
+}
+
+#[derive(Channel)]
+pub struct VoiceChannel;
+
+/// One frame of encoded audio. To the Server, `user_id` is ignored; to Clients, it is
+/// the speaker.
+#[derive(Message)]
+pub struct VoiceFrame {
+	pub user_id: VoiceUserId,
+	/// Frame sequence number, incremented for each frame sent by a speaker
+	pub seq: u16,
+	pub data: Box<[u8]>,
+}
+
+/// Registers `VoiceChannel` and `VoiceFrame`
+pub struct VoicePlugin;
+
+impl ProtocolPlugin for VoicePlugin {
+	fn build(&self, builder: SchemaBuilder) -> SchemaBuilder {
+		builder
+			.add_channel::<VoiceChannel>(
+				ChannelDirection::Bidirectional, ChannelMode::SequencedUnreliable,
+			)
+			.add_message::<VoiceFrame>()
+	}
+}
+
+#[cfg_attr(not(any(feature = "client", feature = "server")), allow(dead_code))]
+fn is_voice(msg: &MessageContainer) -> bool { msg.is::<VoiceFrame>() }
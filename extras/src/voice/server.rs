@@ -0,0 +1,79 @@
+use naia_server::{Server, ServerEvent, UserKey};
+use naia_shared::MessageContainer;
+use std::collections::HashMap;
+use super::*;
+
+/// Relays voice frames between participants
+pub struct VoiceServer {
+	config: VoiceConfig,
+	/// Whether each participant is muted
+	participants: HashMap<UserKey, bool>,
+	drop_count: u64,
+}
+
+impl VoiceServer {
+	pub fn new(config: VoiceConfig) -> Self {
+		Self { config, participants: HashMap::new(), drop_count: 0 }
+	}
+
+	/// Add `user_key` to the voice session; frames from non-participants are dropped
+	pub fn join(&mut self, user_key: &UserKey) { self.participants.entry(*user_key).or_insert(false); }
+
+	pub fn leave(&mut self, user_key: &UserKey) { self.participants.remove(user_key); }
+
+	/// Iterate over all participants, and whether each is muted
+	pub fn participants(&self) -> impl Iterator<Item = (&UserKey, bool)> {
+		self.participants.iter().map(|(user_key, muted)| (user_key, *muted))
+	}
+
+	/// Mute (or unmute) `user_key` for all other participants. Muted participants
+	/// still hear the others.
+	pub fn set_muted(&mut self, user_key: &UserKey, muted: bool) {
+		if let Some(participant) = self.participants.get_mut(user_key) {
+			*participant = muted;
+		}
+	}
+
+	pub fn is_muted(&self, user_key: &UserKey) -> bool {
+		self.participants.get(user_key).copied().unwrap_or(false)
+	}
+
+	/// Relay the voice frames among `events`, and remove disconnected users from the
+	/// session. Returns all other events, including disconnects.
+	pub fn process(&mut self, server: &mut Server, events: Vec<ServerEvent>) -> Vec<ServerEvent> {
+		let mut remaining = Vec::with_capacity(events.len());
+		for event in events {
+			match event {
+				ServerEvent::Message { user_key, msg } if is_voice(&msg) =>
+					self.receive(server, &user_key, msg),
+				ServerEvent::Disconnect { user_key, addr } => {
+					self.leave(&user_key);
+					remaining.push(ServerEvent::Disconnect { user_key, addr });
+				}
+				event => remaining.push(event),
+			}
+		}
+		remaining
+	}
+
+	fn receive(&mut self, server: &mut Server, user_key: &UserKey, msg: MessageContainer) {
+		let VoiceFrame { seq, data, .. } = msg.downcast::<VoiceFrame>().unwrap();
+		let allowed = self.participants.get(user_key) == Some(&false)
+			&& data.len() <= self.config.max_frame_bytes;
+		if !allowed {
+			self.drop_count = self.drop_count.wrapping_add(1);
+			return;
+		}
+
+		let msg = VoiceFrame { user_id: user_key.to_bits(), seq, data };
+		for participant in self.participants.keys().filter(|participant| *participant != user_key) {
+			server.send_message::<VoiceChannel, _>(participant, &msg);
+		}
+	}
+
+	// performance counters
+
+	/// The number of frames dropped for being oversized, or coming from muted or
+	/// non-participants
+	pub fn drop_count(&self) -> u64 { self.drop_count }
+}
@@ -0,0 +1,141 @@
+use crate::LifecycleHooks;
+use log::warn;
+use std::{
+	io::{self, Read, Write},
+	net::{Ipv4Addr, SocketAddr, TcpStream},
+	sync::{
+		Arc,
+		atomic::{AtomicBool, Ordering},
+		mpsc::{self, RecvTimeoutError},
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+/// The port of the SDK sidecar's HTTP API, unless `AGONES_SDK_HTTP_PORT` is set
+pub const AGONES_SDK_HTTP_PORT: u16 = 9358;
+/// Requests go to the local sidecar, which normally answers right away
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A blocking client for the HTTP API of the Agones SDK sidecar
+pub struct AgonesSdk {
+	addr: SocketAddr,
+}
+
+impl AgonesSdk {
+	pub fn new(addr: SocketAddr) -> Self {
+		Self { addr }
+	}
+
+	/// The sidecar on localhost, at the port in `AGONES_SDK_HTTP_PORT` if set
+	pub fn from_env() -> Self {
+		let port = std::env::var("AGONES_SDK_HTTP_PORT").ok()
+			.and_then(|port| port.parse().ok())
+			.unwrap_or(AGONES_SDK_HTTP_PORT);
+		Self::new((Ipv4Addr::LOCALHOST, port).into())
+	}
+
+	/// Mark the GameServer as ready to be allocated
+	pub fn ready(&self) -> io::Result<()> { self.post("/ready") }
+
+	/// Mark the GameServer as allocated, e.g. when players connect without going
+	/// through the Agones allocator
+	pub fn allocate(&self) -> io::Result<()> { self.post("/allocate") }
+
+	/// Ask Agones to shut the GameServer down
+	pub fn shutdown(&self) -> io::Result<()> { self.post("/shutdown") }
+
+	/// Report the GameServer as healthy; required periodically, see the fleet's
+	/// health check settings
+	pub fn health(&self) -> io::Result<()> { self.post("/health") }
+
+	fn post(&self, path: &str) -> io::Result<()> {
+		let mut stream = TcpStream::connect_timeout(&self.addr, REQUEST_TIMEOUT)?;
+		stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+		stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+		write!(
+			stream,
+			"POST {path} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{{}}",
+			self.addr,
+		)?;
+
+		let mut response = String::new();
+		stream.read_to_string(&mut response)?;
+		match response.split_ascii_whitespace().nth(1) {
+			Some("200") => Ok(()),
+			status => Err(io::Error::other(
+				format!("Agones SDK {path} failed with status {}", status.unwrap_or("none")),
+			)),
+		}
+	}
+}
+
+/// The SDK calls made by `AgonesHooks`
+enum Call {
+	Ready,
+	Allocate,
+	Shutdown,
+}
+
+/// Maps the Server's lifecycle to the Agones GameServer states: Ready once listening,
+/// Allocated when the first user is accepted, and Shutdown once a draining Server is
+/// empty. Also sends health checks every `health_interval`, as long as the Server is
+/// updated. The SDK is called from a separate thread, so a slow sidecar doesn't stall
+/// the Server; the thread stops once the hooks are dropped and its calls are made.
+pub struct AgonesHooks {
+	calls: mpsc::Sender<Call>,
+	/// Whether `on_update()` was called since the last health check
+	updated: Arc<AtomicBool>,
+}
+
+impl AgonesHooks {
+	pub fn new(sdk: AgonesSdk, health_interval: Duration) -> Self {
+		let (calls, call_rx) = mpsc::channel();
+		let updated = Arc::new(AtomicBool::new(false));
+		let worker_updated = updated.clone();
+		thread::Builder::new()
+			.name("naia-agones".to_string())
+			.spawn(move || call_sdk(sdk, health_interval, call_rx, worker_updated))
+			.expect("failed to spawn the Agones SDK thread");
+		Self { calls, updated }
+	}
+
+	fn queue(&self, call: Call) {
+		if self.calls.send(call).is_err() {
+			warn!("Dropped an Agones SDK call, the SDK thread stopped");
+		}
+	}
+}
+
+impl LifecycleHooks for AgonesHooks {
+	fn on_ready(&mut self) { self.queue(Call::Ready) }
+
+	fn on_allocate(&mut self) { self.queue(Call::Allocate) }
+
+	fn on_empty(&mut self) { self.queue(Call::Shutdown) }
+
+	fn on_update(&mut self) { self.updated.store(true, Ordering::Relaxed) }
+}
+
+/// Make the queued calls, and the health checks of a Server which is still updated
+fn call_sdk(sdk: AgonesSdk, health_interval: Duration, calls: mpsc::Receiver<Call>, updated: Arc<AtomicBool>) {
+	let mut next_health = Instant::now();
+	loop {
+		let (name, result) = match calls.recv_timeout(next_health.saturating_duration_since(Instant::now())) {
+			Ok(Call::Ready) => ("ready", sdk.ready()),
+			Ok(Call::Allocate) => ("allocate", sdk.allocate()),
+			Ok(Call::Shutdown) => ("shutdown", sdk.shutdown()),
+			Err(RecvTimeoutError::Timeout) => {
+				next_health = Instant::now() + health_interval;
+				if !updated.swap(false, Ordering::Relaxed) {
+					continue;
+				}
+				("health", sdk.health())
+			}
+			Err(RecvTimeoutError::Disconnected) => return,
+		};
+		if let Err(e) = result {
+			warn!("Failed to call Agones SDK {name}: {e}");
+		}
+	}
+}
@@ -0,0 +1,86 @@
+use crate::user::UserKey;
+use std::collections::BTreeMap;
+
+/// The number of ticks for which checksums are kept, on either side
+const CHECKSUM_HISTORY: usize = 256;
+
+/// Compares the state checksums of Clients against the Server's, see
+/// `Server::set_checksum()`
+#[derive(Default)]
+pub(crate) struct Checksums {
+	authoritative: BTreeMap<u32, u64>,
+	/// Client checksums for ticks the Server didn't record yet
+	pending: BTreeMap<u32, Vec<(UserKey, u64)>>,
+}
+
+impl Checksums {
+	/// Record the Server's checksum for `tick`, returning the users whose pending
+	/// checksums for it differ
+	pub fn record(&mut self, tick: u32, hash: u64) -> Vec<UserKey> {
+		self.authoritative.insert(tick, hash);
+		while self.authoritative.len() > CHECKSUM_HISTORY {
+			self.authoritative.pop_first();
+		}
+
+		self.pending.remove(&tick).unwrap_or_default().into_iter()
+			.filter(|(_, client_hash)| *client_hash != hash)
+			.map(|(user_key, _)| user_key)
+			.collect()
+	}
+
+	/// Check a Client's checksum for `tick`, returning whether it differs from the
+	/// Server's. Checksums for ticks the Server didn't record yet are compared once it
+	/// does; those for ticks it forgot are ignored.
+	pub fn check(&mut self, user_key: UserKey, tick: u32, hash: u64) -> bool {
+		if let Some(authoritative) = self.authoritative.get(&tick) {
+			return *authoritative != hash;
+		}
+		if self.authoritative.first_key_value().is_some_and(|(oldest, _)| tick < *oldest) {
+			return false;
+		}
+
+		self.pending.entry(tick).or_default().push((user_key, hash));
+		while self.pending.len() > CHECKSUM_HISTORY {
+			self.pending.pop_first();
+		}
+		false
+	}
+
+	pub fn remove_user(&mut self, user_key: &UserKey) {
+		self.pending.retain(|_, hashes| {
+			hashes.retain(|(key, _)| key != user_key);
+			!hashes.is_empty()
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn compare() {
+		let mut checksums = Checksums::default();
+		let (a, b) = (UserKey::new(0, 0), UserKey::new(1, 0));
+
+		// Client ahead of the Server
+		assert!(!checksums.check(a, 1, 10));
+		assert!(!checksums.check(b, 1, 11));
+		assert_eq!(checksums.record(1, 10), [b]);
+
+		// Server ahead of the Client
+		checksums.record(2, 20);
+		assert!(!checksums.check(a, 2, 20));
+		assert!(checksums.check(b, 2, 21));
+
+		// forgotten ticks
+		for tick in 3..3 + CHECKSUM_HISTORY as u32 {
+			checksums.record(tick, 0);
+		}
+		assert!(!checksums.check(a, 2, 21));
+
+		checksums.check(a, 1000, 1);
+		checksums.remove_user(&a);
+		assert!(checksums.record(1000, 2).is_empty());
+	}
+}
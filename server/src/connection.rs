@@ -1,12 +1,13 @@
-use crate::user::UserKey;
-use log::trace;
+use crate::HandshakeProgress;
+use crate::user::{UserKey, UserState};
 use naia_shared::{
-	BaseConnection, BitReader, ChannelKind, ChannelKinds, ConnectionConfig,
-	error::*, HostType, Io, MessageContainer, Schema, Serde, packet::*,
+	BaseConnection, BitReader, BitWrite, ChannelKind, ChannelKinds, CipherKind, ConnectionConfig, EphemeralKey,
+	ConnectionMiddleware, ConnectionStats, error::*, HandshakeTiming, HostType, Io, LinkQuality, MessageContainer, QueueDepth,
+	Salt, Schema, Serde, SnapshotSender, SyncChannel, packet::*,
 };
+use naia_shared::metrics::BandwidthReport;
 use std::net::SocketAddr;
-use std::time::Instant;
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use std::time::{Duration, Instant};
 
 pub enum ReceiveEvent {
 	Connecting(packet::ConnectRequest, Option<MessageContainer>),
@@ -18,7 +19,7 @@ pub enum ReceiveEvent {
 #[derive(PartialEq)]
 pub enum ConnectionState {
 	PendingEncrypt,
-	PendingConnect{ pub_key: PublicKey },
+	PendingConnect{ pub_key: [u8; packet::DH_KEY_SIZE] },
 	PendingAccept,
 	Connected,
 	Disconnected,
@@ -28,33 +29,133 @@ pub struct Connection {
     pub user_key: UserKey,
     base: BaseConnection,
 	state: ConnectionState,
+	cipher: Option<CipherKind>,
+	session_id: SessionId,
+	/// Sequence number of the last accepted Migrate packet, to reject replays
+	migrate_seq: Option<u64>,
+	/// Salt of the last accepted Migrate packet, whose key is already in use
+	migrate_salt: Option<Salt>,
+	observer: bool,
+	/// Whether to accept a Client's proposal to use compact headers
+	compact_headers: bool,
+	/// See `ServerConfig::max_connect_bytes`
+	max_connect_bytes: Option<usize>,
+	/// When the Client must have completed the handshake, see
+	/// `ServerConfig::handshake_timeout`
+	handshake_deadline: Option<Instant>,
+	/// The snapshot being streamed to the Client, until it acknowledges it
+	sync: Option<SnapshotSender>,
+	/// Messages dropped by `Server::set_message_validator()` validators
+	pub msg_rx_reject_count: u64,
+	/// The number of `receive()` calls which dropped messages for exceeding a channel's
+	/// rate limit; see `ServerConfig::throttle_disconnect`
+	pub throttle_strikes: u32,
+	/// The maintenance deadline this connection is scheduled at by the Server, while
+	/// idle
+	pub maintenance_at: Option<Instant>,
 }
 
+/// The maximum number of queued messages up to which more snapshot chunks are queued
+const SYNC_WINDOW_MESSAGES: usize = 64;
+
 impl Connection {
 	pub fn new(
 		address: &SocketAddr,
 		config: &ConnectionConfig,
 		channel_kinds: &ChannelKinds,
 		user_key: &UserKey,
+		max_connect_bytes: Option<usize>,
+		handshake_deadline: Option<Instant>,
     ) -> Self {
         Self {
             user_key: *user_key,
             base: BaseConnection::new(address, HostType::Server, config, channel_kinds),
 			state: ConnectionState::PendingEncrypt,
+			cipher: config.cipher,
+			session_id: fastrand::u64(..),
+			migrate_seq: None,
+			migrate_salt: None,
+			observer: false,
+			compact_headers: config.compact_headers,
+			max_connect_bytes,
+			handshake_deadline,
+			sync: None,
+			msg_rx_reject_count: 0,
+			throttle_strikes: 0,
+			maintenance_at: None,
         }
     }
 
+	pub fn address(&self) -> &SocketAddr { self.base.address() }
+	pub fn session_id(&self) -> SessionId { self.session_id }
+
 	pub fn is_connected(&self) -> bool { self.state == ConnectionState::Connected }
+	pub fn is_observer(&self) -> bool { self.observer }
+
+	/// How far the handshake got, unless connected
+	pub fn handshake_progress(&self) -> Option<HandshakeProgress> {
+		match self.state {
+			ConnectionState::PendingEncrypt => Some(HandshakeProgress::Contacted),
+			ConnectionState::PendingConnect{..} => Some(HandshakeProgress::KeysExchanged),
+			ConnectionState::PendingAccept => Some(HandshakeProgress::ConnectReceived),
+			ConnectionState::Connected | ConnectionState::Disconnected => None,
+		}
+	}
+
+	pub fn user_state(&self) -> UserState {
+		match self.state {
+			ConnectionState::PendingEncrypt | ConnectionState::PendingConnect{..} => UserState::Handshaking,
+			ConnectionState::PendingAccept => UserState::PendingAccept,
+			ConnectionState::Connected | ConnectionState::Disconnected => match self.sync {
+				Some(_) => UserState::Syncing,
+				None => UserState::Connected,
+			},
+		}
+	}
 
 	// Handshake
 
 	pub fn accept_connection(
-		&mut self, req: &packet::ConnectRequest, io: &mut Io,
+		&mut self, req: &packet::ConnectRequest, observer: bool, io: &mut Io,
 	) -> NaiaResult {
 		self.state = ConnectionState::Connected;
+		self.handshake_deadline = None;
+		self.base.mark_connected();
+		self.observer = observer;
+		self.base.set_observer(observer);
 		self.send_connect_response(req, io)
 	}
 
+	/// Stream `snapshot` to the Client, see `Server::set_snapshot_provider()`
+	pub fn start_sync(&mut self, snapshot: Box<[u8]>) {
+		self.sync = Some(SnapshotSender::new(snapshot));
+	}
+
+	/// Handle the Client's acknowledgement of the snapshot. Returns whether the sync
+	/// completed.
+	pub fn finish_sync(&mut self) -> bool {
+		let done = self.sync.as_ref().is_some_and(SnapshotSender::is_sent);
+		if done {
+			self.sync = None;
+		}
+		done
+	}
+
+	fn queue_sync_chunks(&mut self, schema: &Schema) -> NaiaResult {
+		let Some(sync) = &mut self.sync else {
+			return Ok(());
+		};
+
+		while self.base.queue_depth().messages < SYNC_WINDOW_MESSAGES {
+			let Some(chunk) = sync.next_chunk() else {
+				break;
+			};
+			let msg = MessageContainer::from_write(Box::new(chunk));
+			self.base.queue_message(schema.message_kinds(), &ChannelKind::of::<SyncChannel>(), msg)?;
+		}
+		Ok(())
+	}
+
 	pub fn reject_connection(&mut self, io: &mut Io, reason: RejectReason) -> NaiaResult {
 		self.state = ConnectionState::Disconnected;
 		let writer = self.write_reject_response(reason);
@@ -66,10 +167,11 @@ impl Connection {
 			return Ok(());
 		}
 
+		let connected = self.state == ConnectionState::Connected;
 		self.state = ConnectionState::Disconnected;
 
 		for _ in 0..3 {
-			let writer = if self.state == ConnectionState::Connected {
+			let writer = if connected {
 				self.write_disconnect()
 			} else {
 				self.write_reject_response(RejectReason::Disconnect)
@@ -94,19 +196,22 @@ impl Connection {
 			| ConnectionState::Disconnected => return Ok(ReceiveEvent::None),
 		}
 
-		let Ok(req) = packet::EncryptRequest::de(reader) else {
-			return Err(NaiaError::malformed::<packet::EncryptRequest>());
-		};
+		let req = packet::EncryptRequest::de(reader)
+			.map_err(NaiaError::malformed_by::<packet::EncryptRequest>)?;
 
-		if req.padding != [0; packet::EncryptRequest::PADDING_SIZE] {
-			return Err(NaiaError::malformed::<packet::EncryptRequest>());
-		}
+		packet::EncryptRequest::read_padding(reader)
+			.map_err(NaiaError::malformed_by::<packet::EncryptRequest>)?;
 
 		if self.state == ConnectionState::PendingEncrypt {
-			let priv_key = EphemeralSecret::random();
-			let pub_key = PublicKey::from(&priv_key);
+			let priv_key = EphemeralKey::random();
+			let pub_key = priv_key.public_key();
 
-			self.base.set_shared_key(priv_key, req.client_public_key.into());
+			// without a shared preference, fall back to the cipher fast on all hardware
+			let cipher = match self.cipher {
+				Some(cipher) if cipher != req.cipher => CipherKind::ChaCha20Poly1305,
+				_ => req.cipher,
+			};
+			self.base.set_shared_key(priv_key, &req.client_public_key, cipher);
 			self.state = ConnectionState::PendingConnect{ pub_key };
 		}
 
@@ -122,15 +227,18 @@ impl Connection {
 		debug_assert!(matches!(self.state, ConnectionState::PendingConnect{..}));
 		let ConnectionState::PendingConnect{ pub_key } = self.state else {
 			return Err(NaiaError::Message(
-				format!("Connection must be in PendingConnect to send encrypt response"),
+				"Connection must be in PendingConnect to send encrypt response".to_string(),
 			));
 		};
+		// the shared key is always set before entering PendingConnect
+		let Some(cipher) = self.base.cipher() else { unreachable!() };
 
 		let mut writer: _ = self.base.packet_writer(PacketType::EncryptResponse);
 		packet::EncryptResponse {
-			server_public_key: pub_key.to_bytes(),
+			server_public_key: pub_key,
 			client_timestamp_ns: req.client_timestamp_ns,
 			server_timestamp_ns: self.base.timestamp_ns(),
+			cipher,
 		}.ser(&mut writer);
 
 		self.base.send(io, writer)
@@ -150,17 +258,26 @@ impl Connection {
 			| ConnectionState::Disconnected => return Ok(ReceiveEvent::None),
 		}
 
-		let Ok(req) = packet::ConnectRequest::de(reader) else {
-			return Err(NaiaError::malformed::<packet::ConnectRequest>());
-		};
+		// refuse oversized requests before decoding the client's message
+		if self.max_connect_bytes.is_some_and(|max| reader.remaining_bits() > max * 8) {
+			self.reject_connection(io, RejectReason::ConnectTooLarge)?;
+			return Ok(ReceiveEvent::Disconnect);
+		}
+
+		let req = packet::ConnectRequest::de(reader)
+			.map_err(NaiaError::malformed_by::<packet::ConnectRequest>)?;
+		if req.schema_fingerprint != schema.fingerprint() {
+			// channel and message ids would be misread
+			self.reject_connection(io, RejectReason::Version)?;
+			return Ok(ReceiveEvent::Disconnect);
+		}
 
 		// read optional message
 		let connect_msg = match bool::de(reader) {
-			Err(_) => return Err(NaiaError::malformed::<packet::ConnectRequest>()),
+			Err(err) => return Err(NaiaError::malformed_by::<packet::ConnectRequest>(err)),
 			Ok(true) => {
-				let Ok(msg) = schema.message_kinds().read(reader) else {
-					return Err(NaiaError::malformed::<packet::ConnectRequest>());
-				};
+				let msg = schema.message_kinds().read(reader)
+					.map_err(NaiaError::malformed_by::<packet::ConnectRequest>)?;
 				Some(msg)
 			}
 			Ok(false) => None,
@@ -175,6 +292,7 @@ impl Connection {
 			},
 			ConnectionState::PendingConnect{..} => {
 				self.state = ConnectionState::PendingAccept;
+				self.base.mark_encrypted();
 				Ok(ReceiveEvent::Connecting(req, connect_msg))
 			}
 			_ => unreachable!(),
@@ -185,13 +303,119 @@ impl Connection {
 	fn send_connect_response(
 		&mut self, req: &packet::ConnectRequest, io: &mut Io,
 	) -> NaiaResult {
+		let compact_headers = self.compact_headers && req.compact_headers;
 		let mut writer: _ = self.base.packet_writer(PacketType::ConnectResponse);
 		packet::ConnectResponse {
 			client_timestamp_ns: req.client_timestamp_ns,
+			session_id: self.session_id,
+			observer: self.observer,
+			compact_headers,
 		}.ser(&mut writer);
+		self.base.set_compact_headers(compact_headers);
 		self.base.send(io, writer)
 	}
 
+	// Migration
+
+	/// Move this connection to `address` and switch to the key of its salt if `req` is
+	/// an authentic, non-replayed Migrate packet, and acknowledge it with a heartbeat.
+	/// Returns whether the connection was migrated.
+	pub fn migrate(
+		&mut self, now: &Instant, address: &SocketAddr, header: &PacketHeader,
+		req: &packet::Migrate, io: &mut Io,
+	) -> NaiaResult<bool> {
+		self.base.set_now(now);
+		if !self.is_connected() || req.session_id != self.session_id {
+			return Ok(false);
+		}
+
+		// further probes of the last migration are signed with the key already in use
+		let salt = (self.migrate_salt != Some(req.salt)).then_some(&req.salt);
+		let data = req.session_id.to_le_bytes();
+		let Some(packet_seq) = self.base.verify(header, &data, &req.tag, salt) else {
+			return Ok(false);
+		};
+		if self.migrate_seq.is_some_and(|seq| seq >= packet_seq) {
+			return Ok(false);
+		}
+		self.migrate_seq = Some(packet_seq);
+		if let Some(salt) = salt {
+			self.base.rekey(salt);
+			self.migrate_salt = Some(*salt);
+		}
+
+		self.base.set_address(address);
+		self.base.mark_heard();
+		self.base.send_heartbeat(io)?;
+		Ok(true)
+	}
+
+	// Handoff
+
+	/// Write this connection's state for `import()` by another Server
+	pub fn export(&self, schema: &Schema, writer: &mut dyn BitWrite) -> NaiaResult {
+		if !self.is_connected() {
+			return Err(NaiaError::from("cannot export a connection which is not established"));
+		}
+
+		self.session_id.ser(writer);
+		self.migrate_seq.ser(writer);
+		self.observer.ser(writer);
+		self.base.export(schema, writer)
+	}
+
+	/// Send nothing but Redirect and Disconnect packets anymore, see
+	/// `BaseConnection::mark_exported()`
+	pub fn mark_exported(&mut self) { self.base.mark_exported() }
+
+	/// Restore an established connection written by `export()`
+	pub fn import(
+		config: &ConnectionConfig, schema: &Schema, user_key: &UserKey, reader: &mut BitReader,
+	) -> NaiaResult<Self> {
+		let session_id = SessionId::de(reader)?;
+		let migrate_seq = Option::<u64>::de(reader)?;
+		let observer = bool::de(reader)?;
+		let mut base = BaseConnection::import(HostType::Server, config, schema, reader)?;
+		base.set_observer(observer);
+
+		Ok(Self {
+			user_key: *user_key,
+			cipher: base.cipher(),
+			base,
+			state: ConnectionState::Connected,
+			session_id,
+			migrate_seq,
+			migrate_salt: None,
+			observer,
+			compact_headers: config.compact_headers,
+			// already connected
+			max_connect_bytes: None,
+			handshake_deadline: None,
+			sync: None,
+			msg_rx_reject_count: 0,
+			throttle_strikes: 0,
+			maintenance_at: None,
+		})
+	}
+
+	/// Tell the Client to move to the Server at `address`, which imported this
+	/// connection
+	pub fn redirect(&mut self, address: &SocketAddr, io: &mut Io) -> NaiaResult {
+		if !self.is_connected() {
+			return Err(NaiaError::from("cannot redirect a connection which is not established"));
+		}
+
+		self.state = ConnectionState::Disconnected;
+
+		for _ in 0..3 {
+			let mut writer: _ = self.base.packet_writer(PacketType::Redirect);
+			packet::Redirect { address: *address, token: self.session_id }.ser(&mut writer);
+			self.base.send(io, writer)?;
+		}
+
+		Ok(())
+	}
+
 	fn write_disconnect(&mut self) -> PacketWriter {
 		let mut writer: _ = self.base.packet_writer(PacketType::Disconnect);
 		packet::Disconnect{}.ser(&mut writer);
@@ -205,9 +429,7 @@ impl Connection {
 	}
 
 	fn recv_disconnect(&mut self, reader: &mut BitReader) -> NaiaResult<ReceiveEvent> {
-		let Ok(_) = packet::Disconnect::de(reader) else {
-			return Err(NaiaError::malformed::<packet::Disconnect>());
-		};
+		packet::Disconnect::de(reader).map_err(NaiaError::malformed_by::<packet::Disconnect>)?;
 
 		Ok(ReceiveEvent::Disconnect)
 	}
@@ -215,71 +437,145 @@ impl Connection {
     // Incoming Data
 
 	pub fn receive_packet(
-		&mut self, reader: &mut BitReader, io: &mut Io, schema: &Schema,
+		&mut self, now: &Instant, reader: &mut BitReader, io: &mut Io, schema: &Schema,
 	) -> NaiaResult<ReceiveEvent> {
-		self.base.mark_heard();
-
+		self.base.set_now(now);
 		let header = self.base.maybe_decrypt(reader)?;
-		match header.packet_type {
+		// only authentic packets keep the connection alive
+		self.base.mark_heard();
+		let result = match header.packet_type {
 			PacketType::EncryptRequest => self.recv_encrypt_request(io, reader),
 			PacketType::ConnectRequest => self.recv_connect_request(schema, io, reader),
-			PacketType::Data => {
-				self.base.read_data_packet(schema, header.packet_seq, reader)?;
-				Ok(ReceiveEvent::Data)
-			}
+			PacketType::Data => self.base.read_data_packet(schema, header.packet_seq, reader)
+				.map(|_| ReceiveEvent::Data),
 			PacketType::Disconnect => self.recv_disconnect(reader),
-			PacketType::Heartbeat => Ok(ReceiveEvent::None),
-			PacketType::Ping => {
-				self.base.ping_pong(reader, io)?;
-				Ok(ReceiveEvent::None)
-			}
-			PacketType::Pong => {
-				self.base.read_pong(reader)?;
-				Ok(ReceiveEvent::None)
-			}
+			PacketType::Heartbeat => self.base.read_heartbeat(header.packet_seq, reader)
+				.map(|_| ReceiveEvent::None),
+			PacketType::Ping => self.base.ping_pong(reader, io).map(|_| ReceiveEvent::None),
+			PacketType::Pong => self.base.read_pong(reader).map(|_| ReceiveEvent::None),
+			PacketType::Migrate => self.recv_migrate(now, &header, io, reader),
 			t => {
-				trace!("Dropping spurious {t:?} from {}", self.base.address());
-				Ok(ReceiveEvent::None)
+				self.base.drop_spurious(t);
+				return Ok(ReceiveEvent::None);
 			}
-		}
+		};
+		self.base.inspect_rx(header.packet_type, &result, reader);
+		result
+	}
+
+	fn recv_migrate(
+		&mut self, now: &Instant, header: &PacketHeader, io: &mut Io, reader: &mut BitReader,
+	) -> NaiaResult<ReceiveEvent> {
+		// the address is unchanged, but the Client still awaits a response
+		let req = packet::Migrate::de(reader)
+			.map_err(NaiaError::malformed_by::<packet::Migrate>)?;
+		let address = *self.base.address();
+		self.migrate(now, &address, header, &req, io)?;
+		Ok(ReceiveEvent::None)
 	}
 
 	pub fn receive_messages(&mut self) -> impl Iterator<Item = MessageContainer> + '_ {
 		self.base.receive_messages()
 	}
 
+	pub fn take_throttled(&mut self) -> Vec<(ChannelKind, u64)> { self.base.take_throttled() }
+
     // Outgoing data
 
 	pub fn queue_message(
 		&mut self, schema: &Schema, channel: &ChannelKind, msg: MessageContainer,
-	) {
-		self.base.queue_message(schema.message_kinds(), channel, msg);
+	) -> NaiaResult {
+		self.base.queue_message(schema.message_kinds(), channel, msg)
+	}
+
+	pub fn pause_channel(&mut self, channel: &ChannelKind) { self.base.pause_channel(channel) }
+
+	pub fn resume_channel(&mut self, schema: &Schema, channel: &ChannelKind) -> NaiaResult {
+		self.base.resume_channel(schema.message_kinds(), channel)
+	}
+
+	pub fn is_channel_paused(&self, channel: &ChannelKind) -> bool {
+		self.base.is_channel_paused(channel)
+	}
+
+	pub fn set_bandwidth_profiler(&mut self, window: Option<Duration>) {
+		self.base.set_bandwidth_profiler(window)
+	}
+
+	pub fn bandwidth_report(&self, schema: &Schema) -> Option<BandwidthReport> {
+		self.base.bandwidth_report(schema.channel_kinds())
 	}
 
 	pub fn send(
 		&mut self, now: &Instant, schema: &Schema, io: &mut Io
 	) -> NaiaResult {
+		self.base.set_now(now);
 		if !self.is_connected() {
 			return Ok(());
 		}
 
+		self.queue_sync_chunks(schema)?;
 		self.base.send_data_packets(schema, now, io)?;
 		self.base.try_send_ping(io)?;
 		self.base.try_send_heartbeat(io)
 	}
 
-	pub fn timed_out(&self) -> bool { self.base.timed_out() }
+	/// Send queued messages right away, outside of the regular `send()`
+	pub fn flush(&mut self, now: &Instant, schema: &Schema, io: &mut Io) -> NaiaResult {
+		self.base.flush_data_packets(schema, now, io)
+	}
+
+	pub fn apply_config(&mut self, config: &ConnectionConfig) { self.base.apply_config(config) }
+
+	pub fn add_middleware(&mut self, middleware: Box<dyn ConnectionMiddleware>) {
+		self.base.add_middleware(middleware);
+	}
+
+	pub fn set_send_interval(&mut self, interval: Duration) {
+		self.base.set_send_interval(interval);
+	}
+
+	pub fn timed_out(&self, now: &Instant) -> bool {
+		self.base.timed_out(now) || self.handshake_deadline.is_some_and(|deadline| deadline <= *now)
+	}
+
+	pub fn timeout_deadline(&self) -> Instant {
+		let deadline = self.base.timeout_deadline();
+		self.handshake_deadline.map_or(deadline, |handshake| handshake.min(deadline))
+	}
+	pub fn next_maintenance(&self) -> Instant { self.base.next_maintenance() }
+
+	/// Whether `send()` has nothing to do until the next ping or heartbeat, e.g.
+	/// because it isn't connected yet
+	pub fn is_idle(&self) -> bool {
+		!self.is_connected() || self.sync.is_none() && self.base.is_idle()
+	}
 
 	pub fn rtt_ms(&self) -> f32 { self.base.rtt_ms() }
 	pub fn jitter_ms(&self) -> f32 { self.base.jitter_ms() }
+	pub fn packet_loss(&self) -> f32 { self.base.packet_loss() }
+	pub fn link_quality(&self) -> LinkQuality { self.base.link_quality() }
+	pub fn queue_depth(&self) -> QueueDepth { self.base.queue_depth() }
+	pub fn stats(&self) -> ConnectionStats { self.base.stats() }
+	pub fn handshake_timing(&self) -> HandshakeTiming { self.base.handshake_timing() }
+	pub fn cipher(&self) -> Option<CipherKind> { self.base.cipher() }
+	pub fn take_link_change(&mut self) -> Option<LinkQuality> { self.base.take_link_change() }
+	pub fn take_decrypt_alarm(&mut self) -> bool { self.base.take_decrypt_alarm() }
 
 	// performance counters
 
 	pub fn msg_rx_count(&self) -> u64 { self.base.msg_rx_count() }
 	pub fn msg_rx_drop_count(&self) -> u64 { self.base.msg_rx_drop_count() }
 	pub fn msg_rx_miss_count(&self) -> u64 { self.base.msg_rx_miss_count() }
+	pub fn frag_rx_expired_count(&self) -> u64 { self.base.frag_rx_expired_count() }
+	pub fn frag_rx_refused_count(&self) -> u64 { self.base.frag_rx_refused_count() }
+	pub fn pkt_rx_decrypt_fail_count(&self) -> u64 { self.base.pkt_rx_decrypt_fail_count() }
+	pub fn msg_rx_observer_drop_count(&self) -> u64 { self.base.msg_rx_observer_drop_count() }
+	pub fn msg_rx_throttle_count(&self) -> u64 { self.base.msg_rx_throttle_count() }
 	pub fn msg_tx_count(&self) -> u64 { self.base.msg_tx_count() }
 	pub fn msg_tx_queue_count(&self) -> u64 { self.base.msg_tx_queue_count() }
+	pub fn msg_rx_per_sec(&self, channel_kind: &ChannelKind) -> f32 { self.base.msg_rx_per_sec(channel_kind) }
+	pub fn msg_tx_per_sec(&self, channel_kind: &ChannelKind) -> f32 { self.base.msg_tx_per_sec(channel_kind) }
 }
 
 pub fn write_reject_response(reason: RejectReason) -> PacketWriter {
@@ -290,3 +586,12 @@ pub fn write_reject_response(reason: RejectReason) -> PacketWriter {
 	packet::HandshakeReject { reason }.ser(&mut writer);
 	writer
 }
+
+pub fn write_retry(cookie: packet::HandshakeCookie) -> PacketWriter {
+	let mut writer: _ = PacketWriter::new(PacketHeader {
+		packet_type: PacketType::HandshakeRetry,
+		packet_seq: 0.into(),
+	});
+	packet::HandshakeRetry { cookie }.ser(&mut writer);
+	writer
+}
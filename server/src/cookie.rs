@@ -0,0 +1,64 @@
+use naia_shared::{packet::packet::HandshakeCookie, MacKey, Salt};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// How long Clients may take to echo a cookie
+const COOKIE_LIFETIME: Duration = Duration::from_secs(10);
+
+/// Issues the cookies of HandshakeRetry packets and checks the ones Clients echo,
+/// without keeping state per Client. Cookies are MACs of the Client's address and the
+/// issue time, under a key random to each Server.
+pub struct CookieJar {
+	key: MacKey,
+	epoch: Instant,
+}
+
+impl CookieJar {
+	pub fn new() -> Self {
+		Self { key: MacKey::random(), epoch: Instant::now() }
+	}
+
+	pub fn issue(&self, now: &Instant, address: &SocketAddr) -> HandshakeCookie {
+		let issued_ns = now.duration_since(self.epoch).as_nanos() as u64;
+		HandshakeCookie { issued_ns, tag: self.tag(address, issued_ns) }
+	}
+
+	/// Whether `cookie` was issued to `address` within the cookie lifetime
+	pub fn check(&self, now: &Instant, address: &SocketAddr, cookie: &HandshakeCookie) -> bool {
+		let age = now.duration_since(self.epoch).saturating_sub(Duration::from_nanos(cookie.issued_ns));
+		age < COOKIE_LIFETIME && cookie.tag == self.tag(address, cookie.issued_ns)
+	}
+
+	/// Every address is encoded in the same two blocks: the IP, IPv4 addresses mapped
+	/// to IPv6, then the port, the family and the issue time
+	fn tag(&self, address: &SocketAddr, issued_ns: u64) -> u64 {
+		let ip = match address {
+			SocketAddr::V4(address) => address.ip().to_ipv6_mapped(),
+			SocketAddr::V6(address) => *address.ip(),
+		};
+		let mut rest: Salt = [0; 16];
+		rest[..2].copy_from_slice(&address.port().to_le_bytes());
+		rest[2] = address.is_ipv6().into();
+		rest[8..].copy_from_slice(&issued_ns.to_le_bytes());
+		self.key.tag(&[ip.octets(), rest])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn check() {
+		let jar = CookieJar::new();
+		let now = Instant::now();
+		let address = "127.0.0.1:4000".parse().unwrap();
+		let cookie = jar.issue(&now, &address);
+		assert!(jar.check(&now, &address, &cookie));
+		assert!(!jar.check(&(now + COOKIE_LIFETIME), &address, &cookie));
+		assert!(!jar.check(&now, &"127.0.0.1:4001".parse().unwrap(), &cookie));
+		assert!(!jar.check(&now, &"[::ffff:127.0.0.1]:4000".parse().unwrap(), &cookie));
+		assert!(!jar.check(&now, &address, &HandshakeCookie { tag: cookie.tag ^ 1, ..cookie }));
+		assert!(!CookieJar::new().check(&now, &address, &cookie));
+	}
+}
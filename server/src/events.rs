@@ -1,14 +1,108 @@
-use naia_shared::{error::*, MessageContainer, packet::*};
-use std::net::SocketAddr;
+use naia_shared::{ChannelKind, error::*, MessageContainer, packet::*};
+use std::{error, fmt, net::SocketAddr, time::Instant};
 use super::user::UserKey;
 
+/// The request behind a `Connect` event, passed back to `Server::accept_connection()`.
+/// It expires after the connection timeout, at which point the Client has likely
+/// given up.
 pub struct ConnectContext {
 	pub(crate) req: packet::ConnectRequest,
+	pub(crate) user_key: UserKey,
+	pub(crate) expires: Instant,
+}
+
+impl ConnectContext {
+	/// Whether the Client requested to connect as a receive-only observer
+	pub fn is_observer(&self) -> bool { self.req.observer }
+
+	pub fn is_expired(&self) -> bool { Instant::now() >= self.expires }
+
+	/// The identity claimed by the Client with `Client::connect_as()`
+	pub fn identity(&self) -> Option<&str> { self.req.identity.as_deref() }
+}
+
+/// Why a connection couldn't be accepted or rejected
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AcceptError {
+	/// The Server isn't listening
+	NotListening,
+	/// The user disconnected, or its UserKey is stale
+	UnknownUser,
+	/// The ConnectContext belongs to a different user
+	WrongUser,
+	/// The ConnectContext expired; reject the connection instead
+	Expired,
+	/// The connection was already accepted
+	AlreadyAccepted,
+	/// Another session with the same identity is connected, so the connection was
+	/// rejected; see `DuplicatePolicy::RejectNew`
+	Duplicate,
+}
+
+impl fmt::Display for AcceptError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			AcceptError::NotListening => write!(f, "server is not listening"),
+			AcceptError::UnknownUser => write!(f, "unknown user"),
+			AcceptError::WrongUser => write!(f, "connect context belongs to a different user"),
+			AcceptError::Expired => write!(f, "connect context expired"),
+			AcceptError::AlreadyAccepted => write!(f, "connection was already accepted"),
+			AcceptError::Duplicate => write!(f, "another session with the same identity is connected"),
+		}
+	}
+}
+
+impl error::Error for AcceptError {}
+
+/// How far a Client got in the handshake before giving up, see
+/// `ServerEvent::ConnectTimeout`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HandshakeProgress {
+	/// Packets were received, but no valid encrypt request, e.g. from a scanner
+	Contacted,
+	/// Keys were exchanged, but no valid connect request followed, e.g. from a Client
+	/// with a different protocol version
+	KeysExchanged,
+	/// The `Connect` event was emitted, but the connection was not accepted
+	ConnectReceived,
 }
 
 pub enum ServerEvent {
 	Connect{ user_key: UserKey, addr: SocketAddr, msg: Option<MessageContainer>, ctx: ConnectContext },
 	Disconnect{ user_key: UserKey, addr: SocketAddr },
+	/// A Client didn't complete the handshake within `ServerConfig::handshake_timeout`,
+	/// or went silent for the connection timeout while handshaking, and its pending
+	/// connection was dropped. Only a user which got a `Connect` event also gets a
+	/// `Disconnect`.
+	ConnectTimeout{ addr: SocketAddr, progress: HandshakeProgress },
 	Error(NaiaError),
+	/// Packets from the user's address failed decryption more often than allowed by
+	/// `ConnectionConfig::decrypt_failures`. A `Disconnect` follows if configured.
+	DecryptFailures{ user_key: UserKey },
 	Message{ user_key: UserKey, msg: MessageContainer },
+	/// The link to the user's Client crossed the thresholds of
+	/// `ConnectionConfig::congestion`. `loss` is the fraction of packets recently lost.
+	LinkDegraded{ user_key: UserKey, loss: f32, rtt_ms: f32 },
+	/// The link to the user's Client stayed within thresholds for the congestion
+	/// controller's recovery time, after `LinkDegraded`
+	LinkRecovered{ user_key: UserKey },
+	/// A timed out or imported session was resumed by its Client, without a handshake;
+	/// see `ServerConfig::resume_window`
+	Resume{ user_key: UserKey, addr: SocketAddr },
+	/// The user's Client received the whole snapshot of
+	/// `Server::set_snapshot_provider()`, and is ready to join the game
+	UserSynced{ user_key: UserKey },
+	/// The user's Client reported a state checksum for `tick` which differs from the
+	/// one given to `Server::set_checksum()`
+	Desync{ user_key: UserKey, tick: u32 },
+	/// The user's session was replaced by a new connection with the same identity,
+	/// e.g. the account logged in elsewhere; see `DuplicatePolicy::ReplaceOld`. A
+	/// `Disconnect` of the user follows.
+	SessionReplaced{ user_key: UserKey, by: UserKey },
+	/// Messages from the user exceeded the rate limit of `channel`, and `dropped` of
+	/// them were dropped; see `SchemaBuilder::rate_limit()`. A `Disconnect` follows
+	/// once `ServerConfig::throttle_disconnect` is reached.
+	Throttled{ user_key: UserKey, channel: ChannelKind, dropped: u64 },
+	/// A `Server::shutdown_graceful()` finished, and the Server stopped listening
+	ShutdownComplete,
 }
@@ -14,15 +14,32 @@ pub mod shared {
 		SignedInteger, SignedVariableInteger, UnsignedInteger, UnsignedVariableInteger,
     };
 }
-pub use naia_shared::packet::RejectReason;
+pub use naia_shared::{packet::RejectReason, ConfigError, ConnectionMiddleware, LinkQuality, QueueDepth, Stamped};
 
+#[cfg(feature = "agones")]
+mod agones;
+mod checksum;
 mod connection;
+mod cookie;
 mod events;
+mod lifecycle;
 mod server;
 mod server_config;
+mod spatial;
+#[cfg(feature = "status")]
+mod status;
+mod threaded;
 mod user;
+mod validation;
 
+#[cfg(feature = "agones")]
+pub use agones::{AGONES_SDK_HTTP_PORT, AgonesHooks, AgonesSdk};
 pub use events::*;
+pub use lifecycle::LifecycleHooks;
 pub use server::Server;
-pub use server_config::ServerConfig;
-pub use user::UserKey;
+#[cfg(feature = "status")]
+pub use status::{MAX_PENDING_REQUESTS, StatusServer};
+pub use server_config::{DuplicatePolicy, ServerConfig, ServerConfigBuilder};
+pub use threaded::{EventReceiver, ServerHandle};
+pub use user::{UserKey, UserRef, UserState};
+pub use validation::Verdict;
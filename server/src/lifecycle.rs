@@ -0,0 +1,18 @@
+/// Callbacks on Server lifecycle transitions, e.g. to report them to an orchestrator
+/// such as Agones; see `Server::set_lifecycle_hooks()`
+pub trait LifecycleHooks: Send {
+	/// The Server started listening, and can accept users
+	fn on_ready(&mut self) {}
+
+	/// The Server accepted its first user
+	fn on_allocate(&mut self) {}
+
+	/// `Server::drain()` was called; no new users are accepted
+	fn on_drain(&mut self) {}
+
+	/// The last user left a draining Server, which can be shut down now
+	fn on_empty(&mut self) {}
+
+	/// Called by every `Server::send()`, e.g. for periodic health checks
+	fn on_update(&mut self) {}
+}
@@ -1,14 +1,29 @@
-use crate::{ConnectContext, server_config::ServerConfig, ServerEvent};
-use crate::user::UserKey;
+use crate::{AcceptError, ConnectContext, HandshakeProgress, server_config::{DuplicatePolicy, ServerConfig}, ServerEvent};
+use crate::checksum::Checksums;
+use crate::cookie::CookieJar;
+use crate::lifecycle::LifecycleHooks;
+use crate::spatial::SpatialGrid;
+use crate::user::{UserKey, UserKeyPool, UserRef, UserState};
+use crate::validation::{self, Validator, Verdict};
 use naia_shared::{
-	Channel, ChannelKind, error::*, IdPool, Io, ConditionerConfig,
-	Message, MessageContainer, RejectReason, Schema,
+	BitReader, BitVecWriter, BitWrite, Channel, ChannelKind, CipherKind, ConnectionMiddleware, error::*,
+	ConfigError, Io, ConditionerConfig, LinkQuality, Message, MessageContainer, packet::*,
+	ConnectionStats, HandshakePadding, HandshakeTiming, MessageKind, QueueDepth, RejectReason, Schema, SendErrorFilter, Serde, SerdeErr, Stamped, StateChecksum, SyncDone,
+	TimerWheel,
 };
+use naia_shared::metrics::{BandwidthReport, MetricsReporter, MetricsSink, MetricsSnapshot};
 use log::warn;
 use std::collections::hash_map::Entry;
-use std::{collections::{HashMap, HashSet}, io, net::SocketAddr, panic, time::Instant};
+use std::{
+	collections::{HashMap, HashSet, VecDeque}, io, net::SocketAddr, panic,
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use super::connection::*;
 
+/// The resolution and number of slots of the wheels scheduling connection maintenance
+const WHEEL_RESOLUTION: Duration = Duration::from_millis(10);
+const WHEEL_SLOTS: usize = 512;
+
 /// A server that uses either UDP communication to send/receive
 /// messages to/from connected clients
 pub struct Server {
@@ -17,25 +32,96 @@ pub struct Server {
     schema: Schema,
 	// Connection
     io: Option<Io>,
+	/// Packets read by `poll()`, awaiting `receive()`
+	polled: VecDeque<(SocketAddr, BitReader)>,
+	/// Packets dropped from a full `polled` queue
+	polled_dropped: u64,
 	addr_conns: HashMap<SocketAddr, Connection>,
+	/// Users with messages to send or acknowledge, visited by every `send()`
+	active: HashSet<UserKey>,
+	/// Idle users by when their next ping or heartbeat is due
+	maintenance: TimerWheel<UserKey>,
+	/// Users by when they time out unless heard from
+	timeouts: TimerWheel<UserKey>,
     // Users
 	user_addrs: HashMap<UserKey, SocketAddr>,
-	user_id_pool: IdPool<UserKey>,
+	/// The addresses of connections by session, to find the target of Migrate packets
+	session_addrs: HashMap<SessionId, SocketAddr>,
+	user_id_pool: UserKeyPool,
+	/// Sessions which may still be resumed; their UserKeys stay reserved
+	resumable: HashMap<SessionId, Resumable>,
+	/// Issues the cookies of HandshakeRetry packets
+	cookies: CookieJar,
+	/// Accepted users by the identity they claimed, unless duplicates are allowed
+	identities: HashMap<String, UserKey>,
+	/// Positions set with `set_user_position()`
+	spatial: SpatialGrid,
     // Events
     incoming_events: Vec<ServerEvent>,
+	/// The number of events returned by `receive()` so far
+	event_seq: u64,
+	/// Counters at the last `reset_metrics()`
+	metrics_baseline: Option<MetricsSnapshot>,
+	metrics_reporter: Option<MetricsReporter>,
+	/// The window of the bandwidth profiler, if enabled
+	bandwidth_window: Option<Duration>,
+	snapshot_provider: Option<Box<dyn FnMut(&UserKey) -> Vec<u8> + Send>>,
+	validators: HashMap<MessageKind, Validator>,
+	checksums: Checksums,
+	lifecycle: Lifecycle,
+	/// The deadline of a `shutdown_graceful()` in progress
+	shutdown_deadline: Option<Instant>,
+	send_errors: Option<SendErrorFilter>,
+}
+
+/// The state reported to `LifecycleHooks`
+#[derive(Default)]
+struct Lifecycle {
+	hooks: Option<Box<dyn LifecycleHooks>>,
+	allocated: bool,
+	draining: bool,
+	empty: bool,
+}
+
+impl Lifecycle {
+	fn notify(&mut self, hook: impl FnOnce(&mut dyn LifecycleHooks)) {
+		if let Some(hooks) = &mut self.hooks {
+			hook(hooks.as_mut());
+		}
+	}
 }
 
 impl Server {
     /// Create a new Server
     pub fn new(config: ServerConfig, schema: Schema) -> Self {
         Server {
+			spatial: SpatialGrid::new(config.spatial_cell_size),
+			send_errors: config.send_error_window.map(SendErrorFilter::new),
             config,
             schema,
 			io: None,
+			polled: VecDeque::new(),
+			polled_dropped: 0,
 			addr_conns: HashMap::new(),
+			active: HashSet::new(),
+			maintenance: TimerWheel::new(WHEEL_RESOLUTION, WHEEL_SLOTS),
+			timeouts: TimerWheel::new(WHEEL_RESOLUTION, WHEEL_SLOTS),
             user_addrs: HashMap::new(),
-			user_id_pool: IdPool::default(),
+			session_addrs: HashMap::new(),
+			user_id_pool: UserKeyPool::default(),
+			resumable: HashMap::new(),
+			cookies: CookieJar::new(),
+			identities: HashMap::new(),
             incoming_events: Vec::new(),
+			event_seq: 0,
+			metrics_baseline: None,
+			metrics_reporter: None,
+			bandwidth_window: None,
+			snapshot_provider: None,
+			validators: HashMap::new(),
+			checksums: Checksums::default(),
+			lifecycle: Lifecycle::default(),
+			shutdown_deadline: None,
         }
     }
 
@@ -43,6 +129,13 @@ impl Server {
 		self.addr_conns.values()
 	}
 
+	/// Whether new users are refused, i.e. `ServerConfig::max_users` are connected or
+	/// the Server is draining
+	fn is_full(&self) -> bool {
+		self.lifecycle.draining
+			|| self.config.max_users.is_some_and(|max| self.user_addrs.len() >= max)
+	}
+
     /// Listen at the given addresses
     pub fn listen(&mut self, addr: SocketAddr) -> NaiaResult {
 		debug_assert!(!self.is_listening(), "Server is already listening");
@@ -50,14 +143,80 @@ impl Server {
 			return Err(io::ErrorKind::AlreadyExists.into());
 		}
 
-		let io = Io::listen(addr, self.conditioner_config())?;
+		let io = Io::listen(addr, self.conditioner_config(), &self.config.socket)?;
 		self.io = Some(io);
+		self.lifecycle.notify(|hooks| hooks.on_ready());
 		Ok(())
     }
 
-	/// Disconnect from all connected clients and stop listening
+	/// Refuse new users, e.g. before a restart or once a match ended, while connected
+	/// users stay until they leave or are disconnected. Calls
+	/// `LifecycleHooks::on_empty()` once none are left.
+	pub fn drain(&mut self) {
+		if self.lifecycle.draining {
+			return;
+		}
+
+		self.lifecycle.draining = true;
+		self.lifecycle.notify(|hooks| hooks.on_drain());
+		self.check_empty();
+	}
+
+	pub fn is_draining(&self) -> bool { self.lifecycle.draining }
+
+	/// Refuse new users like `drain()`, and send `message` on reliable channel `C` to
+	/// all connected users, e.g. the reason for the shutdown. Once every Client
+	/// acknowledged all messages sent to it, or after `timeout`, `receive()` shuts the
+	/// Server down and emits `ServerEvent::ShutdownComplete`, along with a Disconnect
+	/// for each user. Keep calling `receive()` and `send()` until then.
+	pub fn shutdown_graceful<C: Channel, M: Message>(&mut self, timeout: Duration, message: &M) {
+		debug_assert!(
+			self.schema.channel_kinds().channel(&ChannelKind::of::<C>()).reliable(),
+			"shutdown messages must be sent on a reliable channel",
+		);
+		if !self.is_listening() || self.shutdown_deadline.is_some() {
+			return;
+		}
+
+		self.drain();
+		self.broadcast_message::<C, M>(message);
+		self.shutdown_deadline = Some(Instant::now() + timeout);
+	}
+
+	/// Whether a `shutdown_graceful()` is in progress
+	pub fn is_shutting_down(&self) -> bool { self.shutdown_deadline.is_some() }
+
+	fn check_shutdown(&mut self) {
+		let Some(deadline) = self.shutdown_deadline else {
+			return;
+		};
+		let acked = self.connections().all(|conn| conn.queue_depth().messages == 0);
+		if acked || Instant::now() >= deadline {
+			self.shutdown();
+			self.incoming_events.push(ServerEvent::ShutdownComplete);
+		}
+	}
+
+	/// Report lifecycle transitions to `hooks`, replacing any previous ones
+	pub fn set_lifecycle_hooks(&mut self, hooks: Box<dyn LifecycleHooks>) {
+		self.lifecycle.hooks = Some(hooks);
+	}
+
+	pub fn remove_lifecycle_hooks(&mut self) -> Option<Box<dyn LifecycleHooks>> {
+		self.lifecycle.hooks.take()
+	}
+
+	fn check_empty(&mut self) {
+		if self.lifecycle.draining && !self.lifecycle.empty && self.user_addrs.is_empty() {
+			self.lifecycle.empty = true;
+			self.lifecycle.notify(|hooks| hooks.on_empty());
+		}
+	}
+
+	/// Disconnect from all connected clients and stop listening. Does nothing if the
+	/// Server isn't listening.
 	pub fn shutdown(&mut self) {
-		debug_assert!(self.is_listening(), "Server is not listening");
+		self.shutdown_deadline = None;
 		let Some(io) = &mut self.io else {
 			return;
 		};
@@ -76,6 +235,7 @@ impl Server {
 
 		// stop listening
 		self.io = None;
+		self.polled.clear();
 	}
 
     /// Returns whether or not the Server has initialized correctly and is
@@ -84,6 +244,43 @@ impl Server {
         self.io.is_some()
     }
 
+	/// The receive and send buffer sizes of the listening socket, in bytes, as
+	/// reported by the OS; see `ServerConfig::socket`
+	pub fn socket_buffer_sizes(&self) -> NaiaResult<(usize, usize)> {
+		match &self.io {
+			Some(io) => io.buffer_sizes(),
+			None => Err(io::ErrorKind::NotConnected.into()),
+		}
+	}
+
+	/// Apply a new config, e.g. after tuning it at runtime. Timeouts, intervals,
+	/// congestion control, coalescing and decrypt failure limits are applied to
+	/// existing connections; the ack window, cipher and reassembly limits only to new
+	/// ones, and socket options on the next `listen()`. Lowering `max_users` doesn't
+	/// disconnect anyone. Returns an error and keeps the
+	/// current config if the new one is invalid.
+	pub fn apply_config(&mut self, config: ServerConfig) -> Result<(), ConfigError> {
+		config.validate()?;
+
+		if let Some(io) = &mut self.io {
+			io.set_conditioner(&config.connection.conditioner);
+		}
+		for conn in self.addr_conns.values_mut() {
+			conn.apply_config(&config.connection);
+			// the new intervals may move deadlines earlier
+			self.timeouts.schedule(conn.user_key, conn.timeout_deadline());
+			self.active.insert(conn.user_key);
+		}
+		self.spatial.set_cell_size(config.spatial_cell_size);
+		if self.send_errors.as_ref().map(SendErrorFilter::window) != config.send_error_window {
+			self.send_errors = config.send_error_window.map(SendErrorFilter::new);
+		}
+		self.config = config;
+		Ok(())
+	}
+
+	pub fn config(&self) -> &ServerConfig { &self.config }
+
 	/// Returns conditioner config
 	pub fn conditioner_config(&self) -> &Option<ConditionerConfig> {
 		&self.config.connection.conditioner
@@ -97,51 +294,15 @@ impl Server {
 			return Vec::new();
 		};
 
+		let now = Instant::now();
 		let mut addresses: HashSet<SocketAddr> = HashSet::new();
+		while let Some((address, mut reader)) = self.polled.pop_front() {
+			self.receive_packet(&now, address, &mut reader, &mut addresses);
+		}
 		loop {
 			let io = self.io.as_mut().unwrap();
 			match io.recv_reader() {
-				Ok(Some((address, mut reader))) => {
-					let conn = match self.addr_conns.entry(address) {
-						Entry::Occupied(entry) => entry.into_mut(),
-						Entry::Vacant(entry) => {
-							let Some(user_key) = self.user_id_pool.get() else {
-								// too many connected users; reject request -- best effort
-								let writer = write_reject_response(RejectReason::ServerFull);
-								let _ = io.send_packet(&address, writer.slice());
-
-								continue;
-							};
-							self.user_addrs.insert(user_key, address);
-							entry.insert(Connection::new(
-								&address,
-								&self.config.connection,
-								self.schema.channel_kinds(),
-								&user_key,
-							))
-						}
-					};
-
-					match conn.receive_packet(&mut reader, io, &self.schema) {
-						Ok(ReceiveEvent::Connecting(req, msg)) => {
-							self.incoming_events.push(ServerEvent::Connect {
-								user_key: conn.user_key,
-								addr: address,
-								msg,
-								ctx: ConnectContext { req },
-							});
-						}
-						Ok(ReceiveEvent::Data) => {
-							addresses.insert(address);
-						}
-						Ok(ReceiveEvent::Disconnect) => {
-							let user_key = conn.user_key;
-							self.user_disconnect(&user_key);
-						}
-						Ok(ReceiveEvent::None) => {}
-						Err(e) => self.incoming_events.push(ServerEvent::Error(e)),
-					}
-				}
+				Ok(Some((address, mut reader))) => self.receive_packet(&now, address, &mut reader, &mut addresses),
 				Ok(None) => {
 					// No more packets, break loop
 					break;
@@ -157,66 +318,348 @@ impl Server {
 			self.process_packets(&address);
 		}
 
-		self.handle_timeouts();
+		self.handle_timeouts(&now);
+		self.check_shutdown();
 
         // return all received messages and reset the buffer
-        std::mem::take(&mut self.incoming_events)
+        let events = std::mem::take(&mut self.incoming_events);
+        let events = self.filter_send_errors(events);
+        self.event_seq += events.len() as u64;
+        events
     }
 
-    // Connections
+    /// Like `receive()`, but with each event's sequence number and receive time; see
+    /// `Stamped`
+    pub fn receive_stamped(&mut self) -> Vec<Stamped<ServerEvent>> {
+		let (first_seq, received) = (self.event_seq, Instant::now());
+		Stamped::stamp_all(self.receive(), first_seq, received)
+    }
 
-    /// Accepts an incoming Client User, allowing them to establish a connection
-    /// with the Server
-    pub fn accept_connection(&mut self, user_key: &UserKey, ctx: &ConnectContext) {
+	/// Read packets waiting on the socket without processing them, queueing them
+	/// for the next `receive()`. Call it often to keep the OS receive buffer from
+	/// overflowing, while parsing messages once per tick. Only reading is moved out of
+	/// `receive()`: it still decrypts and parses every queued packet. Once
+	/// `ServerConfig::max_polled_packets` are queued, the oldest are dropped. Returns
+	/// the number of packets read.
+	pub fn poll(&mut self) -> usize {
 		debug_assert!(self.is_listening(), "Server is not listening");
-		let Some(io) = &mut self.io else {
-			return;
+		let Some(io) = self.io.as_mut() else {
+			return 0;
 		};
 
-		let Some(addr) = self.user_addrs.get(user_key) else {
-			debug_assert!(false, "cannot reject connection for unknown user {user_key}");
+		let mut count = 0;
+		loop {
+			match io.recv_reader() {
+				Ok(Some(packet)) => {
+					count += 1;
+					self.polled.push_back(packet);
+					if self.polled.len() > self.config.max_polled_packets {
+						self.polled.pop_front();
+						self.polled_dropped += 1;
+					}
+				}
+				Ok(None) => break,
+				Err(error) => {
+					self.incoming_events.push(ServerEvent::Error(error));
+					break;
+				}
+			}
+		}
+		count
+	}
+
+	fn receive_packet(
+		&mut self, now: &Instant, address: SocketAddr, reader: &mut BitReader,
+		addresses: &mut HashSet<SocketAddr>,
+	) {
+		if self.io.as_ref().is_some_and(|io| io.is_own_addr(&address)) {
+			// never answer our own packets, e.g. when redirected to ourselves
+			self.incoming_events.push(ServerEvent::Error(NaiaError::Loopback(address)));
 			return;
+		}
+		if !self.addr_conns.contains_key(&address) {
+			// read the header once to pick out packets handled without a new connection
+			let checkpoint = reader.save();
+			let handled = match reader.read::<PacketHeader>() {
+				Ok(header) if header.packet_type == PacketType::Migrate => {
+					self.migrate(now, &address, &header, reader);
+					true
+				}
+				Ok(header) if header.packet_type == PacketType::EncryptRequest => self.try_retry(now, &address, reader),
+				_ => false,
+			};
+			if handled {
+				return;
+			}
+			reader.restore(checkpoint);
+		}
+
+		let full = self.is_full();
+		let io = self.io.as_mut().unwrap();
+		let conn = match self.addr_conns.entry(address) {
+			Entry::Occupied(entry) => entry.into_mut(),
+			Entry::Vacant(entry) => {
+				let user_key = match full {
+					true => None,
+					false => self.user_id_pool.get(),
+				};
+				let Some(user_key) = user_key else {
+					// too many connected users; reject request -- best effort
+					let writer = write_reject_response(RejectReason::ServerFull);
+					let _ = io.send_packet(&address, writer.slice());
+
+					return;
+				};
+				self.user_addrs.insert(user_key, address);
+				let conn = entry.insert(Connection::new(
+					&address,
+					&self.config.connection,
+					self.schema.channel_kinds(),
+					&user_key,
+					self.config.max_connect_bytes,
+					self.config.handshake_timeout.map(|timeout| *now + timeout),
+				));
+				self.session_addrs.insert(conn.session_id(), address);
+				self.timeouts.schedule(user_key, conn.timeout_deadline());
+				conn
+			}
 		};
 
-		let Some(conn) = self.addr_conns.get_mut(addr) else {
-			debug_assert!(false, "cannot reject connection for unknown user {user_key} @ {addr}");
-			return;
+		match conn.receive_packet(now, reader, io, &self.schema) {
+			Ok(ReceiveEvent::Connecting(req, msg)) => {
+				self.incoming_events.push(ServerEvent::Connect {
+					user_key: conn.user_key,
+					addr: address,
+					msg,
+					ctx: ConnectContext {
+						req,
+						user_key: conn.user_key,
+						expires: *now + self.config.connection.timeout,
+					},
+				});
+			}
+			Ok(ReceiveEvent::Data) => {
+				addresses.insert(address);
+				self.active.insert(conn.user_key);
+			}
+			Ok(ReceiveEvent::Disconnect) => {
+				let user_key = conn.user_key;
+				self.user_disconnect(&user_key);
+			}
+			Ok(ReceiveEvent::None) => {}
+			Err(e) if matches!(e, NaiaError::MessageTooLarge(_) | NaiaError::ReassemblyFailed)
+				|| matches!(e, NaiaError::ChannelMisuse(_)) && self.config.connection.strict_channels => {
+				self.incoming_events.push(ServerEvent::Error(e));
+				// best effort
+				if let Err(e) = conn.disconnect(io) {
+					warn!("Failed to send disconnect to {:?} @ {address}: {e}", conn.user_key);
+				}
+				let user_key = conn.user_key;
+				self.user_disconnect(&user_key);
+			}
+			// only the messages on the misused channel were dropped
+			Err(e @ NaiaError::ChannelMisuse(_)) => {
+				self.incoming_events.push(ServerEvent::Error(e));
+				addresses.insert(address);
+				self.active.insert(conn.user_key);
+			}
+			Err(e) => {
+				self.incoming_events.push(ServerEvent::Error(e));
+				if !conn.take_decrypt_alarm() {
+					return;
+				}
+
+				let user_key = conn.user_key;
+				self.incoming_events.push(ServerEvent::DecryptFailures { user_key });
+				if self.config.connection.decrypt_failures.as_ref().is_some_and(|config| config.disconnect) {
+					// best effort
+					if let Err(e) = conn.disconnect(io) {
+						warn!("Failed to send disconnect to {user_key:?} @ {address}: {e}");
+					}
+					self.user_disconnect(&user_key);
+				}
+			}
+		}
+	}
+
+    /// Suppress repeated send errors, see `ServerConfig::send_error_window`
+    fn filter_send_errors(&mut self, events: Vec<ServerEvent>) -> Vec<ServerEvent> {
+		let Some(filter) = &mut self.send_errors else {
+			return events;
 		};
 
-		if let Err(e) = conn.accept_connection(&ctx.req, io) {
+		let mut events: Vec<_> = events.into_iter()
+			.filter_map(|event| match event {
+				ServerEvent::Error(NaiaError::Send(e)) => filter.report(e).map(|e| ServerEvent::Error(NaiaError::Send(e))),
+				event => Some(event),
+			})
+			.collect();
+		events.extend(filter.expire().into_iter().map(|e| ServerEvent::Error(NaiaError::Send(e))));
+		events
+    }
+
+    // Connections
+
+    /// Accepts an incoming Client User, allowing them to establish a connection
+    /// with the Server. Clients requesting it are accepted as observers; see
+    /// `accept_observer()`.
+    pub fn accept_connection(&mut self, user_key: &UserKey, ctx: &ConnectContext) -> Result<(), AcceptError> {
+		self.accept(user_key, ctx, ctx.is_observer())
+    }
+
+    /// Accepts an incoming Client User as a receive-only observer, e.g. a spectator,
+    /// whether or not it was requested. Messages from observers are dropped, except
+    /// on channels registered with `SchemaBuilder::add_observer_channel()`, and
+    /// counted by `msg_rx_observer_drop_count()`.
+    pub fn accept_observer(&mut self, user_key: &UserKey, ctx: &ConnectContext) -> Result<(), AcceptError> {
+		self.accept(user_key, ctx, true)
+    }
+
+    /// Like `accept_connection()`, but also sends `message` on channel `C` right after
+    /// the connect response, e.g. spawn data or a message of the day, rather than with
+    /// the next `send()`.
+    pub fn accept_connection_with<C: Channel, M: Message>(
+		&mut self, user_key: &UserKey, ctx: &ConnectContext, message: &M,
+    ) -> Result<(), AcceptError> {
+		debug_assert!(
+			self.schema.channel_kinds().channel(&ChannelKind::of::<C>()).reliable(),
+			"initial messages must be sent on a reliable channel",
+		);
+		self.accept_connection(user_key, ctx)?;
+		self.send_message::<C, M>(user_key, message);
+
+		let io = self.io.as_mut().ok_or(AcceptError::NotListening)?;
+		let conn = self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get_mut(addr))
+			.ok_or(AcceptError::UnknownUser)?;
+		if let Err(e) = conn.flush(&Instant::now(), &self.schema, io) {
+			self.incoming_events.push(ServerEvent::Error(e));
+		}
+		Ok(())
+    }
+
+    fn accept(&mut self, user_key: &UserKey, ctx: &ConnectContext, observer: bool) -> Result<(), AcceptError> {
+		if ctx.user_key != *user_key {
+			return Err(AcceptError::WrongUser);
+		}
+		self.pending_connection(user_key)?;
+		if ctx.is_expired() {
+			return Err(AcceptError::Expired);
+		}
+		if let Some(identity) = ctx.identity() {
+			self.claim_identity(user_key, identity)?;
+		}
+
+		let (io, conn) = self.pending_connection(user_key)?;
+		if let Err(e) = conn.accept_connection(&ctx.req, observer, io) {
 			self.incoming_events.push(ServerEvent::Error(e));
 		}
+		self.active.insert(*user_key);
+		self.start_sync(user_key);
+		if !self.lifecycle.allocated {
+			self.lifecycle.allocated = true;
+			self.lifecycle.notify(|hooks| hooks.on_allocate());
+		}
+		Ok(())
     }
 
-    /// Rejects an incoming Client User, terminating their attempt to establish
-    /// a connection with the Server
-    pub fn reject_connection(&mut self, user_key: &UserKey, reason: RejectReason) {
-		debug_assert!(self.is_listening(), "Server is not listening");
-		let Some(io) = &mut self.io else {
-			return;
-		};
+    /// Apply `ServerConfig::duplicate_identity` to a user being accepted
+    fn claim_identity(&mut self, user_key: &UserKey, identity: &str) -> Result<(), AcceptError> {
+		let policy = self.config.duplicate_identity;
+		if policy == DuplicatePolicy::Allow {
+			return Ok(());
+		}
 
-		let Some(addr) = self.user_addrs.get(user_key) else {
-			debug_assert!(false, "cannot reject connection for unknown user {user_key}");
-			return;
-		};
+		if let Some(existing) = self.identities.get(identity).copied() {
+			if policy == DuplicatePolicy::RejectNew {
+				self.reject_connection(user_key, RejectReason::Duplicate)?;
+				return Err(AcceptError::Duplicate);
+			}
+			self.incoming_events.push(ServerEvent::SessionReplaced { user_key: existing, by: *user_key });
+			self.kick(&existing);
+		}
+		self.identities.insert(identity.to_string(), *user_key);
+		Ok(())
+    }
 
-		let Some(conn) = self.addr_conns.get_mut(addr) else {
-			debug_assert!(false, "cannot reject connection for unknown user {user_key} @ {addr}");
+    fn start_sync(&mut self, user_key: &UserKey) {
+		let Some(provider) = &mut self.snapshot_provider else {
 			return;
 		};
+		if let Some(conn) = self.user_addrs.get(user_key).and_then(|addr| self.addr_conns.get_mut(addr)) {
+			conn.start_sync(provider(user_key).into_boxed_slice());
+		}
+    }
+
+    /// Stream a snapshot to each Client after it is accepted, e.g. the current state
+    /// of the match, before it joins the game. `provider` is called on each accept.
+    /// The user is in the `UserState::Syncing` state until the `UserSynced` event,
+    /// while Clients emit `SyncProgress` events and finally `Synced`. Requires
+    /// `SchemaBuilder::enable_sync()`.
+    pub fn set_snapshot_provider(&mut self, provider: impl FnMut(&UserKey) -> Vec<u8> + Send + 'static) {
+		debug_assert!(self.schema.has_sync(), "the Schema doesn't enable sync");
+		self.snapshot_provider = Some(Box::new(provider));
+    }
+
+    pub fn remove_snapshot_provider(&mut self) {
+		self.snapshot_provider = None;
+    }
 
+    /// Check each received `M` before it is surfaced as a Message event, e.g. to
+    /// clamp or reject implausible inputs. Rejected messages are dropped and counted
+    /// by `msg_rx_reject_count()`. Replaces any previous validator for `M`.
+    pub fn set_message_validator<M: Message>(
+		&mut self, validator: impl FnMut(&UserKey, &M) -> Verdict<M> + Send + 'static,
+    ) {
+		self.validators.insert(MessageKind::of::<M>(), validation::erase(validator));
+    }
+
+    pub fn remove_message_validator<M: Message>(&mut self) {
+		self.validators.remove(&MessageKind::of::<M>());
+    }
+
+    /// Record the authoritative hash of the state after `tick`. Clients reporting a
+    /// different hash for the same tick with `Client::send_checksum()` emit a
+    /// `Desync` event, whichever side reports first. Requires
+    /// `SchemaBuilder::enable_checksums()`.
+    pub fn set_checksum(&mut self, tick: u32, hash: u64) {
+		debug_assert!(self.schema.has_checksums(), "the Schema doesn't enable checksums");
+		for user_key in self.checksums.record(tick, hash) {
+			self.incoming_events.push(ServerEvent::Desync { user_key, tick });
+		}
+    }
+
+    /// Rejects an incoming Client User, terminating their attempt to establish
+    /// a connection with the Server. Use `user_disconnect()` for accepted users.
+    pub fn reject_connection(&mut self, user_key: &UserKey, reason: RejectReason) -> Result<(), AcceptError> {
+		let (io, conn) = self.pending_connection(user_key)?;
 		if let Err(e) = conn.reject_connection(io, reason) {
 			self.incoming_events.push(ServerEvent::Error(e));
 		}
 
         self.user_delete(user_key);
+		Ok(())
+    }
+
+    /// The connection of a user which is yet to be accepted
+    fn pending_connection(&mut self, user_key: &UserKey) -> Result<(&mut Io, &mut Connection), AcceptError> {
+		debug_assert!(self.is_listening(), "Server is not listening");
+		let io = self.io.as_mut().ok_or(AcceptError::NotListening)?;
+		let conn = self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get_mut(addr))
+			.ok_or(AcceptError::UnknownUser)?;
+		match conn.user_state() {
+			UserState::Connected => Err(AcceptError::AlreadyAccepted),
+			_ => Ok((io, conn)),
+		}
     }
 
     // Messages
 
     /// Queues up an Message to be sent to the Client associated with a given
-    /// UserKey
+    /// UserKey. Channels which don't allow server-to-client messages emit a
+    /// `NaiaError::ChannelMisuse` error event instead, and messages exceeding the
+    /// Schema's maximum message size a `NaiaError::MessageTooLarge` error event.
     pub fn send_message<C: Channel, M: Message>(&mut self, user_key: &UserKey, message: &M) {
         let cloned_message = M::clone_box(message);
         self.send_message_inner(user_key, &ChannelKind::of::<C>(), cloned_message);
@@ -224,35 +667,42 @@ impl Server {
 
     /// Queues up an Message to be sent to the Client associated with a given
     /// UserKey
-    fn send_message_inner(
+    pub(crate) fn send_message_inner(
         &mut self,
         user_key: &UserKey,
         channel_kind: &ChannelKind,
         message_box: Box<dyn Message>,
     ) {
-        let channel_settings = self.schema.channel_kinds().channel(channel_kind);
-		debug_assert!(channel_settings.can_send_to_client(), "Cannot send message to Client on this Channel");
-        if !channel_settings.can_send_to_client() {
+        if !self.can_send_to_client(channel_kind) {
 			return;
         }
 
         if let Some(addr) = self.user_addrs.get(user_key) {
             if let Some(connection) = self.addr_conns.get_mut(addr) {
                 let msg = MessageContainer::from_write(message_box);
-                connection.queue_message(&self.schema, channel_kind, msg);
+                if let Err(e) = connection.queue_message(&self.schema, channel_kind, msg) {
+					self.incoming_events.push(ServerEvent::Error(e));
+				}
+				self.active.insert(*user_key);
             }
         }
     }
 
-    /// Sends a message to all connected users using a given channel
+    /// Sends a message to all connected users using a given channel. Channels which
+    /// don't allow server-to-client messages emit a `NaiaError::ChannelMisuse` error
+    /// event instead.
     pub fn broadcast_message<C: Channel, M: Message>(&mut self, message: &M) {
         let cloned_message = M::clone_box(message);
         self.broadcast_message_inner(&ChannelKind::of::<C>(), cloned_message);
     }
 
-    fn broadcast_message_inner(
+    pub(crate) fn broadcast_message_inner(
 		&mut self, channel_kind: &ChannelKind, message_box: Box<dyn Message>,
     ) {
+		if !self.can_send_to_client(channel_kind) {
+			return;
+		}
+
 		let connected_users: Vec<_> = self.addr_conns.iter()
 			.filter(|(_, conn)| conn.is_connected())
 			.map(|(_, conn)| conn.user_key)
@@ -262,6 +712,54 @@ impl Server {
 		}
     }
 
+    /// Sends a message to all connected users within `radius` of `position`, e.g. for
+    /// proximity chat, using the positions set with `set_user_position()`. Users
+    /// without a position are skipped.
+    pub fn broadcast_near<C: Channel, M: Message>(&mut self, position: [f32; 2], radius: f32, message: &M) {
+		let channel_kind = ChannelKind::of::<C>();
+		if !self.can_send_to_client(&channel_kind) {
+			return;
+		}
+
+		let message_box = M::clone_box(message);
+		let users: Vec<_> = self.spatial.query(position, radius).collect();
+		for user_key in users {
+			let connected = self.user_addrs.get(&user_key)
+				.and_then(|addr| self.addr_conns.get(addr))
+				.is_some_and(Connection::is_connected);
+			if connected {
+				self.send_message_inner(&user_key, &channel_kind, message_box.clone());
+			}
+		}
+    }
+
+    /// Set the position of a user for `broadcast_near()`. 3D games may pass the
+    /// horizontal coordinates. Positions are forgotten once the user disconnects.
+    pub fn set_user_position(&mut self, user_key: &UserKey, position: [f32; 2]) {
+		if !self.user_addrs.contains_key(user_key) {
+			debug_assert!(false, "cannot set position of unknown user {user_key}");
+			return;
+		}
+		self.spatial.set(*user_key, position);
+    }
+
+    pub fn clear_user_position(&mut self, user_key: &UserKey) { self.spatial.remove(user_key) }
+
+    pub fn user_position(&self, user_key: &UserKey) -> Option<[f32; 2]> { self.spatial.position(user_key) }
+
+    /// Returns whether messages may be sent to Clients on the given channel, emitting
+    /// an error event if not
+    fn can_send_to_client(&mut self, channel_kind: &ChannelKind) -> bool {
+		let channel_kinds = self.schema.channel_kinds();
+		if channel_kinds.channel(channel_kind).can_send_to_client() {
+			return true;
+		}
+
+		let name = channel_kinds.name(channel_kind).to_string();
+		self.incoming_events.push(ServerEvent::Error(NaiaError::ChannelMisuse(name)));
+		false
+    }
+
     // Updates
 
     /// Sends all update messages to all Clients. If you don't call this
@@ -275,19 +773,236 @@ impl Server {
 
         let now = Instant::now();
 
-        // loop through all connections, send packet
-        let mut user_addresses: Vec<_> = self.addr_conns.keys().copied().collect();
+        // visit the connections with messages to send, and the idle ones due for a ping
+        // or heartbeat, rather than every connection
+		let mut user_keys: Vec<_> = std::mem::take(&mut self.active).into_iter().collect();
+		for user_key in self.maintenance.expire(&now) {
+			if let Some(conn) = self.user_addrs.get(&user_key).and_then(|addr| self.addr_conns.get_mut(addr)) {
+				conn.maintenance_at = None;
+				user_keys.push(user_key);
+			}
+		}
+		user_keys.sort_unstable();
+		user_keys.dedup();
 
         // shuffle order of connections in order to avoid priority among users
-        fastrand::shuffle(&mut user_addresses);
+        fastrand::shuffle(&mut user_keys);
 
-		for addr in user_addresses {
-			let conn = self.addr_conns.get_mut(&addr).unwrap();
+		for user_key in user_keys {
+			let Some(conn) = self.user_addrs.get(&user_key).and_then(|addr| self.addr_conns.get_mut(addr)) else {
+				continue;
+			};
+			conn.set_bandwidth_profiler(self.bandwidth_window);
 
 			if let Err(e) = conn.send(&now, &self.schema, io) {
 				self.incoming_events.push(ServerEvent::Error(e));
 			}
+
+			if conn.timed_out(&now) {
+				// resends exhausted; disconnect on the next `receive()`
+				self.timeouts.schedule(user_key, now);
+			}
+			if !conn.is_idle() {
+				self.active.insert(user_key);
+			} else if conn.is_connected() {
+				let due = conn.next_maintenance();
+				if conn.maintenance_at != Some(due) {
+					conn.maintenance_at = Some(due);
+					self.maintenance.schedule(user_key, due);
+				}
+			}
+
+			match conn.take_link_change() {
+				Some(LinkQuality::Bad) => self.incoming_events.push(ServerEvent::LinkDegraded {
+					user_key, loss: conn.packet_loss(), rtt_ms: conn.rtt_ms(),
+				}),
+				Some(LinkQuality::Good) => self.incoming_events.push(ServerEvent::LinkRecovered { user_key }),
+				None => (),
+			}
         }
+
+		self.report_metrics();
+		self.lifecycle.notify(|hooks| hooks.on_update());
+    }
+
+    /// Hold back messages sent to the given User on channel `C`, e.g. while its Client
+    /// is loading a level and shouldn't receive gameplay updates yet. Depending on the
+    /// channel's `PausePolicy`, messages are either buffered until `resume_channel()`
+    /// or dropped.
+    pub fn pause_channel<C: Channel>(&mut self, user_key: &UserKey) {
+		let channel_kind = ChannelKind::of::<C>();
+		if self.can_send_to_client(&channel_kind) {
+			self.pause_channels(user_key, &[channel_kind]);
+		}
+    }
+
+    /// Like `pause_channel()`, for all channels of `group` the Server sends on; see
+    /// `SchemaBuilder::channel_group()`
+    pub fn pause_group(&mut self, user_key: &UserKey, group: &str) {
+		let channel_kinds = self.sent_group(group);
+		self.pause_channels(user_key, &channel_kinds);
+    }
+
+    fn pause_channels(&mut self, user_key: &UserKey, channel_kinds: &[ChannelKind]) {
+		let Some(conn) = self.user_addrs.get(user_key).and_then(|addr| self.addr_conns.get_mut(addr)) else {
+			debug_assert!(false, "cannot pause channel for unknown user {user_key}");
+			return;
+		};
+		for channel_kind in channel_kinds {
+			conn.pause_channel(channel_kind);
+		}
+    }
+
+    /// Resume channel `C` for the given User, queueing any buffered messages in order
+    pub fn resume_channel<C: Channel>(&mut self, user_key: &UserKey) {
+		self.resume_channels(user_key, &[ChannelKind::of::<C>()]);
+    }
+
+    /// Resume all channels of `group` for the given User
+    pub fn resume_group(&mut self, user_key: &UserKey, group: &str) {
+		let channel_kinds = self.sent_group(group);
+		self.resume_channels(user_key, &channel_kinds);
+    }
+
+    fn resume_channels(&mut self, user_key: &UserKey, channel_kinds: &[ChannelKind]) {
+		let Some(conn) = self.user_addrs.get(user_key).and_then(|addr| self.addr_conns.get_mut(addr)) else {
+			debug_assert!(false, "cannot resume channel for unknown user {user_key}");
+			return;
+		};
+		for channel_kind in channel_kinds {
+			if let Err(e) = conn.resume_channel(&self.schema, channel_kind) {
+				self.incoming_events.push(ServerEvent::Error(e));
+			}
+		}
+		self.active.insert(*user_key);
+    }
+
+    /// Returns whether channel `C` is paused for the given User
+    pub fn is_channel_paused<C: Channel>(&self, user_key: &UserKey) -> bool {
+		self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get(addr))
+			.is_some_and(|conn| conn.is_channel_paused(&ChannelKind::of::<C>()))
+    }
+
+    /// Returns whether all channels of `group` the Server sends on are paused for the
+    /// given User
+    pub fn is_group_paused(&self, user_key: &UserKey, group: &str) -> bool {
+		let channel_kinds = self.sent_group(group);
+		!channel_kinds.is_empty() && self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get(addr))
+			.is_some_and(|conn| channel_kinds.iter().all(|kind| conn.is_channel_paused(kind)))
+    }
+
+    /// The channels of `group` which can send to Clients
+    fn sent_group(&self, group: &str) -> Vec<ChannelKind> {
+		let channel_kinds = self.schema.channel_kinds();
+		let mut group = self.schema.channel_group(group);
+		group.retain(|kind| channel_kinds.channel(kind).can_send_to_client());
+		group
+    }
+
+    /// Sets the minimum interval between packet flushes to the given User's Client, so
+    /// that e.g. spectators or far-away players are updated less often than everyone
+    /// else. Messages queued in between are accumulated and sent together. Defaults to
+    /// `Duration::ZERO`, which flushes on every call to `send()`.
+    pub fn set_send_interval(&mut self, user_key: &UserKey, interval: Duration) {
+		let Some(addr) = self.user_addrs.get(user_key) else {
+			debug_assert!(false, "cannot set send interval for unknown user {user_key}");
+			return;
+		};
+
+		if let Some(conn) = self.addr_conns.get_mut(addr) {
+			conn.set_send_interval(interval);
+		}
+    }
+
+    /// Report the performance counters, rates, and per-user round trip times to the
+    /// given sink every `interval`, from within `send()`. Replaces any previous sink.
+    /// Counters are reported as returned by their getters, i.e. unaffected by
+    /// `reset_metrics()`.
+    pub fn set_metrics_sink(&mut self, sink: Box<dyn MetricsSink>, interval: Duration) {
+		self.metrics_reporter = Some(MetricsReporter::new(sink, interval));
+    }
+
+    /// Stop reporting metrics, returning the current sink
+    pub fn remove_metrics_sink(&mut self) -> Option<Box<dyn MetricsSink>> {
+		self.metrics_reporter.take().map(MetricsReporter::into_sink)
+    }
+
+    /// Attribute the messages sent to each user to their channel and message kind over
+    /// a sliding `window`, see `bandwidth_report()`
+    pub fn enable_bandwidth_profiler(&mut self, window: Duration) {
+		self.set_bandwidth_profiler(Some(window));
+    }
+
+    pub fn disable_bandwidth_profiler(&mut self) {
+		self.set_bandwidth_profiler(None);
+    }
+
+    fn set_bandwidth_profiler(&mut self, window: Option<Duration>) {
+		self.bandwidth_window = window;
+		for conn in self.addr_conns.values_mut() {
+			conn.set_bandwidth_profiler(window);
+		}
+    }
+
+    /// The outgoing traffic of all users, ranked by channel and message kind; empty
+    /// unless the profiler is enabled. Its `Display` impl prints a table.
+    pub fn bandwidth_report(&self) -> BandwidthReport {
+		let mut report = BandwidthReport::default();
+		for conn_report in self.connections().filter_map(|conn| conn.bandwidth_report(&self.schema)) {
+			report.merge(conn_report);
+		}
+		report
+    }
+
+    /// The outgoing traffic of the given User, if the profiler is enabled
+    pub fn user_bandwidth_report(&self, user_key: &UserKey) -> Option<BandwidthReport> {
+		self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get(addr))
+			.and_then(|conn| conn.bandwidth_report(&self.schema))
+    }
+
+    fn report_metrics(&mut self) {
+		if !self.metrics_reporter.as_mut().is_some_and(MetricsReporter::due) {
+			return;
+		}
+
+		let snapshot = self.counters_snapshot();
+		let users: Vec<_> = self.users()
+			.map(|user| (user.key().to_string(), user.rtt_ms(), user.packet_loss()))
+			.collect();
+		let mut gauges: Vec<(&str, &[(&str, &str)], f64)> = vec![
+			("users", &[], self.users_count() as f64),
+			("bytes_rx_per_sec", &[], self.bytes_rx_per_sec() as f64),
+			("bytes_tx_per_sec", &[], self.bytes_tx_per_sec() as f64),
+			("pkt_rx_per_sec", &[], self.pkt_rx_per_sec() as f64),
+			("pkt_tx_per_sec", &[], self.pkt_tx_per_sec() as f64),
+		];
+		let labels: Vec<[(&str, &str); 1]> = users.iter()
+			.map(|(user_key, _, _)| [("user", user_key.as_str())])
+			.collect();
+		for ((_, rtt_ms, loss), labels) in users.iter().zip(&labels) {
+			gauges.push(("rtt_ms", labels, *rtt_ms as f64));
+			gauges.push(("packet_loss", labels, *loss as f64));
+		}
+
+		self.metrics_reporter.as_mut().unwrap().report(&snapshot, &gauges);
+    }
+
+    /// Register middleware to observe (or modify) the packets exchanged with the given
+    /// User's Client. Middleware is dropped along with the connection.
+    pub fn add_middleware(
+		&mut self, user_key: &UserKey, middleware: Box<dyn ConnectionMiddleware>,
+	) {
+		let Some(addr) = self.user_addrs.get(user_key) else {
+			debug_assert!(false, "cannot add middleware for unknown user {user_key}");
+			return;
+		};
+
+		if let Some(conn) = self.addr_conns.get_mut(addr) {
+			conn.add_middleware(middleware);
+		}
     }
 
     // Users
@@ -297,11 +1012,24 @@ impl Server {
         self.user_addrs.contains_key(user_key)
     }
 
-    /// Return a list of all currently connected Users' keys
+    /// Return a list of all currently connected Users' keys; see `users()` to iterate
+    /// without allocating
     pub fn user_keys(&self) -> Vec<UserKey> {
 		self.user_addrs.keys().copied().collect()
     }
 
+    /// Iterate all Users, including those still handshaking, in no particular order
+    pub fn users(&self) -> impl Iterator<Item = UserRef<'_>> + '_ {
+		self.connections().map(UserRef::new)
+    }
+
+    /// Get a view of the given User, if it exists
+    pub fn user(&self, user_key: &UserKey) -> Option<UserRef<'_>> {
+		self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get(addr))
+			.map(UserRef::new)
+    }
+
     /// Get the number of Users currently connected
     pub fn users_count(&self) -> usize {
         self.user_addrs.len()
@@ -325,6 +1053,60 @@ impl Server {
 			.map(Connection::jitter_ms)
     }
 
+    /// Gets the fraction of packets recently lost in transit to the given User's
+    /// Client
+    pub fn packet_loss(&self, user_key: &UserKey) -> Option<f32> {
+		debug_assert!(self.user_addrs.contains_key(user_key));
+		self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get(addr))
+			.map(Connection::packet_loss)
+    }
+
+    /// Gets the link quality to the given User's Client, as determined by congestion
+    /// control. Applications may want to reduce update fidelity when this is `Bad`.
+    pub fn link_quality(&self, user_key: &UserKey) -> Option<LinkQuality> {
+		debug_assert!(self.user_addrs.contains_key(user_key));
+		self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get(addr))
+			.map(Connection::link_quality)
+    }
+
+    /// Gets the backlog of messages queued for the given User's Client, or awaiting
+    /// its acknowledgement, e.g. to skip optional updates while it grows
+    pub fn outgoing_queue_depth(&self, user_key: &UserKey) -> Option<QueueDepth> {
+		debug_assert!(self.user_addrs.contains_key(user_key));
+		self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get(addr))
+			.map(Connection::queue_depth)
+    }
+
+    /// Gets the counts of packets received from the given User's Client, by type
+    pub fn connection_stats(&self, user_key: &UserKey) -> Option<ConnectionStats> {
+		debug_assert!(self.user_addrs.contains_key(user_key));
+		self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get(addr))
+			.map(Connection::stats)
+    }
+
+    /// Gets how long each step of the handshake with the given User's Client took,
+    /// timed from its first packet. The connect step includes the time until the
+    /// application accepted it, e.g. waiting on an authentication backend.
+    pub fn handshake_timing(&self, user_key: &UserKey) -> Option<HandshakeTiming> {
+		debug_assert!(self.user_addrs.contains_key(user_key));
+		self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get(addr))
+			.map(Connection::handshake_timing)
+    }
+
+    /// Gets the cipher negotiated with the given User's Client, once keys were
+    /// exchanged
+    pub fn cipher(&self, user_key: &UserKey) -> Option<CipherKind> {
+		debug_assert!(self.user_addrs.contains_key(user_key));
+		self.user_addrs.get(user_key)
+			.and_then(|addr| self.addr_conns.get(addr))
+			.and_then(Connection::cipher)
+    }
+
     // Crate-Public methods
 
     //// Users
@@ -339,19 +1121,274 @@ impl Server {
         self.incoming_events.push(ServerEvent::Disconnect { user_key:*user_key, addr });
     }
 
+	/// Disconnect a user, notifying its Client rather than letting it time out. Does
+	/// nothing if the user doesn't exist.
+	pub fn kick(&mut self, user_key: &UserKey) {
+		let Some(addr) = self.user_addrs.get(user_key) else {
+			return;
+		};
+		if let (Some(io), Some(conn)) = (&mut self.io, self.addr_conns.get_mut(addr)) {
+			if let Err(e) = conn.disconnect(io) {
+				warn!("Failed to send disconnect to {user_key:?} @ {addr}: {e}");
+			}
+		}
+		self.user_disconnect(user_key);
+	}
+
     fn user_delete(&mut self, user_key: &UserKey) -> SocketAddr {
         let Some(addr) = self.user_addrs.remove(user_key) else {
             panic!("Attempting to delete non-existant user!");
         };
 
-        self.addr_conns.remove(&addr);
+        if let Some(conn) = self.addr_conns.remove(&addr) {
+			let session_id = conn.session_id();
+			if self.session_addrs.get(&session_id) == Some(&addr) {
+				self.session_addrs.remove(&session_id);
+			}
+		}
+		self.active.remove(user_key);
 		self.user_id_pool.put(*user_key);
+		self.spatial.remove(user_key);
+		self.checksums.remove_user(user_key);
+		self.identities.retain(|_, key| key != user_key);
+		self.check_empty();
 
         return addr;
     }
 
+    // Handoff
+
+    /// Serialize an established user's connection, so another Server with the same
+    /// Schema can continue it with `import_session()`, e.g. for zone transfers. The
+    /// user stays connected to this Server, but nothing is sent to it anymore, as the
+    /// other Server continues with the same encryption key; follow up with
+    /// `redirect()`. Messages queued after the export are not included. The output
+    /// contains the connection's encryption key, so it must be transferred securely.
+    pub fn export_session(&mut self, user_key: &UserKey) -> NaiaResult<Box<[u8]>> {
+		let Some(conn) = self.user_addrs.get(user_key).and_then(|addr| self.addr_conns.get_mut(addr)) else {
+			return Err(format!("cannot export session of unknown user {user_key}").into());
+		};
+
+		let mut writer = BitVecWriter::new();
+		conn.export(&self.schema, &mut writer)?;
+		conn.mark_exported();
+		Ok(writer.into_bytes())
+    }
+
+    /// Continue a connection exported by another Server with `export_session()`. The
+    /// user is connected immediately, without a Connect event, and resumes sending
+    /// once its Client reaches this Server, e.g. after `redirect()`. Returns the
+    /// UserKey assigned to the user by this Server.
+    pub fn import_session(&mut self, session: &[u8]) -> NaiaResult<UserKey> {
+		let user_key = match self.is_full() {
+			true => None,
+			false => self.user_id_pool.get(),
+		};
+		let Some(user_key) = user_key else {
+			return Err("cannot import session: too many connected users".into());
+		};
+
+		let mut reader = BitReader::from_slice(session);
+		let conn = Connection::import(&self.config.connection, &self.schema, &user_key, &mut reader)
+			.and_then(|conn| {
+				let duplicate = self.addr_conns.contains_key(conn.address())
+					|| self.session_addrs.contains_key(&conn.session_id());
+				match duplicate {
+					true => Err("cannot import session: already connected".into()),
+					false => Ok(conn),
+				}
+			});
+		let conn = match conn {
+			Ok(conn) => conn,
+			Err(e) => {
+				self.user_id_pool.put(user_key);
+				return Err(e);
+			}
+		};
+
+		self.user_addrs.insert(user_key, *conn.address());
+		self.session_addrs.insert(conn.session_id(), *conn.address());
+		self.timeouts.schedule(user_key, conn.timeout_deadline());
+		self.active.insert(user_key);
+		self.addr_conns.insert(*conn.address(), conn);
+		Ok(user_key)
+    }
+
+    /// Tell an established user's Client to reconnect to the Server at `addr`, which
+    /// should have imported the user's session with `import_session()`, then
+    /// disconnect the user from this Server
+    pub fn redirect(&mut self, user_key: &UserKey, addr: SocketAddr) {
+		debug_assert!(self.is_listening(), "Server is not listening");
+		let Some(io) = &mut self.io else {
+			return;
+		};
+
+		let Some(old_addr) = self.user_addrs.get(user_key) else {
+			debug_assert!(false, "cannot redirect unknown user {user_key}");
+			return;
+		};
+
+		let Some(conn) = self.addr_conns.get_mut(old_addr) else {
+			debug_assert!(false, "cannot redirect unknown user {user_key} @ {old_addr}");
+			return;
+		};
+
+		if let Err(e) = conn.redirect(&addr, io) {
+			self.incoming_events.push(ServerEvent::Error(e));
+		}
+
+		self.user_disconnect(user_key);
+    }
+
+    /// Serialize the sessions of all established users, and of timed out users which
+    /// may still be resumed, so a restarted Server can accept their resumption after
+    /// `import_resumable()`. Established users get the full `resume_window`. The
+    /// connections keep using their keys after the export, so resumed connections
+    /// switch to new keys derived from them. The output contains the connections'
+    /// encryption keys, so it must be stored securely.
+    pub fn export_resumable(&self) -> NaiaResult<Box<[u8]>> {
+		let mut writer = BitVecWriter::new();
+		let expires = SystemTime::now() + self.config.resume_window;
+		for conn in self.connections().filter(|conn| conn.is_connected()) {
+			let mut session = BitVecWriter::new();
+			conn.export(&self.schema, &mut session)?;
+
+			true.ser(&mut writer);
+			let resumable = Resumable { user_key: conn.user_key, session: session.into_bytes(), expires };
+			resumable.ser(conn.session_id(), &mut writer);
+		}
+		for (session_id, resumable) in &self.resumable {
+			true.ser(&mut writer);
+			resumable.ser(*session_id, &mut writer);
+		}
+		false.ser(&mut writer);
+
+		Ok(writer.into_bytes())
+    }
+
+    /// Accept the resumption of the sessions written by `export_resumable()`, until
+    /// they expire (including the time spent in between). Sessions whose UserKey or id
+    /// is already in use are skipped. Returns the number of sessions imported.
+    pub fn import_resumable(&mut self, sessions: &[u8]) -> NaiaResult<usize> {
+		let now = SystemTime::now();
+		let mut reader = BitReader::from_slice(sessions);
+		let mut count = 0;
+		while bool::de(&mut reader)? {
+			let (session_id, resumable) = Resumable::de(&mut reader)?;
+			let skip = resumable.expires <= now
+				|| self.resumable.contains_key(&session_id)
+				|| self.session_addrs.contains_key(&session_id)
+				|| !self.user_id_pool.take(resumable.user_key);
+			if skip {
+				continue;
+			}
+
+			self.resumable.insert(session_id, resumable);
+			count += 1;
+		}
+
+		Ok(count)
+    }
+
     // Private methods
 
+    /// Handle a Migrate packet from an unknown address, moving the matching connection
+    /// to `address`, or resuming the matching resumable session there
+    fn migrate(&mut self, now: &Instant, address: &SocketAddr, header: &PacketHeader, reader: &mut BitReader) {
+		// drop malformed or unknown Migrate packets without creating a connection
+		let Ok(req) = packet::Migrate::de(reader) else {
+			return;
+		};
+		let Some(old_addr) = self.session_addrs.get(&req.session_id).copied() else {
+			self.try_resume(now, address, header, &req);
+			return;
+		};
+
+		let io = self.io.as_mut().unwrap();
+		let mut conn = self.addr_conns.remove(&old_addr).unwrap();
+		let result = conn.migrate(now, address, header, &req, io);
+
+		// re-key by the (possibly) new address
+		let new_addr = *conn.address();
+		self.user_addrs.insert(conn.user_key, new_addr);
+		self.session_addrs.insert(req.session_id, new_addr);
+		self.addr_conns.insert(new_addr, conn);
+
+		if let Err(e) = result {
+			self.incoming_events.push(ServerEvent::Error(e));
+		}
+    }
+
+    /// Answer an EncryptRequest from a new address with a HandshakeRetry, rather than
+    /// creating a connection, unless it's padded per
+    /// `ConnectionConfig::handshake_padding` or echoes a valid cookie. Returns whether
+    /// the packet was answered.
+    fn try_retry(&mut self, now: &Instant, address: &SocketAddr, reader: &mut BitReader) -> bool {
+		// malformed requests are reported by the connection
+		let Ok(req) = packet::EncryptRequest::de(reader) else {
+			return false;
+		};
+		let Ok(padding) = packet::EncryptRequest::read_padding(reader) else {
+			return false;
+		};
+		if req.cookie.is_some_and(|cookie| self.cookies.check(now, address, &cookie)) {
+			return false;
+		}
+		if let HandshakePadding::Bytes(min) = self.config.connection.handshake_padding {
+			if padding >= usize::from(min) {
+				return false;
+			}
+		}
+
+		// best effort
+		let writer = write_retry(self.cookies.issue(now, address));
+		let _ = self.io.as_mut().unwrap().send_packet(address, writer.slice());
+		true
+	}
+
+    /// Restore the resumable session named by an authentic Migrate packet at
+    /// `address`
+    fn try_resume(
+		&mut self, now: &Instant, address: &SocketAddr, header: &PacketHeader, req: &packet::Migrate,
+    ) {
+		let Some(resumable) = self.resumable.remove(&req.session_id) else {
+			return;
+		};
+		if resumable.expires <= SystemTime::now() {
+			self.user_id_pool.put(resumable.user_key);
+			return;
+		}
+
+		let io = self.io.as_mut().unwrap();
+		let mut reader = BitReader::from_slice(&resumable.session);
+		let result = Connection::import(
+			&self.config.connection, &self.schema, &resumable.user_key, &mut reader,
+		).and_then(|mut conn| {
+			let migrated = conn.migrate(now, address, header, req, io)?;
+			Ok(migrated.then_some(conn))
+		});
+
+		match result {
+			Ok(Some(conn)) => {
+				let user_key = conn.user_key;
+				self.user_addrs.insert(user_key, *address);
+				self.session_addrs.insert(req.session_id, *address);
+				self.timeouts.schedule(user_key, conn.timeout_deadline());
+				self.active.insert(user_key);
+				self.addr_conns.insert(*address, conn);
+				self.incoming_events.push(ServerEvent::Resume { user_key, addr: *address });
+			}
+			// forged or replayed; keep waiting for the real Client
+			Ok(None) => {
+				self.resumable.insert(req.session_id, resumable);
+			}
+			Err(e) => {
+				self.user_id_pool.put(resumable.user_key);
+				self.incoming_events.push(ServerEvent::Error(e));
+			}
+		}
+    }
+
     fn process_packets(&mut self, address: &SocketAddr) {
         // Packets requiring established connection
 		let Some(connection) = self.addr_conns.get_mut(address) else {
@@ -359,35 +1396,216 @@ impl Server {
 		};
 
 		let user_key = connection.user_key;
+		let mut synced = false;
+		let mut rejected = 0;
 		for msg in connection.receive_messages() {
-			self.incoming_events.push(ServerEvent::Message { user_key, msg });
+			if msg.is::<SyncDone>() {
+				synced = true;
+				continue;
+			}
+			let msg = match msg.downcast::<StateChecksum>() {
+				Ok(StateChecksum { tick, hash }) => {
+					if self.checksums.check(user_key, tick, hash) {
+						self.incoming_events.push(ServerEvent::Desync { user_key, tick });
+					}
+					continue;
+				}
+				Err(msg) => msg,
+			};
+			let msg = match self.validators.get_mut(&msg.kind()) {
+				Some(validator) => validator(&user_key, msg),
+				None => Some(msg),
+			};
+			match msg {
+				Some(msg) => self.incoming_events.push(ServerEvent::Message { user_key, msg }),
+				None => rejected += 1,
+			}
+		}
+		connection.msg_rx_reject_count += rejected;
+
+		let throttled = connection.take_throttled();
+		if !throttled.is_empty() {
+			connection.throttle_strikes += 1;
+		}
+		let strikes = connection.throttle_strikes;
+		for (channel, dropped) in throttled {
+			self.incoming_events.push(ServerEvent::Throttled { user_key, channel, dropped });
+		}
+
+		if synced && connection.finish_sync() {
+			self.incoming_events.push(ServerEvent::UserSynced { user_key });
+		}
+
+		if self.config.throttle_disconnect.is_some_and(|max| strikes >= max) {
+			self.kick(&user_key);
 		}
     }
 
-    fn handle_timeouts(&mut self) {
+    fn handle_timeouts(&mut self, now: &Instant) {
+		// only check the users whose deadline passed, rescheduling those heard from since
 		let mut user_disconnects: Vec<UserKey> = Vec::new();
-
-		for connection in self.connections() {
-			// user disconnects
-			if connection.timed_out() {
-				user_disconnects.push(connection.user_key);
+		for user_key in self.timeouts.expire(now) {
+			let Some(conn) = self.user_addrs.get(&user_key).and_then(|addr| self.addr_conns.get(addr)) else {
+				continue;
+			};
+			if conn.timed_out(now) {
+				user_disconnects.push(user_key);
+			} else {
+				self.timeouts.schedule(user_key, conn.timeout_deadline());
 			}
 		}
+		user_disconnects.sort_unstable();
+		user_disconnects.dedup();
 
 		for user_key in user_disconnects {
+			let progress = self.user_addrs.get(&user_key)
+				.and_then(|addr| self.addr_conns.get(addr))
+				.and_then(Connection::handshake_progress);
+			if let Some(progress) = progress {
+				let addr = self.user_delete(&user_key);
+				self.incoming_events.push(ServerEvent::ConnectTimeout { addr, progress });
+				if progress == HandshakeProgress::ConnectReceived {
+					self.incoming_events.push(ServerEvent::Disconnect { user_key, addr });
+				}
+				continue;
+			}
+
+			let resumable = self.resumable_session(&user_key);
 			self.user_disconnect(&user_key);
+
+			if let Some((session_id, resumable)) = resumable {
+				// keep the UserKey reserved until the session expires
+				self.user_id_pool.take(user_key);
+				self.resumable.insert(session_id, resumable);
+			}
+		}
+
+		let now = SystemTime::now();
+		let user_id_pool = &mut self.user_id_pool;
+		self.resumable.retain(|_, resumable| {
+			let expired = resumable.expires <= now;
+			if expired {
+				user_id_pool.put(resumable.user_key);
+			}
+			!expired
+		});
+    }
+
+    /// Export an established user's session for resumption, if enabled
+    fn resumable_session(&self, user_key: &UserKey) -> Option<(SessionId, Resumable)> {
+		if self.config.resume_window.is_zero() {
+			return None;
 		}
+
+		let conn = self.user_addrs.get(user_key).and_then(|addr| self.addr_conns.get(addr))?;
+		let mut writer = BitVecWriter::new();
+		conn.export(&self.schema, &mut writer).ok()?;
+
+		Some((conn.session_id(), Resumable {
+			user_key: *user_key,
+			session: writer.into_bytes(),
+			expires: SystemTime::now() + self.config.resume_window,
+		}))
     }
 
 	// performance counters
 
 	pub fn bytes_rx(&self) -> u64 { self.io.as_ref().map(Io::bytes_rx).unwrap_or(0) }
 	pub fn bytes_tx(&self) -> u64 { self.io.as_ref().map(Io::bytes_tx).unwrap_or(0) }
+	pub fn frag_rx_expired_count(&self) -> u64 { self.connections().map(Connection::frag_rx_expired_count).sum() }
+	pub fn frag_rx_refused_count(&self) -> u64 { self.connections().map(Connection::frag_rx_refused_count).sum() }
 	pub fn msg_rx_count(&self) -> u64 { self.connections().map(Connection::msg_rx_count).sum() }
 	pub fn msg_rx_drop_count(&self) -> u64 { self.connections().map(Connection::msg_rx_drop_count).sum() }
 	pub fn msg_rx_miss_count(&self) -> u64 { self.connections().map(Connection::msg_rx_miss_count).sum() }
+	pub fn msg_rx_observer_drop_count(&self) -> u64 { self.connections().map(Connection::msg_rx_observer_drop_count).sum() }
+	pub fn msg_rx_reject_count(&self) -> u64 { self.connections().map(|conn| conn.msg_rx_reject_count).sum() }
+	pub fn msg_rx_throttle_count(&self) -> u64 { self.connections().map(Connection::msg_rx_throttle_count).sum() }
 	pub fn msg_tx_count(&self) -> u64 { self.connections().map(Connection::msg_tx_count).sum() }
 	pub fn msg_tx_queue_count(&self) -> u64 { self.connections().map(Connection::msg_tx_queue_count).sum() }
 	pub fn pkt_rx_count(&self) -> u64 { self.io.as_ref().map(Io::pkt_rx_count).unwrap_or(0) }
+	pub fn pkt_rx_decrypt_fail_count(&self) -> u64 { self.connections().map(Connection::pkt_rx_decrypt_fail_count).sum() }
+	/// Packets dropped by `poll()` for exceeding `ServerConfig::max_polled_packets`
+	pub fn pkt_rx_poll_drop_count(&self) -> u64 { self.polled_dropped }
 	pub fn pkt_tx_count(&self) -> u64 { self.io.as_ref().map(Io::pkt_tx_count).unwrap_or(0) }
+
+	/// The performance counters accumulated since the last `reset_metrics()`, e.g. to
+	/// `diff()` with an older snapshot
+	pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+		let snapshot = self.counters_snapshot();
+		match &self.metrics_baseline {
+			Some(baseline) => snapshot.since(baseline),
+			None => snapshot,
+		}
+	}
+
+	/// Restart the counters of `metrics_snapshot()` from 0. The individual counter
+	/// getters are unaffected.
+	pub fn reset_metrics(&mut self) {
+		self.metrics_baseline = Some(self.counters_snapshot());
+	}
+
+	fn counters_snapshot(&self) -> MetricsSnapshot {
+		MetricsSnapshot {
+			taken: Instant::now(),
+			bytes_rx: self.bytes_rx(),
+			bytes_tx: self.bytes_tx(),
+			frag_rx_expired_count: self.frag_rx_expired_count(),
+			frag_rx_refused_count: self.frag_rx_refused_count(),
+			msg_rx_count: self.msg_rx_count(),
+			msg_rx_drop_count: self.msg_rx_drop_count(),
+			msg_rx_miss_count: self.msg_rx_miss_count(),
+			msg_rx_observer_drop_count: self.msg_rx_observer_drop_count(),
+			msg_rx_reject_count: self.msg_rx_reject_count(),
+			msg_rx_throttle_count: self.msg_rx_throttle_count(),
+			msg_tx_count: self.msg_tx_count(),
+			msg_tx_queue_count: self.msg_tx_queue_count(),
+			pkt_rx_count: self.pkt_rx_count(),
+			pkt_rx_decrypt_fail_count: self.pkt_rx_decrypt_fail_count(),
+			pkt_tx_count: self.pkt_tx_count(),
+		}
+	}
+
+	// rates over the last second
+
+	pub fn bytes_rx_per_sec(&self) -> f32 { self.io.as_ref().map(Io::bytes_rx_per_sec).unwrap_or(0.0) }
+	pub fn bytes_tx_per_sec(&self) -> f32 { self.io.as_ref().map(Io::bytes_tx_per_sec).unwrap_or(0.0) }
+	pub fn pkt_rx_per_sec(&self) -> f32 { self.io.as_ref().map(Io::pkt_rx_per_sec).unwrap_or(0.0) }
+	pub fn pkt_tx_per_sec(&self) -> f32 { self.io.as_ref().map(Io::pkt_tx_per_sec).unwrap_or(0.0) }
+
+	/// The rate of messages received on channel `C` from all users
+	pub fn msg_rx_per_sec<C: Channel>(&self) -> f32 {
+		let channel_kind = ChannelKind::of::<C>();
+		self.connections().map(|conn| conn.msg_rx_per_sec(&channel_kind)).sum()
+	}
+
+	/// The rate of messages sent (including resends) on channel `C` to all users
+	pub fn msg_tx_per_sec<C: Channel>(&self) -> f32 {
+		let channel_kind = ChannelKind::of::<C>();
+		self.connections().map(|conn| conn.msg_tx_per_sec(&channel_kind)).sum()
+	}
+}
+
+/// A serialized session awaiting resumption by its Client
+struct Resumable {
+	user_key: UserKey,
+	session: Box<[u8]>,
+	expires: SystemTime,
+}
+
+impl Resumable {
+	fn ser(&self, session_id: SessionId, writer: &mut dyn BitWrite) {
+		let expires_ms = self.expires.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+		session_id.ser(writer);
+		self.user_key.to_bits().ser(writer);
+		(expires_ms as u64).ser(writer);
+		self.session.ser(writer);
+	}
+
+	fn de(reader: &mut BitReader) -> Result<(SessionId, Self), SerdeErr> {
+		let session_id = SessionId::de(reader)?;
+		let user_key = UserKey::from_bits(u32::de(reader)?);
+		let expires = UNIX_EPOCH + Duration::from_millis(u64::de(reader)?);
+		let session = Box::<[u8]>::de(reader)?;
+		Ok((session_id, Self { user_key, session, expires }))
+	}
 }
@@ -1,8 +1,186 @@
-use naia_shared::ConnectionConfig;
+use naia_shared::{ConfigError, ConnectionConfig, SocketConfig};
+use std::time::Duration;
 
 /// Contains Config properties which will be used by the Server
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct ServerConfig {
     /// Used to configure the connections with Clients
     pub connection: ConnectionConfig,
+    /// How long after timing out a user's session may be resumed by its Client with
+    /// `Client::resume_session()`, keeping its UserKey and skipping the handshake.
+    /// Also applies to sessions exported with `Server::export_resumable()`. Zero (the
+    /// default) disables resumption.
+    pub resume_window: Duration,
+    /// The maximum number of users, including those still handshaking. Further
+    /// connection attempts are rejected with `RejectReason::ServerFull`. `None` (the
+    /// default) only limits users to the number of distinct UserKeys.
+    pub max_users: Option<usize>,
+    /// The size of the grid cells bucketing user positions for
+    /// `Server::broadcast_near()`, in world units. Ideally close to the typical
+    /// broadcast radius.
+    pub spatial_cell_size: f32,
+    /// Options of the listening socket, e.g. a larger receive buffer for busy servers
+    pub socket: SocketConfig,
+    /// After reporting a `NaiaError::Send` error, suppress further errors of the same
+    /// kind to the same address for this long, then report their count. `None` (the
+    /// default) reports every error.
+    pub send_error_window: Option<Duration>,
+    /// What to do when accepting a connection which claims the identity of a connected
+    /// user; see `Client::connect_as()`
+    pub duplicate_identity: DuplicatePolicy,
+    /// Disconnect users once messages were dropped for exceeding a channel's rate
+    /// limit in this many `receive()` calls; see `SchemaBuilder::rate_limit()`. `None`
+    /// (the default) only drops the excess.
+    pub throttle_disconnect: Option<u32>,
+    /// The maximum size of a connect request's payload in bytes, mostly the identity
+    /// and the message passed to `Client::connect()`. Larger requests are rejected with
+    /// `RejectReason::ConnectTooLarge` before the message is decoded. `None` (the
+    /// default) only limits them to a packet.
+    pub max_connect_bytes: Option<usize>,
+    /// How long Clients have to complete the handshake and be accepted, from their
+    /// first packet. Pending connections are dropped after a `ConnectTimeout` event.
+    /// `None` (the default) only drops them after the connection timeout of silence.
+    pub handshake_timeout: Option<Duration>,
+    /// The maximum number of packets `Server::poll()` queues for the next `receive()`.
+    /// Beyond it, the oldest queued packets are dropped and counted by
+    /// `Server::pkt_rx_poll_drop_count()`.
+    pub max_polled_packets: usize,
+}
+
+/// How the Server handles a second session with the same identity, e.g. an account
+/// logged in elsewhere
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DuplicatePolicy {
+	/// Keep both sessions
+	#[default]
+	Allow,
+	/// Reject the new connection with `RejectReason::Duplicate`
+	RejectNew,
+	/// Disconnect the existing session, after a `ServerEvent::SessionReplaced` event
+	ReplaceOld,
+}
+
+impl Default for ServerConfig {
+	fn default() -> Self {
+		Self {
+			connection: ConnectionConfig::default(),
+			resume_window: Duration::ZERO,
+			max_users: None,
+			spatial_cell_size: 32.0,
+			socket: SocketConfig::default(),
+			send_error_window: None,
+			duplicate_identity: DuplicatePolicy::Allow,
+			throttle_disconnect: None,
+			max_connect_bytes: None,
+			handshake_timeout: None,
+			max_polled_packets: 4096,
+		}
+	}
+}
+
+impl ServerConfig {
+	pub fn builder() -> ServerConfigBuilder { ServerConfigBuilder::new() }
+
+	/// See `ConnectionConfig::lan()`
+	pub fn lan() -> Self {
+		Self { connection: ConnectionConfig::lan(), ..Self::default() }
+	}
+
+	/// See `ConnectionConfig::internet()`
+	pub fn internet() -> Self {
+		Self { connection: ConnectionConfig::internet(), ..Self::default() }
+	}
+
+	/// See `ConnectionConfig::mobile()`. Sessions may be resumed for a while, since
+	/// mobile Clients often change networks.
+	pub fn mobile() -> Self {
+		Self {
+			connection: ConnectionConfig::mobile(),
+			resume_window: Duration::from_secs(30),
+			..Self::default()
+		}
+	}
+
+	pub fn validate(&self) -> Result<(), ConfigError> {
+		let cell_size = self.spatial_cell_size;
+		if !(cell_size > 0.0 && cell_size.is_finite()) {
+			return Err(ConfigError::NotPositive { name: "spatial cell size", value: cell_size });
+		}
+		self.connection.validate()
+	}
+}
+
+/// Builds a validated ServerConfig, starting from `ServerConfig::default()` or any
+/// other config, e.g. a preset
+#[derive(Clone, Debug, Default)]
+pub struct ServerConfigBuilder {
+	config: ServerConfig,
+}
+
+impl ServerConfigBuilder {
+	pub fn new() -> Self { Self::default() }
+
+	pub fn connection(mut self, connection: ConnectionConfig) -> Self {
+		self.config.connection = connection;
+		self
+	}
+
+	pub fn resume_window(mut self, window: Duration) -> Self {
+		self.config.resume_window = window;
+		self
+	}
+
+	pub fn max_users(mut self, max: Option<usize>) -> Self {
+		self.config.max_users = max;
+		self
+	}
+
+	pub fn spatial_cell_size(mut self, cell_size: f32) -> Self {
+		self.config.spatial_cell_size = cell_size;
+		self
+	}
+
+	pub fn socket(mut self, socket: SocketConfig) -> Self {
+		self.config.socket = socket;
+		self
+	}
+
+	pub fn send_error_window(mut self, window: Option<Duration>) -> Self {
+		self.config.send_error_window = window;
+		self
+	}
+
+	pub fn duplicate_identity(mut self, policy: DuplicatePolicy) -> Self {
+		self.config.duplicate_identity = policy;
+		self
+	}
+
+	pub fn throttle_disconnect(mut self, strikes: Option<u32>) -> Self {
+		self.config.throttle_disconnect = strikes;
+		self
+	}
+
+	pub fn max_connect_bytes(mut self, max: Option<usize>) -> Self {
+		self.config.max_connect_bytes = max;
+		self
+	}
+
+	pub fn handshake_timeout(mut self, timeout: Option<Duration>) -> Self {
+		self.config.handshake_timeout = timeout;
+		self
+	}
+
+	pub fn max_polled_packets(mut self, max: usize) -> Self {
+		self.config.max_polled_packets = max;
+		self
+	}
+
+	pub fn build(self) -> Result<ServerConfig, ConfigError> {
+		self.config.validate()?;
+		Ok(self.config)
+	}
+}
+
+impl From<ServerConfig> for ServerConfigBuilder {
+	fn from(config: ServerConfig) -> Self { Self { config } }
 }
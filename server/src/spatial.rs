@@ -0,0 +1,118 @@
+use crate::user::UserKey;
+use std::collections::HashMap;
+
+type Cell = (i32, i32);
+
+/// A uniform grid of user positions, see `Server::broadcast_near()`
+pub(crate) struct SpatialGrid {
+	cell_size: f32,
+	positions: HashMap<UserKey, [f32; 2]>,
+	cells: HashMap<Cell, Vec<UserKey>>,
+}
+
+impl SpatialGrid {
+	pub fn new(cell_size: f32) -> Self {
+		debug_assert!(cell_size > 0.0, "invalid cell size {cell_size}");
+		Self { cell_size, positions: HashMap::new(), cells: HashMap::new() }
+	}
+
+	fn cell(&self, position: [f32; 2]) -> Cell {
+		let [x, y] = position.map(|p| (p / self.cell_size).floor() as i32);
+		(x, y)
+	}
+
+	pub fn set(&mut self, user_key: UserKey, position: [f32; 2]) {
+		let cell = self.cell(position);
+		if let Some(old) = self.positions.insert(user_key, position) {
+			let old = self.cell(old);
+			if old == cell {
+				return;
+			}
+			self.remove_from_cell(old, &user_key);
+		}
+		self.cells.entry(cell).or_default().push(user_key);
+	}
+
+	pub fn remove(&mut self, user_key: &UserKey) {
+		if let Some(position) = self.positions.remove(user_key) {
+			self.remove_from_cell(self.cell(position), user_key);
+		}
+	}
+
+	fn remove_from_cell(&mut self, cell: Cell, user_key: &UserKey) {
+		let Some(keys) = self.cells.get_mut(&cell) else {
+			return;
+		};
+		keys.retain(|key| key != user_key);
+		if keys.is_empty() {
+			self.cells.remove(&cell);
+		}
+	}
+
+	pub fn position(&self, user_key: &UserKey) -> Option<[f32; 2]> {
+		self.positions.get(user_key).copied()
+	}
+
+	/// Re-bucket all positions, e.g. after `ServerConfig::spatial_cell_size` changed
+	pub fn set_cell_size(&mut self, cell_size: f32) {
+		if cell_size == self.cell_size {
+			return;
+		}
+		let positions = std::mem::take(&mut self.positions);
+		*self = Self::new(cell_size);
+		for (user_key, position) in positions {
+			self.set(user_key, position);
+		}
+	}
+
+	/// The users within `radius` of `center`, inclusive
+	pub fn query(&self, center: [f32; 2], radius: f32) -> impl Iterator<Item = UserKey> + '_ {
+		let (min_x, min_y) = self.cell([center[0] - radius, center[1] - radius]);
+		let (max_x, max_y) = self.cell([center[0] + radius, center[1] + radius]);
+		(min_x..=max_x)
+			.flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+			.filter_map(|cell| self.cells.get(&cell))
+			.flatten()
+			.copied()
+			.filter(move |user_key| {
+				let [x, y] = self.positions[user_key];
+				let (dx, dy) = (x - center[0], y - center[1]);
+				dx * dx + dy * dy <= radius * radius
+			})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sorted(keys: impl Iterator<Item = UserKey>) -> Vec<u16> {
+		let mut indexes: Vec<_> = keys.map(|key| key.index()).collect();
+		indexes.sort();
+		indexes
+	}
+
+	#[test]
+	fn query() {
+		let mut grid = SpatialGrid::new(10.0);
+		let key = |index| UserKey::new(index, 0);
+		grid.set(key(0), [0.0, 0.0]);
+		grid.set(key(1), [5.0, 0.0]);
+		grid.set(key(2), [-9.0, -9.0]);
+		grid.set(key(3), [100.0, 100.0]);
+
+		assert_eq!(sorted(grid.query([0.0, 0.0], 5.0)), [0, 1]);
+		assert_eq!(sorted(grid.query([0.0, 0.0], 13.0)), [0, 1, 2]);
+		assert!(sorted(grid.query([50.0, 50.0], 10.0)).is_empty());
+
+		// moving across cells
+		grid.set(key(3), [1.0, 1.0]);
+		assert_eq!(sorted(grid.query([0.0, 0.0], 5.0)), [0, 1, 3]);
+		grid.remove(&key(1));
+		assert_eq!(sorted(grid.query([0.0, 0.0], 5.0)), [0, 3]);
+
+		grid.set_cell_size(1.0);
+		assert_eq!(sorted(grid.query([0.0, 0.0], 13.0)), [0, 2, 3]);
+		assert_eq!(grid.position(&key(2)), Some([-9.0, -9.0]));
+	}
+}
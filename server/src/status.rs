@@ -0,0 +1,138 @@
+use crate::Server;
+use std::{
+	fmt::Write as _,
+	io::{self, ErrorKind, Read, Write},
+	net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+	time::{Duration, Instant},
+};
+
+/// Max size of a request head; health checks send a few short lines
+const MAX_REQUEST_BYTES: usize = 2048;
+/// How long a connection may take to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+/// Max number of connections waiting for their request to complete; further ones are
+/// closed right away
+pub const MAX_PENDING_REQUESTS: usize = 64;
+
+struct Pending {
+	stream: TcpStream,
+	request: Vec<u8>,
+	accepted: Instant,
+}
+
+/// A minimal HTTP endpoint for orchestration health checks, polled from the game loop.
+/// Serves `GET /health` with a 200 status, and `GET /status` with the user count,
+/// uptime, tick rate and performance counters as JSON.
+pub struct StatusServer {
+	listener: TcpListener,
+	started: Instant,
+	pending: Vec<Pending>,
+}
+
+impl StatusServer {
+	/// Listen on `addr`, typically a localhost port
+	pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+		let listener = TcpListener::bind(addr)?;
+		listener.set_nonblocking(true)?;
+		Ok(Self { listener, started: Instant::now(), pending: Vec::new() })
+	}
+
+	pub fn local_addr(&self) -> io::Result<SocketAddr> { self.listener.local_addr() }
+
+	/// Answer the requests received so far, without blocking; call once per tick.
+	/// `tick_rate` is the application's simulation rate, in ticks per second.
+	pub fn update(&mut self, server: &Server, tick_rate: f32) {
+		let now = Instant::now();
+		loop {
+			match self.listener.accept() {
+				Ok((stream, _)) => {
+					if self.pending.len() >= MAX_PENDING_REQUESTS {
+						continue;
+					}
+					if stream.set_nonblocking(true).is_ok() {
+						self.pending.push(Pending { stream, request: Vec::new(), accepted: now });
+					}
+				}
+				Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+				Err(_) => break,
+			}
+		}
+
+		let mut pending = std::mem::take(&mut self.pending);
+		pending.retain_mut(|conn| match read_head(conn) {
+			Ok(true) => {
+				let response = self.respond(&conn.request, server, tick_rate);
+				// best effort, the response is small enough for the socket buffer
+				let _ = conn.stream.write_all(response.as_bytes());
+				false
+			}
+			Ok(false) => now.duration_since(conn.accepted) < REQUEST_TIMEOUT,
+			Err(_) => false,
+		});
+		self.pending = pending;
+	}
+
+	fn respond(&self, request: &[u8], server: &Server, tick_rate: f32) -> String {
+		let request = String::from_utf8_lossy(request);
+		let mut words = request.split_ascii_whitespace();
+		let (status, body) = match (words.next(), words.next()) {
+			(Some("GET"), Some("/health")) => ("200 OK", "{\"healthy\":true}".to_string()),
+			(Some("GET"), Some("/status")) => ("200 OK", self.status_json(server, tick_rate)),
+			(Some("GET"), _) => ("404 Not Found", "{}".to_string()),
+			_ => ("405 Method Not Allowed", "{}".to_string()),
+		};
+		format!(
+			"HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+			body.len(),
+		)
+	}
+
+	fn status_json(&self, server: &Server, tick_rate: f32) -> String {
+		let mut out = String::new();
+		let max_users = server.config().max_users
+			.map_or("null".to_string(), |max| max.to_string());
+		write!(
+			out,
+			"{{\"users\":{},\"max_users\":{max_users},\"uptime_secs\":{},\"tick_rate\":{},\"metrics\":{{",
+			server.users_count(),
+			Instant::now().duration_since(self.started).as_secs(),
+			json_number(tick_rate),
+		).unwrap();
+		for (i, (name, value)) in server.metrics_snapshot().counters().into_iter().enumerate() {
+			if i > 0 {
+				out.push(',');
+			}
+			write!(out, "\"{name}\":{value}").unwrap();
+		}
+		out.push_str("}}");
+		out
+	}
+}
+
+/// Read what arrived of the request, returning whether its head is complete
+fn read_head(conn: &mut Pending) -> io::Result<bool> {
+	let mut buf = [0; 512];
+	loop {
+		match conn.stream.read(&mut buf) {
+			Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+			Ok(len) => conn.request.extend_from_slice(&buf[..len]),
+			Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(false),
+			Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e),
+		}
+		if conn.request.windows(4).any(|w| w == b"\r\n\r\n") {
+			return Ok(true);
+		}
+		if conn.request.len() > MAX_REQUEST_BYTES {
+			return Err(ErrorKind::InvalidData.into());
+		}
+	}
+}
+
+/// JSON has no representation for NaN or infinities
+fn json_number(value: f32) -> String {
+	match value.is_finite() {
+		true => value.to_string(),
+		false => "null".to_string(),
+	}
+}
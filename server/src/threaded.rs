@@ -0,0 +1,123 @@
+use crate::{AcceptError, ConnectContext, Server, ServerConfig, ServerEvent, UserKey};
+use log::warn;
+use naia_shared::{Channel, ChannelKind, Message, Schema, error::NaiaResult, packet::RejectReason};
+use std::{
+	net::SocketAddr,
+	sync::mpsc::{self, RecvTimeoutError},
+	thread::{self, JoinHandle},
+	time::Duration,
+};
+
+/// How long the networking thread waits for commands before its next receive and send
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+type Command = Box<dyn FnOnce(&mut Server) + Send>;
+
+/// The events of a Server running on its own thread; see `Server::spawn_threaded()`
+pub type EventReceiver = mpsc::Receiver<ServerEvent>;
+
+/// Controls a Server running on its own thread. Commands are queued to the thread and
+/// applied before its next receive. Dropping the handle stops the thread.
+pub struct ServerHandle {
+	commands: mpsc::Sender<Command>,
+	thread: JoinHandle<Server>,
+}
+
+impl Server {
+	/// Run a Server on its own thread, which receives and sends every millisecond while
+	/// listening. Control it with the returned handle, and read its events from the
+	/// receiver.
+	pub fn spawn_threaded(config: ServerConfig, schema: Schema) -> (ServerHandle, EventReceiver) {
+		let (commands, command_rx) = mpsc::channel::<Command>();
+		let (event_tx, events) = mpsc::channel();
+		let thread = thread::Builder::new()
+			.name("naia-server".to_string())
+			.spawn(move || {
+				let mut server = Server::new(config, schema);
+				loop {
+					match command_rx.recv_timeout(POLL_INTERVAL) {
+						Ok(command) => command(&mut server),
+						Err(RecvTimeoutError::Timeout) => (),
+						Err(RecvTimeoutError::Disconnected) => break,
+					}
+					while let Ok(command) = command_rx.try_recv() {
+						command(&mut server);
+					}
+					if !server.is_listening() {
+						continue;
+					}
+
+					for event in server.receive() {
+						// the app may not care for events, keep serving anyway
+						let _ = event_tx.send(event);
+					}
+					server.send();
+				}
+				server
+			})
+			.expect("failed to spawn the server thread");
+
+		(ServerHandle { commands, thread }, events)
+	}
+}
+
+impl ServerHandle {
+	/// Run `f` on the Server's thread, e.g. to use the parts of the Server API not
+	/// wrapped by the handle, and wait for its result
+	pub fn run<R: Send + 'static>(&self, f: impl FnOnce(&mut Server) -> R + Send + 'static) -> R {
+		let (reply_tx, reply) = mpsc::sync_channel(1);
+		self.queue(move |server| {
+			let _ = reply_tx.send(f(server));
+		});
+		reply.recv().expect("the server thread stopped")
+	}
+
+	/// Queue `f` to run on the Server's thread, without waiting for it
+	pub fn queue(&self, f: impl FnOnce(&mut Server) + Send + 'static) {
+		if self.commands.send(Box::new(f)).is_err() {
+			warn!("Dropped a command, the server thread stopped");
+		}
+	}
+
+	/// See `Server::listen()`
+	pub fn listen(&self, addr: SocketAddr) -> NaiaResult {
+		self.run(move |server| server.listen(addr))
+	}
+
+	/// See `Server::accept_connection()`
+	pub fn accept_connection(&self, user_key: UserKey, ctx: ConnectContext) -> Result<(), AcceptError> {
+		self.run(move |server| server.accept_connection(&user_key, &ctx))
+	}
+
+	/// See `Server::reject_connection()`
+	pub fn reject_connection(&self, user_key: UserKey, reason: RejectReason) -> Result<(), AcceptError> {
+		self.run(move |server| server.reject_connection(&user_key, reason))
+	}
+
+	/// See `Server::send_message()`
+	pub fn send_message<C: Channel, M: Message>(&self, user_key: UserKey, message: &M) {
+		let message = M::clone_box(message);
+		self.queue(move |server| server.send_message_inner(&user_key, &ChannelKind::of::<C>(), message));
+	}
+
+	/// See `Server::broadcast_message()`
+	pub fn broadcast_message<C: Channel, M: Message>(&self, message: &M) {
+		let message = M::clone_box(message);
+		self.queue(move |server| server.broadcast_message_inner(&ChannelKind::of::<C>(), message));
+	}
+
+	/// See `Server::kick()`
+	pub fn kick(&self, user_key: UserKey) {
+		self.queue(move |server| server.kick(&user_key));
+	}
+
+	/// Stop the thread, returning the Server, e.g. to `shutdown()` it or export its
+	/// sessions
+	pub fn join(self) -> Server {
+		drop(self.commands);
+		match self.thread.join() {
+			Ok(server) => server,
+			Err(panic) => std::panic::resume_unwind(panic),
+		}
+	}
+}
@@ -1,16 +1,159 @@
-use naia_shared::CheckedIncr;
-use std::{fmt, hash::Hash};
+use crate::connection::Connection;
+use naia_shared::{Channel, ChannelKind, ConnectionStats, IdPool, LinkQuality, QueueDepth};
+use std::{fmt, hash::Hash, net::SocketAddr, str::FromStr};
 
 // UserKey
+/// Identifies a connected user. Keys are made of a slot index, which is recycled once
+/// the user leaves, and the generation of that slot, so a stale key never refers to a
+/// later user of the same slot.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct UserKey(pub u16);
+pub struct UserKey {
+	index: u16,
+	generation: u16,
+}
+
+impl UserKey {
+	pub fn new(index: u16, generation: u16) -> Self {
+		Self { index, generation }
+	}
+
+	/// The slot of this key, the smallest free one at the time the user connected
+	pub fn index(&self) -> u16 { self.index }
+
+	/// The number of previous users of this key's slot (wrapping)
+	pub fn generation(&self) -> u16 { self.generation }
 
-impl CheckedIncr for UserKey {
-	fn checked_incr(&self) -> Option<Self> {
-		self.0.checked_incr().map(|v| { UserKey(v) })
+	/// Pack the key into a single integer, e.g. to send it to Clients
+	pub fn to_bits(&self) -> u32 {
+		(self.generation as u32) << 16 | self.index as u32
+	}
+
+	pub fn from_bits(bits: u32) -> Self {
+		Self { index: bits as u16, generation: (bits >> 16) as u16 }
 	}
 }
 
 impl fmt::Display for UserKey {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { fmt::Debug::fmt(self, f) }
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}.{}", self.index, self.generation)
+	}
+}
+
+/// Parses the `index.generation` form written by `Display`
+impl FromStr for UserKey {
+	type Err = std::num::ParseIntError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (index, generation) = s.split_once('.').unwrap_or((s, ""));
+		Ok(Self { index: index.parse()?, generation: generation.parse()? })
+	}
+}
+
+/// The progress of a user's connection
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UserState {
+	/// The Client is establishing encryption and sending its connect request
+	Handshaking,
+	/// The Connect event was emitted, but the connection was not accepted yet
+	PendingAccept,
+	/// Accepted, and receiving the snapshot of `Server::set_snapshot_provider()`;
+	/// `UserSynced` follows
+	Syncing,
+	/// Accepted, either as a regular user or an observer
+	Connected,
+}
+
+/// A borrowed view of a user, see `Server::users()`
+#[derive(Clone, Copy)]
+pub struct UserRef<'s> {
+	conn: &'s Connection,
+}
+
+impl<'s> UserRef<'s> {
+	pub(crate) fn new(conn: &'s Connection) -> Self {
+		Self { conn }
+	}
+
+	pub fn key(&self) -> UserKey { self.conn.user_key }
+	pub fn address(&self) -> &'s SocketAddr { self.conn.address() }
+	pub fn state(&self) -> UserState { self.conn.user_state() }
+	pub fn is_observer(&self) -> bool { self.conn.is_observer() }
+
+	pub fn rtt_ms(&self) -> f32 { self.conn.rtt_ms() }
+	pub fn jitter_ms(&self) -> f32 { self.conn.jitter_ms() }
+	pub fn packet_loss(&self) -> f32 { self.conn.packet_loss() }
+	pub fn link_quality(&self) -> LinkQuality { self.conn.link_quality() }
+	pub fn outgoing_queue_depth(&self) -> QueueDepth { self.conn.queue_depth() }
+	pub fn connection_stats(&self) -> ConnectionStats { self.conn.stats() }
+
+	// performance counters
+
+	pub fn frag_rx_expired_count(&self) -> u64 { self.conn.frag_rx_expired_count() }
+	pub fn frag_rx_refused_count(&self) -> u64 { self.conn.frag_rx_refused_count() }
+	pub fn msg_rx_count(&self) -> u64 { self.conn.msg_rx_count() }
+	pub fn msg_rx_drop_count(&self) -> u64 { self.conn.msg_rx_drop_count() }
+	pub fn msg_rx_miss_count(&self) -> u64 { self.conn.msg_rx_miss_count() }
+	pub fn msg_rx_observer_drop_count(&self) -> u64 { self.conn.msg_rx_observer_drop_count() }
+	pub fn msg_rx_reject_count(&self) -> u64 { self.conn.msg_rx_reject_count }
+	pub fn msg_rx_throttle_count(&self) -> u64 { self.conn.msg_rx_throttle_count() }
+	pub fn msg_tx_count(&self) -> u64 { self.conn.msg_tx_count() }
+	pub fn msg_tx_queue_count(&self) -> u64 { self.conn.msg_tx_queue_count() }
+	pub fn pkt_rx_decrypt_fail_count(&self) -> u64 { self.conn.pkt_rx_decrypt_fail_count() }
+
+	pub fn msg_rx_per_sec<C: Channel>(&self) -> f32 { self.conn.msg_rx_per_sec(&ChannelKind::of::<C>()) }
+	pub fn msg_tx_per_sec<C: Channel>(&self) -> f32 { self.conn.msg_tx_per_sec(&ChannelKind::of::<C>()) }
+}
+
+impl fmt::Debug for UserRef<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("UserRef")
+			.field("key", &self.key())
+			.field("address", self.address())
+			.field("state", &self.state())
+			.finish()
+	}
+}
+
+/// Hands out UserKeys, recycling slot indexes but bumping their generation each time a
+/// key is returned
+#[derive(Default)]
+pub(crate) struct UserKeyPool {
+	indexes: IdPool<u16>,
+	generations: Vec<u16>,
+}
+
+impl UserKeyPool {
+	pub fn get(&mut self) -> Option<UserKey> {
+		let index = self.indexes.get()?;
+		Some(UserKey::new(index, self.generation(index)))
+	}
+
+	/// Return `user_key` to the pool; its slot is handed out with the next generation
+	pub fn put(&mut self, user_key: UserKey) {
+		let generation = user_key.generation.wrapping_add(1);
+		self.set_generation(user_key.index, generation);
+		self.indexes.put(user_key.index);
+	}
+
+	/// Take a specific key out of the pool, e.g. to restore a session. Returns false if
+	/// its slot is already taken.
+	pub fn take(&mut self, user_key: UserKey) -> bool {
+		if !self.indexes.take(user_key.index) {
+			return false;
+		}
+		self.set_generation(user_key.index, user_key.generation);
+		true
+	}
+
+	fn generation(&self, index: u16) -> u16 {
+		self.generations.get(index as usize).copied().unwrap_or(0)
+	}
+
+	fn set_generation(&mut self, index: u16, generation: u16) {
+		let index = index as usize;
+		if self.generations.len() <= index {
+			self.generations.resize(index + 1, 0);
+		}
+		self.generations[index] = generation;
+	}
 }
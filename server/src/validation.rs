@@ -0,0 +1,28 @@
+use crate::user::UserKey;
+use naia_shared::{Message, MessageContainer};
+
+/// The outcome of a message validator, see `Server::set_message_validator()`
+pub enum Verdict<M> {
+	/// Surface the message as received
+	Accept,
+	/// Surface the given replacement instead, e.g. an input with clamped values
+	Clamp(M),
+	/// Drop the message, counted by `msg_rx_reject_count()`
+	Reject,
+}
+
+/// A type-erased validator, returning the message to surface, if any
+pub(crate) type Validator = Box<dyn FnMut(&UserKey, MessageContainer) -> Option<MessageContainer> + Send>;
+
+pub(crate) fn erase<M: Message>(
+	mut validator: impl FnMut(&UserKey, &M) -> Verdict<M> + Send + 'static,
+) -> Validator {
+	Box::new(move |user_key, msg| {
+		let msg = msg.downcast::<M>().ok()?;
+		match validator(user_key, &msg) {
+			Verdict::Accept => Some(MessageContainer::from_read(Box::new(msg))),
+			Verdict::Clamp(msg) => Some(MessageContainer::from_read(Box::new(msg))),
+			Verdict::Reject => None,
+		}
+	})
+}
@@ -7,10 +7,12 @@ use quote::quote;
 
 mod channel;
 mod message;
+mod router;
 mod shared;
 
 use channel::channel_impl;
 use message::message_impl;
+use router::router_impl;
 
 // Channel
 
@@ -23,7 +25,7 @@ pub fn channel_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 // Message
 
 /// Derives the Message trait for a given struct, for internal
-#[proc_macro_derive(MessageInternal)]
+#[proc_macro_derive(MessageInternal, attributes(message, serde))]
 pub fn message_derive_internal(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let shared_crate_name = quote! { crate };
     message_impl(input, shared_crate_name, false)
@@ -36,9 +38,25 @@ pub fn message_derive_fragment(input: proc_macro::TokenStream) -> proc_macro::To
     message_impl(input, shared_crate_name, true)
 }
 
-/// Derives the Message trait for a given struct
-#[proc_macro_derive(Message)]
+/// Derives the Message trait for a given struct. With `#[message(compress)]`, the
+/// fields are compressed together before being written, e.g. for large map chunks.
+/// With `#[message(id = N)]`, the message is always sent with id N, rather than an id
+/// depending on its registration order; see `SchemaBuilder::add_message_with_id()`.
+/// With `#[serde(max_len = N)]`, a `String` or `Vec` field's length is limited to N.
+#[proc_macro_derive(Message, attributes(message, serde))]
 pub fn message_derive_shared(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let shared_crate_name = quote! { naia_shared };
     message_impl(input, shared_crate_name, false)
 }
+
+// MessageRouter
+
+/// Derives conversion from a received `MessageContainer` into an enum with a variant
+/// for each of an application's message types, e.g. `Chat(Chat)`, to `match` on
+/// instead of chaining downcasts. Generates `route()` and `TryFrom`, which give back
+/// messages of types without a variant.
+#[proc_macro_derive(MessageRouter)]
+pub fn message_router_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let shared_crate_name = quote! { naia_shared };
+    router_impl(input, shared_crate_name)
+}
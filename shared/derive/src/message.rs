@@ -1,6 +1,9 @@
 use proc_macro2::{Span, TokenStream};
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Index, LitStr, Member, Type};
+use syn::{
+    parse_macro_input, punctuated::Punctuated, Data, DeriveInput, Expr, Fields, Ident, Index,
+    LitInt, LitStr, Member, Meta, Token, Type,
+};
 
 use super::shared::{get_struct_type, StructType};
 
@@ -13,7 +16,14 @@ pub fn message_impl(
 
     // Helper Properties
     let struct_type = get_struct_type(&input);
-    let fields = get_fields(&input);
+    let fields = match get_fields(&input) {
+        Ok(fields) => fields,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let MessageAttributes { compress, id } = match get_message_attributes(&input) {
+        Ok(attributes) => attributes,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
     // Names
     let struct_name = input.ident;
@@ -27,11 +37,16 @@ pub fn message_impl(
 
     // Methods
     let clone_method = get_clone_method(&fields, &struct_type);
-    let bit_length_method = get_bit_length_method(&fields, &struct_type);
-    let write_method = get_write_method(&fields, &struct_type);
+    let bit_length_method = get_bit_length_method(&fields, &struct_type, compress);
+    let write_method = get_write_method(&fields, &struct_type, compress);
     let create_builder_method = get_create_builder_method(&builder_name);
-    let read_method = get_read_method(&struct_name, &fields, &struct_type);
+    let read_method = get_read_method(&struct_name, &fields, &struct_type, compress);
     let is_fragment_method = get_is_fragment_method(is_fragment);
+    let is_compressed_method = get_is_compressed_method(compress);
+    let encode_fields_method = get_encode_fields_method(compress);
+    let stable_id_method = get_stable_id_method(id);
+    let fields_bytes_fn = get_fields_bytes_fn(&struct_name, &fields, &struct_type, compress);
+    let fields_method = get_fields_method(&fields, &struct_type);
 
     proc_macro::TokenStream::from(quote! {
         mod #module_name {
@@ -40,9 +55,13 @@ pub fn message_impl(
             pub use #shared_crate_name::{
                 Named, Message, BitWrite, MessageKind, MessageKinds, Serde,
 				MessageBuilder, BitReader, SerdeErr, ConstBitLength, MessageContainer,
+				FieldSpec, BitWidthProbe, FixedBitWidth, VariableBitWidth, BitVecWriter,
+				SerdeBounded, compression,
             };
             use super::*;
 
+            #fields_bytes_fn
+
             struct #builder_name;
             impl MessageBuilder for #builder_name {
                 #read_method
@@ -56,6 +75,10 @@ pub fn message_impl(
                     self
                 }
                 #is_fragment_method
+                #is_compressed_method
+                #encode_fields_method
+                #stable_id_method
+                #fields_method
                 #bit_length_method
                 #create_builder_method
                 #write_method
@@ -87,6 +110,91 @@ fn get_is_fragment_method(is_fragment: bool) -> TokenStream {
     }
 }
 
+struct MessageAttributes {
+    compress: bool,
+    id: Option<u16>,
+}
+
+/// Parses `#[message(compress)]` and `#[message(id = N)]`
+fn get_message_attributes(input: &DeriveInput) -> syn::Result<MessageAttributes> {
+    let mut attributes = MessageAttributes { compress: false, id: None };
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("message")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("compress") {
+                attributes.compress = true;
+                Ok(())
+            } else if meta.path.is_ident("id") {
+                let id: LitInt = meta.value()?.parse()?;
+                attributes.id = Some(id.base10_parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported message attribute"))
+            }
+        })?;
+    }
+    Ok(attributes)
+}
+
+fn get_is_compressed_method(compress: bool) -> TokenStream {
+    quote! {
+        fn is_compressed() -> bool where Self: Sized {
+            #compress
+        }
+    }
+}
+
+fn get_encode_fields_method(compress: bool) -> TokenStream {
+    if !compress {
+        return quote! {};
+    }
+    quote! {
+        fn encode_fields(&self) -> Option<compression::EncodedFields> {
+            Some(compression::EncodedFields::new(fields_bytes(self).slice()))
+        }
+    }
+}
+
+fn get_stable_id_method(id: Option<u16>) -> TokenStream {
+    match id {
+        Some(id) => quote! {
+            fn stable_id() -> Option<u16> where Self: Sized {
+                Some(#id)
+            }
+        },
+        None => quote! {},
+    }
+}
+
+fn get_fields_method(fields: &[Field], struct_type: &StructType) -> TokenStream {
+    let mut output = quote! {};
+
+    for (index, field) in fields.iter().enumerate() {
+        let field_name = match struct_type {
+            StructType::TupleStruct => index.to_string(),
+            _ => field.variable_name.to_string(),
+        };
+        let field_type = &field.field_type;
+        let mut type_name = quote! { #field_type }.to_string().replace(' ', "");
+        if let Some(max_len) = &field.max_len {
+            type_name += &format!("(max_len={})", quote! { #max_len }.to_string().replace(' ', ""));
+        }
+        output = quote! {
+            #output
+            FieldSpec::new(
+                #field_name,
+                #type_name,
+                (&&BitWidthProbe::<#field_type>::new()).bit_width(),
+            ),
+        };
+    }
+
+    quote! {
+        fn fields() -> Vec<FieldSpec> {
+            vec![#output]
+        }
+    }
+}
+
 fn get_clone_method(fields: &[Field], struct_type: &StructType) -> TokenStream {
     let mut output = quote! {};
 
@@ -116,6 +224,7 @@ pub fn get_read_method(
     struct_name: &Ident,
     fields: &[Field],
     struct_type: &StructType,
+    compress: bool,
 ) -> TokenStream {
     let mut field_names = quote! {};
     for field in fields.iter() {
@@ -135,8 +244,13 @@ pub fn get_read_method(
         let new_output_right = {
 			let field_name = &field.variable_name;
 			let field_type = &field.field_type;
-			quote! {
-				let #field_name = <#field_type>::de(reader)?;
+			match &field.max_len {
+				Some(max_len) => quote! {
+					let #field_name = <#field_type as SerdeBounded>::de_bounded(#max_len, reader)?;
+				},
+				None => quote! {
+					let #field_name = <#field_type>::de(reader)?;
+				},
 			}
 		};
 
@@ -169,8 +283,22 @@ pub fn get_read_method(
         }
     };
 
+    // compressed fields are read from a separate reader over the decompressed bytes
+    let (message_kinds, fields_reader) = if compress {
+        (
+            quote! { message_kinds },
+            quote! {
+                let mut fields_reader = compression::read_fields(message_kinds.max_message_bytes(), reader)?;
+                let reader = &mut fields_reader;
+            },
+        )
+    } else {
+        (quote! { _message_kinds }, quote! {})
+    };
+
     quote! {
-        fn read(&self, reader: &mut BitReader) -> Result<MessageContainer, SerdeErr> {
+        fn read(&self, #message_kinds: &MessageKinds, reader: &mut BitReader) -> Result<MessageContainer, SerdeErr> {
+            #fields_reader
             #field_reads
 
             return Ok(MessageContainer::from_read(Box::new(#struct_build)));
@@ -178,16 +306,12 @@ pub fn get_read_method(
     }
 }
 
-fn get_write_method(fields: &[Field], struct_type: &StructType) -> TokenStream {
+fn get_write_method(fields: &[Field], struct_type: &StructType, compress: bool) -> TokenStream {
     let mut field_writes = quote! {};
 
     for (index, field) in fields.iter().enumerate() {
         let field_name = get_field_name(field, index, struct_type);
-        let new_output_right = {
-			quote! {
-				self.#field_name.ser(writer);
-			}
-		};
+        let new_output_right = field.ser(quote! { self.#field_name }, quote! { writer });
 
         let new_output_result = quote! {
             #field_writes
@@ -196,6 +320,15 @@ fn get_write_method(fields: &[Field], struct_type: &StructType) -> TokenStream {
         field_writes = new_output_result;
     }
 
+    if compress {
+        return quote! {
+            fn write(&self, message_kinds: &MessageKinds, writer: &mut dyn BitWrite) {
+                self.kind().ser(message_kinds, writer);
+                compression::EncodedFields::new(fields_bytes(self).slice()).write(writer);
+            }
+        };
+    }
+
     quote! {
         fn write(&self, message_kinds: &MessageKinds, writer: &mut dyn BitWrite) {
             self.kind().ser(message_kinds, writer);
@@ -204,16 +337,42 @@ fn get_write_method(fields: &[Field], struct_type: &StructType) -> TokenStream {
     }
 }
 
-fn get_bit_length_method(fields: &[Field], struct_type: &StructType) -> TokenStream {
+/// For compressed messages, serializes the fields into a separate buffer to compress
+fn get_fields_bytes_fn(
+    struct_name: &Ident,
+    fields: &[Field],
+    struct_type: &StructType,
+    compress: bool,
+) -> TokenStream {
+    if !compress {
+        return quote! {};
+    }
+
+    let mut field_writes = quote! {};
+    for (index, field) in fields.iter().enumerate() {
+        let field_name = get_field_name(field, index, struct_type);
+        let field_write = field.ser(quote! { message.#field_name }, quote! { &mut writer });
+        field_writes = quote! {
+            #field_writes
+            #field_write
+        };
+    }
+
+    quote! {
+        fn fields_bytes(message: &#struct_name) -> BitVecWriter {
+            let mut writer = BitVecWriter::new();
+            #field_writes
+            writer
+        }
+    }
+}
+
+fn get_bit_length_method(fields: &[Field], struct_type: &StructType, compress: bool) -> TokenStream {
     let mut field_bit_lengths = quote! {};
 
     for (index, field) in fields.iter().enumerate() {
         let field_name = get_field_name(field, index, struct_type);
-        let new_output_right = {
-			quote! {
-				output += self.#field_name.bit_length();
-			}
-		};
+        let new_output_right = field.bit_length(quote! { self.#field_name });
 
         let new_output_result = quote! {
             #field_bit_lengths
@@ -222,6 +381,17 @@ fn get_bit_length_method(fields: &[Field], struct_type: &StructType) -> TokenStr
         field_bit_lengths = new_output_result;
     }
 
+    if compress {
+        return quote! {
+            fn bit_length(&self) -> u32 {
+                let mut output = 0;
+                output += <MessageKind as ConstBitLength>::const_bit_length();
+                output += compression::EncodedFields::new(fields_bytes(self).slice()).bit_length();
+                output
+            }
+        };
+    }
+
     quote! {
         fn bit_length(&self) -> u32 {
             let mut output = 0;
@@ -240,7 +410,7 @@ pub fn get_create_builder_method(builder_name: &Ident) -> TokenStream {
     }
 }
 
-fn get_fields(input: &DeriveInput) -> Vec<Field> {
+fn get_fields(input: &DeriveInput) -> syn::Result<Vec<Field>> {
     let mut fields = Vec::new();
 
     if let Data::Struct(data_struct) = &input.data {
@@ -248,17 +418,19 @@ fn get_fields(input: &DeriveInput) -> Vec<Field> {
             Fields::Named(fields_named) => {
                 for field in fields_named.named.iter() {
                     if let Some(variable_name) = &field.ident {
+                        let max_len = get_max_len(field)?;
                         match &field.ty {
                             Type::Path(type_path) => {
-                                if let Some(_) = type_path.path.segments.first() {
+                                if type_path.path.segments.first().is_some() {
 									fields.push(Field::new(
 										variable_name.clone(),
 										field.ty.clone(),
+										max_len,
 									));
                                 }
                             }
                             _ => {
-                                fields.push(Field::new(variable_name.clone(), field.ty.clone()));
+                                fields.push(Field::new(variable_name.clone(), field.ty.clone(), max_len));
                             }
                         }
                     }
@@ -271,7 +443,7 @@ fn get_fields(input: &DeriveInput) -> Vec<Field> {
                             let property_type = property_seg.ident.clone();
                             let variable_name =
                                 get_variable_name_for_unnamed_field(index, property_type.span());
-							fields.push(Field::new(variable_name, field.ty.clone()));
+							fields.push(Field::new(variable_name, field.ty.clone(), get_max_len(field)?));
                         }
                     }
                 }
@@ -282,7 +454,24 @@ fn get_fields(input: &DeriveInput) -> Vec<Field> {
         panic!("Can only derive Message on a struct");
     }
 
-    fields
+    Ok(fields)
+}
+
+/// Parses `#[serde(max_len = N)]`, ignoring any other `serde` attributes, e.g. those
+/// of the serde crate
+fn get_max_len(field: &syn::Field) -> syn::Result<Option<Expr>> {
+    let mut max_len = None;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+        for meta in metas {
+            if let Meta::NameValue(name_value) = meta {
+                if name_value.path.is_ident("max_len") {
+                    max_len = Some(name_value.value);
+                }
+            }
+        }
+    }
+    Ok(max_len)
 }
 
 /// Get the field name as a TokenStream
@@ -302,7 +491,7 @@ fn get_field_name(field: &Field, index: usize, struct_type: &StructType) -> Memb
     }
 }
 
-const UNNAMED_FIELD_PREFIX: &'static str = "unnamed_field_";
+const UNNAMED_FIELD_PREFIX: &str = "unnamed_field_";
 fn get_variable_name_for_unnamed_field(index: usize, span: Span) -> Ident {
     Ident::new(&format!("{}{}", UNNAMED_FIELD_PREFIX, index), span)
 }
@@ -310,13 +499,32 @@ fn get_variable_name_for_unnamed_field(index: usize, span: Span) -> Ident {
 pub struct Field {
     pub variable_name: Ident,
     pub field_type: Type,
+    /// From `#[serde(max_len = N)]`
+    pub max_len: Option<Expr>,
 }
 
 impl Field {
-    pub fn new(variable_name: Ident, field_type: Type) -> Self {
+    pub fn new(variable_name: Ident, field_type: Type, max_len: Option<Expr>) -> Self {
         Self {
             variable_name: variable_name.clone(),
             field_type,
+            max_len,
+        }
+    }
+
+    /// Writes `value`, this field of some message, to `writer`
+    fn ser(&self, value: TokenStream, writer: TokenStream) -> TokenStream {
+        match &self.max_len {
+            Some(max_len) => quote! { SerdeBounded::ser_bounded(&#value, #max_len, #writer); },
+            None => quote! { #value.ser(#writer); },
+        }
+    }
+
+    /// Adds the bit length of `value`, this field of some message, to `output`
+    fn bit_length(&self, value: TokenStream) -> TokenStream {
+        match &self.max_len {
+            Some(max_len) => quote! { output += SerdeBounded::bit_length_bounded(&#value, #max_len); },
+            None => quote! { output += #value.bit_length(); },
         }
     }
 }
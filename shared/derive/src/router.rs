@@ -0,0 +1,61 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+pub fn router_impl(
+    input: proc_macro::TokenStream,
+    shared_crate_name: TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match router_tokens(&input, &shared_crate_name) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn router_tokens(input: &DeriveInput, shared_crate_name: &TokenStream) -> syn::Result<TokenStream> {
+    let Data::Enum(data_enum) = &input.data else {
+        return Err(syn::Error::new_spanned(&input.ident, "Can only derive MessageRouter on an enum"));
+    };
+
+    let mut arms = Vec::new();
+    for variant in &data_enum.variants {
+        let message_type = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0].ty,
+            _ => return Err(syn::Error::new_spanned(
+                variant,
+                "MessageRouter variants must wrap a single Message, e.g. `Chat(Chat)`",
+            )),
+        };
+        let variant_name = &variant.ident;
+        arms.push(quote! {
+            let message = match message.downcast::<#message_type>() {
+                Ok(message) => return Ok(Self::#variant_name(message)),
+                Err(message) => message,
+            };
+        });
+    }
+
+    let enum_name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #enum_name #type_generics #where_clause {
+            /// Convert a received message into the variant wrapping its type, or give it
+            /// back if no variant does
+            pub fn route(
+                message: #shared_crate_name::MessageContainer,
+            ) -> Result<Self, #shared_crate_name::MessageContainer> {
+                #(#arms)*
+                Err(message)
+            }
+        }
+
+        impl #impl_generics TryFrom<#shared_crate_name::MessageContainer> for #enum_name #type_generics #where_clause {
+            type Error = #shared_crate_name::MessageContainer;
+
+            fn try_from(message: #shared_crate_name::MessageContainer) -> Result<Self, Self::Error> {
+                Self::route(message)
+            }
+        }
+    })
+}
@@ -2,6 +2,8 @@ use proc_macro2::{Ident, Span, TokenStream};
 use quote::{format_ident, quote};
 use syn::{DataEnum, Fields};
 
+use super::get_max_len;
+
 fn bits_needed_for(max_value: usize) -> u8 {
 	(usize::BITS - max_value.leading_zeros()) as u8
 }
@@ -12,6 +14,17 @@ pub fn derive_serde_enum(
     enum_name: &Ident,
     serde_crate_name: TokenStream,
 ) -> TokenStream {
+    for field in enum_.variants.iter().flat_map(|variant| &variant.fields) {
+        match get_max_len(field) {
+            Ok(None) => (),
+            Ok(Some(max_len)) => {
+                return syn::Error::new_spanned(max_len, "max_len is only supported on struct fields")
+                    .to_compile_error();
+            }
+            Err(err) => return err.to_compile_error(),
+        }
+    }
+
     let variant_number = enum_.variants.len();
     let bits_needed = bits_needed_for(variant_number);
 
@@ -179,7 +192,7 @@ fn get_de_method(enum_: &DataEnum, bits_needed: u8) -> TokenStream {
             let index_u16: u16 = index.get() as u16;
             Ok(match index_u16 {
                 #de
-                _ => return Err(SerdeErr)
+                _ => return Err(SerdeErr::at(reader.position()))
             })
         }
     }
@@ -187,7 +200,7 @@ fn get_de_method(enum_: &DataEnum, bits_needed: u8) -> TokenStream {
 
 fn get_bit_length_method(enum_: &DataEnum, bits_needed: u8) -> TokenStream {
     let mut bit_length = quote! {};
-    for (_, variant) in enum_.variants.iter().enumerate() {
+    for variant in enum_.variants.iter() {
         let variant_name = &variant.ident;
         let base = match &variant.fields {
             Fields::Unit => {
@@ -6,4 +6,22 @@ mod unit_structure;
 pub use enumeration::*;
 pub use structure::*;
 pub use tuple_structure::*;
-pub use unit_structure::*;
\ No newline at end of file
+pub use unit_structure::*;
+use syn::{punctuated::Punctuated, Expr, Field, Meta, Token};
+
+/// Parses `#[serde(max_len = N)]`, ignoring any other `serde` attributes, e.g. those
+/// of the serde crate
+pub fn get_max_len(field: &Field) -> syn::Result<Option<Expr>> {
+	let mut max_len = None;
+	for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+		let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+		for meta in metas {
+			if let Meta::NameValue(name_value) = meta {
+				if name_value.path.is_ident("max_len") {
+					max_len = Some(name_value.value);
+				}
+			}
+		}
+	}
+	Ok(max_len)
+}
@@ -2,6 +2,8 @@ use proc_macro2::{Ident, Span, TokenStream};
 use quote::{format_ident, quote};
 use syn::DataStruct;
 
+use super::get_max_len;
+
 #[allow(clippy::format_push_string)]
 pub fn derive_serde_struct(
     struct_: &DataStruct,
@@ -14,6 +16,25 @@ pub fn derive_serde_struct(
 
     for field in &struct_.fields {
         let field_name = field.ident.as_ref().expect("expected field to have a name");
+        let max_len = match get_max_len(field) {
+            Ok(max_len) => max_len,
+            Err(err) => return err.to_compile_error(),
+        };
+        if let Some(max_len) = max_len {
+            ser_body = quote! {
+                #ser_body
+                SerdeBounded::ser_bounded(&self.#field_name, #max_len, writer);
+            };
+            de_body = quote! {
+                #de_body
+                #field_name: SerdeBounded::de_bounded(#max_len, reader)?,
+            };
+            bit_length_body = quote! {
+                #bit_length_body
+                output += SerdeBounded::bit_length_bounded(&self.#field_name, #max_len);
+            };
+            continue;
+        }
         ser_body = quote! {
             #ser_body
             self.#field_name.ser(writer);
@@ -36,16 +57,17 @@ pub fn derive_serde_struct(
 
     quote! {
         mod #module_name {
-			use #serde_crate_name::{BitReader, BitWrite, Serde, SerdeResult};
-            use super::#struct_name;
+			use #serde_crate_name::{BitReader, BitWrite, Serde, SerdeBounded, SerdeErr, SerdeResult};
+            use super::*;
             impl Serde for #struct_name {
                  fn ser(&self, writer: &mut dyn BitWrite) {
                     #ser_body
                  }
                  fn de(reader: &mut BitReader) -> SerdeResult<Self> {
-                    Ok(Self {
+                    let mut read = || Ok(Self {
                         #de_body
-                    })
+                    });
+                    read().map_err(SerdeErr::reading::<Self>)
                  }
                 fn bit_length(&self) -> u32 {
                     let mut output = 0;
@@ -2,6 +2,8 @@ use proc_macro2::{Ident, Span, TokenStream};
 use quote::{format_ident, quote};
 use syn::{DataStruct, Index};
 
+use super::get_max_len;
+
 #[allow(clippy::format_push_string)]
 pub fn derive_serde_tuple_struct(
     struct_: &DataStruct,
@@ -12,8 +14,27 @@ pub fn derive_serde_tuple_struct(
     let mut de_body = quote! {};
     let mut bit_length_body = quote! {};
 
-    for (i, _) in struct_.fields.iter().enumerate() {
+    for (i, field) in struct_.fields.iter().enumerate() {
         let field_index = Index::from(i);
+        let max_len = match get_max_len(field) {
+            Ok(max_len) => max_len,
+            Err(err) => return err.to_compile_error(),
+        };
+        if let Some(max_len) = max_len {
+            ser_body = quote! {
+                #ser_body
+                SerdeBounded::ser_bounded(&self.#field_index, #max_len, writer);
+            };
+            de_body = quote! {
+                #de_body
+                SerdeBounded::de_bounded(#max_len, reader)?,
+            };
+            bit_length_body = quote! {
+                #bit_length_body
+                output += SerdeBounded::bit_length_bounded(&self.#field_index, #max_len);
+            };
+            continue;
+        }
         ser_body = quote! {
             #ser_body
             self.#field_index.ser(writer);
@@ -37,17 +58,18 @@ pub fn derive_serde_tuple_struct(
     quote! {
         mod #module_name {
 			use #serde_crate_name::{
-				BitReader, BitWrite, ConstBitLength, Serde, SerdeResult,
+				BitReader, BitWrite, ConstBitLength, Serde, SerdeBounded, SerdeErr, SerdeResult,
 			};
-            use super::#struct_name;
+            use super::*;
             impl Serde for #struct_name {
                  fn ser(&self, writer: &mut dyn BitWrite) {
                     #ser_body
                  }
                  fn de(reader: &mut BitReader) -> SerdeResult<Self> {
-					Ok(Self (
+					let mut read = || Ok(Self (
                         #de_body
-					))
+					));
+					read().map_err(SerdeErr::reading::<Self>)
                  }
                  fn bit_length(&self) -> u32 {
                     let mut output = 0;
@@ -7,7 +7,7 @@ pub fn derive_serde_unit_struct(
 	struct_name: &Ident,
 	serde_crate_name: TokenStream,
 ) -> TokenStream {
-	assert_eq!(data_struct.fields, Fields::Unit);
+	assert!(matches!(data_struct.fields, Fields::Unit));
 
 	let module_name = format!("define_{}", struct_name.to_string().to_lowercase());
 	let module = Ident::new(module_name.as_str(), Span::call_site());
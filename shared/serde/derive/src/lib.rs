@@ -4,13 +4,15 @@ use syn::{parse_macro_input, Data, DeriveInput, Fields};
 mod impls;
 use impls::*;
 
-#[proc_macro_derive(Serde)]
+/// Derives Serde for a struct or enum. With `#[serde(max_len = N)]`, a `String` or `Vec`
+/// field's length is limited to N, see `SerdeBounded`.
+#[proc_macro_derive(Serde, attributes(serde))]
 pub fn derive_serde(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let serde_crate_name = quote! { naia_shared };
     derive_serde_common(input, serde_crate_name)
 }
 
-#[proc_macro_derive(SerdeInternal)]
+#[proc_macro_derive(SerdeInternal, attributes(serde))]
 pub fn derive_serde_internal(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let serde_crate_name = quote! { naia_serde };
     derive_serde_common(input, serde_crate_name)
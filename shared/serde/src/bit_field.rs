@@ -0,0 +1,226 @@
+use crate::{BitReader, BitWrite, ConstBitLength, Serde, SerdeErr, SerdeResult};
+use core::fmt;
+
+/// `N` flags (up to 128) written as exactly `N` bits, e.g. `BitField<5>` for five
+/// booleans in less than a byte. Flag 0 is written first.
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
+pub struct BitField<const N: usize>(u128);
+
+impl<const N: usize> BitField<N> {
+	const MASK: u128 = match N {
+		128 => u128::MAX,
+		_ => (1 << N) - 1,
+	};
+
+	/// All flags cleared
+	pub const fn new() -> Self {
+		const { assert!(N <= 128, "a BitField holds up to 128 flags") };
+		Self(0)
+	}
+
+	/// Flag `i` is bit `i` of `bits`. Panics if bits beyond `N` are set.
+	pub fn from_bits(bits: u128) -> Self {
+		assert!(bits & !Self::MASK == 0, "bits beyond the {N} flags of the BitField");
+		let mut field = Self::new();
+		field.0 = bits;
+		field
+	}
+
+	pub fn bits(&self) -> u128 { self.0 }
+
+	/// Panics if `i` is not below `N`
+	pub fn get(&self, i: usize) -> bool {
+		assert!(i < N, "flag {i} of a BitField<{N}>");
+		self.0 >> i & 1 != 0
+	}
+
+	/// Panics if `i` is not below `N`
+	pub fn set(&mut self, i: usize, value: bool) {
+		assert!(i < N, "flag {i} of a BitField<{N}>");
+		match value {
+			true => self.0 |= 1 << i,
+			false => self.0 &= !(1 << i),
+		}
+	}
+
+	/// The number of set flags
+	pub fn count(&self) -> u32 { self.0.count_ones() }
+
+	/// The flags in order
+	pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+		(0..N).map(|i| self.get(i))
+	}
+}
+
+impl<const N: usize> fmt::Debug for BitField<N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "BitField(")?;
+		for flag in self.iter() {
+			write!(f, "{}", u8::from(flag))?;
+		}
+		write!(f, ")")
+	}
+}
+
+impl<const N: usize> Serde for BitField<N> {
+	fn ser(&self, writer: &mut dyn BitWrite) {
+		for flag in self.iter() {
+			writer.write_bit(flag);
+		}
+	}
+
+	fn de(reader: &mut BitReader) -> SerdeResult<Self> {
+		let mut field = Self::new();
+		for i in 0..N {
+			field.0 |= u128::from(reader.read_bit()?) << i;
+		}
+		Ok(field)
+	}
+
+	fn bit_length(&self) -> u32 { N as u32 }
+}
+
+impl<const N: usize> ConstBitLength for BitField<N> {
+	fn const_bit_length() -> u32 { N as u32 }
+}
+
+/// An ASCII string of up to `N` characters, written as exactly `N` 7 bit characters,
+/// e.g. `FixedAscii<4>` for a region code in 28 bits. Shorter strings are padded with
+/// NULs, so the string itself can't contain NUL.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct FixedAscii<const N: usize>([u8; N]);
+
+impl<const N: usize> FixedAscii<N> {
+	/// Returns None if `value` is longer than `N`, or isn't ASCII without NULs
+	pub fn new(value: &str) -> Option<Self> {
+		let bytes = value.as_bytes();
+		if bytes.len() > N || !bytes.iter().all(|byte| byte.is_ascii() && *byte != 0) {
+			return None;
+		}
+		let mut chars = [0; N];
+		chars[..bytes.len()].copy_from_slice(bytes);
+		Some(Self(chars))
+	}
+
+	pub fn as_str(&self) -> &str {
+		let len = self.0.iter().position(|byte| *byte == 0).unwrap_or(N);
+		// only ever holds ASCII
+		core::str::from_utf8(&self.0[..len]).unwrap()
+	}
+
+	pub fn len(&self) -> usize { self.as_str().len() }
+
+	pub fn is_empty(&self) -> bool { N == 0 || self.0[0] == 0 }
+}
+
+impl<const N: usize> Default for FixedAscii<N> {
+	fn default() -> Self { Self([0; N]) }
+}
+
+impl<const N: usize> fmt::Debug for FixedAscii<N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Debug::fmt(self.as_str(), f)
+	}
+}
+
+impl<const N: usize> fmt::Display for FixedAscii<N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+impl<const N: usize> Serde for FixedAscii<N> {
+	fn ser(&self, writer: &mut dyn BitWrite) {
+		for byte in self.0 {
+			for bit in 0..7 {
+				writer.write_bit(byte >> bit & 1 != 0);
+			}
+		}
+	}
+
+	fn de(reader: &mut BitReader) -> SerdeResult<Self> {
+		let start = reader.position();
+		let mut chars = [0; N];
+		for byte in &mut chars {
+			for bit in 0..7 {
+				*byte |= u8::from(reader.read_bit()?) << bit;
+			}
+		}
+		// padding only ever follows the string, so each string has one encoding
+		let len = chars.iter().position(|byte| *byte == 0).unwrap_or(N);
+		if chars[len..].iter().any(|byte| *byte != 0) {
+			return Err(SerdeErr::at(start));
+		}
+		Ok(Self(chars))
+	}
+
+	fn bit_length(&self) -> u32 { N as u32 * 7 }
+}
+
+impl<const N: usize> ConstBitLength for FixedAscii<N> {
+	fn const_bit_length() -> u32 { N as u32 * 7 }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{BitWriter, UnsignedInteger};
+
+	#[test]
+	fn bit_field() {
+		let mut flags = BitField::<5>::new();
+		flags.set(0, true);
+		flags.set(3, true);
+		assert_eq!(flags.bits(), 0b01001);
+		assert_eq!(flags.count(), 2);
+		assert_eq!(format!("{flags:?}"), "BitField(10010)");
+
+		let mut writer = BitWriter::new();
+		flags.ser(&mut writer);
+		BitField::<128>::from_bits(u128::MAX).ser(&mut writer);
+		true.ser(&mut writer);
+		assert_eq!(writer.slice().len(), (5 + 128 + 1_usize).div_ceil(8));
+
+		let mut reader = BitReader::from_slice(writer.slice());
+		assert_eq!(BitField::<5>::de(&mut reader).unwrap(), flags);
+		assert_eq!(BitField::<128>::de(&mut reader).unwrap().count(), 128);
+		assert!(bool::de(&mut reader).unwrap());
+	}
+
+	#[test]
+	#[should_panic]
+	fn bit_field_overflow() {
+		BitField::<3>::from_bits(0b1000);
+	}
+
+	#[test]
+	fn fixed_ascii() {
+		assert!(FixedAscii::<3>::new("abcd").is_none());
+		assert!(FixedAscii::<3>::new("é").is_none());
+		assert!(FixedAscii::<3>::new("a\0").is_none());
+
+		let code = FixedAscii::<4>::new("EU").unwrap();
+		assert_eq!((code.as_str(), code.len()), ("EU", 2));
+		assert_eq!(FixedAscii::<4>::const_bit_length(), 28);
+
+		let mut writer = BitWriter::new();
+		code.ser(&mut writer);
+		FixedAscii::<4>::default().ser(&mut writer);
+		FixedAscii::<2>::new("ok").unwrap().ser(&mut writer);
+		assert_eq!(writer.slice().len(), 70_usize.div_ceil(8));
+
+		let mut reader = BitReader::from_slice(writer.slice());
+		assert_eq!(FixedAscii::<4>::de(&mut reader).unwrap(), code);
+		assert!(FixedAscii::<4>::de(&mut reader).unwrap().is_empty());
+		assert_eq!(FixedAscii::<2>::de(&mut reader).unwrap().to_string(), "ok");
+	}
+
+	#[test]
+	fn fixed_ascii_padding() {
+		// a character after the padding
+		let mut writer = BitWriter::new();
+		UnsignedInteger::<7>::new(0).ser(&mut writer);
+		UnsignedInteger::<7>::new(b'a').ser(&mut writer);
+		assert!(FixedAscii::<2>::de(&mut BitReader::from_slice(writer.slice())).is_err());
+	}
+}
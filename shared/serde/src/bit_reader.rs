@@ -1,5 +1,14 @@
 use crate::{Serde, SerdeErr, SerdeResult};
+use alloc::boxed::Box;
 
+/// A position in a `BitReader` to return to; see `BitReader::save()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+	bit_offset: u8,
+	buffer_index: usize,
+}
+
+#[derive(Clone)]
 pub struct BitReader {
 	bit_offset: u8,
     buffer: Box<[u8]>,
@@ -17,11 +26,43 @@ impl BitReader {
 
 	pub fn from_slice(slice: &[u8]) -> Self { Self::new(slice.into()) }
 
+	/// The number of bits left to read
+	pub fn remaining_bits(&self) -> usize {
+		8 * (self.buffer.len() - self.buffer_index) - self.bit_offset as usize
+	}
+
+	/// The number of bits read so far
+	pub fn position(&self) -> usize {
+		8 * self.buffer_index + self.bit_offset as usize
+	}
+
+	/// Mark the current position, to `restore()` after a speculative read
+	pub fn save(&self) -> Checkpoint {
+		Checkpoint { bit_offset: self.bit_offset, buffer_index: self.buffer_index }
+	}
+
+	/// Rewind, or fast forward, to a `save()`d position
+	pub fn restore(&mut self, checkpoint: Checkpoint) {
+		debug_assert!(checkpoint.buffer_index <= self.buffer.len(), "checkpoint from another reader");
+		self.bit_offset = checkpoint.bit_offset;
+		self.buffer_index = checkpoint.buffer_index;
+	}
+
+	/// Zero the bits read since `start`, a previous `position()`
+	pub(crate) fn zero_since(&mut self, start: usize) {
+		for bit in start..self.position() {
+			self.buffer[bit / 8] &= !(0x80 >> (bit % 8));
+		}
+	}
+
+	#[cfg(test)]
+	pub(crate) fn buffer(&self) -> &[u8] { &self.buffer }
+
 	pub fn remaining_mut(&mut self) -> &mut [u8] { &mut self.buffer[self.buffer_index..] }
 
     pub fn read_bit(&mut self) -> Result<bool, SerdeErr> {
 		if self.buffer_index == self.buffer.len() {
-			return Err(SerdeErr);
+			return Err(SerdeErr::at(self.position()));
 		}
 
 		let mask = 1 << (7 - self.bit_offset);
@@ -37,9 +78,8 @@ impl BitReader {
     }
 
     pub fn read_byte(&mut self) -> Result<u8, SerdeErr> {
-		let bits_left = 8 * (self.buffer.len() - self.buffer_index) - self.bit_offset as usize;
-		if bits_left < 8 {
-			return Err(SerdeErr);
+		if self.remaining_bits() < 8 {
+			return Err(SerdeErr::at(self.position()));
 		}
 
 		let mut byte = self.buffer[self.buffer_index];
@@ -53,7 +93,24 @@ impl BitReader {
         Ok(byte)
     }
 
-	pub fn read<T: Serde>(&mut self) -> SerdeResult<T> { T::de(self) }
+	/// Read a `T`, attributing errors to it; see `SerdeErr::reading()`
+	pub fn read<T: Serde>(&mut self) -> SerdeResult<T> { T::de(self).map_err(SerdeErr::reading::<T>) }
+
+	/// Read a value without consuming it, e.g. to branch on a tag
+	pub fn peek<T: Serde>(&mut self) -> SerdeResult<T> {
+		let checkpoint = self.save();
+		let value = self.read();
+		self.restore(checkpoint);
+		value
+	}
+
+	/// Read a bit without consuming it, e.g. to check for an optional section
+	pub fn peek_bit(&self) -> SerdeResult<bool> {
+		match self.buffer.get(self.buffer_index) {
+			Some(byte) => Ok(byte & (0x80 >> self.bit_offset) != 0),
+			None => Err(SerdeErr::at(self.position())),
+		}
+	}
 }
 
 #[cfg(test)]
@@ -68,12 +125,41 @@ mod tests {
 		assert_eq!(reader.read_bit(), Ok(false));
 		assert_eq!(reader.read_bit(), Ok(true));
 		assert_eq!(reader.read_byte(), Ok(0b1100_0100));
-		assert_eq!(reader.read_byte(), Err(SerdeErr));
+		assert_eq!(reader.read_byte(), Err(SerdeErr::at(11)));
 		assert_eq!(reader.read_bit(), Ok(true));
 		assert_eq!(reader.read_bit(), Ok(true));
 		assert_eq!(reader.read_bit(), Ok(false));
 		assert_eq!(reader.read_bit(), Ok(true));
 		assert_eq!(reader.read_bit(), Ok(false));
-		assert_eq!(reader.read_bit(), Err(SerdeErr));
+		assert_eq!(reader.read_bit(), Err(SerdeErr::at(16)));
+	}
+
+	#[test]
+	fn peek() {
+		let mut reader = BitReader::new([0b1011_1000, 0b1001_1010].into());
+		assert_eq!(reader.read_bit(), Ok(true));
+		assert_eq!(reader.peek::<u8>(), Ok(0b0111_0001));
+		assert_eq!(reader.read_byte(), Ok(0b0111_0001));
+		assert_eq!(reader.peek::<u8>(), Err(SerdeErr::at(9).reading::<u8>()));
+		assert_eq!(reader.remaining_bits(), 7);
+		assert_eq!(reader.peek_bit(), Ok(false));
+		assert_eq!(reader.read_bit(), Ok(false));
+		assert_eq!(reader.read_bit(), Ok(false));
+		assert_eq!(reader.peek_bit(), Ok(true));
+		assert_eq!(reader.remaining_bits(), 5);
+	}
+
+	#[test]
+	fn save_restore() {
+		let mut reader = BitReader::new([0b1011_1000, 0b1001_1010].into());
+		reader.read_bit().unwrap();
+		let checkpoint = reader.save();
+		assert_eq!(reader.read::<u16>(), Err(SerdeErr::at(9).reading::<u16>()));
+		reader.restore(checkpoint);
+		assert_eq!(reader.position(), 1);
+		assert_eq!(reader.read_byte(), Ok(0b0111_0001));
+
+		let end = BitReader::from_slice(&[]);
+		assert_eq!(end.peek_bit(), Err(SerdeErr::at(0)));
 	}
 }
\ No newline at end of file
@@ -2,6 +2,7 @@ use crate::{
     constants::{MTU_SIZE_BITS, MTU_SIZE_BYTES},
     BitCounter, Serde,
 };
+use alloc::{boxed::Box, vec::Vec};
 
 pub trait BitWrite {
     fn write_bit(&mut self, bit: bool);
@@ -81,6 +82,45 @@ impl BitWrite for BitWriter {
     }
 }
 
+/// A BitWriter without a capacity limit, for data which is never sent as a single
+/// packet, e.g. connection state handed off to another process
+#[derive(Default)]
+pub struct BitVecWriter {
+	bit_offset: u8,
+	buffer: Vec<u8>,
+}
+
+impl BitVecWriter {
+	pub fn new() -> Self { Self::default() }
+
+	pub fn slice(&self) -> &[u8] { &self.buffer }
+	pub fn into_bytes(self) -> Box<[u8]> { self.buffer.into_boxed_slice() }
+
+	pub fn write<T: Serde>(&mut self, value: &T) { value.ser(self) }
+}
+
+impl BitWrite for BitVecWriter {
+	fn write_bit(&mut self, bit: bool) {
+		if self.bit_offset == 0 {
+			self.buffer.push(0);
+		}
+
+		let mask = (bit as u8) << (7 - self.bit_offset);
+		*self.buffer.last_mut().unwrap() |= mask;
+		self.bit_offset = (self.bit_offset + 1) % 8;
+	}
+
+	fn write_byte(&mut self, byte: u8) {
+		if self.bit_offset == 0 {
+			self.buffer.push(byte);
+			return;
+		}
+
+		*self.buffer.last_mut().unwrap() |= byte >> self.bit_offset;
+		self.buffer.push(byte << (8 - self.bit_offset));
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use crate::{bit_reader::BitReader, Serde};
@@ -166,4 +206,19 @@ mod tests {
 		assert_eq!(counter.bits_needed(), 34);
 		assert!(!counter.overflowed());
 	}
+
+	#[test]
+	fn vec_writer() {
+		let mut writer = BitVecWriter::new();
+		for i in 0..MTU_SIZE_BYTES as u32 {
+			writer.write_bit(i % 3 == 0);
+			i.ser(&mut writer);
+		}
+
+		let mut reader = BitReader::from_slice(writer.slice());
+		for i in 0..MTU_SIZE_BYTES as u32 {
+			assert_eq!(reader.read_bit(), Ok(i % 3 == 0));
+			assert_eq!(u32::de(&mut reader), Ok(i));
+		}
+	}
 }
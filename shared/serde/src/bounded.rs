@@ -0,0 +1,120 @@
+use crate::{BitReader, BitWrite, Serde, SerdeErr, SerdeResult};
+use alloc::{string::String, vec::Vec};
+
+/// Length prefixed values with a maximum length, for fields marked
+/// `#[serde(max_len = N)]`. The length prefix takes only the bits needed for
+/// `max_len`, and longer lengths fail to read before anything is allocated.
+pub trait SerdeBounded: Sized {
+	/// Panics if longer than `max_len`
+	fn ser_bounded(&self, max_len: usize, writer: &mut dyn BitWrite);
+
+	fn de_bounded(max_len: usize, reader: &mut BitReader) -> SerdeResult<Self>;
+
+	fn bit_length_bounded(&self, max_len: usize) -> u32;
+}
+
+fn length_bits(max_len: usize) -> u32 { usize::BITS - max_len.leading_zeros() }
+
+fn ser_length(len: usize, max_len: usize, writer: &mut dyn BitWrite) {
+	assert!(len <= max_len, "length {len} exceeds max_len of {max_len}");
+	for i in 0..length_bits(max_len) {
+		writer.write_bit(len >> i & 1 != 0);
+	}
+}
+
+fn de_length(max_len: usize, reader: &mut BitReader) -> SerdeResult<usize> {
+	let mut len = 0;
+	for i in 0..length_bits(max_len) {
+		if reader.read_bit()? {
+			len |= 1 << i;
+		}
+	}
+	match len <= max_len {
+		true => Ok(len),
+		false => Err(SerdeErr::at(reader.position())),
+	}
+}
+
+impl SerdeBounded for String {
+	fn ser_bounded(&self, max_len: usize, writer: &mut dyn BitWrite) {
+		ser_length(self.len(), max_len, writer);
+		for byte in self.as_bytes() {
+			writer.write_byte(*byte);
+		}
+	}
+
+	fn de_bounded(max_len: usize, reader: &mut BitReader) -> SerdeResult<Self> {
+		let len = de_length(max_len, reader)?;
+		if len > reader.remaining_bits() / 8 {
+			return Err(SerdeErr::at(reader.position()));
+		}
+		let mut bytes = Vec::with_capacity(len);
+		for _ in 0..len {
+			bytes.push(reader.read_byte()?);
+		}
+		// as for `String::de()`, reuse the buffer for valid strings
+		Ok(String::from_utf8(bytes).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()))
+	}
+
+	fn bit_length_bounded(&self, max_len: usize) -> u32 {
+		length_bits(max_len) + self.len() as u32 * 8
+	}
+}
+
+impl<T: Serde> SerdeBounded for Vec<T> {
+	fn ser_bounded(&self, max_len: usize, writer: &mut dyn BitWrite) {
+		ser_length(self.len(), max_len, writer);
+		for item in self {
+			item.ser(writer);
+		}
+	}
+
+	fn de_bounded(max_len: usize, reader: &mut BitReader) -> SerdeResult<Self> {
+		let len = de_length(max_len, reader)?;
+		let mut output = Vec::with_capacity(len.min(reader.remaining_bits()));
+		for _ in 0..len {
+			output.push(T::de(reader)?);
+		}
+		Ok(output)
+	}
+
+	fn bit_length_bounded(&self, max_len: usize) -> u32 {
+		length_bits(max_len) + self.iter().map(Serde::bit_length).sum::<u32>()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::BitWriter;
+
+	#[test]
+	fn read_write() {
+		let mut writer = BitWriter::new();
+		"hello".to_string().ser_bounded(64, &mut writer);
+		vec![1u8, 2, 3].ser_bounded(3, &mut writer);
+		Vec::<u8>::new().ser_bounded(0, &mut writer);
+		assert_eq!("hello".to_string().bit_length_bounded(64), 7 + 40);
+		assert_eq!(writer.slice().len(), (7 + 40 + 2 + 24_u32).div_ceil(8) as usize);
+
+		let mut reader = BitReader::from_slice(writer.slice());
+		assert_eq!(String::de_bounded(64, &mut reader).unwrap(), "hello");
+		assert_eq!(Vec::<u8>::de_bounded(3, &mut reader).unwrap(), [1, 2, 3]);
+		assert!(Vec::<u8>::de_bounded(0, &mut reader).unwrap().is_empty());
+	}
+
+	#[test]
+	fn read_too_long() {
+		// the prefix has room for lengths beyond the limit
+		let mut writer = BitWriter::new();
+		vec![0u8; 6].ser_bounded(7, &mut writer);
+		let mut reader = BitReader::from_slice(writer.slice());
+		assert!(Vec::<u8>::de_bounded(5, &mut reader).is_err());
+	}
+
+	#[test]
+	#[should_panic]
+	fn write_too_long() {
+		"too long".to_string().ser_bounded(4, &mut BitWriter::new());
+	}
+}
@@ -1,20 +1,68 @@
-/// The error message when failing to serialize/deserialize to/from the bit
-/// stream.
-#[derive(Clone, PartialEq)]
-pub struct SerdeErr;
+use core::fmt;
+
+/// The error when failing to deserialize from the bit stream. Where known, it carries
+/// the bit position of the failure in the input, and the innermost type being read.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct SerdeErr {
+	position: Option<usize>,
+	type_name: Option<&'static str>,
+}
 
 pub type SerdeResult<T> = Result<T, SerdeErr>;
 
-impl std::fmt::Debug for SerdeErr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Bin deserialize error",)
-    }
+impl SerdeErr {
+	/// An error without context
+	pub const fn new() -> Self { Self { position: None, type_name: None } }
+
+	/// An error at bit `position` of the input, e.g. `BitReader::position()`
+	pub const fn at(position: usize) -> Self { Self { position: Some(position), type_name: None } }
+
+	pub fn position(&self) -> Option<usize> { self.position }
+
+	pub fn type_name(&self) -> Option<&'static str> { self.type_name }
+
+	/// Attribute the error to reading a `T`, unless already attributed to a type
+	/// within it
+	pub fn reading<T: ?Sized>(mut self) -> Self {
+		self.type_name.get_or_insert(core::any::type_name::<T>());
+		self
+	}
 }
 
-impl std::fmt::Display for SerdeErr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Debug::fmt(self, f)
-    }
+impl fmt::Debug for SerdeErr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Bin deserialize error")?;
+		if let Some(type_name) = self.type_name {
+			write!(f, " reading {type_name}")?;
+		}
+		if let Some(position) = self.position {
+			write!(f, " at bit {position}")?;
+		}
+		Ok(())
+	}
 }
 
-impl std::error::Error for SerdeErr {}
+impl fmt::Display for SerdeErr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Debug::fmt(self, f)
+	}
+}
+
+impl core::error::Error for SerdeErr {}
+
+#[cfg(test)]
+mod tests {
+	use crate::{BitReader, Serde};
+
+	#[test]
+	fn context() {
+		let mut reader = BitReader::from_slice(&[1, 2]);
+		reader.read::<u8>().unwrap();
+		let err = reader.read::<(u8, u16)>().unwrap_err();
+		assert_eq!((err.position(), err.type_name()), (Some(16), Some("(u8, u16)")));
+		assert_eq!(err.to_string(), "Bin deserialize error reading (u8, u16) at bit 16");
+
+		let err = String::de(&mut BitReader::from_slice(&[0xff])).unwrap_err();
+		assert_eq!(err.position(), Some(8));
+	}
+}
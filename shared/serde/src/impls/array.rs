@@ -4,6 +4,7 @@ use crate::{
     error::SerdeErr,
     serde::{ConstBitLength, Serde},
 };
+use alloc::vec::Vec;
 
 impl<T: Serde> Serde for &[T] {
     fn ser(&self, writer: &mut dyn BitWrite) {
@@ -13,7 +14,7 @@ impl<T: Serde> Serde for &[T] {
     }
 
     fn de(_: &mut BitReader) -> Result<Self, SerdeErr> {
-        Err(SerdeErr {})
+        Err(SerdeErr::new())
     }
 
     fn bit_length(&self) -> u32 {
@@ -33,13 +34,11 @@ impl<T: Serde, const N: usize> Serde for [T; N] {
     }
 
     fn de(reader: &mut BitReader) -> Result<Self, SerdeErr> {
-        unsafe {
-            let mut to = std::mem::MaybeUninit::<[T; N]>::uninit();
-            let top: *mut T = &mut to as *mut std::mem::MaybeUninit<[T; N]> as *mut T;
-            for c in 0..N {
-                top.add(c).write(Serde::de(reader)?);
-            }
-            Ok(to.assume_init())
+        // collected first, so the items read are dropped if a later one fails
+        let items = (0..N).map(|_| T::de(reader)).collect::<Result<Vec<T>, SerdeErr>>()?;
+        match items.try_into() {
+            Ok(array) => Ok(array),
+            Err(_) => unreachable!("collected N items"),
         }
     }
 
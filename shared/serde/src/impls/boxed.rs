@@ -3,8 +3,9 @@ use crate::{
     bit_writer::BitWrite,
     error::SerdeErr,
     serde::{ConstBitLength, Serde},
-    UnsignedVariableInteger,
+    ByteLen,
 };
+use alloc::{boxed::Box, vec::Vec};
 
 impl<T: Serde> Serde for Box<T> {
     fn ser(&self, writer: &mut dyn BitWrite) {
@@ -28,8 +29,7 @@ impl<T: ConstBitLength> ConstBitLength for Box<T> {
 
 impl Serde for Box<[u8]> {
     fn ser(&self, writer: &mut dyn BitWrite) {
-        let length = UnsignedVariableInteger::<9>::new(self.len() as u64);
-        length.ser(writer);
+        ByteLen::ser(self.len(), writer);
         let bytes: &[u8] = self;
         for byte in bytes {
             writer.write_byte(*byte);
@@ -37,8 +37,8 @@ impl Serde for Box<[u8]> {
     }
 
     fn de(reader: &mut BitReader) -> Result<Box<[u8]>, SerdeErr> {
-        let length_int = UnsignedVariableInteger::<9>::de(reader)?;
-        let length_usize = length_int.get() as usize;
+        // reject untrusted lengths before allocating
+        let length_usize = ByteLen::de(reader, 8)?;
         let mut bytes: Vec<u8> = Vec::with_capacity(length_usize);
         for _ in 0..length_usize {
             bytes.push(reader.read_byte()?);
@@ -49,8 +49,7 @@ impl Serde for Box<[u8]> {
 
     fn bit_length(&self) -> u32 {
         let mut output = 0;
-        let length = UnsignedVariableInteger::<9>::new(self.len() as u64);
-        output += length.bit_length();
+        output += ByteLen::bit_length(self.len());
         output += (self.len() as u32) * 8;
         output
     }
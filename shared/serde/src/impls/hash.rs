@@ -1,6 +1,5 @@
 use crate::{
-    bit_reader::BitReader, bit_writer::BitWrite, error::SerdeErr, serde::Serde,
-    UnsignedVariableInteger,
+    bit_reader::BitReader, bit_writer::BitWrite, error::SerdeErr, serde::Serde, ItemLen,
 };
 use std::{
     collections::{HashMap, HashSet},
@@ -9,16 +8,14 @@ use std::{
 
 impl<K: Serde + Eq + Hash> Serde for HashSet<K> {
     fn ser(&self, writer: &mut dyn BitWrite) {
-        let length = UnsignedVariableInteger::<5>::new(self.len() as u64);
-        length.ser(writer);
+        ItemLen::ser(self.len(), writer);
         for value in self {
             value.ser(writer);
         }
     }
 
     fn de(reader: &mut BitReader) -> Result<Self, SerdeErr> {
-        let length_int = UnsignedVariableInteger::<5>::de(reader)?;
-        let length_usize = length_int.get() as usize;
+        let length_usize = ItemLen::de(reader, 1)?;
         let mut output: HashSet<K> = HashSet::new();
         for _ in 0..length_usize {
             let value = K::de(reader)?;
@@ -29,8 +26,7 @@ impl<K: Serde + Eq + Hash> Serde for HashSet<K> {
 
     fn bit_length(&self) -> u32 {
         let mut output = 0;
-        let length = UnsignedVariableInteger::<5>::new(self.len() as u64);
-        output += length.bit_length();
+        output += ItemLen::bit_length(self.len());
         for value in self {
             output += value.bit_length();
         }
@@ -40,8 +36,7 @@ impl<K: Serde + Eq + Hash> Serde for HashSet<K> {
 
 impl<K: Serde + Eq + Hash, V: Serde> Serde for HashMap<K, V> {
     fn ser(&self, writer: &mut dyn BitWrite) {
-        let length = UnsignedVariableInteger::<5>::new(self.len() as u64);
-        length.ser(writer);
+        ItemLen::ser(self.len(), writer);
         for (key, value) in self {
             key.ser(writer);
             value.ser(writer);
@@ -49,8 +44,7 @@ impl<K: Serde + Eq + Hash, V: Serde> Serde for HashMap<K, V> {
     }
 
     fn de(reader: &mut BitReader) -> Result<Self, SerdeErr> {
-        let length_int = UnsignedVariableInteger::<5>::de(reader)?;
-        let length_usize = length_int.get() as usize;
+        let length_usize = ItemLen::de(reader, 1)?;
         let mut output: HashMap<K, V> = HashMap::new();
         for _ in 0..length_usize {
             let key = K::de(reader)?;
@@ -62,8 +56,7 @@ impl<K: Serde + Eq + Hash, V: Serde> Serde for HashMap<K, V> {
 
     fn bit_length(&self) -> u32 {
         let mut output = 0;
-        let length = UnsignedVariableInteger::<5>::new(self.len() as u64);
-        output += length.bit_length();
+        output += ItemLen::bit_length(self.len());
         for (key, value) in self {
             output += key.bit_length();
             output += value.bit_length();
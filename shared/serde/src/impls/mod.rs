@@ -1,8 +1,121 @@
 mod array;
 mod boxed;
+#[cfg(feature = "std")]
 mod hash;
+mod net;
 mod option;
 mod scalars;
 mod string;
+mod time;
 mod tuple;
 mod vector;
+
+// Tests
+
+#[cfg(all(test, feature = "std"))]
+mod fuzz_tests {
+	use crate::{
+		BitField, BitReader, BitWriter, FixedAscii, Quantized, Secret, Serde, SerdeBounded,
+		SignedVariableInteger, UnsignedVariableInteger,
+	};
+	use std::{
+		collections::{HashMap, HashSet, VecDeque},
+		net::SocketAddr,
+		num::NonZeroU32,
+		time::{Duration, SystemTime},
+	};
+
+	/// xorshift64, deterministic so failures reproduce
+	struct Rng(u64);
+
+	impl Rng {
+		fn next(&mut self) -> u64 {
+			self.0 ^= self.0 << 13;
+			self.0 ^= self.0 >> 7;
+			self.0 ^= self.0 << 17;
+			self.0
+		}
+
+		fn bytes(&mut self) -> Vec<u8> {
+			let len = (self.next() % 96) as usize;
+			// mostly random bytes, sometimes runs of 0xff claiming huge lengths
+			match self.next() % 4 {
+				0 => vec![0xff; len],
+				_ => (0..len).map(|_| self.next() as u8).collect(),
+			}
+		}
+	}
+
+	/// Read `T` from random input, which must fail or succeed without panicking
+	fn fuzz<T: Serde>(rng: &mut Rng) {
+		for _ in 0..2000 {
+			let bytes = rng.bytes();
+			let _ = T::de(&mut BitReader::from_slice(&bytes));
+		}
+	}
+
+	#[derive(Clone, PartialEq)]
+	struct Bounded(Vec<u16>);
+
+	impl Serde for Bounded {
+		fn ser(&self, writer: &mut dyn crate::BitWrite) { self.0.ser_bounded(100, writer) }
+
+		fn de(reader: &mut BitReader) -> crate::SerdeResult<Self> { Vec::de_bounded(100, reader).map(Self) }
+
+		fn bit_length(&self) -> u32 { self.0.bit_length_bounded(100) }
+	}
+
+	#[test]
+	fn containers() {
+		let mut rng = Rng(0x2545f4914f6cdd1d);
+		fuzz::<String>(&mut rng);
+		fuzz::<Box<[u8]>>(&mut rng);
+		fuzz::<Box<String>>(&mut rng);
+		fuzz::<Vec<u8>>(&mut rng);
+		fuzz::<Vec<String>>(&mut rng);
+		fuzz::<Vec<Vec<u32>>>(&mut rng);
+		fuzz::<Vec<()>>(&mut rng);
+		fuzz::<VecDeque<u16>>(&mut rng);
+		fuzz::<VecDeque<()>>(&mut rng);
+		fuzz::<HashSet<u16>>(&mut rng);
+		fuzz::<HashSet<()>>(&mut rng);
+		fuzz::<HashMap<u8, String>>(&mut rng);
+		fuzz::<Option<Vec<u8>>>(&mut rng);
+		fuzz::<[String; 3]>(&mut rng);
+		fuzz::<(u8, String, bool)>(&mut rng);
+		fuzz::<Secret<String>>(&mut rng);
+		fuzz::<Bounded>(&mut rng);
+	}
+
+	#[test]
+	fn scalars() {
+		let mut rng = Rng(0x9e3779b97f4a7c15);
+		fuzz::<UnsignedVariableInteger<5>>(&mut rng);
+		fuzz::<UnsignedVariableInteger<9>>(&mut rng);
+		fuzz::<SignedVariableInteger<3>>(&mut rng);
+		fuzz::<SocketAddr>(&mut rng);
+		fuzz::<NonZeroU32>(&mut rng);
+		fuzz::<Duration>(&mut rng);
+		fuzz::<SystemTime>(&mut rng);
+		fuzz::<Quantized<-1, 1, 12>>(&mut rng);
+		fuzz::<BitField<77>>(&mut rng);
+		fuzz::<FixedAscii<6>>(&mut rng);
+	}
+
+	#[test]
+	fn huge_lengths() {
+		// lengths far beyond the input, each followed by a few bytes
+		for length in [u64::MAX, 1 << 40, 1 << 20, 100] {
+			let mut writer = BitWriter::new();
+			UnsignedVariableInteger::<5>::new(length).ser(&mut writer);
+			1u8.ser(&mut writer);
+			let bytes = writer.slice();
+			assert!(Vec::<()>::de(&mut BitReader::from_slice(bytes)).is_err());
+			assert!(Vec::<u8>::de(&mut BitReader::from_slice(bytes)).is_err());
+			assert!(HashSet::<()>::de(&mut BitReader::from_slice(bytes)).is_err());
+		}
+
+		// a variable length integer never ending
+		assert!(UnsignedVariableInteger::<5>::de(&mut BitReader::from_slice(&[0xff; 64])).is_err());
+	}
+}
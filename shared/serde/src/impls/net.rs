@@ -0,0 +1,140 @@
+use crate::{
+    bit_reader::BitReader,
+    bit_writer::BitWrite,
+    error::SerdeErr,
+    serde::{ConstBitLength, Serde},
+};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+impl Serde for Ipv4Addr {
+    fn ser(&self, writer: &mut dyn BitWrite) {
+        self.octets().ser(writer);
+    }
+
+    fn de(reader: &mut BitReader) -> Result<Ipv4Addr, SerdeErr> {
+        Ok(Ipv4Addr::from(<[u8; 4]>::de(reader)?))
+    }
+
+    fn bit_length(&self) -> u32 {
+        <Self as ConstBitLength>::const_bit_length()
+    }
+}
+
+impl ConstBitLength for Ipv4Addr {
+    fn const_bit_length() -> u32 {
+        32
+    }
+}
+
+impl Serde for Ipv6Addr {
+    fn ser(&self, writer: &mut dyn BitWrite) {
+        self.octets().ser(writer);
+    }
+
+    fn de(reader: &mut BitReader) -> Result<Ipv6Addr, SerdeErr> {
+        Ok(Ipv6Addr::from(<[u8; 16]>::de(reader)?))
+    }
+
+    fn bit_length(&self) -> u32 {
+        <Self as ConstBitLength>::const_bit_length()
+    }
+}
+
+impl ConstBitLength for Ipv6Addr {
+    fn const_bit_length() -> u32 {
+        128
+    }
+}
+
+impl Serde for IpAddr {
+    fn ser(&self, writer: &mut dyn BitWrite) {
+        match self {
+            IpAddr::V4(ip) => {
+                writer.write_bit(false);
+                ip.ser(writer);
+            }
+            IpAddr::V6(ip) => {
+                writer.write_bit(true);
+                ip.ser(writer);
+            }
+        }
+    }
+
+    fn de(reader: &mut BitReader) -> Result<IpAddr, SerdeErr> {
+        if reader.read_bit()? {
+            Ok(IpAddr::V6(Ipv6Addr::de(reader)?))
+        } else {
+            Ok(IpAddr::V4(Ipv4Addr::de(reader)?))
+        }
+    }
+
+    fn bit_length(&self) -> u32 {
+        1 + match self {
+            IpAddr::V4(ip) => ip.bit_length(),
+            IpAddr::V6(ip) => ip.bit_length(),
+        }
+    }
+}
+
+impl Serde for SocketAddr {
+    fn ser(&self, writer: &mut dyn BitWrite) {
+        self.ip().ser(writer);
+        self.port().ser(writer);
+    }
+
+    fn de(reader: &mut BitReader) -> Result<SocketAddr, SerdeErr> {
+        let ip = IpAddr::de(reader)?;
+        let port = u16::de(reader)?;
+        Ok(SocketAddr::new(ip, port))
+    }
+
+    fn bit_length(&self) -> u32 {
+        self.ip().bit_length() + 16
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use crate::{bit_reader::BitReader, bit_writer::BitWriter, serde::Serde};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+    #[test]
+    fn read_write() {
+        // Write
+        let mut writer = BitWriter::new();
+
+        let in_1: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let in_2: SocketAddr = "[::1]:65535".parse().unwrap();
+
+        in_1.ser(&mut writer);
+        in_2.ser(&mut writer);
+        assert_eq!(writer.slice().len(), (in_1.bit_length() + in_2.bit_length()).div_ceil(8) as usize);
+
+        //Read
+        let mut reader = BitReader::from_slice(writer.slice());
+
+        let out_1 = SocketAddr::de(&mut reader).unwrap();
+        let out_2 = SocketAddr::de(&mut reader).unwrap();
+
+        assert_eq!(in_1, out_1);
+        assert_eq!(in_2, out_2);
+    }
+
+    #[test]
+    fn read_write_ip() {
+        let mut writer = BitWriter::new();
+        let v4 = Ipv4Addr::new(10, 0, 0, 1);
+        let v6 = Ipv6Addr::LOCALHOST;
+        v4.ser(&mut writer);
+        v6.ser(&mut writer);
+        IpAddr::V4(v4).ser(&mut writer);
+        assert_eq!(writer.slice().len(), (32 + 128 + 33_u32).div_ceil(8) as usize);
+
+        let mut reader = BitReader::from_slice(writer.slice());
+        assert_eq!(Ipv4Addr::de(&mut reader).unwrap(), v4);
+        assert_eq!(Ipv6Addr::de(&mut reader).unwrap(), v6);
+        assert_eq!(IpAddr::de(&mut reader).unwrap(), IpAddr::V4(v4));
+    }
+}
@@ -32,6 +32,7 @@ mod unit_tests {
     use crate::{bit_reader::BitReader, bit_writer::BitWriter, serde::Serde};
 
     #[test]
+    #[allow(clippy::let_unit_value, clippy::unit_cmp)]
     fn read_write() {
         // Write
         let mut writer = BitWriter::new();
@@ -111,7 +112,7 @@ macro_rules! impl_serde_for {
             }
 
             fn de(reader: &mut BitReader) -> Result<$impl_type, SerdeErr> {
-                const BYTES_LENGTH: usize = std::mem::size_of::<$impl_type>();
+                const BYTES_LENGTH: usize = core::mem::size_of::<$impl_type>();
                 let mut byte_array = [0_u8; BYTES_LENGTH];
                 for index in 0..BYTES_LENGTH {
                     byte_array[index] = reader.read_byte()?;
@@ -125,7 +126,7 @@ macro_rules! impl_serde_for {
         }
         impl ConstBitLength for $impl_type {
             fn const_bit_length() -> u32 {
-                const BYTES_LENGTH: u32 = std::mem::size_of::<$impl_type>() as u32;
+                const BYTES_LENGTH: u32 = core::mem::size_of::<$impl_type>() as u32;
                 return BYTES_LENGTH * 8;
             }
         }
@@ -144,6 +145,40 @@ impl_serde_for!(i64);
 impl_serde_for!(f32);
 impl_serde_for!(f64);
 
+// Non-zero Integers //
+
+macro_rules! impl_serde_for_non_zero {
+    ($impl_type:ident, $inner_type:ident) => {
+        impl Serde for core::num::$impl_type {
+            fn ser(&self, writer: &mut dyn BitWrite) {
+                self.get().ser(writer);
+            }
+
+            fn de(reader: &mut BitReader) -> Result<Self, SerdeErr> {
+                Self::new($inner_type::de(reader)?).ok_or_else(|| SerdeErr::at(reader.position()))
+            }
+
+            fn bit_length(&self) -> u32 {
+                <Self as ConstBitLength>::const_bit_length()
+            }
+        }
+        impl ConstBitLength for core::num::$impl_type {
+            fn const_bit_length() -> u32 {
+                <$inner_type as ConstBitLength>::const_bit_length()
+            }
+        }
+    };
+}
+
+impl_serde_for_non_zero!(NonZeroU8, u8);
+impl_serde_for_non_zero!(NonZeroU16, u16);
+impl_serde_for_non_zero!(NonZeroU32, u32);
+impl_serde_for_non_zero!(NonZeroU64, u64);
+impl_serde_for_non_zero!(NonZeroI8, i8);
+impl_serde_for_non_zero!(NonZeroI16, i16);
+impl_serde_for_non_zero!(NonZeroI32, i32);
+impl_serde_for_non_zero!(NonZeroI64, i64);
+
 impl ConstBitLength for isize {
     fn const_bit_length() -> u32 {
         <u64 as ConstBitLength>::const_bit_length()
@@ -177,4 +212,20 @@ mod tests {
     test_roundtrip!(i64, i64_roundtrip, -1234567890123456789i64);
     test_roundtrip!(f32, f32_roundtrip, 123.456f32);
     test_roundtrip!(f64, f64_roundtrip, 1234567890123456789.1234567890123456789f64);
+
+    #[test]
+    fn non_zero_roundtrip() {
+        use std::num::{NonZeroI16, NonZeroU32};
+
+        let mut writer = BitWriter::new();
+        NonZeroU32::new(7).unwrap().ser(&mut writer);
+        NonZeroI16::new(-3).unwrap().ser(&mut writer);
+        0u32.ser(&mut writer);
+        assert_eq!(writer.slice().len(), 10);
+
+        let mut reader = BitReader::from_slice(writer.slice());
+        assert_eq!(NonZeroU32::de(&mut reader), Ok(NonZeroU32::new(7).unwrap()));
+        assert_eq!(NonZeroI16::de(&mut reader), Ok(NonZeroI16::new(-3).unwrap()));
+        assert!(NonZeroU32::de(&mut reader).is_err());
+    }
 }
@@ -1,12 +1,11 @@
 use crate::{
-    bit_reader::BitReader, bit_writer::BitWrite, error::SerdeErr, serde::Serde,
-    UnsignedVariableInteger,
+    bit_reader::BitReader, bit_writer::BitWrite, error::SerdeErr, serde::Serde, ByteLen,
 };
+use alloc::{string::String, vec::Vec};
 
 impl Serde for String {
     fn ser(&self, writer: &mut dyn BitWrite) {
-        let length = UnsignedVariableInteger::<9>::new(self.len() as u64);
-        length.ser(writer);
+        ByteLen::ser(self.len(), writer);
         let bytes = self.as_bytes();
         for byte in bytes {
             writer.write_byte(*byte);
@@ -14,21 +13,23 @@ impl Serde for String {
     }
 
     fn de(reader: &mut BitReader) -> Result<Self, SerdeErr> {
-        let length_int = UnsignedVariableInteger::<9>::de(reader)?;
-        let length_usize = length_int.get() as usize;
+        // reject untrusted lengths before allocating
+        let length_usize = ByteLen::de(reader, 8)?;
         let mut bytes: Vec<u8> = Vec::with_capacity(length_usize);
         for _ in 0..length_usize {
             bytes.push(reader.read_byte()?);
         }
 
-        let result = String::from_utf8_lossy(&bytes).into_owned();
+        // reuse the buffer for valid strings, so no copies are left behind, e.g. of
+        // secrets
+        let result = String::from_utf8(bytes)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned());
         Ok(result)
     }
 
     fn bit_length(&self) -> u32 {
         let mut output = 0;
-        let length = UnsignedVariableInteger::<9>::new(self.len() as u64);
-        output += length.bit_length();
+        output += ByteLen::bit_length(self.len());
         output += (self.len() as u32) * 8;
         output
     }
@@ -38,7 +39,7 @@ impl Serde for String {
 
 #[cfg(test)]
 mod tests {
-    use crate::{bit_reader::BitReader, bit_writer::BitWriter, serde::Serde};
+    use crate::{bit_reader::BitReader, bit_writer::BitWriter, serde::Serde, UnsignedVariableInteger};
 
     #[test]
     fn read_write() {
@@ -60,4 +61,13 @@ mod tests {
         assert_eq!(in_1, out_1);
         assert_eq!(in_2, out_2);
     }
+
+    #[test]
+    fn read_truncated() {
+        let mut writer = BitWriter::new();
+        UnsignedVariableInteger::<9>::new(1u64 << 40).ser(&mut writer);
+
+        let mut reader = BitReader::from_slice(writer.slice());
+        assert!(String::de(&mut reader).is_err());
+    }
 }
@@ -0,0 +1,102 @@
+use crate::{
+    bit_reader::BitReader,
+    bit_writer::BitWrite,
+    error::SerdeErr,
+    integer::UnsignedVariableInteger,
+    serde::Serde,
+};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+fn from_nanos(nanos: u128) -> Result<Duration, SerdeErr> {
+    let secs = u64::try_from(nanos / NANOS_PER_SEC).map_err(|_| SerdeErr::new())?;
+    Ok(Duration::new(secs, (nanos % NANOS_PER_SEC) as u32))
+}
+
+// Durations are written as nanoseconds, in 7 bit chunks
+type Nanos = UnsignedVariableInteger<7>;
+// Times are written as nanoseconds since the unix epoch, negative before it
+#[cfg(feature = "std")]
+type UnixNanos = crate::SignedVariableInteger<7>;
+
+impl Serde for Duration {
+    fn ser(&self, writer: &mut dyn BitWrite) {
+        Nanos::new(self.as_nanos() as i128).ser(writer);
+    }
+
+    fn de(reader: &mut BitReader) -> Result<Self, SerdeErr> {
+        let nanos = Nanos::de(reader)?.get();
+        from_nanos(u128::try_from(nanos).map_err(|_| SerdeErr::new())?)
+    }
+
+    fn bit_length(&self) -> u32 {
+        Nanos::new(self.as_nanos() as i128).bit_length()
+    }
+}
+
+#[cfg(feature = "std")]
+fn unix_nanos(time: &SystemTime) -> UnixNanos {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => UnixNanos::new(since.as_nanos() as i128),
+        Err(e) => UnixNanos::new(-(e.duration().as_nanos() as i128)),
+    }
+}
+
+#[cfg(feature = "std")]
+impl Serde for SystemTime {
+    fn ser(&self, writer: &mut dyn BitWrite) {
+        unix_nanos(self).ser(writer);
+    }
+
+    fn de(reader: &mut BitReader) -> Result<Self, SerdeErr> {
+        let nanos = UnixNanos::de(reader)?.get();
+        let time = match nanos >= 0 {
+            true => UNIX_EPOCH.checked_add(from_nanos(nanos.unsigned_abs())?),
+            false => UNIX_EPOCH.checked_sub(from_nanos(nanos.unsigned_abs())?),
+        };
+        time.ok_or(SerdeErr::new())
+    }
+
+    fn bit_length(&self) -> u32 {
+        unix_nanos(self).bit_length()
+    }
+}
+
+// Tests
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{bit_reader::BitReader, bit_writer::BitWriter, serde::Serde};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn read_write() {
+        let mut writer = BitWriter::new();
+        let now = SystemTime::now();
+        let before_epoch = UNIX_EPOCH - Duration::new(86_400, 123);
+        let durations = [Duration::ZERO, Duration::from_millis(16), Duration::new(u64::MAX, 999_999_999)];
+
+        now.ser(&mut writer);
+        before_epoch.ser(&mut writer);
+        UNIX_EPOCH.ser(&mut writer);
+        for duration in &durations {
+            duration.ser(&mut writer);
+        }
+        let bits = now.bit_length() + before_epoch.bit_length() + UNIX_EPOCH.bit_length()
+            + durations.iter().map(Serde::bit_length).sum::<u32>();
+        assert_eq!(writer.slice().len(), bits.div_ceil(8) as usize);
+        // small values stay small
+        assert_eq!(Duration::ZERO.bit_length(), 8);
+
+        let mut reader = BitReader::from_slice(writer.slice());
+        assert_eq!(SystemTime::de(&mut reader).unwrap(), now);
+        assert_eq!(SystemTime::de(&mut reader).unwrap(), before_epoch);
+        assert_eq!(SystemTime::de(&mut reader).unwrap(), UNIX_EPOCH);
+        for duration in &durations {
+            assert_eq!(Duration::de(&mut reader).unwrap(), *duration);
+        }
+    }
+}
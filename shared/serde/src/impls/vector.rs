@@ -1,22 +1,20 @@
-use std::collections::VecDeque;
+use alloc::{collections::VecDeque, vec::Vec};
 
 use crate::{
-    bit_reader::BitReader, bit_writer::BitWrite, error::SerdeErr, serde::Serde,
-    UnsignedVariableInteger,
+    bit_reader::BitReader, bit_writer::BitWrite, error::SerdeErr, serde::Serde, ItemLen,
 };
 
 impl<T: Serde> Serde for Vec<T> {
     fn ser(&self, writer: &mut dyn BitWrite) {
-        let length = UnsignedVariableInteger::<5>::new(self.len() as u64);
-        length.ser(writer);
+        ItemLen::ser(self.len(), writer);
         for item in self {
             item.ser(writer);
         }
     }
 
     fn de(reader: &mut BitReader) -> Result<Self, SerdeErr> {
-        let length_int = UnsignedVariableInteger::<5>::de(reader)?;
-        let length_usize = length_int.get() as usize;
+        // the length is untrusted, so don't preallocate beyond what the reader could hold
+        let length_usize = ItemLen::de(reader, 1)?;
         let mut output: Vec<T> = Vec::with_capacity(length_usize);
         for _ in 0..length_usize {
             output.push(T::de(reader)?)
@@ -26,8 +24,7 @@ impl<T: Serde> Serde for Vec<T> {
 
     fn bit_length(&self) -> u32 {
         let mut output = 0;
-        let length = UnsignedVariableInteger::<5>::new(self.len() as u64);
-        output += length.bit_length();
+        output += ItemLen::bit_length(self.len());
         for item in self {
             output += item.bit_length();
         }
@@ -37,16 +34,14 @@ impl<T: Serde> Serde for Vec<T> {
 
 impl<T: Serde> Serde for VecDeque<T> {
     fn ser(&self, writer: &mut dyn BitWrite) {
-        let length = UnsignedVariableInteger::<5>::new(self.len() as u64);
-        length.ser(writer);
+        ItemLen::ser(self.len(), writer);
         for item in self {
             item.ser(writer);
         }
     }
 
     fn de(reader: &mut BitReader) -> Result<Self, SerdeErr> {
-        let length_int = UnsignedVariableInteger::<5>::de(reader)?;
-        let length_usize = length_int.get() as usize;
+        let length_usize = ItemLen::de(reader, 1)?;
         let mut output: VecDeque<T> = VecDeque::with_capacity(length_usize);
         for _ in 0..length_usize {
             output.push_back(T::de(reader)?)
@@ -56,8 +51,7 @@ impl<T: Serde> Serde for VecDeque<T> {
 
     fn bit_length(&self) -> u32 {
         let mut output = 0;
-        let length = UnsignedVariableInteger::<5>::new(self.len() as u64);
-        output += length.bit_length();
+        output += ItemLen::bit_length(self.len());
         for item in self {
             output += item.bit_length();
         }
@@ -4,7 +4,11 @@ use crate::{
 
 pub type UnsignedInteger<const BITS: u8> = SerdeInteger<false, false, BITS>;
 pub type SignedInteger<const BITS: u8> = SerdeInteger<true, false, BITS>;
+/// Written in chunks of a continuation bit followed by the next `BITS` bits of the
+/// value, least significant first, until a chunk's continuation bit is 0; see `VarLen`
+/// for length prefixes
 pub type UnsignedVariableInteger<const BITS: u8> = SerdeInteger<false, true, BITS>;
+/// A sign bit (1 if negative), then the magnitude as an `UnsignedVariableInteger`
 pub type SignedVariableInteger<const BITS: u8> = SerdeInteger<true, true, BITS>;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -90,11 +94,7 @@ impl<const SIGNED: bool, const VARIABLE: bool, const BITS: u8> Serde
         if VARIABLE {
             let mut proceed;
             loop {
-                if value >= 2_u128.pow(BITS as u32) {
-                    proceed = true;
-                } else {
-                    proceed = false;
-                }
+                proceed = value >= 2_u128.pow(BITS as u32);
                 writer.write_bit(proceed);
 
                 for _ in 0..BITS {
@@ -136,11 +136,16 @@ impl<const SIGNED: bool, const VARIABLE: bool, const BITS: u8> Serde
                     }
                 }
 
+                // the value must fit an i128, however many chunks the input claims
+                if total_bits > 128 {
+                    return Err(SerdeErr::at(reader.position()));
+                }
+
                 if !proceed {
                     output <<= 128 - total_bits;
                     output = output.reverse_bits();
 
-                    let value: i128 = output as i128;
+                    let value = i128::try_from(output).map_err(|_| SerdeErr::at(reader.position()))?;
                     if negative {
                         return Ok(SerdeInteger::new_unchecked(-value));
                     } else {
@@ -180,13 +185,9 @@ impl<const SIGNED: bool, const VARIABLE: bool, const BITS: u8> Serde
 
         if VARIABLE {
             let mut proceed;
-            let mut value = self.inner.abs() as u128;
+            let mut value = self.inner.unsigned_abs();
             loop {
-                if value >= 2_u128.pow(BITS as u32) {
-                    proceed = true;
-                } else {
-                    proceed = false;
-                }
+                proceed = value >= 2_u128.pow(BITS as u32);
                 output += 1;
 
                 for _ in 0..BITS {
@@ -1,26 +1,44 @@
+//! Bit-level de/serialization for naia. Builds with `no_std` + `alloc` without the
+//! default `std` feature, e.g. to reuse the wire format in embedded relays.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
 pub use naia_serde_derive::{
     Serde, SerdeInternal,
 };
 
 mod bit_counter;
+mod bit_field;
 mod bit_reader;
 mod bit_writer;
+mod bounded;
 mod constants;
 mod error;
 mod impls;
 mod integer;
+mod quantize;
+mod remote;
+mod secret;
 mod serde;
+mod var_len;
 
 pub use bit_counter::BitCounter;
-pub use bit_reader::BitReader;
-pub use bit_writer::{BitWrite, BitWriter};
+pub use bit_field::{BitField, FixedAscii};
+pub use bit_reader::{BitReader, Checkpoint};
+pub use bit_writer::{BitVecWriter, BitWrite, BitWriter};
+pub use bounded::SerdeBounded;
 pub use constants::{MTU_SIZE_BITS, MTU_SIZE_BYTES};
 pub use error::{SerdeErr, SerdeResult};
 pub use integer::{
     SerdeIntegerConversion, SignedInteger, SignedVariableInteger, UnsignedInteger,
     UnsignedVariableInteger,
 };
+pub use quantize::{dequantize, quantize, Quantized};
+pub use secret::Secret;
 pub use serde::{
     ConstBitLength, Serde, Serde as SerdeInternal,
 };
+pub use var_len::{ByteLen, ItemLen, VarLen};
+pub use zeroize::Zeroize;
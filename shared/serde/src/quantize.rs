@@ -0,0 +1,109 @@
+use crate::{BitReader, BitWrite, ConstBitLength, Serde, SerdeResult, UnsignedInteger};
+
+fn steps(bits: u8) -> f64 {
+	debug_assert!((1..=32).contains(&bits), "quantize to 1 to 32 bits");
+	((1_u64 << bits) - 1) as f64
+}
+
+/// Map `value` to the nearest of `2^bits` evenly spaced steps across `[min, max]`,
+/// clamping values outside the range. The error is at most half a step, i.e.
+/// `(max - min) / (2^bits - 1) / 2`.
+pub fn quantize(value: f32, min: f32, max: f32, bits: u8) -> u32 {
+	let t = ((f64::from(value) - f64::from(min)) / (f64::from(max) - f64::from(min))).clamp(0.0, 1.0);
+	// rounded half up by hand, as f64::round() needs std; NaN maps to the first step
+	let x = t * steps(bits);
+	let floor = x as u32;
+	if x - f64::from(floor) >= 0.5 { floor + 1 } else { floor }
+}
+
+/// The value of a step returned by `quantize()`
+pub fn dequantize(step: u32, min: f32, max: f32, bits: u8) -> f32 {
+	let t = f64::from(step) / steps(bits);
+	(f64::from(min) + t * (f64::from(max) - f64::from(min))) as f32
+}
+
+/// An f32 within `[MIN, MAX]`, written as a `BITS` bit integer (1 to 32), e.g.
+/// `Quantized<-1, 1, 12>` for the components of a unit vector or quaternion. The
+/// value is quantized when set, so it compares equal once read back.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Quantized<const MIN: i32, const MAX: i32, const BITS: u8>(f32);
+
+impl<const MIN: i32, const MAX: i32, const BITS: u8> Quantized<MIN, MAX, BITS> {
+	pub fn new(value: f32) -> Self {
+		Self::from_step(Self::step(value))
+	}
+
+	pub fn get(&self) -> f32 { self.0 }
+
+	fn step(value: f32) -> u32 { quantize(value, MIN as f32, MAX as f32, BITS) }
+
+	fn from_step(step: u32) -> Self { Self(dequantize(step, MIN as f32, MAX as f32, BITS)) }
+}
+
+impl<const MIN: i32, const MAX: i32, const BITS: u8> From<f32> for Quantized<MIN, MAX, BITS> {
+	fn from(value: f32) -> Self { Self::new(value) }
+}
+
+impl<const MIN: i32, const MAX: i32, const BITS: u8> From<Quantized<MIN, MAX, BITS>> for f32 {
+	fn from(value: Quantized<MIN, MAX, BITS>) -> Self { value.0 }
+}
+
+impl<const MIN: i32, const MAX: i32, const BITS: u8> Serde for Quantized<MIN, MAX, BITS> {
+	fn ser(&self, writer: &mut dyn BitWrite) {
+		UnsignedInteger::<BITS>::new(Self::step(self.0)).ser(writer)
+	}
+
+	fn de(reader: &mut BitReader) -> SerdeResult<Self> {
+		let step = UnsignedInteger::<BITS>::de(reader)?;
+		Ok(Self::from_step(step.to()))
+	}
+
+	fn bit_length(&self) -> u32 { u32::from(BITS) }
+}
+
+impl<const MIN: i32, const MAX: i32, const BITS: u8> ConstBitLength for Quantized<MIN, MAX, BITS> {
+	fn const_bit_length() -> u32 { u32::from(BITS) }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::BitWriter;
+
+	#[test]
+	fn error_bound() {
+		for bits in [1, 8, 12, 24, 32] {
+			let half_step = 200.0 / steps(bits) / 2.0;
+			for i in 0..=1000 {
+				let value = -100.0 + i as f32 * 0.2;
+				let step = quantize(value, -100.0, 100.0, bits);
+				let error = f64::from(dequantize(step, -100.0, 100.0, bits) - value).abs();
+				assert!(error <= half_step + 1e-4, "{value} with {bits} bits is off by {error}");
+			}
+		}
+		assert_eq!(quantize(-5.0, 0.0, 1.0, 8), 0);
+		assert_eq!(quantize(5.0, 0.0, 1.0, 8), 255);
+		assert_eq!(quantize(f32::NAN, 0.0, 1.0, 8), 0);
+	}
+
+	#[test]
+	fn read_write() {
+		type Unit = Quantized<-1, 1, 12>;
+		let values = [Unit::new(-1.0), Unit::new(0.3), Unit::new(1.0), Unit::new(7.0)];
+		assert_eq!(values[3], values[2]);
+
+		let mut writer = BitWriter::new();
+		for value in &values {
+			value.ser(&mut writer);
+		}
+		true.ser(&mut writer);
+		assert_eq!(values[1].bit_length(), 12);
+
+		let mut reader = BitReader::from_slice(writer.slice());
+		for value in &values {
+			assert_eq!(Unit::de(&mut reader).unwrap(), *value);
+		}
+		assert!(bool::de(&mut reader).unwrap());
+		assert!((values[1].get() - 0.3).abs() < 0.001);
+	}
+}
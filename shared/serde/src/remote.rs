@@ -0,0 +1,66 @@
+/// Declares a local newtype wrapping a type from a foreign crate, e.g. `glam::Vec3`,
+/// and implements `Serde` for it by converting to and from a representation which
+/// already implements `Serde`. The orphan rule prevents implementing `Serde` for the
+/// foreign type itself. The representation must implement `From` the foreign type,
+/// and `Into` it; for whole foreign structs, derive `Serde` on a local mirror struct
+/// and implement the conversions for it.
+///
+/// The wrapper derefs to the foreign type, converts from and into it, and can be
+/// used as a field of Messages, or as the only field of a Message for the foreign
+/// type as a whole.
+///
+/// ```ignore
+/// serde_remote! {
+///     /// A position, sent as its three components
+///     #[derive(Debug)]
+///     pub struct Position(pub glam::Vec3) as [f32; 3];
+/// }
+///
+/// #[derive(Message)]
+/// pub struct Spawn {
+///     pub position: Position,
+/// }
+/// ```
+#[macro_export]
+macro_rules! serde_remote {
+	(
+		$(#[$attr:meta])*
+		$vis:vis struct $name:ident($inner_vis:vis $remote:ty) as $repr:ty;
+	) => {
+		$(#[$attr])*
+		#[derive(Clone, PartialEq)]
+		$vis struct $name($inner_vis $remote);
+
+		impl From<$remote> for $name {
+			fn from(value: $remote) -> Self { Self(value) }
+		}
+
+		impl From<$name> for $remote {
+			fn from(value: $name) -> Self { value.0 }
+		}
+
+		impl core::ops::Deref for $name {
+			type Target = $remote;
+
+			fn deref(&self) -> &$remote { &self.0 }
+		}
+
+		impl core::ops::DerefMut for $name {
+			fn deref_mut(&mut self) -> &mut $remote { &mut self.0 }
+		}
+
+		impl $crate::Serde for $name {
+			fn ser(&self, writer: &mut dyn $crate::BitWrite) {
+				<$repr as $crate::Serde>::ser(&<$repr>::from(self.0.clone()), writer)
+			}
+
+			fn de(reader: &mut $crate::BitReader) -> $crate::SerdeResult<Self> {
+				<$repr as $crate::Serde>::de(reader).map(|repr| Self(repr.into()))
+			}
+
+			fn bit_length(&self) -> u32 {
+				<$repr as $crate::Serde>::bit_length(&<$repr>::from(self.0.clone()))
+			}
+		}
+	};
+}
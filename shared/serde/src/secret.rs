@@ -0,0 +1,88 @@
+use crate::{BitReader, BitWrite, ConstBitLength, Serde, SerdeResult};
+use core::fmt;
+use zeroize::Zeroize;
+
+/// Wraps sensitive values such as passwords or auth tokens, e.g. in connect messages.
+/// The value is zeroed when dropped, the bits it was read from are zeroed in the
+/// reader's buffer, and `Debug` doesn't print it.
+#[derive(Clone, Default, PartialEq)]
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+	pub fn new(value: T) -> Self { Self(value) }
+
+	pub fn expose(&self) -> &T { &self.0 }
+
+	pub fn expose_mut(&mut self) -> &mut T { &mut self.0 }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+	fn from(value: T) -> Self { Self(value) }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+	fn drop(&mut self) { self.0.zeroize() }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("Secret(<redacted>)")
+	}
+}
+
+impl<T: Serde + Zeroize> Serde for Secret<T> {
+	fn ser(&self, writer: &mut dyn BitWrite) { self.0.ser(writer) }
+
+	fn de(reader: &mut BitReader) -> SerdeResult<Self> {
+		let start = reader.position();
+		let value = T::de(reader);
+		reader.zero_since(start);
+		Ok(Self(value?))
+	}
+
+	fn bit_length(&self) -> u32 { self.0.bit_length() }
+}
+
+impl<T: ConstBitLength + Zeroize> ConstBitLength for Secret<T> {
+	fn const_bit_length() -> u32 { T::const_bit_length() }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::BitWriter;
+
+	#[test]
+	fn read_write() {
+		let mut writer = BitWriter::new();
+		true.ser(&mut writer);
+		Secret::new("hunter2".to_string()).ser(&mut writer);
+		7u8.ser(&mut writer);
+
+		let mut reader = BitReader::from_slice(writer.slice());
+		assert!(bool::de(&mut reader).unwrap());
+		let secret = Secret::<String>::de(&mut reader).unwrap();
+		assert_eq!(secret.expose(), "hunter2");
+		assert_eq!(u8::de(&mut reader).unwrap(), 7);
+
+		// only the secret's bits are zeroed
+		let original = BitReader::from_slice(writer.slice());
+		let mut reader = original.clone();
+		bool::de(&mut reader).unwrap();
+		let start = reader.position();
+		Secret::<String>::de(&mut reader).unwrap();
+		let end = reader.position();
+		let bit = |reader: &BitReader, i: usize| reader.buffer()[i / 8] & (0x80 >> (i % 8)) != 0;
+		for i in 0..8 * writer.slice().len() {
+			let expected = bit(&original, i) && !(start..end).contains(&i);
+			assert_eq!(bit(&reader, i), expected, "bit {i}");
+		}
+		assert_eq!(u8::de(&mut reader).unwrap(), 7);
+	}
+
+	#[test]
+	fn debug_redacts() {
+		let secret = Secret::new("hunter2".to_string());
+		assert_eq!(format!("{secret:?}"), "Secret(<redacted>)");
+	}
+}
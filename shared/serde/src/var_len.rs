@@ -0,0 +1,101 @@
+use crate::{BitReader, BitWrite, Serde, SerdeErr, SerdeResult, UnsignedVariableInteger};
+
+/// The length prefix of variable length values, written as an
+/// `UnsignedVariableInteger<BITS>`: chunks of a continuation bit followed by the next
+/// `BITS` bits of the length, least significant first, until a chunk's continuation
+/// bit is 0. E.g. with `BITS = 5`, a length of 40 (`0b101000`) is written as the bits
+/// `1 00010 0 10000`, and lengths below 32 take a single 6 bit chunk.
+///
+/// The built-in impls prefix collections with `ItemLen`, and strings and byte slices
+/// with `ByteLen`.
+pub struct VarLen<const BITS: u8>;
+
+/// The length prefix of collections, e.g. `Vec`, `VecDeque`, `HashMap` and `HashSet`
+pub type ItemLen = VarLen<5>;
+
+/// The length prefix of `String`, `Box<[u8]>`, and other byte strings
+pub type ByteLen = VarLen<9>;
+
+impl<const BITS: u8> VarLen<BITS> {
+	pub fn ser(len: usize, writer: &mut dyn BitWrite) {
+		UnsignedVariableInteger::<BITS>::new(len as u64).ser(writer);
+	}
+
+	/// Read an untrusted length, rejecting lengths of more items than the reader could
+	/// still hold at `item_bits` each. Every item takes at least a bit, except for zero
+	/// bit items, e.g. `()`, whose collections are limited to the remaining bits.
+	pub fn de(reader: &mut BitReader, item_bits: usize) -> SerdeResult<usize> {
+		let length = Self::de_unbounded(reader)?;
+		match length.checked_mul(item_bits) {
+			Some(bits) if bits <= reader.remaining_bits() => Ok(length),
+			_ => Err(SerdeErr::at(reader.position())),
+		}
+	}
+
+	/// Read a length without bounding it, e.g. of data decompressed from fewer bits.
+	/// Callers must bound it before allocating.
+	pub fn de_unbounded(reader: &mut BitReader) -> SerdeResult<usize> {
+		let length = UnsignedVariableInteger::<BITS>::de(reader)?.get();
+		usize::try_from(length).map_err(|_| SerdeErr::at(reader.position()))
+	}
+
+	/// The number of bits `ser()` writes for `len`
+	pub fn bit_length(len: usize) -> u32 {
+		let significant_bits = usize::BITS - len.leading_zeros();
+		significant_bits.div_ceil(u32::from(BITS)).max(1) * (u32::from(BITS) + 1)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{BitCounter, BitWriter};
+
+	#[test]
+	fn encoding() {
+		let mut writer = BitWriter::new();
+		ItemLen::ser(40, &mut writer);
+		let mut reader = BitReader::from_slice(writer.slice());
+		let bits: Vec<u8> = (0..12).map(|_| u8::from(reader.read_bit().unwrap())).collect();
+		assert_eq!(bits, [1, 0, 0, 0, 1, 0, 0, 1, 0, 0, 0, 0]);
+
+		assert_eq!(ItemLen::bit_length(0), 6);
+		assert_eq!(ItemLen::bit_length(31), 6);
+		assert_eq!(ItemLen::bit_length(32), 12);
+		assert_eq!(ByteLen::bit_length(511), 10);
+		assert_eq!(ByteLen::bit_length(512), 20);
+	}
+
+	#[test]
+	fn bit_length() {
+		let lens = (0..5000).chain((10..64).map(|shift| (1 << shift) - 1)).chain([usize::MAX]);
+		for len in lens {
+			let mut counter = BitCounter::new(u32::MAX);
+			ItemLen::ser(len, &mut counter);
+			assert_eq!(counter.bits_needed(), ItemLen::bit_length(len), "{len}");
+			let mut counter = BitCounter::new(u32::MAX);
+			ByteLen::ser(len, &mut counter);
+			assert_eq!(counter.bits_needed(), ByteLen::bit_length(len), "{len}");
+
+			let mut writer = BitWriter::new();
+			ItemLen::ser(len, &mut writer);
+			ByteLen::ser(len, &mut writer);
+
+			let mut reader = BitReader::from_slice(writer.slice());
+			assert_eq!(ItemLen::de_unbounded(&mut reader), Ok(len));
+			assert_eq!(ByteLen::de_unbounded(&mut reader), Ok(len));
+		}
+	}
+
+	#[test]
+	fn untrusted() {
+		let mut writer = BitWriter::new();
+		ByteLen::ser(3, &mut writer);
+		writer.write_byte(1);
+		writer.write_byte(2);
+		let bytes = writer.slice();
+		assert!(ByteLen::de(&mut BitReader::from_slice(bytes), 8).is_err());
+		assert_eq!(ByteLen::de(&mut BitReader::from_slice(bytes), 1), Ok(3));
+		assert_eq!(ByteLen::de(&mut BitReader::from_slice(bytes), 0), Ok(3));
+	}
+}
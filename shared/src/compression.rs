@@ -0,0 +1,314 @@
+//! A small LZ77 byte compressor, used for the fields of Messages deriving with
+//! `#[message(compress)]`. The format is similar to an LZ4 block: a sequence of
+//! tokens, each followed by a run of literal bytes and optionally a back reference.
+//! Without the `compression` feature, fields are always sent as-is, and compressed
+//! fields from the remote host are refused.
+
+use naia_serde::{BitReader, BitWrite, ByteLen, Serde, SerdeErr};
+
+/// The largest decompressed payload accepted from the remote host
+pub const MAX_DECOMPRESSED_BYTES: usize = 1 << 20;
+
+#[cfg(feature = "compression")]
+const MIN_MATCH: usize = 4;
+#[cfg(feature = "compression")]
+const MAX_OFFSET: usize = u16::MAX as usize;
+#[cfg(feature = "compression")]
+const HASH_BITS: u32 = 12;
+/// Lengths of 15 or more continue in additional bytes
+#[cfg(feature = "compression")]
+const LEN_MASK: usize = 0xf;
+
+#[cfg(feature = "compression")]
+pub fn compress(input: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(input.len() / 2);
+	let mut table = [usize::MAX; 1 << HASH_BITS];
+	let mut anchor = 0;
+	let mut i = 0;
+
+	while i + MIN_MATCH <= input.len() {
+		let seq = u32::from_le_bytes(input[i..i + MIN_MATCH].try_into().unwrap());
+		let hash = (seq.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize;
+		let candidate = table[hash];
+		table[hash] = i;
+
+		let matched = candidate != usize::MAX
+			&& i - candidate <= MAX_OFFSET
+			&& input[candidate..candidate + MIN_MATCH] == input[i..i + MIN_MATCH];
+		if !matched {
+			i += 1;
+			continue;
+		}
+
+		let mut len = MIN_MATCH;
+		while i + len < input.len() && input[candidate + len] == input[i + len] {
+			len += 1;
+		}
+
+		write_sequence(&mut out, &input[anchor..i], Some(((i - candidate) as u16, len)));
+		i += len;
+		anchor = i;
+	}
+
+	write_sequence(&mut out, &input[anchor..], None);
+	out
+}
+
+#[cfg(feature = "compression")]
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], back_ref: Option<(u16, usize)>) {
+	let match_len = back_ref.map_or(0, |(_, len)| len - MIN_MATCH);
+	out.push(((literals.len().min(LEN_MASK) << 4) | match_len.min(LEN_MASK)) as u8);
+
+	write_len(out, literals.len());
+	out.extend_from_slice(literals);
+
+	if let Some((offset, _)) = back_ref {
+		out.extend_from_slice(&offset.to_le_bytes());
+		write_len(out, match_len);
+	}
+}
+
+#[cfg(feature = "compression")]
+fn write_len(out: &mut Vec<u8>, len: usize) {
+	if len < LEN_MASK {
+		return;
+	}
+
+	let mut rest = len - LEN_MASK;
+	while rest >= 255 {
+		out.push(255);
+		rest -= 255;
+	}
+	out.push(rest as u8);
+}
+
+/// Decompress `input`, which must expand to exactly `len` bytes
+#[cfg(feature = "compression")]
+pub fn decompress(input: &[u8], len: usize) -> Option<Vec<u8>> {
+	let mut out = Vec::with_capacity(len.min(MAX_DECOMPRESSED_BYTES));
+	let mut pos = 0;
+
+	loop {
+		let token = *input.get(pos)? as usize;
+		pos += 1;
+
+		let literal_len = read_len(input, &mut pos, token >> 4)?;
+		let literals = input.get(pos..pos.checked_add(literal_len)?)?;
+		if out.len() + literal_len > len {
+			return None;
+		}
+		out.extend_from_slice(literals);
+		pos += literal_len;
+
+		if pos == input.len() {
+			break;
+		}
+
+		let offset = u16::from_le_bytes([*input.get(pos)?, *input.get(pos + 1)?]) as usize;
+		pos += 2;
+		if offset == 0 || offset > out.len() {
+			return None;
+		}
+
+		let match_len = read_len(input, &mut pos, token & LEN_MASK)? + MIN_MATCH;
+		if out.len() + match_len > len {
+			return None;
+		}
+		// may overlap the bytes being written, so copy one at a time
+		for _ in 0..match_len {
+			out.push(out[out.len() - offset]);
+		}
+	}
+
+	(out.len() == len).then_some(out)
+}
+
+#[cfg(feature = "compression")]
+fn read_len(input: &[u8], pos: &mut usize, nibble: usize) -> Option<usize> {
+	let mut len = nibble;
+	if nibble < LEN_MASK {
+		return Some(len);
+	}
+
+	loop {
+		let byte = *input.get(*pos)?;
+		*pos += 1;
+		len = len.checked_add(byte as usize)?;
+		if byte != 255 {
+			return Some(len);
+		}
+	}
+}
+
+// Message field encoding: a compressed bit, then the decompressed length if compressed,
+// then the byte length and bytes of the payload. Fields are sent as-is when
+// compression doesn't make them smaller.
+
+/// The compressed form of `fields`, if it is smaller
+fn compressed(fields: &[u8]) -> Option<Vec<u8>> {
+	#[cfg(feature = "compression")]
+	{
+		let compressed = compress(fields);
+		(compressed.len() < fields.len()).then_some(compressed)
+	}
+	#[cfg(not(feature = "compression"))]
+	{
+		let _ = fields;
+		None
+	}
+}
+
+fn decompressed(payload: &[u8], len: usize) -> Option<Vec<u8>> {
+	#[cfg(feature = "compression")]
+	{
+		decompress(payload, len)
+	}
+	#[cfg(not(feature = "compression"))]
+	{
+		let _ = (payload, len);
+		None
+	}
+}
+
+/// The serialized fields of a Message in their wire encoding, compressed once so they
+/// can be measured and written any number of times
+#[derive(Clone)]
+pub struct EncodedFields {
+	/// The length of the fields before compression, if compressed
+	len: Option<usize>,
+	payload: Box<[u8]>,
+}
+
+impl EncodedFields {
+	pub fn new(fields: &[u8]) -> Self {
+		match compressed(fields) {
+			Some(compressed) => Self { len: Some(fields.len()), payload: compressed.into() },
+			None => Self { len: None, payload: fields.into() },
+		}
+	}
+
+	pub fn write(&self, writer: &mut dyn BitWrite) {
+		self.len.is_some().ser(writer);
+		if let Some(len) = self.len {
+			ByteLen::ser(len, writer);
+		}
+		ByteLen::ser(self.payload.len(), writer);
+		for byte in self.payload.iter() {
+			writer.write_byte(*byte);
+		}
+	}
+
+	/// The number of bits `write()` writes
+	pub fn bit_length(&self) -> u32 {
+		1 + self.len.map_or(0, ByteLen::bit_length)
+			+ ByteLen::bit_length(self.payload.len())
+			+ 8 * self.payload.len() as u32
+	}
+}
+
+/// Read fields written with `EncodedFields::write()`, returning a reader over the serialized
+/// fields. Compressed fields may not expand beyond `max_bytes`, or
+/// `MAX_DECOMPRESSED_BYTES` without a maximum.
+pub fn read_fields(max_bytes: Option<usize>, reader: &mut BitReader) -> Result<BitReader, SerdeErr> {
+	let is_compressed = bool::de(reader)?;
+	let len = if is_compressed { Some(ByteLen::de_unbounded(reader)?) } else { None };
+	// refuse oversized lengths before allocating anything for them
+	let max_len = max_bytes.map_or(MAX_DECOMPRESSED_BYTES, |max| max.min(MAX_DECOMPRESSED_BYTES));
+	if len.is_some_and(|len| len > max_len) {
+		return Err(SerdeErr::at(reader.position()));
+	}
+
+	let payload_len = ByteLen::de(reader, 8)?;
+	let payload: Vec<u8> = (0..payload_len).map(|_| reader.read_byte()).collect::<Result<_, _>>()?;
+
+	let fields = match len {
+		Some(len) => decompressed(&payload, len).ok_or_else(|| SerdeErr::at(reader.position()))?,
+		None => payload,
+	};
+	Ok(BitReader::new(fields.into_boxed_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use naia_serde::{BitCounter, BitWriter};
+
+	#[cfg(feature = "compression")]
+	fn roundtrip(input: &[u8]) -> usize {
+		let compressed = compress(input);
+		assert_eq!(decompress(&compressed, input.len()).as_deref(), Some(input));
+		compressed.len()
+	}
+
+	#[test]
+	#[cfg(feature = "compression")]
+	fn compress_roundtrip() {
+		assert_eq!(roundtrip(&[]), 1);
+		roundtrip(b"abc");
+		roundtrip(b"abcdabcdabcd");
+
+		// long runs and long literals use extended lengths
+		assert!(roundtrip(&[7; 1000]) < 20);
+		let noise: Vec<u8> = (0..1000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+		roundtrip(&noise);
+
+		let mut map = Vec::new();
+		for row in 0..64u8 {
+			map.extend_from_slice(&[0, 0, 1, 1, 2, row % 4, 0, 0]);
+		}
+		assert!(roundtrip(&map) < map.len() / 4);
+	}
+
+	#[test]
+	#[cfg(feature = "compression")]
+	fn decompress_invalid() {
+		let compressed = compress(b"abcdabcdabcd");
+		// wrong length
+		assert_eq!(decompress(&compressed, 11), None);
+		assert_eq!(decompress(&compressed, 13), None);
+		// truncated
+		assert_eq!(decompress(&compressed[..compressed.len() - 1], 12), None);
+		// back reference before the start of the output
+		assert_eq!(decompress(&[0x00, 0x01, 0x00], 4), None);
+	}
+
+	#[test]
+	fn fields() {
+		for fields in [vec![1, 2, 3], vec![9; 300]] {
+			let mut writer = BitWriter::with_capacity(8 * 1024);
+			writer.write_bit(true);
+			let encoded = EncodedFields::new(&fields);
+			encoded.write(&mut writer);
+			let mut counter = BitCounter::new(u32::MAX);
+			encoded.write(&mut counter);
+			assert_eq!(encoded.bit_length(), counter.bits_needed());
+
+			let mut reader = BitReader::from_slice(writer.slice());
+			assert!(reader.read_bit().unwrap());
+			let mut fields_reader = read_fields(None, &mut reader).unwrap();
+			for byte in &fields {
+				assert_eq!(fields_reader.read_byte(), Ok(*byte));
+			}
+		}
+	}
+
+	#[test]
+	fn oversized_fields() {
+		// a small payload declaring a huge decompressed length
+		let mut writer = BitWriter::with_capacity(8 * 1024);
+		true.ser(&mut writer);
+		ByteLen::ser(MAX_DECOMPRESSED_BYTES + 1, &mut writer);
+		ByteLen::ser(1, &mut writer);
+		writer.write_byte(0);
+		assert!(read_fields(None, &mut BitReader::from_slice(writer.slice())).is_err());
+
+		// compressed fields expanding beyond the maximum message size
+		#[cfg(feature = "compression")]
+		{
+			let mut writer = BitWriter::with_capacity(8 * 1024);
+			EncodedFields::new(&[9; 300]).write(&mut writer);
+			assert!(read_fields(Some(299), &mut BitReader::from_slice(writer.slice())).is_err());
+			assert!(read_fields(Some(300), &mut BitReader::from_slice(writer.slice())).is_ok());
+		}
+	}
+}
@@ -1,30 +1,112 @@
 use crate::MessageManager;
+use crate::metrics::RollingWindow;
+use naia_serde::*;
 use std::collections::HashSet;
+use std::time::Duration;
 use super::packet::*;
 use super::sequence_buffer::SequenceBuffer;
 
-pub const REDUNDANT_PACKET_ACKS_SIZE: u16 = 32;
 const DEFAULT_SEND_PACKETS_SIZE: usize = 256;
+const LOSS_WINDOW_SIZE: Duration = Duration::from_secs(5);
+
+/// The number of previously received packets redundantly acknowledged in each outgoing
+/// data packet. Larger windows tolerate longer runs of lost packets, or higher packet
+/// rates, at the cost of a larger packet header.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, SerdeInternal)]
+pub enum AckWindow {
+	#[default]
+	Bits32,
+	Bits64,
+	Bits128,
+}
+
+impl AckWindow {
+	/// Number of packets acknowledged by the bitfield
+	pub fn size(&self) -> u16 {
+		match self {
+			AckWindow::Bits32 => 32,
+			AckWindow::Bits64 => 64,
+			AckWindow::Bits128 => 128,
+		}
+	}
+
+	fn size_bytes(&self) -> usize { self.size() as usize / 8 }
+}
+
+/// A bitfield of acknowledged packets, where bit `n` acknowledges packet
+/// `ack_index - (n + 1)`. The window size is serialized along with the bits, so each
+/// side of a connection is free to pick its own window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AckBitfield {
+	window: AckWindow,
+	bits: u128,
+}
+
+impl AckBitfield {
+	pub fn new(window: AckWindow) -> Self { Self { window, bits: 0 } }
+
+	pub fn window(&self) -> AckWindow { self.window }
+
+	/// Acknowledge the packet `offset` packets prior to `ack_index`
+	pub fn set(&mut self, offset: u16) {
+		debug_assert!(offset >= 1 && offset <= self.window.size());
+		self.bits |= 1 << (offset - 1);
+	}
+
+	/// Whether the packet `offset` packets prior to `ack_index` was acknowledged
+	pub fn is_set(&self, offset: u16) -> bool {
+		debug_assert!(offset >= 1 && offset <= self.window.size());
+		self.bits & (1 << (offset - 1)) != 0
+	}
+}
+
+impl Serde for AckBitfield {
+	fn ser(&self, writer: &mut dyn BitWrite) {
+		self.window.ser(writer);
+		for byte in &self.bits.to_le_bytes()[..self.window.size_bytes()] {
+			writer.write_byte(*byte);
+		}
+	}
+
+	fn de(reader: &mut BitReader) -> SerdeResult<Self> {
+		let window = AckWindow::de(reader)?;
+		let mut bytes = [0u8; size_of::<u128>()];
+		for byte in &mut bytes[..window.size_bytes()] {
+			*byte = reader.read_byte()?;
+		}
+		Ok(Self { window, bits: u128::from_le_bytes(bytes) })
+	}
+
+	fn bit_length(&self) -> u32 {
+		self.window.bit_length() + self.window.size() as u32
+	}
+}
 
 /// Keeps track of sent & received packets, and contains ack information that is
 /// copied into the standard header on each outgoing packet
 pub struct AckManager {
+    // The number of received packets acknowledged in each outgoing packet
+    window: AckWindow,
     // The last acked packet index of the packets we've sent to the remote host.
     last_recv_packet_index: PacketSeq,
     // Using a `Hashmap` to track every packet we send out so we can ensure that we can resend when
     // dropped.
     sent_packets: HashSet<PacketSeq>,
-    // However, we can only reasonably ack up to `REDUNDANT_PACKET_ACKS_SIZE + 1` packets on each
-    // message we send so this should be that large.
+    // However, we can only reasonably ack up to `window.size() + 1` packets on each message
+    // we send so this should be that large.
     received_packets: SequenceBuffer,
+    // Delivery samples of sent packets; 1.0 for lost packets, 0.0 for delivered ones
+    loss: RollingWindow,
 }
 
 impl AckManager {
-    pub fn new() -> Self {
+    pub fn new(window: AckWindow) -> Self {
         Self {
+            window,
             last_recv_packet_index: PacketSeq::MAX,
             sent_packets: HashSet::with_capacity(DEFAULT_SEND_PACKETS_SIZE),
-            received_packets: SequenceBuffer::with_capacity(REDUNDANT_PACKET_ACKS_SIZE + 1),
+            received_packets: SequenceBuffer::with_capacity(window.size() + 1),
+            loss: RollingWindow::new(LOSS_WINDOW_SIZE),
         }
     }
 
@@ -37,7 +119,7 @@ impl AckManager {
         message_manager: &mut MessageManager,
     ) {
         let sender_ack_index = data_header.ack_index;
-        let mut sender_ack_bitfield = data_header.ack_bitfield;
+        let sender_ack_bitfield = data_header.ack_bitfield;
 
         self.received_packets.set(packet_seq);
 
@@ -48,24 +130,49 @@ impl AckManager {
 
         // the current `sender_ack_index` was (clearly) received so we should remove it
 		if self.sent_packets.remove(&sender_ack_index) {
+			self.loss.sample(0.0);
 			message_manager.notify_packet_delivered(sender_ack_index);
 		}
 
-        // The `sender_ack_bitfield` is going to include whether or not the past 32
-        // packets have been received successfully.
+        // The `sender_ack_bitfield` is going to include whether or not the past
+        // `window.size()` packets have been received successfully.
         // If so, we have no need to resend old packets.
-        for i in 1..=REDUNDANT_PACKET_ACKS_SIZE {
-			if sender_ack_bitfield & 1 == 1 {
+        for i in 1..=sender_ack_bitfield.window().size() {
+			if sender_ack_bitfield.is_set(i) {
 				let sent_packet_index = sender_ack_index - i;
 				if self.sent_packets.remove(&sent_packet_index) {
+					self.loss.sample(0.0);
 					message_manager.notify_packet_delivered(sent_packet_index);
 				}
 			}
+        }
 
-            sender_ack_bitfield >>= 1;
+        // Packets older than the sender's ack window can never be acked, so consider
+        // them lost
+        let oldest_ackable = self.last_recv_packet_index - sender_ack_bitfield.window().size();
+        let sent_count = self.sent_packets.len();
+        self.sent_packets.retain(|packet_index| *packet_index >= oldest_ackable);
+        for _ in self.sent_packets.len()..sent_count {
+            self.loss.sample(1.0);
         }
     }
 
+    /// Write the state needed to continue acknowledging packets in another process.
+    /// Packets in flight are forgotten; their reliable messages are resent.
+    pub fn export(&self, writer: &mut dyn BitWrite) {
+        self.last_recv_packet_index.ser(writer);
+    }
+
+    /// Restore the state written by `export()`
+    pub fn import(&mut self, reader: &mut BitReader) -> SerdeResult<()> {
+        self.last_recv_packet_index = PacketSeq::de(reader)?;
+        self.sent_packets.clear();
+        Ok(())
+    }
+
+    /// The fraction of sent packets recently lost, between 0 and 1
+    pub fn loss_frac(&self) -> f32 { self.loss.mean() }
+
     /// Records the packet with the given packet index
     fn track_packet(&mut self, packet_index: PacketSeq) {
         self.sent_packets.insert(packet_index);
@@ -73,6 +180,12 @@ impl AckManager {
 
     pub fn next_outgoing_data_header(&mut self, packet_seq: PacketSeq) -> packet::Data {
         self.track_packet(packet_seq);
+        self.ack_header()
+    }
+
+    /// Builds an ack header without tracking the outgoing packet, for packets which
+    /// carry no messages
+    pub fn ack_header(&self) -> packet::Data {
         packet::Data {
             ack_index: self.last_received_packet_index(),
             ack_bitfield: self.ack_bitfield(),
@@ -83,21 +196,45 @@ impl AckManager {
         self.received_packets.sequence_num() - 1
     }
 
-    fn ack_bitfield(&self) -> u32 {
+    fn ack_bitfield(&self) -> AckBitfield {
         let last_received_remote_packet_index: PacketSeq = self.last_received_packet_index();
-        let mut ack_bitfield: u32 = 0;
-        let mut mask: u32 = 1;
+        let mut ack_bitfield = AckBitfield::new(self.window);
 
-        // iterate the past `REDUNDANT_PACKET_ACKS_SIZE` received packets and set the
-        // corresponding bit for each packet which exists in the buffer.
-        for i in 1..=REDUNDANT_PACKET_ACKS_SIZE {
+        // iterate the past `window.size()` received packets and set the corresponding
+        // bit for each packet which exists in the buffer.
+        for i in 1..=self.window.size() {
             let received_packet_index = last_received_remote_packet_index - i;
-            if self.received_packets.is_set(received_packet_index.into()) {
-                ack_bitfield |= mask;
+            if self.received_packets.is_set(received_packet_index) {
+                ack_bitfield.set(i);
             }
-            mask <<= 1;
         }
 
         ack_bitfield
     }
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ack_bitfield_read_write() {
+		for window in [AckWindow::Bits32, AckWindow::Bits64, AckWindow::Bits128] {
+			let mut bitfield = AckBitfield::new(window);
+			bitfield.set(1);
+			bitfield.set(window.size() / 2);
+			bitfield.set(window.size());
+
+			let mut writer = BitWriter::new();
+			bitfield.ser(&mut writer);
+			assert_eq!(writer.slice().len(), bitfield.bit_length().div_ceil(8) as usize);
+
+			let mut reader = BitReader::from_slice(writer.slice());
+			let out = AckBitfield::de(&mut reader).unwrap();
+			assert_eq!(out, bitfield);
+			assert!(out.is_set(1));
+			assert!(!out.is_set(2));
+			assert!(out.is_set(window.size()));
+		}
+	}
+}
@@ -1,35 +1,119 @@
-use chacha20poly1305::{ aead::{AeadMutInPlace, KeyInit}, ChaCha20Poly1305, Nonce, Tag};
 use crate::{
-	ChannelKind, error::*, Io, MessageContainer, MessageKinds, RolloverCounter, Schema,
-	Timer,
+	ChannelKind, error::*, Instant, Io, MessageContainer, MessageKinds, QueueDepth,
+	RolloverCounter, Schema, Timer,
 };
 use crate::messages::{
 	channels::channel_kinds::ChannelKinds, message_manager::MessageManager,
 };
 use crate::metrics::*;
 use crate::types::HostType;
-use naia_serde::{BitReader, Serde};
+use log::trace;
+use naia_serde::{BitReader, BitWrite, Serde};
 use std::net::SocketAddr;
-use std::time::{Duration, Instant};
-use super::{ack_manager::AckManager, connection_config::ConnectionConfig, packet::*};
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use std::time::Duration;
+use super::{
+	ack_manager::AckManager,
+	cipher::{self, Cipher, CipherKind, EphemeralKey, KEY_SIZE, Nonce, Salt},
+	congestion::{CongestionController, LinkQuality},
+	connection_config::{CoalesceConfig, ConnectionConfig, DecryptFailureConfig},
+	middleware::ConnectionMiddleware,
+	packet::*,
+	packet_series::PacketSeries,
+};
 
 const METRICS_WINDOW_SIZE: Duration = Duration::from_secs(17);
 
+/// The least time between heartbeats sent only to acknowledge data packets, which are
+/// otherwise paced at a quarter of the RTT
+const MIN_ACK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Number of packets the exporting process may still send after `mark_exported()`
+/// (Redirects and Disconnects) without reusing a nonce of the importing process
+const HANDOFF_SEQ_GAP: u64 = 1024;
+
+/// Counts of the packets received on a connection, by type, and of anomalous packets
+/// suggesting a buggy or malicious remote host, e.g. for kick rules
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ConnectionStats {
+	pub data_rx: u64,
+	pub heartbeat_rx: u64,
+	pub ping_rx: u64,
+	pub pong_rx: u64,
+	/// Authentic packets of a type unexpected in the connection's state, which were
+	/// dropped
+	pub spurious_rx: u64,
+	/// Packets whose header or contents failed to parse
+	pub malformed_rx: u64,
+	/// Encrypted packets too short to carry the encryption tag
+	pub undersized_rx: u64,
+	/// Authentic data, heartbeat, ping, pong and disconnect packets carrying unread
+	/// bytes beyond their contents
+	pub oversized_rx: u64,
+}
+
+/// How long each step of a connection's handshake took, e.g. to detect slow
+/// authentication or lost handshake packets. Steps not completed yet are `None`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HandshakeTiming {
+	/// From the first handshake packet until the key exchange completed, i.e. the
+	/// Client received the encrypt response, or the Server the connect request
+	pub encrypt: Option<Duration>,
+	/// From the key exchange until connected, including the Server's decision to
+	/// accept the connection
+	pub connect: Option<Duration>,
+}
+
+impl HandshakeTiming {
+	/// From the first handshake packet until connected
+	pub fn total(&self) -> Option<Duration> { Some(self.encrypt? + self.connect?) }
+}
+
 /// Represents a connection to a remote host, and provides functionality to
 /// manage the connection and the communications to it
 pub struct BaseConnection {
 	address: SocketAddr,
 	ack_manager: AckManager,
+	congestion: Option<CongestionController>,
+	/// Whether the link quality changed since `take_link_change()` was last called
+	link_changed: bool,
 	message_manager: MessageManager,
 	host_type: HostType,
 	packet_seq: RolloverCounter,
-	encrypt_key: Option<ChaCha20Poly1305>,
+	/// Whether data and heartbeat packets are sent with compact headers
+	compact_headers: bool,
+	/// The latest sequence number of an authentic packet received, to expand compact
+	/// headers
+	last_rx_seq: PacketSeq,
+	encrypt_key: Option<Cipher>,
+	/// Raw shared key, kept for `export()`
+	shared_key: Option<[u8; 32]>,
+	/// Whether another process took over this connection, see `mark_exported()`
+	exported: bool,
 	heartbeat_timer: Timer,
 	ping_timer: Timer,
 	timeout_timer: Timer,
 	epoch: Instant,
+	/// The time of the current receive or send cycle, see `set_now()`
+	now: Instant,
 	rtt_ms: RollingWindow,
+	/// Whether data packets have been received since acks were last sent
+	ack_pending: bool,
+	/// When acks were last sent, in a data packet or heartbeat
+	ack_sent: Instant,
+	/// Minimum interval between data packet flushes
+	send_interval: Duration,
+	last_flush: Option<Instant>,
+	coalesce: Option<CoalesceConfig>,
+	/// When the small payload being held back for coalescing became pending
+	coalesce_since: Option<Instant>,
+	middleware: Vec<Box<dyn ConnectionMiddleware>>,
+	decrypt_failures: Option<DecryptFailureConfig>,
+	/// The start of the current decrypt failure window, and the failures within it
+	decrypt_window: (Instant, u32),
+	decrypt_alarm: bool,
+	pkt_rx_decrypt_fail_count: u64,
+	stats: ConnectionStats,
+	handshake: HandshakeTiming,
 }
 
 impl BaseConnection {
@@ -40,56 +124,150 @@ impl BaseConnection {
         config: &ConnectionConfig,
         channel_kinds: &ChannelKinds,
     ) -> Self {
+		let now = Instant::now();
         BaseConnection {
 			address: *address,
-			ack_manager: AckManager::new(),
-			message_manager: MessageManager::new(host_type, channel_kinds),
+			ack_manager: AckManager::new(config.ack_window),
+			congestion: config.congestion.clone().map(CongestionController::new),
+			link_changed: false,
+			message_manager: MessageManager::new(host_type, channel_kinds, &config.reassembly),
 			host_type,
 			packet_seq: RolloverCounter::MAX,
+			compact_headers: false,
+			last_rx_seq: PacketSeq::ZERO,
 			encrypt_key: None,
+			shared_key: None,
+			exported: false,
 			heartbeat_timer: Timer::new(config.heartbeat_interval),
 			ping_timer: Timer::new(config.ping_interval),
 			timeout_timer: Timer::new(config.timeout),
-			epoch: Instant::now(),
+			epoch: now,
+			now,
 			rtt_ms: RollingWindow::new(METRICS_WINDOW_SIZE),
+			ack_pending: false,
+			ack_sent: now,
+			send_interval: Duration::ZERO,
+			last_flush: None,
+			coalesce: config.coalesce.clone(),
+			coalesce_since: None,
+			middleware: Vec::new(),
+			decrypt_failures: config.decrypt_failures.clone(),
+			decrypt_window: (now, 0),
+			decrypt_alarm: false,
+			pkt_rx_decrypt_fail_count: 0,
+			stats: ConnectionStats::default(),
+			handshake: HandshakeTiming::default(),
         }
     }
 
 	pub fn address(&self) -> &SocketAddr { &self.address }
 
+	/// Record the end of the key exchange, timed from this connection's creation
+	pub fn mark_encrypted(&mut self) {
+		self.handshake.encrypt.get_or_insert(self.epoch.elapsed());
+	}
+
+	/// Record the end of the handshake
+	pub fn mark_connected(&mut self) {
+		let encrypt = *self.handshake.encrypt.get_or_insert(self.epoch.elapsed());
+		self.handshake.connect.get_or_insert(self.epoch.elapsed().saturating_sub(encrypt));
+	}
+
+	pub fn handshake_timing(&self) -> HandshakeTiming { self.handshake }
+	pub fn set_address(&mut self, address: &SocketAddr) { self.address = *address }
+
+	/// Set the time of the current receive or send cycle, read once by the caller for
+	/// all connections, rather than by each timer, timestamp and rate window
+	pub fn set_now(&mut self, now: &Instant) { self.now = *now }
+
 	pub fn timestamp_ns(&self) -> TimestampNs {
-		self.epoch.elapsed().as_nanos() as TimestampNs
+		self.now.duration_since(self.epoch).as_nanos() as TimestampNs
 	}
 
-	pub fn set_shared_key(&mut self, priv_key: EphemeralSecret, pub_key: PublicKey) {
+	pub fn set_shared_key(
+		&mut self, priv_key: EphemeralKey, pub_key: &[u8; KEY_SIZE], cipher: CipherKind,
+	) {
 		debug_assert!(self.encrypt_key.is_none());
-		let shared_key = priv_key.diffie_hellman(&pub_key);
-		let key: _ = ChaCha20Poly1305::new_from_slice(shared_key.as_bytes()).unwrap();
-		self.encrypt_key = Some(key);
+		let shared_key = priv_key.agree(pub_key);
+		self.encrypt_key = Some(Cipher::new(cipher, &shared_key));
+		self.shared_key = Some(shared_key);
+	}
+
+	/// Switch to a key derived from the shared key and `salt`, e.g. when migrating
+	pub fn rekey(&mut self, salt: &Salt) {
+		let (Some(kind), Some(key)) = (self.cipher(), self.shared_key) else {
+			return;
+		};
+
+		let key = cipher::derive_key(&key, salt);
+		self.encrypt_key = Some(Cipher::new(kind, &key));
+		self.shared_key = Some(key);
+	}
+
+	/// The cipher negotiated during the handshake, if any
+	pub fn cipher(&self) -> Option<CipherKind> { self.encrypt_key.as_ref().map(Cipher::kind) }
+
+	/// Apply the timeouts, intervals, congestion control, coalescing and decrypt failure
+	/// limits of `config`. The ack window, cipher and reassembly limits can't be changed
+	/// once connected.
+	pub fn apply_config(&mut self, config: &ConnectionConfig) {
+		self.heartbeat_timer.set_duration(config.heartbeat_interval);
+		self.ping_timer.set_duration(config.ping_interval);
+		self.timeout_timer.set_duration(config.timeout);
+
+		match (&mut self.congestion, &config.congestion) {
+			(Some(congestion), Some(config)) => congestion.set_config(config.clone()),
+			(congestion, config) => {
+				self.link_changed |= congestion.as_ref().is_some_and(|c| c.quality() != LinkQuality::Good);
+				*congestion = config.clone().map(CongestionController::new);
+			}
+		}
+
+		self.decrypt_failures = config.decrypt_failures.clone();
+		self.coalesce = config.coalesce.clone();
+	}
+
+	/// Register middleware to observe (or modify) the packets sent and received by
+	/// this connection
+	pub fn add_middleware(&mut self, middleware: Box<dyn ConnectionMiddleware>) {
+		self.middleware.push(middleware);
 	}
 
     // Heartbeats
 
     /// Record that a message has been sent (to prevent needing to send a
     /// heartbeat)
-    pub fn mark_sent(&mut self) { self.heartbeat_timer.reset() }
+    pub fn mark_sent(&mut self) { self.heartbeat_timer.reset_at(&self.now) }
 
     // Timeouts
 
     /// Record that a message has been received from a remote host (to prevent
     /// disconnecting from the remote host)
-	pub fn mark_heard(&mut self) { self.timeout_timer.reset() }
+	pub fn mark_heard(&mut self) { self.timeout_timer.reset_at(&self.now) }
+
+    /// Returns whether this connection has timed out, including by exceeding the
+    /// maximum resend attempts of a reliable channel
+	pub fn timed_out(&self, now: &Instant) -> bool {
+		self.timeout_timer.ringing_at(now) || self.message_manager.resends_exhausted()
+	}
 
-    /// Returns whether this connection has timed out
-	pub fn timed_out(&self) -> bool { self.timeout_timer.ringing() }
+	/// When the connection times out unless heard from
+	pub fn timeout_deadline(&self) -> Instant { self.timeout_timer.deadline() }
 
     // Acks & Headers
 
 	pub fn packet_writer(&mut self, packet_type: PacketType) -> PacketWriter {
 		let header: _ = PacketHeader { packet_type, packet_seq: self.packet_seq.incr() };
-		PacketWriter::new(header)
+		match self.compact_headers && PacketHeader::is_compactable(packet_type) {
+			true => PacketWriter::new_compact(header),
+			false => PacketWriter::new(header),
+		}
 	}
 
+	/// Send data and heartbeat packets with compact headers, as negotiated during the
+	/// handshake. Either kind of header is accepted regardless.
+	pub fn set_compact_headers(&mut self, compact: bool) { self.compact_headers = compact }
+
 	pub fn has_outgoing_messages(&self) -> bool {
 		self.message_manager.has_outgoing_messages()
 	}
@@ -99,37 +277,159 @@ impl BaseConnection {
 		message_kinds: &MessageKinds,
 		channel_kind: &ChannelKind,
 		message: MessageContainer,
-	) {
-        self.message_manager.queue_message(message_kinds, channel_kind, message);
+	) -> NaiaResult {
+        self.message_manager.queue_message(message_kinds, channel_kind, message)
     }
 
 	pub fn receive_messages(&mut self) -> impl Iterator<Item = MessageContainer> + '_ {
 		self.message_manager.receive_messages()
 	}
 
-	/// Fill and send as many data packets as necessary to send all pending messages
-	pub fn send_data_packets(
-		&mut self, schema: &Schema, now: &Instant, io: &mut Io,
+	/// See `MessageManager::take_throttled()`
+	pub fn take_throttled(&mut self) -> Vec<(ChannelKind, u64)> {
+		self.message_manager.take_throttled()
+	}
+
+	pub fn pause_channel(&mut self, channel_kind: &ChannelKind) {
+		self.message_manager.pause_channel(channel_kind)
+	}
+
+	pub fn resume_channel(
+		&mut self, message_kinds: &MessageKinds, channel_kind: &ChannelKind,
 	) -> NaiaResult {
-		let resend_ms = self.rtt_ms() + 1.5 * self.jitter_ms();
-		self.message_manager.collect_messages(now, &resend_ms);
+		self.message_manager.resume_channel(message_kinds, channel_kind)
+	}
 
-		while self.has_outgoing_messages() {
-			let writer = self.write_data_packet(schema);
-			self.send(io, writer)?;
+	pub fn is_channel_paused(&self, channel_kind: &ChannelKind) -> bool {
+		self.message_manager.is_channel_paused(channel_kind)
+	}
+
+	pub fn set_bandwidth_profiler(&mut self, window: Option<Duration>) {
+		self.message_manager.set_bandwidth_profiler(window)
+	}
+
+	pub fn bandwidth_report(&self, channel_kinds: &ChannelKinds) -> Option<BandwidthReport> {
+		self.message_manager.bandwidth_report(channel_kinds)
+	}
+
+	pub fn hold_channel(&mut self, channel_kind: &ChannelKind) {
+		self.message_manager.hold_channel(channel_kind)
+	}
+
+	pub fn release_channel(&mut self, channel_kind: &ChannelKind) {
+		self.message_manager.release_channel(channel_kind)
+	}
+
+	pub fn is_channel_held(&self, channel_kind: &ChannelKind) -> bool {
+		self.message_manager.is_channel_held(channel_kind)
+	}
+
+	/// Set the minimum interval between data packet flushes. Messages queued in between
+	/// are accumulated and sent together.
+	pub fn set_send_interval(&mut self, interval: Duration) { self.send_interval = interval }
+
+	/// Treat the remote host as a receive-only observer, dropping its messages on all
+	/// channels not registered with `add_observer_channel()`
+	pub fn set_observer(&mut self, observer: bool) { self.message_manager.set_observer(observer) }
+
+	/// Returns whether data packets may be flushed now, and records the flush if so
+	fn try_flush(&mut self, now: &Instant) -> bool {
+		let mut interval = self.send_interval;
+		if let Some(congestion) = &mut self.congestion {
+			if congestion.update(now, self.rtt_ms.mean(), self.ack_manager.loss_frac()) {
+				self.link_changed = true;
+			}
+			interval = interval.max(congestion.send_interval());
 		}
 
-		Ok(())
+		if let Some(last_flush) = self.last_flush {
+			if now.duration_since(last_flush) < interval {
+				return false;
+			}
+		}
+
+		self.last_flush = Some(*now);
+		true
 	}
 
-	fn write_data_packet(&mut self, schema: &Schema) -> PacketWriter {
-		let mut writer = self.packet_writer(PacketType::Data);
+	/// Returns whether to hold back the pending messages, so that messages queued
+	/// shortly after share their packet
+	fn try_coalesce(&mut self, now: &Instant) -> bool {
+		let Some(coalesce) = &self.coalesce else {
+			return false;
+		};
+		if !self.has_outgoing_messages()
+			|| self.message_manager.outgoing_bits() > 8 * coalesce.max_bytes as u64
+		{
+			self.coalesce_since = None;
+			return false;
+		}
 
-		let seq = writer.packet_seq();
-		self.ack_manager.next_outgoing_data_header(seq).ser(&mut writer);
-		self.message_manager.write_messages(&schema, writer.inner_mut(), seq);
+		let since = *self.coalesce_since.get_or_insert(*now);
+		if now.duration_since(since) < coalesce.delay {
+			return true;
+		}
+		self.coalesce_since = None;
+		false
+	}
 
-		writer
+	/// Fill and send as many data packets as necessary to send all pending messages,
+	/// unless holding back a small payload for coalescing
+	pub fn send_data_packets(
+		&mut self, schema: &Schema, now: &Instant, io: &mut Io,
+	) -> NaiaResult {
+		self.write_data_packets(schema, now, io, true)
+	}
+
+	/// Like `send_data_packets()`, but sends small payloads right away
+	pub fn flush_data_packets(
+		&mut self, schema: &Schema, now: &Instant, io: &mut Io,
+	) -> NaiaResult {
+		self.write_data_packets(schema, now, io, false)
+	}
+
+	fn write_data_packets(
+		&mut self, schema: &Schema, now: &Instant, io: &mut Io, coalesce: bool,
+	) -> NaiaResult {
+		self.now = *now;
+		if !self.try_flush(now) {
+			return Ok(());
+		}
+
+		let (rtt_ms, jitter_ms) = (self.rtt_ms(), self.jitter_ms());
+		self.message_manager.collect_messages(now, rtt_ms, jitter_ms);
+
+		if coalesce && self.try_coalesce(now) {
+			// the send interval already passed, so retry on the next call
+			self.last_flush = None;
+			return Ok(());
+		}
+		self.coalesce_since = None;
+
+		let mut series = PacketSeries::new(|| {
+			// as `packet_writer()`, borrowing only the fields needed
+			let seq = self.packet_seq.incr();
+			let header = PacketHeader { packet_type: PacketType::Data, packet_seq: seq };
+			let mut writer = match self.compact_headers && PacketHeader::is_compactable(PacketType::Data) {
+				true => PacketWriter::new_compact(header),
+				false => PacketWriter::new(header),
+			};
+			self.ack_manager.next_outgoing_data_header(seq).ser(&mut writer);
+			self.ack_pending = false;
+			self.ack_sent = self.now;
+			writer
+		});
+		while self.message_manager.has_outgoing_messages() {
+			let writer = series.current();
+			let seq = writer.packet_seq();
+			self.message_manager.write_messages(schema, writer.inner_mut(), seq);
+			series.next_packet();
+		}
+
+		for writer in series.finish() {
+			self.send(io, writer)?;
+		}
+		Ok(())
 	}
 
     pub fn read_data_packet(
@@ -138,17 +438,32 @@ impl BaseConnection {
 		packet_seq: PacketSeq,
         reader: &mut BitReader,
     ) -> NaiaResult {
-		let Ok(data_header) = packet::Data::de(reader) else {
-			return Err(NaiaError::malformed::<packet::Data>());
-		};
+		self.stats.data_rx = self.stats.data_rx.wrapping_add(1);
+		let data_header = packet::Data::de(reader).map_err(NaiaError::malformed_by::<packet::Data>)?;
 
         self.ack_manager.process_incoming_header(packet_seq, &data_header, &mut self.message_manager);
+		self.ack_pending = true;
         self.message_manager.read_messages(schema, reader)
     }
 
+	/// Read an incoming heartbeat to process the acks it carries
+	pub fn read_heartbeat(
+		&mut self, packet_seq: PacketSeq, reader: &mut BitReader,
+	) -> NaiaResult {
+		self.stats.heartbeat_rx = self.stats.heartbeat_rx.wrapping_add(1);
+		let ack_header = packet::Data::de(reader).map_err(NaiaError::malformed_by::<packet::Data>)?;
+
+		self.ack_manager.process_incoming_header(packet_seq, &ack_header, &mut self.message_manager);
+		Ok(())
+	}
+
 	pub fn maybe_decrypt(&mut self, reader: &mut BitReader) -> NaiaResult<PacketHeader> {
-		let Ok(header) = reader.read::<PacketHeader>() else {
-			return Err(NaiaError::malformed::<PacketHeader>());
+		let header = match PacketHeader::read_any(reader, self.last_rx_seq) {
+			Ok(header) => header,
+			Err(err) => {
+				self.stats.malformed_rx = self.stats.malformed_rx.wrapping_add(1);
+				return Err(NaiaError::malformed_by::<PacketHeader>(err));
+			}
 		};
 
 		if header.packet_type.is_encrypted() {
@@ -160,26 +475,139 @@ impl BaseConnection {
 			let nonce: _ = build_nonce(
 				self.host_type.other(), header.packet_type, packet_seq,
 			);
-			let tag = reader.read::<[u8; packet::ENCRYPT_TAG_SIZE]>()?;
+			let tag = match reader.read::<[u8; packet::ENCRYPT_TAG_SIZE]>() {
+				Ok(tag) => tag,
+				Err(err) => {
+					self.stats.undersized_rx = self.stats.undersized_rx.wrapping_add(1);
+					return Err(err.into());
+				}
+			};
+
+			let decrypted = shared_key.decrypt_in_place_detached(
+				&nonce, &[], reader.remaining_mut(), &tag,
+			);
+			if !decrypted {
+				self.record_decrypt_failure();
+				return Err(NaiaError::Decryption);
+			}
+			self.advance_rx_seq(header.packet_seq);
+		}
 
-			shared_key.decrypt_in_place_detached(
-				&nonce, &[], reader.remaining_mut(), Tag::from_slice(&tag),
-			).map_err(|_| NaiaError::Decryption)?;
+		for middleware in &mut self.middleware {
+			middleware.on_incoming(reader);
 		}
 
 		Ok(header)
 	}
 
+	fn advance_rx_seq(&mut self, seq: PacketSeq) {
+		if seq > self.last_rx_seq {
+			self.last_rx_seq = seq;
+		}
+	}
+
+	fn record_decrypt_failure(&mut self) {
+		self.pkt_rx_decrypt_fail_count = self.pkt_rx_decrypt_fail_count.wrapping_add(1);
+		let Some(config) = &self.decrypt_failures else {
+			return;
+		};
+
+		let (start, count) = &mut self.decrypt_window;
+		if self.now.duration_since(*start) >= config.window {
+			*start = self.now;
+			*count = 0;
+		}
+		*count += 1;
+		// reported once per window
+		if *count == config.max_failures {
+			self.decrypt_alarm = true;
+		}
+	}
+
+	/// Returns whether packets failing decryption exceeded
+	/// `ConnectionConfig::decrypt_failures` since the last call
+	pub fn take_decrypt_alarm(&mut self) -> bool { std::mem::take(&mut self.decrypt_alarm) }
+
+	/// Count the anomalies of an authentic packet once handled: contents which failed
+	/// to parse, or bytes left unread. Handshake packets are skipped when unexpected
+	/// in the connection's state, so they are only checked for the former.
+	pub fn inspect_rx<T>(&mut self, packet_type: PacketType, result: &NaiaResult<T>, reader: &BitReader) {
+		let checked = matches!(
+			packet_type,
+			PacketType::Data | PacketType::Heartbeat | PacketType::Ping | PacketType::Pong | PacketType::Disconnect,
+		);
+		match result {
+			Err(NaiaError::Malformed(..)) => self.stats.malformed_rx = self.stats.malformed_rx.wrapping_add(1),
+			Ok(_) if checked && reader.remaining_bits() >= 8 =>
+				self.stats.oversized_rx = self.stats.oversized_rx.wrapping_add(1),
+			_ => (),
+		}
+	}
+
+	/// Drop an authentic packet which isn't expected in the connection's state
+	pub fn drop_spurious(&mut self, packet_type: PacketType) {
+		trace!("Dropping spurious {packet_type:?} from {}", self.address);
+		self.stats.spurious_rx = self.stats.spurious_rx.wrapping_add(1);
+	}
+
+	/// Authenticate, but don't encrypt, `data` carried by an unencrypted packet
+	pub fn sign(
+		&mut self, writer: &PacketWriter, data: &[u8],
+	) -> NaiaResult<[u8; packet::ENCRYPT_TAG_SIZE]> {
+		let Some(shared_key) = self.encrypt_key.as_mut() else {
+			return Err(NaiaError::Encryption);
+		};
+
+		let nonce: _ = build_nonce(
+			self.host_type, writer.packet_type(), self.packet_seq.value(),
+		);
+		shared_key.encrypt_in_place_detached(&nonce, data, &mut [])
+			.ok_or(NaiaError::Encryption)
+	}
+
+	/// Verify the remote host's `sign()` of `data`, under the key derived from the
+	/// shared key and `salt` if given (see `rekey()`). Returns the packet's full
+	/// sequence number if the tag is valid.
+	pub fn verify(
+		&mut self, header: &PacketHeader, data: &[u8], tag: &[u8; packet::ENCRYPT_TAG_SIZE],
+		salt: Option<&Salt>,
+	) -> Option<u64> {
+		let (kind, key) = (self.cipher()?, self.shared_key?);
+		let key = salt.map_or(key, |salt| cipher::derive_key(&key, salt));
+		let mut shared_key = Cipher::new(kind, &key);
+
+		let packet_seq = self.packet_seq.infer(header.packet_seq);
+		let nonce: _ = build_nonce(
+			self.host_type.other(), header.packet_type, packet_seq,
+		);
+		if !shared_key.decrypt_in_place_detached(&nonce, data, &mut [], tag) {
+			return None;
+		}
+
+		self.advance_rx_seq(header.packet_seq);
+		Some(packet_seq)
+	}
+
 	pub fn send(&mut self, io: &mut Io, mut writer: PacketWriter) -> NaiaResult {
+		if self.exported && !matches!(writer.packet_type(), PacketType::Redirect | PacketType::Disconnect) {
+			return Ok(());
+		}
+
+		for middleware in &mut self.middleware {
+			middleware.on_outgoing(&mut writer);
+		}
+
 		if writer.packet_type().is_encrypted() {
+			// the writer's own sequence number, as it may not be the latest, e.g. in
+			// a `PacketSeries`
 			let nonce: _ = build_nonce(
-				self.host_type, writer.packet_type(), self.packet_seq.value(),
+				self.host_type, writer.packet_type(), self.packet_seq.infer(writer.packet_seq()),
 			);
 			let shared_key: _ = self.encrypt_key.as_mut().unwrap();
 			let tag: _ = shared_key.encrypt_in_place_detached(
 				&nonce, &[], writer.body_mut(),
-			).map_err(|_| NaiaError::Encryption)?;
-			writer.tag_mut().copy_from_slice(tag.as_slice());
+			).ok_or(NaiaError::Encryption)?;
+			writer.tag_mut().copy_from_slice(&tag);
 		}
 
 		io.send_packet(&self.address, writer.slice())?;
@@ -196,6 +624,7 @@ impl BaseConnection {
 
 	/// Read an incoming pong to update link quality metrics
 	pub fn read_pong(&mut self, reader: &mut BitReader) -> NaiaResult {
+		self.stats.pong_rx = self.stats.pong_rx.wrapping_add(1);
 		let pong: packet::Pong = packet::Pong::de(reader)?;
 		self.sample_rtt(pong.timestamp_ns);
 
@@ -203,6 +632,7 @@ impl BaseConnection {
 	}
 
 	pub fn ping_pong(&mut self, reader: &mut BitReader, io: &mut Io) -> NaiaResult {
+		self.stats.ping_rx = self.stats.ping_rx.wrapping_add(1);
 		let ping = packet::Ping::de(reader)?;
 
 		let mut writer = self.packet_writer(PacketType::Pong);
@@ -210,18 +640,57 @@ impl BaseConnection {
 		self.send(io, writer)
 	}
 
+	/// Send a heartbeat packet if nothing else has been sent recently, or if received
+	/// data packets have not been acknowledged for a while. Heartbeats carry acks, so
+	/// reliable messages from a remote host are not needlessly retransmitted when
+	/// this host has no data of its own to send. Acks are sent at most every quarter
+	/// RTT, or `MIN_ACK_INTERVAL`, so one-way traffic isn't answered packet for packet.
 	pub fn try_send_heartbeat(&mut self, io: &mut Io) -> NaiaResult {
-		if !self.heartbeat_timer.try_reset() {
+		if !self.heartbeat_timer.try_reset_at(&self.now) && !self.ack_due() {
 			return Ok(());
 		}
 
-		let writer = self.packet_writer(PacketType::Heartbeat);
+		self.send_heartbeat(io)
+	}
+
+	fn ack_due(&self) -> bool {
+		self.ack_pending && self.now >= self.ack_deadline()
+	}
+
+	/// When pending acks may be sent in a heartbeat
+	fn ack_deadline(&self) -> Instant {
+		let interval = Duration::from_secs_f32(self.rtt_ms.mean().max(0.0) / 4000.0);
+		self.ack_sent + interval.max(MIN_ACK_INTERVAL)
+	}
+
+	/// Whether no messages are queued, awaiting acknowledgement or held back for
+	/// coalescing, and no received data awaits acknowledgement, so that only pings and
+	/// heartbeats are due until more messages are queued or received
+	pub fn is_idle(&self) -> bool {
+		!self.ack_pending && self.coalesce_since.is_none() && self.queue_depth().messages == 0
+	}
+
+	/// When the next ping or heartbeat packet is due
+	pub fn next_maintenance(&self) -> Instant {
+		let deadline = self.heartbeat_timer.deadline().min(self.ping_timer.deadline());
+		match self.ack_pending {
+			true => deadline.min(self.ack_deadline()),
+			false => deadline,
+		}
+	}
+
+	/// Send a heartbeat packet immediately
+	pub fn send_heartbeat(&mut self, io: &mut Io) -> NaiaResult {
+		let mut writer = self.packet_writer(PacketType::Heartbeat);
+		self.ack_manager.ack_header().ser(&mut writer);
+		self.ack_pending = false;
+		self.ack_sent = self.now;
 		self.send(io, writer)
 	}
 
 	/// Send a ping packet if enough time has passed
 	pub fn try_send_ping(&mut self, io: &mut Io) -> NaiaResult {
-		if !self.ping_timer.try_reset() {
+		if !self.ping_timer.try_reset_at(&self.now) {
 			return Ok(());
 		}
 
@@ -230,6 +699,55 @@ impl BaseConnection {
 		self.send(io, writer)
 	}
 
+	// Handoff
+
+	/// Write the state needed to continue this connection in another process: the
+	/// address, shared key, packet sequence, acks, and channel state (including
+	/// unacknowledged reliable messages). The output contains the shared key, so it
+	/// must be kept secret. Timers and link metrics start over after `import()`.
+	pub fn export(&self, schema: &Schema, writer: &mut dyn BitWrite) -> NaiaResult {
+		let (Some(cipher), Some(key)) = (self.cipher(), self.shared_key) else {
+			return Err(NaiaError::Encryption);
+		};
+
+		self.address.ser(writer);
+		cipher.ser(writer);
+		key.ser(writer);
+		self.packet_seq.value().ser(writer);
+		self.compact_headers.ser(writer);
+		self.last_rx_seq.ser(writer);
+		self.ack_manager.export(writer);
+		self.message_manager.export(schema, writer);
+		Ok(())
+	}
+
+	/// Stop sending anything but Redirect and Disconnect packets, once the output of
+	/// `export()` is handed to another process, which continues with the same shared key
+	pub fn mark_exported(&mut self) { self.exported = true }
+
+	/// Restore a connection written by `export()`, which must have used the same Schema
+	pub fn import(
+		host_type: HostType, config: &ConnectionConfig, schema: &Schema, reader: &mut BitReader,
+	) -> NaiaResult<Self> {
+		let address = SocketAddr::de(reader)?;
+		let mut base = Self::new(&address, host_type, config, schema.channel_kinds());
+
+		let cipher = CipherKind::de(reader)?;
+		let key = <[u8; KEY_SIZE]>::de(reader)?;
+		base.encrypt_key = Some(Cipher::new(cipher, &key));
+		base.shared_key = Some(key);
+
+		// skip the packets the exporting process may still send
+		let packet_seq = u64::de(reader)?;
+		base.packet_seq = RolloverCounter::from_value(packet_seq + HANDOFF_SEQ_GAP);
+		base.compact_headers = bool::de(reader)?;
+		base.last_rx_seq = PacketSeq::de(reader)?;
+
+		base.ack_manager.import(reader)?;
+		base.message_manager.import(schema, reader)?;
+		Ok(base)
+	}
+
 	pub fn rtt_ms(&self) -> f32 { self.rtt_ms.mean() }
 
 	pub fn jitter_ms(&self) -> f32 {
@@ -237,22 +755,146 @@ impl BaseConnection {
 		f32::max(self.rtt_ms.max() - mean, mean - self.rtt_ms.min())
 	}
 
+	/// The fraction of sent packets recently lost, between 0 and 1
+	pub fn packet_loss(&self) -> f32 { self.ack_manager.loss_frac() }
+
+	pub fn link_quality(&self) -> LinkQuality {
+		self.congestion.as_ref().map(CongestionController::quality).unwrap_or_default()
+	}
+
+	/// Returns the new link quality if it changed since the last call
+	pub fn take_link_change(&mut self) -> Option<LinkQuality> {
+		std::mem::take(&mut self.link_changed).then(|| self.link_quality())
+	}
+
+	pub fn queue_depth(&self) -> QueueDepth { self.message_manager.queue_depth() }
+
 	// performance counters
 
 	pub fn msg_rx_count(&self) -> u64 { self.message_manager.msg_rx_count() }
 	pub fn msg_rx_drop_count(&self) -> u64 { self.message_manager.msg_rx_drop_count() }
 	pub fn msg_rx_miss_count(&self) -> u64 { self.message_manager.msg_rx_miss_count() }
+	pub fn frag_rx_expired_count(&self) -> u64 { self.message_manager.frag_rx_expired_count() }
+	pub fn frag_rx_refused_count(&self) -> u64 { self.message_manager.frag_rx_refused_count() }
+	pub fn pkt_rx_decrypt_fail_count(&self) -> u64 { self.pkt_rx_decrypt_fail_count }
+	pub fn stats(&self) -> ConnectionStats { self.stats }
+	pub fn msg_rx_observer_drop_count(&self) -> u64 { self.message_manager.msg_rx_observer_drop_count() }
+	pub fn msg_rx_throttle_count(&self) -> u64 { self.message_manager.msg_rx_throttle_count() }
 	pub fn msg_tx_count(&self) -> u64 { self.message_manager.msg_tx_count() }
 	pub fn msg_tx_queue_count(&self) -> u64 { self.message_manager.msg_tx_queue_count() }
+	pub fn msg_rx_per_sec(&self, channel_kind: &ChannelKind) -> f32 { self.message_manager.msg_rx_per_sec(channel_kind) }
+	pub fn msg_tx_per_sec(&self, channel_kind: &ChannelKind) -> f32 { self.message_manager.msg_tx_per_sec(channel_kind) }
 }
 
 fn build_nonce(
 	host_type: HostType, packet_type: PacketType, packet_seq: u64,
 ) -> Nonce {
-	let mut nonce = [0u8; size_of::<Nonce>()];
+	let mut nonce: Nonce = Default::default();
 	nonce[0] = host_type.to_u8();
 	nonce[1] = packet_type.to_u8();
 	nonce[2..10].copy_from_slice(packet_seq.to_le_bytes().as_slice());
+	nonce
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{AckBitfield, AckWindow, SeqNum};
+	use naia_serde::BitWriter;
+	use super::*;
+
+	fn keyed_connection(config: &ConnectionConfig) -> BaseConnection {
+		let address = "127.0.0.1:4000".parse().unwrap();
+		let mut conn = BaseConnection::new(&address, HostType::Server, config, &ChannelKinds::new());
+		let remote_key = EphemeralKey::random().public_key();
+		conn.set_shared_key(EphemeralKey::random(), &remote_key, CipherKind::ChaCha20Poly1305);
+		conn
+	}
 
-	Nonce::clone_from_slice(nonce.as_slice())
-}
\ No newline at end of file
+	#[test]
+	#[cfg(feature = "crypto")]
+	fn decrypt_alarm() {
+		let decrypt_failures = DecryptFailureConfig { max_failures: 3, ..DecryptFailureConfig::default() };
+		let config = ConnectionConfig { decrypt_failures: Some(decrypt_failures), ..ConnectionConfig::default() };
+		let mut conn = keyed_connection(&config);
+
+		// the tag doesn't match the key
+		let mut forged = conn.packet_writer(PacketType::Data);
+		forged.write(&0u32);
+		for i in 1..=4 {
+			let mut reader = BitReader::from_slice(forged.slice());
+			assert!(matches!(conn.maybe_decrypt(&mut reader), Err(NaiaError::Decryption)));
+			// reported once per window
+			assert_eq!(conn.take_decrypt_alarm(), i == 3);
+		}
+		assert_eq!(conn.pkt_rx_decrypt_fail_count(), 4);
+	}
+
+	#[test]
+	#[cfg(feature = "crypto")]
+	fn rekey() {
+		let address = "127.0.0.1:4000".parse().unwrap();
+		let config = ConnectionConfig::default();
+		let mut client = BaseConnection::new(&address, HostType::Client, &config, &ChannelKinds::new());
+		let mut server = BaseConnection::new(&address, HostType::Server, &config, &ChannelKinds::new());
+		let (client_key, server_key) = (EphemeralKey::random(), EphemeralKey::random());
+		let (client_public, server_public) = (client_key.public_key(), server_key.public_key());
+		client.set_shared_key(client_key, &server_public, CipherKind::ChaCha20Poly1305);
+		server.set_shared_key(server_key, &client_public, CipherKind::ChaCha20Poly1305);
+
+		// both hosts have sent a packet
+		server.packet_writer(PacketType::Heartbeat);
+		let salt = [1; cipher::SALT_SIZE];
+		client.rekey(&salt);
+		let writer = client.packet_writer(PacketType::Migrate);
+		let tag = client.sign(&writer, b"session").unwrap();
+		let header = PacketHeader { packet_type: PacketType::Migrate, packet_seq: writer.packet_seq() };
+
+		// only valid under the key of the same salt
+		assert!(server.verify(&header, b"session", &tag, None).is_none());
+		assert!(server.verify(&header, b"session", &tag, Some(&[2; cipher::SALT_SIZE])).is_none());
+		assert!(server.verify(&header, b"session", &tag, Some(&salt)).is_some());
+		server.rekey(&salt);
+		assert!(server.verify(&header, b"session", &tag, None).is_some());
+	}
+
+	#[test]
+	fn stats() {
+		let mut conn = keyed_connection(&ConnectionConfig::default());
+
+		let mut heartbeat = BitWriter::new();
+		let ack_bitfield = AckBitfield::new(AckWindow::default());
+		packet::Data { ack_index: SeqNum(0), ack_bitfield }.ser(&mut heartbeat);
+		conn.read_heartbeat(SeqNum(0), &mut BitReader::from_slice(heartbeat.slice())).unwrap();
+		conn.drop_spurious(PacketType::ConnectResponse);
+		conn.drop_spurious(PacketType::EncryptResponse);
+
+		let stats = ConnectionStats { heartbeat_rx: 1, spurious_rx: 2, ..ConnectionStats::default() };
+		assert_eq!(conn.stats(), stats);
+	}
+
+	#[test]
+	fn anomalies() {
+		let mut conn = keyed_connection(&ConnectionConfig::default());
+
+		// a data header without the encryption tag
+		let mut truncated = BitWriter::new();
+		PacketHeader { packet_type: PacketType::Data, packet_seq: SeqNum(0) }.ser(&mut truncated);
+		assert!(conn.maybe_decrypt(&mut BitReader::from_slice(truncated.slice())).is_err());
+
+		// a heartbeat with trailing bytes
+		let mut heartbeat = BitWriter::new();
+		let ack_bitfield = AckBitfield::new(AckWindow::default());
+		packet::Data { ack_index: SeqNum(0), ack_bitfield }.ser(&mut heartbeat);
+		heartbeat.write(&0u32);
+		let mut reader = BitReader::from_slice(heartbeat.slice());
+		let result = conn.read_heartbeat(SeqNum(0), &mut reader);
+		conn.inspect_rx(PacketType::Heartbeat, &result, &reader);
+
+		// contents failing to parse
+		let result = conn.read_heartbeat(SeqNum(1), &mut BitReader::from_slice(&[]));
+		conn.inspect_rx(PacketType::Heartbeat, &result, &reader);
+
+		let stats = conn.stats();
+		assert_eq!((stats.undersized_rx, stats.oversized_rx, stats.malformed_rx), (1, 1, 1));
+	}
+}
@@ -0,0 +1,273 @@
+#[cfg(feature = "crypto")]
+use aes_gcm::Aes256Gcm;
+#[cfg(feature = "crypto")]
+use chacha20::{cipher::consts::U10, hchacha};
+#[cfg(feature = "crypto")]
+use chacha20poly1305::{aead::{AeadMutInPlace, KeyInit}, ChaCha20Poly1305};
+use naia_serde::SerdeInternal;
+#[cfg(feature = "crypto")]
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+/// The size of the public and shared keys of the key exchange
+pub const KEY_SIZE: usize = 32;
+pub const NONCE_SIZE: usize = 12;
+/// The size of the authentication tag of encrypted packets
+pub const TAG_SIZE: usize = 16;
+/// The size of the salts of `derive_key()`
+pub const SALT_SIZE: usize = 16;
+
+pub type Nonce = [u8; NONCE_SIZE];
+pub type Tag = [u8; TAG_SIZE];
+pub type Salt = [u8; SALT_SIZE];
+
+/// The AEAD cipher used to encrypt connected packets
+#[derive(Copy, Clone, Debug, Eq, PartialEq, SerdeInternal)]
+pub enum CipherKind {
+	/// Fast on all hardware; the best choice without hardware AES support
+	ChaCha20Poly1305,
+	/// Faster than ChaCha20-Poly1305 on CPUs with hardware AES support (e.g. AES-NI)
+	Aes256Gcm,
+}
+
+impl CipherKind {
+	/// Returns the fastest cipher for the current CPU
+	pub fn preferred() -> Self {
+		if has_hardware_aes() { Self::Aes256Gcm } else { Self::ChaCha20Poly1305 }
+	}
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn has_hardware_aes() -> bool {
+	std::is_x86_feature_detected!("aes") && std::is_x86_feature_detected!("pclmulqdq")
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn has_hardware_aes() -> bool { false }
+
+/// The local half of the ephemeral Diffie-Hellman key exchange of the handshake
+#[cfg(feature = "crypto")]
+pub struct EphemeralKey {
+	secret: EphemeralSecret,
+	public: PublicKey,
+}
+
+#[cfg(feature = "crypto")]
+impl EphemeralKey {
+	pub fn random() -> Self {
+		let secret = EphemeralSecret::random();
+		let public = PublicKey::from(&secret);
+		Self { secret, public }
+	}
+
+	pub fn public_key(&self) -> [u8; KEY_SIZE] { self.public.to_bytes() }
+
+	/// Derive the key shared with the remote host from its public key
+	pub fn agree(self, remote: &[u8; KEY_SIZE]) -> [u8; KEY_SIZE] {
+		*self.secret.diffie_hellman(&PublicKey::from(*remote)).as_bytes()
+	}
+}
+
+/// Without the `crypto` feature, keys are all zeros and there is nothing to exchange
+#[cfg(not(feature = "crypto"))]
+pub struct EphemeralKey;
+
+#[cfg(not(feature = "crypto"))]
+impl EphemeralKey {
+	pub fn random() -> Self { Self }
+
+	pub fn public_key(&self) -> [u8; KEY_SIZE] { [0; KEY_SIZE] }
+
+	pub fn agree(self, _remote: &[u8; KEY_SIZE]) -> [u8; KEY_SIZE] { [0; KEY_SIZE] }
+}
+
+/// Derive a new key from `key` and a `salt` which must not have been used with `key`
+/// before, with HChaCha20
+#[cfg(feature = "crypto")]
+pub fn derive_key(key: &[u8; KEY_SIZE], salt: &Salt) -> [u8; KEY_SIZE] {
+	hchacha::<U10>(key.into(), salt.into()).into()
+}
+
+#[cfg(not(feature = "crypto"))]
+pub fn derive_key(_key: &[u8; KEY_SIZE], _salt: &Salt) -> [u8; KEY_SIZE] { [0; KEY_SIZE] }
+
+/// A random key tagging fixed-length messages with a 64-bit MAC, e.g. state handed
+/// to the remote host which it must echo unchanged
+#[cfg(feature = "crypto")]
+pub struct MacKey([u8; KEY_SIZE]);
+
+#[cfg(feature = "crypto")]
+impl MacKey {
+	pub fn random() -> Self {
+		let mut key = [0; KEY_SIZE];
+		getrandom_02::getrandom(&mut key).expect("OS random number generator failed");
+		Self(key)
+	}
+
+	/// The tag of a message, cascading `derive_key()` over its blocks. Only secure
+	/// while every message tagged with the key has the same number of blocks.
+	pub fn tag(&self, blocks: &[Salt]) -> u64 {
+		let key = blocks.iter().fold(self.0, |key, block| derive_key(&key, block));
+		u64::from_le_bytes(key[..8].try_into().unwrap())
+	}
+}
+
+/// Without the `crypto` feature, tags are keyed hashes, which resist guessing but
+/// not forgery
+#[cfg(not(feature = "crypto"))]
+pub struct MacKey(std::hash::RandomState);
+
+#[cfg(not(feature = "crypto"))]
+impl MacKey {
+	pub fn random() -> Self { Self(std::hash::RandomState::new()) }
+
+	pub fn tag(&self, blocks: &[Salt]) -> u64 {
+		use std::hash::BuildHasher;
+		self.0.hash_one(blocks)
+	}
+}
+
+/// An initialized AEAD cipher. All supported ciphers share the same nonce and tag
+/// types, so the packet layout does not depend on the negotiated cipher.
+#[cfg(feature = "crypto")]
+pub enum Cipher {
+	ChaCha20Poly1305(ChaCha20Poly1305),
+	Aes256Gcm(Box<Aes256Gcm>),
+}
+
+#[cfg(feature = "crypto")]
+impl Cipher {
+	pub fn new(kind: CipherKind, key: &[u8; KEY_SIZE]) -> Self {
+		match kind {
+			CipherKind::ChaCha20Poly1305 =>
+				Self::ChaCha20Poly1305(ChaCha20Poly1305::new(key.into())),
+			CipherKind::Aes256Gcm =>
+				Self::Aes256Gcm(Box::new(Aes256Gcm::new(key.into()))),
+		}
+	}
+
+	pub fn kind(&self) -> CipherKind {
+		match self {
+			Self::ChaCha20Poly1305(_) => CipherKind::ChaCha20Poly1305,
+			Self::Aes256Gcm(_) => CipherKind::Aes256Gcm,
+		}
+	}
+
+	/// Encrypt `buffer` in place, returning the tag authenticating it and
+	/// `associated_data`
+	pub fn encrypt_in_place_detached(
+		&mut self, nonce: &Nonce, associated_data: &[u8], buffer: &mut [u8],
+	) -> Option<Tag> {
+		let nonce = nonce.into();
+		let tag = match self {
+			Self::ChaCha20Poly1305(c) =>
+				c.encrypt_in_place_detached(nonce, associated_data, buffer),
+			Self::Aes256Gcm(c) => c.encrypt_in_place_detached(nonce, associated_data, buffer),
+		};
+		tag.ok().map(Into::into)
+	}
+
+	/// Decrypt `buffer` in place, returning whether `tag` is authentic
+	pub fn decrypt_in_place_detached(
+		&mut self, nonce: &Nonce, associated_data: &[u8], buffer: &mut [u8], tag: &Tag,
+	) -> bool {
+		let (nonce, tag) = (nonce.into(), tag.into());
+		let decrypted = match self {
+			Self::ChaCha20Poly1305(c) =>
+				c.decrypt_in_place_detached(nonce, associated_data, buffer, tag),
+			Self::Aes256Gcm(c) =>
+				c.decrypt_in_place_detached(nonce, associated_data, buffer, tag),
+		};
+		decrypted.is_ok()
+	}
+}
+
+/// Without the `crypto` feature, packets are sent in plaintext with all-zero tags, so
+/// the packet layout stays the same. Nothing is authenticated, so only use it on
+/// trusted networks, or below a transport with its own encryption.
+#[cfg(not(feature = "crypto"))]
+pub struct Cipher {
+	kind: CipherKind,
+}
+
+#[cfg(not(feature = "crypto"))]
+impl Cipher {
+	pub fn new(kind: CipherKind, _key: &[u8; KEY_SIZE]) -> Self { Self { kind } }
+
+	pub fn kind(&self) -> CipherKind { self.kind }
+
+	pub fn encrypt_in_place_detached(
+		&mut self, _nonce: &Nonce, _associated_data: &[u8], _buffer: &mut [u8],
+	) -> Option<Tag> {
+		Some([0; TAG_SIZE])
+	}
+
+	pub fn decrypt_in_place_detached(
+		&mut self, _nonce: &Nonce, _associated_data: &[u8], _buffer: &mut [u8], tag: &Tag,
+	) -> bool {
+		*tag == [0; TAG_SIZE]
+	}
+}
+
+#[cfg(all(test, feature = "crypto"))]
+mod tests {
+	use super::*;
+
+	fn roundtrip(kind: CipherKind) {
+		let key = [7u8; 32];
+		let nonce = [3u8; NONCE_SIZE];
+		let mut sender = Cipher::new(kind, &key);
+		let mut receiver = Cipher::new(kind, &key);
+		assert_eq!(sender.kind(), kind);
+
+		let mut buffer = *b"hello world";
+		let tag = sender.encrypt_in_place_detached(&nonce, &[], &mut buffer).unwrap();
+		assert_ne!(&buffer, b"hello world");
+
+		assert!(receiver.decrypt_in_place_detached(&nonce, &[], &mut buffer, &tag));
+		assert_eq!(&buffer, b"hello world");
+	}
+
+	#[test]
+	fn chacha20poly1305_roundtrip() { roundtrip(CipherKind::ChaCha20Poly1305) }
+
+	#[test]
+	fn aes256gcm_roundtrip() { roundtrip(CipherKind::Aes256Gcm) }
+
+	#[test]
+	fn mismatched_ciphers_fail() {
+		let key = [7u8; 32];
+		let nonce = [3u8; NONCE_SIZE];
+		let mut sender = Cipher::new(CipherKind::ChaCha20Poly1305, &key);
+		let mut receiver = Cipher::new(CipherKind::Aes256Gcm, &key);
+
+		let mut buffer = *b"hello world";
+		let tag = sender.encrypt_in_place_detached(&nonce, &[], &mut buffer).unwrap();
+		assert!(!receiver.decrypt_in_place_detached(&nonce, &[], &mut buffer, &tag));
+	}
+
+	#[test]
+	fn derived_keys() {
+		let key = [7u8; 32];
+		let derived = derive_key(&key, &[1; SALT_SIZE]);
+		assert_ne!(derived, key);
+		assert_eq!(derived, derive_key(&key, &[1; SALT_SIZE]));
+		assert_ne!(derived, derive_key(&key, &[2; SALT_SIZE]));
+		assert_ne!(derived, derive_key(&[8; 32], &[1; SALT_SIZE]));
+	}
+
+	#[test]
+	fn mac() {
+		let (key, blocks) = (MacKey::random(), [[1; SALT_SIZE], [2; SALT_SIZE]]);
+		let tag = key.tag(&blocks);
+		assert_eq!(tag, key.tag(&blocks));
+		assert_ne!(tag, key.tag(&[[1; SALT_SIZE], [3; SALT_SIZE]]));
+		assert_ne!(tag, MacKey::random().tag(&blocks));
+	}
+
+	#[test]
+	fn key_exchange() {
+		let (client, server) = (EphemeralKey::random(), EphemeralKey::random());
+		let (client_public, server_public) = (client.public_key(), server.public_key());
+		assert_eq!(client.agree(&server_public), server.agree(&client_public));
+	}
+}
@@ -1,9 +1,11 @@
-use crate::TimeQueue;
+use crate::{Instant, TimeQueue};
 use log::trace;
-use rand::Rng;
 use std::net::SocketAddr;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
+/// Simulated network conditions for testing, applied to received packets by `Io`, and
+/// thus to every transport, UDP and WebSocket alike; see
+/// `ConnectionConfig::conditioner`
 #[derive(Clone, Debug)]
 pub struct ConditionerConfig {
 	/// Base delay added to all received packets, in milliseconds
@@ -55,13 +57,18 @@ impl PacketConditioner {
 		Self { config, time_queue: TimeQueue::new() }
 	}
 
+	/// Change the conditions applied to packets received from now on
+	pub fn set_config(&mut self, config: ConditionerConfig) {
+		self.config = config;
+	}
+
 	pub fn push(&mut self, addr: SocketAddr, data: Box<[u8]>) {
 		let mut packets = 1;
-		if rand::rng().random_range(0.0..=1.0) < self.config.loss_frac {
+		if fastrand::f32() < self.config.loss_frac {
 			packets -= 1;
 			trace!("Conditioner dropped packet");
 		}
-		if rand::rng().random_range(0.0..=1.0) < self.config.duplication_frac {
+		if fastrand::f32() < self.config.duplication_frac {
 			packets += 1;
 			trace!("Conditioner duplicated packet");
 		}
@@ -70,9 +77,10 @@ impl PacketConditioner {
 		let max = f32::min(self.config.half_rtt_ms + self.config.jitter_ms, f32::MAX);
 
 		for _ in 0..packets {
-			let half_rtt_ms = rand::rng().random_range(min..=max);
+			let half_rtt_ms = min + fastrand::f32() * (max - min);
 			let timestamp = Instant::now() + Duration::from_secs_f32(half_rtt_ms / 1000.0);
-			self.time_queue.add_item(timestamp, (addr, data.clone()));
+			// unbounded, so never refused
+			let _ = self.time_queue.add_item(timestamp, (addr, data.clone()));
 		}
 	}
 
@@ -0,0 +1,120 @@
+use crate::{Instant, Timer};
+use std::time::Duration;
+
+/// Coarse link quality, as determined by a connection's congestion controller
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LinkQuality {
+	#[default]
+	Good,
+	Bad,
+}
+
+#[derive(Clone, Debug)]
+pub struct CongestionConfig {
+	/// The packet loss fraction, between 0 and 1, above which a link is considered bad
+	pub bad_loss_frac: f32,
+	/// The round-trip-time above which a link is considered bad, in milliseconds
+	pub bad_rtt_ms: f32,
+	/// The amount of time a bad link must stay within thresholds before it is
+	/// considered good again
+	pub recovery_time: Duration,
+	/// The minimum interval between data packet flushes while a link is bad. Messages
+	/// queued in between are accumulated and sent together.
+	pub bad_send_interval: Duration,
+}
+
+impl Default for CongestionConfig {
+	fn default() -> Self {
+		Self {
+			bad_loss_frac: 0.1,
+			bad_rtt_ms: 250.0,
+			recovery_time: Duration::from_secs(10),
+			bad_send_interval: Duration::from_millis(100),
+		}
+	}
+}
+
+/// Tracks link quality and scales back the data send rate while the link is bad
+pub struct CongestionController {
+	config: CongestionConfig,
+	quality: LinkQuality,
+	recovery_timer: Timer,
+}
+
+impl CongestionController {
+	pub fn new(config: CongestionConfig) -> Self {
+		Self {
+			recovery_timer: Timer::new(config.recovery_time),
+			config,
+			quality: LinkQuality::Good,
+		}
+	}
+
+	/// Change the thresholds, keeping the current link quality
+	pub fn set_config(&mut self, config: CongestionConfig) {
+		self.recovery_timer.set_duration(config.recovery_time);
+		self.config = config;
+	}
+
+	/// Update link quality given the latest link metrics. Returns whether the quality
+	/// changed.
+	pub fn update(&mut self, now: &Instant, rtt_ms: f32, loss_frac: f32) -> bool {
+		let previous = self.quality;
+		let bad = rtt_ms > self.config.bad_rtt_ms || loss_frac > self.config.bad_loss_frac;
+		if bad {
+			self.quality = LinkQuality::Bad;
+			self.recovery_timer.reset_at(now);
+		} else if self.quality == LinkQuality::Bad && self.recovery_timer.ringing_at(now) {
+			self.quality = LinkQuality::Good;
+		}
+		self.quality != previous
+	}
+
+	pub fn quality(&self) -> LinkQuality { self.quality }
+
+	/// The minimum interval between data packet flushes for the current link quality
+	pub fn send_interval(&self) -> Duration {
+		match self.quality {
+			LinkQuality::Good => Duration::ZERO,
+			LinkQuality::Bad => self.config.bad_send_interval,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config() -> CongestionConfig {
+		CongestionConfig {
+			bad_loss_frac: 0.1,
+			bad_rtt_ms: 100.0,
+			recovery_time: Duration::ZERO,
+			bad_send_interval: Duration::from_secs(60),
+		}
+	}
+
+	#[test]
+	fn quality_transitions() {
+		let mut cc = CongestionController::new(config());
+		assert_eq!(cc.quality(), LinkQuality::Good);
+
+		assert!(cc.update(&Instant::now(), 50.0, 0.2));
+		assert_eq!(cc.quality(), LinkQuality::Bad);
+
+		assert!(!cc.update(&Instant::now(), 150.0, 0.0));
+		assert_eq!(cc.quality(), LinkQuality::Bad);
+
+		assert!(cc.update(&Instant::now(), 50.0, 0.0));
+		assert_eq!(cc.quality(), LinkQuality::Good);
+	}
+
+	#[test]
+	fn throttle_when_bad() {
+		let mut cc = CongestionController::new(config());
+		assert_eq!(cc.send_interval(), Duration::ZERO);
+
+		cc.update(&Instant::now(), 500.0, 0.0);
+		assert_eq!(cc.send_interval(), Duration::from_secs(60));
+	}
+}
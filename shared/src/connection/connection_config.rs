@@ -1,5 +1,5 @@
-use crate::ConditionerConfig;
-use std::{default::Default, time::Duration};
+use crate::{AckWindow, CipherKind, ConditionerConfig, CongestionConfig, packet::packet, ReassemblyConfig};
+use std::{default::Default, error, fmt, time::Duration};
 
 #[derive(Clone, Debug)]
 pub struct ConnectionConfig {
@@ -15,17 +15,319 @@ pub struct ConnectionConfig {
     pub ping_interval: Duration,
 	/// Packet conditioner configuration. Use `None` to disable conditioning.
 	pub conditioner: Option<ConditionerConfig>,
+	/// The number of previously received packets acknowledged in each outgoing data
+	/// packet. Consider a larger window for high packet rate or high loss links.
+	pub ack_window: AckWindow,
+	/// Congestion control configuration. Disabled (`None`) by default, in which case
+	/// link quality is always reported as good.
+	pub congestion: Option<CongestionConfig>,
+	/// The cipher used to encrypt connected packets. Clients propose this cipher
+	/// during the handshake, and servers accept the proposal if it's the same, falling
+	/// back to ChaCha20-Poly1305 otherwise. Use `None` to propose the fastest cipher
+	/// for the current CPU (clients), or to accept each client's proposal (servers).
+	pub cipher: Option<CipherKind>,
+	/// Whether to disconnect remote hosts which send messages on channels that don't
+	/// allow them in that direction. Either way, such messages are dropped and
+	/// reported as `NaiaError::ChannelMisuse`, while the rest of their packet is read.
+	pub strict_channels: bool,
+	/// Limits on reassembling fragmented messages, beyond which the peer is disconnected
+	pub reassembly: ReassemblyConfig,
+	/// The reaction to packets failing decryption, which suggest a key desync or
+	/// forged packets. Use `None` to only report each failure as an error.
+	pub decrypt_failures: Option<DecryptFailureConfig>,
+	/// Whether to propose (clients) or allow (servers) 2 byte headers for data and
+	/// heartbeat packets, rather than 3 bytes. Used if both sides enable it.
+	pub compact_headers: bool,
+	/// Delay sending data packets which would carry only a few bytes of messages, to
+	/// combine them with messages queued shortly after. Use `None` to send right away.
+	pub coalesce: Option<CoalesceConfig>,
+	/// How Clients show they receive packets at their address before the Server
+	/// answers their first handshake packet, so the Server can't be used to flood
+	/// spoofed addresses
+	pub handshake_padding: HandshakePadding,
+}
+
+#[derive(Clone, Debug)]
+pub struct DecryptFailureConfig {
+	/// The number of packets failing decryption within `window` which triggers a
+	/// `DecryptFailures` event
+	pub max_failures: u32,
+	pub window: Duration,
+	/// Whether to also disconnect the remote host. Beware that anyone able to spoof
+	/// the remote host's address may then disconnect it.
+	pub disconnect: bool,
+}
+
+impl Default for DecryptFailureConfig {
+	fn default() -> Self {
+		Self { max_failures: 16, window: Duration::from_secs(1), disconnect: false }
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct CoalesceConfig {
+	/// The longest time messages are held back. Packets are only sent by `send()`, so
+	/// the actual delay rounds up to the next call.
+	pub delay: Duration,
+	/// The total size of pending messages up to which they are held back
+	pub max_bytes: usize,
+}
+
+impl Default for CoalesceConfig {
+	fn default() -> Self {
+		Self { delay: Duration::from_millis(5), max_bytes: 32 }
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HandshakePadding {
+	/// Pad the first handshake packet with this many bytes, making it larger than the
+	/// Server's answer; at least `HandshakePadding::MIN_BYTES`. Servers answer requests
+	/// with less padding as with `Retry`.
+	Bytes(u16),
+	/// Don't pad the first handshake packet. The Server answers it with a small cookie
+	/// which the Client echoes, costing a round trip instead. Servers answer every
+	/// Client this way.
+	Retry,
+}
+
+impl HandshakePadding {
+	pub const MIN_BYTES: u16 = packet::EncryptRequest::MIN_PADDING_SIZE as u16;
+
+	/// The number of bytes Clients pad with
+	pub fn bytes(&self) -> usize {
+		match self {
+			HandshakePadding::Bytes(bytes) => usize::from(*bytes),
+			HandshakePadding::Retry => 0,
+		}
+	}
+}
+
+impl Default for HandshakePadding {
+	fn default() -> Self { Self::Bytes(packet::EncryptRequest::PADDING_SIZE as u16) }
 }
 
 impl ConnectionConfig {
+	/// Frozen at the settings it takes now; settings added later keep their defaults
+	#[deprecated(note = "use `ConnectionConfig::builder()`, or `..ConnectionConfig::default()`")]
+	#[allow(clippy::too_many_arguments)]
     pub fn new(
 		timeout: Duration,
 		heartbeat_interval: Duration,
 		ping_interval: Duration,
 		conditioner: Option<ConditionerConfig>,
+		ack_window: AckWindow,
+		congestion: Option<CongestionConfig>,
+		cipher: Option<CipherKind>,
+		strict_channels: bool,
+		reassembly: ReassemblyConfig,
+		decrypt_failures: Option<DecryptFailureConfig>,
+		compact_headers: bool,
+		coalesce: Option<CoalesceConfig>,
+		handshake_padding: HandshakePadding,
 	) -> Self {
-		Self { timeout, heartbeat_interval, ping_interval, conditioner }
+		Self {
+			timeout, heartbeat_interval, ping_interval, conditioner, ack_window, congestion,
+			cipher, strict_channels, reassembly, decrypt_failures, compact_headers, coalesce,
+			handshake_padding,
+		}
     }
+
+	pub fn builder() -> ConnectionConfigBuilder { ConnectionConfigBuilder::new() }
+
+	/// Tuned for local networks: low latency and loss, so dead peers are detected
+	/// quickly
+	pub fn lan() -> Self {
+		Self {
+			timeout: Duration::from_secs(5),
+			heartbeat_interval: Duration::from_secs(1),
+			ping_interval: Duration::from_millis(250),
+			congestion: Some(CongestionConfig { bad_rtt_ms: 50.0, ..CongestionConfig::default() }),
+			..Self::default()
+		}
+	}
+
+	/// Tuned for typical internet links; `default()` with congestion control
+	pub fn internet() -> Self {
+		Self { congestion: Some(CongestionConfig::default()), ..Self::default() }
+	}
+
+	/// Tuned for cellular links, which stall during handovers and see more loss.
+	/// Packets are sent less often to save battery.
+	pub fn mobile() -> Self {
+		Self {
+			timeout: Duration::from_secs(60),
+			heartbeat_interval: Duration::from_secs(8),
+			ping_interval: Duration::from_secs(2),
+			ack_window: AckWindow::Bits64,
+			congestion: Some(CongestionConfig {
+				bad_loss_frac: 0.2,
+				bad_rtt_ms: 500.0,
+				..CongestionConfig::default()
+			}),
+			..Self::default()
+		}
+	}
+
+	/// Check for settings which can't work together, e.g. a timeout shorter than the
+	/// heartbeat interval, which would disconnect idle peers
+	pub fn validate(&self) -> Result<(), ConfigError> {
+		if self.ping_interval.is_zero() {
+			return Err(ConfigError::ZeroPingInterval);
+		}
+		if self.timeout <= self.heartbeat_interval {
+			return Err(ConfigError::TimeoutTooShort { timeout: self.timeout, interval: self.heartbeat_interval });
+		}
+		if self.timeout <= self.ping_interval {
+			return Err(ConfigError::TimeoutTooShort { timeout: self.timeout, interval: self.ping_interval });
+		}
+		if let Some(conditioner) = &self.conditioner {
+			check_fraction("conditioner.loss_frac", conditioner.loss_frac)?;
+			check_fraction("conditioner.duplication_frac", conditioner.duplication_frac)?;
+		}
+		if let Some(congestion) = &self.congestion {
+			check_fraction("congestion.bad_loss_frac", congestion.bad_loss_frac)?;
+		}
+		if self.reassembly.timeout.is_zero() {
+			return Err(ConfigError::ZeroReassemblyTimeout);
+		}
+		if self.decrypt_failures.as_ref().is_some_and(|config| config.max_failures == 0) {
+			return Err(ConfigError::ZeroMaxDecryptFailures);
+		}
+		if let HandshakePadding::Bytes(bytes) = self.handshake_padding {
+			if bytes < HandshakePadding::MIN_BYTES {
+				return Err(ConfigError::HandshakePaddingTooSmall { bytes });
+			}
+		}
+		Ok(())
+	}
+}
+
+fn check_fraction(name: &'static str, value: f32) -> Result<(), ConfigError> {
+	match (0.0..=1.0).contains(&value) {
+		true => Ok(()),
+		false => Err(ConfigError::InvalidFraction { name, value }),
+	}
+}
+
+/// Invalid combinations of settings, see `ConnectionConfig::validate()`
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+	ZeroPingInterval,
+	/// The timeout must exceed the heartbeat and ping intervals
+	TimeoutTooShort { timeout: Duration, interval: Duration },
+	/// The named setting must be between 0 and 1
+	InvalidFraction { name: &'static str, value: f32 },
+	ZeroReassemblyTimeout,
+	ZeroMaxDecryptFailures,
+	/// The named setting must be positive and finite
+	NotPositive { name: &'static str, value: f32 },
+	/// Handshake padding must be at least `HandshakePadding::MIN_BYTES`
+	HandshakePaddingTooSmall { bytes: u16 },
+}
+
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ConfigError::ZeroPingInterval => write!(f, "ping interval must not be zero"),
+			ConfigError::TimeoutTooShort { timeout, interval } =>
+				write!(f, "timeout of {timeout:?} must exceed the {interval:?} heartbeat and ping intervals"),
+			ConfigError::InvalidFraction { name, value } =>
+				write!(f, "{name} of {value} must be between 0 and 1"),
+			ConfigError::ZeroReassemblyTimeout => write!(f, "reassembly timeout must not be zero"),
+			ConfigError::ZeroMaxDecryptFailures => write!(f, "max decrypt failures must not be zero"),
+			ConfigError::NotPositive { name, value } => write!(f, "{name} of {value} must be positive"),
+			ConfigError::HandshakePaddingTooSmall { bytes } =>
+				write!(f, "handshake padding of {bytes} bytes must be at least {}", HandshakePadding::MIN_BYTES),
+		}
+	}
+}
+
+impl error::Error for ConfigError {}
+
+/// Builds a validated ConnectionConfig, starting from `ConnectionConfig::default()` or
+/// any other config, e.g. a preset
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionConfigBuilder {
+	config: ConnectionConfig,
+}
+
+impl ConnectionConfigBuilder {
+	pub fn new() -> Self { Self::default() }
+
+	pub fn timeout(mut self, timeout: Duration) -> Self {
+		self.config.timeout = timeout;
+		self
+	}
+
+	pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+		self.config.heartbeat_interval = interval;
+		self
+	}
+
+	pub fn ping_interval(mut self, interval: Duration) -> Self {
+		self.config.ping_interval = interval;
+		self
+	}
+
+	pub fn conditioner(mut self, conditioner: Option<ConditionerConfig>) -> Self {
+		self.config.conditioner = conditioner;
+		self
+	}
+
+	pub fn ack_window(mut self, ack_window: AckWindow) -> Self {
+		self.config.ack_window = ack_window;
+		self
+	}
+
+	pub fn congestion(mut self, congestion: Option<CongestionConfig>) -> Self {
+		self.config.congestion = congestion;
+		self
+	}
+
+	pub fn cipher(mut self, cipher: Option<CipherKind>) -> Self {
+		self.config.cipher = cipher;
+		self
+	}
+
+	pub fn strict_channels(mut self, strict: bool) -> Self {
+		self.config.strict_channels = strict;
+		self
+	}
+
+	pub fn reassembly(mut self, reassembly: ReassemblyConfig) -> Self {
+		self.config.reassembly = reassembly;
+		self
+	}
+
+	pub fn decrypt_failures(mut self, decrypt_failures: Option<DecryptFailureConfig>) -> Self {
+		self.config.decrypt_failures = decrypt_failures;
+		self
+	}
+
+	pub fn compact_headers(mut self, compact: bool) -> Self {
+		self.config.compact_headers = compact;
+		self
+	}
+
+	pub fn coalesce(mut self, coalesce: Option<CoalesceConfig>) -> Self {
+		self.config.coalesce = coalesce;
+		self
+	}
+
+	pub fn handshake_padding(mut self, padding: HandshakePadding) -> Self {
+		self.config.handshake_padding = padding;
+		self
+	}
+
+	pub fn build(self) -> Result<ConnectionConfig, ConfigError> {
+		self.config.validate()?;
+		Ok(self.config)
+	}
+}
+
+impl From<ConnectionConfig> for ConnectionConfigBuilder {
+	fn from(config: ConnectionConfig) -> Self { Self { config } }
 }
 
 impl Default for ConnectionConfig {
@@ -35,6 +337,51 @@ impl Default for ConnectionConfig {
             heartbeat_interval: Duration::from_secs(4),
 			ping_interval: Duration::from_secs(1),
 			conditioner: None,
+			ack_window: AckWindow::default(),
+			congestion: None,
+			cipher: None,
+			strict_channels: false,
+			reassembly: ReassemblyConfig::default(),
+			decrypt_failures: Some(DecryptFailureConfig::default()),
+			compact_headers: true,
+			coalesce: None,
+			handshake_padding: HandshakePadding::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn validate() {
+		for config in [ConnectionConfig::lan(), ConnectionConfig::internet(), ConnectionConfig::mobile()] {
+			assert_eq!(config.validate(), Ok(()));
+		}
+
+		let builder = || ConnectionConfigBuilder::from(ConnectionConfig::lan());
+		assert_eq!(
+			builder().ping_interval(Duration::ZERO).build().unwrap_err(),
+			ConfigError::ZeroPingInterval,
+		);
+		assert_eq!(
+			builder().timeout(Duration::from_secs(1)).build().unwrap_err(),
+			ConfigError::TimeoutTooShort { timeout: Duration::from_secs(1), interval: Duration::from_secs(1) },
+		);
+		assert_eq!(
+			builder().congestion(Some(CongestionConfig { bad_loss_frac: 1.5, ..CongestionConfig::default() }))
+				.build().unwrap_err(),
+			ConfigError::InvalidFraction { name: "congestion.bad_loss_frac", value: 1.5 },
+		);
+
+		assert_eq!(
+			builder().handshake_padding(HandshakePadding::Bytes(16)).build().unwrap_err(),
+			ConfigError::HandshakePaddingTooSmall { bytes: 16 },
+		);
+		assert!(builder().handshake_padding(HandshakePadding::Retry).build().is_ok());
+
+		let config = ConnectionConfig::builder().strict_channels(true).build().unwrap();
+		assert!(config.strict_channels);
+	}
+}
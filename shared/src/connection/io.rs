@@ -1,9 +1,17 @@
 use crate::{BitReader, error::*, ConditionerConfig, MTU_SIZE_BYTES};
+use crate::metrics::{RATE_WINDOW_SIZE, RateCounter};
 use std::io;
-use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
-use super::conditioner::PacketConditioner;
+use std::net::SocketAddr;
+use super::{
+	conditioner::PacketConditioner, send_error::SendError, socket_config::{self, SocketConfig},
+};
 
-fn receive(socket: &UdpSocket) -> Result<(SocketAddr, Box<[u8]>), io::Error> {
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::{Ipv4Addr, UdpSocket as Socket};
+#[cfg(target_arch = "wasm32")]
+use super::web_socket::WebSocket as Socket;
+
+fn receive(socket: &Socket) -> Result<(SocketAddr, Box<[u8]>), io::Error> {
 	let mut buffer = [0u8; MTU_SIZE_BYTES];
 	match socket.recv_from(buffer.as_mut_slice()) {
 		Ok((size, src_addr)) => Ok((src_addr, buffer[..size].into())),
@@ -12,7 +20,7 @@ fn receive(socket: &UdpSocket) -> Result<(SocketAddr, Box<[u8]>), io::Error> {
 }
 
 fn receive_conditioned(
-	socket: &UdpSocket, conditioner: &mut PacketConditioner,
+	socket: &Socket, conditioner: &mut PacketConditioner,
 ) -> Result<(SocketAddr, Box<[u8]>), io::Error> {
 	// Eagerly consume packets to ensure injected delay accuracy
 	loop {
@@ -37,51 +45,133 @@ pub struct Io {
 	conditioner: Option<PacketConditioner>,
 	pkt_rx_count: u64,
 	pkt_tx_count: u64,
-	socket: UdpSocket,
+	bytes_rx_rate: RateCounter,
+	bytes_tx_rate: RateCounter,
+	pkt_rx_rate: RateCounter,
+	pkt_tx_rate: RateCounter,
+	socket: Socket,
+	local_addr: Option<SocketAddr>,
+	/// Reapplied by `reconnect()`
+	socket_config: SocketConfig,
 }
 
 impl Io {
     fn new(
-		socket: UdpSocket,
+		socket: Socket,
 		conditioner_config: &Option<ConditionerConfig>,
+		socket_config: &SocketConfig,
 	) -> Self {
         Io {
+			local_addr: Self::socket_addr(&socket),
 			bytes_tx: 0,
 			bytes_rx: 0,
 			conditioner: conditioner_config.clone().map(PacketConditioner::new),
 			pkt_rx_count: 0,
 			pkt_tx_count: 0,
+			bytes_rx_rate: RateCounter::new(RATE_WINDOW_SIZE),
+			bytes_tx_rate: RateCounter::new(RATE_WINDOW_SIZE),
+			pkt_rx_rate: RateCounter::new(RATE_WINDOW_SIZE),
+			pkt_tx_rate: RateCounter::new(RATE_WINDOW_SIZE),
 			socket,
+			socket_config: socket_config.clone(),
         }
     }
 
+	#[cfg(not(target_arch = "wasm32"))]
+	fn socket_addr(socket: &Socket) -> Option<SocketAddr> { socket.local_addr().ok() }
+
+	#[cfg(target_arch = "wasm32")]
+	fn socket_addr(_socket: &Socket) -> Option<SocketAddr> { None }
+
+	#[cfg(not(target_arch = "wasm32"))]
+	fn connect_socket(server_addr: SocketAddr, socket_config: &SocketConfig) -> io::Result<Socket> {
+		let socket = Socket::bind((Ipv4Addr::LOCALHOST, 0))?;
+		socket.set_nonblocking(true)?;
+		socket_config::apply(&socket, socket_config)?;
+		socket.connect(server_addr)?;
+
+		Ok(socket)
+	}
+
+	#[cfg(target_arch = "wasm32")]
+	fn connect_socket(server_addr: SocketAddr, socket_config: &SocketConfig) -> io::Result<Socket> {
+		let socket = Socket::connect(server_addr)?;
+		socket_config::apply(&socket, socket_config)?;
+		Ok(socket)
+	}
+
 	pub fn connect(
 		server_addr: SocketAddr,
 		conditioner_config: &Option<ConditionerConfig>,
+		socket_config: &SocketConfig,
 	) -> NaiaResult<Self> {
-		let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
-		socket.set_nonblocking(true)?;
-		socket.connect(server_addr)?;
+		let socket = Self::connect_socket(server_addr, socket_config)?;
+		let io = Self::new(socket, conditioner_config, socket_config);
+		if io.is_own_addr(&server_addr) {
+			return Err(NaiaError::Loopback(server_addr));
+		}
 
-		Ok(Self::new(socket, conditioner_config))
+		Ok(io)
     }
 
+	/// Replace the underlying socket with a freshly bound one, e.g. after the OS
+	/// invalidated it while the app was in the background. Packets still queued on
+	/// the old socket are dropped.
+	pub fn reconnect(&mut self, server_addr: SocketAddr) -> NaiaResult {
+		self.socket = Self::connect_socket(server_addr, &self.socket_config)?;
+		self.local_addr = Self::socket_addr(&self.socket);
+		if self.is_own_addr(&server_addr) {
+			return Err(NaiaError::Loopback(server_addr));
+		}
+		Ok(())
+	}
+
+	/// Whether packets to `addr` arrive at this socket, i.e. at its local address or,
+	/// when bound to all interfaces, at its port on the loopback interface
+	pub fn is_own_addr(&self, addr: &SocketAddr) -> bool {
+		self.local_addr.is_some_and(|local| local.port() == addr.port()
+			&& (local.ip() == addr.ip() || local.ip().is_unspecified() && addr.ip().is_loopback()))
+	}
+
+	#[cfg(not(target_arch = "wasm32"))]
 	pub fn listen(
 		server_addr: SocketAddr,
 		conditioner_config: &Option<ConditionerConfig>,
+		socket_config: &SocketConfig,
 	) -> NaiaResult<Self> {
-		let socket = UdpSocket::bind(server_addr)?;
+		let socket = Socket::bind(server_addr)?;
 		socket.set_nonblocking(true)?;
+		socket_config::apply(&socket, socket_config)?;
 
-		Ok(Self::new(socket, conditioner_config))
+		Ok(Self::new(socket, conditioner_config, socket_config))
+	}
+
+	/// The receive and send buffer sizes of the socket, in bytes, as reported by the
+	/// OS. Linux reports twice the size set, to account for its bookkeeping.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn buffer_sizes(&self) -> NaiaResult<(usize, usize)> {
+		Ok(socket_config::buffer_sizes(&self.socket)?)
+	}
+
+	/// Change the packet conditioner. Packets held back by a removed conditioner are
+	/// dropped.
+	pub fn set_conditioner(&mut self, config: &Option<ConditionerConfig>) {
+		match (&mut self.conditioner, config) {
+			(Some(conditioner), Some(config)) => conditioner.set_config(config.clone()),
+			(conditioner, config) => *conditioner = config.clone().map(PacketConditioner::new),
+		}
 	}
 
     pub fn send_packet(&mut self, addr: &SocketAddr, payload: &[u8]) -> NaiaResult {
         // Bandwidth monitoring
 		self.bytes_tx = self.bytes_tx.wrapping_add(payload.len() as u64);
 		self.pkt_tx_count = self.pkt_tx_count.wrapping_add(1);
+		self.bytes_tx_rate.add(payload.len() as u64);
+		self.pkt_tx_rate.add(1);
 
-		self.socket.send_to(payload, addr)?;
+		if let Err(e) = self.socket.send_to(payload, addr) {
+			return Err(NaiaError::Send(SendError::new(*addr, &e)));
+		}
         Ok(())
     }
 
@@ -96,6 +186,8 @@ impl Io {
             Ok((src_addr, payload)) => {
 				self.bytes_rx = self.bytes_rx.wrapping_add(payload.len() as u64);
 				self.pkt_rx_count = self.pkt_rx_count.wrapping_add(1);
+				self.bytes_rx_rate.add(payload.len() as u64);
+				self.pkt_rx_rate.add(1);
 
 				return Ok(Some((src_addr, BitReader::new(payload))));
 			},
@@ -107,7 +199,11 @@ impl Io {
 	// Performance counters
 
 	pub fn bytes_rx(&self) -> u64 { self.bytes_rx }
+	pub fn bytes_rx_per_sec(&self) -> f32 { self.bytes_rx_rate.per_sec() }
 	pub fn bytes_tx(&self) -> u64 { self.bytes_tx }
+	pub fn bytes_tx_per_sec(&self) -> f32 { self.bytes_tx_rate.per_sec() }
 	pub fn pkt_rx_count(&self) -> u64 { self.pkt_rx_count }
+	pub fn pkt_rx_per_sec(&self) -> f32 { self.pkt_rx_rate.per_sec() }
 	pub fn pkt_tx_count(&self) -> u64 { self.pkt_tx_count }
+	pub fn pkt_tx_per_sec(&self) -> f32 { self.pkt_tx_rate.per_sec() }
 }
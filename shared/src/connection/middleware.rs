@@ -0,0 +1,14 @@
+use naia_serde::BitReader;
+use super::packet::PacketWriter;
+
+/// Hooks into the packets sent and received by a single connection, so applications
+/// can implement custom telemetry, tagging, or experimental extensions without
+/// modifying packet assembly. Hooks run in the order they were added.
+pub trait ConnectionMiddleware: Send + Sync {
+	/// Called with each fully assembled outgoing packet, before it is encrypted
+	fn on_outgoing(&mut self, _writer: &mut PacketWriter) {}
+
+	/// Called with each incoming packet after it is decrypted, before its body is
+	/// read. The reader is positioned at the start of the packet body.
+	fn on_incoming(&mut self, _reader: &mut BitReader) {}
+}
@@ -1,7 +1,15 @@
 pub mod ack_manager;
 pub mod base_connection;
+pub mod cipher;
 pub mod conditioner;
+pub mod congestion;
 pub mod connection_config;
 pub mod io;
+pub mod middleware;
 pub mod packet;
+pub mod packet_series;
+pub mod send_error;
 mod sequence_buffer;
+pub mod socket_config;
+#[cfg(target_arch = "wasm32")]
+mod web_socket;
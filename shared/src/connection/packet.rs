@@ -1,40 +1,55 @@
 use crate::SeqNum;
 use naia_serde::*;
-use x25519_dalek::PublicKey;
+use std::net::SocketAddr;
+use super::{ack_manager::AckBitfield, cipher::{self, CipherKind}};
 
 pub struct PacketWriter {
 	header: PacketHeader,
+	header_bytes: usize,
 	writer: BitWriter,
 }
 
 impl PacketWriter {
 	pub fn new(header: PacketHeader) -> Self {
-		// packet format (byte aligned): [packet_header][encrypt_tag][body]
 		let mut writer = BitWriter::new();
 		writer.write(&header);
+		Self::with_header(header, writer)
+	}
+
+	/// Like `new()`, but with a compact header; see `PacketHeader::write_compact()`
+	pub fn new_compact(header: PacketHeader) -> Self {
+		let mut writer = BitWriter::new();
+		header.write_compact(&mut writer);
+		Self::with_header(header, writer)
+	}
+
+	fn with_header(header: PacketHeader, mut writer: BitWriter) -> Self {
+		// packet format (byte aligned): [packet_header][encrypt_tag][body]
+		let header_bytes = writer.slice().len();
 		if header.packet_type.is_encrypted() {
 			// reserve space for the encryption tag
 			writer.write(&[0u8; packet::ENCRYPT_TAG_SIZE]);
 		}
 
-		Self { header, writer }
+		Self { header, header_bytes, writer }
 	}
 
 	pub fn packet_type(&self) -> PacketType { self.header.packet_type }
 	pub fn packet_seq(&self) -> PacketSeq { self.header.packet_seq }
 	pub fn tag_mut(&mut self) -> &mut [u8] {
-		let start = self.header.byte_length();
+		let start = self.header_bytes;
 		let end = start + packet::ENCRYPT_TAG_SIZE;
 		&mut self.writer.slice_mut()[start..end]
 	}
 	pub fn body_mut(&mut self) -> &mut [u8] {
-		let start = self.header.byte_length() +
+		let start = self.header_bytes +
 			if self.packet_type().is_encrypted() { packet::ENCRYPT_TAG_SIZE } else { 0 };
 		&mut self.writer.slice_mut()[start..]
 	}
-	pub fn slice(&self) -> &[u8] { &self.writer.slice() }
+	pub fn slice(&self) -> &[u8] { self.writer.slice() }
 
 	pub fn inner_mut(&mut self) -> &mut BitWriter { &mut self.writer }
+	pub fn bits_free(&self) -> u32 { self.writer.bits_free() }
 	pub fn write<T: Serde>(&mut self, value: &T) { self.writer.write(value) }
 }
 
@@ -49,6 +64,9 @@ pub type PacketSeq = SeqNum;
 // u64 is enough for ~584 years of nanoseconds
 pub type TimestampNs = u64;
 
+/// identifies an established connection independently of its address
+pub type SessionId = u64;
+
 /// An enum representing the different types of packets that can be sent/received
 #[derive(Copy, Debug, Clone, Eq, PartialEq, SerdeInternal)]
 pub enum PacketType {
@@ -73,20 +91,33 @@ pub enum PacketType {
     // A Pong message, used to calculate RTT. Must be the response to all Ping
     // messages
     Pong,
-    // A packet sent to maintain the connection by preventing a timeout
+    // A packet sent to maintain the connection by preventing a timeout. Also carries
+    // acks when there is no data to send.
     Heartbeat,
+    // (unencrypted) Sent by the Client to move an established connection to a new
+    // address, e.g. after resuming from the background. Switches both hosts to a key
+    // derived from the shared key, authenticated with that key, and answered with a
+    // Heartbeat.
+    Migrate,
 
 // Connected packets
     // A packet containing Message/Entity/Component data
     Data,
     // Used to request a graceful disconnect
     Disconnect,
+    // Sent by the Server to hand the connection off to another Server
+    Redirect,
+
+// Handshake, continued
+    // Step 1b: (unencrypted) The Server's answer to an EncryptRequest lacking padding,
+    // carrying a cookie the Client must echo in another EncryptRequest
+    HandshakeRetry,
 }
 
 impl PacketType {
 	pub fn is_encrypted(&self) -> bool {
 		use PacketType::*;
-		!matches!(self, HandshakeReject | EncryptRequest | EncryptResponse)
+		!matches!(self, HandshakeReject | EncryptRequest | EncryptResponse | Migrate | HandshakeRetry)
 	}
 
 	pub fn to_u8(self) -> u8 { self as u8 }
@@ -105,12 +136,58 @@ impl PacketHeader {
 		(self.packet_type.bit_length() + self.packet_seq.bit_length()) as usize
 	}
 	fn padded_bits(&self) -> usize { 8 * self.padded_bytes() }
-	fn padded_bytes(&self) -> usize { (self.field_bits() + 7) / 8 }
+	fn padded_bytes(&self) -> usize { self.field_bits().div_ceil(8) }
 	fn pad_bits(&self) -> usize { self.padded_bits() - self.field_bits() }
 
 	pub fn byte_length(&self) -> usize { self.padded_bytes() }
+
+	/// Whether packets of this type may use a compact header
+	pub fn is_compactable(packet_type: PacketType) -> bool {
+		matches!(packet_type, PacketType::Data | PacketType::Heartbeat)
+	}
+
+	/// Write a 2 byte header: a tag in place of the packet type, using values no
+	/// PacketType has, followed by the low `COMPACT_SEQ_BITS` of the sequence number
+	pub fn write_compact(&self, writer: &mut dyn BitWrite) {
+		debug_assert!(Self::is_compactable(self.packet_type));
+		let tag = match self.packet_type {
+			PacketType::Heartbeat => COMPACT_HEARTBEAT_TAG,
+			_ => COMPACT_DATA_TAG,
+		};
+		UnsignedInteger::<PACKET_TYPE_BITS>::new(tag).ser(writer);
+		UnsignedInteger::<COMPACT_SEQ_BITS>::new(self.packet_seq.0 & COMPACT_SEQ_MASK).ser(writer);
+	}
+
+	/// Read a full or compact header. Compact headers are expanded to the sequence
+	/// number closest to `last_seq`, the latest one received.
+	pub fn read_any(reader: &mut BitReader, last_seq: PacketSeq) -> SerdeResult<Self> {
+		let packet_type = match reader.peek::<UnsignedInteger<PACKET_TYPE_BITS>>()?.get() as u16 {
+			COMPACT_DATA_TAG => PacketType::Data,
+			COMPACT_HEARTBEAT_TAG => PacketType::Heartbeat,
+			_ => return reader.read(),
+		};
+
+		reader.read::<UnsignedInteger<PACKET_TYPE_BITS>>()?;
+		let low = reader.read::<UnsignedInteger<COMPACT_SEQ_BITS>>()?.get() as u16;
+		let delta = low.wrapping_sub(last_seq.0) & COMPACT_SEQ_MASK;
+		let packet_seq = match delta > COMPACT_SEQ_MASK / 2 {
+			true => last_seq.0.wrapping_sub(COMPACT_SEQ_MASK + 1 - delta),
+			false => last_seq.0.wrapping_add(delta),
+		};
+		Ok(Self { packet_type, packet_seq: packet_seq.into() })
+	}
 }
 
+/// The width of the packet type in a full header
+const PACKET_TYPE_BITS: u8 = 4;
+const _: () = assert!((PacketType::HandshakeRetry as u16) < COMPACT_DATA_TAG);
+const COMPACT_DATA_TAG: u16 = 14;
+const COMPACT_HEARTBEAT_TAG: u16 = 15;
+/// The number of low sequence number bits in a compact header, which tolerates
+/// reordering or loss of up to half as many packets
+pub const COMPACT_SEQ_BITS: u8 = 12;
+const COMPACT_SEQ_MASK: u16 = (1 << COMPACT_SEQ_BITS) - 1;
+
 impl Serde for PacketHeader {
 	fn de(reader: &mut BitReader) -> SerdeResult<Self> {
 		let header = Self {
@@ -120,7 +197,7 @@ impl Serde for PacketHeader {
 		// un-pad to byte boundary
 		for _ in 0..header.pad_bits() {
 			if reader.read_bit()? {
-				return Err(SerdeErr);
+				return Err(SerdeErr::at(reader.position()));
 			}
 		}
 		Ok(header)
@@ -145,13 +222,19 @@ pub enum RejectReason {
 	Disconnect,
 	ServerFull,
 	Version,
+	/// Another session with the same identity is connected; see
+	/// `ServerConfig::duplicate_identity`
+	Duplicate,
+	/// The connect request, including its message, exceeded
+	/// `ServerConfig::max_connect_bytes`
+	ConnectTooLarge,
 }
 
 pub mod packet {
 use super::*;
 
-pub const DH_KEY_SIZE: usize = size_of::<PublicKey>();
-pub const ENCRYPT_TAG_SIZE: usize = size_of::<chacha20poly1305::Tag>();
+pub const DH_KEY_SIZE: usize = cipher::KEY_SIZE;
+pub const ENCRYPT_TAG_SIZE: usize = cipher::TAG_SIZE;
 
 #[derive(Clone, Debug, PartialEq, SerdeInternal)]
 pub struct HandshakeReject {
@@ -164,13 +247,39 @@ pub struct EncryptRequest {
 	pub client_public_key: [u8; DH_KEY_SIZE],
 	/// client's transmission timestamp (monotonic nanoseconds since an arbitrary epoch)
 	pub client_timestamp_ns: TimestampNs,
-	/// Arbitrary padding to ensure EncryptRequest is larger than EncryptResponse to
-	/// mitigate amplification attacks.
-	pub padding: [u8; Self::PADDING_SIZE],
+	/// client's proposed cipher for connected packets
+	pub cipher: CipherKind,
+	/// the cookie of the server's HandshakeRetry, once received
+	pub cookie: Option<HandshakeCookie>,
+
+	// zero padding up to the end of the packet, making EncryptRequest larger than
+	// EncryptResponse to mitigate amplification attacks; can't derive Serde
 }
 
 impl EncryptRequest {
+	/// The default size of the padding
 	pub const PADDING_SIZE: usize = 256;
+	/// The smallest padding making EncryptRequest more than twice as large as
+	/// EncryptResponse
+	pub const MIN_PADDING_SIZE: usize =
+		2 * size_of::<EncryptResponse>() + 1 - DH_KEY_SIZE - size_of::<TimestampNs>();
+
+	pub fn write_padding(writer: &mut dyn BitWrite, bytes: usize) {
+		for _ in 0..bytes {
+			writer.write_byte(0);
+		}
+	}
+
+	/// Read the padding up to the end of the packet, returning its size in bytes
+	pub fn read_padding(reader: &mut BitReader) -> SerdeResult<usize> {
+		let bytes = reader.remaining_bits() / 8;
+		while reader.remaining_bits() > 0 {
+			if reader.read_bit()? {
+				return Err(SerdeErr::at(reader.position()));
+			}
+		}
+		Ok(bytes)
+	}
 }
 
 #[derive(Clone, Debug, PartialEq, SerdeInternal)]
@@ -181,12 +290,32 @@ pub struct EncryptResponse {
 	pub client_timestamp_ns: TimestampNs,
 	/// server's transmission timestamp (monotonic nanoseconds since an arbitrary epoch)
 	pub server_timestamp_ns: TimestampNs,
+	/// cipher selected by the server for connected packets
+	pub cipher: CipherKind,
 }
 
 // To mitigate amplification attacks, EncryptResponse must be smaller than EncryptRequest.
-// We don't have access to the actual bit stream sizes at compile time, so use struct size
-// with 2x safety margin as a proxy.
-const _: () = assert!(2 * size_of::<EncryptResponse>() < size_of::<EncryptRequest>());
+// We don't have access to the actual bit stream sizes at compile time, so
+// MIN_PADDING_SIZE uses struct sizes with 2x safety margin as a proxy.
+const _: () = assert!(EncryptRequest::MIN_PADDING_SIZE <= EncryptRequest::PADDING_SIZE);
+
+/// Proof that the client receives packets at its address, issued by the server without
+/// keeping any state
+#[derive(Clone, Copy, Debug, PartialEq, SerdeInternal)]
+pub struct HandshakeCookie {
+	/// server's issue timestamp (monotonic nanoseconds since an arbitrary epoch)
+	pub issued_ns: TimestampNs,
+	/// server's MAC of the client's address and `issued_ns`
+	pub tag: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, SerdeInternal)]
+pub struct HandshakeRetry {
+	pub cookie: HandshakeCookie,
+}
+
+// HandshakeRetry answers unpadded EncryptRequests, so it must be smaller than those
+const _: () = assert!(2 * size_of::<HandshakeRetry>() < DH_KEY_SIZE + size_of::<TimestampNs>());
 
 #[derive(Clone, Debug, PartialEq, SerdeInternal)]
 pub struct ConnectRequest {
@@ -194,6 +323,14 @@ pub struct ConnectRequest {
 	pub client_timestamp_ns: TimestampNs,
 	/// server's transmission timestamp from ClientChallengeRequest (verbatim)
 	pub server_timestamp_ns: TimestampNs,
+	/// whether the client requests to connect as a receive-only observer
+	pub observer: bool,
+	/// whether the client proposes compact headers for connected packets
+	pub compact_headers: bool,
+	/// the client's `Schema::fingerprint()`, which must match the server's
+	pub schema_fingerprint: u64,
+	/// the account the client claims to be, e.g. to detect duplicate sessions
+	pub identity: Option<String>,
 
 	// optional message; can't derive Serde
 }
@@ -202,6 +339,12 @@ pub struct ConnectRequest {
 pub struct ConnectResponse {
 	/// client's transmission timestamp from ClientConnectRequest (verbatim)
 	pub client_timestamp_ns: TimestampNs,
+	/// server-assigned id used to migrate the connection to a new address
+	pub session_id: SessionId,
+	/// whether the server accepted the client as a receive-only observer
+	pub observer: bool,
+	/// whether both sides use compact headers for connected packets from now on
+	pub compact_headers: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, SerdeInternal)]
@@ -217,12 +360,33 @@ pub struct Pong {
 #[derive(Clone, Debug, PartialEq, SerdeInternal)]
 pub struct Disconnect;
 
+// Heartbeat: ack header only (see Data)
+
+#[derive(Clone, Debug, PartialEq, SerdeInternal)]
+pub struct Migrate {
+	/// session id from ConnectResponse (verbatim)
+	pub session_id: SessionId,
+	/// salt of the key both hosts switch to, chosen by the Client for each migration
+	pub salt: cipher::Salt,
+	/// authentication tag over the session id under the new key, proving possession
+	/// of the shared key
+	pub tag: [u8; ENCRYPT_TAG_SIZE],
+}
+
+#[derive(Clone, Debug, PartialEq, SerdeInternal)]
+pub struct Redirect {
+	/// address of the Server which imported the connection
+	pub address: SocketAddr,
+	/// session id to present to the new Server with a Migrate packet
+	pub token: SessionId,
+}
+
 #[derive(Clone, Debug, PartialEq, SerdeInternal)]
 pub struct Data {
 	/// This is the last acknowledged packet index.
 	pub ack_index: PacketSeq,
-	/// This is an bitfield of all last 32 acknowledged packets
-	pub ack_bitfield: u32,
+	/// This is a bitfield of the last `AckWindow::size()` acknowledged packets
+	pub ack_bitfield: AckBitfield,
 
 	// each channel with messages:
 	//   true bit (channel continuation)
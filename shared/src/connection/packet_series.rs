@@ -0,0 +1,109 @@
+use naia_serde::Serde;
+use super::packet::PacketWriter;
+
+/// Writes a long stream of items across as many packets as needed, opening the next
+/// packet whenever the current one is full, so that writers don't manage the fill
+/// and flush loop themselves. Each packet is opened by `open`, which writes its
+/// header, plus acks for data packets.
+pub struct PacketSeries<F> {
+	open: F,
+	current: Option<PacketWriter>,
+	/// The free bits of the current packet right after opening it
+	empty_bits: u32,
+	packets: Vec<PacketWriter>,
+}
+
+impl<F: FnMut() -> PacketWriter> PacketSeries<F> {
+	pub fn new(open: F) -> Self {
+		Self { open, current: None, empty_bits: 0, packets: Vec::new() }
+	}
+
+	/// The current packet, opening one if needed, for writers which check the
+	/// remaining space themselves, e.g. with `BitWriter::counter()`
+	pub fn current(&mut self) -> &mut PacketWriter {
+		self.current.get_or_insert_with(|| {
+			let writer = (self.open)();
+			self.empty_bits = writer.bits_free();
+			writer
+		})
+	}
+
+	/// Whether nothing was written to the current packet since it was opened
+	fn current_is_empty(&self) -> bool {
+		self.current.as_ref().is_none_or(|writer| writer.bits_free() == self.empty_bits)
+	}
+
+	/// Close the current packet, if any, so the next write opens a new one
+	pub fn next_packet(&mut self) {
+		self.packets.extend(self.current.take());
+	}
+
+	/// Write `item` to the current packet, or to a new one if it doesn't fit. Returns
+	/// false, writing nothing, if it doesn't fit an empty packet either.
+	pub fn write<T: Serde>(&mut self, item: &T) -> bool {
+		if !self.fits(item) {
+			if self.current_is_empty() {
+				return false;
+			}
+			self.next_packet();
+			if !self.fits(item) {
+				return false;
+			}
+		}
+		item.ser(self.current());
+		true
+	}
+
+	fn fits<T: Serde>(&mut self, item: &T) -> bool {
+		let mut counter = self.current().inner_mut().counter();
+		item.ser(&mut counter);
+		!counter.overflowed()
+	}
+
+	/// The number of packets written so far, including the current one
+	pub fn len(&self) -> usize { self.packets.len() + self.current.is_some() as usize }
+
+	pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+	/// Close the current packet, returning all packets in the order written
+	pub fn finish(mut self) -> Vec<PacketWriter> {
+		self.next_packet();
+		self.packets
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::connection::packet::{PacketHeader, PacketType};
+	use naia_serde::BitReader;
+
+	#[test]
+	fn splits_stream() {
+		let mut seq = 0u16;
+		let mut series = PacketSeries::new(|| {
+			seq += 1;
+			// unencrypted, so the body directly follows the header
+			PacketWriter::new(PacketHeader { packet_type: PacketType::EncryptRequest, packet_seq: seq.into() })
+		});
+		assert!(series.is_empty());
+		for i in 0..1000u32 {
+			assert!(series.write(&i));
+		}
+		assert!(!series.write(&[0u8; 2000].to_vec()));
+		let packets = series.finish();
+		assert!(packets.len() > 1);
+
+		let mut next = 0u32;
+		for (i, packet) in packets.iter().enumerate() {
+			assert_eq!(packet.packet_seq(), (i as u16 + 1).into());
+			let mut reader = BitReader::from_slice(packet.slice());
+			reader.read::<PacketHeader>().unwrap();
+			while reader.remaining_bits() >= 32 {
+				assert_eq!(reader.read::<u32>(), Ok(next));
+				next += 1;
+			}
+		}
+		assert_eq!(next, 1000);
+	}
+}
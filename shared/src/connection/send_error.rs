@@ -0,0 +1,175 @@
+use crate::Instant;
+use std::{collections::HashMap, fmt, io, net::SocketAddr, time::Duration};
+
+/// The cause of a failed packet send
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SendErrorKind {
+	/// The packet exceeds the path MTU, e.g. with `SocketConfig::dont_fragment` set
+	/// (EMSGSIZE)
+	TooLarge,
+	/// No route to the destination network or host (ENETUNREACH, EHOSTUNREACH)
+	Unreachable,
+	/// The host reported the port closed to an earlier packet, with an ICMP port
+	/// unreachable (ECONNREFUSED, or WSAECONNRESET on Windows). Only reported on
+	/// connected sockets, i.e. by Clients.
+	Refused,
+	/// A firewall or policy blocks the destination (EPERM, EACCES)
+	Denied,
+	/// The socket's send buffer is full
+	WouldBlock,
+	Other,
+}
+
+impl SendErrorKind {
+	pub(crate) fn classify(error: &io::Error) -> Self {
+		if is_emsgsize(error) {
+			return Self::TooLarge;
+		}
+		match error.kind() {
+			io::ErrorKind::NetworkUnreachable | io::ErrorKind::HostUnreachable => Self::Unreachable,
+			io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset => Self::Refused,
+			io::ErrorKind::PermissionDenied => Self::Denied,
+			io::ErrorKind::WouldBlock => Self::WouldBlock,
+			_ => Self::Other,
+		}
+	}
+}
+
+#[cfg(unix)]
+fn is_emsgsize(error: &io::Error) -> bool { error.raw_os_error() == Some(libc::EMSGSIZE) }
+
+#[cfg(windows)]
+fn is_emsgsize(error: &io::Error) -> bool {
+	const WSAEMSGSIZE: i32 = 10040;
+	error.raw_os_error() == Some(WSAEMSGSIZE)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn is_emsgsize(_: &io::Error) -> bool { false }
+
+/// A packet which could not be sent to `addr`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SendError {
+	pub addr: SocketAddr,
+	pub kind: SendErrorKind,
+	/// The OS error code, if any
+	pub os_error: Option<i32>,
+	/// The number of identical errors suppressed since the last one reported; see
+	/// `SendErrorFilter`
+	pub suppressed: u64,
+}
+
+impl SendError {
+	pub fn new(addr: SocketAddr, error: &io::Error) -> Self {
+		Self { addr, kind: SendErrorKind::classify(error), os_error: error.raw_os_error(), suppressed: 0 }
+	}
+}
+
+impl fmt::Display for SendError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Failed to send packet to {} ({:?})", self.addr, self.kind)?;
+		if let Some(code) = self.os_error {
+			write!(f, ": {}", io::Error::from_raw_os_error(code))?;
+		}
+		if self.suppressed > 0 {
+			write!(f, ", {} more suppressed", self.suppressed)?;
+		}
+		Ok(())
+	}
+}
+
+/// Limits repeated send errors to the same address: after reporting an error, those
+/// of the same kind are suppressed for `window`, then reported once with their count
+pub struct SendErrorFilter {
+	window: Duration,
+	/// The errors reported within their window, when, and the count suppressed since
+	recent: HashMap<(SocketAddr, SendErrorKind), (SendError, Instant, u64)>,
+}
+
+impl SendErrorFilter {
+	pub fn new(window: Duration) -> Self {
+		Self { window, recent: HashMap::new() }
+	}
+
+	pub fn window(&self) -> Duration { self.window }
+
+	/// Returns `error` if it should be reported, or `None` if suppressed
+	pub fn report(&mut self, error: SendError) -> Option<SendError> {
+		let key = (error.addr, error.kind);
+		if let Some((_, _, suppressed)) = self.recent.get_mut(&key) {
+			*suppressed += 1;
+			return None;
+		}
+
+		self.recent.insert(key, (error.clone(), Instant::now(), 0));
+		Some(error)
+	}
+
+	/// End the windows elapsed by now, returning a summary of each one which
+	/// suppressed errors
+	pub fn expire(&mut self) -> Vec<SendError> {
+		let now = Instant::now();
+		let mut summaries = Vec::new();
+		self.recent.retain(|_, (error, reported, suppressed)| {
+			if now.duration_since(*reported) < self.window {
+				return true;
+			}
+			if *suppressed > 0 {
+				summaries.push(SendError { suppressed: *suppressed, ..error.clone() });
+			}
+			false
+		});
+		summaries
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn filter() {
+		let addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+		let error = |kind| SendError { addr, kind, os_error: None, suppressed: 0 };
+
+		let mut filter = SendErrorFilter::new(Duration::ZERO);
+		assert!(filter.report(error(SendErrorKind::Unreachable)).is_some());
+		assert!(filter.report(error(SendErrorKind::Unreachable)).is_none());
+		assert!(filter.report(error(SendErrorKind::Unreachable)).is_none());
+		assert!(filter.report(error(SendErrorKind::TooLarge)).is_some());
+
+		let summaries = filter.expire();
+		assert_eq!(summaries, [SendError { suppressed: 2, ..error(SendErrorKind::Unreachable) }]);
+		assert!(filter.report(error(SendErrorKind::Unreachable)).is_some());
+	}
+
+	#[test]
+	fn classify() {
+		let error = io::Error::from(io::ErrorKind::HostUnreachable);
+		assert_eq!(SendErrorKind::classify(&error), SendErrorKind::Unreachable);
+		let error = io::Error::from(io::ErrorKind::ConnectionRefused);
+		assert_eq!(SendErrorKind::classify(&error), SendErrorKind::Refused);
+		#[cfg(unix)]
+		assert_eq!(SendErrorKind::classify(&io::Error::from_raw_os_error(libc::EMSGSIZE)), SendErrorKind::TooLarge);
+		#[cfg(unix)]
+		assert_eq!(SendErrorKind::classify(&io::Error::from_raw_os_error(libc::EPERM)), SendErrorKind::Denied);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn broadcast_denied() {
+		use crate::{Io, NaiaError, SocketConfig};
+		use std::net::Ipv4Addr;
+
+		// sockets need SO_BROADCAST to send broadcasts
+		let mut io = Io::listen((Ipv4Addr::LOCALHOST, 0).into(), &None, &SocketConfig::default()).unwrap();
+		let addr: SocketAddr = (Ipv4Addr::BROADCAST, 4000).into();
+		match io.send_packet(&addr, &[0]) {
+			Err(NaiaError::Send(error)) => {
+				assert_eq!((error.addr, error.kind), (addr, SendErrorKind::Denied));
+				assert!(error.to_string().starts_with("Failed to send packet to 255.255.255.255:4000 (Denied): "));
+			}
+			result => panic!("unexpected {result:?}"),
+		}
+	}
+}
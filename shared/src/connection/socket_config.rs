@@ -0,0 +1,143 @@
+use std::io;
+
+/// Options of the UDP socket, applied when binding it. Each option left unset keeps
+/// the OS default. Setting options is only supported on Linux, Android, macOS, iOS
+/// and FreeBSD; elsewhere, and on wasm, binding fails if any option is set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SocketConfig {
+	/// The receive buffer size, in bytes (SO_RCVBUF). Busy servers drop incoming
+	/// packets once the buffer fills between receives. The OS may clamp the size, e.g.
+	/// to `net.core.rmem_max` on Linux.
+	pub recv_buffer_size: Option<usize>,
+	/// The send buffer size, in bytes (SO_SNDBUF). The OS may clamp the size, e.g. to
+	/// `net.core.wmem_max` on Linux.
+	pub send_buffer_size: Option<usize>,
+	/// The DSCP code point (0 to 63) marking outgoing packets for QoS, e.g. 46
+	/// (Expedited Forwarding). Networks may ignore or rewrite it.
+	pub dscp: Option<u8>,
+	/// Set the Don't Fragment bit, so oversized packets are dropped rather than
+	/// fragmented
+	pub dont_fragment: bool,
+}
+
+impl SocketConfig {
+	fn is_default(&self) -> bool { *self == Self::default() }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), any(
+	target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios",
+	target_os = "freebsd",
+)))]
+mod sys {
+	use super::SocketConfig;
+	use std::{io, mem, net::UdpSocket, os::fd::AsRawFd};
+
+	fn set(socket: &UdpSocket, level: libc::c_int, name: libc::c_int, value: libc::c_int) -> io::Result<()> {
+		// SAFETY: the value outlives the call, and its size is passed along
+		let result = unsafe {
+			libc::setsockopt(
+				socket.as_raw_fd(), level, name,
+				&value as *const libc::c_int as *const libc::c_void,
+				mem::size_of::<libc::c_int>() as libc::socklen_t,
+			)
+		};
+		match result {
+			0 => Ok(()),
+			_ => Err(io::Error::last_os_error()),
+		}
+	}
+
+	fn get(socket: &UdpSocket, level: libc::c_int, name: libc::c_int) -> io::Result<libc::c_int> {
+		let mut value: libc::c_int = 0;
+		let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+		// SAFETY: the value and its length outlive the call
+		let result = unsafe {
+			libc::getsockopt(
+				socket.as_raw_fd(), level, name,
+				&mut value as *mut libc::c_int as *mut libc::c_void, &mut len,
+			)
+		};
+		match result {
+			0 => Ok(value),
+			_ => Err(io::Error::last_os_error()),
+		}
+	}
+
+	fn size(bytes: usize) -> io::Result<libc::c_int> {
+		libc::c_int::try_from(bytes).map_err(|_| io::ErrorKind::InvalidInput.into())
+	}
+
+	pub fn apply(socket: &UdpSocket, config: &SocketConfig) -> io::Result<()> {
+		let ipv6 = socket.local_addr()?.is_ipv6();
+		if let Some(bytes) = config.recv_buffer_size {
+			set(socket, libc::SOL_SOCKET, libc::SO_RCVBUF, size(bytes)?)?;
+		}
+		if let Some(bytes) = config.send_buffer_size {
+			set(socket, libc::SOL_SOCKET, libc::SO_SNDBUF, size(bytes)?)?;
+		}
+		if let Some(dscp) = config.dscp {
+			// the low 2 bits of the traffic class are for ECN
+			let tos = libc::c_int::from(dscp) << 2;
+			match ipv6 {
+				true => set(socket, libc::IPPROTO_IPV6, libc::IPV6_TCLASS, tos)?,
+				false => set(socket, libc::IPPROTO_IP, libc::IP_TOS, tos)?,
+			}
+		}
+		if config.dont_fragment {
+			match ipv6 {
+				true => set(socket, libc::IPPROTO_IPV6, libc::IPV6_DONTFRAG, 1)?,
+				#[cfg(any(target_os = "linux", target_os = "android"))]
+				false => set(socket, libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_DO)?,
+				#[cfg(not(any(target_os = "linux", target_os = "android")))]
+				false => set(socket, libc::IPPROTO_IP, libc::IP_DONTFRAG, 1)?,
+			}
+		}
+		Ok(())
+	}
+
+	pub fn buffer_sizes(socket: &UdpSocket) -> io::Result<(usize, usize)> {
+		let recv = get(socket, libc::SOL_SOCKET, libc::SO_RCVBUF)?;
+		let send = get(socket, libc::SOL_SOCKET, libc::SO_SNDBUF)?;
+		Ok((recv as usize, send as usize))
+	}
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(any(
+	target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios",
+	target_os = "freebsd",
+))))]
+mod sys {
+	use super::SocketConfig;
+	use std::{io, net::UdpSocket};
+
+	pub fn apply(_: &UdpSocket, _: &SocketConfig) -> io::Result<()> { Err(io::ErrorKind::Unsupported.into()) }
+
+	pub fn buffer_sizes(_: &UdpSocket) -> io::Result<(usize, usize)> { Err(io::ErrorKind::Unsupported.into()) }
+}
+
+/// Apply `config` to a freshly bound socket
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn apply(socket: &std::net::UdpSocket, config: &SocketConfig) -> io::Result<()> {
+	if config.is_default() {
+		return Ok(());
+	}
+	if config.dscp.is_some_and(|dscp| dscp > 63) {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "DSCP code points range from 0 to 63"));
+	}
+	sys::apply(socket, config)
+}
+
+/// Browsers don't expose socket options
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn apply<S>(_: &S, config: &SocketConfig) -> io::Result<()> {
+	match config.is_default() {
+		true => Ok(()),
+		false => Err(io::ErrorKind::Unsupported.into()),
+	}
+}
+
+/// The receive and send buffer sizes of `socket`, in bytes, as reported by the OS
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn buffer_sizes(socket: &std::net::UdpSocket) -> io::Result<(usize, usize)> {
+	sys::buffer_sizes(socket)
+}
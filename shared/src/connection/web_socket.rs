@@ -0,0 +1,80 @@
+use std::{cell::{Cell, RefCell}, collections::VecDeque, io, net::SocketAddr, rc::Rc};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{BinaryType, MessageEvent};
+
+/// A WebSocket with a UdpSocket-like, non-blocking interface. Each binary WebSocket
+/// message carries exactly one packet. Browsers cannot send UDP datagrams, so servers
+/// must be reachable through a WebSocket endpoint which relays these messages to and
+/// from the server's UDP socket.
+pub struct WebSocket {
+	addr: SocketAddr,
+	socket: web_sys::WebSocket,
+	incoming: Rc<RefCell<VecDeque<Box<[u8]>>>>,
+	closed: Rc<Cell<bool>>,
+	_on_message: Closure<dyn FnMut(MessageEvent)>,
+	_on_close: Closure<dyn FnMut()>,
+}
+
+impl WebSocket {
+	pub fn connect(addr: SocketAddr) -> io::Result<Self> {
+		let socket = web_sys::WebSocket::new(&format!("ws://{addr}"))
+			.map_err(|_| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+		socket.set_binary_type(BinaryType::Arraybuffer);
+
+		let incoming: Rc<RefCell<VecDeque<Box<[u8]>>>> = Rc::default();
+		let on_message = {
+			let incoming = incoming.clone();
+			Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+				if let Ok(buffer) = event.data().dyn_into::<js_sys::ArrayBuffer>() {
+					let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+					incoming.borrow_mut().push_back(bytes.into_boxed_slice());
+				}
+			})
+		};
+		socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+		let closed: Rc<Cell<bool>> = Rc::default();
+		let on_close = {
+			let closed = closed.clone();
+			Closure::<dyn FnMut()>::new(move || closed.set(true))
+		};
+		socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+		Ok(Self {
+			addr, socket, incoming, closed, _on_message: on_message, _on_close: on_close,
+		})
+	}
+
+	pub fn send_to(&self, payload: &[u8], _addr: &SocketAddr) -> io::Result<usize> {
+		// like UDP, packets sent before the socket opens are silently dropped
+		if self.socket.ready_state() != web_sys::WebSocket::OPEN {
+			return Ok(payload.len());
+		}
+
+		self.socket.send_with_u8_array(payload)
+			.map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+		Ok(payload.len())
+	}
+
+	pub fn recv_from(&self, buffer: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+		let Some(packet) = self.incoming.borrow_mut().pop_front() else {
+			return Err(if self.closed.get() {
+				io::ErrorKind::ConnectionReset.into()
+			} else {
+				io::ErrorKind::WouldBlock.into()
+			});
+		};
+
+		let size = packet.len().min(buffer.len());
+		buffer[..size].copy_from_slice(&packet[..size]);
+		Ok((size, self.addr))
+	}
+}
+
+impl Drop for WebSocket {
+	fn drop(&mut self) {
+		self.socket.set_onmessage(None);
+		self.socket.set_onclose(None);
+		let _ = self.socket.close();
+	}
+}
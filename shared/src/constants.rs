@@ -1,2 +1,4 @@
-pub const FRAGMENTATION_LIMIT_BYTES: usize = 400;
+/// Leaves room within `MTU_SIZE_BYTES` for packet headers, the encryption tag, and
+/// the fragment header
+pub const FRAGMENTATION_LIMIT_BYTES: usize = 300;
 pub const FRAGMENTATION_LIMIT_BITS: u32 = (FRAGMENTATION_LIMIT_BYTES as u32) * 8;
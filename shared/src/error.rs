@@ -1,5 +1,6 @@
+use crate::{SendError, SendErrorKind};
 use naia_serde::SerdeErr;
-use std::{error, fmt, io};
+use std::{error, fmt, io, net::SocketAddr};
 
 #[derive(Debug)]
 pub enum NaiaError {
@@ -8,12 +9,40 @@ pub enum NaiaError {
 	Io(io::Error),
     Message(String),
 	Serde(SerdeErr),
-	Malformed(&'static str),
+	/// A packet or message of the named type failed to decode, with the cause if known
+	Malformed(&'static str, Option<SerdeErr>),
+	/// A message was sent on the named channel in a direction it doesn't allow
+	ChannelMisuse(String),
+	/// A message of the named type exceeded the Schema's maximum message size
+	MessageTooLarge(String),
+	/// A fragmented message on a reliable channel had to be dropped before it was
+	/// reassembled, exceeding the limits of `ReassemblyConfig`
+	ReassemblyFailed,
+	/// A packet could not be sent
+	Send(SendError),
+	/// Packets sent to the given address came back from a host of the same kind, e.g.
+	/// a Client connecting to its own address or to another Client
+	Loopback(SocketAddr),
 }
 
 impl NaiaError {
 	pub fn malformed<T>() -> Self {
-		Self::Malformed(std::any::type_name::<T>())
+		Self::Malformed(std::any::type_name::<T>(), None)
+	}
+
+	/// As `malformed()`, keeping where decoding failed
+	pub fn malformed_by<T>(err: SerdeErr) -> Self {
+		Self::Malformed(std::any::type_name::<T>(), Some(err))
+	}
+
+	/// Whether the socket reported the remote host's port closed; see
+	/// `SendErrorKind::Refused`
+	pub fn is_refused(&self) -> bool {
+		match self {
+			NaiaError::Io(err) => SendErrorKind::classify(err) == SendErrorKind::Refused,
+			NaiaError::Send(err) => err.kind == SendErrorKind::Refused,
+			_ => false,
+		}
 	}
 }
 
@@ -27,7 +56,17 @@ impl fmt::Display for NaiaError {
 			NaiaError::Io(err) => io::Error::fmt(err, f),
             NaiaError::Message(msg) => write!(f, "Naia Error: {msg}"),
 			NaiaError::Serde(err) => SerdeErr::fmt(err, f),
-			NaiaError::Malformed(name) => write!(f, "Received malformed {name}"),
+			NaiaError::Malformed(name, None) => write!(f, "Received malformed {name}"),
+			NaiaError::Malformed(name, Some(err)) => write!(f, "Received malformed {name}: {err}"),
+			NaiaError::ChannelMisuse(name) =>
+				write!(f, "Channel {name} does not allow messages in this direction"),
+			NaiaError::MessageTooLarge(name) =>
+				write!(f, "Message {name} exceeds the maximum message size"),
+			NaiaError::ReassemblyFailed =>
+				write!(f, "Fragmented message dropped before reassembly"),
+			NaiaError::Send(err) => SendError::fmt(err, f),
+			NaiaError::Loopback(addr) =>
+				write!(f, "Connection to {addr} loops back to this host or one of the same kind"),
         }
     }
 }
@@ -5,52 +5,86 @@
 
 extern crate core;
 
+// the wasm32 backend is built on browser APIs
+#[cfg(all(target_arch = "wasm32", not(target_os = "unknown")))]
+compile_error!("wasm32 targets other than wasm32-unknown-unknown (e.g. WASI) are not supported");
+
 pub use naia_derive::{
-    Channel, Message,
+    Channel, Message, MessageRouter,
 };
 pub use naia_serde::{
-	BitCounter, BitReader, BitWrite, BitWriter, ConstBitLength, Serde,
-	SerdeErr, SerdeResult, SerdeIntegerConversion, SerdeInternal, SignedInteger,
-	SignedVariableInteger, UnsignedInteger, UnsignedVariableInteger, MTU_SIZE_BITS,
-	MTU_SIZE_BYTES,
+	BitCounter, BitField, BitReader, BitVecWriter, BitWrite, BitWriter, ByteLen,
+	ConstBitLength, FixedAscii, ItemLen, Serde, Quantized, Secret, SerdeBounded, SerdeErr,
+	SerdeResult, SerdeIntegerConversion, SerdeInternal, SignedInteger, SignedVariableInteger,
+	UnsignedInteger, UnsignedVariableInteger, VarLen, Zeroize, MTU_SIZE_BITS, MTU_SIZE_BYTES,
+	dequantize, quantize, serde_remote,
 };
 
+pub mod compression;
 mod connection;
 mod constants;
 pub mod error;
 mod messages;
 pub mod metrics;
+mod protocol_spec;
 mod schema;
+mod schema_manifest;
 mod timer;
 mod types;
 
 pub use error::NaiaError;
 pub use connection::{
-    ack_manager::AckManager,
-    base_connection::BaseConnection,
+    ack_manager::{AckBitfield, AckManager, AckWindow},
+    base_connection::{BaseConnection, ConnectionStats, HandshakeTiming},
+	cipher::{CipherKind, EphemeralKey, MacKey, Salt},
 	conditioner::ConditionerConfig,
-    connection_config::ConnectionConfig,
+	congestion::{CongestionConfig, LinkQuality},
+    connection_config::{
+        CoalesceConfig, ConfigError, ConnectionConfig, ConnectionConfigBuilder, DecryptFailureConfig,
+        HandshakePadding,
+    },
     io::Io,
+	middleware::ConnectionMiddleware,
     packet::{ self, * },
+	packet_series::PacketSeries,
+	send_error::{SendError, SendErrorFilter, SendErrorKind},
+	socket_config::SocketConfig,
 };
 pub use messages::{
+	blob::{Blob, BlobChannel, MAX_BLOB_CHANNELS},
     channels::{
-        channel::{Channel, ChannelDirection, ChannelMode},
+        channel::{Channel, ChannelDirection, ChannelMode, PausePolicy, RateLimit, ResendConfig},
         channel_kinds::{ChannelKind, ChannelKinds},
         receivers::{
-            channel_receiver::ChannelReceiver, ordered_reliable_receiver::OrderedReliableReceiver,
+            channel_receiver::ChannelReceiver, fragment_receiver::ReassemblyConfig,
+            ordered_reliable_receiver::OrderedReliableReceiver,
             unordered_reliable_receiver::UnorderedReliableReceiver,
         },
-        senders::{channel_sender::ChannelSender, reliable_sender::ReliableSender},
+        senders::{
+            channel_sender::{ChannelSender, QueueDepth}, reliable_sender::ReliableSender,
+        },
     },
+    checksum::{ChecksumChannel, StateChecksum},
     message::{Message, MessageBuilder},
     message_container::MessageContainer,
     message_kinds::{MessageKind, MessageKinds},
     message_manager::MessageManager,
     named::Named,
+    sync::{SnapshotReceiver, SnapshotSender, SyncChannel, SyncChunk, SyncDone, SYNC_CHUNK_BYTES},
 };
 pub use packet::RejectReason;
 
-pub use schema::Schema;
+pub use protocol_spec::{
+	BitWidthProbe, ChannelSpec, FieldSpec, FixedBitWidth, MessageSpec, ProtocolSpec,
+	VariableBitWidth,
+};
+pub use schema::{ProtocolPlugin, Schema, SchemaBuilder};
+pub use schema_manifest::{
+	check_schema_manifest, ManifestEntry, ManifestError, SchemaDiff, SchemaManifest,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub use web_time::Instant;
 pub use timer::Timer;
 pub use types::*;
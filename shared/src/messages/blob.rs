@@ -0,0 +1,22 @@
+use naia_derive::MessageInternal;
+use crate::Channel;
+
+/// An opaque message, for peers that cannot share Rust Message types, such as clients
+/// using the C bindings. The application decides how to encode the bytes.
+#[derive(MessageInternal)]
+pub struct Blob {
+	pub bytes: Box<[u8]>,
+}
+
+impl Blob {
+	pub fn new(bytes: &[u8]) -> Self { Self { bytes: bytes.into() } }
+}
+
+/// The maximum number of distinct BlobChannels
+pub const MAX_BLOB_CHANNELS: u8 = 8;
+
+/// A Channel identified by index rather than by Rust type, for peers that cannot
+/// share Rust Channel types. `ID` must be less than `MAX_BLOB_CHANNELS`.
+pub struct BlobChannel<const ID: u8>;
+
+impl<const ID: u8> Channel for BlobChannel<ID> {}
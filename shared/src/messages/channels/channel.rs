@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 // Channel Trait
 pub trait Channel: 'static {}
 
@@ -6,11 +8,25 @@ pub trait Channel: 'static {}
 pub struct ChannelSettings {
     pub mode: ChannelMode,
     pub direction: ChannelDirection,
+    /// Whether receive-only observers may still send on this channel, e.g. for
+    /// spectator chat
+    pub observer_send: bool,
+    /// Retransmission tuning, for reliable channels
+    pub resend: ResendConfig,
+    /// What happens to messages queued while the channel is paused for a remote host
+    pub pause: PausePolicy,
+    /// The group to configure and pause this channel with related ones, e.g.
+    /// "gameplay"; see `SchemaBuilder::channel_group()`
+    pub group: Option<&'static str>,
+    /// The most messages each Client may send on this channel, enforced by the Server;
+    /// see `SchemaBuilder::rate_limit()`
+    pub rate_limit: Option<RateLimit>,
 }
 
 impl ChannelSettings {
     pub fn new(mode: ChannelMode, direction: ChannelDirection) -> Self {
-        Self { mode, direction }
+        let pause = PausePolicy::default_for(&mode);
+        Self { mode, direction, observer_send: false, resend: ResendConfig::default(), pause, group: None, rate_limit: None }
     }
 
     pub fn reliable(&self) -> bool {
@@ -40,7 +56,79 @@ impl ChannelSettings {
     }
 }
 
-#[derive(Clone)]
+// ResendConfig
+/// Retransmission tuning for a reliable channel. Unacknowledged messages are first
+/// resent after `initial_delay + rtt_factor * rtt + jitter_factor * jitter`, and the
+/// delay is multiplied by `backoff` after each resend.
+#[derive(Clone, Debug)]
+pub struct ResendConfig {
+    pub initial_delay: Duration,
+    pub rtt_factor: f32,
+    pub jitter_factor: f32,
+    /// Use 1 to resend at a constant interval
+    pub backoff: f32,
+    /// The number of times a message may be resent before the connection is considered
+    /// timed out. Use `None` to resend until the connection times out on its own.
+    pub max_attempts: Option<u32>,
+}
+
+impl ResendConfig {
+    /// The delay before resending a message which was already sent `attempts` times
+    pub fn delay(&self, rtt_ms: f32, jitter_ms: f32, attempts: u32) -> Duration {
+        let base_ms = self.rtt_factor * rtt_ms + self.jitter_factor * jitter_ms;
+        let backoff = self.backoff.powi(attempts.saturating_sub(1) as i32);
+        (self.initial_delay + Duration::from_secs_f32(base_ms.max(0.0) / 1000.0)).mul_f32(backoff)
+    }
+}
+
+impl Default for ResendConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::ZERO,
+            rtt_factor: 1.0,
+            jitter_factor: 1.5,
+            backoff: 1.0,
+            max_attempts: None,
+        }
+    }
+}
+
+// RateLimit
+/// A cap on the messages received on a channel, per connection. Messages beyond
+/// `max_messages` within each `window` are dropped.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    pub max_messages: u32,
+    pub window: Duration,
+}
+
+impl RateLimit {
+    pub fn per_sec(max_messages: u32) -> Self {
+        Self { max_messages, window: Duration::from_secs(1) }
+    }
+}
+
+// PausePolicy
+/// How a paused channel treats newly queued messages, see `Server::pause_channel()`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PausePolicy {
+    /// Keep messages, and queue them in order once the channel is resumed
+    Buffer,
+    /// Discard messages, e.g. stale state deltas
+    Drop,
+}
+
+impl PausePolicy {
+    /// Reliable channels buffer, unreliable channels drop
+    pub fn default_for(mode: &ChannelMode) -> Self {
+        match mode {
+            ChannelMode::UnorderedUnreliable | ChannelMode::SequencedUnreliable => Self::Drop,
+            _ => Self::Buffer,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum ChannelMode {
     /// Messages can be dropped, duplicated and/or arrive in any order.
     /// Resend=no, Dedupe=no, Order=no
@@ -67,7 +155,7 @@ pub enum ChannelMode {
 }
 
 // ChannelDirection
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum ChannelDirection {
     ClientToServer,
     ServerToClient,
@@ -1,8 +1,9 @@
 use std::{any::TypeId, collections::HashMap};
 
-use naia_serde::{BitReader, BitWrite, ConstBitLength, Serde, SerdeErr};
+use naia_serde::{BitReader, BitWrite, SerdeErr};
 
 use crate::messages::channels::channel::{Channel, ChannelSettings};
+use crate::protocol_spec::{ChannelSpec, short_type_name};
 
 type NetId = u16;
 
@@ -19,19 +20,22 @@ impl ChannelKind {
         }
     }
 
+    /// Write the net id, on `ChannelKinds::bit_length()` bits
     pub fn ser(&self, channel_kinds: &ChannelKinds, writer: &mut dyn BitWrite) {
-        channel_kinds.kind_to_net_id(self).ser(writer);
+        let net_id = channel_kinds.kind_to_net_id(self);
+        for i in 0..channel_kinds.bit_length() {
+            writer.write_bit(net_id >> i & 1 != 0);
+        }
     }
 
     pub fn de(channel_kinds: &ChannelKinds, reader: &mut BitReader) -> Result<Self, SerdeErr> {
-        let net_id: NetId = NetId::de(reader)?;
-        Ok(channel_kinds.net_id_to_kind(&net_id))
-    }
-}
-
-impl ConstBitLength for ChannelKind {
-    fn const_bit_length() -> u32 {
-        <NetId as ConstBitLength>::const_bit_length()
+        let mut net_id: NetId = 0;
+        for i in 0..channel_kinds.bit_length() {
+            if reader.read_bit()? {
+                net_id |= 1 << i;
+            }
+        }
+        channel_kinds.net_id_map.get(&net_id).copied().ok_or_else(|| SerdeErr::at(reader.position()))
     }
 }
 
@@ -40,6 +44,7 @@ pub struct ChannelKinds {
     current_net_id: NetId,
     kind_map: HashMap<ChannelKind, (NetId, ChannelSettings)>,
     net_id_map: HashMap<NetId, ChannelKind>,
+    names: HashMap<NetId, String>,
 }
 
 impl ChannelKinds {
@@ -48,18 +53,41 @@ impl ChannelKinds {
             current_net_id: 0,
             kind_map: HashMap::new(),
             net_id_map: HashMap::new(),
+            names: HashMap::new(),
         }
     }
 
+    /// Register `C`. Registering it again, e.g. from several plugins, is allowed with
+    /// the same mode, direction and observer setting, and panics otherwise.
     pub fn add_channel<C: Channel>(&mut self, settings: ChannelSettings) {
         let channel_kind = ChannelKind::of::<C>();
+        if let Some((_, current)) = self.kind_map.get(&channel_kind) {
+            let same = (&current.mode, &current.direction, current.observer_send)
+                == (&settings.mode, &settings.direction, settings.observer_send);
+            assert!(same, "channel {} registered with different settings", short_type_name::<C>());
+            return;
+        }
+
         let net_id = self.current_net_id;
         self.kind_map.insert(channel_kind, (net_id, settings));
         self.net_id_map.insert(net_id, channel_kind);
+        self.names.insert(net_id, short_type_name::<C>());
         self.current_net_id += 1;
         //TODO: check for current_id overflow?
     }
 
+    /// The width of a channel id on the wire, i.e. just enough bits for the number of
+    /// registered channels. Ids are assigned in registration order, so both hosts must
+    /// register the same channels in the same order; see `Schema::fingerprint()`.
+    pub fn bit_length(&self) -> u32 {
+        let max_id = self.current_net_id.saturating_sub(1);
+        NetId::BITS - max_id.leading_zeros()
+    }
+
+    pub fn channel_mut(&mut self, kind: &ChannelKind) -> Option<&mut ChannelSettings> {
+        self.kind_map.get_mut(kind).map(|(_, settings)| settings)
+    }
+
     pub fn channels(&self) -> Vec<(ChannelKind, ChannelSettings)> {
         // TODO: is there a better way to do this without copying + cloning?
         // How to return a reference here (behind a Mutex ..)
@@ -75,10 +103,30 @@ impl ChannelKinds {
         settings.clone()
     }
 
-    fn net_id_to_kind(&self, net_id: &NetId) -> ChannelKind {
-        return *self.net_id_map.get(net_id).expect(
-            "Must properly initialize Channel with Protocol via `add_channel()` function!",
-        );
+    /// The channels in the given group, in net id order
+    pub fn group(&self, group: &str) -> Vec<ChannelKind> {
+        (0..self.current_net_id)
+            .map(|id| self.net_id_map[&id])
+            .filter(|kind| self.kind_map[kind].1.group == Some(group))
+            .collect()
+    }
+
+    /// The channel's type name, for diagnostics
+    pub fn name(&self, kind: &ChannelKind) -> &str {
+        &self.names[&self.kind_to_net_id(kind)]
+    }
+
+    /// Describes all channels, in net id order
+    pub fn describe(&self) -> Vec<ChannelSpec> {
+        (0..self.current_net_id).map(|id| {
+            let (_, settings) = &self.kind_map[&self.net_id_map[&id]];
+            ChannelSpec {
+                id,
+                name: self.names[&id].clone(),
+                mode: settings.mode.clone(),
+                direction: settings.direction.clone(),
+            }
+        }).collect()
     }
 
     fn kind_to_net_id(&self, channel_kind: &ChannelKind) -> NetId {
@@ -1,6 +1,6 @@
-use naia_serde::{BitReader, SerdeErr};
+use naia_serde::{BitReader, BitWrite, SerdeErr};
 
-use crate::messages::{message_container::MessageContainer, message_kinds::MessageKinds};
+use crate::{error::NaiaResult, messages::{message_container::MessageContainer, message_kinds::MessageKinds}};
 
 pub trait ChannelReceiver: Send + Sync {
     /// Read messages from an internal buffer and return their content
@@ -11,7 +11,16 @@ pub trait ChannelReceiver: Send + Sync {
         &mut self,
         message_kinds: &MessageKinds,
         reader: &mut BitReader,
-    ) -> Result<(), SerdeErr>;
+    ) -> NaiaResult;
+
+	/// Write the state needed to continue receiving on this channel in another
+	/// process; see `BaseConnection::export()`
+	fn export(&self, message_kinds: &MessageKinds, writer: &mut dyn BitWrite);
+
+	/// Restore the state written by `export()`
+	fn import(
+		&mut self, message_kinds: &MessageKinds, reader: &mut BitReader,
+	) -> Result<(), SerdeErr>;
 
 	/// Performance counter for the number of total received messages
 	fn msg_rx_count(&self) -> u64;
@@ -21,4 +30,12 @@ pub trait ChannelReceiver: Send + Sync {
 
 	/// Performance counter for the number of received messages missed
 	fn msg_rx_miss_count(&self) -> u64;
+
+	/// Performance counter for the number of partially assembled messages dropped
+	/// after the reassembly timeout
+	fn frag_rx_expired_count(&self) -> u64;
+
+	/// Performance counter for the number of fragments dropped because too many
+	/// messages were already partially assembled
+	fn frag_rx_refused_count(&self) -> u64;
 }
@@ -1,60 +1,154 @@
-use crate::{MessageContainer, MessageKinds};
-use crate::messages::fragment::{FragmentId, FragmentedMessage};
-use log::info;
-use naia_serde::BitReader;
-use std::collections::HashMap;
+use crate::{
+	constants::FRAGMENTATION_LIMIT_BYTES,
+	error::{NaiaError, NaiaResult},
+	messages::fragment::{FragmentId, FragmentedMessage},
+	Instant, MessageContainer, MessageKinds,
+};
+use naia_serde::{BitReader, BitWrite, Serde, SerdeErr};
+use std::{collections::{BTreeMap, HashMap}, time::Duration};
+
+/// Limits on reassembling fragmented messages, which protect receivers from peers
+/// sending endless first fragments. Reliable messages can't be dropped silently, so
+/// exceeding either limit fails with `NaiaError::ReassemblyFailed`, which disconnects
+/// the peer.
+#[derive(Clone, Debug)]
+pub struct ReassemblyConfig {
+	/// The number of partially assembled messages held on each reliable channel
+	pub max_partial: usize,
+	/// The amount of time to wait for the remaining fragments of a message
+	pub timeout: Duration,
+}
+
+impl Default for ReassemblyConfig {
+	fn default() -> Self {
+		Self { max_partial: 8, timeout: Duration::from_secs(10) }
+	}
+}
+
+struct PartialMessage {
+	total: usize,
+	fragments: BTreeMap<usize, Box<[u8]>>,
+	started: Instant,
+}
 
 pub struct FragmentReceiver {
-    map: HashMap<FragmentId, (u32, Vec<Box<[u8]>>)>,
+	config: ReassemblyConfig,
+	map: HashMap<FragmentId, PartialMessage>,
+	frag_rx_expired_count: u64,
+	frag_rx_refused_count: u64,
 }
 
 impl FragmentReceiver {
-    pub fn new() -> Self {
-        Self {
-            map: HashMap::new(),
-        }
-    }
-
-    pub(crate) fn receive(
-        &mut self,
-        message_kinds: &MessageKinds,
-        message: MessageContainer,
-    ) -> Option<MessageContainer> {
-
-        if !message.is_fragment() {
-            panic!("Received non-fragmented message in FragmentReceiver!");
-        }
-
-        // Message is a fragment, need to process
-        let fragment = message
-            .to_boxed_any()
-            .downcast::<FragmentedMessage>()
-            .unwrap();
-        let fragment_id = fragment.id();
-        let fragment_index = fragment.index();
-        let fragment_total = fragment.total().as_usize();
-        info!("fragment_total: {fragment_total}");
-        if !self.map.contains_key(&fragment_id) {
-            self.map
-                .insert(fragment_id, (0, vec![Box::new([]); fragment_total]));
-        }
-        let (fragments_received, fragment_list) = self.map.get_mut(&fragment_id).unwrap();
-        fragment_list[fragment_index.as_usize()] = fragment.to_payload();
-        *fragments_received += 1;
-        if *fragments_received != fragment_total as u32 {
-            return None;
-        }
-
-        // we have received all fragments! put it all together
-        let (_, fragment_list) = self.map.remove(&fragment_id).unwrap();
-        let concat_list = fragment_list.concat();
-        let mut reader = BitReader::new(concat_list.into());
-        let full_message_result = message_kinds.read(&mut reader);
-        if full_message_result.is_err() {
-            // TODO: bubble up error instead of panicking here
-            panic!("Cannot read fragmented message!");
-        }
-        let full_message = full_message_result.unwrap();
-        Some(full_message)
-    }
+	pub fn new(config: ReassemblyConfig) -> Self {
+		Self {
+			config,
+			map: HashMap::new(),
+			frag_rx_expired_count: 0,
+			frag_rx_refused_count: 0,
+		}
+	}
+
+	/// Returns the reassembled message once all of its fragments were received
+	pub(crate) fn receive(
+		&mut self,
+		message_kinds: &MessageKinds,
+		message: MessageContainer,
+	) -> NaiaResult<Option<MessageContainer>> {
+		debug_assert!(message.is_fragment());
+
+		let timeout = self.config.timeout;
+		let partial_count = self.map.len();
+		self.map.retain(|_, partial| partial.started.elapsed() < timeout);
+		let expired = (partial_count - self.map.len()) as u64;
+		self.frag_rx_expired_count = self.frag_rx_expired_count.wrapping_add(expired);
+		if expired > 0 {
+			return Err(NaiaError::ReassemblyFailed);
+		}
+
+		let name = message.name();
+		let Ok(fragment) = message.to_boxed_any().downcast::<FragmentedMessage>() else {
+			return Err(NaiaError::malformed::<FragmentedMessage>());
+		};
+		let fragment_id = fragment.id();
+		let fragment_index = fragment.index().as_usize();
+		let fragment_total = fragment.total().as_usize();
+		if fragment_index >= fragment_total {
+			return Err(NaiaError::malformed::<FragmentedMessage>());
+		}
+
+		// all but the last fragment are full, so refuse oversized messages up front
+		let max_bytes = message_kinds.max_message_bytes();
+		if max_bytes.is_some_and(|max| (fragment_total - 1) * FRAGMENTATION_LIMIT_BYTES >= max) {
+			return Err(NaiaError::MessageTooLarge(name));
+		}
+
+		if !self.map.contains_key(&fragment_id) && self.map.len() >= self.config.max_partial {
+			self.frag_rx_refused_count = self.frag_rx_refused_count.wrapping_add(1);
+			return Err(NaiaError::ReassemblyFailed);
+		}
+		let partial = self.map.entry(fragment_id).or_insert_with(|| PartialMessage {
+			total: fragment_total,
+			fragments: BTreeMap::new(),
+			started: Instant::now(),
+		});
+		if partial.total != fragment_total {
+			return Err(NaiaError::malformed::<FragmentedMessage>());
+		}
+		partial.fragments.insert(fragment_index, fragment.to_payload());
+		if partial.fragments.len() != partial.total {
+			return Ok(None);
+		}
+
+		// we have received all fragments! put it all together
+		let partial = self.map.remove(&fragment_id).unwrap();
+		let concat_list: Vec<u8> = partial.fragments.into_values().flatten().collect();
+		let bytes = concat_list.len();
+		let mut reader = BitReader::new(concat_list.into());
+		let full_message = match message_kinds.read(&mut reader) {
+			Ok(message) if !message.is_fragment() => message,
+			Ok(_) => return Err(NaiaError::malformed::<FragmentedMessage>()),
+			Err(err) => return Err(NaiaError::malformed_by::<FragmentedMessage>(err)),
+		};
+		if max_bytes.is_some_and(|max| bytes > max) {
+			return Err(NaiaError::MessageTooLarge(full_message.name()));
+		}
+		Ok(Some(full_message))
+	}
+
+	/// Write the partially assembled messages
+	pub(crate) fn export(&self, writer: &mut dyn BitWrite) {
+		for (fragment_id, partial) in &self.map {
+			true.ser(writer);
+			fragment_id.ser(writer);
+			(partial.total as u32).ser(writer);
+			for (index, bytes) in &partial.fragments {
+				true.ser(writer);
+				(*index as u32).ser(writer);
+				bytes.ser(writer);
+			}
+			false.ser(writer);
+		}
+		false.ser(writer);
+	}
+
+	/// Restore the state written by `export()`, restarting the reassembly timeouts
+	pub(crate) fn import(&mut self, reader: &mut BitReader) -> Result<(), SerdeErr> {
+		self.map.clear();
+		while bool::de(reader)? {
+			let fragment_id = FragmentId::de(reader)?;
+			let total = u32::de(reader)? as usize;
+			let mut fragments = BTreeMap::new();
+			while bool::de(reader)? {
+				let index = u32::de(reader)? as usize;
+				fragments.insert(index, Box::<[u8]>::de(reader)?);
+			}
+			self.map.insert(fragment_id, PartialMessage { total, fragments, started: Instant::now() });
+		}
+		Ok(())
+	}
+
+	// performance counters
+
+	pub fn frag_rx_expired_count(&self) -> u64 { self.frag_rx_expired_count }
+	pub fn frag_rx_refused_count(&self) -> u64 { self.frag_rx_refused_count }
 }
@@ -1,7 +1,7 @@
 use naia_serde::{BitReader, Serde, SerdeErr, UnsignedVariableInteger};
 
 use crate::{
-    messages::message_kinds::MessageKinds, types::MessageIndex,
+    error::NaiaResult, messages::message_kinds::MessageKinds, types::MessageIndex,
     MessageContainer,
 };
 
@@ -11,7 +11,7 @@ impl IndexedMessageReader {
     pub fn read_messages(
         message_kinds: &MessageKinds,
         reader: &mut BitReader,
-    ) -> Result<Vec<(MessageIndex, MessageContainer)>, SerdeErr> {
+    ) -> NaiaResult<Vec<(MessageIndex, MessageContainer)>> {
         let mut last_read_id: Option<MessageIndex> = None;
         let mut output = Vec::new();
 
@@ -42,12 +42,12 @@ impl IndexedMessageReader {
         message_kinds: &MessageKinds,
         reader: &mut BitReader,
         last_read_id: &Option<MessageIndex>,
-    ) -> Result<(MessageIndex, MessageContainer), SerdeErr> {
+    ) -> NaiaResult<(MessageIndex, MessageContainer)> {
         // read index
         let message_index = Self::read_message_index(reader, last_read_id)?;
 
         // read payload
-        let new_message = message_kinds.read(reader)?;
+        let new_message = message_kinds.read_remote(reader)?;
 
         Ok((message_index, new_message))
     }
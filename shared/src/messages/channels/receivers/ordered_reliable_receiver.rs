@@ -1,22 +1,27 @@
 use std::collections::VecDeque;
 
 use crate::{
-    messages::channels::receivers::reliable_message_receiver::{
-        ReceiverArranger, ReliableMessageReceiver,
+    messages::{
+        channels::receivers::{
+            fragment_receiver::ReassemblyConfig,
+            reliable_message_receiver::{ReceiverArranger, ReliableMessageReceiver},
+        },
+        message_kinds::MessageKinds,
     },
     types::MessageIndex,
     MessageContainer,
 };
+use naia_serde::{BitReader, BitWrite, Serde, SerdeErr};
 
 // OrderedReliableReceiver
 pub type OrderedReliableReceiver = ReliableMessageReceiver<OrderedArranger>;
 
 impl OrderedReliableReceiver {
-    pub fn new() -> Self {
+    pub fn new(reassembly: ReassemblyConfig) -> Self {
         Self::with_arranger(OrderedArranger {
             oldest_received_message_index: MessageIndex::ZERO,
             buffer: VecDeque::new(),
-        })
+        }, reassembly)
     }
 }
 
@@ -76,4 +81,33 @@ impl ReceiverArranger for OrderedArranger {
             self.oldest_received_message_index.incr();
         }
     }
+
+	fn export(&self, message_kinds: &MessageKinds, writer: &mut dyn BitWrite) {
+		self.oldest_received_message_index.ser(writer);
+		for (message_index, message) in &self.buffer {
+			true.ser(writer);
+			message_index.ser(writer);
+			message.is_some().ser(writer);
+			if let Some(message) = message {
+				message.write(message_kinds, writer);
+			}
+		}
+		false.ser(writer);
+	}
+
+	fn import(
+		&mut self, message_kinds: &MessageKinds, reader: &mut BitReader,
+	) -> Result<(), SerdeErr> {
+		self.oldest_received_message_index = MessageIndex::de(reader)?;
+		self.buffer.clear();
+		while bool::de(reader)? {
+			let message_index = MessageIndex::de(reader)?;
+			let message = match bool::de(reader)? {
+				true => Some(message_kinds.read(reader)?),
+				false => None,
+			};
+			self.buffer.push_back((message_index, message));
+		}
+		Ok(())
+	}
 }
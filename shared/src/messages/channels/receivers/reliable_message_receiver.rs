@@ -1,8 +1,9 @@
 use crate::{
+    error::NaiaResult,
     messages::{
         channels::receivers::{
             channel_receiver::ChannelReceiver,
-            fragment_receiver::FragmentReceiver,
+            fragment_receiver::{FragmentReceiver, ReassemblyConfig},
             indexed_message_reader::IndexedMessageReader,
             reliable_receiver::ReliableReceiver,
         },
@@ -11,16 +12,30 @@ use crate::{
     types::MessageIndex,
     MessageContainer,
 };
-use naia_serde::{BitReader, SerdeErr};
+use naia_serde::{BitReader, BitWrite, Serde, SerdeErr};
 
 // Receiver Arranger Trait
 pub trait ReceiverArranger: Send + Sync {
+	/// Whether fragments are reassembled before arranging, rather than arranged one by
+	/// one. Arrangers which drop messages must see whole messages, or they would drop
+	/// fragments which arrived out of order.
+	const REASSEMBLE_FIRST: bool = false;
+
     fn process(
         &mut self,
         incoming_messages: &mut Vec<(MessageIndex, MessageContainer)>,
         message_index: MessageIndex,
         message: MessageContainer,
     );
+
+	/// Write the messages held back for arrangement, and any state needed to continue
+	/// arranging in another process
+	fn export(&self, message_kinds: &MessageKinds, writer: &mut dyn BitWrite);
+
+	/// Restore the state written by `export()`
+	fn import(
+		&mut self, message_kinds: &MessageKinds, reader: &mut BitReader,
+	) -> Result<(), SerdeErr>;
 }
 
 // Reliable Receiver
@@ -35,12 +50,12 @@ pub struct ReliableMessageReceiver<A: ReceiverArranger> {
 }
 
 impl<A: ReceiverArranger> ReliableMessageReceiver<A> {
-    pub fn with_arranger(arranger: A) -> Self {
+    pub fn with_arranger(arranger: A, reassembly: ReassemblyConfig) -> Self {
         Self {
             reliable_receiver: ReliableReceiver::new(),
             incoming_messages: Vec::new(),
             arranger,
-            fragment_receiver: FragmentReceiver::new(),
+            fragment_receiver: FragmentReceiver::new(reassembly),
             current_index: MessageIndex::ZERO,
             msg_rx_count: 0,
 			msg_rx_drop_count: 0,
@@ -52,23 +67,36 @@ impl<A: ReceiverArranger> ReliableMessageReceiver<A> {
 		message_index: MessageIndex,
         message_kinds: &MessageKinds,
         message: MessageContainer,
-    ) {
-        let Some(full_message) = ({
-			// Message framgmentation is not currently supported!
-			assert!(!message.is_fragment());
-            if message.is_fragment() {
-                self.fragment_receiver.receive(message_kinds, message)
-            } else {
-                Some(message)
-            }
-        }) else {
-            return;
-        };
-
+    ) -> NaiaResult {
         self.current_index.incr();
 
+		if A::REASSEMBLE_FIRST {
+			// any fragment's index orders the message among the others
+			let message = match message.is_fragment() {
+				true => match self.fragment_receiver.receive(message_kinds, message)? {
+					Some(full_message) => full_message,
+					None => return Ok(()),
+				},
+				false => message,
+			};
+			self.arranger.process(&mut self.incoming_messages, message_index, message);
+			return Ok(());
+		}
+
+        let arranged_from = self.incoming_messages.len();
         self.arranger
-            .process(&mut self.incoming_messages, message_index, full_message);
+            .process(&mut self.incoming_messages, message_index, message);
+
+		// reassemble fragments once arranged, so each fragment keeps its own index
+		let arranged = self.incoming_messages.split_off(arranged_from);
+		for (message_index, message) in arranged {
+			if !message.is_fragment() {
+				self.incoming_messages.push((message_index, message));
+			} else if let Some(full_message) = self.fragment_receiver.receive(message_kinds, message)? {
+				self.incoming_messages.push((message_index, full_message));
+			}
+		}
+		Ok(())
     }
 
     pub fn buffer_message(
@@ -76,15 +104,16 @@ impl<A: ReceiverArranger> ReliableMessageReceiver<A> {
         message_kinds: &MessageKinds,
         message_index: MessageIndex,
         message: MessageContainer,
-    ) {
+    ) -> NaiaResult {
 		self.msg_rx_count = self.msg_rx_count.wrapping_add(1);
         if !self.reliable_receiver.buffer_message(message_index, message) {
 			self.msg_rx_drop_count = self.msg_rx_drop_count.wrapping_add(1);
 		}
         let received_messages = self.reliable_receiver.receive_messages();
         for (msg_idx, received_message) in received_messages {
-            self.push_message(msg_idx, message_kinds, received_message)
+            self.push_message(msg_idx, message_kinds, received_message)?;
         }
+        Ok(())
     }
 
     pub fn receive_messages(&mut self) -> Vec<(MessageIndex, MessageContainer)> {
@@ -104,15 +133,35 @@ impl<A: ReceiverArranger> ChannelReceiver for ReliableMessageReceiver<A> {
         &mut self,
         message_kinds: &MessageKinds,
         reader: &mut BitReader,
-    ) -> Result<(), SerdeErr> {
+    ) -> NaiaResult {
         let id_w_msgs = IndexedMessageReader::read_messages(message_kinds, reader)?;
         for (id, message) in id_w_msgs {
-            self.buffer_message(message_kinds, id, message);
+            self.buffer_message(message_kinds, id, message)?;
         }
         Ok(())
     }
 
+	fn export(&self, message_kinds: &MessageKinds, writer: &mut dyn BitWrite) {
+		self.reliable_receiver.export(writer);
+		self.current_index.ser(writer);
+		self.arranger.export(message_kinds, writer);
+		self.fragment_receiver.export(writer);
+	}
+
+	fn import(
+		&mut self, message_kinds: &MessageKinds, reader: &mut BitReader,
+	) -> Result<(), SerdeErr> {
+		self.reliable_receiver.import(reader)?;
+		self.current_index = MessageIndex::de(reader)?;
+		self.arranger.import(message_kinds, reader)?;
+		self.fragment_receiver.import(reader)?;
+		self.incoming_messages.clear();
+		Ok(())
+	}
+
 	fn msg_rx_count(&self) -> u64 { self.msg_rx_count }
 	fn msg_rx_drop_count(&self) -> u64 { self.msg_rx_drop_count }
 	fn msg_rx_miss_count(&self) -> u64 { 0 }
+	fn frag_rx_expired_count(&self) -> u64 { self.fragment_receiver.frag_rx_expired_count() }
+	fn frag_rx_refused_count(&self) -> u64 { self.fragment_receiver.frag_rx_refused_count() }
 }
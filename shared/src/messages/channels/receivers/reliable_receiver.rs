@@ -1,4 +1,5 @@
 use crate::MessageIndex;
+use naia_serde::{BitReader, BitWrite, Serde, SerdeErr};
 use std::collections::VecDeque;
 
 pub struct ReliableReceiver<M> {
@@ -70,17 +71,27 @@ impl<M> ReliableReceiver<M> {
 
     fn clear_old_messages(&mut self) {
         // clear all received messages from record
-        loop {
-            if let Some((_, true)) = self.record.front() {
-                self.record.pop_front();
-                self.oldest_received_message_index.incr();
-            } else {
-                break;
-            }
+        while let Some((_, true)) = self.record.front() {
+            self.record.pop_front();
+            self.oldest_received_message_index.incr();
         }
     }
 
     pub(crate) fn receive_messages(&mut self) -> Vec<(MessageIndex, M)> {
         std::mem::take(&mut self.incoming_messages)
     }
+
+	/// Write the sliding window of received message indices
+	pub(crate) fn export(&self, writer: &mut dyn BitWrite) {
+		self.oldest_received_message_index.ser(writer);
+		self.record.ser(writer);
+	}
+
+	/// Restore the state written by `export()`
+	pub(crate) fn import(&mut self, reader: &mut BitReader) -> Result<(), SerdeErr> {
+		self.oldest_received_message_index = MessageIndex::de(reader)?;
+		self.record = VecDeque::de(reader)?;
+		self.incoming_messages.clear();
+		Ok(())
+	}
 }
@@ -1,18 +1,23 @@
 use crate::{
-    messages::channels::receivers::reliable_message_receiver::{
-        ReceiverArranger, ReliableMessageReceiver,
+    messages::{
+        channels::receivers::{
+            fragment_receiver::ReassemblyConfig,
+            reliable_message_receiver::{ReceiverArranger, ReliableMessageReceiver},
+        },
+        message_kinds::MessageKinds,
     },
     types::MessageIndex,
     MessageContainer,
 };
+use naia_serde::{BitReader, BitWrite, Serde, SerdeErr};
 
 pub type SequencedReliableReceiver = ReliableMessageReceiver<SequencedArranger>;
 
 impl SequencedReliableReceiver {
-    pub fn new() -> Self {
+    pub fn new(reassembly: ReassemblyConfig) -> Self {
         Self::with_arranger(SequencedArranger {
             newest_received_message_index: MessageIndex::ZERO,
-        })
+        }, reassembly)
     }
 }
 
@@ -22,6 +27,8 @@ pub struct SequencedArranger {
 }
 
 impl ReceiverArranger for SequencedArranger {
+	const REASSEMBLE_FIRST: bool = true;
+
     fn process(
         &mut self,
         incoming_messages: &mut Vec<(MessageIndex, MessageContainer)>,
@@ -33,4 +40,13 @@ impl ReceiverArranger for SequencedArranger {
             incoming_messages.push((message_index, message));
         }
     }
+
+	fn export(&self, _: &MessageKinds, writer: &mut dyn BitWrite) {
+		self.newest_received_message_index.ser(writer);
+	}
+
+	fn import(&mut self, _: &MessageKinds, reader: &mut BitReader) -> Result<(), SerdeErr> {
+		self.newest_received_message_index = MessageIndex::de(reader)?;
+		Ok(())
+	}
 }
@@ -1,4 +1,5 @@
 use crate::{
+    error::{NaiaError, NaiaResult},
     messages::{
         channels::receivers::{
             channel_receiver::ChannelReceiver,
@@ -9,7 +10,7 @@ use crate::{
     types::MessageIndex,
     MessageContainer,
 };
-use naia_serde::{BitReader, SerdeErr};
+use naia_serde::{BitReader, BitWrite, Serde, SerdeErr};
 use std::mem;
 
 pub struct SequencedUnreliableReceiver {
@@ -59,7 +60,7 @@ impl SequencedUnreliableReceiver {
 
 impl ChannelReceiver for SequencedUnreliableReceiver {
     fn receive_messages(&mut self) -> Vec<MessageContainer> {
-        Vec::from(mem::take(&mut self.incoming_messages))
+        mem::take(&mut self.incoming_messages)
     }
 
     /// Read messages and add them to the buffer, discard messages that are older
@@ -68,15 +69,30 @@ impl ChannelReceiver for SequencedUnreliableReceiver {
         &mut self,
         message_kinds: &MessageKinds,
         reader: &mut BitReader,
-    ) -> Result<(), SerdeErr> {
+    ) -> NaiaResult {
         let id_w_msgs = IndexedMessageReader::read_messages(message_kinds, reader)?;
         for (id, message) in id_w_msgs {
+            if message.is_fragment() {
+                // only reliable channels carry messages too large for a single packet
+                return Err(NaiaError::MessageTooLarge(message.name()));
+            }
             self.buffer_message(id, message);
         }
         Ok(())
     }
 
+	fn export(&self, _: &MessageKinds, writer: &mut dyn BitWrite) {
+		self.newest_received_message_index.ser(writer);
+	}
+
+	fn import(&mut self, _: &MessageKinds, reader: &mut BitReader) -> Result<(), SerdeErr> {
+		self.newest_received_message_index = Option::<MessageIndex>::de(reader)?;
+		Ok(())
+	}
+
 	fn msg_rx_count(&self) -> u64 { self.msg_rx_count }
 	fn msg_rx_drop_count(&self) -> u64 { self.msg_rx_drop_count }
 	fn msg_rx_miss_count(&self) -> u64 { self.msg_rx_miss_count }
+	fn frag_rx_expired_count(&self) -> u64 { 0 }
+	fn frag_rx_refused_count(&self) -> u64 { 0 }
 }
@@ -1,16 +1,21 @@
 use crate::{
-    messages::channels::receivers::reliable_message_receiver::{
-        ReceiverArranger, ReliableMessageReceiver,
+    messages::{
+        channels::receivers::{
+            fragment_receiver::ReassemblyConfig,
+            reliable_message_receiver::{ReceiverArranger, ReliableMessageReceiver},
+        },
+        message_kinds::MessageKinds,
     },
     types::MessageIndex,
     MessageContainer,
 };
+use naia_serde::{BitReader, BitWrite, SerdeErr};
 
 pub type UnorderedReliableReceiver = ReliableMessageReceiver<UnorderedArranger>;
 
 impl UnorderedReliableReceiver {
-    pub fn new() -> Self {
-        Self::with_arranger(UnorderedArranger)
+    pub fn new(reassembly: ReassemblyConfig) -> Self {
+        Self::with_arranger(UnorderedArranger, reassembly)
     }
 }
 
@@ -26,4 +31,10 @@ impl ReceiverArranger for UnorderedArranger {
     ) {
         incoming_messages.push((message_index, message));
     }
+
+	fn export(&self, _: &MessageKinds, _: &mut dyn BitWrite) {
+		// stateless
+	}
+
+	fn import(&mut self, _: &MessageKinds, _: &mut BitReader) -> Result<(), SerdeErr> { Ok(()) }
 }
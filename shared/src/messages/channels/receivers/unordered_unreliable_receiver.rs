@@ -1,11 +1,12 @@
 use crate::{
+    error::{NaiaError, NaiaResult},
     messages::{
         channels::receivers::channel_receiver::ChannelReceiver,
         message_kinds::MessageKinds,
     },
     MessageContainer,
 };
-use naia_serde::{BitReader, Serde, SerdeErr};
+use naia_serde::{BitReader, BitWrite, Serde, SerdeErr};
 use std::{collections::VecDeque, mem};
 
 pub struct UnorderedUnreliableReceiver {
@@ -25,9 +26,14 @@ impl UnorderedUnreliableReceiver {
         &mut self,
         message_kinds: &MessageKinds,
         reader: &mut BitReader,
-    ) -> Result<MessageContainer, SerdeErr> {
+    ) -> NaiaResult<MessageContainer> {
         // read payload
-        message_kinds.read(reader)
+        let message = message_kinds.read_remote(reader)?;
+        if message.is_fragment() {
+            // only reliable channels carry messages too large for a single packet
+            return Err(NaiaError::MessageTooLarge(message.name()));
+        }
+        Ok(message)
     }
 
     fn recv_message(&mut self, message: MessageContainer) {
@@ -45,7 +51,7 @@ impl ChannelReceiver for UnorderedUnreliableReceiver {
         &mut self,
         message_kinds: &MessageKinds,
         reader: &mut BitReader,
-    ) -> Result<(), SerdeErr> {
+    ) -> NaiaResult {
 		// while read continuation bit
 		while bool::de(reader)? {
             let message = self.read_message(message_kinds, reader)?;
@@ -55,7 +61,15 @@ impl ChannelReceiver for UnorderedUnreliableReceiver {
         Ok(())
     }
 
+	fn export(&self, _: &MessageKinds, _: &mut dyn BitWrite) {
+		// stateless
+	}
+
+	fn import(&mut self, _: &MessageKinds, _: &mut BitReader) -> Result<(), SerdeErr> { Ok(()) }
+
 	fn msg_rx_count(&self) -> u64 { self.msg_rx_count }
 	fn msg_rx_drop_count(&self) -> u64 { 0 }
 	fn msg_rx_miss_count(&self) -> u64 { 0 }
+	fn frag_rx_expired_count(&self) -> u64 { 0 }
+	fn frag_rx_refused_count(&self) -> u64 { 0 }
 }
@@ -1,13 +1,28 @@
-use crate::{MessageContainer, messages::message_kinds::MessageKinds, types::MessageIndex};
-use naia_serde::BitWriter;
-use std::time::Instant;
+use crate::{Instant, MessageContainer, messages::message_kinds::MessageKinds, types::MessageIndex};
+use naia_serde::{BitReader, BitWrite, BitWriter, SerdeErr};
+
+/// The backlog of messages queued for transmission to a remote host, or awaiting its
+/// acknowledgement
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct QueueDepth {
+	pub messages: usize,
+	/// The encoded size of the messages
+	pub bytes: usize,
+	/// The estimated number of packets needed to send the messages, ignoring
+	/// congestion control and headers
+	pub est_packets: usize,
+}
 
 pub trait ChannelSender: Send + Sync {
     /// Queues a Message to be transmitted to the remote host into an internal buffer
     fn send(&mut self, message: MessageContainer);
 
     /// For reliable channels, will collect any Messages that need to be resent
-    fn collect_messages(&mut self, now: &Instant, resend_ms: &f32);
+    fn collect_messages(&mut self, now: &Instant, rtt_ms: f32, jitter_ms: f32);
+
+    /// For reliable channels, returns whether a Message exceeded the maximum resend
+    /// attempts
+    fn resends_exhausted(&self) -> bool;
 
     /// Returns true if there are queued Messages ready to be written
     fn has_messages(&self) -> bool;
@@ -15,17 +30,33 @@ pub trait ChannelSender: Send + Sync {
     /// Called when it receives acknowledgement that a Message has been received
     fn ack(&mut self, index: &MessageIndex);
 
-    /// Gets Messages from the internal buffer and writes it to the BitWriter
+    /// Gets Messages from the internal buffer and writes it to the BitWriter, calling
+    /// `on_write` for each written Message
     fn write_messages(
         &mut self,
         kinds: &MessageKinds,
         writer: &mut BitWriter,
         has_written: &mut bool,
+        on_write: &mut dyn FnMut(&MessageContainer),
     ) -> Option<Vec<MessageIndex>>;
 
+	/// Write the state needed to continue sending on this channel from another
+	/// process; see `BaseConnection::export()`
+	fn export(&self, kinds: &MessageKinds, writer: &mut dyn BitWrite);
+
+	/// Restore the state written by `export()`
+	fn import(&mut self, kinds: &MessageKinds, reader: &mut BitReader) -> Result<(), SerdeErr>;
+
+	/// Returns the number of messages awaiting transmission (or acknowledgement, for
+	/// reliable channels), and their total size in bits
+	fn queued_bits(&self) -> (usize, u64);
+
+	/// Returns the total size in bits of the messages ready to be written
+	fn outgoing_bits(&self) -> u64;
+
 	/// Performance counter for the number of messages transmitted
 	fn msg_tx_count(&self) -> u64;
 
-	/// Performance counter for the	number of messages queued for transmission
+	/// Performance counter for the number of messages queued for transmission
 	fn msg_tx_queue_count(&self) -> u64;
 }
@@ -16,6 +16,7 @@ impl IndexedMessageWriter {
         outgoing_messages: &mut VecDeque<(MessageIndex, MessageContainer)>,
         writer: &mut BitWriter,
         has_written: &mut bool,
+        on_write: &mut dyn FnMut(&MessageContainer),
     ) -> Option<Vec<MessageIndex>> {
         let mut last_written_id: Option<MessageIndex> = None;
         let mut message_indices = Vec::new();
@@ -56,6 +57,7 @@ impl IndexedMessageWriter {
                 message,
             );
 
+            on_write(message);
             message_indices.push(*message_index);
             last_written_id = Some(*message_index);
 
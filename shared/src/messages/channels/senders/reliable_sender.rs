@@ -1,31 +1,38 @@
 use crate::{
     messages::{
-        channels::senders::{
+        channels::{channel::ResendConfig, senders::{
             channel_sender::ChannelSender,
             indexed_message_writer::IndexedMessageWriter,
-        },
+        }},
         message_container::MessageContainer,
         message_kinds::MessageKinds,
     },
     types::MessageIndex,
 };
-use naia_serde::BitWriter;
-use std::{collections::VecDeque, time::{Duration, Instant}};
+use naia_serde::{BitReader, BitWrite, BitWriter, Serde, SerdeErr};
+use crate::Instant;
+use std::collections::VecDeque;
 
 pub struct ReliableSender {
-    sending_messages: VecDeque<Option<(MessageIndex, Option<Instant>, MessageContainer)>>,
+    resend: ResendConfig,
+    /// Unacknowledged messages, with the time they were last sent and their number of
+    /// sends
+    sending_messages: VecDeque<Option<(MessageIndex, Option<(Instant, u32)>, MessageContainer)>>,
     next_send_message_index: MessageIndex,
     outgoing_messages: VecDeque<(MessageIndex, MessageContainer)>,
+	resends_exhausted: bool,
 	msg_tx_count: u64,
 	msg_tx_queue_count: u64,
 }
 
 impl ReliableSender {
-    pub fn new() -> Self {
+    pub fn new(resend: ResendConfig) -> Self {
         Self {
+            resend,
             next_send_message_index: MessageIndex::ZERO,
             sending_messages: VecDeque::new(),
             outgoing_messages: VecDeque::new(),
+			resends_exhausted: false,
 			msg_tx_count: 0,
 			msg_tx_queue_count: 0,
         }
@@ -46,23 +53,29 @@ impl ChannelSender for ReliableSender {
         self.next_send_message_index.incr();
     }
 
-    fn collect_messages(&mut self, now: &Instant, resend_ms: &f32) {
-        let resend_duration = Duration::from_secs_f32(resend_ms / 1000.0);
-
+    fn collect_messages(&mut self, now: &Instant, rtt_ms: f32, jitter_ms: f32) {
         for (message_index, last_sent_opt, message) in self.sending_messages.iter_mut().flatten() {
-			if let Some(last_sent) = last_sent_opt {
-				if last_sent.elapsed() < resend_duration {
+			let mut sends = 0;
+			if let Some((last_sent, count)) = last_sent_opt {
+				if last_sent.elapsed() < self.resend.delay(rtt_ms, jitter_ms, *count) {
+					continue;
+				}
+				if self.resend.max_attempts.is_some_and(|max| *count > max) {
+					self.resends_exhausted = true;
 					continue;
 				}
+				sends = *count;
 			}
 
 			self.msg_tx_count = self.msg_tx_count.wrapping_add(1);
 			self.outgoing_messages
 				.push_back((*message_index, message.clone()));
-			*last_sent_opt = Some(now.clone());
+			*last_sent_opt = Some((*now, sends + 1));
         }
     }
 
+    fn resends_exhausted(&self) -> bool { self.resends_exhausted }
+
     fn has_messages(&self) -> bool {
         !self.outgoing_messages.is_empty()
     }
@@ -89,15 +102,105 @@ impl ChannelSender for ReliableSender {
         kinds: &MessageKinds,
         writer: &mut BitWriter,
         has_written: &mut bool,
+        on_write: &mut dyn FnMut(&MessageContainer),
     ) -> Option<Vec<MessageIndex>> {
         IndexedMessageWriter::write_messages(
             kinds,
             &mut self.outgoing_messages,
             writer,
             has_written,
+            on_write,
         )
     }
 
+	fn export(&self, kinds: &MessageKinds, writer: &mut dyn BitWrite) {
+		self.next_send_message_index.ser(writer);
+		for (message_index, _, message) in self.sending_messages.iter().flatten() {
+			true.ser(writer);
+			message_index.ser(writer);
+			message.write(kinds, writer);
+		}
+		false.ser(writer);
+	}
+
+	fn import(&mut self, kinds: &MessageKinds, reader: &mut BitReader) -> Result<(), SerdeErr> {
+		self.next_send_message_index = MessageIndex::de(reader)?;
+
+		// unacked messages are resent immediately
+		self.sending_messages.clear();
+		self.outgoing_messages.clear();
+		while bool::de(reader)? {
+			let message_index = MessageIndex::de(reader)?;
+			let message = kinds.read(reader)?.into_write();
+			self.sending_messages.push_back(Some((message_index, None, message)));
+		}
+		Ok(())
+	}
+
+	fn queued_bits(&self) -> (usize, u64) {
+		self.sending_messages.iter().flatten()
+			.fold((0, 0), |(count, bits), (_, _, message)| (count + 1, bits + message.bit_length() as u64))
+	}
+
+	fn outgoing_bits(&self) -> u64 {
+		self.outgoing_messages.iter().map(|(_, message)| message.bit_length() as u64).sum()
+	}
+
 	fn msg_tx_count(&self) -> u64 { self.msg_tx_count }
 	fn msg_tx_queue_count(&self) -> u64 { self.msg_tx_queue_count }
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Blob;
+	use std::time::Duration;
+
+	#[test]
+	fn queued_bits() {
+		let blob = || MessageContainer::from_write(Box::new(Blob::new(&[0; 16])));
+		let bits = blob().bit_length() as u64;
+
+		let mut sender = ReliableSender::new(ResendConfig::default());
+		sender.send(blob());
+		sender.send(blob());
+		assert_eq!(sender.queued_bits(), (2, 2 * bits));
+
+		// sent messages count until they are acknowledged
+		sender.collect_messages(&Instant::now(), 0.0, 0.0);
+		assert_eq!(sender.queued_bits(), (2, 2 * bits));
+		sender.ack(&MessageIndex::ZERO);
+		assert_eq!(sender.queued_bits(), (1, bits));
+	}
+
+	#[test]
+	fn resend_attempts() {
+		let resend = ResendConfig { max_attempts: Some(1), ..ResendConfig::default() };
+		let mut sender = ReliableSender::new(resend);
+		sender.send(MessageContainer::from_write(Box::new(Blob::new(&[0; 16]))));
+
+		// the first send, and one resend
+		for _ in 0..2 {
+			sender.collect_messages(&Instant::now(), 0.0, 0.0);
+			assert!(!sender.resends_exhausted());
+		}
+		assert_eq!(sender.outgoing_messages.len(), 2);
+
+		sender.collect_messages(&Instant::now(), 0.0, 0.0);
+		assert!(sender.resends_exhausted());
+		assert_eq!(sender.outgoing_messages.len(), 2);
+	}
+
+	#[test]
+	fn resend_backoff() {
+		let resend = ResendConfig {
+			initial_delay: Duration::from_millis(10),
+			backoff: 2.0,
+			..ResendConfig::default()
+		};
+		let ms = |delay: Duration| (delay.as_secs_f32() * 1000.0).round();
+		assert_eq!(ms(resend.delay(100.0, 0.0, 1)), 110.0);
+		assert_eq!(ms(resend.delay(100.0, 0.0, 3)), 440.0);
+		assert_eq!(ms(ResendConfig::default().delay(100.0, 10.0, 5)), 115.0);
+	}
+}
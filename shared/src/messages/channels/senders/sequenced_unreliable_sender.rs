@@ -9,9 +9,9 @@ use crate::{
     },
     types::MessageIndex,
 };
-use naia_serde::BitWriter;
+use naia_serde::{BitReader, BitWrite, BitWriter, Serde, SerdeErr};
 use std::collections::VecDeque;
-use std::time::Instant;
+use crate::Instant;
 
 pub struct SequencedUnreliableSender {
     /// Buffer of the next messages to send along with their MessageKind
@@ -39,10 +39,12 @@ impl ChannelSender for SequencedUnreliableSender {
         self.next_send_message_index.incr();
     }
 
-    fn collect_messages(&mut self, _: &Instant, _: &f32) {
+    fn collect_messages(&mut self, _: &Instant, _: f32, _: f32) {
         // not necessary for an unreliable channel
     }
 
+    fn resends_exhausted(&self) -> bool { false }
+
     fn has_messages(&self) -> bool {
         !self.outgoing_messages.is_empty()
     }
@@ -58,15 +60,34 @@ impl ChannelSender for SequencedUnreliableSender {
         kinds: &MessageKinds,
         writer: &mut BitWriter,
         has_written: &mut bool,
+        on_write: &mut dyn FnMut(&MessageContainer),
     ) -> Option<Vec<MessageIndex>> {
         IndexedMessageWriter::write_messages(
             kinds,
             &mut self.outgoing_messages,
             writer,
             has_written,
+            on_write,
         )
     }
 
+	fn export(&self, _: &MessageKinds, writer: &mut dyn BitWrite) {
+		// unsent unreliable messages are dropped
+		self.next_send_message_index.ser(writer);
+	}
+
+	fn import(&mut self, _: &MessageKinds, reader: &mut BitReader) -> Result<(), SerdeErr> {
+		self.next_send_message_index = MessageIndex::de(reader)?;
+		Ok(())
+	}
+
+	fn queued_bits(&self) -> (usize, u64) {
+		let bits = self.outgoing_messages.iter().map(|(_, message)| message.bit_length() as u64).sum();
+		(self.outgoing_messages.len(), bits)
+	}
+
+	fn outgoing_bits(&self) -> u64 { self.queued_bits().1 }
+
 	fn msg_tx_count(&self) -> u64 { self.msg_tx_count }
 	fn msg_tx_queue_count(&self) -> u64 { self.msg_tx_count }
 }
@@ -6,9 +6,9 @@ use crate::{
     },
     types::MessageIndex,
 };
-use naia_serde::{BitWrite, BitWriter, Serde};
+use naia_serde::{BitReader, BitWrite, BitWriter, Serde, SerdeErr};
 use std::collections::VecDeque;
-use std::time::Instant;
+use crate::Instant;
 
 pub struct UnorderedUnreliableSender {
     outgoing_messages: VecDeque<MessageContainer>,
@@ -46,10 +46,12 @@ impl ChannelSender for UnorderedUnreliableSender {
         self.outgoing_messages.push_back(message);
     }
 
-    fn collect_messages(&mut self, _: &Instant, _: &f32) {
+    fn collect_messages(&mut self, _: &Instant, _: f32, _: f32) {
         // not necessary for an unreliable channel
     }
 
+    fn resends_exhausted(&self) -> bool { false }
+
     fn has_messages(&self) -> bool {
         !self.outgoing_messages.is_empty()
     }
@@ -63,6 +65,7 @@ impl ChannelSender for UnorderedUnreliableSender {
         kinds: &MessageKinds,
         writer: &mut BitWriter,
         has_written: &mut bool,
+        on_write: &mut dyn FnMut(&MessageContainer),
     ) -> Option<Vec<MessageIndex>> {
         while let Some(message) = self.outgoing_messages.front() {
             // Check that we can write the next message
@@ -86,7 +89,8 @@ impl ChannelSender for UnorderedUnreliableSender {
             // write MessageContinue bit
             true.ser(writer);
             // write data
-            self.write_message(kinds, writer, &message);
+            self.write_message(kinds, writer, message);
+            on_write(message);
 
             // pop message we've written
             self.outgoing_messages.pop_front();
@@ -94,6 +98,19 @@ impl ChannelSender for UnorderedUnreliableSender {
         None
     }
 
+	fn export(&self, _: &MessageKinds, _: &mut dyn BitWrite) {
+		// unsent unreliable messages are dropped
+	}
+
+	fn import(&mut self, _: &MessageKinds, _: &mut BitReader) -> Result<(), SerdeErr> { Ok(()) }
+
+	fn queued_bits(&self) -> (usize, u64) {
+		let bits = self.outgoing_messages.iter().map(|message| message.bit_length() as u64).sum();
+		(self.outgoing_messages.len(), bits)
+	}
+
+	fn outgoing_bits(&self) -> u64 { self.queued_bits().1 }
+
 	fn msg_tx_count(&self) -> u64 { self.msg_tx_count }
 	fn msg_tx_queue_count(&self) -> u64 { self.msg_tx_count }
 }
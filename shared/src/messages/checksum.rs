@@ -0,0 +1,15 @@
+use naia_derive::MessageInternal;
+use crate::Channel;
+
+/// Carries state checksums from Clients to the Server; see
+/// `SchemaBuilder::enable_checksums()`
+pub struct ChecksumChannel;
+
+impl Channel for ChecksumChannel {}
+
+/// A hash of a Client's simulation state after `tick`
+#[derive(MessageInternal)]
+pub struct StateChecksum {
+	pub tick: u32,
+	pub hash: u64,
+}
@@ -3,10 +3,12 @@ use std::any::Any;
 use naia_serde::{BitReader, BitWrite, SerdeErr};
 
 use crate::{
+    compression::EncodedFields,
     messages::{
         message_kinds::{MessageKind, MessageKinds},
         named::Named,
     },
+    protocol_spec::FieldSpec,
     MessageContainer,
 };
 
@@ -15,6 +17,7 @@ pub trait MessageBuilder: Send + Sync {
     /// Create new Message from incoming bit stream
     fn read(
         &self,
+        message_kinds: &MessageKinds,
         reader: &mut BitReader,
     ) -> Result<MessageContainer, SerdeErr>;
 }
@@ -29,6 +32,26 @@ pub trait Message: Send + Sync + Named + MessageClone + Any {
         Self: Sized;
     fn bit_length(&self) -> u32;
     fn is_fragment(&self) -> bool;
+    /// Whether the fields are compressed on the wire, see `#[message(compress)]`
+    fn is_compressed() -> bool
+    where
+        Self: Sized;
+    /// The wire encoding of the fields of compressed Messages, which
+    /// `MessageContainer` keeps to avoid compressing them on every write
+    fn encode_fields(&self) -> Option<EncodedFields> {
+        None
+    }
+    /// The id given with `#[message(id = N)]`, which this type is always sent with
+    fn stable_id() -> Option<u16>
+    where
+        Self: Sized,
+    {
+        None
+    }
+    /// Describes the wire layout of this type's fields
+    fn fields() -> Vec<FieldSpec>
+    where
+        Self: Sized;
     /// Writes data into an outgoing byte stream
     fn write(&self, message_kinds: &MessageKinds, writer: &mut dyn BitWrite);
 }
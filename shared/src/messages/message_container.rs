@@ -1,21 +1,34 @@
-use crate::{Message, MessageKind, MessageKinds};
-use naia_serde::BitWrite;
-use std::any::Any;
+use crate::{compression::EncodedFields, Message, MessageKind, MessageKinds};
+use naia_serde::{BitReader, BitWrite, BitWriter, ConstBitLength, SerdeErr};
+use std::{any::Any, fmt};
 
 #[derive(Clone)]
 pub struct MessageContainer {
     inner: Box<dyn Message>,
     bit_length: Option<u32>,
+    /// The fields of compressed messages, compressed once when the message is written
+    encoded_fields: Option<EncodedFields>,
+}
+
+impl fmt::Debug for MessageContainer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("MessageContainer").field(&self.name()).finish()
+	}
 }
 
 impl MessageContainer {
     pub fn from_write(
         message: Box<dyn Message>,
     ) -> Self {
-        let bit_length = message.bit_length();
+        let encoded_fields = message.encode_fields();
+        let bit_length = match &encoded_fields {
+            Some(fields) => <MessageKind as ConstBitLength>::const_bit_length() + fields.bit_length(),
+            None => message.bit_length(),
+        };
         Self {
             inner: message,
             bit_length: Some(bit_length),
+            encoded_fields,
         }
     }
 
@@ -23,6 +36,7 @@ impl MessageContainer {
         Self {
             inner: message,
             bit_length: None,
+            encoded_fields: None,
         }
     }
 
@@ -30,14 +44,38 @@ impl MessageContainer {
         self.inner.name()
     }
 
+	/// Prepare a received message to be sent, e.g. after `ChannelSender::import()`
+	pub(crate) fn into_write(self) -> Self { Self::from_write(self.inner) }
+
     pub fn bit_length(&self) -> u32 {
         self.bit_length.expect("bit_length should never be called on a MessageContainer that was created from a read operation")
     }
 
     pub fn write(&self, message_kinds: &MessageKinds, writer: &mut dyn BitWrite) {
-        self.inner.write(message_kinds, writer);
+        match &self.encoded_fields {
+            Some(fields) => {
+                self.inner.kind().ser(message_kinds, writer);
+                fields.write(writer);
+            }
+            None => self.inner.write(message_kinds, writer),
+        }
     }
 
+	/// Encode the message, including its kind, exactly as it appears on the wire
+	/// (zero-padded to a byte boundary). The layout is described by
+	/// `Schema::describe()`. Messages must fit within `MTU_SIZE_BYTES`.
+	pub fn to_bytes(&self, message_kinds: &MessageKinds) -> Box<[u8]> {
+		let mut writer = BitWriter::new();
+		self.write(message_kinds, &mut writer);
+		writer.slice().into()
+	}
+
+	/// Decode a message previously encoded with `to_bytes()`
+	pub fn from_bytes(message_kinds: &MessageKinds, bytes: &[u8]) -> Result<Self, SerdeErr> {
+		let mut reader = BitReader::from_slice(bytes);
+		message_kinds.read(&mut reader)
+	}
+
     pub fn is_fragment(&self) -> bool {
         return self.inner.is_fragment();
     }
@@ -50,13 +88,49 @@ impl MessageContainer {
         return self.inner.kind();
     }
 
-	pub fn downcast<M: Message>(self) -> M {
-		*self.to_boxed_any()
-			.downcast::<M>()
-			.unwrap()
+	/// Take the message out if it is an `M`, or return the container unchanged so
+	/// other types can be tried
+	pub fn downcast<M: Message>(self) -> Result<M, Self> {
+		if !self.is::<M>() {
+			return Err(self);
+		}
+
+		let Ok(message) = self.to_boxed_any().downcast::<M>() else {
+			unreachable!("message kind matched, but not its type");
+		};
+		Ok(*message)
 	}
 
 	pub fn is<M: Message>(&self) -> bool {
 		self.inner.kind() == MessageKind::of::<M>()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Blob, messages::fragment::FragmentedMessage};
+
+	#[test]
+	fn bytes_roundtrip() {
+		let mut message_kinds = MessageKinds::new();
+		message_kinds.add_message::<Blob>();
+
+		let container = MessageContainer::from_write(Box::new(Blob::new(&[1, 2, 3])));
+		let bytes = container.to_bytes(&message_kinds);
+		assert_eq!(bytes.len(), container.bit_length().div_ceil(8) as usize);
+
+		let decoded = MessageContainer::from_bytes(&message_kinds, &bytes).unwrap();
+		assert!(decoded.is::<Blob>());
+		assert_eq!(&*decoded.downcast::<Blob>().unwrap().bytes, &[1, 2, 3]);
+	}
+
+	#[test]
+	fn downcast_mismatch() {
+		let container = MessageContainer::from_write(Box::new(Blob::new(&[1])));
+		let Err(container) = container.downcast::<FragmentedMessage>() else {
+			panic!("downcast to the wrong type");
+		};
+		assert_eq!(&*container.downcast::<Blob>().unwrap().bytes, &[1]);
+	}
+}
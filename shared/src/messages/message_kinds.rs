@@ -1,8 +1,9 @@
-use std::{any::TypeId, collections::HashMap};
+use std::{any::TypeId, collections::{HashMap, HashSet}};
 
 use naia_serde::{BitReader, BitWrite, ConstBitLength, Serde, SerdeErr};
 
-use crate::{Message, MessageBuilder, MessageContainer};
+use crate::{Message, MessageBuilder, MessageContainer, error::{NaiaError, NaiaResult}};
+use crate::protocol_spec::{MessageSpec, short_type_name};
 
 type NetId = u16;
 
@@ -25,7 +26,8 @@ impl MessageKind {
 
     pub fn de(message_kinds: &MessageKinds, reader: &mut BitReader) -> Result<Self, SerdeErr> {
         let net_id: NetId = NetId::de(reader)?;
-        Ok(message_kinds.net_id_to_kind(&net_id))
+        // the remote host may send unknown ids
+        message_kinds.net_id_map.get(&net_id).copied().ok_or_else(|| SerdeErr::at(reader.position()))
     }
 }
 
@@ -36,43 +38,154 @@ impl ConstBitLength for MessageKind {
 }
 
 // MessageKinds
+/// The registered message types, and the ids they are sent with. Messages are given
+/// the lowest free id in registration order, unless registered with an explicit id,
+/// which stays the same however other messages are registered.
 pub struct MessageKinds {
-    current_net_id: NetId,
     kind_map: HashMap<MessageKind, (NetId, Box<dyn MessageBuilder>)>,
     net_id_map: HashMap<NetId, MessageKind>,
+    /// Messages registered with an explicit id
+    explicit: HashSet<MessageKind>,
+    /// Sorted by id
+    specs: Vec<MessageSpec>,
+    max_message_bytes: Option<usize>,
 }
 
 impl MessageKinds {
     pub fn new() -> Self {
         Self {
-            current_net_id: 0,
             kind_map: HashMap::new(),
             net_id_map: HashMap::new(),
+            explicit: HashSet::new(),
+            specs: Vec::new(),
+            max_message_bytes: None,
         }
     }
 
+    pub fn max_message_bytes(&self) -> Option<usize> { self.max_message_bytes }
+
+    pub(crate) fn set_max_message_bytes(&mut self, max: usize) {
+        self.max_message_bytes = Some(max);
+    }
+
+    fn exceeds_max(&self, bits: usize) -> bool {
+        self.max_message_bytes.is_some_and(|max| bits.div_ceil(8) > max)
+    }
+
+    /// Returns an error if an outgoing message exceeds the maximum message size
+    pub(crate) fn check_size(&self, message: &MessageContainer) -> NaiaResult {
+        if self.exceeds_max(message.bit_length() as usize) {
+            return Err(NaiaError::MessageTooLarge(message.name()));
+        }
+        Ok(())
+    }
+
+    /// Register `M`, with its `#[message(id = N)]` if given. Registering it again, e.g.
+    /// from several plugins, has no effect.
     pub fn add_message<M: Message>(&mut self) {
+        self.register::<M>(M::stable_id());
+    }
+
+    /// Register `M` with an explicit id. Panics if another message was registered with
+    /// the same explicit id, or `M` with a different one. Messages given the id
+    /// implicitly move to the lowest free id.
+    pub fn add_message_with_id<M: Message>(&mut self, net_id: NetId) {
+        self.register::<M>(Some(net_id));
+    }
+
+    fn register<M: Message>(&mut self, net_id: Option<NetId>) {
         let message_kind = MessageKind::of::<M>();
+        let name = short_type_name::<M>();
+        if let Some((current, _)) = self.kind_map.get(&message_kind) {
+            match net_id {
+                // plugins may share types
+                None => return,
+                Some(net_id) if net_id == *current => {
+                    self.explicit.insert(message_kind);
+                    return;
+                }
+                Some(net_id) if self.explicit.contains(&message_kind) => {
+                    panic!("message {name} registered with ids {current} and {net_id}");
+                }
+                Some(_) => {
+                    self.remove(&message_kind);
+                }
+            }
+        }
 
-        let net_id = self.current_net_id;
-        self.kind_map
-            .insert(message_kind, (net_id, M::create_builder()));
+        let mut displaced = None;
+        let net_id = match net_id {
+            Some(net_id) => {
+                if let Some(other) = self.net_id_map.get(&net_id).copied() {
+                    if self.explicit.contains(&other) {
+                        let other_name = &self.specs[self.spec_index(net_id)].name;
+                        panic!("messages {other_name} and {name} both registered with id {net_id}");
+                    }
+                    displaced = Some((other, self.remove(&other)));
+                }
+                self.explicit.insert(message_kind);
+                net_id
+            }
+            None => self.free_net_id(),
+        };
+
+        let spec = MessageSpec {
+            id: net_id,
+            name,
+            compressed: M::is_compressed(),
+            fields: M::fields(),
+        };
+        self.insert(message_kind, M::create_builder(), spec);
+        if let Some((other, (builder, mut spec))) = displaced {
+            spec.id = self.free_net_id();
+            self.insert(other, builder, spec);
+        }
+    }
+
+    fn free_net_id(&self) -> NetId {
+        (0..=NetId::MAX)
+            .find(|net_id| !self.net_id_map.contains_key(net_id))
+            .expect("too many message types")
+    }
+
+    fn spec_index(&self, net_id: NetId) -> usize {
+        self.specs.binary_search_by_key(&net_id, |spec| spec.id).unwrap()
+    }
+
+    fn insert(&mut self, message_kind: MessageKind, builder: Box<dyn MessageBuilder>, spec: MessageSpec) {
+        let net_id = spec.id;
+        self.kind_map.insert(message_kind, (net_id, builder));
         self.net_id_map.insert(net_id, message_kind);
-        self.current_net_id += 1;
-        //TODO: check for current_id overflow?
+        let index = self.specs.partition_point(|other| other.id < net_id);
+        self.specs.insert(index, spec);
+    }
+
+    fn remove(&mut self, message_kind: &MessageKind) -> (Box<dyn MessageBuilder>, MessageSpec) {
+        let (net_id, builder) = self.kind_map.remove(message_kind).unwrap();
+        self.net_id_map.remove(&net_id);
+        let spec = self.specs.remove(self.spec_index(net_id));
+        (builder, spec)
     }
 
     pub fn read(&self, reader: &mut BitReader) -> Result<MessageContainer, SerdeErr> {
         let message_kind: MessageKind = MessageKind::de(self, reader)?;
-        return self.kind_to_builder(&message_kind).read(reader);
+        return self.kind_to_builder(&message_kind).read(self, reader);
     }
 
-    fn net_id_to_kind(&self, net_id: &NetId) -> MessageKind {
-        return *self.net_id_map.get(net_id).expect(
-            "Must properly initialize Message with Protocol via `add_message()` function!",
-        );
+    /// Like `read()`, but for messages from the remote host, which must not exceed
+    /// the maximum message size
+    pub(crate) fn read_remote(&self, reader: &mut BitReader) -> NaiaResult<MessageContainer> {
+        let start = reader.remaining_bits();
+        let message = self.read(reader)?;
+        if self.exceeds_max(start - reader.remaining_bits()) {
+            return Err(NaiaError::MessageTooLarge(message.name()));
+        }
+        Ok(message)
     }
 
+    /// Describes all messages, in net id order
+    pub fn describe(&self) -> &[MessageSpec] { &self.specs }
+
     fn kind_to_net_id(&self, message_kind: &MessageKind) -> NetId {
         return self
             .kind_map
@@ -81,11 +194,12 @@ impl MessageKinds {
             .0;
     }
 
-    fn kind_to_builder(&self, message_kind: &MessageKind) -> &Box<dyn MessageBuilder> {
-        return &self
+    fn kind_to_builder(&self, message_kind: &MessageKind) -> &dyn MessageBuilder {
+        return self
             .kind_map
-            .get(&message_kind)
+            .get(message_kind)
             .expect("Must properly initialize Message with Protocol via `add_message()` function!")
-            .1;
+            .1
+            .as_ref();
     }
 }
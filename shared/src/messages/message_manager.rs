@@ -1,16 +1,17 @@
-use crate::{MessageKinds, error::*, packet::*, Schema};
-use naia_serde::{BitReader, BitWrite, BitWriter, Serde};
-use std::collections::HashMap;
-use std::time::Instant;
+use crate::{Instant, MessageKinds, error::*, packet::*, Schema};
+use crate::metrics::{BandwidthProfiler, BandwidthReport, RATE_WINDOW_SIZE, RateCounter};
+use naia_serde::{BitReader, BitWrite, BitWriter, MTU_SIZE_BYTES, Serde, SerdeErr};
+use std::{collections::HashMap, time::Duration};
 
 use crate::{
     constants::FRAGMENTATION_LIMIT_BITS,
 	messages::{
         channels::{
-            channel::{ChannelMode, ChannelSettings},
+            channel::{ChannelMode, ChannelSettings, PausePolicy, RateLimit},
             channel_kinds::{ChannelKind, ChannelKinds},
             receivers::{
                 channel_receiver::ChannelReceiver,
+                fragment_receiver::ReassemblyConfig,
                 ordered_reliable_receiver::OrderedReliableReceiver,
                 sequenced_reliable_receiver::SequencedReliableReceiver,
                 sequenced_unreliable_receiver::SequencedUnreliableReceiver,
@@ -18,7 +19,7 @@ use crate::{
                 unordered_unreliable_receiver::UnorderedUnreliableReceiver,
             },
             senders::{
-                channel_sender::ChannelSender, message_fragmenter::MessageFragmenter,
+                channel_sender::{ChannelSender, QueueDepth}, message_fragmenter::MessageFragmenter,
                 reliable_sender::ReliableSender,
                 sequenced_unreliable_sender::SequencedUnreliableSender,
                 unordered_unreliable_sender::UnorderedUnreliableSender,
@@ -37,11 +38,40 @@ pub struct MessageManager {
     channel_settings: HashMap<ChannelKind, ChannelSettings>,
     packet_to_message_map: HashMap<PacketSeq, Vec<(ChannelKind, Vec<MessageIndex>)>>,
     message_fragmenter: MessageFragmenter,
+    /// Outgoing messages held back on paused channels, per `ChannelSettings::pause`
+    paused: HashMap<ChannelKind, Vec<MessageContainer>>,
+    /// Incoming messages held back on channels, until `release_channel()`
+    held: HashMap<ChannelKind, Vec<MessageContainer>>,
+    /// Messages of released channels, delivered before any newer ones
+    released: Vec<MessageContainer>,
+    /// Whether the remote host is a receive-only observer
+    observer: bool,
+    msg_rx_observer_drop_count: u64,
+    /// The state of each channel's `ChannelSettings::rate_limit`, on the Server
+    throttles: HashMap<ChannelKind, Throttle>,
+    msg_rx_throttle_count: u64,
+    msg_rx_rates: HashMap<ChannelKind, RateCounter>,
+    msg_tx_rates: HashMap<ChannelKind, RateCounter>,
+	profiler: Option<BandwidthProfiler>,
+	/// For receivers of messages on misused channels, which are only read to skip them
+	reassembly: ReassemblyConfig,
+}
+
+fn new_receiver(mode: &ChannelMode, reassembly: &ReassemblyConfig) -> Box<dyn ChannelReceiver> {
+	match mode {
+		ChannelMode::UnorderedUnreliable => Box::new(UnorderedUnreliableReceiver::new()),
+		ChannelMode::SequencedUnreliable => Box::new(SequencedUnreliableReceiver::new()),
+		ChannelMode::UnorderedReliable => Box::new(UnorderedReliableReceiver::new(reassembly.clone())),
+		ChannelMode::SequencedReliable => Box::new(SequencedReliableReceiver::new(reassembly.clone())),
+		ChannelMode::OrderedReliable => Box::new(OrderedReliableReceiver::new(reassembly.clone())),
+	}
 }
 
 impl MessageManager {
     /// Creates a new MessageManager
-    pub fn new(host_type: HostType, channel_kinds: &ChannelKinds) -> Self {
+    pub fn new(
+		host_type: HostType, channel_kinds: &ChannelKinds, reassembly: &ReassemblyConfig,
+	) -> Self {
         // initialize all reliable channels
 
         // initialize senders
@@ -73,7 +103,7 @@ impl MessageManager {
                 | ChannelMode::SequencedReliable
                 | ChannelMode::OrderedReliable => {
                     channel_senders
-						.insert(channel_kind, Box::new(ReliableSender::new()));
+						.insert(channel_kind, Box::new(ReliableSender::new(channel_settings.resend.clone())));
                 }
             };
         }
@@ -94,52 +124,51 @@ impl MessageManager {
                 }
             }
 
-            match &channel_settings.mode {
-                ChannelMode::UnorderedUnreliable => {
-                    channel_receivers.insert(
-                        channel_kind.clone(),
-                        Box::new(UnorderedUnreliableReceiver::new()),
-                    );
-                }
-                ChannelMode::SequencedUnreliable => {
-                    channel_receivers.insert(
-                        channel_kind.clone(),
-                        Box::new(SequencedUnreliableReceiver::new()),
-                    );
-                }
-                ChannelMode::UnorderedReliable => {
-                    channel_receivers.insert(
-                        channel_kind.clone(),
-                        Box::new(UnorderedReliableReceiver::new()),
-                    );
-                }
-                ChannelMode::SequencedReliable => {
-                    channel_receivers.insert(
-                        channel_kind.clone(),
-                        Box::new(SequencedReliableReceiver::new()),
-                    );
-                }
-                ChannelMode::OrderedReliable => {
-                    channel_receivers.insert(
-                        channel_kind.clone(),
-                        Box::new(OrderedReliableReceiver::new()),
-                    );
-                }
-            };
+            channel_receivers.insert(channel_kind, new_receiver(&channel_settings.mode, reassembly));
         }
 
         // initialize settings
         let mut channel_settings_map = HashMap::new();
         for (channel_kind, channel_settings) in channel_kinds.channels() {
-            channel_settings_map.insert(channel_kind.clone(), channel_settings);
+            channel_settings_map.insert(channel_kind, channel_settings);
         }
 
+        // initialize rate limits
+        let throttles = match host_type {
+            HostType::Server => channel_receivers.keys()
+                .filter_map(|channel_kind| {
+                    let limit = channel_settings_map[channel_kind].rate_limit?;
+                    Some((*channel_kind, Throttle::new(limit)))
+                })
+                .collect(),
+            HostType::Client => HashMap::new(),
+        };
+
+        // initialize rates
+        let msg_rx_rates = channel_receivers.keys()
+            .map(|channel_kind| (*channel_kind, RateCounter::new(RATE_WINDOW_SIZE)))
+            .collect();
+        let msg_tx_rates = channel_senders.keys()
+            .map(|channel_kind| (*channel_kind, RateCounter::new(RATE_WINDOW_SIZE)))
+            .collect();
+
         MessageManager {
             channel_senders,
             channel_receivers,
             channel_settings: channel_settings_map,
             packet_to_message_map: HashMap::new(),
             message_fragmenter: MessageFragmenter::new(),
+            paused: HashMap::new(),
+            held: HashMap::new(),
+            released: Vec::new(),
+            observer: false,
+            msg_rx_observer_drop_count: 0,
+            throttles,
+            msg_rx_throttle_count: 0,
+            msg_rx_rates,
+            msg_tx_rates,
+			profiler: None,
+			reassembly: reassembly.clone(),
         }
     }
 
@@ -159,10 +188,19 @@ impl MessageManager {
         message_kinds: &MessageKinds,
         channel_kind: &ChannelKind,
         message: MessageContainer,
-    ) {
+    ) -> NaiaResult {
         let Some(channel) = self.channel_senders.get_mut(channel_kind) else {
             panic!("Channel not configured correctly! Cannot send message.");
         };
+        message_kinds.check_size(&message)?;
+        if let Some(buffer) = self.paused.get_mut(channel_kind) {
+            if self.channel_settings[channel_kind].pause == PausePolicy::Buffer {
+                buffer.push(message);
+            }
+            return Ok(());
+        }
+        // unreliable channels count messages as sent once queued
+        let msg_tx_count = channel.msg_tx_count();
 
         let message_bit_length = message.bit_length();
         if message_bit_length > FRAGMENTATION_LIMIT_BITS {
@@ -186,11 +224,37 @@ impl MessageManager {
         } else {
             channel.send(message);
         }
+        self.msg_tx_rates.get_mut(channel_kind).unwrap().add(channel.msg_tx_count().wrapping_sub(msg_tx_count));
+        Ok(())
+    }
+
+    /// Hold back messages queued on the given channel until `resume_channel()`
+    pub fn pause_channel(&mut self, channel_kind: &ChannelKind) {
+        if self.channel_senders.contains_key(channel_kind) {
+            self.paused.entry(*channel_kind).or_default();
+        }
     }
 
-    pub fn collect_messages(&mut self, now: &Instant, resend_ms: &f32) {
+    /// Queue the messages buffered while the channel was paused, in order
+    pub fn resume_channel(
+        &mut self, message_kinds: &MessageKinds, channel_kind: &ChannelKind,
+    ) -> NaiaResult {
+        let Some(buffer) = self.paused.remove(channel_kind) else {
+            return Ok(());
+        };
+        for message in buffer {
+            self.queue_message(message_kinds, channel_kind, message)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_channel_paused(&self, channel_kind: &ChannelKind) -> bool {
+        self.paused.contains_key(channel_kind)
+    }
+
+    pub fn collect_messages(&mut self, now: &Instant, rtt_ms: f32, jitter_ms: f32) {
         for channel in self.channel_senders.values_mut() {
-            channel.collect_messages(now, resend_ms);
+            channel.collect_messages(now, rtt_ms, jitter_ms);
         }
     }
 
@@ -234,14 +298,19 @@ impl MessageManager {
             // write ChannelIndex
             channel_kind.ser(schema.channel_kinds(), writer);
             // write Messages
-            if let Some(message_indices) =
-                channel.write_messages(schema.message_kinds(), writer, &mut has_written)
-            {
+            let msg_tx_count = channel.msg_tx_count();
+            let profiler = &mut self.profiler;
+            let mut on_write = |message: &MessageContainer| if let Some(profiler) = profiler {
+				profiler.record(*channel_kind, message);
+			};
+            let message_indices = channel.write_messages(schema.message_kinds(), writer, &mut has_written, &mut on_write);
+            self.msg_tx_rates.get_mut(channel_kind).unwrap().add(channel.msg_tx_count().wrapping_sub(msg_tx_count));
+            if let Some(message_indices) = message_indices {
                 self.packet_to_message_map
                     .entry(packet_seq)
-                    .or_insert_with(Vec::new);
+                    .or_default();
                 let channel_list = self.packet_to_message_map.get_mut(&packet_seq).unwrap();
-                channel_list.push((channel_kind.clone(), message_indices));
+                channel_list.push((*channel_kind, message_indices));
             }
 
             // write MessageContinue finish bit, release
@@ -254,35 +323,97 @@ impl MessageManager {
 
     // Incoming Messages
 
+    /// Read the messages of a data packet. Messages on channels the remote host may
+    /// not send on are dropped, and reported as `NaiaError::ChannelMisuse` once the
+    /// rest are read.
     pub fn read_messages(
 		&mut self, schema: &Schema, reader: &mut BitReader,
     ) -> NaiaResult {
+		let mut misused = None;
         loop {
-            let Ok(message_continue) = bool::de(reader) else {
-				return Err(NaiaError::malformed::<packet::Data>());
-			};
+            let message_continue = bool::de(reader)
+				.map_err(NaiaError::malformed_by::<packet::Data>)?;
 
             if !message_continue {
                 break;
             }
 
             // read channel id
-            let Ok(channel_kind) = ChannelKind::de(schema.channel_kinds(), reader) else {
-				return Err(NaiaError::malformed::<packet::Data>());
-			};
+            let channel_kind = ChannelKind::de(schema.channel_kinds(), reader)
+				.map_err(NaiaError::malformed_by::<packet::Data>)?;
 
             // continue read inside channel
-            let channel = self.channel_receivers.get_mut(&channel_kind).unwrap();
-            channel.read_messages(schema.message_kinds(), reader)?;
+            let Some(channel) = self.channel_receivers.get_mut(&channel_kind) else {
+				// the remote host may not send on this channel; skip its messages
+				let mode = &self.channel_settings[&channel_kind].mode;
+				new_receiver(mode, &self.reassembly).read_messages(schema.message_kinds(), reader)?;
+				misused = Some(schema.channel_kinds().name(&channel_kind).to_string());
+				continue;
+			};
+            let msg_rx_count = channel.msg_rx_count();
+            let result = channel.read_messages(schema.message_kinds(), reader);
+            self.msg_rx_rates.get_mut(&channel_kind).unwrap().add(channel.msg_rx_count().wrapping_sub(msg_rx_count));
+            result?;
         }
 
-        Ok(())
+        match misused {
+			Some(name) => Err(NaiaError::ChannelMisuse(name)),
+			None => Ok(()),
+		}
     }
 
+	/// Treat the remote host as a receive-only observer, dropping its messages on all
+	/// channels without `observer_send`
+	pub fn set_observer(&mut self, observer: bool) { self.observer = observer }
+
+	/// Buffer messages received on the given channel until `release_channel()`
+	pub fn hold_channel(&mut self, channel_kind: &ChannelKind) {
+		if self.channel_receivers.contains_key(channel_kind) {
+			self.held.entry(*channel_kind).or_default();
+		}
+	}
+
+	/// Deliver the messages held on the given channel with the next
+	/// `receive_messages()`, in the order they were received
+	pub fn release_channel(&mut self, channel_kind: &ChannelKind) {
+		if let Some(held) = self.held.remove(channel_kind) {
+			self.released.extend(held);
+		}
+	}
+
+	pub fn is_channel_held(&self, channel_kind: &ChannelKind) -> bool {
+		self.held.contains_key(channel_kind)
+	}
+
     /// Retrieve all messages from the channel buffers
 	pub fn receive_messages(&mut self) -> impl Iterator<Item = MessageContainer> + '_ {
-		self.channel_receivers.values_mut()
-			.flat_map(|chan| chan.receive_messages())
+		let mut received = std::mem::take(&mut self.released);
+		for (channel_kind, channel) in &mut self.channel_receivers {
+			let mut messages = channel.receive_messages();
+			if let Some(throttle) = self.throttles.get_mut(channel_kind) {
+				let dropped = throttle.apply(&mut messages);
+				self.msg_rx_throttle_count = self.msg_rx_throttle_count.wrapping_add(dropped);
+			}
+
+			if let Some(held) = self.held.get_mut(channel_kind) {
+				held.extend(messages);
+			} else if self.observer && !self.channel_settings[channel_kind].observer_send {
+				let dropped = messages.len() as u64;
+				self.msg_rx_observer_drop_count = self.msg_rx_observer_drop_count.wrapping_add(dropped);
+			} else {
+				received.extend(messages);
+			}
+		}
+		received.into_iter()
+	}
+
+	/// The channels on which messages were dropped for exceeding their rate limit since
+	/// the last call, with the number dropped
+	pub fn take_throttled(&mut self) -> Vec<(ChannelKind, u64)> {
+		self.throttles.iter_mut()
+			.filter(|(_, throttle)| throttle.dropped > 0)
+			.map(|(channel_kind, throttle)| (*channel_kind, std::mem::take(&mut throttle.dropped)))
+			.collect()
 	}
 
     /// Occurs when a packet has been notified as delivered. Stops tracking the
@@ -299,11 +430,125 @@ impl MessageManager {
         }
     }
 
+	// Handoff
+
+	/// Write the state of all channels, including unacknowledged reliable messages, so
+	/// another process can continue the connection; see `BaseConnection::export()`
+	pub fn export(&self, schema: &Schema, writer: &mut dyn BitWrite) {
+		for (channel_kind, channel) in &self.channel_senders {
+			true.ser(writer);
+			channel_kind.ser(schema.channel_kinds(), writer);
+			channel.export(schema.message_kinds(), writer);
+		}
+		false.ser(writer);
+		for (channel_kind, channel) in &self.channel_receivers {
+			true.ser(writer);
+			channel_kind.ser(schema.channel_kinds(), writer);
+			channel.export(schema.message_kinds(), writer);
+		}
+		false.ser(writer);
+	}
+
+	/// Restore the state written by `export()`, which must have used the same Schema
+	pub fn import(&mut self, schema: &Schema, reader: &mut BitReader) -> Result<(), SerdeErr> {
+		while bool::de(reader)? {
+			let channel_kind = ChannelKind::de(schema.channel_kinds(), reader)?;
+			let channel = self.channel_senders.get_mut(&channel_kind).ok_or(SerdeErr::new())?;
+			channel.import(schema.message_kinds(), reader)?;
+		}
+		while bool::de(reader)? {
+			let channel_kind = ChannelKind::de(schema.channel_kinds(), reader)?;
+			let channel = self.channel_receivers.get_mut(&channel_kind).ok_or(SerdeErr::new())?;
+			channel.import(schema.message_kinds(), reader)?;
+		}
+		self.packet_to_message_map.clear();
+		Ok(())
+	}
+
+	/// Returns whether a message exceeded its channel's maximum resend attempts
+	pub fn resends_exhausted(&self) -> bool {
+		self.senders().any(ChannelSender::resends_exhausted)
+	}
+
+	/// Attribute written messages to their channel and kind over `window`, or stop
+	/// with `None`. Keeps the measurements if the window is unchanged.
+	pub fn set_bandwidth_profiler(&mut self, window: Option<Duration>) {
+		if self.profiler.as_ref().map(BandwidthProfiler::window) != window {
+			self.profiler = window.map(BandwidthProfiler::new);
+		}
+	}
+
+	pub fn bandwidth_report(&self, channel_kinds: &ChannelKinds) -> Option<BandwidthReport> {
+		self.profiler.as_ref().map(|profiler| profiler.report(channel_kinds))
+	}
+
+	/// The total size in bits of the messages ready to be written, across all channels
+	pub fn outgoing_bits(&self) -> u64 { self.senders().map(ChannelSender::outgoing_bits).sum() }
+
+	/// The backlog of outgoing messages across all channels
+	pub fn queue_depth(&self) -> QueueDepth {
+		let (messages, bits) = self.senders()
+			.map(ChannelSender::queued_bits)
+			.fold((0, 0), |(count, bits), (c, b)| (count + c, bits + b));
+		let bytes = bits.div_ceil(8) as usize;
+		QueueDepth { messages, bytes, est_packets: bytes.div_ceil(MTU_SIZE_BYTES) }
+	}
+
 	// performance counters
 
 	pub fn msg_rx_count(&self) -> u64 { self.receivers().map(ChannelReceiver::msg_rx_count).sum() }
 	pub fn msg_rx_drop_count(&self) -> u64 { self.receivers().map(ChannelReceiver::msg_rx_drop_count).sum() }
 	pub fn msg_rx_miss_count(&self) -> u64 { self.receivers().map(ChannelReceiver::msg_rx_miss_count).sum() }
+	pub fn frag_rx_expired_count(&self) -> u64 { self.receivers().map(ChannelReceiver::frag_rx_expired_count).sum() }
+	pub fn frag_rx_refused_count(&self) -> u64 { self.receivers().map(ChannelReceiver::frag_rx_refused_count).sum() }
+	pub fn msg_rx_observer_drop_count(&self) -> u64 { self.msg_rx_observer_drop_count }
+	pub fn msg_rx_throttle_count(&self) -> u64 { self.msg_rx_throttle_count }
 	pub fn msg_tx_count(&self) -> u64 { self.senders().map(ChannelSender::msg_tx_count).sum() }
 	pub fn msg_tx_queue_count(&self) -> u64 { self.senders().map(ChannelSender::msg_tx_queue_count).sum() }
+
+	/// The rate of messages received on `channel_kind`, or 0 if the remote host may
+	/// not send on it
+	pub fn msg_rx_per_sec(&self, channel_kind: &ChannelKind) -> f32 {
+		self.msg_rx_rates.get(channel_kind).map(RateCounter::per_sec).unwrap_or(0.0)
+	}
+
+	/// The rate of messages sent (including resends) on `channel_kind`, or 0 if this
+	/// host may not send on it
+	pub fn msg_tx_per_sec(&self, channel_kind: &ChannelKind) -> f32 {
+		self.msg_tx_rates.get(channel_kind).map(RateCounter::per_sec).unwrap_or(0.0)
+	}
+}
+
+/// Enforces a `RateLimit` over fixed windows
+struct Throttle {
+	limit: RateLimit,
+	window_start: Instant,
+	/// Messages delivered in the current window
+	count: u32,
+	/// Messages dropped since `MessageManager::take_throttled()`
+	dropped: u64,
+}
+
+impl Throttle {
+	fn new(limit: RateLimit) -> Self {
+		Self { limit, window_start: Instant::now(), count: 0, dropped: 0 }
+	}
+
+	/// Drop the messages beyond the limit, returning how many were dropped
+	fn apply(&mut self, messages: &mut Vec<MessageContainer>) -> u64 {
+		if messages.is_empty() {
+			return 0;
+		}
+		if self.window_start.elapsed() >= self.limit.window {
+			self.window_start = Instant::now();
+			self.count = 0;
+		}
+
+		let allowed = self.limit.max_messages.saturating_sub(self.count) as usize;
+		let dropped = messages.len().saturating_sub(allowed) as u64;
+		messages.truncate(allowed);
+		self.count += messages.len() as u32;
+		self.dropped += dropped;
+		dropped
+	}
 }
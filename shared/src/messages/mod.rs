@@ -1,10 +1,13 @@
+pub mod blob;
 pub mod channels;
+pub mod checksum;
 pub mod fragment;
 pub mod message;
 pub mod message_container;
 pub mod message_kinds;
 pub mod message_manager;
 pub mod named;
+pub mod sync;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,125 @@
+use naia_derive::MessageInternal;
+use crate::Channel;
+
+/// The size of each piece of a snapshot, small enough to avoid fragmentation
+pub const SYNC_CHUNK_BYTES: usize = 256;
+
+/// Carries snapshots to Clients, and their acknowledgement back to the Server; see
+/// `SchemaBuilder::enable_sync()`
+pub struct SyncChannel;
+
+impl Channel for SyncChannel {}
+
+/// A piece of a snapshot. `total` is the size of the whole snapshot in bytes.
+#[derive(MessageInternal)]
+pub struct SyncChunk {
+	pub total: u32,
+	pub bytes: Box<[u8]>,
+}
+
+/// Sent by Clients once the whole snapshot was received
+#[derive(MessageInternal)]
+pub struct SyncDone;
+
+/// Splits a snapshot into SyncChunks
+pub struct SnapshotSender {
+	snapshot: Box<[u8]>,
+	offset: usize,
+	/// Whether a chunk was returned, so that empty snapshots are still sent
+	started: bool,
+}
+
+impl SnapshotSender {
+	pub fn new(snapshot: Box<[u8]>) -> Self {
+		debug_assert!(snapshot.len() <= u32::MAX as usize, "snapshot too large");
+		Self { snapshot, offset: 0, started: false }
+	}
+
+	pub fn next_chunk(&mut self) -> Option<SyncChunk> {
+		if self.is_sent() {
+			return None;
+		}
+
+		let end = usize::min(self.offset + SYNC_CHUNK_BYTES, self.snapshot.len());
+		let bytes = self.snapshot[self.offset..end].into();
+		self.offset = end;
+		self.started = true;
+		Some(SyncChunk { total: self.snapshot.len() as u32, bytes })
+	}
+
+	/// Whether all chunks were returned by `next_chunk()`
+	pub fn is_sent(&self) -> bool {
+		self.started && self.offset == self.snapshot.len()
+	}
+}
+
+/// Reassembles a snapshot from SyncChunks received in order
+#[derive(Default)]
+pub struct SnapshotReceiver {
+	buffer: Vec<u8>,
+	total: usize,
+}
+
+impl SnapshotReceiver {
+	/// Add the next chunk, returning the whole snapshot once complete. Chunks which
+	/// overrun the snapshot's announced size are dropped.
+	pub fn receive(&mut self, chunk: SyncChunk) -> Option<Box<[u8]>> {
+		if self.buffer.is_empty() {
+			self.total = chunk.total as usize;
+		}
+		if chunk.total as usize != self.total || self.buffer.len() + chunk.bytes.len() > self.total {
+			return None;
+		}
+
+		self.buffer.extend_from_slice(&chunk.bytes);
+		(self.buffer.len() == self.total).then(|| std::mem::take(&mut self.buffer).into_boxed_slice())
+	}
+
+	/// The fraction of the snapshot received so far, between 0 and 1
+	pub fn progress(&self) -> f32 {
+		match self.total {
+			0 => 1.0,
+			total => self.buffer.len() as f32 / total as f32,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn roundtrip() {
+		for len in [0, 1, SYNC_CHUNK_BYTES, 3 * SYNC_CHUNK_BYTES + 7] {
+			let snapshot: Box<[u8]> = (0..len).map(|i| i as u8).collect();
+			let mut sender = SnapshotSender::new(snapshot.clone());
+			let mut receiver = SnapshotReceiver::default();
+
+			let mut chunks = 0;
+			let mut received = None;
+			while let Some(chunk) = sender.next_chunk() {
+				assert!(received.is_none());
+				received = receiver.receive(chunk);
+				chunks += 1;
+			}
+			assert!(sender.is_sent());
+			assert_eq!(chunks, len.div_ceil(SYNC_CHUNK_BYTES).max(1));
+			assert_eq!(received, Some(snapshot));
+		}
+	}
+
+	#[test]
+	fn progress() {
+		let mut sender = SnapshotSender::new(vec![0; 2 * SYNC_CHUNK_BYTES].into());
+		let mut receiver = SnapshotReceiver::default();
+		assert_eq!(receiver.receive(sender.next_chunk().unwrap()), None);
+		assert_eq!(receiver.progress(), 0.5);
+
+		// overruns are dropped
+		let overrun = SyncChunk { total: 2 * SYNC_CHUNK_BYTES as u32, bytes: vec![0; 2 * SYNC_CHUNK_BYTES].into() };
+		assert_eq!(receiver.receive(overrun), None);
+		assert_eq!(receiver.progress(), 0.5);
+
+		assert!(receiver.receive(sender.next_chunk().unwrap()).is_some());
+	}
+}
@@ -0,0 +1,92 @@
+use naia_derive::MessageInternal;
+use naia_serde::BitCounter;
+
+use crate::{MessageContainer, MessageKinds};
+
+#[derive(MessageInternal)]
+#[message(compress)]
+pub struct MapChunk {
+    pub x: i32,
+    pub y: i32,
+    pub tiles: Vec<u8>,
+}
+
+#[derive(MessageInternal)]
+pub struct RawChunk {
+    pub x: i32,
+    pub y: i32,
+    pub tiles: Vec<u8>,
+}
+
+#[derive(MessageInternal)]
+#[message(compress)]
+pub struct Inventory(Vec<u16>);
+
+fn message_kinds() -> MessageKinds {
+    let mut message_kinds = MessageKinds::new();
+    message_kinds.add_message::<MapChunk>();
+    message_kinds.add_message::<RawChunk>();
+    message_kinds.add_message::<Inventory>();
+    message_kinds
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn compressed_roundtrip() {
+    let message_kinds = message_kinds();
+    let tiles: Vec<u8> = (0..400).map(|i| (i / 100) as u8).collect();
+
+    let compressed = MessageContainer::from_write(Box::new(MapChunk { x: 3, y: -4, tiles: tiles.clone() }));
+    let raw = MessageContainer::from_write(Box::new(RawChunk { x: 3, y: -4, tiles: tiles.clone() }));
+    let bytes = compressed.to_bytes(&message_kinds);
+    assert!(bytes.len() < raw.to_bytes(&message_kinds).len() / 4);
+
+    // the container writes the fields it compressed once, as the message would
+    let mut writer = naia_serde::BitWriter::new();
+    crate::Message::write(&MapChunk { x: 3, y: -4, tiles: tiles.clone() }, &message_kinds, &mut writer);
+    assert_eq!(writer.slice(), &*bytes);
+
+    // bit_length matches the written bits
+    let mut counter = BitCounter::new(u32::MAX);
+    compressed.write(&message_kinds, &mut counter);
+    assert_eq!(compressed.bit_length(), counter.bits_needed());
+
+    let chunk = MessageContainer::from_bytes(&message_kinds, &bytes).unwrap()
+        .downcast::<MapChunk>().ok().unwrap();
+    assert_eq!((chunk.x, chunk.y, chunk.tiles), (3, -4, tiles));
+}
+
+#[test]
+fn incompressible_roundtrip() {
+    let message_kinds = message_kinds();
+    let items = vec![1, 2, 3];
+    let container = MessageContainer::from_write(Box::new(Inventory(items.clone())));
+
+    let mut counter = BitCounter::new(u32::MAX);
+    container.write(&message_kinds, &mut counter);
+    assert_eq!(container.bit_length(), counter.bits_needed());
+
+    let bytes = container.to_bytes(&message_kinds);
+    let inventory = MessageContainer::from_bytes(&message_kinds, &bytes).unwrap()
+        .downcast::<Inventory>().ok().unwrap();
+    assert_eq!(inventory.0, items);
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn corrupt_payload() {
+    let message_kinds = message_kinds();
+    let container = MessageContainer::from_write(Box::new(MapChunk { x: 0, y: 0, tiles: vec![5; 500] }));
+    let mut bytes = container.to_bytes(&message_kinds).to_vec();
+    bytes.truncate(bytes.len() - 2);
+    assert!(message_kinds.read(&mut naia_serde::BitReader::from_slice(&bytes)).is_err());
+}
+
+#[test]
+fn describe() {
+    let message_kinds = message_kinds();
+    let specs = message_kinds.describe();
+    assert!(specs[0].compressed);
+    assert!(!specs[1].compressed);
+    assert_eq!(specs[0].fields.len(), 3);
+}
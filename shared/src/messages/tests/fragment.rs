@@ -2,12 +2,16 @@ use naia_derive::MessageInternal;
 
 use crate::{
     messages::channels::{
-        receivers::fragment_receiver::FragmentReceiver,
+        receivers::{
+            fragment_receiver::{FragmentReceiver, ReassemblyConfig},
+            sequenced_reliable_receiver::SequencedReliableReceiver,
+        },
         senders::message_fragmenter::MessageFragmenter,
     },
     messages::fragment::FragmentedMessage,
-    MessageContainer, MessageKinds,
+    MessageContainer, MessageKinds, NaiaError, SeqNum,
 };
+use std::time::Duration;
 
 #[derive(MessageInternal)]
 pub struct StringMessage {
@@ -36,7 +40,7 @@ fn setup() -> (
     let fragmenter = MessageFragmenter::new();
 
     // Fragment Receiver
-    let receiver = FragmentReceiver::new();
+    let receiver = FragmentReceiver::new(ReassemblyConfig::default());
 
     (message_kinds, fragmenter, receiver)
 }
@@ -59,7 +63,7 @@ fn convert_single_fragment() {
     let mut incoming_message_container_opt = None;
     for fragment in fragments {
         if let Some(reassembled_message) =
-            receiver.receive(&message_kinds, fragment)
+            receiver.receive(&message_kinds, fragment).unwrap()
         {
             incoming_message_container_opt = Some(reassembled_message);
             break;
@@ -111,7 +115,7 @@ fn convert_multiple_fragments() {
 
         let fragment = &fragments[j];
         if let Some(reassembled_message) =
-            receiver.receive(&message_kinds, fragment.clone())
+            receiver.receive(&message_kinds, fragment.clone()).unwrap()
         {
             incoming_message_container_opt = Some(reassembled_message);
             break;
@@ -125,6 +129,76 @@ fn convert_multiple_fragments() {
     };
 
     // Compare
-    assert_eq!(fragment_count, 3);
+    assert_eq!(fragment_count, 4);
     assert_eq!(initial_message.inner, incoming_message.inner);
 }
+
+#[test]
+fn refuse_beyond_max_partial() {
+    let (message_kinds, mut fragmenter, _) = setup();
+    let config = ReassemblyConfig { max_partial: 1, ..ReassemblyConfig::default() };
+    let mut receiver = FragmentReceiver::new(config);
+
+    let long = "x".repeat(1000);
+    let first = fragmenter.fragment_message(&message_kinds, MessageContainer::from_write(Box::new(StringMessage::new(&long))));
+    let second = fragmenter.fragment_message(&message_kinds, MessageContainer::from_write(Box::new(StringMessage::new(&long))));
+
+    // only the first message may be partially assembled
+    assert!(receiver.receive(&message_kinds, first[0].clone()).unwrap().is_none());
+    assert!(matches!(receiver.receive(&message_kinds, second[0].clone()), Err(NaiaError::ReassemblyFailed)));
+    assert_eq!(receiver.frag_rx_refused_count(), 1);
+
+    let mut reassembled = None;
+    for fragment in &first[1..] {
+        reassembled = receiver.receive(&message_kinds, fragment.clone()).unwrap();
+    }
+    assert!(reassembled.is_some());
+}
+
+#[test]
+fn expire_partial() {
+    let (message_kinds, mut fragmenter, _) = setup();
+    let config = ReassemblyConfig { timeout: Duration::ZERO, ..ReassemblyConfig::default() };
+    let mut receiver = FragmentReceiver::new(config);
+
+    let long = "x".repeat(1000);
+    let fragments = fragmenter.fragment_message(&message_kinds, MessageContainer::from_write(Box::new(StringMessage::new(&long))));
+    assert!(receiver.receive(&message_kinds, fragments[0].clone()).unwrap().is_none());
+
+    // the next fragment finds the partial message expired
+    assert!(matches!(receiver.receive(&message_kinds, fragments[1].clone()), Err(NaiaError::ReassemblyFailed)));
+    assert_eq!(receiver.frag_rx_expired_count(), 1);
+}
+
+#[test]
+fn refuse_oversized() {
+    let (mut message_kinds, mut fragmenter, mut receiver) = setup();
+    message_kinds.set_max_message_bytes(500);
+
+    // the fragment count already shows the message is too large
+    let long = "x".repeat(1000);
+    let fragments = fragmenter.fragment_message(&message_kinds, MessageContainer::from_write(Box::new(StringMessage::new(&long))));
+    assert!(matches!(receiver.receive(&message_kinds, fragments[0].clone()), Err(NaiaError::MessageTooLarge(_))));
+}
+
+#[test]
+fn sequenced_out_of_order_fragments() {
+    let (message_kinds, mut fragmenter, _) = setup();
+    let mut receiver = SequencedReliableReceiver::new(ReassemblyConfig::default());
+
+    let long = "x".repeat(1000);
+    let fragments = fragmenter.fragment_message(&message_kinds, MessageContainer::from_write(Box::new(StringMessage::new(&long))));
+    let fragment_count = fragments.len() as u16;
+    let newer = MessageContainer::from_write(Box::new(StringMessage::new("newer")));
+
+    // the last fragment arrives first, yet the message is not older than itself
+    for (index, fragment) in fragments.into_iter().enumerate().rev() {
+        receiver.buffer_message(&message_kinds, SeqNum(index as u16), fragment).unwrap();
+    }
+    receiver.buffer_message(&message_kinds, SeqNum(fragment_count), newer).unwrap();
+
+    let received: Vec<_> = receiver.receive_messages().into_iter()
+        .map(|(_, message)| message.to_boxed_any().downcast::<StringMessage>().unwrap().inner)
+        .collect();
+    assert_eq!(received, [long, "newer".to_string()]);
+}
@@ -1 +1,2 @@
 mod fragment;
+mod compression;
@@ -0,0 +1,148 @@
+use crate::{ChannelKind, ChannelKinds, MessageContainer, MessageKind};
+use std::{collections::HashMap, fmt, time::Duration};
+use super::RateCounter;
+
+struct ProfileEntry {
+	message: String,
+	bits: RateCounter,
+	count: RateCounter,
+}
+
+/// Attributes the messages written into packets to their channel and message kind,
+/// over a sliding window
+pub struct BandwidthProfiler {
+	window: Duration,
+	entries: HashMap<(ChannelKind, MessageKind), ProfileEntry>,
+}
+
+impl BandwidthProfiler {
+	pub fn new(window: Duration) -> Self {
+		Self { window, entries: HashMap::new() }
+	}
+
+	pub fn window(&self) -> Duration { self.window }
+
+	pub fn record(&mut self, channel_kind: ChannelKind, message: &MessageContainer) {
+		let window = self.window;
+		let entry = self.entries.entry((channel_kind, message.kind())).or_insert_with(|| ProfileEntry {
+			message: message.name(),
+			bits: RateCounter::new(window),
+			count: RateCounter::new(window),
+		});
+		entry.bits.add(message.bit_length() as u64);
+		entry.count.add(1);
+	}
+
+	pub fn report(&self, channel_kinds: &ChannelKinds) -> BandwidthReport {
+		let mut report = BandwidthReport { window: self.window, entries: Vec::new() };
+		for ((channel_kind, _), entry) in &self.entries {
+			report.add(BandwidthEntry {
+				channel: channel_kinds.name(channel_kind).to_string(),
+				message: entry.message.clone(),
+				bytes_per_sec: entry.bits.per_sec() / 8.0,
+				msgs_per_sec: entry.count.per_sec(),
+			});
+		}
+		report
+	}
+}
+
+/// The outgoing traffic of one message kind on one channel. Rates cover the message
+/// payloads, excluding packet and channel headers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BandwidthEntry {
+	pub channel: String,
+	pub message: String,
+	pub bytes_per_sec: f32,
+	pub msgs_per_sec: f32,
+}
+
+/// Outgoing traffic by channel and message kind, ranked by bytes per second
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BandwidthReport {
+	/// The window the rates were measured over
+	pub window: Duration,
+	pub entries: Vec<BandwidthEntry>,
+}
+
+impl BandwidthReport {
+	/// Add `entry`, summing it with any entry of the same channel and message
+	pub fn add(&mut self, entry: BandwidthEntry) {
+		match self.entries.iter_mut().find(|other| other.channel == entry.channel && other.message == entry.message) {
+			Some(other) => {
+				other.bytes_per_sec += entry.bytes_per_sec;
+				other.msgs_per_sec += entry.msgs_per_sec;
+			}
+			None => self.entries.push(entry),
+		}
+		self.entries.sort_by(|a, b| b.bytes_per_sec.total_cmp(&a.bytes_per_sec));
+	}
+
+	/// Merge the report of another connection
+	pub fn merge(&mut self, other: BandwidthReport) {
+		self.window = self.window.max(other.window);
+		for entry in other.entries {
+			self.add(entry);
+		}
+	}
+
+	/// The total bytes per second by channel, ranked
+	pub fn channels(&self) -> Vec<(&str, f32)> {
+		let mut channels: Vec<(&str, f32)> = Vec::new();
+		for entry in &self.entries {
+			match channels.iter_mut().find(|(name, _)| *name == entry.channel) {
+				Some((_, bytes_per_sec)) => *bytes_per_sec += entry.bytes_per_sec,
+				None => channels.push((&entry.channel, entry.bytes_per_sec)),
+			}
+		}
+		channels.sort_by(|a, b| b.1.total_cmp(&a.1));
+		channels
+	}
+
+	pub fn total_bytes_per_sec(&self) -> f32 {
+		self.entries.iter().map(|entry| entry.bytes_per_sec).sum()
+	}
+}
+
+impl fmt::Display for BandwidthReport {
+	/// A table of the entries, with each one's share of the total
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let total = self.total_bytes_per_sec();
+		writeln!(f, "{:>10} {:>6} {:>8}  channel/message", "B/s", "share", "msg/s")?;
+		for entry in &self.entries {
+			let share = match total > 0.0 {
+				true => 100.0 * entry.bytes_per_sec / total,
+				false => 0.0,
+			};
+			writeln!(
+				f, "{:>10.1} {:>5.1}% {:>8.1}  {}/{}",
+				entry.bytes_per_sec, share, entry.msgs_per_sec, entry.channel, entry.message,
+			)?;
+		}
+		write!(f, "{total:>10.1} total over {:?}", self.window)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn merge() {
+		let entry = |message: &str, bytes_per_sec: f32| BandwidthEntry {
+			channel: "Gameplay".to_string(),
+			message: message.to_string(),
+			bytes_per_sec,
+			msgs_per_sec: 1.0,
+		};
+		let mut report = BandwidthReport { window: Duration::from_secs(1), entries: vec![entry("Input", 10.0)] };
+		report.merge(BandwidthReport { window: Duration::from_secs(1), entries: vec![entry("Chat", 15.0), entry("Input", 10.0)] });
+
+		assert_eq!(report.entries, [
+			BandwidthEntry { msgs_per_sec: 2.0, ..entry("Input", 20.0) },
+			entry("Chat", 15.0),
+		]);
+		assert_eq!(report.channels(), [("Gameplay", 35.0)]);
+		assert!(report.to_string().ends_with("35.0 total over 1s"));
+	}
+}
@@ -1,2 +1,15 @@
+mod bandwidth;
+mod rate_counter;
 mod rolling_window;
-pub use rolling_window::*;
\ No newline at end of file
+mod sink;
+mod snapshot;
+pub use bandwidth::*;
+pub use rate_counter::*;
+pub use rolling_window::*;
+pub use sink::*;
+pub use snapshot::*;
+
+use std::time::Duration;
+
+/// The window over which connections measure their rates
+pub(crate) const RATE_WINDOW_SIZE: Duration = Duration::from_secs(1);
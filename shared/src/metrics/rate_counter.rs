@@ -0,0 +1,66 @@
+use crate::Instant;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of buckets per window. Amounts added within the same bucket are summed, so
+/// memory use doesn't grow with the rate.
+const BUCKETS: u32 = 16;
+
+/// Measure the rate of events (or bytes) per second over a sliding window
+pub struct RateCounter {
+	buckets: VecDeque<(Instant, u64)>,
+	duration: Duration,
+}
+
+impl RateCounter {
+	pub fn new(duration: Duration) -> Self {
+		RateCounter { buckets: VecDeque::new(), duration }
+	}
+
+	pub fn add(&mut self, amount: u64) {
+		// trim expired buckets
+		while let Some((ts, _)) = self.buckets.front() {
+			if ts.elapsed() <= self.duration {
+				break;
+			}
+
+			self.buckets.pop_front();
+		}
+
+		match self.buckets.back_mut() {
+			Some((ts, sum)) if ts.elapsed() < self.duration / BUCKETS => *sum += amount,
+			_ => self.buckets.push_back((Instant::now(), amount)),
+		}
+	}
+
+	/// The rate over the window, ignoring buckets which expired since the last `add()`
+	pub fn per_sec(&self) -> f32 {
+		let sum: u64 = self.buckets.iter()
+			.filter(|(ts, _)| ts.elapsed() <= self.duration)
+			.map(|(_, sum)| sum)
+			.sum();
+		sum as f32 / self.duration.as_secs_f32()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn per_sec() {
+		let mut rate = RateCounter::new(Duration::from_millis(100));
+		assert_eq!(rate.per_sec(), 0.0);
+
+		rate.add(10);
+		rate.add(20);
+		assert_eq!(rate.buckets.len(), 1);
+		assert_eq!(rate.per_sec().round(), 300.0);
+
+		std::thread::sleep(Duration::from_millis(120));
+		assert_eq!(rate.per_sec(), 0.0);
+		rate.add(5);
+		assert_eq!(rate.buckets.len(), 1);
+		assert_eq!(rate.per_sec().round(), 50.0);
+	}
+}
@@ -1,5 +1,6 @@
+use crate::Instant;
 use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 /// Calculate metrics over a rolling window of samples. Stale samples are dropped when
 /// new samples are added, so metric values may be inaccurate if significant time passes
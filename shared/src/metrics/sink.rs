@@ -0,0 +1,200 @@
+use crate::Timer;
+use log::{info, warn};
+use std::{fmt::Write, time::Duration};
+use super::MetricsSnapshot;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{collections::HashMap, io, net::{SocketAddr, UdpSocket}};
+
+/// Receives the performance counters of a Server or Client each time they are reported;
+/// see `Server::set_metrics_sink()` and `Client::set_metrics_sink()`. Counters are
+/// cumulative, gauges are instantaneous values such as rates or round trip times.
+pub trait MetricsSink: Send {
+	fn record_counter(&mut self, name: &str, labels: &[(&str, &str)], value: u64);
+
+	fn record_gauge(&mut self, name: &str, labels: &[(&str, &str)], value: f64);
+
+	/// Called after all values of a report were recorded
+	fn flush(&mut self) {}
+}
+
+/// Calls a MetricsSink at a fixed interval
+pub struct MetricsReporter {
+	sink: Box<dyn MetricsSink>,
+	timer: Timer,
+}
+
+impl MetricsReporter {
+	pub fn new(sink: Box<dyn MetricsSink>, interval: Duration) -> Self {
+		Self { sink, timer: Timer::new(interval) }
+	}
+
+	/// Returns whether a report is due, restarting the interval if it is
+	pub fn due(&mut self) -> bool {
+		self.timer.try_reset()
+	}
+
+	/// Record the counters of `snapshot` and the given (name, labels, value) gauges
+	pub fn report(&mut self, snapshot: &MetricsSnapshot, gauges: &[(&str, &[(&str, &str)], f64)]) {
+		for (name, value) in snapshot.counters() {
+			self.sink.record_counter(name, &[], value);
+		}
+		for (name, labels, value) in gauges {
+			self.sink.record_gauge(name, labels, *value);
+		}
+		self.sink.flush();
+	}
+
+	pub fn into_sink(self) -> Box<dyn MetricsSink> {
+		self.sink
+	}
+}
+
+fn write_labels(out: &mut String, labels: &[(&str, &str)], open: &str, sep: char, close: &str) {
+	if labels.is_empty() {
+		return;
+	}
+
+	out.push_str(open);
+	for (i, (key, value)) in labels.iter().enumerate() {
+		if i > 0 {
+			out.push(',');
+		}
+		let _ = write!(out, "{key}{sep}{value}");
+	}
+	out.push_str(close);
+}
+
+/// Writes each value to the `log` crate at info level, as `prefix.name{key=value} value`
+pub struct LogSink {
+	prefix: String,
+}
+
+impl LogSink {
+	pub fn new(prefix: &str) -> Self {
+		Self { prefix: prefix.to_string() }
+	}
+
+	fn log(&self, name: &str, labels: &[(&str, &str)], value: &dyn std::fmt::Display) {
+		let mut line = format!("{}{name}", self.prefix);
+		write_labels(&mut line, labels, "{", '=', "}");
+		info!("{line} {value}");
+	}
+}
+
+impl MetricsSink for LogSink {
+	fn record_counter(&mut self, name: &str, labels: &[(&str, &str)], value: u64) {
+		self.log(name, labels, &value);
+	}
+
+	fn record_gauge(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+		self.log(name, labels, &value);
+	}
+}
+
+/// Max payload of a single statsd datagram, small enough to avoid IP fragmentation
+#[cfg(not(target_arch = "wasm32"))]
+const STATSD_MAX_DATAGRAM: usize = 1432;
+
+/// Sends each value to a statsd server over UDP. Labels are sent as DogStatsD style
+/// `|#key:value` tags. Since statsd counters are increments, counters are sent as the
+/// change since the previous report.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct StatsdSink {
+	socket: UdpSocket,
+	prefix: String,
+	/// The last reported value of each counter line, keyed by name and tags
+	counters: HashMap<String, u64>,
+	buffer: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl StatsdSink {
+	pub fn new(addr: SocketAddr, prefix: &str) -> io::Result<Self> {
+		let local: SocketAddr = match addr {
+			SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+			SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+		};
+		let socket = UdpSocket::bind(local)?;
+		socket.connect(addr)?;
+		socket.set_nonblocking(true)?;
+
+		Ok(Self {
+			socket,
+			prefix: prefix.to_string(),
+			counters: HashMap::new(),
+			buffer: String::new(),
+		})
+	}
+
+	fn push(&mut self, line: &str) {
+		if !self.buffer.is_empty() && self.buffer.len() + 1 + line.len() > STATSD_MAX_DATAGRAM {
+			self.flush();
+		}
+		if !self.buffer.is_empty() {
+			self.buffer.push('\n');
+		}
+		self.buffer.push_str(line);
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MetricsSink for StatsdSink {
+	fn record_counter(&mut self, name: &str, labels: &[(&str, &str)], value: u64) {
+		let mut key = format!("{}{name}", self.prefix);
+		write_labels(&mut key, labels, "|#", ':', "");
+
+		// counters which decreased (e.g. after users disconnected) restart from 0
+		let last = self.counters.insert(key.clone(), value).unwrap_or(0);
+		let delta = value.checked_sub(last).unwrap_or(value);
+		let (name, tags) = key.split_at(key.find('|').unwrap_or(key.len()));
+		self.push(&format!("{name}:{delta}|c{tags}"));
+	}
+
+	fn record_gauge(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+		let mut line = format!("{}{name}:{value}|g", self.prefix);
+		write_labels(&mut line, labels, "|#", ':', "");
+		self.push(&line);
+	}
+
+	fn flush(&mut self) {
+		if self.buffer.is_empty() {
+			return;
+		}
+
+		if let Err(e) = self.socket.send(self.buffer.as_bytes()) {
+			warn!("Failed to send metrics to statsd: {e}");
+		}
+		self.buffer.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn statsd() {
+		let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+		server.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+		let mut sink = StatsdSink::new(server.local_addr().unwrap(), "naia.").unwrap();
+
+		let mut buf = [0; STATSD_MAX_DATAGRAM];
+		let mut recv = || {
+			let len = server.recv(&mut buf).unwrap();
+			String::from_utf8(buf[..len].to_vec()).unwrap()
+		};
+
+		sink.record_counter("pkt_rx_count", &[], 5);
+		sink.record_gauge("rtt_ms", &[("user", "1"), ("region", "eu")], 12.5);
+		sink.flush();
+		assert_eq!(recv(), "naia.pkt_rx_count:5|c\nnaia.rtt_ms:12.5|g|#user:1,region:eu");
+
+		sink.record_counter("pkt_rx_count", &[], 8);
+		sink.flush();
+		assert_eq!(recv(), "naia.pkt_rx_count:3|c");
+
+		sink.record_counter("pkt_rx_count", &[], 2);
+		sink.flush();
+		assert_eq!(recv(), "naia.pkt_rx_count:2|c");
+	}
+}
@@ -0,0 +1,112 @@
+use crate::Instant;
+use std::time::Duration;
+
+macro_rules! snapshot {
+	($($counter:ident),* $(,)?) => {
+		/// The performance counters of a Server or Client at a point in time. See the
+		/// getters of the same names; `msg_rx_observer_drop_count`, `msg_rx_reject_count`
+		/// and `msg_rx_throttle_count` are always 0 for Clients.
+		#[derive(Clone, Debug, PartialEq)]
+		pub struct MetricsSnapshot {
+			pub taken: Instant,
+			$(pub $counter: u64,)*
+		}
+
+		/// The per-second rates of the performance counters between two snapshots
+		#[derive(Clone, Debug, Default, PartialEq)]
+		pub struct MetricsRates {
+			pub elapsed: Duration,
+			$(pub $counter: f32,)*
+		}
+
+		impl MetricsSnapshot {
+			/// The name and value of each counter
+			pub fn counters(&self) -> Vec<(&'static str, u64)> {
+				vec![$((stringify!($counter), self.$counter),)*]
+			}
+
+			/// The rates since the `older` snapshot. Counters summed over connections
+			/// decrease when users disconnect, and have a rate of 0 then.
+			pub fn diff(&self, older: &Self) -> MetricsRates {
+				let elapsed = self.taken.duration_since(older.taken);
+				let secs = elapsed.as_secs_f32();
+				if secs == 0.0 {
+					return MetricsRates { elapsed, ..MetricsRates::default() };
+				}
+				MetricsRates {
+					elapsed,
+					$($counter: self.$counter.saturating_sub(older.$counter) as f32 / secs,)*
+				}
+			}
+
+			/// The counts accumulated since the `baseline` snapshot
+			pub fn since(&self, baseline: &Self) -> Self {
+				Self {
+					taken: self.taken,
+					$($counter: self.$counter.saturating_sub(baseline.$counter),)*
+				}
+			}
+		}
+	};
+}
+
+snapshot!(
+	bytes_rx,
+	bytes_tx,
+	frag_rx_expired_count,
+	frag_rx_refused_count,
+	msg_rx_count,
+	msg_rx_drop_count,
+	msg_rx_miss_count,
+	msg_rx_observer_drop_count,
+	msg_rx_reject_count,
+	msg_rx_throttle_count,
+	msg_tx_count,
+	msg_tx_queue_count,
+	pkt_rx_count,
+	pkt_rx_decrypt_fail_count,
+	pkt_tx_count,
+);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn snapshot(taken: Instant, msg_rx_count: u64) -> MetricsSnapshot {
+		MetricsSnapshot {
+			taken,
+			bytes_rx: 1000,
+			bytes_tx: 0,
+			frag_rx_expired_count: 0,
+			frag_rx_refused_count: 0,
+			msg_rx_count,
+			msg_rx_drop_count: 0,
+			msg_rx_miss_count: 0,
+			msg_rx_observer_drop_count: 0,
+			msg_rx_reject_count: 0,
+			msg_rx_throttle_count: 0,
+			msg_tx_count: 0,
+			msg_tx_queue_count: 0,
+			pkt_rx_count: 0,
+			pkt_rx_decrypt_fail_count: 0,
+			pkt_tx_count: 0,
+		}
+	}
+
+	#[test]
+	fn diff() {
+		let now = Instant::now();
+		let older = snapshot(now, 10);
+		let newer = snapshot(now + Duration::from_secs(2), 30);
+
+		let rates = newer.diff(&older);
+		assert_eq!(rates.elapsed, Duration::from_secs(2));
+		assert_eq!((rates.msg_rx_count, rates.bytes_rx), (10.0, 0.0));
+		// counters which decreased
+		assert_eq!(older.diff(&newer), MetricsRates::default());
+
+		let since = newer.since(&older);
+		assert_eq!((since.msg_rx_count, since.bytes_rx), (20, 0));
+		assert_eq!(since.counters()[4], ("msg_rx_count", 20));
+	}
+}
@@ -0,0 +1,216 @@
+use crate::{ChannelDirection, ChannelMode};
+use naia_serde::ConstBitLength;
+use std::{fmt::Write, marker::PhantomData};
+
+/// A machine-readable description of a protocol's channels and message layouts, for
+/// generating external tools (clients in other languages, packet analyzers, etc.)
+#[derive(Clone, Debug)]
+pub struct ProtocolSpec {
+	/// Width of the channel id preceding each channel's messages in a data packet
+	pub channel_kind_bits: u32,
+	/// Width of the message id preceding each message
+	pub message_kind_bits: u32,
+	pub channels: Vec<ChannelSpec>,
+	pub messages: Vec<MessageSpec>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChannelSpec {
+	/// Id written on the wire
+	pub id: u16,
+	pub name: String,
+	pub mode: ChannelMode,
+	pub direction: ChannelDirection,
+}
+
+#[derive(Clone, Debug)]
+pub struct MessageSpec {
+	/// Id written on the wire
+	pub id: u16,
+	pub name: String,
+	/// Whether the fields are compressed together, in which case they are preceded by
+	/// the compression header and their widths describe the decompressed layout
+	pub compressed: bool,
+	/// Fields, in wire order
+	pub fields: Vec<FieldSpec>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldSpec {
+	/// Field name, or index for tuple structs
+	pub name: String,
+	/// Rust type, as written in the Message definition
+	pub type_name: String,
+	/// Width of the field in bits, or `None` if it is variable
+	pub bit_width: Option<u32>,
+}
+
+impl FieldSpec {
+	pub fn new(name: &str, type_name: &str, bit_width: Option<u32>) -> Self {
+		Self { name: name.to_string(), type_name: type_name.to_string(), bit_width }
+	}
+}
+
+impl ProtocolSpec {
+	/// Serialize to a JSON document
+	pub fn to_json(&self) -> String {
+		let mut out = String::new();
+		write!(
+			out, "{{\"channel_kind_bits\":{},\"message_kind_bits\":{},\"channels\":[",
+			self.channel_kind_bits, self.message_kind_bits,
+		).unwrap();
+		for (i, channel) in self.channels.iter().enumerate() {
+			if i > 0 { out.push(',') }
+			write!(
+				out, "{{\"id\":{},\"name\":{},\"mode\":{},\"direction\":{}}}",
+				channel.id,
+				json_string(&channel.name),
+				json_string(&format!("{:?}", channel.mode)),
+				json_string(&format!("{:?}", channel.direction)),
+			).unwrap();
+		}
+		out.push_str("],\"messages\":[");
+		for (i, message) in self.messages.iter().enumerate() {
+			if i > 0 { out.push(',') }
+			write!(out, "{{\"id\":{},\"name\":{},", message.id, json_string(&message.name)).unwrap();
+			if message.compressed {
+				out.push_str("\"compressed\":true,");
+			}
+			out.push_str("\"fields\":[");
+			for (j, field) in message.fields.iter().enumerate() {
+				if j > 0 { out.push(',') }
+				let bit_width = match field.bit_width {
+					Some(bits) => bits.to_string(),
+					None => "null".to_string(),
+				};
+				write!(
+					out, "{{\"name\":{},\"type\":{},\"bit_width\":{}}}",
+					json_string(&field.name), json_string(&field.type_name), bit_width,
+				).unwrap();
+			}
+			out.push_str("]}");
+		}
+		out.push_str("]}");
+		out
+	}
+}
+
+fn json_string(value: &str) -> String {
+	let mut out = String::with_capacity(value.len() + 2);
+	out.push('"');
+	for c in value.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	out
+}
+
+/// Returns the name of a type without its module path
+pub(crate) fn short_type_name<T: ?Sized>() -> String {
+	let name = std::any::type_name::<T>();
+	name.rsplit("::").next().unwrap_or(name).to_string()
+}
+
+// Lets the Message derive report the width of fields implementing ConstBitLength, and
+// fall back to `None` for all others. Calling `(&&BitWidthProbe::<T>::new()).bit_width()`
+// resolves to FixedBitWidth when it applies, since it needs one less auto-deref.
+
+#[doc(hidden)]
+pub struct BitWidthProbe<T>(PhantomData<T>);
+
+impl<T> BitWidthProbe<T> {
+	pub fn new() -> Self { Self(PhantomData) }
+}
+
+#[doc(hidden)]
+pub trait FixedBitWidth {
+	fn bit_width(&self) -> Option<u32>;
+}
+
+impl<T: ConstBitLength> FixedBitWidth for &BitWidthProbe<T> {
+	fn bit_width(&self) -> Option<u32> { Some(T::const_bit_length()) }
+}
+
+#[doc(hidden)]
+pub trait VariableBitWidth {
+	fn bit_width(&self) -> Option<u32>;
+}
+
+impl<T> VariableBitWidth for BitWidthProbe<T> {
+	fn bit_width(&self) -> Option<u32> { None }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Channel, Schema};
+	use naia_derive::MessageInternal;
+
+	#[derive(Channel)]
+	struct Updates;
+
+	#[derive(MessageInternal)]
+	struct Position {
+		x: i16,
+		y: i16,
+		name: String,
+	}
+
+	#[derive(MessageInternal)]
+	struct Score(u32);
+
+	#[test]
+	fn describe() {
+		let spec = Schema::builder()
+			.add_channel::<Updates>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+			.add_message::<Position>()
+			.add_message::<Score>()
+			.build()
+			.describe();
+
+		assert_eq!(spec.channels.len(), 1);
+		assert_eq!(spec.channels[0].id, 0);
+		assert_eq!(spec.channels[0].name, "Updates");
+
+		// FragmentedMessage is always registered first
+		assert_eq!(spec.messages.len(), 3);
+		assert_eq!(spec.messages[0].name, "FragmentedMessage");
+		assert_eq!(spec.messages[1].id, 1);
+		assert_eq!(spec.messages[1].name, "Position");
+		assert_eq!(spec.messages[1].fields, vec![
+			FieldSpec::new("x", "i16", Some(16)),
+			FieldSpec::new("y", "i16", Some(16)),
+			FieldSpec::new("name", "String", None),
+		]);
+		assert_eq!(spec.messages[2].fields, vec![FieldSpec::new("0", "u32", Some(32))]);
+	}
+
+	#[test]
+	fn to_json() {
+		let spec = Schema::builder()
+			.add_channel::<Updates>(ChannelDirection::Bidirectional, ChannelMode::UnorderedUnreliable)
+			.add_message::<Score>()
+			.build()
+			.describe();
+
+		let json = spec.to_json();
+		assert!(json.starts_with("{\"channel_kind_bits\":0,\"message_kind_bits\":16,"));
+		assert!(json.contains(
+			"{\"id\":0,\"name\":\"Updates\",\"mode\":\"UnorderedUnreliable\",\"direction\":\"Bidirectional\"}"
+		));
+		assert!(json.contains(
+			"{\"id\":1,\"name\":\"Score\",\"fields\":[{\"name\":\"0\",\"type\":\"u32\",\"bit_width\":32}]}"
+		));
+		assert!(json.contains("\"bit_width\":null"));
+	}
+
+	#[test]
+	fn escape() {
+		assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+	}
+}
@@ -1,14 +1,19 @@
 use crate::{
     messages::{
         channels::{
-            channel::{Channel, ChannelDirection, ChannelMode, ChannelSettings},
-            channel_kinds::ChannelKinds,
+            channel::{Channel, ChannelDirection, ChannelMode, ChannelSettings, PausePolicy, RateLimit, ResendConfig},
+            channel_kinds::{ChannelKind, ChannelKinds},
         },
+        checksum::{ChecksumChannel, StateChecksum},
         fragment::FragmentedMessage,
         message::Message,
-        message_kinds::MessageKinds,
+        message_kinds::{MessageKind, MessageKinds},
+        sync::{SyncChannel, SyncChunk, SyncDone},
     },
+    protocol_spec::ProtocolSpec,
+    schema_manifest::{SchemaDiff, SchemaManifest},
 };
+use naia_serde::ConstBitLength;
 
 pub struct Schema {
     channel_kinds: ChannelKinds,
@@ -31,8 +36,83 @@ impl Schema {
 	pub fn builder() -> SchemaBuilder { SchemaBuilder::new() }
 	pub fn channel_kinds(&self) -> &ChannelKinds { &self.channel_kinds }
 	pub fn message_kinds(&self) -> &MessageKinds { &self.message_kinds }
+
+	/// Whether `SchemaBuilder::enable_sync()` was called
+	pub fn has_sync(&self) -> bool {
+		self.channel_kinds.channels().iter().any(|(kind, _)| *kind == ChannelKind::of::<SyncChannel>())
+	}
+
+	/// Whether `SchemaBuilder::enable_checksums()` was called
+	pub fn has_checksums(&self) -> bool {
+		self.channel_kinds.channels().iter().any(|(kind, _)| *kind == ChannelKind::of::<ChecksumChannel>())
+	}
+
+	/// The channels added to `group` with `SchemaBuilder::channel_group()`, in
+	/// registration order
+	pub fn channel_group(&self, group: &str) -> Vec<ChannelKind> { self.channel_kinds.group(group) }
+
+	/// The width of the channel id preceding each channel's messages in a data packet
+	pub fn channel_kind_bits(&self) -> u32 { self.channel_kinds.bit_length() }
+
+	/// The width of the message id preceding each message
+	pub fn message_kind_bits(&self) -> u32 { MessageKind::const_bit_length() }
+
+	/// A hash of the channels and message layouts, and their ids, i.e. of everything
+	/// both hosts must agree on to decode data packets. Ids on the wire are assigned in
+	/// registration order, unless explicit, so Clients with a different fingerprint are
+	/// rejected with `RejectReason::Version`. Channel directions are left out, as
+	/// misuse is reported instead; see `NaiaError::ChannelMisuse`.
+	pub fn fingerprint(&self) -> u64 {
+		let spec = self.describe();
+		let mut text = String::new();
+		for channel in &spec.channels {
+			text += &format!("{}:{:?};", channel.name, channel.mode);
+		}
+		for message in &spec.messages {
+			text += &format!("{}={}:{}", message.id, message.name, message.compressed);
+			for field in &message.fields {
+				text += &format!(",{}:{}", field.name, field.type_name);
+			}
+			text += ";";
+		}
+
+		// FNV-1a, stable across builds and platforms
+		text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+			(hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+		})
+	}
+
+	/// Describes the channels and message layouts of this Schema, e.g. for generating
+	/// external tools. See `ProtocolSpec::to_json()`.
+	pub fn describe(&self) -> ProtocolSpec {
+		ProtocolSpec {
+			channel_kind_bits: self.channel_kind_bits(),
+			message_kind_bits: self.message_kind_bits(),
+			channels: self.channel_kinds.describe(),
+			messages: self.message_kinds.describe().to_vec(),
+		}
+	}
+
+	/// A plain text snapshot of the channels and messages, to compare later versions
+	/// against with `diff()`
+	pub fn manifest(&self) -> SchemaManifest { SchemaManifest::of(self) }
+
+	/// The channels and messages added, removed or changed since `before`, e.g. a
+	/// manifest checked in with an earlier version; see `assert_schema_manifest!()`
+	pub fn diff(&self, before: &SchemaManifest) -> SchemaDiff {
+		SchemaDiff::new(before, &self.manifest())
+	}
 }
 
+/// A reusable set of channels and messages, e.g. a chat or voice module, which can be
+/// added to an application's Schema with `SchemaBuilder::add_plugin()`
+pub trait ProtocolPlugin {
+	/// Register this plugin's channels and messages
+	fn build(&self, builder: SchemaBuilder) -> SchemaBuilder;
+}
+
+/// Builds a Schema. Registering an already registered channel or message type has no
+/// effect, so plugins may share types.
 pub struct SchemaBuilder {
 	schema: Schema,
 }
@@ -50,10 +130,296 @@ impl SchemaBuilder {
         self
     }
 
+    /// Like `add_channel()`, but observers may also send on this channel. Messages
+    /// from observers on all other channels are dropped by the Server.
+    pub fn add_observer_channel<C: Channel>(
+		mut self, direction: ChannelDirection, mode: ChannelMode,
+    ) -> Self {
+		let settings = ChannelSettings { observer_send: true, ..ChannelSettings::new(mode, direction) };
+		self.schema.channel_kinds.add_channel::<C>(settings);
+        self
+    }
+
+    /// Tune retransmissions on a reliable channel registered earlier. This only
+    /// affects the local host, so hosts may use different settings.
+    pub fn resend_config<C: Channel>(mut self, resend: ResendConfig) -> Self {
+		let settings = self.schema.channel_kinds.channel_mut(&ChannelKind::of::<C>());
+		debug_assert!(
+			settings.as_ref().is_some_and(|settings| settings.reliable()),
+			"resend config for an unknown or unreliable channel",
+		);
+		if let Some(settings) = settings {
+			settings.resend = resend;
+		}
+        self
+    }
+
+    /// Choose what happens to messages queued on a channel registered earlier while it
+    /// is paused. Defaults to `PausePolicy::default_for()` the channel's mode.
+    pub fn pause_policy<C: Channel>(mut self, pause: PausePolicy) -> Self {
+		let settings = self.schema.channel_kinds.channel_mut(&ChannelKind::of::<C>());
+		debug_assert!(settings.is_some(), "pause policy for an unknown channel");
+		if let Some(settings) = settings {
+			settings.pause = pause;
+		}
+        self
+    }
+
+    /// Cap the messages each Client may send on a channel registered earlier, e.g. to
+    /// protect the Server from flooding. The excess is dropped and reported by
+    /// `ServerEvent::Throttled`. This only affects the Server, so Clients may omit it.
+    pub fn rate_limit<C: Channel>(mut self, limit: RateLimit) -> Self {
+		let settings = self.schema.channel_kinds.channel_mut(&ChannelKind::of::<C>());
+		debug_assert!(
+			settings.as_ref().is_some_and(|settings| settings.can_send_to_server()),
+			"rate limit for an unknown or server-to-client channel",
+		);
+		if let Some(settings) = settings {
+			settings.rate_limit = Some(limit);
+		}
+        self
+    }
+
+    /// Add channel `C`, registered earlier, to `group`, so that related channels can be
+    /// configured with `group_resend_config()`, `group_pause_policy()` and
+    /// `group_rate_limit()`, and paused with `Server::pause_group()`. A channel belongs to at most one group.
+    pub fn channel_group<C: Channel>(mut self, group: &'static str) -> Self {
+		let settings = self.schema.channel_kinds.channel_mut(&ChannelKind::of::<C>());
+		debug_assert!(settings.is_some(), "channel group for an unknown channel");
+		if let Some(settings) = settings {
+			settings.group = Some(group);
+		}
+        self
+    }
+
+    /// Like `resend_config()`, for the reliable channels of `group`
+    pub fn group_resend_config(mut self, group: &str, resend: ResendConfig) -> Self {
+		for kind in self.schema.channel_kinds.group(group) {
+			let settings = self.schema.channel_kinds.channel_mut(&kind).unwrap();
+			if settings.reliable() {
+				settings.resend = resend.clone();
+			}
+		}
+        self
+    }
+
+    /// Like `pause_policy()`, for all channels of `group`
+    pub fn group_pause_policy(mut self, group: &str, pause: PausePolicy) -> Self {
+		for kind in self.schema.channel_kinds.group(group) {
+			self.schema.channel_kinds.channel_mut(&kind).unwrap().pause = pause;
+		}
+        self
+    }
+
+    /// Like `rate_limit()`, for the client-to-server channels of `group`, each limited
+    /// on its own
+    pub fn group_rate_limit(mut self, group: &str, limit: RateLimit) -> Self {
+		for kind in self.schema.channel_kinds.group(group) {
+			let settings = self.schema.channel_kinds.channel_mut(&kind).unwrap();
+			if settings.can_send_to_server() {
+				settings.rate_limit = Some(limit);
+			}
+		}
+        self
+    }
+
+    /// Refuse messages larger than `max` encoded bytes. Sending one emits a
+    /// `NaiaError::MessageTooLarge` error event instead, and receiving one disconnects
+    /// the remote host. Fields of `#[message(compress)]` messages from the remote host
+    /// may not decompress to more than `max` bytes either.
+    pub fn max_message_bytes(mut self, max: usize) -> Self {
+		self.schema.message_kinds.set_max_message_bytes(max);
+        self
+    }
+
+    /// Register message `M`. Its id on the wire depends on the order messages are
+    /// registered in, unless given with `#[message(id = N)]`.
     pub fn add_message<M: Message>(mut self) -> Self {
 		self.schema.message_kinds.add_message::<M>();
         self
     }
 
+    /// Register message `M` with an explicit id, so that adding or reordering other
+    /// messages doesn't change it, e.g. to stay compatible with older Clients. Panics if
+    /// another message was given the same id explicitly.
+    pub fn add_message_with_id<M: Message>(mut self, id: u16) -> Self {
+		self.schema.message_kinds.add_message_with_id::<M>(id);
+        self
+    }
+
+    /// Register the channels and messages of the given plugin. Plugins must be added
+    /// in the same order on all hosts, like everything else.
+    pub fn add_plugin<P: ProtocolPlugin>(self, plugin: &P) -> Self {
+		plugin.build(self)
+    }
+
+    /// Register the channel and messages used to stream a snapshot to each Client
+    /// after it is accepted; see `Server::set_snapshot_provider()`
+    pub fn enable_sync(self) -> Self {
+		self.add_observer_channel::<SyncChannel>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+			.add_message::<SyncChunk>()
+			.add_message::<SyncDone>()
+    }
+
+    /// Register the channel and message used by Clients to report a hash of their
+    /// state for each tick, so the Server can detect desyncs; see
+    /// `Client::send_checksum()`
+    pub fn enable_checksums(self) -> Self {
+		self.add_channel::<ChecksumChannel>(ChannelDirection::ClientToServer, ChannelMode::UnorderedReliable)
+			.add_message::<StateChecksum>()
+    }
+
 	pub fn build(self) -> Schema { self.schema }
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Channel;
+	use naia_derive::MessageInternal;
+
+	#[derive(Channel)]
+	struct ChatChannel;
+
+	#[derive(Channel)]
+	struct VoiceChannel;
+
+	#[derive(MessageInternal)]
+	struct Text(String);
+
+	#[derive(MessageInternal)]
+	struct Mute(u16);
+
+	#[derive(MessageInternal)]
+	#[message(id = 100)]
+	struct Ping;
+
+	struct ChatPlugin;
+
+	impl ProtocolPlugin for ChatPlugin {
+		fn build(&self, builder: SchemaBuilder) -> SchemaBuilder {
+			builder
+				.add_channel::<ChatChannel>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+				.add_message::<Text>()
+				.add_message::<Mute>()
+		}
+	}
+
+	struct VoicePlugin;
+
+	impl ProtocolPlugin for VoicePlugin {
+		fn build(&self, builder: SchemaBuilder) -> SchemaBuilder {
+			builder
+				.add_channel::<VoiceChannel>(ChannelDirection::Bidirectional, ChannelMode::UnorderedUnreliable)
+				.add_message::<Mute>()
+		}
+	}
+
+	#[test]
+	fn plugins() {
+		let spec = Schema::builder()
+			.add_plugin(&ChatPlugin)
+			.add_plugin(&VoicePlugin)
+			.build()
+			.describe();
+
+		let channels: Vec<_> = spec.channels.iter().map(|c| c.name.as_str()).collect();
+		assert_eq!(channels, ["ChatChannel", "VoiceChannel"]);
+
+		// the shared message is only registered once
+		let messages: Vec<_> = spec.messages.iter().map(|m| m.name.as_str()).collect();
+		assert_eq!(messages, ["FragmentedMessage", "Text", "Mute"]);
+	}
+
+	#[test]
+	fn channel_kind_bits() {
+		assert_eq!(Schema::builder().build().channel_kind_bits(), 0);
+		let chat = Schema::builder().add_plugin(&ChatPlugin).build();
+		assert_eq!(chat.channel_kind_bits(), 0);
+		let both = Schema::builder().add_plugin(&ChatPlugin).add_plugin(&VoicePlugin).build();
+		assert_eq!(both.channel_kind_bits(), 1);
+		let synced = Schema::builder().add_plugin(&ChatPlugin).add_plugin(&VoicePlugin).enable_sync().build();
+		assert_eq!(synced.channel_kind_bits(), 2);
+	}
+
+	#[test]
+	fn fingerprint() {
+		let chat_voice = Schema::builder().add_plugin(&ChatPlugin).add_plugin(&VoicePlugin).build();
+		let voice_chat = Schema::builder().add_plugin(&VoicePlugin).add_plugin(&ChatPlugin).build();
+		let again = Schema::builder().add_plugin(&ChatPlugin).add_plugin(&VoicePlugin).build();
+		assert_eq!(chat_voice.fingerprint(), again.fingerprint());
+		assert_ne!(chat_voice.fingerprint(), voice_chat.fingerprint());
+	}
+
+	fn ids(schema: &Schema) -> Vec<(u16, String)> {
+		schema.describe().messages.into_iter().map(|m| (m.id, m.name)).collect()
+	}
+
+	#[test]
+	fn explicit_ids() {
+		let schema = Schema::builder()
+			.add_message::<Ping>()
+			.add_message::<Text>()
+			.add_message_with_id::<Mute>(1)
+			.build();
+		// Text makes way for Mute
+		let expected = [(0, "FragmentedMessage"), (1, "Mute"), (2, "Text"), (100, "Ping")];
+		let expected: Vec<_> = expected.iter().map(|(id, name)| (*id, name.to_string())).collect();
+		assert_eq!(ids(&schema), expected);
+
+		// explicit ids don't depend on the registration order
+		let reordered = Schema::builder()
+			.add_message_with_id::<Mute>(1)
+			.add_message::<Text>()
+			.add_message::<Ping>()
+			.build();
+		assert_eq!(ids(&reordered), expected);
+		assert_eq!(schema.fingerprint(), reordered.fingerprint());
+
+		// an implicitly registered message may be given an explicit id later
+		let plugin = Schema::builder().add_plugin(&ChatPlugin).add_message_with_id::<Text>(5).build();
+		assert_eq!(ids(&plugin)[1..], [(2, "Mute".to_string()), (5, "Text".to_string())]);
+	}
+
+	#[test]
+	#[should_panic(expected = "both registered with id 100")]
+	fn colliding_ids() {
+		Schema::builder().add_message::<Ping>().add_message_with_id::<Text>(100);
+	}
+
+	#[test]
+	#[should_panic(expected = "Text registered with ids 5 and 6")]
+	fn conflicting_ids() {
+		Schema::builder().add_message_with_id::<Text>(5).add_message_with_id::<Text>(6);
+	}
+
+	#[test]
+	#[should_panic(expected = "channel ChatChannel registered with different settings")]
+	fn conflicting_channels() {
+		Schema::builder()
+			.add_plugin(&ChatPlugin)
+			.add_channel::<ChatChannel>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable);
+	}
+
+	#[test]
+	fn channel_groups() {
+		let resend = ResendConfig { backoff: 2.0, ..ResendConfig::default() };
+		let schema = Schema::builder()
+			.add_plugin(&ChatPlugin)
+			.add_plugin(&VoicePlugin)
+			.channel_group::<VoiceChannel>("social")
+			.channel_group::<ChatChannel>("social")
+			.group_resend_config("social", resend)
+			.group_pause_policy("social", PausePolicy::Drop)
+			.build();
+
+		let chat = ChannelKind::of::<ChatChannel>();
+		let voice = ChannelKind::of::<VoiceChannel>();
+		assert!(schema.channel_group("social") == [chat, voice]);
+		assert!(schema.channel_group("other").is_empty());
+		assert_eq!(schema.channel_kinds().channel(&chat).resend.backoff, 2.0);
+		// unreliable channels keep their settings
+		assert_eq!(schema.channel_kinds().channel(&voice).resend.backoff, 1.0);
+		assert_eq!(schema.channel_kinds().channel(&chat).pause, PausePolicy::Drop);
+	}
+}
@@ -0,0 +1,301 @@
+use crate::Schema;
+use std::{fmt, path::Path};
+
+/// A channel or message in a SchemaManifest
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ManifestEntry {
+	/// Id written on the wire
+	pub id: u16,
+	pub name: String,
+	/// What both hosts must agree on besides the id: a channel's mode, or whether a
+	/// message is compressed and its fields
+	pub layout: String,
+}
+
+/// A plain text snapshot of a Schema's channels and messages, to check in next to the
+/// protocol and compare later versions against with `Schema::diff()`, catching
+/// accidental wire-breaking changes. One line per channel or message:
+///
+/// ```text
+/// fingerprint 5f1c2a0b9e3d4c71
+/// channel 0 ChatChannel OrderedReliable
+/// message 1 Text 0:String
+/// message 2 MapChunk compressed x:i32,y:i32,tiles:Vec<u8>
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SchemaManifest {
+	/// See `Schema::fingerprint()`
+	pub fingerprint: u64,
+	pub channels: Vec<ManifestEntry>,
+	pub messages: Vec<ManifestEntry>,
+}
+
+/// A line of a SchemaManifest failed to parse
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ManifestError {
+	/// 1-based
+	pub line: usize,
+}
+
+impl fmt::Display for ManifestError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "invalid schema manifest line {}", self.line)
+	}
+}
+
+impl std::error::Error for ManifestError {}
+
+impl SchemaManifest {
+	pub fn of(schema: &Schema) -> Self {
+		let spec = schema.describe();
+		let channels = spec.channels.iter().map(|channel| ManifestEntry {
+			id: channel.id,
+			name: channel.name.clone(),
+			layout: format!("{:?}", channel.mode),
+		});
+		let messages = spec.messages.iter().map(|message| {
+			let fields: Vec<_> = message.fields.iter()
+				.map(|field| format!("{}:{}", field.name, field.type_name))
+				.collect();
+			let fields = if fields.is_empty() { "-".to_string() } else { fields.join(",") };
+			ManifestEntry {
+				id: message.id,
+				name: message.name.clone(),
+				layout: if message.compressed { format!("compressed {fields}") } else { fields },
+			}
+		});
+		Self {
+			fingerprint: schema.fingerprint(),
+			channels: channels.collect(),
+			messages: messages.collect(),
+		}
+	}
+
+	/// Parse a manifest written by `to_string()`. Blank lines and lines starting with
+	/// `#` are ignored.
+	pub fn parse(text: &str) -> Result<Self, ManifestError> {
+		let mut manifest = Self::default();
+		for (i, line) in text.lines().enumerate() {
+			let error = ManifestError { line: i + 1 };
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			let mut parts = line.splitn(4, ' ');
+			let (kind, id) = (parts.next().unwrap(), parts.next().ok_or(error)?);
+			if kind == "fingerprint" {
+				manifest.fingerprint = u64::from_str_radix(id, 16).map_err(|_| error)?;
+				continue;
+			}
+			let entry = ManifestEntry {
+				id: id.parse().map_err(|_| error)?,
+				name: parts.next().ok_or(error)?.to_string(),
+				layout: parts.next().ok_or(error)?.to_string(),
+			};
+			match kind {
+				"channel" => manifest.channels.push(entry),
+				"message" => manifest.messages.push(entry),
+				_ => return Err(error),
+			}
+		}
+		Ok(manifest)
+	}
+}
+
+impl fmt::Display for SchemaManifest {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "fingerprint {:016x}", self.fingerprint)?;
+		for channel in &self.channels {
+			writeln!(f, "channel {} {} {}", channel.id, channel.name, channel.layout)?;
+		}
+		for message in &self.messages {
+			writeln!(f, "message {} {} {}", message.id, message.name, message.layout)?;
+		}
+		Ok(())
+	}
+}
+
+/// The differences between a SchemaManifest and a later Schema, matching channels and
+/// messages by name; see `Schema::diff()`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SchemaDiff {
+	pub added_channels: Vec<ManifestEntry>,
+	pub removed_channels: Vec<ManifestEntry>,
+	/// Channels whose id or mode changed, before and after
+	pub changed_channels: Vec<(ManifestEntry, ManifestEntry)>,
+	pub added_messages: Vec<ManifestEntry>,
+	pub removed_messages: Vec<ManifestEntry>,
+	/// Messages whose id or layout changed, before and after
+	pub changed_messages: Vec<(ManifestEntry, ManifestEntry)>,
+}
+
+impl SchemaDiff {
+	pub(crate) fn new(before: &SchemaManifest, after: &SchemaManifest) -> Self {
+		let (added_channels, removed_channels, changed_channels) =
+			diff(&before.channels, &after.channels);
+		let (added_messages, removed_messages, changed_messages) =
+			diff(&before.messages, &after.messages);
+		Self {
+			added_channels, removed_channels, changed_channels,
+			added_messages, removed_messages, changed_messages,
+		}
+	}
+
+	/// Whether the wire format is unchanged
+	pub fn is_empty(&self) -> bool { *self == Self::default() }
+}
+
+type EntryDiff = (Vec<ManifestEntry>, Vec<ManifestEntry>, Vec<(ManifestEntry, ManifestEntry)>);
+
+fn diff(before: &[ManifestEntry], after: &[ManifestEntry]) -> EntryDiff {
+	let find = |entries: &[ManifestEntry], name: &str| {
+		entries.iter().find(|entry| entry.name == name).cloned()
+	};
+	let added = after.iter().filter(|entry| find(before, &entry.name).is_none()).cloned().collect();
+	let removed = before.iter().filter(|entry| find(after, &entry.name).is_none()).cloned().collect();
+	let changed = before.iter()
+		.filter_map(|old| Some((old.clone(), find(after, &old.name)?)))
+		.filter(|(old, new)| old != new)
+		.collect();
+	(added, removed, changed)
+}
+
+impl fmt::Display for SchemaDiff {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let sections = [
+			("channel", &self.added_channels, &self.removed_channels, &self.changed_channels),
+			("message", &self.added_messages, &self.removed_messages, &self.changed_messages),
+		];
+		for (kind, added, removed, changed) in sections {
+			for entry in added {
+				writeln!(f, "+ {kind} {} {} {}", entry.id, entry.name, entry.layout)?;
+			}
+			for entry in removed {
+				writeln!(f, "- {kind} {} {} {}", entry.id, entry.name, entry.layout)?;
+			}
+			for (old, new) in changed {
+				writeln!(f, "~ {kind} {}: {} {} -> {} {}", old.name, old.id, old.layout, new.id, new.layout)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Compares `schema` against the manifest at `path`, panicking with the differences.
+/// With the `NAIA_UPDATE_MANIFEST` environment variable set, writes the current
+/// manifest instead. See `assert_schema_manifest!()`.
+#[doc(hidden)]
+pub fn check_schema_manifest(schema: &Schema, path: &Path) {
+	let manifest = SchemaManifest::of(schema);
+	if std::env::var_os("NAIA_UPDATE_MANIFEST").is_some() {
+		std::fs::write(path, manifest.to_string()).expect("failed to write the schema manifest");
+		return;
+	}
+
+	let Ok(text) = std::fs::read_to_string(path) else {
+		panic!("no schema manifest at {}; run with NAIA_UPDATE_MANIFEST=1 to create it", path.display());
+	};
+	let diff = schema.diff(&SchemaManifest::parse(&text).unwrap());
+	assert!(
+		diff.is_empty(),
+		"the schema differs from {}:\n{diff}run with NAIA_UPDATE_MANIFEST=1 if intended",
+		path.display(),
+	);
+}
+
+/// Asserts, e.g. in a test, that a Schema matches the manifest checked in at the given
+/// path, relative to the crate's root, catching accidental wire-breaking changes. Run
+/// with `NAIA_UPDATE_MANIFEST=1` to create or update the manifest.
+///
+/// ```ignore
+/// #[test]
+/// fn schema_unchanged() {
+///     naia_shared::assert_schema_manifest!(protocol::schema(), "schema.manifest");
+/// }
+/// ```
+#[macro_export]
+macro_rules! assert_schema_manifest {
+	($schema:expr, $path:expr) => {
+		$crate::check_schema_manifest(
+			&$schema,
+			&::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join($path),
+		)
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Channel, ChannelDirection, ChannelMode, SchemaBuilder};
+	use naia_derive::MessageInternal;
+
+	#[derive(Channel)]
+	struct GameChannel;
+
+	#[derive(MessageInternal)]
+	struct Move { x: i16, y: i16 }
+
+	#[derive(MessageInternal)]
+	#[message(compress)]
+	struct Chunk(Vec<u8>);
+
+	#[derive(MessageInternal)]
+	struct Quit;
+
+	fn builder() -> SchemaBuilder {
+		Schema::builder()
+			.add_channel::<GameChannel>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+			.add_message::<Move>()
+			.add_message::<Chunk>()
+	}
+
+	#[test]
+	fn roundtrip() {
+		let manifest = SchemaManifest::of(&builder().build());
+		let text = manifest.to_string();
+		assert!(text.contains("\nchannel 0 GameChannel OrderedReliable\n"));
+		assert!(text.contains("\nmessage 1 Move x:i16,y:i16\n"));
+		assert!(text.contains("\nmessage 2 Chunk compressed 0:Vec<u8>\n"));
+		assert_eq!(SchemaManifest::parse(&format!("# comment\n\n{text}")), Ok(manifest));
+		assert_eq!(SchemaManifest::parse("fingerprint 0\nmessage x Move -"), Err(ManifestError { line: 2 }));
+	}
+
+	#[test]
+	fn diff() {
+		let before = SchemaManifest::of(&builder().build());
+		assert!(builder().build().diff(&before).is_empty());
+
+		// Quit takes Chunk's id
+		let after = Schema::builder()
+			.add_channel::<GameChannel>(ChannelDirection::Bidirectional, ChannelMode::UnorderedReliable)
+			.add_message::<Move>()
+			.add_message::<Quit>()
+			.build();
+		let diff = after.diff(&before);
+		assert_eq!(diff.added_messages.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["Quit"]);
+		assert_eq!(diff.removed_messages.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["Chunk"]);
+		assert_eq!(diff.changed_channels.len(), 1);
+		assert!(diff.changed_messages.is_empty());
+		assert_eq!(
+			diff.to_string(),
+			"~ channel GameChannel: 0 OrderedReliable -> 0 UnorderedReliable\n\
+			+ message 2 Quit -\n\
+			- message 2 Chunk compressed 0:Vec<u8>\n",
+		);
+	}
+
+	#[test]
+	fn check() {
+		let path = std::env::temp_dir().join(format!("naia-schema-{}.manifest", std::process::id()));
+		std::fs::write(&path, SchemaManifest::of(&builder().build()).to_string()).unwrap();
+		assert_schema_manifest!(builder().build(), &path);
+
+		let changed = builder().add_message::<Quit>().build();
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			assert_schema_manifest!(changed, &path)
+		}));
+		std::fs::remove_file(&path).unwrap();
+		assert!(result.is_err());
+	}
+}
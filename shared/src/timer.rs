@@ -1,4 +1,5 @@
-use std::time::{Duration, Instant};
+use crate::Instant;
+use std::time::Duration;
 
 /// A Timer with a given duration after which it will enter into a "Ringing"
 /// state. The Timer can be reset at an given time, or manually set to start
@@ -25,30 +26,68 @@ impl Timer {
 		}
 	}
 
+	/// Change the duration, keeping the time of the last reset
+	pub fn set_duration(&mut self, duration: Duration) {
+		self.target = self.target - self.duration + duration;
+		self.duration = duration;
+	}
+
     /// Reset the Timer to stop ringing and wait till 'Duration' has elapsed
     /// again
-    pub fn reset(&mut self) {
-        self.target = Instant::now() + self.duration;
+    pub fn reset(&mut self) { self.reset_at(&Instant::now()) }
+
+    /// Like `reset()`, given the current time, e.g. read once per frame rather than
+    /// by each timer
+    pub fn reset_at(&mut self, now: &Instant) {
+        self.target = *now + self.duration;
     }
 
     /// Gets whether or not the Timer is "Ringing" (i.e. the given Duration has
     /// elapsed since the last "reset")
-    pub fn ringing(&self) -> bool {
-        Instant::now() >= self.target
+    pub fn ringing(&self) -> bool { self.ringing_at(&Instant::now()) }
+
+    /// Like `ringing()`, given the current time
+    pub fn ringing_at(&self, now: &Instant) -> bool {
+        *now >= self.target
     }
 
+    /// The time at which the Timer starts ringing
+    pub fn deadline(&self) -> Instant { self.target }
+
     /// Manually causes the Timer to enter into a "Ringing" state
     pub fn ring_manual(&mut self) {
         self.target = Instant::now();
     }
 
 	/// Returns if the timer is ringing, and does a reset if it is
-	pub fn try_reset(&mut self) -> bool {
-		if self.ringing() {
-			self.reset();
+	pub fn try_reset(&mut self) -> bool { self.try_reset_at(&Instant::now()) }
+
+	/// Like `try_reset()`, given the current time
+	pub fn try_reset_at(&mut self, now: &Instant) -> bool {
+		if self.ringing_at(now) {
+			self.reset_at(now);
 			true
 		} else {
 			false
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn explicit_now() {
+		let start = Instant::now();
+		let mut timer = Timer::new(Duration::from_secs(1));
+		timer.reset_at(&start);
+		assert!(!timer.ringing_at(&(start + Duration::from_millis(999))));
+		assert!(timer.ringing_at(&(start + Duration::from_secs(1))));
+
+		let later = start + Duration::from_secs(2);
+		assert!(timer.try_reset_at(&later));
+		assert!(!timer.try_reset_at(&later));
+		assert_eq!(timer.deadline(), later + Duration::from_secs(1));
+	}
+}
@@ -0,0 +1,226 @@
+use crate::Instant;
+use std::{collections::{BTreeMap, HashMap}, hash::Hash, time::Duration};
+
+/// When a `CacheMap` evicts its entries
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CachePolicy {
+	/// The maximum number of entries; inserting more evicts the least recently used
+	pub max_len: Option<usize>,
+	/// How long entries live after being inserted
+	pub ttl: Option<Duration>,
+}
+
+impl CachePolicy {
+	/// Evict the least recently used entries beyond `max_len`
+	pub fn lru(max_len: usize) -> Self {
+		Self { max_len: Some(max_len), ttl: None }
+	}
+
+	/// Evict entries `ttl` after they are inserted
+	pub fn ttl(ttl: Duration) -> Self {
+		Self { max_len: None, ttl: Some(ttl) }
+	}
+}
+
+/// Counters of a `CacheMap`'s usage
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+	pub hits: u64,
+	pub misses: u64,
+	/// Entries evicted for exceeding `CachePolicy::max_len`
+	pub evicted: u64,
+	/// Entries dropped after `CachePolicy::ttl`
+	pub expired: u64,
+}
+
+struct CacheEntry<V> {
+	value: V,
+	expires: Option<Instant>,
+	/// The position of the entry in the LRU order
+	used: u64,
+}
+
+impl<V> CacheEntry<V> {
+	fn is_expired(&self, now: &Instant) -> bool {
+		self.expires.is_some_and(|expires| expires <= *now)
+	}
+}
+
+/// A map which evicts entries per its `CachePolicy`, e.g. to cache per-user data keyed
+/// by UserKey. Expired entries are never returned, and are dropped lazily or by
+/// `evict_expired()`.
+pub struct CacheMap<K: Clone + Eq + Hash, V> {
+	policy: CachePolicy,
+	entries: HashMap<K, CacheEntry<V>>,
+	/// Keys by the order in which they were last used
+	lru: BTreeMap<u64, K>,
+	next_use: u64,
+	stats: CacheStats,
+}
+
+impl<K: Clone + Eq + Hash, V> CacheMap<K, V> {
+	pub fn new(policy: CachePolicy) -> Self {
+		Self {
+			policy,
+			entries: HashMap::new(),
+			lru: BTreeMap::new(),
+			next_use: 0,
+			stats: CacheStats::default(),
+		}
+	}
+
+	pub fn policy(&self) -> &CachePolicy { &self.policy }
+
+	/// Change the policy, evicting entries beyond the new `max_len`. The new `ttl`
+	/// applies to entries inserted from now on.
+	pub fn set_policy(&mut self, policy: CachePolicy) {
+		self.policy = policy;
+		self.evict_lru();
+	}
+
+	/// Insert an entry, returning the value it replaced, and evicting the least
+	/// recently used entry if the cache is full
+	pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+		self.insert_at(key, value, &Instant::now())
+	}
+
+	/// Like `insert()`, given the current time
+	pub fn insert_at(&mut self, key: K, value: V, now: &Instant) -> Option<V> {
+		let used = self.touch(&key);
+		let expires = self.policy.ttl.map(|ttl| *now + ttl);
+		let old = self.entries.insert(key, CacheEntry { value, expires, used });
+		let old = old.and_then(|old| {
+			self.lru.remove(&old.used);
+			(!old.is_expired(now)).then_some(old.value)
+		});
+		self.evict_lru();
+		old
+	}
+
+	/// Get an entry unless it expired, marking it as recently used
+	pub fn get(&mut self, key: &K) -> Option<&V> {
+		self.get_at(key, &Instant::now())
+	}
+
+	/// Like `get()`, given the current time
+	pub fn get_at(&mut self, key: &K, now: &Instant) -> Option<&V> {
+		let expired = match self.entries.get(key) {
+			None => {
+				self.stats.misses += 1;
+				return None;
+			}
+			Some(entry) => entry.is_expired(now),
+		};
+		if expired {
+			self.remove(key);
+			self.stats.expired += 1;
+			self.stats.misses += 1;
+			return None;
+		}
+
+		self.stats.hits += 1;
+		let used = self.touch(key);
+		let entry = self.entries.get_mut(key).unwrap();
+		self.lru.remove(&std::mem::replace(&mut entry.used, used));
+		Some(&entry.value)
+	}
+
+	/// Returns whether an unexpired entry exists, without marking it as used
+	pub fn contains_key(&self, key: &K) -> bool {
+		let now = Instant::now();
+		self.entries.get(key).is_some_and(|entry| !entry.is_expired(&now))
+	}
+
+	pub fn remove(&mut self, key: &K) -> Option<V> {
+		let entry = self.entries.remove(key)?;
+		self.lru.remove(&entry.used);
+		Some(entry.value)
+	}
+
+	/// Drop all expired entries, returning how many were dropped
+	pub fn evict_expired(&mut self, now: &Instant) -> usize {
+		let expired: Vec<K> = self.entries.iter()
+			.filter(|(_, entry)| entry.is_expired(now))
+			.map(|(key, _)| key.clone())
+			.collect();
+		for key in &expired {
+			self.remove(key);
+		}
+		self.stats.expired += expired.len() as u64;
+		expired.len()
+	}
+
+	pub fn clear(&mut self) {
+		self.entries.clear();
+		self.lru.clear();
+	}
+
+	/// The number of entries, including expired ones not dropped yet
+	pub fn len(&self) -> usize { self.entries.len() }
+
+	pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+	pub fn stats(&self) -> CacheStats { self.stats }
+
+	/// Record `key` as the most recently used, returning its position in the LRU order
+	fn touch(&mut self, key: &K) -> u64 {
+		let used = self.next_use;
+		self.next_use += 1;
+		self.lru.insert(used, key.clone());
+		used
+	}
+
+	fn evict_lru(&mut self) {
+		let Some(max_len) = self.policy.max_len else {
+			return;
+		};
+		while self.entries.len() > max_len {
+			let Some((_, key)) = self.lru.pop_first() else {
+				break;
+			};
+			self.entries.remove(&key);
+			self.stats.evicted += 1;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lru() {
+		let mut cache = CacheMap::new(CachePolicy::lru(2));
+		cache.insert(1, "a");
+		cache.insert(2, "b");
+		assert_eq!(cache.get(&1), Some(&"a"));
+
+		// 2 is the least recently used
+		cache.insert(3, "c");
+		assert_eq!(cache.len(), 2);
+		assert_eq!(cache.get(&2), None);
+		assert_eq!(cache.insert(1, "aa"), Some("a"));
+
+		cache.set_policy(CachePolicy::lru(1));
+		assert_eq!(cache.get(&1), Some(&"aa"));
+		assert!(!cache.contains_key(&3));
+		assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1, evicted: 2, expired: 0 });
+	}
+
+	#[test]
+	fn ttl() {
+		let ttl = Duration::from_millis(10);
+		let now = Instant::now();
+		let mut cache = CacheMap::new(CachePolicy::ttl(ttl));
+		cache.insert_at(1, "a", &now);
+		cache.insert_at(2, "b", &(now + ttl));
+		assert_eq!(cache.get_at(&1, &now), Some(&"a"));
+		assert_eq!(cache.get_at(&1, &(now + ttl)), None);
+		assert_eq!(cache.len(), 1);
+
+		assert_eq!(cache.evict_expired(&(now + ttl)), 0);
+		assert_eq!(cache.evict_expired(&(now + 2 * ttl)), 1);
+		assert!(cache.is_empty());
+		assert_eq!(cache.stats().expired, 2);
+	}
+}
@@ -8,9 +8,14 @@ pub trait CheckedIncr: Sized {
 /// makes the Ids more useful as Vec/slice/array indexes, where you want data to be as
 /// dense as possible.
 pub struct IdPool<T: Copy + Default + CheckedIncr + Ord> {
+	/// The smallest Id never handed out, or None once all Ids of `T` were
 	next: Option<T>,
 	/// min-heap of returned Ids (see: https://github.com/rust-lang/rust/issues/15947)
 	free_list: BinaryHeap<Reverse<T>>,
+	/// The maximum number of Ids in use at once, if limited
+	capacity: Option<usize>,
+	/// The number of Ids in use
+	len: usize,
 }
 
 impl<T: Copy + Default + CheckedIncr+ Ord> Default for IdPool<T> {
@@ -18,6 +23,8 @@ impl<T: Copy + Default + CheckedIncr+ Ord> Default for IdPool<T> {
 		Self {
 			next: Some(T::default()),
 			free_list: Default::default(),
+			capacity: None,
+			len: 0,
 		}
 	}
 }
@@ -27,17 +34,226 @@ impl CheckedIncr for u16 {
 }
 
 impl<T: Copy + Default + CheckedIncr+ Ord> IdPool<T> {
+	/// Creates a pool handing out at most `capacity` Ids at once
+	pub fn with_capacity(capacity: usize) -> Self {
+		Self { capacity: Some(capacity), ..Self::default() }
+	}
+
+	/// The maximum number of Ids in use at once, if limited. Ids are recycled, so they
+	/// stay below the capacity while nothing is `take()`n.
+	pub fn capacity(&self) -> Option<usize> { self.capacity }
+
+	/// Change the capacity; lowering it below `len()` only refuses new Ids
+	pub fn set_capacity(&mut self, capacity: Option<usize>) { self.capacity = capacity }
+
+	/// The number of Ids in use
+	pub fn len(&self) -> usize { self.len }
+
+	pub fn is_empty(&self) -> bool { self.len == 0 }
+
+	/// Whether `get()` refuses new Ids, because the capacity is reached or all Ids of
+	/// `T` are in use
+	pub fn is_full(&self) -> bool {
+		self.capacity.is_some_and(|capacity| self.len >= capacity)
+			|| self.next.is_none() && self.free_list.is_empty()
+	}
+
+	/// Hand out the smallest free Id, or None if the pool is full
 	pub fn get(&mut self) -> Option<T> {
-		if let Some(Reverse(id)) = self.free_list.pop() {
-			return Some(id);
+		if self.capacity.is_some_and(|capacity| self.len >= capacity) {
+			return None;
 		}
 
-		let id = self.next;
-		self.next = self.next.and_then(|v| { v.checked_incr() });
-		id
+		let id = match self.free_list.pop() {
+			Some(Reverse(id)) => id,
+			None => {
+				let id = self.next?;
+				self.next = id.checked_incr();
+				id
+			}
+		};
+		self.len += 1;
+		Some(id)
 	}
 
+	/// Return an Id to the pool, for `get()` to hand out again
 	pub fn put(&mut self, value: T) {
+		debug_assert!(self.contains(value), "returning an Id which is not in use");
 		self.free_list.push(Reverse(value));
+		self.len -= 1;
+	}
+
+	/// Take a specific Id out of the pool, e.g. to reserve it for a resumed session.
+	/// Returns false if it is already taken, or the capacity is reached.
+	pub fn take(&mut self, value: T) -> bool {
+		if self.capacity.is_some_and(|capacity| self.len >= capacity) {
+			return false;
+		}
+
+		let len = self.free_list.len();
+		self.free_list.retain(|Reverse(id)| *id != value);
+		if self.free_list.len() != len {
+			self.len += 1;
+			return true;
+		}
+
+		// everything from `next` up to `value` becomes free
+		let Some(mut next) = self.next.filter(|next| *next <= value) else {
+			return false;
+		};
+		while next < value {
+			self.free_list.push(Reverse(next));
+			next = next.checked_incr().unwrap();
+		}
+		self.next = value.checked_incr();
+		self.len += 1;
+		true
+	}
+
+	/// Returns whether `value` is in use
+	pub fn contains(&self, value: T) -> bool {
+		self.next.is_none_or(|next| value < next)
+			&& !self.free_list.iter().any(|Reverse(id)| *id == value)
+	}
+
+	/// The Ids in use, in ascending order
+	pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+		let mut free: Vec<T> = self.free_list.iter().map(|Reverse(id)| *id).collect();
+		free.sort_unstable();
+
+		let next = self.next;
+		std::iter::successors(Some(T::default()), |id| id.checked_incr())
+			.take_while(move |id| next.is_none_or(|next| *id < next))
+			.filter(move |id| free.binary_search(id).is_err())
+	}
+
+	/// Forget the free Ids above the largest one in use, so they don't take up memory;
+	/// `get()` still hands out the smallest Id available
+	pub fn shrink(&mut self) {
+		let mut free: Vec<T> = std::mem::take(&mut self.free_list).into_iter()
+			.map(|Reverse(id)| id)
+			.collect();
+		free.sort_unstable();
+
+		while let Some(last) = free.last().copied() {
+			let is_top = match self.next {
+				Some(next) => last.checked_incr() == Some(next),
+				// every Id was handed out, so the largest one is the top
+				None => last.checked_incr().is_none(),
+			};
+			if !is_top {
+				break;
+			}
+			free.pop();
+			self.next = Some(last);
+		}
+
+		free.shrink_to_fit();
+		self.free_list = free.into_iter().map(Reverse).collect();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn take() {
+		let mut pool = IdPool::<u16>::default();
+		assert!(pool.take(2));
+		assert!(!pool.take(2));
+		assert!(pool.take(0));
+		assert_eq!(pool.get(), Some(1));
+		assert_eq!(pool.get(), Some(3));
+
+		pool.put(1);
+		assert!(pool.take(1));
+		assert_eq!(pool.get(), Some(4));
+		assert_eq!(pool.len(), 5);
+	}
+
+	#[test]
+	fn capacity() {
+		let mut pool = IdPool::<u16>::with_capacity(2);
+		assert_eq!(pool.get(), Some(0));
+		assert_eq!(pool.get(), Some(1));
+		assert!(pool.is_full());
+		assert_eq!(pool.get(), None);
+		assert!(!pool.take(5));
+
+		pool.put(0);
+		assert_eq!(pool.len(), 1);
+		assert_eq!(pool.get(), Some(0));
+
+		// lowering the capacity keeps the Ids in use
+		pool.set_capacity(Some(1));
+		assert_eq!(pool.len(), 2);
+		pool.put(1);
+		assert_eq!(pool.get(), None);
+		pool.put(0);
+		assert_eq!(pool.get(), Some(0));
+	}
+
+	#[test]
+	fn iter_and_contains() {
+		let mut pool = IdPool::<u16>::default();
+		for _ in 0..5 {
+			pool.get();
+		}
+		pool.put(1);
+		pool.put(3);
+		assert!(pool.take(7));
+		assert_eq!(pool.iter().collect::<Vec<_>>(), vec![0, 2, 4, 7]);
+		assert!(pool.contains(7));
+		assert!(!pool.contains(3));
+		assert!(!pool.contains(6));
+		assert!(!pool.contains(8));
+	}
+
+	#[test]
+	fn shrink() {
+		let mut pool = IdPool::<u16>::default();
+		for _ in 0..10 {
+			pool.get();
+		}
+		for id in [1, 7, 8, 9] {
+			pool.put(id);
+		}
+		pool.shrink();
+		assert_eq!(pool.len(), 6);
+		assert_eq!(pool.free_list.len(), 1);
+		assert_eq!(pool.next, Some(7));
+
+		// still smallest first, and no Id handed out twice
+		assert_eq!(pool.get(), Some(1));
+		assert_eq!(pool.get(), Some(7));
+		assert_eq!(pool.iter().collect::<Vec<_>>(), (0..8).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn exhaustion_and_reuse() {
+		let mut pool = IdPool::<u16>::default();
+		for id in 0..=u16::MAX {
+			assert_eq!(pool.get(), Some(id));
+		}
+		assert!(pool.is_full());
+		assert_eq!(pool.get(), None);
+		assert_eq!(pool.len(), u16::MAX as usize + 1);
+
+		// Ids never wrap around, but returned ones are reused smallest first
+		pool.put(u16::MAX);
+		pool.put(42);
+		assert!(!pool.is_full());
+		assert_eq!(pool.get(), Some(42));
+		assert_eq!(pool.get(), Some(u16::MAX));
+		assert_eq!(pool.get(), None);
+
+		pool.put(u16::MAX);
+		pool.put(u16::MAX - 1);
+		pool.shrink();
+		assert_eq!(pool.next, Some(u16::MAX - 1));
+		assert!(pool.take(u16::MAX));
+		assert_eq!(pool.get(), Some(u16::MAX - 1));
+		assert!(pool.is_full());
 	}
 }
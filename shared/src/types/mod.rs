@@ -1,11 +1,19 @@
+mod cache_map;
 mod id_pool;
 mod index_buffer;
+mod priority_accumulator;
 mod rollover_counter;
 mod seq_num;
+mod stamped;
 mod time_queue;
+mod timer_wheel;
 
+pub use cache_map::*;
 pub use id_pool::*;
 pub use index_buffer::*;
+pub use priority_accumulator::*;
 pub use rollover_counter::*;
 pub use seq_num::*;
-pub use time_queue::*;
\ No newline at end of file
+pub use stamped::*;
+pub use time_queue::*;
+pub use timer_wheel::*;
\ No newline at end of file
@@ -0,0 +1,94 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// Fairly shares a limited budget between many updates, e.g. the state of entities
+/// in a large world, where not everything fits in each packet. Each key accumulates
+/// priority every tick until it is picked, so starved keys eventually win over keys
+/// with higher base priorities. Keep one per user to track (user, entity) pairs.
+pub struct PriorityAccumulator<K> {
+	priorities: HashMap<K, f32>,
+}
+
+impl<K> Default for PriorityAccumulator<K> {
+	fn default() -> Self {
+		Self { priorities: HashMap::new() }
+	}
+}
+
+impl<K: Clone + Eq + Hash> PriorityAccumulator<K> {
+	pub fn new() -> Self { Self::default() }
+
+	/// Add `priority` to the accumulated priority of `key`, e.g. once per tick for each
+	/// key with pending changes
+	pub fn accumulate(&mut self, key: K, priority: f32) {
+		*self.priorities.entry(key).or_default() += priority;
+	}
+
+	/// The accumulated priority of `key`, or 0 if it isn't tracked
+	pub fn priority(&self, key: &K) -> f32 {
+		self.priorities.get(key).copied().unwrap_or(0.0)
+	}
+
+	/// Stop tracking `key`, e.g. once its entity left the user's scope
+	pub fn remove(&mut self, key: &K) {
+		self.priorities.remove(key);
+	}
+
+	pub fn len(&self) -> usize { self.priorities.len() }
+
+	pub fn is_empty(&self) -> bool { self.priorities.is_empty() }
+
+	/// Pick up to `budget` keys with the highest accumulated priorities, in descending
+	/// order, and stop tracking them until they accumulate priority again. The order
+	/// of equal priorities is unspecified.
+	pub fn take(&mut self, budget: usize) -> Vec<K> {
+		let mut entries: Vec<_> = self.priorities.iter().map(|(key, priority)| (*priority, key)).collect();
+		let by_priority = |a: &(f32, &K), b: &(f32, &K)| b.0.total_cmp(&a.0);
+		if budget < entries.len() {
+			entries.select_nth_unstable_by(budget, by_priority);
+			entries.truncate(budget);
+		}
+		entries.sort_unstable_by(by_priority);
+
+		let keys: Vec<K> = entries.into_iter().map(|(_, key)| key.clone()).collect();
+		for key in &keys {
+			self.priorities.remove(key);
+		}
+		keys
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn take() {
+		let mut acc = PriorityAccumulator::new();
+		acc.accumulate('a', 1.0);
+		acc.accumulate('b', 3.0);
+		acc.accumulate('c', 2.0);
+		assert_eq!(acc.take(2), ['b', 'c']);
+		assert_eq!(acc.priority(&'b'), 0.0);
+		assert_eq!(acc.len(), 1);
+
+		// everything fits
+		acc.accumulate('b', 3.0);
+		assert_eq!(acc.take(5), ['b', 'a']);
+		assert!(acc.is_empty());
+	}
+
+	#[test]
+	fn starvation() {
+		// 'low' has a third of the priority of 'high', but is still picked every few ticks
+		let mut acc = PriorityAccumulator::new();
+		let mut low_picks = 0;
+		for _ in 0..40 {
+			acc.accumulate("high", 3.0);
+			acc.accumulate("low", 1.0);
+			if acc.take(1) == ["low"] {
+				low_picks += 1;
+			}
+		}
+		assert!((8..=14).contains(&low_picks), "{low_picks}");
+	}
+}
@@ -1,20 +1,31 @@
-use std::ops::{BitOr, Shl};
+use std::{cmp::Ordering, ops::{BitOr, Shl}};
 use super::seq_num::SeqNum;
 
-#[derive(Copy, Clone, Debug, Default)]
+/// A non-wrapping sequence number that uses a smaller, wrapping sequence number and
+/// counts the number of rollovers. This can be used as a form of compression, where only
+/// the low bytes of the sequence number ever need to be communicated. Both sides count
+/// the number of rollovers to infer the high bytes, and thus, the actual sequence number.
+///
+/// Inference only works while the remote sequence numbers are less than half the range
+/// of `SeqNum` away from the local count. Counters order by their non-wrapping value.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct RolloverCounter {
 	rollovers: u32,
 	seq: SeqNum,
 }
 
-/// A non-wrapping sequence number that uses a smaller, wrapping sequence number and
-/// counts the number of rollovers. This can be used as a form of compression, where only
-/// the low bytes of the sequence number ever need to be communicated. Both sides count
-/// the number of rollovers to infer the high bytes, and thus, the actual sequence number.
 impl RolloverCounter {
 	pub const ZERO: Self = Self { rollovers: 0, seq: SeqNum::ZERO };
 	pub const MAX: Self = Self { rollovers: u32::MAX, seq: SeqNum::MAX };
 
+	/// Create a counter from a non-wrapping sequence number returned by `value()`
+	pub fn from_value(value: u64) -> Self {
+		Self {
+			rollovers: (value >> SeqNum::SIZE_BITS) as u32,
+			seq: (value as u16).into(),
+		}
+	}
+
 	/// Get the non-wrapping sequence number
 	pub fn value(&self) -> u64 {
 		(self.rollovers as u64).shl(SeqNum::SIZE_BITS).bitor(self.seq.0 as u64)
@@ -54,6 +65,23 @@ impl RolloverCounter {
 
 	/// Get the wrapping sequence number for the current count
 	pub fn seq(&self) -> SeqNum { self.seq }
+
+	/// Get the number of times the wrapping sequence number rolled over
+	pub fn rollovers(&self) -> u32 { self.rollovers }
+
+	/// Iterate over the sequence numbers following the current count, advancing the
+	/// counter as the iterator is consumed
+	pub fn iter_next(&mut self) -> impl Iterator<Item = SeqNum> + '_ {
+		std::iter::repeat_with(move || self.incr())
+	}
+}
+
+impl Ord for RolloverCounter {
+	fn cmp(&self, other: &Self) -> Ordering { self.value().cmp(&other.value()) }
+}
+
+impl PartialOrd for RolloverCounter {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
 }
 
 #[cfg(test)]
@@ -102,4 +130,15 @@ mod tests {
 		assert_eq!(counter.rollovers, 1);
 		assert_eq!(counter.seq, 3.into());
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn rollover_counter_iter() {
+		let mut counter = RolloverCounter { rollovers: 0, seq: SeqNum(u16::MAX - 1) };
+		let before = counter;
+		let seqs: Vec<_> = counter.iter_next().take(3).collect();
+		assert_eq!(seqs, vec![SeqNum::MAX, SeqNum(0), SeqNum(1)]);
+		assert_eq!(counter.rollovers(), 1);
+		assert!(counter > before);
+		assert_eq!(RolloverCounter::from_value(counter.value()), counter);
+	}
+}
@@ -19,99 +19,195 @@ impl HostType {
 	pub fn to_u8(self) -> u8 { self as u8 }
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
-pub struct SeqNum(pub u16);
+/// Defines a wrapping sequence number over `$uint`, whose differences and ordering
+/// are taken the short way around, so that they stay meaningful across wraparound as
+/// long as compared values are less than half the range apart
+macro_rules! wrapping_seq {
+	($(#[$meta:meta])* $name:ident, $uint:ty, $int:ty) => {
+		$(#[$meta])*
+		#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+		pub struct $name(pub $uint);
+
+		impl Display for $name {
+			fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { self.0.fmt(f) }
+		}
 
-impl Display for SeqNum {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { self.0.fmt(f) }
-}
+		impl From<$uint> for $name {
+			fn from(value: $uint) -> Self { Self(value) }
+		}
 
-impl From<u16> for SeqNum {
-	fn from(value: u16) -> Self { Self(value) }
-}
+		impl From<$name> for $uint {
+			fn from(val: $name) -> Self { val.0 }
+		}
 
-impl Into<u16> for SeqNum {
-	fn into(self) -> u16 { self.0 }
-}
+		impl $name {
+			pub const MIN: Self = Self(<$uint>::MIN);
+			pub const MAX: Self = Self(<$uint>::MAX);
+			pub const ZERO: Self = Self(0);
+			pub const SIZE_BYTES: usize = size_of::<Self>();
+			pub const SIZE_BITS: usize = Self::SIZE_BYTES * 8;
+
+			pub fn add_diff(&self, rhs: $int) -> Self {
+				Self(self.0.wrapping_add_signed(rhs))
+			}
+
+			/// The signed distance from `rhs` to `self`, the short way around. Values
+			/// exactly half the range apart are ambiguous, and differ by `MIN` either
+			/// way.
+			pub fn diff(&self, rhs: Self) -> $int {
+				self.0.wrapping_sub(rhs.0) as $int
+			}
+
+			pub fn incr(&mut self) {
+				*self += 1;
+			}
+		}
 
-impl SeqNum {
-	pub const MIN: Self = Self(u16::MIN);
-	pub const MAX: Self = Self(u16::MAX);
-	pub const ZERO: Self = Self(0);
-	pub const SIZE_BYTES: usize = size_of::<Self>();
-	pub const SIZE_BITS: usize = Self::SIZE_BYTES * 8;
+		/// Orders by the sign of `diff()`, so it is not transitive across more than half
+		/// the range. Values exactly half the range apart are ordered by their raw
+		/// value, keeping the ordering antisymmetric.
+		impl Ord for $name {
+			fn cmp(&self, rhs: &Self) -> Ordering {
+				match self.diff(*rhs) {
+					<$int>::MIN => self.0.cmp(&rhs.0),
+					diff => diff.cmp(&0),
+				}
+			}
+		}
 
-	pub fn add_diff(&self, rhs: i16) -> Self {
-		Self(self.0.wrapping_add_signed(rhs))
-	}
+		impl PartialOrd for $name {
+			fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> { Some(self.cmp(rhs)) }
+		}
 
-	pub fn diff(&self, rhs: Self) -> i16 {
-		Self::seq_diff(self.0, rhs.0)
-	}
+		impl Add<$uint> for $name {
+			type Output = Self;
+			fn add(self, rhs: $uint) -> Self::Output { Self(self.0.wrapping_add(rhs)) }
+		}
+		impl AddAssign<$uint> for $name {
+			fn add_assign(&mut self, rhs: $uint) { self.0 = self.0.wrapping_add(rhs); }
+		}
 
-	pub fn incr(&mut self) {
-		*self += 1;
-	}
+		impl Sub<$uint> for $name {
+			type Output = Self;
+			fn sub(self, rhs: $uint) -> Self::Output { Self(self.0.wrapping_sub(rhs)) }
+		}
 
-	fn seq_diff(lhs: u16, rhs: u16) -> i16 {
-		let range = u16::MAX as i32 + 1;
-
-		let diff = lhs as i32 - rhs as i32; // +/- (64k - 1)
-		return if diff > i16::MAX as i32 { // > 32k - 1
-			diff - range
-		} else if diff < i16::MIN as i32 { // < -32k
-			diff + range
-		} else {
-			diff
-		} as i16;
-	}
+		impl SubAssign<$uint> for $name {
+			fn sub_assign(&mut self, rhs: $uint) { self.0 = self.0.wrapping_sub(rhs); }
+		}
 
-	fn seq_gt(lhs: u16, rhs: u16) -> bool {
-		let half_range = u16::MAX / 2 + 1;
-		return (lhs > rhs && lhs - rhs <= half_range)
-			|| (lhs < rhs && rhs - lhs > half_range);
-	}
+		impl Serde for $name {
+			fn ser(&self, writer: &mut dyn naia_serde::BitWrite) { self.0.ser(writer) }
+
+			fn de(reader: &mut naia_serde::BitReader) -> Result<Self, naia_serde::SerdeErr> {
+				<$uint>::de(reader).map(|v| { Self(v) })
+			}
+
+			fn bit_length(&self) -> u32 { self.0.bit_length()}
+		}
+	};
 }
 
-impl Ord for SeqNum {
-	fn cmp(&self, rhs: &Self) -> Ordering {
-		if self == rhs {
-			Ordering::Equal
-		} else if Self::seq_gt(self.0, rhs.0) {
-			Ordering::Greater
-		} else {
-			Ordering::Less
+wrapping_seq!(
+	/// A 16-bit packet or message sequence number
+	SeqNum, u16, i16
+);
+
+wrapping_seq!(
+	/// A 32-bit tick counter, for long-running servers where 16-bit ticks would wrap
+	/// every ~18 minutes at 60Hz; 32-bit ticks take over two years
+	Tick, u32, i32
+);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn seq_num_diff_roundtrip() {
+		// exhaustive over values, sampled over distances
+		let diffs = (i16::MIN..=i16::MAX).step_by(997).chain([-2, -1, 0, 1, 2, i16::MAX]);
+		for diff in diffs {
+			for value in 0..=u16::MAX {
+				let seq = SeqNum(value);
+				let other = seq.add_diff(diff);
+				assert_eq!(other.diff(seq), diff);
+				assert_eq!(other - diff as u16, seq);
+				if diff != i16::MIN {
+					assert_eq!(seq.diff(other), -diff);
+				}
+			}
 		}
 	}
-}
 
-impl PartialOrd for SeqNum {
-	fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> { Some(self.cmp(rhs)) }
-}
+	#[test]
+	fn seq_num_ordering() {
+		let diffs = (i16::MIN..=i16::MAX).step_by(331).chain([-1, 0, 1, i16::MAX]);
+		for diff in diffs {
+			for value in (0..=u16::MAX).step_by(7).chain([u16::MAX - 1, u16::MAX]) {
+				let (a, b) = (SeqNum(value), SeqNum(value).add_diff(diff));
+				// antisymmetric, and consistent with diff() away from the half point
+				assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+				if diff != i16::MIN {
+					assert_eq!(b.cmp(&a), diff.cmp(&0));
+				}
+			}
+		}
 
-impl Add<u16> for SeqNum {
-	type Output = Self;
-	fn add(self, rhs: u16) -> Self::Output { Self(self.0.wrapping_add(rhs)) }
-}
-impl AddAssign<u16> for SeqNum {
-	fn add_assign(&mut self, rhs: u16) { self.0 = self.0.wrapping_add(rhs); }
-}
+		// across wraparound
+		assert!(SeqNum(0) > SeqNum::MAX);
+		assert!(SeqNum(10) > SeqNum(u16::MAX - 10));
+		assert!(SeqNum(1 << 15) > SeqNum(0));
+		assert!(SeqNum(0) < SeqNum(1 << 15));
+	}
 
-impl Sub<u16> for SeqNum {
-	type Output = Self;
-	fn sub(self, rhs: u16) -> Self::Output { Self(self.0.wrapping_sub(rhs)) }
-}
+	/// The distance from `b` to `a` the short way around, computed without wrapping
+	fn reference_diff(a: u16, b: u16) -> i32 {
+		let forward = (a as i32 - b as i32).rem_euclid(1 << 16);
+		if forward >= 1 << 15 { forward - (1 << 16) } else { forward }
+	}
 
-impl SubAssign<u16> for SeqNum {
-	fn sub_assign(&mut self, rhs: u16) { self.0 = self.0.wrapping_sub(rhs); }
-}
+	#[test]
+	fn seq_num_every_distance() {
+		// differences only depend on the distance, so every distance is checked from a
+		// few bases, including both sides of each wraparound
+		let bases = (0..=u16::MAX).step_by(4099).chain([1, (1 << 15) - 1, 1 << 15, u16::MAX - 1, u16::MAX]);
+		for base in bases {
+			for distance in 0..=u16::MAX {
+				let (a, b) = (SeqNum(base.wrapping_add(distance)), SeqNum(base));
+				let expected = reference_diff(a.0, b.0);
+				assert_eq!(a.diff(b) as i32, expected);
+				assert_eq!(b.add_diff(a.diff(b)), a);
+				let ordering = match expected {
+					diff if diff == i16::MIN as i32 => a.0.cmp(&b.0),
+					diff => diff.cmp(&0),
+				};
+				assert_eq!(a.cmp(&b), ordering);
+				assert_eq!(b.cmp(&a), ordering.reverse());
+			}
+		}
+	}
 
-impl Serde for SeqNum {
-	fn ser(&self, writer: &mut dyn naia_serde::BitWrite) { self.0.ser(writer) }
+	#[test]
+	fn tick_arithmetic() {
+		let edges = [0, 1, i32::MAX as u32, 1 << 31, u32::MAX - 1, u32::MAX];
+		let values = (0..10_000).map(|_| fastrand::u32(..)).chain(edges);
+		for value in values {
+			let diff = fastrand::i32(..);
+			let (a, b) = (Tick(value), Tick(value).add_diff(diff));
+			assert_eq!(b.diff(a), diff);
+			assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+			if diff != i32::MIN {
+				assert_eq!(a.diff(b), -diff);
+				assert_eq!(b.cmp(&a), diff.cmp(&0));
+			}
+		}
 
-	fn de(reader: &mut naia_serde::BitReader) -> Result<Self, naia_serde::SerdeErr> {
-		u16::de(reader).map(|v| { Self(v) })
+		// 16-bit ticks would have wrapped long before
+		let mut tick = Tick(u16::MAX as u32);
+		tick.incr();
+		assert_eq!(tick.diff(Tick(0)), 1 << 16);
+		assert!(Tick(0) > Tick::MAX);
+		assert_eq!(Tick::SIZE_BITS, 32);
 	}
-
-	fn bit_length(&self) -> u32 { self.0.bit_length()}
 }
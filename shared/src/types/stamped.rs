@@ -0,0 +1,23 @@
+use crate::Instant;
+
+/// An event with its place in the sequence of all events returned by a Client's or
+/// Server's `receive()`, and the time it was returned, so that events processed
+/// asynchronously or recorded can be put back in order
+#[derive(Clone, Debug)]
+pub struct Stamped<E> {
+	/// Consecutive from 0, across `receive()` calls
+	pub seq: u64,
+	pub received: Instant,
+	pub event: E,
+}
+
+impl<E> Stamped<E> {
+	/// Stamp `events`, the latest returned by `receive()`, given the number of events
+	/// returned before them
+	pub fn stamp_all(events: Vec<E>, first_seq: u64, received: Instant) -> Vec<Self> {
+		events.into_iter()
+			.zip(first_seq..)
+			.map(|(event, seq)| Self { seq, received, event })
+			.collect()
+	}
+}
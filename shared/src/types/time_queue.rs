@@ -1,46 +1,84 @@
-use std::{cmp::Ordering, collections::BinaryHeap, time::Instant};
+use crate::Instant;
+use std::{cmp::Ordering, collections::BinaryHeap};
 
-/// A queue for items marked by time, will only ever pop items from the queue if
-/// the time
+/// A queue of items marked by time, which only pops items once their time has come,
+/// earliest first. Items marked with the same time pop in the order they were added.
+/// Useful for scheduling, e.g. delaying packets or timed events.
 #[derive(Clone)]
 pub struct TimeQueue<T: Eq + PartialEq> {
     queue: BinaryHeap<ItemContainer<T>>,
+    /// The maximum number of queued items, if bounded
+    capacity: Option<usize>,
+    /// The number of items added so far, to order items with equal times
+    seq: u64,
 }
 
-#[allow(clippy::new_without_default)]
-impl<T: Eq + PartialEq> TimeQueue<T> {
-    pub fn new() -> Self {
+impl<T: Eq + PartialEq> Default for TimeQueue<T> {
+    fn default() -> Self {
         Self {
             queue: BinaryHeap::default(),
+            capacity: None,
+            seq: 0,
         }
     }
 }
 
 impl<T: Eq + PartialEq> TimeQueue<T> {
-    /// Adds an item to the queue marked by time
-    pub fn add_item(&mut self, instant: Instant, item: T) {
-        self.queue.push(ItemContainer { instant, item });
-    }
+    /// Creates an unbounded queue
+    pub fn new() -> Self { Self::default() }
 
-    /// Returns whether or not there is an item that is ready to be returned
-    pub fn has_item(&self) -> bool {
-        if self.queue.is_empty() {
-            return false;
+    /// Creates a queue holding at most `capacity` items
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            queue: BinaryHeap::with_capacity(capacity),
+            capacity: Some(capacity),
+            seq: 0,
         }
-        if let Some(item) = self.queue.peek() {
-            return item.instant <= Instant::now();
+    }
+
+    /// The maximum number of queued items, if bounded
+    pub fn capacity(&self) -> Option<usize> { self.capacity }
+
+    /// Whether the queue is at capacity, so `add_item()` refuses more items
+    pub fn is_full(&self) -> bool {
+        self.capacity.is_some_and(|capacity| self.queue.len() >= capacity)
+    }
+
+    /// Adds an item to the queue marked by time. Returns the item back if the queue is
+    /// at capacity.
+    pub fn add_item(&mut self, instant: Instant, item: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(item);
         }
-        false
+
+        self.queue.push(ItemContainer { instant, item, seq: self.seq });
+        self.seq += 1;
+        Ok(())
+    }
+
+    /// Returns whether or not there is an item that is ready to be returned
+    pub fn has_item(&self) -> bool { self.has_item_at(&Instant::now()) }
+
+    /// Like `has_item()`, given the current time
+    pub fn has_item_at(&self, now: &Instant) -> bool {
+        self.queue.peek().is_some_and(|item| item.instant <= *now)
     }
 
     /// Pops an item from the queue if the sufficient time has elapsed
-    pub fn pop_item(&mut self) -> Option<T> {
-        if self.has_item() {
-            if let Some(container) = self.queue.pop() {
-                return Some(container.item);
-            }
+    pub fn pop_item(&mut self) -> Option<T> { self.pop_item_at(&Instant::now()) }
+
+    /// Like `pop_item()`, given the current time
+    pub fn pop_item_at(&mut self, now: &Instant) -> Option<T> {
+        if !self.has_item_at(now) {
+            return None;
         }
-        None
+        self.queue.pop().map(|container| container.item)
+    }
+
+    /// Pops all items whose time has come by `now`, earliest first. Items not consumed
+    /// from the iterator stay queued.
+    pub fn drain_until(&mut self, now: Instant) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(move || self.pop_item_at(&now))
     }
 
     /// Peeks at the top level item container on the queue
@@ -48,6 +86,21 @@ impl<T: Eq + PartialEq> TimeQueue<T> {
         self.queue.peek()
     }
 
+    /// The time of the earliest item, e.g. to sleep until it is due
+    pub fn next_instant(&self) -> Option<Instant> {
+        self.queue.peek().map(|item| item.instant)
+    }
+
+    /// Iterates over the queued items and their times, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&Instant, &T)> + '_ {
+        self.queue.iter().map(|container| (&container.instant, &container.item))
+    }
+
+    /// Removes all items, whether due or not
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
     /// Returns the length of the underlying queue
     pub fn len(&self) -> usize {
         self.queue.len()
@@ -63,11 +116,14 @@ impl<T: Eq + PartialEq> TimeQueue<T> {
 pub struct ItemContainer<T: Eq + PartialEq> {
     pub instant: Instant,
     pub item: T,
+    seq: u64,
 }
 
+/// Reversed, so that the max-heap pops the earliest item first
 impl<T: Eq + PartialEq> Ord for ItemContainer<T> {
     fn cmp(&self, other: &ItemContainer<T>) -> Ordering {
         other.instant.cmp(&self.instant)
+            .then_with(|| other.seq.cmp(&self.seq))
     }
 }
 
@@ -76,3 +132,49 @@ impl<T: Eq + PartialEq> PartialOrd for ItemContainer<T> {
         Some(self.cmp(other))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn pops_in_time_order() {
+        let now = Instant::now();
+        let mut queue = TimeQueue::new();
+        queue.add_item(now + 20 * MS, 3).unwrap();
+        queue.add_item(now, 1).unwrap();
+        queue.add_item(now + 10 * MS, 2).unwrap();
+        queue.add_item(now + 10 * MS, 22).unwrap();
+        assert_eq!(queue.next_instant(), Some(now));
+
+        assert_eq!(queue.pop_item_at(&now), Some(1));
+        assert_eq!(queue.pop_item_at(&now), None);
+        assert!(!queue.has_item_at(&(now + 5 * MS)));
+
+        // equal times pop in insertion order
+        assert_eq!(queue.drain_until(now + 10 * MS).collect::<Vec<_>>(), vec![2, 22]);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.iter().map(|(_, item)| *item).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(queue.drain_until(now + 30 * MS).collect::<Vec<_>>(), vec![3]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn capacity() {
+        let now = Instant::now();
+        let mut queue = TimeQueue::with_capacity(2);
+        queue.add_item(now, 1).unwrap();
+        queue.add_item(now, 2).unwrap();
+        assert!(queue.is_full());
+        assert_eq!(queue.add_item(now, 3), Err(3));
+
+        // partially consumed drains leave the rest queued
+        assert_eq!(queue.drain_until(now).next(), Some(1));
+        assert_eq!(queue.add_item(now, 3), Ok(()));
+        queue.clear();
+        assert!(queue.is_empty());
+    }
+}
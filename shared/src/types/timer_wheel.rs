@@ -0,0 +1,172 @@
+use crate::Instant;
+use std::time::Duration;
+
+/// A hashed timer wheel, which schedules keys by deadline so that only the keys
+/// coming due are visited, rather than polling every key on every tick. Deadlines are
+/// rounded up to the wheel's resolution, so keys never expire early. Deadlines
+/// further out than one revolution of the wheel wait in their slot for as many
+/// revolutions as needed.
+///
+/// Scheduled keys can't be cancelled; a key scheduled more than once expires once per
+/// schedule, so callers should check whether an expired key is still due.
+pub struct TimerWheel<K> {
+	/// Keys by the tick at which they expire, hashed into slots by tick
+	slots: Vec<Vec<(u64, K)>>,
+	/// Keys scheduled at ticks already expired
+	overdue: Vec<K>,
+	resolution: Duration,
+	/// The time of tick 0
+	origin: Instant,
+	/// The first tick not expired yet
+	tick: u64,
+	len: usize,
+}
+
+impl<K> TimerWheel<K> {
+	/// Creates a wheel of `slots` slots, each spanning `resolution`
+	pub fn new(resolution: Duration, slots: usize) -> Self {
+		debug_assert!(!resolution.is_zero(), "resolution must be positive");
+		debug_assert!(slots > 0, "a timer wheel needs at least one slot");
+		Self {
+			slots: (0..slots.max(1)).map(|_| Vec::new()).collect(),
+			overdue: Vec::new(),
+			resolution: resolution.max(Duration::from_nanos(1)),
+			origin: Instant::now(),
+			tick: 0,
+			len: 0,
+		}
+	}
+
+	/// The tick `time` falls in, rounded down or up
+	fn tick_of(&self, time: &Instant, round_up: bool) -> u64 {
+		let elapsed = time.saturating_duration_since(self.origin).as_nanos();
+		let resolution = self.resolution.as_nanos();
+		let tick = match round_up {
+			true => elapsed.div_ceil(resolution),
+			false => elapsed / resolution,
+		};
+		tick.try_into().unwrap_or(u64::MAX)
+	}
+
+	/// Schedule `key` to expire at `deadline`. Past deadlines expire on the next call
+	/// to `expire()`.
+	pub fn schedule(&mut self, key: K, deadline: Instant) {
+		let tick = self.tick_of(&deadline, true);
+		self.len += 1;
+		if tick < self.tick {
+			self.overdue.push(key);
+			return;
+		}
+
+		let slot = (tick % self.slots.len() as u64) as usize;
+		self.slots[slot].push((tick, key));
+	}
+
+	/// Remove and return the keys whose deadline passed by `now`, in no particular
+	/// order
+	pub fn expire(&mut self, now: &Instant) -> Vec<K> {
+		let now_tick = self.tick_of(now, false);
+		let mut expired = std::mem::take(&mut self.overdue);
+		if now_tick < self.tick {
+			self.len -= expired.len();
+			return expired;
+		}
+
+		// visit each elapsed slot, but each slot only once per call
+		let count = self.slots.len() as u64;
+		let elapsed = (now_tick - self.tick + 1).min(count);
+		for tick in self.tick..self.tick + elapsed {
+			let slot = &mut self.slots[(tick % count) as usize];
+			let mut i = 0;
+			while i < slot.len() {
+				if slot[i].0 <= now_tick {
+					expired.push(slot.swap_remove(i).1);
+				} else {
+					i += 1;
+				}
+			}
+		}
+
+		self.tick = now_tick + 1;
+		self.len -= expired.len();
+		expired
+	}
+
+	/// The number of scheduled keys
+	pub fn len(&self) -> usize { self.len }
+
+	pub fn is_empty(&self) -> bool { self.len == 0 }
+
+	/// Remove all scheduled keys
+	pub fn clear(&mut self) {
+		self.slots.iter_mut().for_each(Vec::clear);
+		self.overdue.clear();
+		self.len = 0;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const MS: Duration = Duration::from_millis(1);
+
+	fn sorted(mut keys: Vec<u32>) -> Vec<u32> {
+		keys.sort();
+		keys
+	}
+
+	#[test]
+	fn expires_by_deadline() {
+		let mut wheel = TimerWheel::new(10 * MS, 8);
+		let start = wheel.origin;
+		wheel.schedule(1, start + 15 * MS);
+		wheel.schedule(2, start + 30 * MS);
+		wheel.schedule(3, start);
+		assert_eq!(wheel.len(), 3);
+
+		assert_eq!(wheel.expire(&start), vec![3]);
+		// rounded up to the next tick, never early
+		assert!(wheel.expire(&(start + 15 * MS)).is_empty());
+		assert_eq!(wheel.expire(&(start + 20 * MS)), vec![1]);
+		assert_eq!(wheel.expire(&(start + 35 * MS)), vec![2]);
+		assert!(wheel.is_empty());
+	}
+
+	#[test]
+	fn multiple_revolutions() {
+		// one revolution spans 40ms
+		let mut wheel = TimerWheel::new(10 * MS, 4);
+		let start = wheel.origin;
+		wheel.schedule(1, start + 10 * MS);
+		wheel.schedule(2, start + 50 * MS);
+		wheel.schedule(3, start + 130 * MS);
+
+		assert_eq!(wheel.expire(&(start + 10 * MS)), vec![1]);
+		assert!(wheel.expire(&(start + 40 * MS)).is_empty());
+		assert_eq!(wheel.expire(&(start + 50 * MS)), vec![2]);
+		assert_eq!(wheel.len(), 1);
+
+		// skipping past several revolutions at once visits every slot once
+		wheel.schedule(4, start + 60 * MS);
+		assert_eq!(sorted(wheel.expire(&(start + 500 * MS))), vec![3, 4]);
+		assert!(wheel.is_empty());
+	}
+
+	#[test]
+	fn past_deadlines() {
+		let mut wheel = TimerWheel::new(10 * MS, 4);
+		let start = wheel.origin;
+		assert!(wheel.expire(&(start + 100 * MS)).is_empty());
+
+		// already past, so due on the next expire
+		wheel.schedule(1, start + 20 * MS);
+		wheel.schedule(2, start + 20 * MS);
+		assert_eq!(sorted(wheel.expire(&(start + 100 * MS))), vec![1, 2]);
+
+		wheel.schedule(3, start);
+		wheel.clear();
+		assert!(wheel.is_empty());
+		assert!(wheel.expire(&(start + 200 * MS)).is_empty());
+	}
+}
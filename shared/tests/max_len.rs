@@ -0,0 +1,59 @@
+mod some_struct {
+    use naia_shared::{Message, Serde};
+
+    pub const MAX_NAME: usize = 16;
+
+    #[derive(Clone, Debug, PartialEq, Serde)]
+    pub struct Profile {
+        #[serde(max_len = MAX_NAME)]
+        pub name: String,
+        #[serde(max_len = 64)]
+        pub avatar: Vec<u8>,
+        pub bio: String,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serde)]
+    pub struct Tag(#[serde(max_len = 3)] pub String, pub u8);
+
+    #[derive(Message)]
+    pub struct Rename {
+        #[serde(max_len = MAX_NAME)]
+        pub name: String,
+    }
+}
+
+use naia_shared::{BitReader, BitWriter, ConstBitLength, Message, MessageKind, Serde, SerdeBounded};
+use some_struct::{Profile, Rename, Tag};
+
+#[test]
+fn read_write_max_len() {
+    let mut writer = BitWriter::new();
+    let profile = Profile { name: "alice".to_string(), avatar: vec![7; 64], bio: "hi".to_string() };
+    let tag = Tag("gg".to_string(), 1);
+    profile.ser(&mut writer);
+    tag.ser(&mut writer);
+
+    // 5 bit name length, 7 bit avatar length, and a variable length bio
+    assert_eq!(profile.bit_length(), 5 + 40 + 7 + 512 + "hi".to_string().bit_length());
+    assert_eq!(tag.bit_length(), 2 + 16 + 8);
+
+    let mut reader = BitReader::from_slice(writer.slice());
+    assert_eq!(Profile::de(&mut reader).unwrap(), profile);
+    assert_eq!(Tag::de(&mut reader).unwrap(), tag);
+
+    let rename = Rename { name: "bob".to_string() };
+    assert_eq!(Message::bit_length(&rename), <MessageKind as ConstBitLength>::const_bit_length() + 5 + 24);
+    assert_eq!(Rename::fields()[0].type_name, "String(max_len=MAX_NAME)");
+}
+
+#[test]
+fn read_beyond_max_len() {
+    // a length that fits the prefix, but exceeds the limit
+    let mut writer = BitWriter::new();
+    "a".repeat(20).ser_bounded(31, &mut writer);
+    Vec::<u8>::new().ser_bounded(64, &mut writer);
+    String::new().ser(&mut writer);
+
+    let mut reader = BitReader::from_slice(writer.slice());
+    assert!(Profile::de(&mut reader).is_err());
+}
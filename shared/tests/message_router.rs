@@ -0,0 +1,40 @@
+use naia_shared::{Message, MessageContainer, MessageRouter};
+
+#[derive(Message)]
+pub struct Chat {
+	pub text: String,
+}
+
+#[derive(Message)]
+pub struct Move {
+	pub x: i16,
+	pub y: i16,
+}
+
+#[derive(Message)]
+pub struct Unrouted;
+
+#[derive(MessageRouter)]
+pub enum ClientMessage {
+	Chat(Chat),
+	Move(Move),
+}
+
+#[test]
+fn routes_by_type() {
+	let chat = MessageContainer::from_read(Box::new(Chat { text: "hi".to_string() }));
+	match ClientMessage::route(chat) {
+		Ok(ClientMessage::Chat(chat)) => assert_eq!(chat.text, "hi"),
+		_ => panic!("expected Chat"),
+	}
+
+	let moved = MessageContainer::from_read(Box::new(Move { x: 1, y: -2 }));
+	match ClientMessage::try_from(moved) {
+		Ok(ClientMessage::Move(moved)) => assert_eq!((moved.x, moved.y), (1, -2)),
+		_ => panic!("expected Move"),
+	}
+
+	// messages without a variant are given back
+	let other = MessageContainer::from_read(Box::new(Unrouted));
+	assert!(ClientMessage::route(other).err().unwrap().is::<Unrouted>());
+}
@@ -0,0 +1,58 @@
+/// Stands in for a type from a foreign crate, which doesn't implement Serde
+mod foreign {
+	#[derive(Clone, Debug, PartialEq)]
+	pub struct Vec3 {
+		pub x: f32,
+		pub y: f32,
+		pub z: f32,
+	}
+
+	impl From<Vec3> for [f32; 3] {
+		fn from(v: Vec3) -> Self { [v.x, v.y, v.z] }
+	}
+
+	impl From<[f32; 3]> for Vec3 {
+		fn from([x, y, z]: [f32; 3]) -> Self { Self { x, y, z } }
+	}
+}
+
+mod some_message {
+	use naia_shared::{Message, serde_remote};
+
+	serde_remote! {
+		#[derive(Debug)]
+		pub struct Position(pub super::foreign::Vec3) as [f32; 3];
+	}
+
+	#[derive(Message)]
+	pub struct Spawn {
+		pub id: u16,
+		pub position: Position,
+	}
+}
+
+use naia_shared::{BitReader, BitWriter, Message, Serde};
+use foreign::Vec3;
+use some_message::{Position, Spawn};
+
+#[test]
+fn read_write_remote() {
+	let position = Position::from(Vec3 { x: 1.0, y: -2.5, z: 3.0 });
+	assert_eq!(position.bit_length(), 96);
+	assert_eq!(position.y, -2.5);
+
+	let mut writer = BitWriter::new();
+	position.ser(&mut writer);
+	7u8.ser(&mut writer);
+
+	let mut reader = BitReader::from_slice(writer.slice());
+	assert_eq!(Position::de(&mut reader).unwrap(), position);
+	assert_eq!(u8::de(&mut reader).unwrap(), 7);
+	assert_eq!(Vec3::from(position.clone()), Vec3 { x: 1.0, y: -2.5, z: 3.0 });
+
+	// usable as a message field
+	let spawn = Spawn { id: 1, position };
+	assert_eq!(spawn.position.x, 1.0);
+	let fields = Spawn::fields();
+	assert_eq!((fields[1].name.as_str(), fields[1].type_name.as_str()), ("position", "Position"));
+}
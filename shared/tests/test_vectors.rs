@@ -0,0 +1,281 @@
+//! Golden byte captures of each packet type, to catch unintended wire format changes.
+//! Encrypted packet types are captured as plaintext, i.e. before encryption, with their
+//! reserved (zeroed) encryption tag. Changing any vector is a wire format break.
+
+use naia_shared::*;
+use naia_shared::packet::packet;
+
+#[derive(Message)]
+pub struct Auth {
+	pub token: String,
+}
+
+#[derive(Message)]
+pub struct Move {
+	pub x: i16,
+	pub y: i16,
+}
+
+#[derive(Channel)]
+pub struct Chat;
+
+#[derive(Channel)]
+pub struct Events;
+
+fn schema() -> Schema {
+	Schema::builder()
+		.add_channel::<Chat>(ChannelDirection::Bidirectional, ChannelMode::UnorderedUnreliable)
+		.add_channel::<Events>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.add_message::<Move>()
+		.build()
+}
+
+fn header(packet_type: PacketType, packet_seq: u16) -> PacketWriter {
+	PacketWriter::new(PacketHeader { packet_type, packet_seq: packet_seq.into() })
+}
+
+fn write_packet(packet_type: PacketType, packet_seq: u16, body: &impl Serde) -> Vec<u8> {
+	let mut writer = header(packet_type, packet_seq);
+	writer.write(body);
+	writer.slice().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Assert that the serialized packet matches the vector, and that the vector parses
+/// back into an identical header and body
+fn assert_vector<T: Serde + std::fmt::Debug>(
+	packet_type: PacketType, packet_seq: u16, body: &T, vector: &str,
+) {
+	assert_eq!(hex(&write_packet(packet_type, packet_seq, body)), vector, "{packet_type:?}");
+
+	let bytes = unhex(vector);
+	let mut reader = BitReader::from_slice(&bytes);
+	let header = reader.read::<PacketHeader>().unwrap();
+	assert_eq!(header, PacketHeader { packet_type, packet_seq: packet_seq.into() });
+	if packet_type.is_encrypted() {
+		assert_eq!(reader.read::<[u8; packet::ENCRYPT_TAG_SIZE]>().unwrap(), [0; packet::ENCRYPT_TAG_SIZE]);
+	}
+	assert_eq!(&reader.read::<T>().unwrap(), body, "{packet_type:?}");
+}
+
+fn unhex(vector: &str) -> Vec<u8> {
+	(0..vector.len()).step_by(2)
+		.map(|i| u8::from_str_radix(&vector[i..i + 2], 16).unwrap())
+		.collect()
+}
+
+fn ack_header() -> packet::Data {
+	let mut ack_bitfield = AckBitfield::new(AckWindow::Bits32);
+	ack_bitfield.set(1);
+	ack_bitfield.set(3);
+	packet::Data { ack_index: 41.into(), ack_bitfield }
+}
+
+fn auth() -> MessageContainer {
+	MessageContainer::from_write(Box::new(Auth { token: "secret".to_string() }))
+}
+
+#[test]
+fn handshake() {
+	assert_vector(
+		PacketType::HandshakeReject, 0,
+		&packet::HandshakeReject { reason: RejectReason::ServerFull },
+		"00000040",
+	);
+	let request = packet::EncryptRequest {
+		client_public_key: std::array::from_fn(|i| i as u8),
+		client_timestamp_ns: 0x0102030405060708,
+		cipher: CipherKind::ChaCha20Poly1305,
+		cookie: None,
+	};
+	const REQUEST: &str =
+		"801000000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f080706050403020100";
+	assert_vector(PacketType::EncryptRequest, 1, &request, REQUEST);
+	let mut writer = header(PacketType::EncryptRequest, 1);
+	writer.write(&request);
+	packet::EncryptRequest::write_padding(&mut writer, packet::EncryptRequest::PADDING_SIZE);
+	let padded = format!("{REQUEST}{}", "00".repeat(packet::EncryptRequest::PADDING_SIZE));
+	assert_eq!(hex(writer.slice()), padded);
+	let bytes = unhex(&padded);
+	let mut reader = BitReader::from_slice(&bytes);
+	reader.read::<PacketHeader>().unwrap();
+	assert_eq!(reader.read::<packet::EncryptRequest>().unwrap(), request);
+	assert_eq!(packet::EncryptRequest::read_padding(&mut reader), Ok(packet::EncryptRequest::PADDING_SIZE));
+	assert_vector(
+		PacketType::EncryptRequest, 1,
+		&packet::EncryptRequest {
+			cookie: Some(packet::HandshakeCookie { issued_ns: 0x2122232425262728, tag: 0x3132333435363738 }),
+			..request
+		},
+		"801000000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f08070605040302012504e4c4a48464442706e6c6a686664620",
+	);
+	assert_vector(
+		PacketType::HandshakeRetry, 0,
+		&packet::HandshakeRetry {
+			cookie: packet::HandshakeCookie { issued_ns: 0x2122232425262728, tag: 0x3132333435363738 },
+		},
+		"30000028272625242322213837363534333231",
+	);
+	assert_vector(
+		PacketType::EncryptResponse, 1,
+		&packet::EncryptResponse {
+			server_public_key: std::array::from_fn(|i| 0xff - i as u8),
+			client_timestamp_ns: 0x0102030405060708,
+			server_timestamp_ns: 0x1112131415161718,
+			cipher: CipherKind::Aes256Gcm,
+		},
+		"401000fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0efeeedecebeae9e8e7e6e5e4e3e2e1e00807060504030201181716151413121180",
+	);
+	assert_vector(
+		PacketType::ConnectResponse, 2,
+		&packet::ConnectResponse {
+			client_timestamp_ns: 0x2122232425262728, session_id: 0xdeadbeef, observer: true,
+			compact_headers: true,
+		},
+		"202000000000000000000000000000000000002827262524232221efbeadde00000000c0",
+	);
+}
+
+#[test]
+fn connect_request() {
+	const VECTOR: &str = "c0200000000000000000000000000000000000080706050403020118171615141312114a09c9894908c88850100301cd958dc995d0";
+	let schema = schema();
+	let request = packet::ConnectRequest {
+		client_timestamp_ns: 0x0102030405060708,
+		server_timestamp_ns: 0x1112131415161718,
+		observer: false,
+		compact_headers: true,
+		schema_fingerprint: 0x2122232425262728,
+		identity: None,
+	};
+
+	// the optional connect message follows the request
+	let mut writer = header(PacketType::ConnectRequest, 2);
+	writer.write(&request);
+	writer.write(&true);
+	auth().write(schema.message_kinds(), &mut writer);
+	assert_eq!(hex(writer.slice()), VECTOR);
+
+	let bytes = unhex(VECTOR);
+	let mut reader = BitReader::from_slice(&bytes);
+	assert_eq!(reader.read::<PacketHeader>().unwrap().packet_type, PacketType::ConnectRequest);
+	assert_eq!(reader.read::<[u8; packet::ENCRYPT_TAG_SIZE]>().unwrap(), [0; packet::ENCRYPT_TAG_SIZE]);
+	assert_eq!(reader.read::<packet::ConnectRequest>().unwrap(), request);
+	assert!(reader.read::<bool>().unwrap());
+	let message = schema.message_kinds().read(&mut reader).unwrap();
+	assert_eq!(message.downcast::<Auth>().ok().unwrap().token, "secret");
+}
+
+#[test]
+fn connection() {
+	let ack_header = ack_header();
+
+	assert_vector(
+		PacketType::Ping, 7, &packet::Ping { timestamp_ns: 1_000_000 },
+		"a070000000000000000000000000000000000040420f0000000000",
+	);
+	assert_vector(
+		PacketType::Pong, 8, &packet::Pong { timestamp_ns: 1_000_000 },
+		"6080000000000000000000000000000000000040420f0000000000",
+	);
+	assert_vector(
+		PacketType::Heartbeat, 12, &ack_header,
+		"e0c0000000000000000000000000000000000029000140000000",
+	);
+	assert_vector(
+		PacketType::Migrate, 10,
+		&packet::Migrate { session_id: 0xdeadbeef, salt: [0x55; 16], tag: [0xaa; packet::ENCRYPT_TAG_SIZE] },
+		"10a000efbeadde0000000055555555555555555555555555555555aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+	);
+	assert_vector(
+		PacketType::Disconnect, 9, &packet::Disconnect,
+		"50900000000000000000000000000000000000",
+	);
+	assert_vector(
+		PacketType::Redirect, 11,
+		&packet::Redirect { address: "127.0.0.1:4000".parse().unwrap(), token: 42 },
+		"d0b000000000000000000000000000000000003f800000d007950000000000000000",
+	);
+}
+
+#[test]
+fn compact_header() {
+	let ack_header = ack_header();
+	let cases = [
+		(PacketType::Heartbeat, 12, 11, "f3000000000000000000000000000000000029000140000000"),
+		(PacketType::Data, 0x1001, 0x0fff, "78000000000000000000000000000000000029000140000000"),
+		// expanded across the sequence number rollover
+		(PacketType::Data, 0x0002, 0xfffe, "74000000000000000000000000000000000029000140000000"),
+	];
+	for (packet_type, packet_seq, last_seq, vector) in cases {
+		let header = PacketHeader { packet_type, packet_seq: packet_seq.into() };
+		let mut writer = PacketWriter::new_compact(header.clone());
+		writer.write(&ack_header);
+		assert_eq!(hex(writer.slice()), vector, "{packet_type:?}");
+
+		let bytes = unhex(vector);
+		let mut reader = BitReader::from_slice(&bytes);
+		assert_eq!(PacketHeader::read_any(&mut reader, last_seq.into()).unwrap(), header);
+		assert_eq!(reader.read::<[u8; packet::ENCRYPT_TAG_SIZE]>().unwrap(), [0; packet::ENCRYPT_TAG_SIZE]);
+		assert_eq!(reader.read::<packet::Data>().unwrap(), ack_header);
+	}
+
+	// full headers still parse
+	let bytes = unhex("e0c0000000000000000000000000000000000029000140000000");
+	let mut reader = BitReader::from_slice(&bytes);
+	assert_eq!(
+		PacketHeader::read_any(&mut reader, 0.into()).unwrap(),
+		PacketHeader { packet_type: PacketType::Heartbeat, packet_seq: 12.into() },
+	);
+}
+
+#[test]
+fn data() {
+	// the order of channels within a packet is unspecified
+	const VECTORS: [&str; 2] = [
+		"90d00000000000000000000000000000000000290001400000380000080100d0d2a04000201ffff02002c0100000",
+		"90d000000000000000000000000000000000002900014000002810000807fffc0800b0040001c000004008068690",
+	];
+	let schema = schema();
+	let ack_header = ack_header();
+
+	let mut sender = MessageManager::new(HostType::Client, schema.channel_kinds(), &ReassemblyConfig::default());
+	let mut queue = |channel_kind: ChannelKind, message: MessageContainer| {
+		sender.queue_message(schema.message_kinds(), &channel_kind, message).unwrap();
+	};
+	queue(ChannelKind::of::<Chat>(), MessageContainer::from_write(Box::new(Move { x: 1, y: -1 })));
+	queue(ChannelKind::of::<Chat>(), MessageContainer::from_write(Box::new(Move { x: 300, y: 0 })));
+	queue(ChannelKind::of::<Events>(), MessageContainer::from_write(Box::new(Auth { token: "hi".to_string() })));
+	sender.collect_messages(&Instant::now(), 0.0, 0.0);
+
+	let mut writer = header(PacketType::Data, 13);
+	writer.write(&ack_header);
+	sender.write_messages(&schema, writer.inner_mut(), 13.into());
+	assert!(VECTORS.contains(&hex(writer.slice()).as_str()));
+
+	for vector in VECTORS {
+		let bytes = unhex(vector);
+		let mut reader = BitReader::from_slice(&bytes);
+		let header = reader.read::<PacketHeader>().unwrap();
+		assert_eq!(header, PacketHeader { packet_type: PacketType::Data, packet_seq: 13.into() });
+		assert_eq!(reader.read::<[u8; packet::ENCRYPT_TAG_SIZE]>().unwrap(), [0; packet::ENCRYPT_TAG_SIZE]);
+		assert_eq!(reader.read::<packet::Data>().unwrap(), ack_header);
+
+		let mut receiver = MessageManager::new(HostType::Server, schema.channel_kinds(), &ReassemblyConfig::default());
+		receiver.read_messages(&schema, &mut reader).unwrap();
+		let mut moves = Vec::new();
+		let mut tokens = Vec::new();
+		for message in receiver.receive_messages() {
+			match message.downcast::<Move>() {
+				Ok(m) => moves.push((m.x, m.y)),
+				Err(message) => tokens.push(message.downcast::<Auth>().ok().unwrap().token),
+			}
+		}
+		assert_eq!(moves, [(1, -1), (300, 0)]);
+		assert_eq!(tokens, ["hi"]);
+	}
+}
@@ -0,0 +1,153 @@
+use naia_client::*;
+use naia_extras::admin::*;
+use naia_server::*;
+use naia_shared::*;
+use std::{net::Ipv4Addr, time::Duration};
+
+#[derive(Message)]
+pub struct Auth {
+	pub token: String,
+}
+
+/// Returns the disconnected users
+fn pump(
+	server: &mut Server, admin: &mut AdminServer, clients: &mut [(Client, AdminClient)],
+) -> Vec<UserKey> {
+	let mut disconnects = Vec::new();
+	for (client, _) in clients.iter_mut() {
+		client.send();
+	}
+	let events = server.receive();
+	for event in admin.process(server, events) {
+		match event {
+			ServerEvent::Connect { user_key, msg: Some(msg), ctx, .. } => {
+				if msg.downcast::<Auth>().unwrap().token == "secret" {
+					admin.grant(&user_key, ADMIN_ROLE);
+				}
+				server.accept_connection(&user_key, &ctx).unwrap();
+			}
+			ServerEvent::Disconnect { user_key, .. } => disconnects.push(user_key),
+			_ => (),
+		}
+	}
+	server.send();
+	for (client, admin) in clients.iter_mut() {
+		let events = client.receive();
+		admin.process(events);
+	}
+	disconnects
+}
+
+fn response(admin: &mut AdminClient, id: AdminRequestId) -> AdminResponse {
+	let mut responses = admin.take_responses();
+	assert_eq!(responses.len(), 1);
+	let response = responses.pop().unwrap();
+	assert_eq!(response.id, id);
+	response
+}
+
+#[test]
+fn admin() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4008).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder().add_plugin(&AdminPlugin).add_message::<Auth>().build();
+	let mut server = Server::new(server_config, schema());
+	let mut admin = AdminServer::new();
+	admin.add_command("echo", &[ADMIN_ROLE, "moderator"], Box::new(
+		|_: &mut Server, _: &UserKey, args: &[String]| Ok(args.join(" "))
+	));
+	server.listen(server_addr).unwrap();
+
+	let connect = |token: &str| {
+		let mut client = Client::new(client_config.clone(), schema());
+		client.connect(server_addr, Auth { token: token.to_string() }).unwrap();
+		(client, AdminClient::new())
+	};
+	let mut clients = vec![connect("secret"), connect("guest"), connect("guest")];
+
+	for _ in 0..3 {
+		pump(&mut server, &mut admin, &mut clients);
+	}
+	assert!(clients.iter().all(|(client, _)| client.is_connected()));
+
+	// unprivileged clients are refused
+	let id = {
+		let (client, admin) = &mut clients[1];
+		admin.run(client, "kick", &["0"])
+	};
+	for _ in 0..2 {
+		pump(&mut server, &mut admin, &mut clients);
+	}
+	assert!(!response(&mut clients[1].1, id).ok);
+	assert_eq!(admin.denied_count(), 1);
+	assert_eq!(server.users_count(), 3);
+
+	// custom and built-in commands
+	let (echo, metrics) = {
+		let (client, admin) = &mut clients[0];
+		(admin.run(client, "echo", &["hello", "world"]), admin.run(client, "metrics", &[]))
+	};
+	for _ in 0..2 {
+		pump(&mut server, &mut admin, &mut clients);
+	}
+	let mut responses = clients[0].1.take_responses();
+	assert_eq!(responses.len(), 2);
+	let metrics_response = responses.pop().unwrap();
+	let echo_response = responses.pop().unwrap();
+	assert!(echo_response.ok && echo_response.id == echo && echo_response.output == "hello world");
+	assert!(metrics_response.ok && metrics_response.id == metrics);
+	assert!(metrics_response.output.contains("users: 3"));
+
+	// kick
+	let target = server.user_keys().into_iter()
+		.find(|user_key| !admin.is_allowed(user_key, "kick"))
+		.unwrap();
+	let id = {
+		let (client, admin) = &mut clients[0];
+		admin.run(client, "kick", &[&target.to_string()])
+	};
+	let disconnects: Vec<_> = (0..2)
+		.flat_map(|_| pump(&mut server, &mut admin, &mut clients))
+		.collect();
+	assert!(response(&mut clients[0].1, id).ok);
+	assert_eq!(disconnects, [target]);
+
+	// banning localhost disconnects everyone, and rejects new connections
+	let users = server.user_keys();
+	{
+		let (client, admin) = &mut clients[0];
+		admin.run(client, "ban", &["127.0.0.1"]);
+	}
+	let disconnects: Vec<_> = (0..2)
+		.flat_map(|_| pump(&mut server, &mut admin, &mut clients))
+		.collect();
+	assert!(users.iter().all(|user_key| disconnects.contains(user_key)));
+
+	let (mut client, _) = connect("secret");
+	let mut rejected = false;
+	for _ in 0..3 {
+		client.send();
+		let events = server.receive();
+		admin.process(&mut server, events);
+		server.send();
+		rejected = client.receive().iter().any(|event| matches!(event, ClientEvent::Reject(..)));
+		if rejected {
+			break;
+		}
+	}
+	assert!(rejected);
+}
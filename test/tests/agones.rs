@@ -0,0 +1,124 @@
+use naia_client::*;
+use naia_server::*;
+use naia_shared::*;
+use std::{
+	io::{Read, Write},
+	net::{Ipv4Addr, SocketAddr, TcpListener},
+	sync::mpsc,
+	thread,
+	time::{Duration, Instant},
+};
+
+#[derive(Message)]
+pub struct Auth;
+
+fn schema() -> Schema {
+	Schema::builder().add_message::<Auth>().build()
+}
+
+/// Answers every request with 200, yielding the requested paths
+fn sidecar() -> (SocketAddr, mpsc::Receiver<String>) {
+	let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+	let addr = listener.local_addr().unwrap();
+	let (tx, rx) = mpsc::channel();
+	thread::spawn(move || {
+		for mut stream in listener.incoming().flatten() {
+			let mut request = Vec::new();
+			let mut buf = [0; 512];
+			while !request.ends_with(b"{}") {
+				match stream.read(&mut buf) {
+					Ok(0) | Err(_) => break,
+					Ok(len) => request.extend_from_slice(&buf[..len]),
+				}
+			}
+			let request = String::from_utf8(request).unwrap();
+			let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}");
+			let path = request.split_ascii_whitespace().nth(1).unwrap_or_default().to_string();
+			if tx.send(path).is_err() {
+				break;
+			}
+		}
+	});
+	(addr, rx)
+}
+
+/// Waits for `count` calls other than health checks, then a little longer for any
+/// unexpected ones
+fn calls(requests: &mpsc::Receiver<String>, count: usize) -> Vec<String> {
+	let mut calls = Vec::new();
+	loop {
+		let timeout = if calls.len() < count { Duration::from_secs(2) } else { Duration::from_millis(50) };
+		match requests.recv_timeout(timeout) {
+			Ok(path) if path == "/health" => (),
+			Ok(path) => calls.push(path),
+			Err(_) => return calls,
+		}
+	}
+}
+
+#[test]
+fn lifecycle() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4034).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let (sidecar_addr, requests) = sidecar();
+
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let hooks = AgonesHooks::new(AgonesSdk::new(sidecar_addr), Duration::from_secs(60));
+	server.set_lifecycle_hooks(Box::new(hooks));
+	server.listen(server_addr).unwrap();
+	assert_eq!(calls(&requests, 1), ["/ready"]);
+
+	let mut client = Client::new(client_config.clone(), schema());
+	client.connect(server_addr, Auth).unwrap();
+	for _ in 0..4 {
+		client.send();
+		for event in server.receive() {
+			if let ServerEvent::Connect { user_key, ctx, .. } = event {
+				server.accept_connection(&user_key, &ctx).unwrap();
+			}
+		}
+		server.send();
+		client.receive();
+	}
+	assert!(client.is_connected());
+	assert_eq!(calls(&requests, 1), ["/allocate"]);
+
+	// draining refuses new users, and shuts down once the last one left
+	server.drain();
+	assert!(server.is_draining());
+	let mut late = Client::new(client_config, schema());
+	late.connect(server_addr, Auth).unwrap();
+	while !late.is_disconnected() {
+		late.send();
+		server.receive();
+		server.send();
+		late.receive();
+	}
+	assert_eq!(server.users_count(), 1);
+	assert!(calls(&requests, 0).is_empty());
+
+	client.disconnect().unwrap();
+	for _ in 0..4 {
+		server.receive();
+		server.send();
+	}
+	assert_eq!(server.users_count(), 0);
+	assert_eq!(calls(&requests, 1), ["/shutdown"]);
+}
+
+#[test]
+fn slow_sidecar() {
+	// accepts connections into its backlog, but never answers
+	let sidecar = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+	let hooks = AgonesHooks::new(AgonesSdk::new(sidecar.local_addr().unwrap()), Duration::ZERO);
+
+	let mut server = Server::new(ServerConfig::default(), schema());
+	server.set_lifecycle_hooks(Box::new(hooks));
+	let start = Instant::now();
+	server.listen((Ipv4Addr::LOCALHOST, 4078).into()).unwrap();
+	for _ in 0..10 {
+		server.receive();
+		server.send();
+	}
+	assert!(start.elapsed() < Duration::from_millis(500));
+}
@@ -0,0 +1,170 @@
+use naia_client::*;
+use naia_extras::chat::*;
+use naia_server::*;
+use naia_shared::*;
+use std::{net::Ipv4Addr, time::Duration};
+
+#[derive(Message)]
+pub struct Auth;
+
+struct Censor;
+
+impl ChatFilter for Censor {
+	fn filter(&mut self, _user_key: &UserKey, text: &mut String) -> bool {
+		*text = text.replace("darn", "****");
+		!text.contains("spam")
+	}
+}
+
+fn pump(server: &mut Server, chat: &mut ChatServer, clients: &mut [(Client, ChatClient)]) {
+	for (client, _) in clients.iter_mut() {
+		client.send();
+	}
+	let events = server.receive();
+	for event in chat.process(server, events) {
+		if let ServerEvent::Connect { user_key, ctx, .. } = event {
+			server.accept_connection(&user_key, &ctx).unwrap();
+		}
+	}
+	server.send();
+	for (client, chat) in clients.iter_mut() {
+		let events = client.receive();
+		chat.process(events);
+	}
+}
+
+/// A Server and two connected Clients, with a chat limited to `burst` messages
+fn setup(port: u16, burst: u32) -> (Server, ChatServer, Vec<(Client, ChatClient)>) {
+	let server_addr = (Ipv4Addr::LOCALHOST, port).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		..ClientConfig::default()
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder().add_plugin(&ChatPlugin).add_message::<Auth>().build();
+	let mut server = Server::new(server_config, schema());
+	let mut chat = ChatServer::new(ChatConfig { rate: 0.0, burst, ..ChatConfig::default() });
+	server.listen(server_addr).unwrap();
+
+	let mut clients: Vec<_> = (0..2).map(|_| {
+		let mut client = Client::new(client_config.clone(), schema());
+		client.connect(server_addr, Auth).unwrap();
+		(client, ChatClient::new())
+	}).collect();
+
+	for _ in 0..3 {
+		pump(&mut server, &mut chat, &mut clients);
+	}
+	assert!(clients.iter().all(|(client, _)| client.is_connected()));
+	(server, chat, clients)
+}
+
+#[test]
+fn chat() {
+	let (mut server, mut chat, mut clients) = setup(4006, 4);
+	chat.set_filter(Box::new(Censor));
+
+	// join
+	for (i, (client, chat)) in clients.iter_mut().enumerate() {
+		chat.join(client, &format!("player{i}"));
+	}
+	for _ in 0..2 {
+		pump(&mut server, &mut chat, &mut clients);
+	}
+	assert_eq!(chat.members().count(), 2);
+	for (_, chat) in &mut clients {
+		assert_eq!(chat.members().len(), 2);
+		chat.take_events();
+	}
+
+	// texts are filtered and rate limited
+	{
+		let (client, chat) = &mut clients[0];
+		chat.say(client, "darn it");
+		chat.say(client, "spam");
+		chat.say(client, "ok");
+		chat.say(client, "too many");
+	}
+	for _ in 0..2 {
+		pump(&mut server, &mut chat, &mut clients);
+	}
+	// "spam" fails the filter and "too many" exceeds the burst, after the join
+	assert_eq!(chat.drop_count(), 2);
+	for (_, chat) in &mut clients {
+		let texts: Vec<_> = chat.take_events().into_iter().filter_map(|event| match event {
+			ChatEvent::Text { text, .. } => Some(text),
+			_ => None,
+		}).collect();
+		assert_eq!(texts, ["**** it", "ok"]);
+	}
+
+	// whispers only reach the recipient
+	let id_of = |chat: &ChatClient, name: &str| {
+		*chat.members().iter().find(|(_, member)| *member == name).unwrap().0
+	};
+	let (player0, player1) = (id_of(&clients[1].1, "player0"), id_of(&clients[1].1, "player1"));
+	{
+		let (client, chat) = &mut clients[1];
+		chat.whisper(client, player0, "psst");
+	}
+	for _ in 0..2 {
+		pump(&mut server, &mut chat, &mut clients);
+	}
+	assert!(clients[1].1.take_events().is_empty());
+	assert!(matches!(
+		clients[0].1.take_events().as_slice(),
+		[ChatEvent::Whisper { user_id, text }] if *user_id == player1 && text == "psst"
+	));
+
+	// leave
+	{
+		let (client, chat) = &mut clients[1];
+		chat.leave(client);
+	}
+	for _ in 0..2 {
+		pump(&mut server, &mut chat, &mut clients);
+	}
+	assert_eq!(chat.members().count(), 1);
+	assert!(matches!(clients[0].1.take_events().as_slice(), [ChatEvent::Leave { name, .. }] if name == "player1"));
+}
+
+#[test]
+fn chat_rename_flood() {
+	let (mut server, mut chat, mut clients) = setup(4076, 3);
+	for (i, (client, chat)) in clients.iter_mut().enumerate() {
+		chat.join(client, &format!("player{i}"));
+	}
+	for _ in 0..2 {
+		pump(&mut server, &mut chat, &mut clients);
+	}
+	for (_, chat) in &mut clients {
+		chat.take_events();
+	}
+
+	// renames, leaves and joins again all draw from the same bucket
+	{
+		let (client, chat) = &mut clients[0];
+		for i in 0..5 {
+			chat.join(client, &format!("flood{i}"));
+		}
+		chat.leave(client);
+		chat.join(client, "rejoined");
+	}
+	for _ in 0..2 {
+		pump(&mut server, &mut chat, &mut clients);
+	}
+	assert_eq!(chat.drop_count(), 5);
+	let names: Vec<_> = clients[1].1.take_events().into_iter().filter_map(|event| match event {
+		ChatEvent::Join { name, .. } => Some(name),
+		_ => None,
+	}).collect();
+	assert_eq!(names, ["flood0", "flood1"]);
+}
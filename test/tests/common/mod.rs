@@ -0,0 +1,63 @@
+//! The protocol and helpers shared by the session handoff and resume tests
+#![allow(dead_code)]
+
+use naia_client::*;
+use naia_server::*;
+use naia_shared::*;
+use std::time::Duration;
+
+#[derive(Message)]
+pub struct Auth;
+
+#[derive(Message)]
+pub struct Text {
+	pub text: String,
+}
+
+#[derive(Channel)]
+pub struct Reliable;
+
+/// Configs without heartbeats or pings, so only messages are sent
+pub fn config(timeout: Duration) -> (ClientConfig, ServerConfig) {
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		..ClientConfig::default()
+	};
+	(client_config, ServerConfig { connection: connection_config, ..ServerConfig::default() })
+}
+
+pub fn schema() -> Schema {
+	Schema::builder()
+		.add_channel::<Reliable>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.add_message::<Text>()
+		.build()
+}
+
+pub fn text(text: &str) -> Text { Text { text: text.to_string() } }
+
+/// Returns the server's events, accepting all connections
+pub fn server_receive(server: &mut Server) -> Vec<ServerEvent> {
+	let events = server.receive();
+	for event in &events {
+		if let ServerEvent::Connect { user_key, ctx, .. } = event {
+			server.accept_connection(user_key, ctx).unwrap();
+		}
+	}
+	events
+}
+
+/// Returns the client's and the server's events
+pub fn pump(client: &mut Client, server: &mut Server) -> (Vec<ClientEvent>, Vec<ServerEvent>) {
+	client.send();
+	let server_events = server_receive(server);
+	server.send();
+	(client.receive(), server_events)
+}
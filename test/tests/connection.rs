@@ -1,13 +1,23 @@
 use naia_client::*;
 use naia_shared::*;
 use naia_server::*;
-use std::{net::Ipv4Addr, time::Duration};
+use std::{
+	net::{Ipv4Addr, SocketAddr},
+	sync::{Arc, atomic::{AtomicUsize, Ordering}},
+	time::{Duration, Instant},
+};
 
 #[derive(Message)]
 pub struct Auth {
 	pub token: String,
 }
 
+#[derive(Channel)]
+pub struct Gameplay;
+
+#[derive(Channel)]
+pub struct Chat;
+
 #[test]
 fn connect() {
 	let server_addr = (Ipv4Addr::LOCALHOST, 4000).into();
@@ -15,13 +25,17 @@ fn connect() {
 		heartbeat_interval: Duration::ZERO,
 		ping_interval: Duration::ZERO,
 		timeout: Duration::from_secs(1),
-		conditioner: None,
+		decrypt_failures: None,
+		..ConnectionConfig::default()
 	};
 	let client_config = ClientConfig {
 		connection: connection_config.clone(),
 		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
 	};
-	let server_config = ServerConfig { connection: connection_config };
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
 
 	let schema = || Schema::builder().add_message::<Auth>().build();
 	let mut client = Client::new(client_config, schema());
@@ -69,9 +83,9 @@ fn connect() {
 		assert_eq!(addr.ip(), server_addr.ip());
 
 		let msg = msg.expect("expected auth message");
-		assert_eq!(msg.downcast::<Auth>().token, token);
+		assert_eq!(msg.downcast::<Auth>().unwrap().token, token);
 
-		server.accept_connection(&user_key, &ctx);
+		server.accept_connection(&user_key, &ctx).unwrap();
 		server.send();
 	}
 
@@ -88,3 +102,1898 @@ fn connect() {
 		assert!(client.is_connected());
 	}
 }
+
+/// Connect `client` to `server`, returning the client's UserKey
+fn handshake(client: &mut Client, server: &mut Server, server_addr: SocketAddr) -> UserKey {
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	client.send();
+	server.receive();
+	server.send();
+	client.receive();
+	client.send();
+	let mut events = server.receive();
+	let Some(ServerEvent::Connect { user_key, ctx, .. }) = events.pop() else {
+		panic!("expected connect event");
+	};
+	server.accept_connection(&user_key, &ctx).unwrap();
+	server.send();
+	client.receive();
+	assert!(client.is_connected());
+
+	user_key
+}
+
+struct PacketCounter {
+	outgoing: Arc<AtomicUsize>,
+	incoming: Arc<AtomicUsize>,
+}
+
+impl ConnectionMiddleware for PacketCounter {
+	fn on_outgoing(&mut self, _writer: &mut PacketWriter) {
+		self.outgoing.fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn on_incoming(&mut self, _reader: &mut BitReader) {
+		self.incoming.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+#[test]
+fn middleware() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4001).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		decrypt_failures: None,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config, schema());
+
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	let outgoing = Arc::new(AtomicUsize::new(0));
+	let incoming = Arc::new(AtomicUsize::new(0));
+	client.add_middleware(Box::new(PacketCounter {
+		outgoing: outgoing.clone(),
+		incoming: incoming.clone(),
+	}));
+
+	// handshake
+	client.send();
+	server.receive();
+	server.send();
+	client.receive();
+	client.send();
+	let mut events = server.receive();
+	let Some(ServerEvent::Connect { user_key, ctx, .. }) = events.pop() else {
+		panic!("expected connect event");
+	};
+	server.accept_connection(&user_key, &ctx).unwrap();
+	server.send();
+	client.receive();
+	assert!(client.is_connected());
+
+	// EncryptRequest + ConnectRequest out; EncryptResponse + ConnectResponse in
+	assert_eq!(outgoing.load(Ordering::Relaxed), 2);
+	assert_eq!(incoming.load(Ordering::Relaxed), 2);
+
+	// heartbeat
+	client.send();
+	assert_eq!(outgoing.load(Ordering::Relaxed), 3);
+}
+
+#[test]
+fn suspend_resume() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4003).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_millis(100),
+		decrypt_failures: None,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig {
+		connection: ConnectionConfig { timeout: Duration::from_secs(10), ..connection_config },
+		..ServerConfig::default()
+	};
+
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config, schema());
+
+	let user_key = handshake(&mut client, &mut server, server_addr);
+	let old_addr = *server.user_address(&user_key).unwrap();
+
+	// suspended for longer than the client's timeout
+	client.suspend();
+	assert!(client.is_suspended());
+	std::thread::sleep(Duration::from_millis(200));
+	assert!(client.receive().is_empty());
+	assert!(client.is_connected());
+
+	// resume from a new address
+	client.resume().unwrap();
+	assert!(!client.is_suspended());
+	assert!(client.receive().is_empty());
+	assert!(client.is_connected());
+	client.send();
+	let events = server.receive();
+	assert!(events.is_empty());
+	assert_eq!(server.users_count(), 1);
+	let new_addr = *server.user_address(&user_key).unwrap();
+	assert_ne!(new_addr, old_addr);
+
+	// the server's heartbeat ends the migration
+	assert!(client.receive().is_empty());
+	assert!(client.is_connected());
+	client.send();
+	let events = server.receive();
+	assert!(events.is_empty());
+	assert_eq!(server.users_count(), 1);
+	assert_eq!(server.user_address(&user_key), Some(&new_addr));
+}
+
+#[test]
+fn observer() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4004).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		decrypt_failures: None,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: true,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ClientToServer, ChannelMode::UnorderedReliable)
+		.add_observer_channel::<Chat>(ChannelDirection::ClientToServer, ChannelMode::UnorderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config, schema());
+
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	// handshake
+	client.send();
+	server.receive();
+	server.send();
+	client.receive();
+	client.send();
+	let mut events = server.receive();
+	let Some(ServerEvent::Connect { user_key, ctx, .. }) = events.pop() else {
+		panic!("expected connect event");
+	};
+	assert!(ctx.is_observer());
+	server.accept_connection(&user_key, &ctx).unwrap();
+	server.send();
+	client.receive();
+	assert!(client.is_connected());
+	assert!(client.is_observer());
+
+	// only the chat message gets through
+	client.send_message::<Gameplay, _>(&Auth { token: "move".to_string() });
+	client.send_message::<Chat, _>(&Auth { token: "hello".to_string() });
+	client.send();
+
+	let mut events = server.receive();
+	let Some(ServerEvent::Message { msg, .. }) = events.pop() else {
+		panic!("expected message event");
+	};
+	assert!(events.is_empty());
+	assert_eq!(msg.downcast::<Auth>().unwrap().token, "hello");
+	assert_eq!(server.msg_rx_observer_drop_count(), 1);
+}
+
+#[test]
+fn channel_misuse() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4005).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		strict_channels: true,
+		decrypt_failures: None,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	// the client disagrees about the direction of Gameplay
+	let schema = |direction| Schema::builder()
+		.add_channel::<Gameplay>(direction, ChannelMode::UnorderedReliable)
+		.add_channel::<Chat>(ChannelDirection::ServerToClient, ChannelMode::UnorderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut client = Client::new(client_config, schema(ChannelDirection::Bidirectional));
+	let mut server = Server::new(server_config, schema(ChannelDirection::ServerToClient));
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	// local misuse is reported, not sent
+	client.send_message::<Chat, _>(&Auth { token: "hello".to_string() });
+	let events = client.receive();
+	assert!(matches!(events.as_slice(), [ClientEvent::Error(NaiaError::ChannelMisuse(name))] if name == "Chat"));
+
+	// remote misuse disconnects the peer in strict mode
+	client.send_message::<Gameplay, _>(&Auth { token: "move".to_string() });
+	client.send();
+	let events = server.receive();
+	assert!(matches!(events.as_slice(), [
+		ServerEvent::Error(NaiaError::ChannelMisuse(name)),
+		ServerEvent::Disconnect { user_key: disconnected, .. },
+		// packets sent after the misuse arrive for an unknown user
+		..
+	] if name == "Gameplay" && *disconnected == user_key));
+}
+
+#[test]
+fn channel_misuse_lenient() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4070).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	// the client disagrees about the direction of Gameplay
+	let schema = |direction| Schema::builder()
+		.add_channel::<Gameplay>(direction, ChannelMode::UnorderedReliable)
+		.add_channel::<Chat>(ChannelDirection::ClientToServer, ChannelMode::UnorderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut client = Client::new(client_config, schema(ChannelDirection::Bidirectional));
+	let mut server = Server::new(ServerConfig::default(), schema(ChannelDirection::ServerToClient));
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	// only the misused channel's message is dropped from the packet
+	client.send_message::<Gameplay, _>(&Auth { token: "move".to_string() });
+	client.send_message::<Chat, _>(&Auth { token: "hello".to_string() });
+	client.send();
+	let events = server.receive();
+	assert!(events.iter().any(|event| matches!(
+		event, ServerEvent::Error(NaiaError::ChannelMisuse(name)) if name == "Gameplay",
+	)));
+	let tokens: Vec<_> = events.into_iter()
+		.filter_map(|event| match event {
+			ServerEvent::Message { msg, .. } => msg.downcast::<Auth>().ok().map(|auth| auth.token),
+			_ => None,
+		})
+		.collect();
+	assert_eq!(tokens, ["hello"]);
+	assert!(server.user(&user_key).is_some());
+}
+
+#[test]
+fn link_degraded() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4014).into();
+	// any measured round trip is too slow
+	let congestion = CongestionConfig { bad_rtt_ms: 0.0, ..CongestionConfig::default() };
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		congestion: Some(congestion),
+		decrypt_failures: None,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	// reported once per transition
+	let mut client_events = Vec::new();
+	let mut server_events = Vec::new();
+	for _ in 0..3 {
+		client.send();
+		server_events.extend(server.receive());
+		server.send();
+		client_events.extend(client.receive());
+	}
+	assert!(matches!(client_events.as_slice(), [ClientEvent::LinkDegraded { rtt_ms, .. }] if *rtt_ms > 0.0));
+	assert!(matches!(server_events.as_slice(), [
+		ServerEvent::LinkDegraded { user_key: degraded, rtt_ms, .. }
+	] if *degraded == user_key && *rtt_ms > 0.0));
+	assert_eq!(client.link_quality(), LinkQuality::Bad);
+}
+
+#[test]
+fn max_message_size() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4015).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		decrypt_failures: None,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	// only the server limits message sizes
+	let schema = |max| {
+		let builder = Schema::builder()
+			.add_channel::<Chat>(ChannelDirection::Bidirectional, ChannelMode::UnorderedReliable)
+			.add_message::<Auth>();
+		match max {
+			Some(max) => builder.max_message_bytes(max),
+			None => builder,
+		}.build()
+	};
+	let mut client = Client::new(client_config, schema(None));
+	let mut server = Server::new(server_config, schema(Some(32)));
+	let user_key = handshake(&mut client, &mut server, server_addr);
+	let oversized = Auth { token: "x".repeat(64) };
+
+	// local oversized messages are reported, not sent
+	server.send_message::<Chat, _>(&user_key, &oversized);
+	assert!(matches!(server.receive().as_slice(), [ServerEvent::Error(NaiaError::MessageTooLarge(name))] if name == "Auth"));
+	assert_eq!(server.outgoing_queue_depth(&user_key).unwrap().messages, 0);
+
+	// remote oversized messages disconnect the peer
+	client.send_message::<Chat, _>(&oversized);
+	client.send();
+	let events = server.receive();
+	assert!(matches!(events.as_slice(), [
+		ServerEvent::Error(NaiaError::MessageTooLarge(name)),
+		ServerEvent::Disconnect { user_key: disconnected, .. },
+		..
+	] if name == "Auth" && *disconnected == user_key));
+}
+
+#[test]
+fn fragmentation() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4016).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		decrypt_failures: None,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Chat>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	// messages larger than a packet are reassembled in order
+	let tokens = ["x".repeat(5000), "small".to_string(), "y".repeat(3000)];
+	for token in &tokens {
+		client.send_message::<Chat, _>(&Auth { token: token.clone() });
+	}
+	let mut received = Vec::new();
+	for _ in 0..3 {
+		client.send();
+		for event in server.receive() {
+			match event {
+				ServerEvent::Message { user_key: sender, msg } if sender == user_key =>
+					received.push(msg.downcast::<Auth>().unwrap().token),
+				_ => panic!("unexpected event"),
+			}
+		}
+		server.send();
+		client.receive();
+	}
+	assert_eq!(received, tokens);
+	assert_eq!(server.frag_rx_refused_count(), 0);
+}
+
+#[test]
+fn rates() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4017).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		decrypt_failures: None,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Chat>(ChannelDirection::Bidirectional, ChannelMode::UnorderedUnreliable)
+		.add_message::<Auth>()
+		.build();
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config, schema());
+	handshake(&mut client, &mut server, server_addr);
+
+	for _ in 0..3 {
+		client.send_message::<Chat, _>(&Auth { token: "token".to_string() });
+	}
+	client.send();
+	server.receive();
+
+	// all within the last second
+	assert_eq!(client.msg_tx_per_sec::<Chat>(), 3.0);
+	assert_eq!(server.msg_rx_per_sec::<Chat>(), 3.0);
+	assert_eq!(server.msg_tx_per_sec::<Chat>(), 0.0);
+	assert!(client.pkt_tx_per_sec() >= 1.0 && server.bytes_rx_per_sec() > 0.0);
+
+	// snapshots count from the last reset
+	let snapshot = server.metrics_snapshot();
+	assert_eq!(snapshot.msg_rx_count, 3);
+	server.reset_metrics();
+	assert_eq!(server.metrics_snapshot().msg_rx_count, 0);
+	assert_eq!(server.msg_rx_count(), 3);
+}
+
+#[derive(Clone, Default)]
+struct RecordingSink {
+	values: Arc<std::sync::Mutex<Vec<(String, String, f64)>>>,
+}
+
+impl metrics::MetricsSink for RecordingSink {
+	fn record_counter(&mut self, name: &str, labels: &[(&str, &str)], value: u64) {
+		self.record_gauge(name, labels, value as f64);
+	}
+
+	fn record_gauge(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+		let labels = labels.iter().map(|(k, v)| format!("{k}={v}")).collect();
+		self.values.lock().unwrap().push((name.to_string(), labels, value));
+	}
+}
+
+#[test]
+fn metrics_sink() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4018).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		decrypt_failures: None,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	let sink = RecordingSink::default();
+	server.set_metrics_sink(Box::new(sink.clone()), Duration::ZERO);
+	server.send();
+
+	let values = sink.values.lock().unwrap().clone();
+	let value = |name: &str, labels: &str| values.iter()
+		.find(|(n, l, _)| n == name && l == labels)
+		.map(|(_, _, value)| *value);
+	assert_eq!(value("users", ""), Some(1.0));
+	assert!(value("pkt_tx_count", "").unwrap() >= 2.0);
+	assert!(value("rtt_ms", &format!("user={user_key}")).is_some());
+
+	// removed sinks are no longer called
+	assert!(server.remove_metrics_sink().is_some());
+	sink.values.lock().unwrap().clear();
+	server.send();
+	assert!(sink.values.lock().unwrap().is_empty());
+}
+
+#[derive(Message)]
+pub struct Login {
+	pub user: String,
+	pub password: Secret<String>,
+}
+
+#[test]
+fn secret_connect_message() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4019).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::ZERO,
+		timeout: Duration::from_secs(1),
+		decrypt_failures: None,
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder().add_message::<Login>().build();
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config, schema());
+	server.listen(server_addr).unwrap();
+	let login = Login { user: "alice".to_string(), password: Secret::new("hunter2".to_string()) };
+	client.connect(server_addr, login).unwrap();
+
+	client.send();
+	server.receive();
+	server.send();
+	client.receive();
+	client.send();
+	let mut events = server.receive();
+	let Some(ServerEvent::Connect { msg: Some(msg), .. }) = events.pop() else {
+		panic!("expected connect event");
+	};
+	let login = msg.downcast::<Login>().ok().unwrap();
+	assert_eq!(login.user, "alice");
+	assert_eq!(login.password.expose(), "hunter2");
+	assert_eq!(format!("{:?}", login.password), "Secret(<redacted>)");
+}
+
+#[test]
+fn user_key_reuse() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4020).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config.clone(), schema());
+	let stale_key = handshake(&mut client, &mut server, server_addr);
+	server.user_disconnect(&stale_key);
+
+	// the next user reuses the slot, but not the key
+	let mut client = Client::new(client_config, schema());
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+	client.send();
+	server.receive();
+	server.send();
+	client.receive();
+	client.send();
+	let user_key = server.receive().into_iter()
+		.find_map(|event| match event {
+			ServerEvent::Connect { user_key, .. } => Some(user_key),
+			_ => None,
+		})
+		.expect("expected connect event");
+
+	assert_eq!(user_key.index(), stale_key.index());
+	assert_ne!(user_key, stale_key);
+	assert_eq!(user_key.to_string().parse::<UserKey>(), Ok(user_key));
+	assert!(server.user_exists(&user_key));
+	assert!(!server.user_exists(&stale_key));
+	assert!(server.export_session(&stale_key).is_err());
+}
+
+#[test]
+fn users() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4021).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	client.send();
+	server.receive();
+	let user = server.users().next().unwrap();
+	assert_eq!(user.state(), UserState::Handshaking);
+	assert!(user.address().ip().is_loopback());
+
+	server.send();
+	client.receive();
+	client.send();
+	let Some(ServerEvent::Connect { user_key, ctx, .. }) = server.receive().pop() else {
+		panic!("expected connect event");
+	};
+	assert_eq!(server.user(&user_key).unwrap().state(), UserState::PendingAccept);
+
+	server.accept_connection(&user_key, &ctx).unwrap();
+	assert_eq!(server.accept_connection(&user_key, &ctx), Err(AcceptError::AlreadyAccepted));
+	assert_eq!(server.reject_connection(&user_key, RejectReason::AuthFailed), Err(AcceptError::AlreadyAccepted));
+	server.send();
+	client.receive();
+	let users: Vec<_> = server.users().map(|user| (user.key(), user.state(), user.is_observer())).collect();
+	assert_eq!(users, [(user_key, UserState::Connected, false)]);
+	assert!(server.user(&user_key).unwrap().msg_tx_count() <= server.msg_tx_count());
+}
+
+#[test]
+fn apply_config() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4022).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config.clone(), schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	// invalid configs are refused
+	let connection = ConnectionConfig { ping_interval: Duration::ZERO, ..ConnectionConfig::default() };
+	let invalid = ServerConfig { connection, ..ServerConfig::default() };
+	assert_eq!(server.apply_config(invalid), Err(ConfigError::ZeroPingInterval));
+
+	let connection = ConnectionConfig {
+		timeout: Duration::from_millis(100),
+		heartbeat_interval: Duration::from_millis(50),
+		ping_interval: Duration::from_millis(50),
+		..ConnectionConfig::default()
+	};
+	server.apply_config(ServerConfig { connection, max_users: Some(1), ..ServerConfig::default() }).unwrap();
+
+	// new users beyond the limit are rejected
+	let mut other = Client::new(client_config, schema());
+	other.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+	other.send();
+	server.receive();
+	assert!(other.receive().iter().any(|event| matches!(event, ClientEvent::Reject(_, RejectReason::ServerFull))));
+
+	// existing connections use the new timeout
+	std::thread::sleep(Duration::from_millis(150));
+	let events = server.receive();
+	assert!(events.iter().any(|event| matches!(event, ServerEvent::Disconnect { user_key: k, .. } if *k == user_key)));
+}
+
+#[test]
+fn connect_context_expiry() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4023).into();
+	let connection_config = ConnectionConfig { timeout: Duration::from_millis(50), ..ConnectionConfig::default() };
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let mut server = Server::new(server_config, schema());
+	let mut client = Client::new(client_config, schema());
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	client.send();
+	server.receive();
+	server.send();
+	client.receive();
+	client.send();
+	let Some(ServerEvent::Connect { user_key, ctx, .. }) = server.receive().pop() else {
+		panic!("expected connect event");
+	};
+
+	std::thread::sleep(Duration::from_millis(60));
+	assert!(ctx.is_expired());
+	assert_eq!(server.accept_connection(&user_key, &ctx), Err(AcceptError::Expired));
+	server.reject_connection(&user_key, RejectReason::AuthFailed).unwrap();
+	assert_eq!(server.accept_connection(&user_key, &ctx), Err(AcceptError::UnknownUser));
+}
+
+#[test]
+fn accept_with_message() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4024).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	client.send();
+	server.receive();
+	server.send();
+	client.receive();
+	client.send();
+	let Some(ServerEvent::Connect { user_key, ctx, .. }) = server.receive().pop() else {
+		panic!("expected connect event");
+	};
+
+	// the message follows the connect response, without another send()
+	let motd = Auth { token: "welcome".to_string() };
+	server.accept_connection_with::<Gameplay, _>(&user_key, &ctx, &motd).unwrap();
+	assert!(matches!(
+		client.receive().as_slice(),
+		[ClientEvent::HandshakeProgress(HandshakeStep::ConnectSent), ClientEvent::Connect(_)],
+	));
+	let Some(ClientEvent::Message(msg)) = client.receive().pop() else {
+		panic!("expected message event");
+	};
+	assert_eq!(msg.downcast::<Auth>().ok().unwrap().token, "welcome");
+}
+
+#[test]
+fn pause_channel() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4026).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_channel::<Chat>(ChannelDirection::ServerToClient, ChannelMode::UnorderedUnreliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	let auth = |token: &str| Auth { token: token.to_string() };
+	let tokens = |server: &mut Server, client: &mut Client| {
+		server.send();
+		client.receive().into_iter()
+			.filter_map(|event| match event {
+				ClientEvent::Message(msg) => Some(msg.downcast::<Auth>().ok().unwrap().token),
+				_ => None,
+			})
+			.collect::<Vec<_>>()
+	};
+
+	server.pause_channel::<Gameplay>(&user_key);
+	server.pause_channel::<Chat>(&user_key);
+	assert!(server.is_channel_paused::<Gameplay>(&user_key));
+	server.send_message::<Gameplay, _>(&user_key, &auth("a"));
+	server.send_message::<Gameplay, _>(&user_key, &auth("b"));
+	server.send_message::<Chat, _>(&user_key, &auth("dropped"));
+	assert!(tokens(&mut server, &mut client).is_empty());
+
+	// reliable channels buffer by default, unreliable ones drop
+	server.resume_channel::<Gameplay>(&user_key);
+	server.resume_channel::<Chat>(&user_key);
+	assert!(!server.is_channel_paused::<Gameplay>(&user_key));
+	server.send_message::<Gameplay, _>(&user_key, &auth("c"));
+	server.send_message::<Chat, _>(&user_key, &auth("d"));
+	let mut received = tokens(&mut server, &mut client);
+	received.sort();
+	assert_eq!(received, ["a", "b", "c", "d"]);
+}
+
+#[test]
+fn hold_channel() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4027).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_channel::<Chat>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	let auth = |token: &str| Auth { token: token.to_string() };
+	let tokens = |client: &mut Client| {
+		client.receive().into_iter()
+			.filter_map(|event| match event {
+				ClientEvent::Message(msg) => Some(msg.downcast::<Auth>().ok().unwrap().token),
+				_ => None,
+			})
+			.collect::<Vec<_>>()
+	};
+
+	client.hold_channel::<Gameplay>();
+	assert!(client.is_channel_held::<Gameplay>());
+	server.send_message::<Gameplay, _>(&user_key, &auth("a"));
+	server.send_message::<Chat, _>(&user_key, &auth("chat"));
+	server.send();
+	assert_eq!(tokens(&mut client), ["chat"]);
+	server.send_message::<Gameplay, _>(&user_key, &auth("b"));
+	server.send();
+	assert!(tokens(&mut client).is_empty());
+
+	// held messages come first, in order
+	client.release_channel::<Gameplay>();
+	assert!(!client.is_channel_held::<Gameplay>());
+	server.send_message::<Gameplay, _>(&user_key, &auth("c"));
+	server.send();
+	assert_eq!(tokens(&mut client), ["a", "b", "c"]);
+}
+
+#[test]
+fn broadcast_near() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4028).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	let auth = |token: &str| Auth { token: token.to_string() };
+	let tokens = |server: &mut Server, client: &mut Client| {
+		server.send();
+		client.receive().into_iter()
+			.filter_map(|event| match event {
+				ClientEvent::Message(msg) => Some(msg.downcast::<Auth>().ok().unwrap().token),
+				_ => None,
+			})
+			.collect::<Vec<_>>()
+	};
+
+	// users without a position are skipped
+	server.broadcast_near::<Gameplay, _>([0.0, 0.0], 1000.0, &auth("none"));
+	assert!(tokens(&mut server, &mut client).is_empty());
+
+	server.set_user_position(&user_key, [100.0, 50.0]);
+	assert_eq!(server.user_position(&user_key), Some([100.0, 50.0]));
+	server.broadcast_near::<Gameplay, _>([0.0, 0.0], 100.0, &auth("far"));
+	server.broadcast_near::<Gameplay, _>([90.0, 40.0], 20.0, &auth("near"));
+	assert_eq!(tokens(&mut server, &mut client), ["near"]);
+
+	server.clear_user_position(&user_key);
+	server.broadcast_near::<Gameplay, _>([90.0, 40.0], 20.0, &auth("cleared"));
+	assert!(tokens(&mut server, &mut client).is_empty());
+}
+
+#[test]
+fn message_validator() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4029).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ClientToServer, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	server.set_message_validator::<Auth>(|_, msg| match msg.token.as_str() {
+		"cheat" => Verdict::Reject,
+		token if token.len() > 4 => Verdict::Clamp(Auth { token: token[..4].to_string() }),
+		_ => Verdict::Accept,
+	});
+	for token in ["ok", "cheat", "toolong"] {
+		client.send_message::<Gameplay, _>(&Auth { token: token.to_string() });
+	}
+	client.send();
+
+	let tokens: Vec<_> = server.receive().into_iter()
+		.filter_map(|event| match event {
+			ServerEvent::Message { msg, .. } => Some(msg.downcast::<Auth>().ok().unwrap().token),
+			_ => None,
+		})
+		.collect();
+	assert_eq!(tokens, ["ok", "tool"]);
+	assert_eq!(server.msg_rx_reject_count(), 1);
+	assert_eq!(server.user(&user_key).unwrap().msg_rx_reject_count(), 1);
+	assert_eq!(server.metrics_snapshot().msg_rx_reject_count, 1);
+}
+
+#[test]
+fn desync() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4030).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder().enable_checksums().add_message::<Auth>().build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	server.set_checksum(1, 0xaa);
+	server.set_checksum(2, 0xbb);
+	client.send_checksum(1, 0xaa);
+	client.send_checksum(2, 0xcc);
+	client.send_checksum(3, 0xdd);
+	client.send();
+	let desyncs = |events: Vec<ServerEvent>| events.into_iter()
+		.filter_map(|event| match event {
+			ServerEvent::Desync { user_key, tick } => Some((user_key, tick)),
+			ServerEvent::Message { .. } => panic!("checksums are not surfaced as messages"),
+			_ => None,
+		})
+		.collect::<Vec<_>>();
+	assert_eq!(desyncs(server.receive()), [(user_key, 2)]);
+
+	// the Client reported first
+	server.set_checksum(3, 0xee);
+	assert_eq!(desyncs(server.receive()), [(user_key, 3)]);
+}
+
+#[test]
+fn shutdown_graceful() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4035).into();
+	let connection_config = ConnectionConfig { heartbeat_interval: Duration::ZERO, ..ConnectionConfig::default() };
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		..ClientConfig::default()
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(server_config, schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	server.shutdown_graceful::<Gameplay, _>(Duration::from_secs(10), &Auth { token: "restart".to_string() });
+	assert!(server.is_shutting_down());
+	assert!(server.is_draining());
+
+	let mut reason = None;
+	let mut server_events = Vec::new();
+	for _ in 0..10 {
+		server.send();
+		for event in client.receive() {
+			if let ClientEvent::Message(msg) = event {
+				reason = Some(msg.downcast::<Auth>().ok().unwrap().token);
+			}
+		}
+		client.send();
+		server_events = server.receive();
+		if !server.is_listening() {
+			break;
+		}
+	}
+	assert_eq!(reason.as_deref(), Some("restart"));
+	assert!(matches!(
+		server_events.as_slice(),
+		[ServerEvent::Disconnect { user_key: key, .. }, ServerEvent::ShutdownComplete] if *key == user_key,
+	));
+	assert!(!server.is_shutting_down());
+
+	// repeated shutdowns are harmless
+	server.shutdown();
+	assert!(!server.is_listening());
+	assert!(matches!(client.receive().as_slice(), [ClientEvent::Disconnect(_)]));
+}
+
+#[test]
+fn bandwidth_report() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4036).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_channel::<Chat>(ChannelDirection::ServerToClient, ChannelMode::UnorderedUnreliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+	assert!(server.bandwidth_report().entries.is_empty());
+	assert!(server.user_bandwidth_report(&user_key).is_none());
+
+	server.enable_bandwidth_profiler(Duration::from_secs(1));
+	server.send_message::<Gameplay, _>(&user_key, &Auth { token: "x".repeat(100) });
+	server.send_message::<Chat, _>(&user_key, &Auth { token: "hi".to_string() });
+	server.send_message::<Chat, _>(&user_key, &Auth { token: "hi".to_string() });
+	server.send();
+
+	let report = server.bandwidth_report();
+	assert_eq!(report.window, Duration::from_secs(1));
+	let ranked: Vec<_> = report.entries.iter().map(|entry| (entry.channel.as_str(), entry.msgs_per_sec)).collect();
+	assert_eq!(ranked.len(), 2);
+	assert!(ranked[0].0.ends_with("Gameplay") && ranked[0].1 == 1.0);
+	assert!(ranked[1].0.ends_with("Chat") && ranked[1].1 == 2.0);
+	assert!(report.entries.iter().all(|entry| entry.message.ends_with("Auth")));
+	assert!(report.to_string().contains("Gameplay"));
+	assert_eq!(server.user_bandwidth_report(&user_key), Some(report));
+
+	server.disable_bandwidth_profiler();
+	server.send();
+	assert!(server.bandwidth_report().entries.is_empty());
+}
+
+#[test]
+fn compact_headers() {
+	// the per packet bytes sent for a message, with compact headers proposed or not
+	let bytes_per_packet = |port: u16, compact_headers: bool| {
+		let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, port).into();
+		let client_config = ClientConfig {
+			connection: ConnectionConfig { compact_headers, ..ConnectionConfig::default() },
+			handshake_resend_interval: Duration::ZERO,
+			..ClientConfig::default()
+		};
+		let schema = || Schema::builder()
+			.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+			.add_message::<Auth>()
+			.build();
+		let mut server = Server::new(ServerConfig::default(), schema());
+		let mut client = Client::new(client_config, schema());
+		let user_key = handshake(&mut client, &mut server, server_addr);
+
+		let bytes_tx = server.bytes_tx();
+		server.send_message::<Gameplay, _>(&user_key, &Auth { token: "hi".to_string() });
+		server.send();
+		let received: Vec<_> = client.receive().into_iter()
+			.filter_map(|event| match event {
+				ClientEvent::Message(msg) => Some(msg.downcast::<Auth>().ok().unwrap().token),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(received, ["hi"]);
+		server.bytes_tx() - bytes_tx
+	};
+
+	assert_eq!(bytes_per_packet(4037, false), bytes_per_packet(4038, true) + 1);
+}
+
+#[test]
+fn coalesce() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4039).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let coalesce = CoalesceConfig { delay: Duration::from_millis(20), max_bytes: 32 };
+	let server_config = ServerConfig {
+		connection: ConnectionConfig { coalesce: Some(coalesce), ..ConnectionConfig::default() },
+		..ServerConfig::default()
+	};
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_channel::<Chat>(ChannelDirection::ServerToClient, ChannelMode::UnorderedUnreliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(server_config, schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	let tokens = |server: &mut Server, client: &mut Client| {
+		server.send();
+		client.receive().into_iter()
+			.filter_map(|event| match event {
+				ClientEvent::Message(msg) => Some(msg.downcast::<Auth>().ok().unwrap().token),
+				_ => None,
+			})
+			.collect::<Vec<_>>()
+	};
+
+	// small payloads wait for the delay, then share a packet
+	let data_rx = client.connection_stats().data_rx;
+	server.send_message::<Gameplay, _>(&user_key, &Auth { token: "a".to_string() });
+	assert!(tokens(&mut server, &mut client).is_empty());
+	server.send_message::<Chat, _>(&user_key, &Auth { token: "b".to_string() });
+	std::thread::sleep(Duration::from_millis(25));
+	let mut received = tokens(&mut server, &mut client);
+	received.sort();
+	assert_eq!(received, ["a", "b"]);
+	assert_eq!(client.connection_stats().data_rx, data_rx + 1);
+
+	// larger payloads are sent right away
+	server.send_message::<Chat, _>(&user_key, &Auth { token: "x".repeat(40) });
+	assert_eq!(tokens(&mut server, &mut client).len(), 1);
+}
+
+#[test]
+fn socket_config() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4040).into();
+	let socket = SocketConfig {
+		recv_buffer_size: Some(16384),
+		send_buffer_size: Some(16384),
+		dscp: Some(46),
+		dont_fragment: true,
+	};
+	let client_config = ClientConfig {
+		handshake_resend_interval: Duration::ZERO,
+		socket: socket.clone(),
+		..ClientConfig::default()
+	};
+	let server_config = ServerConfig { socket, ..ServerConfig::default() };
+
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let invalid = SocketConfig { dscp: Some(64), ..SocketConfig::default() };
+	let mut server = Server::new(ServerConfig { socket: invalid, ..ServerConfig::default() }, schema());
+	assert!(server.listen(server_addr).is_err());
+	assert!(server.socket_buffer_sizes().is_err());
+
+	let mut server = Server::new(server_config, schema());
+	let mut client = Client::new(client_config, schema());
+	handshake(&mut client, &mut server, server_addr);
+
+	// the OS may round the sizes up, e.g. Linux doubles them
+	let (recv, send) = server.socket_buffer_sizes().unwrap();
+	assert!((16384..=2 * 16384).contains(&recv));
+	assert!((16384..=2 * 16384).contains(&send));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn disconnect_on_refused() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4041).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ClientToServer, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	handshake(&mut client, &mut server, server_addr);
+
+	// the ICMP port unreachable answering a packet fails a later socket call
+	drop(server);
+	let mut events = Vec::new();
+	for _ in 0..100 {
+		client.send_message::<Gameplay, _>(&Auth { token: "hello".to_string() });
+		client.send();
+		std::thread::sleep(Duration::from_millis(1));
+		events = client.receive();
+		if client.is_disconnected() {
+			break;
+		}
+	}
+	assert!(client.is_disconnected());
+	assert!(matches!(events.last(), Some(ClientEvent::Disconnect(addr)) if *addr == server_addr));
+}
+
+#[test]
+fn duty_cycle() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4048).into();
+	let client_config = ClientConfig {
+		connection: ConnectionConfig { heartbeat_interval: Duration::from_millis(20), ..ConnectionConfig::default() },
+		handshake_resend_interval: Duration::ZERO,
+		..ClientConfig::default()
+	};
+	let server_config = ServerConfig {
+		connection: ConnectionConfig { timeout: Duration::from_millis(150), ..ConnectionConfig::default() },
+		..ServerConfig::default()
+	};
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ClientToServer, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(server_config, schema());
+	let mut client = Client::new(client_config, schema());
+	assert!(client.duty_cycle().is_none());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	// heartbeats keep the connection alive, but messages wait for send()
+	client.send_message::<Gameplay, _>(&Auth { token: "hello".to_string() });
+	let start = Instant::now();
+	while start.elapsed() < Duration::from_millis(300) {
+		let deadline = client.duty_cycle().unwrap();
+		assert!(deadline <= Instant::now() + Duration::from_millis(20));
+		std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+		let events = server.receive();
+		assert!(events.iter().all(|event| !matches!(event, ServerEvent::Message { .. })));
+	}
+	assert!(server.user(&user_key).is_some());
+
+	client.send();
+	assert!(server.receive().iter().any(|event| matches!(event, ServerEvent::Message { .. })));
+}
+
+#[test]
+fn poll_then_receive() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4050).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ClientToServer, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	handshake(&mut client, &mut server, server_addr);
+
+	for token in ["a", "b", "c"] {
+		client.send_message::<Gameplay, _>(&Auth { token: token.to_string() });
+		client.send();
+	}
+
+	// packets are queued, but parsed only by receive()
+	let deadline = Instant::now() + Duration::from_secs(1);
+	let mut polled = 0;
+	while polled < 3 && Instant::now() < deadline {
+		polled += server.poll();
+	}
+	assert_eq!(polled, 3);
+	assert_eq!(server.poll(), 0);
+	let tokens: Vec<_> = server.receive().into_iter()
+		.filter_map(|event| match event {
+			ServerEvent::Message { msg, .. } => msg.downcast::<Auth>().ok().map(|auth| auth.token),
+			_ => None,
+		})
+		.collect();
+	assert_eq!(tokens, ["a", "b", "c"]);
+}
+
+#[test]
+fn poll_overflow() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4065).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let server_config = ServerConfig { max_polled_packets: 2, ..ServerConfig::default() };
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ClientToServer, ChannelMode::UnorderedUnreliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(server_config, schema());
+	let mut client = Client::new(client_config, schema());
+	handshake(&mut client, &mut server, server_addr);
+
+	for token in ["a", "b", "c"] {
+		client.send_message::<Gameplay, _>(&Auth { token: token.to_string() });
+		client.send();
+	}
+
+	// the oldest packet is dropped from the full queue
+	let deadline = Instant::now() + Duration::from_secs(1);
+	let mut polled = 0;
+	while polled < 3 && Instant::now() < deadline {
+		polled += server.poll();
+	}
+	assert_eq!(polled, 3);
+	assert_eq!(server.pkt_rx_poll_drop_count(), 1);
+	let tokens: Vec<_> = server.receive().into_iter()
+		.filter_map(|event| match event {
+			ServerEvent::Message { msg, .. } => msg.downcast::<Auth>().ok().map(|auth| auth.token),
+			_ => None,
+		})
+		.collect();
+	assert_eq!(tokens, ["b", "c"]);
+}
+
+#[test]
+fn one_way_acks() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4069).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ClientToServer, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	handshake(&mut client, &mut server, server_addr);
+
+	// the server has nothing to send, and doesn't ack every packet on its own
+	let server_tx = server.pkt_tx_count();
+	for _ in 0..20 {
+		client.send_message::<Gameplay, _>(&Auth { token: "1234567".to_string() });
+		client.send();
+		std::thread::sleep(Duration::from_millis(1));
+		server.receive();
+		server.send();
+		client.receive();
+	}
+	assert!(server.pkt_tx_count() - server_tx < 10);
+
+	// but acks everything soon after, so the client's reliable queue drains
+	std::thread::sleep(Duration::from_millis(20));
+	server.receive();
+	server.send();
+	std::thread::sleep(Duration::from_millis(10));
+	client.receive();
+	assert_eq!(client.outgoing_queue_depth().messages, 0);
+}
+
+#[test]
+fn send_interval() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4071).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let schema = || Schema::builder()
+		.add_channel::<Chat>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+	let mut exchange = |server: &mut Server, token: Option<&str>| {
+		if let Some(token) = token {
+			server.send_message::<Chat, _>(&user_key, &Auth { token: token.to_string() });
+		}
+		server.send();
+		std::thread::sleep(Duration::from_millis(10));
+		client.receive().into_iter()
+			.filter_map(|event| match event {
+				ClientEvent::Message(msg) => msg.downcast::<Auth>().ok().map(|auth| auth.token),
+				_ => None,
+			})
+			.collect::<Vec<_>>()
+	};
+	assert_eq!(exchange(&mut server, Some("a")), ["a"]);
+
+	// messages are held until the interval since the last flush passes
+	server.set_send_interval(&user_key, Duration::from_secs(60));
+	assert!(exchange(&mut server, Some("b")).is_empty());
+	assert!(exchange(&mut server, Some("c")).is_empty());
+	server.set_send_interval(&user_key, Duration::ZERO);
+	assert_eq!(exchange(&mut server, None), ["b", "c"]);
+}
+
+#[test]
+fn cipher_negotiation() {
+	use CipherKind::*;
+	let cases = [
+		// both prefer AES-GCM
+		(4072, Some(Aes256Gcm), Some(Aes256Gcm), Aes256Gcm),
+		// the server accepts any proposal
+		(4073, Some(Aes256Gcm), None, Aes256Gcm),
+		// mismatched preferences fall back to ChaCha20-Poly1305
+		(4074, Some(Aes256Gcm), Some(ChaCha20Poly1305), ChaCha20Poly1305),
+		(4075, Some(ChaCha20Poly1305), Some(Aes256Gcm), ChaCha20Poly1305),
+	];
+	for (port, client_cipher, server_cipher, negotiated) in cases {
+		let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, port).into();
+		let client_config = ClientConfig {
+			connection: ConnectionConfig { cipher: client_cipher, ..ConnectionConfig::default() },
+			handshake_resend_interval: Duration::ZERO,
+			..ClientConfig::default()
+		};
+		let server_config = ServerConfig {
+			connection: ConnectionConfig { cipher: server_cipher, ..ConnectionConfig::default() },
+			..ServerConfig::default()
+		};
+		let schema = || Schema::builder().add_message::<Auth>().build();
+		let mut server = Server::new(server_config, schema());
+		let mut client = Client::new(client_config, schema());
+		assert_eq!(client.cipher(), None);
+		let user_key = handshake(&mut client, &mut server, server_addr);
+		assert_eq!(client.cipher(), Some(negotiated));
+		assert_eq!(server.cipher(&user_key), Some(negotiated));
+	}
+}
+
+#[test]
+fn schema_mismatch() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4051).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	// channels registered in a different order get different ids
+	let mut client = Client::new(client_config, Schema::builder()
+		.add_channel::<Chat>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_channel::<Gameplay>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build());
+	let mut server = Server::new(ServerConfig::default(), Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_channel::<Chat>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build());
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	client.send();
+	server.receive();
+	server.send();
+	client.receive();
+	client.send();
+	let events = server.receive();
+	assert!(events.iter().all(|event| !matches!(event, ServerEvent::Connect { .. })));
+	assert_eq!(server.users_count(), 0);
+	let events = client.receive();
+	assert!(events.iter().any(|event| matches!(event, ClientEvent::Reject(_, RejectReason::Version))));
+}
+
+#[test]
+fn connect_too_large() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4061).into();
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let server_config = ServerConfig { max_connect_bytes: Some(100), ..ServerConfig::default() };
+	let mut server = Server::new(server_config, schema());
+	server.listen(server_addr).unwrap();
+
+	let mut large = Client::new(client_config.clone(), schema());
+	large.connect(server_addr, Auth { token: "x".repeat(200) }).unwrap();
+	let mut small = Client::new(client_config, schema());
+	small.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	for client in [&mut large, &mut small] {
+		client.send();
+		server.receive();
+		server.send();
+		client.receive();
+		client.send();
+	}
+	// only the small client's request is decoded
+	let events = server.receive();
+	assert!(matches!(events.as_slice(), [ServerEvent::Connect { .. }]));
+	let events = large.receive();
+	assert!(events.iter().any(|event| matches!(event, ClientEvent::Reject(_, RejectReason::ConnectTooLarge))));
+}
+
+#[test]
+fn handshake_retry() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4062).into();
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let client_config = ClientConfig {
+		connection: ConnectionConfig { handshake_padding: HandshakePadding::Retry, ..ConnectionConfig::default() },
+		handshake_resend_interval: Duration::from_secs(60),
+		..ClientConfig::default()
+	};
+	let mut server = Server::new(ServerConfig::default(), schema());
+	server.listen(server_addr).unwrap();
+	let mut client = Client::new(client_config, schema());
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	// the unpadded request is answered with a cookie, without creating a user
+	client.send();
+	assert!(server.receive().is_empty());
+	assert_eq!(server.users_count(), 0);
+	client.receive();
+
+	// the cookie is echoed without waiting for the resend interval
+	client.send();
+	server.receive();
+	client.receive();
+	client.send();
+	let events = server.receive();
+	assert!(matches!(events.as_slice(), [ServerEvent::Connect { .. }]));
+}
+
+#[test]
+fn handshake_retry_spoofed() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4066).into();
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let client_config = ClientConfig {
+		connection: ConnectionConfig { handshake_padding: HandshakePadding::Retry, ..ConnectionConfig::default() },
+		handshake_resend_interval: Duration::from_secs(60),
+		..ClientConfig::default()
+	};
+	let mut server = Server::new(ServerConfig::default(), schema());
+	server.listen(server_addr).unwrap();
+
+	// an attacker relays the client's packets from its own address
+	let relay_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4067).into();
+	let relay = std::net::UdpSocket::bind(relay_addr).unwrap();
+	relay.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+	let mut client = Client::new(client_config, schema());
+	client.connect(relay_addr, Auth { token: "1234567".to_string() }).unwrap();
+	let mut buffer = [0u8; 1500];
+	// forwards a packet, returning where it came from
+	let mut relay_packet = |to: Option<SocketAddr>| {
+		let (len, from) = relay.recv_from(&mut buffer).unwrap();
+		relay.send_to(&buffer[..len], to.unwrap_or(server_addr)).unwrap();
+		from
+	};
+
+	// the attacker gets a cookie for its own address
+	client.send();
+	let client_addr = relay_packet(None);
+	server.receive();
+	relay_packet(Some(client_addr));
+	std::thread::sleep(Duration::from_millis(10));
+	client.receive();
+	client.send();
+	let mut request = [0u8; 1500];
+	let (request_len, _) = relay.recv_from(&mut request).unwrap();
+	let request = &request[..request_len];
+
+	// the request with that cookie, spoofed from another address, gets only another
+	// small HandshakeRetry
+	let spoofed = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 4068)).unwrap();
+	spoofed.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+	spoofed.send_to(request, server_addr).unwrap();
+	std::thread::sleep(Duration::from_millis(10));
+	assert!(server.receive().is_empty());
+	assert_eq!(server.users_count(), 0);
+	let (retry_len, _) = spoofed.recv_from(&mut [0; 1500]).unwrap();
+	assert!(retry_len < request_len);
+
+	// from the address it was issued to, the cookie is accepted
+	relay.send_to(request, server_addr).unwrap();
+	std::thread::sleep(Duration::from_millis(10));
+	server.receive();
+	assert_eq!(server.users_count(), 1);
+}
+
+#[test]
+fn connect_timeout() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4063).into();
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let server_config = ServerConfig {
+		handshake_timeout: Some(Duration::from_millis(50)),
+		..ServerConfig::default()
+	};
+	let mut server = Server::new(server_config, schema());
+	server.listen(server_addr).unwrap();
+	let mut client = Client::new(client_config, schema());
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	// the client stalls after exchanging keys
+	client.send();
+	server.receive();
+	assert_eq!(server.users_count(), 1);
+
+	std::thread::sleep(Duration::from_millis(60));
+	let events = server.receive();
+	let [ServerEvent::ConnectTimeout { addr, progress }] = events.as_slice() else {
+		panic!("expected connect timeout event");
+	};
+	assert_eq!(addr.ip(), Ipv4Addr::LOCALHOST);
+	assert_eq!(*progress, HandshakeProgress::KeysExchanged);
+	assert_eq!(server.users_count(), 0);
+}
+
+#[test]
+fn handshake_progress() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4064).into();
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let mut server = Server::new(ServerConfig::default(), schema());
+	server.listen(server_addr).unwrap();
+	let mut client = Client::new(client_config, schema());
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	let mut steps = Vec::new();
+	let mut step_events = |events: Vec<ClientEvent>| {
+		steps.extend(events.into_iter().filter_map(|event| match event {
+			ClientEvent::HandshakeProgress(step) => Some(step),
+			_ => None,
+		}));
+	};
+	// resends aren't reported again
+	client.send();
+	client.send();
+	server.receive();
+	step_events(client.receive());
+	client.send();
+	client.send();
+	let Some(ServerEvent::Connect { user_key, ctx, .. }) = server.receive().pop() else {
+		panic!("expected connect event");
+	};
+	server.accept_connection(&user_key, &ctx).unwrap();
+	step_events(client.receive());
+	assert_eq!(steps, [HandshakeStep::EncryptSent, HandshakeStep::KeysEstablished, HandshakeStep::ConnectSent]);
+}
+
+#[test]
+fn handshake_timing() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4052).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	client.send();
+	server.receive();
+	server.send();
+	client.receive();
+	client.send();
+	let Some(ServerEvent::Connect { user_key, ctx, .. }) = server.receive().pop() else {
+		panic!("expected connect event");
+	};
+	let timing = server.handshake_timing(&user_key).unwrap();
+	assert!(timing.encrypt.is_some() && timing.connect.is_none());
+	assert!(client.handshake_timing().encrypt.is_some());
+
+	// the connect step includes the application's decision
+	std::thread::sleep(Duration::from_millis(20));
+	server.accept_connection(&user_key, &ctx).unwrap();
+	server.send();
+	client.receive();
+	let timing = server.handshake_timing(&user_key).unwrap();
+	assert!(timing.connect.unwrap() >= Duration::from_millis(20));
+	assert_eq!(timing.total(), Some(timing.encrypt.unwrap() + timing.connect.unwrap()));
+	assert!(client.handshake_timing().total().unwrap() >= Duration::from_millis(20));
+}
+
+#[test]
+fn connection_quality() {
+	assert_eq!(QualityRating::rate(20.0, 2.0, 0.0), QualityRating::Excellent);
+	assert_eq!(QualityRating::rate(20.0, 2.0, 5.0), QualityRating::Fair);
+	assert_eq!(QualityRating::rate(180.0, 40.0, 2.0), QualityRating::Fair);
+	assert_eq!(QualityRating::rate(400.0, 2.0, 0.0), QualityRating::Poor);
+	assert_eq!(QualityRating::Poor.bars(), 1);
+	assert_eq!(QualityRating::Excellent.bars(), 4);
+
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4053).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	handshake(&mut client, &mut server, server_addr);
+	let quality = client.connection_quality();
+	assert_eq!(quality.rating, QualityRating::Excellent);
+	assert_eq!(quality.rtt_ms, client.rtt_ms());
+}
+
+#[test]
+fn simulated_latency() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4054).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	client.set_simulated_latency(Duration::from_millis(50));
+	let start = Instant::now();
+	server.send_message::<Gameplay, _>(&user_key, &Auth { token: "hello".to_string() });
+	server.send();
+	let mut received = None;
+	while received.is_none() && start.elapsed() < Duration::from_secs(1) {
+		if client.receive().iter().any(|event| matches!(event, ClientEvent::Message(_))) {
+			received = Some(start.elapsed());
+		}
+		std::thread::sleep(Duration::from_millis(1));
+	}
+	assert!(received.unwrap() >= Duration::from_millis(50));
+
+	// events are delivered right away again once the queue drains
+	client.set_simulated_latency(Duration::ZERO);
+	server.send_message::<Gameplay, _>(&user_key, &Auth { token: "world".to_string() });
+	server.send();
+	let deadline = Instant::now() + Duration::from_secs(1);
+	while !client.receive().iter().any(|event| matches!(event, ClientEvent::Message(_))) {
+		assert!(Instant::now() < deadline);
+	}
+}
+
+#[test]
+fn stamped_events() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4055).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	// the handshake already returned its progress and a Connect event from each
+	for token in ["a", "b"] {
+		server.send_message::<Gameplay, _>(&user_key, &Auth { token: token.to_string() });
+		server.send();
+	}
+	let start = Instant::now();
+	let mut stamped = Vec::new();
+	while stamped.len() < 2 && start.elapsed() < Duration::from_secs(1) {
+		stamped.extend(client.receive_stamped());
+	}
+	let seqs: Vec<_> = stamped.iter().map(|stamped| stamped.seq).collect();
+	assert_eq!(seqs, [4, 5]);
+	assert!(stamped[0].received <= stamped[1].received && stamped[0].received >= start);
+	assert!(matches!(stamped[0].event, ClientEvent::Message(_)));
+
+	client.disconnect().unwrap();
+	let deadline = Instant::now() + Duration::from_secs(1);
+	loop {
+		assert!(Instant::now() < deadline);
+		let events = server.receive_stamped();
+		if let Some(disconnect) = events.iter().find(|stamped| matches!(stamped.event, ServerEvent::Disconnect { .. })) {
+			assert!(disconnect.seq >= 1);
+			break;
+		}
+	}
+}
+
+#[test]
+fn pause_group() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4056).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ServerToClient, ChannelMode::OrderedReliable)
+		.add_channel::<Chat>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.channel_group::<Gameplay>("level")
+		.channel_group::<Chat>("level")
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	let auth = |token: &str| Auth { token: token.to_string() };
+	let tokens = |server: &mut Server, client: &mut Client| {
+		server.send();
+		let mut tokens: Vec<_> = client.receive().into_iter()
+			.filter_map(|event| match event {
+				ClientEvent::Message(msg) => Some(msg.downcast::<Auth>().ok().unwrap().token),
+				_ => None,
+			})
+			.collect();
+		tokens.sort();
+		tokens
+	};
+
+	server.pause_group(&user_key, "level");
+	assert!(server.is_group_paused(&user_key, "level"));
+	assert!(server.is_channel_paused::<Chat>(&user_key));
+	server.send_message::<Gameplay, _>(&user_key, &auth("a"));
+	server.send_message::<Chat, _>(&user_key, &auth("b"));
+	assert!(tokens(&mut server, &mut client).is_empty());
+
+	server.resume_group(&user_key, "level");
+	assert!(!server.is_group_paused(&user_key, "level"));
+	assert_eq!(tokens(&mut server, &mut client), ["a", "b"]);
+
+	client.hold_group("level");
+	assert!(client.is_channel_held::<Gameplay>() && client.is_channel_held::<Chat>());
+	server.send_message::<Chat, _>(&user_key, &auth("c"));
+	assert!(tokens(&mut server, &mut client).is_empty());
+	client.release_group("level");
+	assert_eq!(tokens(&mut server, &mut client), ["c"]);
+}
+
+#[test]
+fn loopback() {
+	// a misconfigured relay which sends every packet back where it came from
+	let reflector_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4057).into();
+	let reflector = std::net::UdpSocket::bind(reflector_addr).unwrap();
+	reflector.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let mut client = Client::new(client_config, Schema::builder().add_message::<Auth>().build());
+	client.connect(reflector_addr, Auth { token: "1234567".to_string() }).unwrap();
+	client.send();
+	let mut buffer = [0u8; 1500];
+	let (len, client_addr) = reflector.recv_from(&mut buffer).unwrap();
+	reflector.send_to(&buffer[..len], client_addr).unwrap();
+	std::thread::sleep(Duration::from_millis(10));
+
+	let events = client.receive();
+	assert!(matches!(
+		events.as_slice(),
+		[ClientEvent::Error(NaiaError::Loopback(addr)), ClientEvent::Disconnect(_)] if *addr == reflector_addr,
+	));
+	assert!(client.is_disconnected());
+}
+
+/// Handshakes as `identity` with a listening Server, returning the accept result
+fn connect_as(
+	client: &mut Client, server: &mut Server, server_addr: SocketAddr, identity: &str,
+) -> (UserKey, Result<(), AcceptError>) {
+	client.connect_as(server_addr, identity, Auth { token: "1234567".to_string() }).unwrap();
+	client.send();
+	server.receive();
+	server.send();
+	client.receive();
+	client.send();
+	let Some(ServerEvent::Connect { user_key, ctx, .. }) = server.receive().pop() else {
+		panic!("expected connect event");
+	};
+	assert_eq!(ctx.identity(), Some(identity));
+	let result = server.accept_connection(&user_key, &ctx);
+	server.send();
+	(user_key, result)
+}
+
+#[test]
+fn duplicate_identity() {
+	let client_config = || ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let schema = || Schema::builder().add_message::<Auth>().build();
+	let server_config = |policy| ServerConfig { duplicate_identity: policy, ..ServerConfig::default() };
+
+	// the new session takes over
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4058).into();
+	let mut server = Server::new(server_config(DuplicatePolicy::ReplaceOld), schema());
+	server.listen(server_addr).unwrap();
+	let (mut first, mut second) = (Client::new(client_config(), schema()), Client::new(client_config(), schema()));
+	let (first_key, result) = connect_as(&mut first, &mut server, server_addr, "alice");
+	result.unwrap();
+	assert!(first.receive().iter().any(|event| matches!(event, ClientEvent::Connect(_))));
+	let (second_key, result) = connect_as(&mut second, &mut server, server_addr, "alice");
+	result.unwrap();
+	assert!(matches!(
+		server.receive().as_slice(),
+		[ServerEvent::SessionReplaced { user_key, by }, ServerEvent::Disconnect { user_key: disconnected, .. }]
+			if *user_key == first_key && *by == second_key && *disconnected == first_key,
+	));
+	assert!(first.receive().iter().any(|event| matches!(event, ClientEvent::Disconnect(_))));
+	assert!(second.receive().iter().any(|event| matches!(event, ClientEvent::Connect(_))));
+
+	// the existing session stays
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4059).into();
+	let mut server = Server::new(server_config(DuplicatePolicy::RejectNew), schema());
+	server.listen(server_addr).unwrap();
+	let (mut first, mut second) = (Client::new(client_config(), schema()), Client::new(client_config(), schema()));
+	connect_as(&mut first, &mut server, server_addr, "bob").1.unwrap();
+	assert_eq!(connect_as(&mut second, &mut server, server_addr, "bob").1, Err(AcceptError::Duplicate));
+	assert!(second.receive().iter().any(|event| matches!(event, ClientEvent::Reject(_, RejectReason::Duplicate))));
+	assert_eq!(server.users_count(), 1);
+
+	// other identities are unaffected
+	let mut third = Client::new(client_config(), schema());
+	connect_as(&mut third, &mut server, server_addr, "carol").1.unwrap();
+}
+
+#[test]
+fn rate_limit() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4060).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let server_config = ServerConfig { throttle_disconnect: Some(2), ..ServerConfig::default() };
+
+	let schema = || Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::ClientToServer, ChannelMode::OrderedReliable)
+		.add_channel::<Chat>(ChannelDirection::ClientToServer, ChannelMode::OrderedReliable)
+		.rate_limit::<Chat>(RateLimit { max_messages: 2, window: Duration::from_secs(60) })
+		.add_message::<Auth>()
+		.build();
+	let mut server = Server::new(server_config, schema());
+	let mut client = Client::new(client_config, schema());
+	let user_key = handshake(&mut client, &mut server, server_addr);
+
+	let flood = |client: &mut Client, server: &mut Server| {
+		for _ in 0..3 {
+			client.send_message::<Chat, _>(&Auth { token: "spam".to_string() });
+			client.send_message::<Gameplay, _>(&Auth { token: "move".to_string() });
+		}
+		client.send();
+		server.receive()
+	};
+
+	// the excess is dropped on the limited channel only
+	let mut tokens = Vec::new();
+	let mut throttled = Vec::new();
+	for event in flood(&mut client, &mut server) {
+		match event {
+			ServerEvent::Message { msg, .. } => tokens.push(msg.downcast::<Auth>().ok().unwrap().token),
+			ServerEvent::Throttled { user_key, channel, dropped } => throttled.push((user_key, channel, dropped)),
+			_ => (),
+		}
+	}
+	assert_eq!(tokens.iter().filter(|token| *token == "spam").count(), 2);
+	assert_eq!(tokens.iter().filter(|token| *token == "move").count(), 3);
+	assert!(throttled == [(user_key, ChannelKind::of::<Chat>(), 1)]);
+	assert_eq!(server.user(&user_key).unwrap().msg_rx_throttle_count(), 1);
+	assert_eq!(server.metrics_snapshot().msg_rx_throttle_count, 1);
+
+	// repeat offenders are disconnected
+	let events = flood(&mut client, &mut server);
+	assert!(events.iter().any(|event| matches!(event, ServerEvent::Throttled { dropped: 3, .. })));
+	assert!(events.iter().any(|event| matches!(event, ServerEvent::Disconnect { user_key: key, .. } if *key == user_key)));
+}
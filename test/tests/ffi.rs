@@ -0,0 +1,86 @@
+use naia_client_ffi::*;
+use naia_server::*;
+use naia_shared::*;
+use std::{ffi::CString, net::Ipv4Addr, ptr};
+
+type Chat = BlobChannel<0>;
+
+fn poll(client: *mut NaiaClient) -> Option<(NaiaEventKind, Vec<u8>)> {
+	let mut event = NaiaEvent { kind: NaiaEventKind::Connect, reject_reason: 0, data: ptr::null(), len: 0 };
+	if !unsafe { naia_client_poll(client, &mut event) } {
+		return None;
+	}
+	let data = if event.len == 0 {
+		Vec::new()
+	} else {
+		unsafe { std::slice::from_raw_parts(event.data, event.len) }.to_vec()
+	};
+	Some((event.kind, data))
+}
+
+#[test]
+fn ffi_client() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4002).into();
+	let schema = Schema::builder()
+		.add_channel::<Chat>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_message::<Blob>()
+		.build();
+	let mut server = Server::new(ServerConfig::default(), schema);
+	server.listen(server_addr).unwrap();
+
+	let channels = [NaiaChannelConfig { mode: 4, direction: 2 }];
+	let client = unsafe { naia_client_new(channels.as_ptr(), channels.len()) };
+	assert!(!client.is_null());
+
+	// invalid settings are rejected
+	let invalid = [NaiaChannelConfig { mode: 9, direction: 2 }];
+	assert!(unsafe { naia_client_new(invalid.as_ptr(), invalid.len()) }.is_null());
+
+	let addr = CString::new("127.0.0.1:4002").unwrap();
+	let auth = b"token";
+	assert_eq!(unsafe { naia_client_connect(client, addr.as_ptr(), auth.as_ptr(), auth.len()) }, NAIA_OK);
+
+	// handshake
+	unsafe { naia_client_send(client) };
+	server.receive();
+	server.send();
+	assert_eq!(unsafe { naia_client_receive(client) }, 2);
+	assert_eq!(poll(client), Some((NaiaEventKind::HandshakeProgress, vec![0])));
+	assert_eq!(poll(client), Some((NaiaEventKind::HandshakeProgress, vec![1])));
+	unsafe { naia_client_send(client) };
+	let mut events = server.receive();
+	let Some(ServerEvent::Connect { user_key, msg, ctx, .. }) = events.pop() else {
+		panic!("expected connect event");
+	};
+	assert_eq!(&*msg.unwrap().downcast::<Blob>().unwrap().bytes, auth);
+	server.accept_connection(&user_key, &ctx).unwrap();
+	server.send();
+
+	assert_eq!(unsafe { naia_client_receive(client) }, 2);
+	assert_eq!(poll(client), Some((NaiaEventKind::HandshakeProgress, vec![2])));
+	assert_eq!(poll(client), Some((NaiaEventKind::Connect, Vec::new())));
+	assert_eq!(poll(client), None);
+	assert!(unsafe { naia_client_is_connected(client) });
+
+	// client -> server
+	let hello = b"hello";
+	assert_eq!(unsafe { naia_client_send_message(client, 0, hello.as_ptr(), hello.len()) }, NAIA_OK);
+	assert_eq!(
+		unsafe { naia_client_send_message(client, 1, hello.as_ptr(), hello.len()) },
+		NAIA_ERR_INVALID_ARGUMENT,
+	);
+	unsafe { naia_client_send(client) };
+	let mut events = server.receive();
+	let Some(ServerEvent::Message { msg, .. }) = events.pop() else {
+		panic!("expected message event");
+	};
+	assert_eq!(&*msg.downcast::<Blob>().unwrap().bytes, hello);
+
+	// server -> client
+	server.send_message::<Chat, _>(&user_key, &Blob::new(b"world"));
+	server.send();
+	unsafe { naia_client_receive(client) };
+	assert_eq!(poll(client), Some((NaiaEventKind::Message, b"world".to_vec())));
+
+	unsafe { naia_client_free(client) };
+}
@@ -0,0 +1,152 @@
+use naia_client::*;
+use naia_server::*;
+use std::{net::Ipv4Addr, time::Duration};
+
+mod common;
+
+use common::*;
+
+/// Splits the client's events into non-message events and received texts
+fn split_texts(client_events: Vec<ClientEvent>) -> (Vec<ClientEvent>, Vec<String>) {
+	let mut events = Vec::new();
+	let mut texts = Vec::new();
+	for event in client_events {
+		match event {
+			ClientEvent::Message(msg) => texts.push(msg.downcast::<Text>().unwrap().text),
+			event => events.push(event),
+		}
+	}
+	(events, texts)
+}
+
+/// Returns the client's non-message events and received texts
+fn receive(client: &mut Client) -> (Vec<ClientEvent>, Vec<String>) { split_texts(client.receive()) }
+
+/// Returns the client's received texts, and the server's events
+fn pump_texts(client: &mut Client, server: &mut Server) -> (Vec<String>, Vec<ServerEvent>) {
+	let (client_events, server_events) = pump(client, server);
+	(split_texts(client_events).1, server_events)
+}
+
+/// Returns the texts received from `user_key`, and fails on any other event
+fn server_texts_of(events: Vec<ServerEvent>, user_key: &UserKey) -> Vec<String> {
+	events.into_iter().map(|event| match event {
+		ServerEvent::Message { user_key: sender, msg } if sender == *user_key =>
+			msg.downcast::<Text>().unwrap().text,
+		ServerEvent::Error(e) => panic!("{e}"),
+		_ => panic!("unexpected event"),
+	}).collect()
+}
+
+#[test]
+fn handoff() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4009).into();
+	let (client_config, server_config) = config(Duration::from_secs(1));
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config.clone(), schema());
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth).unwrap();
+
+	for _ in 0..3 {
+		pump(&mut client, &mut server);
+	}
+	assert!(client.is_connected());
+	let user_key = server.user_keys()[0];
+
+	// only established users can be exported
+	assert!(server.export_session(&UserKey::new(u16::MAX, 0)).is_err());
+
+	// in flight on both sides during the export
+	server.send_message::<Reliable, _>(&user_key, &text("from old server"));
+	let depth = server.outgoing_queue_depth(&user_key).unwrap();
+	assert_eq!((depth.messages, depth.est_packets), (1, 1));
+	client.send_message::<Reliable, _>(&text("from client"));
+	client.send();
+
+	// the old server goes away, and the new one takes over its address
+	let session = server.export_session(&user_key).unwrap();
+	drop(server);
+	let mut server = Server::new(server_config, schema());
+	server.listen(server_addr).unwrap();
+	let user_key = server.import_session(&session).unwrap();
+	assert!(server.user_exists(&user_key));
+	assert!(server.import_session(&session).is_err());
+
+	// unacknowledged messages are resent in both directions, once the client's resend
+	// timeout expires
+	std::thread::sleep(Duration::from_millis(50));
+	let mut client_texts = Vec::new();
+	let mut server_texts = Vec::new();
+	for _ in 0..3 {
+		let (texts, events) = pump_texts(&mut client, &mut server);
+		client_texts.extend(texts);
+		server_texts.extend(server_texts_of(events, &user_key));
+	}
+	assert_eq!(client_texts, ["from old server"]);
+	assert_eq!(server_texts, ["from client"]);
+	assert!(client.is_connected());
+
+	// and the connection carries on
+	server.send_message::<Reliable, _>(&user_key, &text("from new server"));
+	let (texts, _) = pump_texts(&mut client, &mut server);
+	assert_eq!(texts, ["from new server"]);
+}
+
+#[test]
+fn redirect() {
+	let old_addr = (Ipv4Addr::LOCALHOST, 4010).into();
+	let new_addr = (Ipv4Addr::LOCALHOST, 4011).into();
+	let (client_config, server_config) = config(Duration::from_secs(1));
+	let mut client = Client::new(client_config, schema());
+	let mut old_server = Server::new(server_config.clone(), schema());
+	let mut new_server = Server::new(server_config, schema());
+	old_server.listen(old_addr).unwrap();
+	new_server.listen(new_addr).unwrap();
+	client.connect(old_addr, Auth).unwrap();
+
+	for _ in 0..3 {
+		pump(&mut client, &mut old_server);
+	}
+	assert!(client.is_connected());
+	let old_key = old_server.user_keys()[0];
+
+	old_server.send_message::<Reliable, _>(&old_key, &text("from old server"));
+	let session = old_server.export_session(&old_key).unwrap();
+	let new_key = new_server.import_session(&session).unwrap();
+
+	// the new server continues with the same key, so the old one goes quiet
+	old_server.send();
+	let (events, texts) = receive(&mut client);
+	assert!(events.is_empty() && texts.is_empty());
+
+	old_server.redirect(&old_key, new_addr);
+	assert!(!old_server.user_exists(&old_key));
+
+	// the client follows the redirect by itself
+	let (events, _) = receive(&mut client);
+	let [ClientEvent::Redirect { addr, .. }] = events.as_slice() else {
+		panic!("expected a Redirect event");
+	};
+	assert_eq!(*addr, new_addr);
+	assert_eq!(client.server_address(), Some(&new_addr));
+
+	client.send_message::<Reliable, _>(&text("from client"));
+	let mut client_events = Vec::new();
+	let mut client_texts = Vec::new();
+	let mut server_texts = Vec::new();
+	for _ in 0..3 {
+		client.send();
+		server_texts.extend(server_texts_of(server_receive(&mut new_server), &new_key));
+		new_server.send();
+		let (events, texts) = receive(&mut client);
+		client_events.extend(events);
+		client_texts.extend(texts);
+	}
+	let [ClientEvent::Redirected(addr)] = client_events.as_slice() else {
+		panic!("expected a Redirected event");
+	};
+	assert_eq!(*addr, new_addr);
+	assert_eq!(client_texts, ["from old server"]);
+	assert_eq!(server_texts, ["from client"]);
+	assert!(client.is_connected());
+}
@@ -0,0 +1,134 @@
+//! Exercises the UDP socket behind `Io` directly, to catch platform differences in
+//! socket behavior, e.g. between Linux, Windows and macOS.
+
+use naia_shared::*;
+use std::{
+	net::{Ipv4Addr, SocketAddr},
+	time::{Duration, Instant},
+};
+
+fn listen(port: u16) -> (SocketAddr, Io) {
+	let addr: SocketAddr = (Ipv4Addr::LOCALHOST, port).into();
+	(addr, Io::listen(addr, &None, &SocketConfig::default()).unwrap())
+}
+
+fn connect(server_addr: SocketAddr) -> Io {
+	Io::connect(server_addr, &None, &SocketConfig::default()).unwrap()
+}
+
+/// Receive up to `count` packets, waiting at most a second for them
+fn recv(io: &mut Io, count: usize) -> Vec<(SocketAddr, Vec<u8>)> {
+	let deadline = Instant::now() + Duration::from_secs(1);
+	let mut packets = Vec::new();
+	while packets.len() < count && Instant::now() < deadline {
+		match io.recv_reader().unwrap() {
+			Some((addr, mut reader)) => packets.push((addr, reader.remaining_mut().to_vec())),
+			None => std::thread::sleep(Duration::from_millis(1)),
+		}
+	}
+	packets
+}
+
+#[test]
+fn round_trip() {
+	let (server_addr, mut server) = listen(4042);
+	let mut client = connect(server_addr);
+	assert!(server.recv_reader().unwrap().is_none());
+
+	client.send_packet(&server_addr, b"ping").unwrap();
+	let packets = recv(&mut server, 1);
+	assert_eq!(packets.len(), 1);
+	let (client_addr, payload) = &packets[0];
+	assert_eq!(payload, b"ping");
+	assert!(client_addr.ip().is_loopback());
+
+	server.send_packet(client_addr, b"pong").unwrap();
+	assert_eq!(recv(&mut client, 1), [(server_addr, b"pong".to_vec())]);
+
+	assert_eq!((client.pkt_tx_count(), client.bytes_tx()), (1, 4));
+	assert_eq!((server.pkt_rx_count(), server.bytes_rx()), (1, 4));
+}
+
+#[test]
+fn second_client() {
+	let (server_addr, mut server) = listen(4043);
+	let mut clients = [connect(server_addr), connect(server_addr)];
+	for (i, client) in clients.iter_mut().enumerate() {
+		client.send_packet(&server_addr, &[i as u8]).unwrap();
+	}
+
+	// each client has its own address, and only receives its own replies
+	let mut packets = recv(&mut server, 2);
+	packets.sort_by_key(|(_, payload)| payload.clone());
+	assert_eq!(packets.len(), 2);
+	assert_ne!(packets[0].0, packets[1].0);
+	for (addr, payload) in &packets {
+		server.send_packet(addr, payload).unwrap();
+	}
+	for (i, client) in clients.iter_mut().enumerate() {
+		assert_eq!(recv(client, 1), [(server_addr, vec![i as u8])]);
+	}
+	std::thread::sleep(Duration::from_millis(10));
+	assert!(clients.iter_mut().all(|client| client.recv_reader().unwrap().is_none()));
+}
+
+#[test]
+fn burst() {
+	const COUNT: usize = 64;
+	let (server_addr, mut server) = listen(4044);
+	let mut client = connect(server_addr);
+	for i in 0..COUNT {
+		let mut payload = vec![0; MTU_SIZE_BYTES];
+		payload[..2].copy_from_slice(&(i as u16).to_le_bytes());
+		client.send_packet(&server_addr, &payload).unwrap();
+	}
+
+	// full size packets arrive intact; loopback neither drops nor reorders them
+	// while they fit the default receive buffer
+	let packets = recv(&mut server, COUNT);
+	assert_eq!(packets.len(), COUNT);
+	for (i, (_, payload)) in packets.iter().enumerate() {
+		assert_eq!(payload.len(), MTU_SIZE_BYTES);
+		assert_eq!(u16::from_le_bytes([payload[0], payload[1]]) as usize, i);
+	}
+}
+
+#[test]
+fn oversized_payload() {
+	let (server_addr, mut server) = listen(4045);
+	let mut client = connect(server_addr);
+
+	// larger than any UDP datagram
+	match client.send_packet(&server_addr, &vec![0; 70_000]) {
+		Err(NaiaError::Send(error)) => {
+			assert_eq!((error.addr, error.kind), (server_addr, SendErrorKind::TooLarge));
+		}
+		result => panic!("unexpected {result:?}"),
+	}
+
+	// the socket remains usable
+	client.send_packet(&server_addr, b"ok").unwrap();
+	assert_eq!(recv(&mut server, 1).len(), 1);
+}
+
+#[test]
+fn reconnect() {
+	let (server_addr, mut server) = listen(4046);
+	let mut client = connect(server_addr);
+	client.send_packet(&server_addr, b"a").unwrap();
+	let old_addr = recv(&mut server, 1)[0].0;
+
+	// a fresh socket, with a new local port
+	client.reconnect(server_addr).unwrap();
+	client.send_packet(&server_addr, b"b").unwrap();
+	let packets = recv(&mut server, 1);
+	assert_eq!(packets.len(), 1);
+	assert_ne!(packets[0].0, old_addr);
+	assert_eq!(packets[0].1, b"b");
+}
+
+#[test]
+fn address_in_use() {
+	let (server_addr, _server) = listen(4047);
+	assert!(Io::listen(server_addr, &None, &SocketConfig::default()).is_err());
+}
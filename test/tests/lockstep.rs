@@ -0,0 +1,144 @@
+use naia_client::*;
+use naia_extras::lockstep::*;
+use naia_server::*;
+use naia_shared::*;
+use std::{net::Ipv4Addr, thread, time::Duration};
+
+#[derive(Message)]
+pub struct Auth;
+
+fn pump(
+	server: &mut Server, lockstep: &mut LockstepServer, clients: &mut [(Client, LockstepClient)],
+) -> Vec<LockstepFrame> {
+	for (client, _) in clients.iter_mut() {
+		client.send();
+	}
+	let events = server.receive();
+	for event in lockstep.process(events) {
+		if let ServerEvent::Connect { user_key, ctx, .. } = event {
+			server.accept_connection(&user_key, &ctx).unwrap();
+			lockstep.join(&user_key);
+		}
+	}
+	let frames = lockstep.update(server);
+	server.send();
+	for (client, lockstep) in clients.iter_mut() {
+		let events = client.receive();
+		lockstep.process(events);
+	}
+	frames
+}
+
+fn setup(
+	port: u16, config: &LockstepConfig,
+) -> (Server, LockstepServer, Vec<(Client, LockstepClient)>) {
+	let server_addr = (Ipv4Addr::LOCALHOST, port).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let schema = || Schema::builder().add_plugin(&LockstepPlugin).add_message::<Auth>().build();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut lockstep = LockstepServer::new(config.clone());
+	server.listen(server_addr).unwrap();
+
+	let mut clients: Vec<_> = (0..2).map(|_| {
+		let mut client = Client::new(client_config.clone(), schema());
+		client.connect(server_addr, Auth).unwrap();
+		(client, LockstepClient::new(config.clone()))
+	}).collect();
+	for _ in 0..3 {
+		pump(&mut server, &mut lockstep, &mut clients);
+	}
+	assert!(clients.iter().all(|(client, _)| client.is_connected()));
+	assert_eq!(lockstep.players().count(), 2);
+	(server, lockstep, clients)
+}
+
+fn ticks(frames: &[LockstepFrame]) -> Vec<u32> {
+	frames.iter().map(|frame| frame.tick).collect()
+}
+
+fn send(clients: &mut [(Client, LockstepClient)], index: usize, tick: u32, input: u8) {
+	let (client, player) = &mut clients[index];
+	player.send_input(client, tick, &[input]);
+}
+
+#[test]
+fn lockstep() {
+	let config = LockstepConfig {
+		input_timeout: Duration::from_millis(20),
+		drop_policy: DropPolicy::RepeatLast,
+		buffer_frames: 1,
+		max_catch_up: 2,
+		..LockstepConfig::default()
+	};
+	let (mut server, mut lockstep, mut clients) = setup(4031, &config);
+
+	// ticks only wait for players which sent input
+	send(&mut clients, 0, 0, 1);
+	assert_eq!(ticks(&pump(&mut server, &mut lockstep, &mut clients)), [0]);
+
+	// late inputs are dropped, and the barrier waits for both players now
+	send(&mut clients, 1, 0, 4);
+	send(&mut clients, 1, 1, 3);
+	assert!(pump(&mut server, &mut lockstep, &mut clients).is_empty());
+	assert_eq!(lockstep.drop_count(), 1);
+	send(&mut clients, 0, 1, 2);
+	let frames = pump(&mut server, &mut lockstep, &mut clients);
+	assert_eq!(ticks(&frames), [1]);
+	let (user_id, _) = frames[0].inputs.iter().find(|(_, input)| input[0] == 3).unwrap();
+	let user_id = *user_id;
+
+	// missing inputs repeat the previous ones once the tick times out
+	send(&mut clients, 0, 2, 5);
+	assert!(pump(&mut server, &mut lockstep, &mut clients).is_empty());
+	thread::sleep(config.input_timeout);
+	let frames = pump(&mut server, &mut lockstep, &mut clients);
+	assert_eq!(ticks(&frames), [2]);
+	assert_eq!(lockstep.late_count(), 1);
+	let mut inputs: Vec<_> = frames[0].inputs.iter().map(|(_, input)| input[0]).collect();
+	inputs.sort();
+	assert_eq!(inputs, [3, 5]);
+	assert!(frames[0].inputs.contains(&(user_id, Box::new([3]))));
+
+	// frames are paced, catching up beyond the buffer
+	for (_, player) in &mut clients {
+		assert_eq!(player.next_tick(), Some(0));
+		assert_eq!(player.pending_frames(), 3);
+		assert_eq!(ticks(&player.take_frames()), [0, 1]);
+		assert_eq!(ticks(&player.take_frames()), [2]);
+		assert!(player.take_frames().is_empty());
+		assert_eq!(player.next_tick(), Some(3));
+	}
+}
+
+#[test]
+fn lockstep_silent_player() {
+	let config = LockstepConfig {
+		input_timeout: Duration::from_millis(20),
+		drop_policy: DropPolicy::RepeatLast,
+		..LockstepConfig::default()
+	};
+	let (mut server, mut lockstep, mut clients) = setup(4077, &config);
+
+	send(&mut clients, 0, 0, 1);
+	send(&mut clients, 1, 0, 2);
+	assert_eq!(ticks(&pump(&mut server, &mut lockstep, &mut clients)), [0]);
+
+	// only the first tick the silent player misses waits for them
+	for tick in 1..6 {
+		send(&mut clients, 0, tick, 1);
+	}
+	assert!(pump(&mut server, &mut lockstep, &mut clients).is_empty());
+	thread::sleep(config.input_timeout);
+	let frames = pump(&mut server, &mut lockstep, &mut clients);
+	assert_eq!(ticks(&frames), [1, 2, 3, 4, 5]);
+	assert_eq!(lockstep.late_count(), 1);
+	assert!(frames.iter().all(|frame| frame.inputs.iter().any(|(_, input)| input[0] == 2)));
+
+	// once their input arrives again, ticks wait for them again
+	send(&mut clients, 0, 6, 1);
+	send(&mut clients, 1, 6, 3);
+	assert_eq!(ticks(&pump(&mut server, &mut lockstep, &mut clients)), [6]);
+	send(&mut clients, 0, 7, 1);
+	assert!(pump(&mut server, &mut lockstep, &mut clients).is_empty());
+}
@@ -0,0 +1,71 @@
+use naia_client::*;
+use naia_extras::matchmaking::*;
+use naia_server::*;
+use naia_shared::*;
+use std::{net::{Ipv4Addr, SocketAddr}, time::{Duration, SystemTime}};
+
+const KEY: [u8; TICKET_KEY_SIZE] = [3; TICKET_KEY_SIZE];
+
+fn claims(match_id: u64, player_id: u64) -> TicketClaims {
+	TicketClaims { match_id, player_id, team: 1, expires: SystemTime::now() + Duration::from_secs(60) }
+}
+
+/// Connect with `ticket`, returning the resulting Client events
+fn connect(
+	server: &mut Server, matchmaking: &mut MatchmakingServer, addr: SocketAddr, ticket: MatchTicket,
+) -> (Client, Vec<ClientEvent>) {
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let mut client = Client::new(client_config, schema());
+	client.connect(addr, ticket).unwrap();
+
+	let mut events = Vec::new();
+	for _ in 0..3 {
+		if client.is_disconnected() {
+			break;
+		}
+		client.send();
+		let server_events = server.receive();
+		for event in matchmaking.process(server, server_events) {
+			if let ServerEvent::Connect { user_key, msg, ctx, .. } = event {
+				let ticket = msg.unwrap().downcast::<MatchTicket>().ok().unwrap();
+				assert_eq!(matchmaking.claims(&user_key).unwrap().player_id, ticket.player_id);
+				server.accept_connection(&user_key, &ctx).unwrap();
+			}
+		}
+		server.send();
+		events.extend(client.receive());
+	}
+	(client, events)
+}
+
+fn schema() -> Schema {
+	Schema::builder().add_plugin(&MatchmakingPlugin).build()
+}
+
+#[test]
+fn tickets() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4032).into();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut matchmaking = MatchmakingServer::new(KEY);
+	matchmaking.set_match(Some(42));
+	server.listen(server_addr).unwrap();
+
+	let (client, _) = connect(&mut server, &mut matchmaking, server_addr, MatchTicket::issue(&KEY, &claims(42, 1)));
+	assert!(client.is_connected());
+	let user = server.users().next().unwrap().key();
+	assert_eq!(matchmaking.claims(&user).map(|claims| claims.team), Some(1));
+
+	let rejected = |events: &[ClientEvent]| events.iter().any(|event| matches!(event, ClientEvent::Reject(_, RejectReason::AuthFailed)));
+	let tickets = [
+		MatchTicket::issue(&[4; TICKET_KEY_SIZE], &claims(42, 2)),
+		MatchTicket::issue(&KEY, &claims(7, 2)),
+		MatchTicket::issue(&KEY, &claims(42, 1)),
+		MatchTicket::issue(&KEY, &TicketClaims { expires: SystemTime::now(), ..claims(42, 2) }),
+	];
+	for ticket in tickets {
+		let (_, events) = connect(&mut server, &mut matchmaking, server_addr, ticket);
+		assert!(rejected(&events));
+	}
+	assert_eq!(matchmaking.reject_count(), 4);
+	assert_eq!(server.users().count(), 1);
+}
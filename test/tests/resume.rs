@@ -0,0 +1,121 @@
+use naia_client::*;
+use naia_server::*;
+use std::{net::{Ipv4Addr, SocketAddr}, time::Duration};
+
+mod common;
+
+use common::*;
+
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+fn config() -> (ClientConfig, ServerConfig) {
+	let (client_config, server_config) = common::config(TIMEOUT);
+	(client_config, ServerConfig { resume_window: Duration::from_secs(60), ..server_config })
+}
+
+/// Connects the server's first user
+fn connect(client: &mut Client, server: &mut Server, addr: SocketAddr) -> UserKey {
+	client.connect(addr, Auth).unwrap();
+	for _ in 0..3 {
+		pump(client, server);
+	}
+	assert!(client.is_connected());
+	server.user_keys()[0]
+}
+
+/// Lets the connection time out on the client, returning its resume token
+fn time_out(client: &mut Client) -> Box<[u8]> {
+	// drain the packets in flight first
+	client.receive();
+	std::thread::sleep(TIMEOUT * 2);
+	let events = client.receive();
+	assert!(matches!(events.as_slice(), [ClientEvent::Disconnect(_)]));
+	client.resume_token().expect("a resume token").into()
+}
+
+/// Pumps until the client reconnects, checking that messages flow both ways
+fn assert_resumed(client: &mut Client, server: &mut Server, user_key: UserKey) {
+	assert!(client.is_connecting());
+	client.send_message::<Reliable, _>(&text("from client"));
+
+	let mut client_events = Vec::new();
+	let mut server_events = Vec::new();
+	for _ in 0..3 {
+		let (c, s) = pump(client, server);
+		client_events.extend(c);
+		server_events.extend(s);
+	}
+	assert!(client.is_connected());
+	assert!(matches!(client_events.as_slice(), [ClientEvent::Connect(_)]));
+
+	let mut resumed = false;
+	let mut server_texts = Vec::new();
+	for event in server_events {
+		match event {
+			ServerEvent::Resume { user_key: key, .. } if key == user_key => resumed = true,
+			ServerEvent::Message { user_key: key, msg } if key == user_key =>
+				server_texts.push(msg.downcast::<Text>().unwrap().text),
+			_ => panic!("unexpected server event"),
+		}
+	}
+	assert!(resumed);
+	assert_eq!(server_texts, ["from client"]);
+
+	server.send_message::<Reliable, _>(&user_key, &text("from server"));
+	let (mut events, _) = pump(client, server);
+	let (Some(ClientEvent::Message(msg)), None) = (events.pop(), events.pop()) else {
+		panic!("expected a message");
+	};
+	assert_eq!(msg.downcast::<Text>().unwrap().text, "from server");
+}
+
+#[test]
+fn resume_after_timeout() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4012).into();
+	let (client_config, server_config) = config();
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config, schema());
+	server.listen(server_addr).unwrap();
+	let user_key = connect(&mut client, &mut server, server_addr);
+
+	// both sides time out, and the server keeps the session
+	let token = time_out(&mut client);
+	let events = server.receive();
+	assert!(matches!(events.as_slice(), [ServerEvent::Disconnect { user_key: key, .. }] if *key == user_key));
+	assert!(!server.user_exists(&user_key));
+
+	client.resume_session(&token).unwrap();
+	assert_resumed(&mut client, &mut server, user_key);
+
+}
+
+#[test]
+fn resume_after_restart() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4013).into();
+	let (client_config, server_config) = config();
+	let mut client = Client::new(client_config, schema());
+	let mut server = Server::new(server_config.clone(), schema());
+	server.listen(server_addr).unwrap();
+	let user_key = connect(&mut client, &mut server, server_addr);
+
+	// another user, whose session is exported along with the client's
+	let mut other = Client::new(config().0, schema());
+	other.connect(server_addr, Auth).unwrap();
+	for _ in 0..3 {
+		pump(&mut other, &mut server);
+	}
+	assert_eq!(server.users_count(), 2);
+
+	let sessions = server.export_resumable().unwrap();
+	drop(server);
+	let token = time_out(&mut client);
+
+	let mut server = Server::new(server_config, schema());
+	server.listen(server_addr).unwrap();
+	assert_eq!(server.import_resumable(&sessions).unwrap(), 2);
+	assert_eq!(server.import_resumable(&sessions).unwrap(), 0);
+	assert_eq!(server.users_count(), 0);
+
+	client.resume_session(&token).unwrap();
+	assert_resumed(&mut client, &mut server, user_key);
+}
@@ -0,0 +1,93 @@
+use naia_client::*;
+use naia_server::*;
+use naia_shared::*;
+use std::{
+	io::{Read, Write},
+	net::{Ipv4Addr, SocketAddr, TcpStream},
+	time::Duration,
+};
+
+#[derive(Message)]
+pub struct Auth;
+
+fn schema() -> Schema {
+	Schema::builder().add_message::<Auth>().build()
+}
+
+fn get(status: &mut StatusServer, server: &Server, path: &str) -> String {
+	let mut stream = TcpStream::connect(status.local_addr().unwrap()).unwrap();
+	stream.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+	write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+
+	let mut response = Vec::new();
+	for _ in 0..100 {
+		status.update(server, 60.0);
+		match stream.read_to_end(&mut response) {
+			Ok(_) => break,
+			Err(_) => continue,
+		}
+	}
+	String::from_utf8(response).unwrap()
+}
+
+#[test]
+fn status() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4033).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let mut client = Client::new(client_config, schema());
+	let mut status = StatusServer::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth).unwrap();
+	for _ in 0..4 {
+		client.send();
+		for event in server.receive() {
+			if let ServerEvent::Connect { user_key, ctx, .. } = event {
+				server.accept_connection(&user_key, &ctx).unwrap();
+			}
+		}
+		server.send();
+		client.receive();
+	}
+	assert!(client.is_connected());
+
+	let health = get(&mut status, &server, "/health");
+	assert!(health.starts_with("HTTP/1.1 200 OK\r\n"));
+	assert!(health.ends_with("\r\n\r\n{\"healthy\":true}"));
+
+	let body = get(&mut status, &server, "/status");
+	assert!(body.starts_with("HTTP/1.1 200 OK\r\n"));
+	assert!(body.contains("{\"users\":1,\"max_users\":null,\"uptime_secs\":0,\"tick_rate\":60,\"metrics\":{"));
+	assert!(body.contains("\"pkt_rx_count\":"));
+	assert!(body.ends_with("}}"));
+
+	assert!(get(&mut status, &server, "/nope").starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+#[test]
+fn pending_limit() {
+	let server = Server::new(ServerConfig::default(), schema());
+	let mut status = StatusServer::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+	let addr = status.local_addr().unwrap();
+
+	// connections which never complete their request fill the queue
+	let idle: Vec<_> = (0..MAX_PENDING_REQUESTS).map(|_| TcpStream::connect(addr).unwrap()).collect();
+	for _ in 0..10 {
+		status.update(&server, 60.0);
+	}
+
+	// so further ones are closed without a response
+	let mut stream = TcpStream::connect(addr).unwrap();
+	stream.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+	write!(stream, "GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+	status.update(&server, 60.0);
+	let mut response = Vec::new();
+	assert!(matches!(stream.read_to_end(&mut response), Ok(0) | Err(_)));
+	assert!(response.is_empty());
+
+	// until the idle connections go away
+	drop(idle);
+	status.update(&server, 60.0);
+	assert!(get(&mut status, &server, "/health").starts_with("HTTP/1.1 200 OK\r\n"));
+}
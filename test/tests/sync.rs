@@ -0,0 +1,61 @@
+use naia_client::*;
+use naia_server::*;
+use naia_shared::*;
+use std::{net::{Ipv4Addr, SocketAddr}, time::Duration};
+
+#[derive(Message)]
+pub struct Auth;
+
+fn schema() -> Schema {
+	Schema::builder().enable_sync().add_message::<Auth>().build()
+}
+
+#[test]
+fn snapshot() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4025).into();
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+
+	let snapshot: Vec<u8> = (0..10 * SYNC_CHUNK_BYTES as u32).map(|i| i as u8).collect();
+	let mut server = Server::new(ServerConfig::default(), schema());
+	let provided = snapshot.clone();
+	server.set_snapshot_provider(move |_| provided.clone());
+	let mut client = Client::new(client_config, schema());
+	server.listen(server_addr).unwrap();
+	client.connect(server_addr, Auth).unwrap();
+
+	let mut progress = Vec::new();
+	let mut received = None;
+	let mut synced = None;
+	for _ in 0..20 {
+		client.send();
+		for event in server.receive() {
+			match event {
+				ServerEvent::Connect { user_key, ctx, .. } => {
+					server.accept_connection(&user_key, &ctx).unwrap();
+					assert_eq!(server.user(&user_key).unwrap().state(), UserState::Syncing);
+				}
+				ServerEvent::UserSynced { user_key } => synced = Some(user_key),
+				_ => (),
+			}
+		}
+		server.send();
+		for event in client.receive() {
+			match event {
+				ClientEvent::SyncProgress(fraction) => progress.push(fraction),
+				ClientEvent::Synced(bytes) => received = Some(bytes),
+				_ => (),
+			}
+		}
+		if synced.is_some() {
+			break;
+		}
+	}
+
+	assert_eq!(received.as_deref(), Some(snapshot.as_slice()));
+	assert_eq!(progress.len(), 10);
+	assert!(progress.windows(2).all(|pair| pair[0] < pair[1]));
+	assert_eq!(progress.last(), Some(&1.0));
+
+	let user_key = synced.expect("expected synced event");
+	assert_eq!(server.user(&user_key).unwrap().state(), UserState::Connected);
+}
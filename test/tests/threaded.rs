@@ -0,0 +1,74 @@
+use naia_client::*;
+use naia_shared::*;
+use naia_server::*;
+use std::{
+	net::{Ipv4Addr, SocketAddr},
+	time::{Duration, Instant},
+};
+
+#[derive(Message)]
+pub struct Auth {
+	pub token: String,
+}
+
+#[derive(Channel)]
+pub struct Gameplay;
+
+fn schema() -> Schema {
+	Schema::builder()
+		.add_channel::<Gameplay>(ChannelDirection::Bidirectional, ChannelMode::OrderedReliable)
+		.add_message::<Auth>()
+		.build()
+}
+
+/// Pump `client` until it reports an event matching `f`, for at most a second
+fn wait_for(client: &mut Client, f: impl Fn(&ClientEvent) -> bool) -> bool {
+	let deadline = Instant::now() + Duration::from_secs(1);
+	while Instant::now() < deadline {
+		client.send();
+		if client.receive().iter().any(&f) {
+			return true;
+		}
+		std::thread::sleep(Duration::from_millis(1));
+	}
+	false
+}
+
+#[test]
+fn spawn_threaded() {
+	let server_addr: SocketAddr = (Ipv4Addr::LOCALHOST, 4049).into();
+	let (handle, events) = Server::spawn_threaded(ServerConfig::default(), schema());
+	handle.listen(server_addr).unwrap();
+
+	let client_config = ClientConfig { handshake_resend_interval: Duration::ZERO, ..ClientConfig::default() };
+	let mut client = Client::new(client_config, schema());
+	client.connect(server_addr, Auth { token: "1234567".to_string() }).unwrap();
+
+	// the handshake takes a few round trips
+	let deadline = Instant::now() + Duration::from_secs(1);
+	let (user_key, ctx) = loop {
+		assert!(Instant::now() < deadline, "expected connect event");
+		client.send();
+		client.receive();
+		if let Ok(ServerEvent::Connect { user_key, ctx, .. }) = events.recv_timeout(Duration::from_millis(1)) {
+			break (user_key, ctx);
+		}
+	};
+	handle.accept_connection(user_key, ctx).unwrap();
+	assert!(wait_for(&mut client, |event| matches!(event, ClientEvent::Connect(_))));
+
+	// messages flow both ways without the app pumping the Server
+	handle.send_message::<Gameplay, _>(user_key, &Auth { token: "hello".to_string() });
+	assert!(wait_for(&mut client, |event| matches!(event, ClientEvent::Message(_))));
+	client.send_message::<Gameplay, _>(&Auth { token: "world".to_string() });
+	client.send();
+	let event = events.recv_timeout(Duration::from_secs(1)).unwrap();
+	assert!(matches!(event, ServerEvent::Message { user_key: key, .. } if key == user_key));
+	assert_eq!(handle.run(|server| server.users_count()), 1);
+
+	handle.kick(user_key);
+	assert!(wait_for(&mut client, |event| matches!(event, ClientEvent::Disconnect(_))));
+	let server = handle.join();
+	assert_eq!(server.users_count(), 0);
+	assert!(server.is_listening());
+}
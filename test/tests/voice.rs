@@ -0,0 +1,100 @@
+use naia_client::*;
+use naia_extras::voice::*;
+use naia_server::*;
+use naia_shared::*;
+use std::{net::Ipv4Addr, time::Duration};
+
+#[derive(Message)]
+pub struct Auth;
+
+fn pump(server: &mut Server, voice: &mut VoiceServer, clients: &mut [(Client, VoiceReceiver)]) {
+	for (client, _) in clients.iter_mut() {
+		client.send();
+	}
+	let events = server.receive();
+	for event in voice.process(server, events) {
+		if let ServerEvent::Connect { user_key, ctx, .. } = event {
+			server.accept_connection(&user_key, &ctx).unwrap();
+			voice.join(&user_key);
+		}
+	}
+	server.send();
+	for (client, receiver) in clients.iter_mut() {
+		let events = client.receive();
+		receiver.process(events);
+	}
+}
+
+#[test]
+fn voice() {
+	let server_addr = (Ipv4Addr::LOCALHOST, 4007).into();
+	let connection_config = ConnectionConfig {
+		heartbeat_interval: Duration::ZERO,
+		ping_interval: Duration::from_secs(60),
+		timeout: Duration::from_secs(1),
+		..ConnectionConfig::default()
+	};
+	let client_config = ClientConfig {
+		connection: connection_config.clone(),
+		handshake_resend_interval: Duration::ZERO,
+		observer: false,
+		socket: SocketConfig::default(),
+		disconnect_on_refused: true,
+	};
+	let server_config = ServerConfig { connection: connection_config, ..ServerConfig::default() };
+	let voice_config = VoiceConfig { jitter_depth: 2, ..VoiceConfig::default() };
+
+	let schema = || Schema::builder().add_plugin(&VoicePlugin).add_message::<Auth>().build();
+	let mut server = Server::new(server_config, schema());
+	let mut voice = VoiceServer::new(voice_config.clone());
+	server.listen(server_addr).unwrap();
+
+	let mut clients: Vec<_> = (0..2).map(|_| {
+		let mut client = Client::new(client_config.clone(), schema());
+		client.connect(server_addr, Auth).unwrap();
+		(client, VoiceReceiver::new(voice_config.clone()))
+	}).collect();
+
+	for _ in 0..3 {
+		pump(&mut server, &mut voice, &mut clients);
+	}
+	assert!(clients.iter().all(|(client, _)| client.is_connected()));
+	assert_eq!(voice.participants().count(), 2);
+
+	// frames are paced out one per frame duration
+	let mut sender = VoiceSender::new(voice_config.clone());
+	for byte in 0..3 {
+		sender.push(Box::new([byte]));
+	}
+	sender.push(vec![0; voice_config.max_frame_bytes + 1].into());
+	assert_eq!(sender.drop_count(), 1);
+
+	let now = Instant::now();
+	for tick in 0..3 {
+		sender.send(&mut clients[0].0, now + voice_config.frame_duration * tick);
+		pump(&mut server, &mut voice, &mut clients);
+	}
+
+	// relayed to the other participant only
+	assert_eq!(clients[0].1.speakers().count(), 0);
+	let receiver = &mut clients[1].1;
+	let speaker = receiver.speakers().next().unwrap();
+	let played: Vec<_> = std::iter::from_fn(|| receiver.pull(speaker)).map(|playout| match playout {
+		VoicePlayout::Frame(data) => Some(data[0]),
+		VoicePlayout::Lost => None,
+	}).collect();
+	assert_eq!(played, [Some(0), Some(1), Some(2), None, None]);
+
+	// muted speakers are dropped by the server
+	let speaker_key = *voice.participants().map(|(user_key, _)| user_key)
+		.find(|user_key| user_key.to_bits() == speaker)
+		.unwrap();
+	voice.set_muted(&speaker_key, true);
+	sender.push(Box::new([3]));
+	sender.send(&mut clients[0].0, now + voice_config.frame_duration * 10);
+	for _ in 0..2 {
+		pump(&mut server, &mut voice, &mut clients);
+	}
+	assert_eq!(voice.drop_count(), 1);
+	assert!(clients[1].1.pull(speaker).is_none());
+}